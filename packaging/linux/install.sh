@@ -0,0 +1,19 @@
+#!/bin/bash
+# Install the release build and its desktop entry for the current user.
+# --default also makes Chonker3 the default application for PDFs.
+set -e
+
+cd "$(dirname "$0")/../.."
+PREFIX="${PREFIX:-$HOME/.local}"
+
+install -Dm755 target/release/chonker3 "$PREFIX/bin/chonker3"
+install -Dm644 packaging/linux/chonker3.desktop "$PREFIX/share/applications/chonker3.desktop"
+update-desktop-database "$PREFIX/share/applications" 2>/dev/null || true
+# Open chonker3:// links with it
+xdg-mime default chonker3.desktop x-scheme-handler/chonker3 2>/dev/null || true
+
+if [ "$1" = "--default" ]; then
+    xdg-mime default chonker3.desktop application/pdf
+fi
+
+echo "Installed chonker3 to $PREFIX/bin"
@@ -0,0 +1,18 @@
+#!/bin/bash
+# Record the SHA-256 of every archive in the pinned pdfium-binaries release
+# (PDFIUM_RELEASE in src/pdfium_bootstrap.rs) for the first-run download to
+# check against. Run after bumping the release, and review the diff.
+set -e
+
+cd "$(dirname "$0")/.."
+RELEASE=$(sed -n 's/^pub const PDFIUM_RELEASE: &str = "\(.*\)";$/\1/p' src/pdfium_bootstrap.rs)
+TMP=$(mktemp -d)
+trap 'rm -rf "$TMP"' EXIT
+
+# The platforms platform_asset() names; not every OS has every architecture
+for asset in pdfium-{linux,mac,win}-{x64,arm64,x86}.tgz; do
+    curl -fsSL -o "$TMP/$asset" "https://github.com/bblanchon/pdfium-binaries/releases/download/${RELEASE//\//%2F}/$asset" \
+        || { echo "No $asset in $RELEASE" >&2; rm -f "$TMP/$asset"; }
+done
+(cd "$TMP" && sha256sum -- *.tgz) > packaging/pdfium-binaries.sha256
+echo "Pinned $(wc -l < packaging/pdfium-binaries.sha256) archives of $RELEASE"
@@ -0,0 +1,41 @@
+# Register Chonker3 for "Open with" on the supported file types and as the
+# handler for chonker3:// links, for the current user. -Unregister removes the
+# entries again.
+param(
+    [Parameter(Mandatory = $true)][string]$Exe,
+    [switch]$Unregister
+)
+
+$classes = "HKCU:\Software\Classes"
+$progId = "Chonker3.Document"
+$extensions = ".pdf", ".png", ".tif", ".tiff", ".eml", ".msg"
+
+if ($Unregister) {
+    Remove-Item -Path "$classes\$progId" -Recurse -ErrorAction SilentlyContinue
+    Remove-Item -Path "$classes\chonker3" -Recurse -ErrorAction SilentlyContinue
+    Remove-Item -Path "$classes\Applications\chonker3.exe" -Recurse -ErrorAction SilentlyContinue
+    foreach ($ext in $extensions) {
+        Remove-ItemProperty -Path "$classes\$ext\OpenWithProgids" -Name $progId -ErrorAction SilentlyContinue
+    }
+    exit
+}
+
+$command = "`"$Exe`" `"%1`""
+New-Item -Path "$classes\$progId\shell\open\command" -Force | Out-Null
+Set-ItemProperty -Path "$classes\$progId" -Name "(default)" -Value "Chonker3 document"
+Set-ItemProperty -Path "$classes\$progId\shell\open\command" -Name "(default)" -Value $command
+
+New-Item -Path "$classes\Applications\chonker3.exe\shell\open\command" -Force | Out-Null
+Set-ItemProperty -Path "$classes\Applications\chonker3.exe\shell\open\command" -Name "(default)" -Value $command
+
+New-Item -Path "$classes\chonker3\shell\open\command" -Force | Out-Null
+Set-ItemProperty -Path "$classes\chonker3" -Name "(default)" -Value "URL:Chonker3 link"
+New-ItemProperty -Path "$classes\chonker3" -Name "URL Protocol" -Value "" -PropertyType String -Force | Out-Null
+Set-ItemProperty -Path "$classes\chonker3\shell\open\command" -Name "(default)" -Value $command
+
+foreach ($ext in $extensions) {
+    New-Item -Path "$classes\$ext\OpenWithProgids" -Force | Out-Null
+    New-ItemProperty -Path "$classes\$ext\OpenWithProgids" -Name $progId -Value "" -PropertyType String -Force | Out-Null
+}
+
+Write-Host "Registered $Exe for $($extensions -join ', ') and chonker3:// links"
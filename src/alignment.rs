@@ -0,0 +1,157 @@
+//! Per-page alignment scores
+//!
+//! Each page's reconstruction (the extracted items typeset at their boxes) is
+//! rendered offscreen with pdfium and compared against a render of the PDF page
+//! using structural similarity (SSIM). Missing text, or text drawn a line off,
+//! pulls a page's score down, so the worst-extracted pages can be found
+//! without paging through the whole document.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use anyhow::{anyhow, Result};
+use pdfium_render::prelude::*;
+
+use crate::jobs::JobHandle;
+use crate::types::DocumentItem;
+
+/// Width both renders are scored at; coarse enough that font differences don't dominate
+pub const SCORE_WIDTH: usize = 256;
+
+/// SSIM window edge in pixels
+const WINDOW: usize = 8;
+
+/// Windows whose darkest mean stays above this are blank on both sides and skipped
+const BLANK_LEVEL: f32 = 0.98;
+
+/// A grayscale raster with luminance in 0..=1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gray {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<f32>,
+}
+
+impl Gray {
+    /// Render a page at `width` pixels, keeping its aspect ratio
+    pub fn render(page: &PdfPage, width: usize) -> Result<Self> {
+        let height = ((width as f32) * page.height().value / page.width().value).round().max(1.0) as usize;
+        let config = PdfRenderConfig::new()
+            .set_target_size(width as i32, height as i32)
+            .render_form_data(true);
+        let bitmap = page.render_with_config(&config).map_err(|e| anyhow!("Render failed: {}", e))?;
+        let pixels = bitmap.as_rgba_bytes()
+            .chunks_exact(4)
+            .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0)
+            .collect();
+        Ok(Self { width, height, pixels })
+    }
+
+    /// 3x3 box blur, so glyph shapes matter less than where the ink is
+    pub fn blurred(&self) -> Self {
+        let mut pixels = vec![0.0; self.pixels.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (mut sum, mut count) = (0.0, 0.0);
+                for ny in y.saturating_sub(1)..=(y + 1).min(self.height - 1) {
+                    for nx in x.saturating_sub(1)..=(x + 1).min(self.width - 1) {
+                        sum += self.pixels[ny * self.width + nx];
+                        count += 1.0;
+                    }
+                }
+                pixels[y * self.width + x] = sum / count;
+            }
+        }
+        Self { width: self.width, height: self.height, pixels }
+    }
+}
+
+/// Mean SSIM over the windows where either image has ink; 1.0 when both are blank.
+/// Images must be the same size.
+pub fn ssim(a: &Gray, b: &Gray) -> f32 {
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+    assert_eq!((a.width, a.height), (b.width, b.height), "images differ in size");
+
+    let (mut total, mut windows) = (0.0, 0usize);
+    for wy in (0..a.height).step_by(WINDOW) {
+        for wx in (0..a.width).step_by(WINDOW) {
+            let indexes: Vec<usize> = (wy..(wy + WINDOW).min(a.height))
+                .flat_map(|y| (wx..(wx + WINDOW).min(a.width)).map(move |x| y * a.width + x))
+                .collect();
+            let n = indexes.len() as f32;
+            let mean_a = indexes.iter().map(|&i| a.pixels[i]).sum::<f32>() / n;
+            let mean_b = indexes.iter().map(|&i| b.pixels[i]).sum::<f32>() / n;
+            if mean_a.min(mean_b) > BLANK_LEVEL {
+                continue;
+            }
+            let (mut var_a, mut var_b, mut covariance) = (0.0, 0.0, 0.0);
+            for &i in &indexes {
+                let (da, db) = (a.pixels[i] - mean_a, b.pixels[i] - mean_b);
+                var_a += da * da;
+                var_b += db * db;
+                covariance += da * db;
+            }
+            let (var_a, var_b, covariance) = (var_a / n, var_b / n, covariance / n);
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        1.0
+    } else {
+        (total / windows as f32).clamp(0.0, 1.0)
+    }
+}
+
+/// Typeset the items on a blank page of the given size (points) and render it
+pub fn render_reconstruction(pdfium: &Pdfium, page_size: (f32, f32), items: &[DocumentItem], width: usize) -> Result<Gray> {
+    let mut document = pdfium.create_new_pdf().map_err(|e| anyhow!("Failed to create PDF: {}", e))?;
+    let font = document.fonts_mut().helvetica();
+    let size = PdfPagePaperSize::from_points(PdfPoints::new(page_size.0), PdfPoints::new(page_size.1));
+    let mut page = document.pages_mut().create_page_at_end(size)
+        .map_err(|e| anyhow!("Failed to create page: {}", e))?;
+    for item in items {
+        let font_size = if item.font_size > 0.0 { item.font_size } else { item.bbox.height as f32 * 0.8 };
+        // Items are top-left origin; PDF text sits on a baseline measured from the bottom
+        let baseline = page_size.1 - item.baseline() as f32;
+        for (line_index, line) in item.content.lines().enumerate() {
+            let y = baseline - line_index as f32 * font_size * 1.2;
+            page.objects_mut()
+                .create_text_object(PdfPoints::new(item.bbox.left as f32), PdfPoints::new(y), line, font, PdfPoints::new(font_size))
+                .map_err(|e| anyhow!("Failed to place text: {}", e))?;
+        }
+    }
+    Gray::render(&page, width)
+}
+
+/// Score one page: 1.0 is a reconstruction indistinguishable from the page at this resolution
+pub fn score_page(pdfium: &Pdfium, page: &PdfPage, items: &[DocumentItem]) -> Result<f32> {
+    let original = Gray::render(page, SCORE_WIDTH)?;
+    let page_size = (page.width().value, page.height().value);
+    let reconstruction = render_reconstruction(pdfium, page_size, items, SCORE_WIDTH)?;
+    Ok(ssim(&original.blurred(), &reconstruction.blurred()))
+}
+
+/// Score every page, one job step each, publishing scores into `scores` as they finish.
+/// `pages[i]` holds page i's items with the user's edits applied.
+pub fn score_pages(
+    pdfium: &Pdfium,
+    pdf_bytes: &[u8],
+    password: Option<&str>,
+    pages: &[Vec<DocumentItem>],
+    scores: &Mutex<BTreeMap<usize, f32>>,
+    job: &JobHandle,
+) -> Result<usize> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let page_count = (document.pages().len() as usize).min(pages.len());
+    job.set_total(page_count);
+    for (index, page) in document.pages().iter().take(page_count).enumerate() {
+        job.begin_step(format!("Page {} of {}", index + 1, page_count))?;
+        let score = score_page(pdfium, &page, &pages[index])?;
+        scores.lock().unwrap().insert(index, score);
+        job.finish_step();
+    }
+    Ok(page_count)
+}
@@ -0,0 +1,168 @@
+//! Layout annotations for training detectors
+//!
+//! The item boxes and types of a corrected extraction, written next to the
+//! page images as COCO JSON or YOLO label files, so a layout-detection dataset
+//! can be started from documents that were already checked by hand. Boxes are
+//! scaled to the page PNGs rendered alongside them at `IMAGE_SCALE`.
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::document;
+use crate::patch::EditPatch;
+use crate::renderer::page_png_name;
+use crate::types::{BoundingBox, ItemType};
+
+/// Scale the page images are rendered at (144 DPI)
+pub const IMAGE_SCALE: f32 = 2.0;
+
+/// Class names for YOLO, one per line
+pub const YOLO_CLASSES_FILE: &str = "classes.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationFormat {
+    /// One `{stem}_coco.json` for all pages
+    Coco,
+    /// A `{image}.txt` of normalized boxes per page image, plus `classes.txt`
+    Yolo,
+}
+
+impl AnnotationFormat {
+    pub const ALL: [AnnotationFormat; 2] = [AnnotationFormat::Coco, AnnotationFormat::Yolo];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnnotationFormat::Coco => "COCO",
+            AnnotationFormat::Yolo => "YOLO",
+        }
+    }
+}
+
+/// An item's box on a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledBox {
+    pub item_type: ItemType,
+    /// TOPLEFT, in PDF points
+    pub bbox: BoundingBox,
+}
+
+/// What one page image shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageAnnotations {
+    /// Zero-based page
+    pub page: usize,
+    /// File name of the page image
+    pub image: String,
+    /// Page size in points
+    pub size: (f64, f64),
+    pub boxes: Vec<LabeledBox>,
+}
+
+/// Class of a type: its position in `ItemType::ALL`, from 0
+pub fn class_index(item_type: ItemType) -> usize {
+    ItemType::ALL.iter().position(|t| *t == item_type).unwrap_or(0)
+}
+
+/// Pixel size of a page image at `scale`, rounded as pdfium rounds it
+pub fn image_size(size: (f64, f64), scale: f32) -> (u32, u32) {
+    let scale = scale as f64;
+    ((size.0 * scale).round() as u32, (size.1 * scale).round() as u32)
+}
+
+/// The edited items of every page, boxes clipped to the page; pages without
+/// items are kept so every image is listed
+pub fn collect(data: &Value, edits: &EditPatch, page_sizes: &[(f64, f64)], stem: &str) -> Vec<PageAnnotations> {
+    page_sizes.iter().enumerate().map(|(page, &(width, height))| {
+        let boxes = document::edited_page_items(data, page, edits).into_iter().filter_map(|item| {
+            let left = item.bbox.left.clamp(0.0, width);
+            let top = item.bbox.top.clamp(0.0, height);
+            let right = (item.bbox.left + item.bbox.width).clamp(0.0, width);
+            let bottom = (item.bbox.top + item.bbox.height).clamp(0.0, height);
+            (right > left && bottom > top).then_some(LabeledBox {
+                item_type: item.item_type,
+                bbox: BoundingBox { left, top, width: right - left, height: bottom - top },
+            })
+        }).collect();
+        PageAnnotations { page, image: page_png_name(stem, page), size: (width, height), boxes }
+    }).collect()
+}
+
+/// COCO object detection JSON for the pages, in image pixels. Category IDs
+/// are `class_index` + 1, since COCO keeps 0 for the background.
+pub fn coco_json(pages: &[PageAnnotations], scale: f32) -> Value {
+    let pixels = scale as f64;
+    let mut annotations = Vec::new();
+    let images: Vec<Value> = pages.iter().map(|page| {
+        let (width, height) = image_size(page.size, scale);
+        for labeled in &page.boxes {
+            let b = &labeled.bbox;
+            let bbox = [b.left * pixels, b.top * pixels, b.width * pixels, b.height * pixels];
+            annotations.push(json!({
+                "id": annotations.len() + 1,
+                "image_id": page.page + 1,
+                "category_id": class_index(labeled.item_type) + 1,
+                "bbox": bbox,
+                "area": bbox[2] * bbox[3],
+                "iscrowd": 0,
+            }));
+        }
+        json!({ "id": page.page + 1, "file_name": page.image, "width": width, "height": height })
+    }).collect();
+    json!({
+        "info": {
+            "description": "Layout annotations exported from chonker3",
+            "date_created": chrono::Local::now().to_rfc3339(),
+        },
+        "images": images,
+        "annotations": annotations,
+        "categories": ItemType::ALL.iter().map(|t| json!({
+            "id": class_index(*t) + 1,
+            "name": t.json_type(),
+            "supercategory": "layout",
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// A page's YOLO labels: `class x_center y_center width height` per box,
+/// as fractions of the page
+pub fn yolo_labels(page: &PageAnnotations) -> String {
+    let (width, height) = page.size;
+    page.boxes.iter().map(|labeled| {
+        let b = &labeled.bbox;
+        format!(
+            "{} {:.6} {:.6} {:.6} {:.6}\n",
+            class_index(labeled.item_type),
+            (b.left + b.width / 2.0) / width,
+            (b.top + b.height / 2.0) / height,
+            b.width / width,
+            b.height / height,
+        )
+    }).collect()
+}
+
+/// `classes.txt`: the class names in `class_index` order
+pub fn yolo_classes() -> String {
+    ItemType::ALL.iter().map(|t| format!("{}\n", t.json_type())).collect()
+}
+
+/// Write the annotations for page images already in `dir`; returns the number of boxes
+pub fn write(dir: &Path, pages: &[PageAnnotations], stem: &str, format: AnnotationFormat) -> Result<usize> {
+    let write_file = |name: &str, contents: &str| {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    };
+    match format {
+        AnnotationFormat::Coco => {
+            write_file(&format!("{}_coco.json", stem), &serde_json::to_string_pretty(&coco_json(pages, IMAGE_SCALE))?)?;
+        }
+        AnnotationFormat::Yolo => {
+            for page in pages {
+                let image = Path::new(&page.image);
+                write_file(&image.with_extension("txt").to_string_lossy(), &yolo_labels(page))?;
+            }
+            write_file(YOLO_CLASSES_FILE, &yolo_classes())?;
+        }
+    }
+    Ok(pages.iter().map(|page| page.boxes.len()).sum())
+}
@@ -0,0 +1,1006 @@
+//! Barcode and QR code decoding
+//!
+//! Pages are rendered and searched for Code 128, Code 39 and EAN-13/UPC-A
+//! barcodes along scanlines in all four reading directions, and for QR codes
+//! by their three finder patterns. Decoded codes become `BarcodeItem`s in the
+//! extraction with the payload as their text, so they show up on the canvas,
+//! in search and in exports like any other item.
+//!
+//! QR codes are read up to version 10 (57×57 modules) in byte, numeric and
+//! alphanumeric modes. Error correction is used as a check only: a damaged
+//! code is skipped rather than repaired.
+
+use anyhow::{anyhow, Result};
+use image::GrayImage;
+use pdfium_render::prelude::*;
+use serde_json::{json, Value};
+
+use crate::jobs::JobHandle;
+use crate::patch::EditPatch;
+use crate::scan_cleanup::otsu_threshold;
+use crate::types::{self, BoundingBox, ProvenanceStage, ProvenanceStep};
+
+/// Resolution pages are rendered at for decoding
+pub const RENDER_DPI: f32 = 300.0;
+
+/// Linear barcodes are searched for along every this many rows and columns
+const LINE_STEP: u32 = 3;
+
+/// A linear barcode has to read the same on this many scanlines
+const MIN_LINES: usize = 2;
+
+/// Light space needed before and after a linear barcode, in modules
+const QUIET_ZONE: f32 = 5.0;
+
+/// Largest average difference, in modules, between measured widths and a pattern
+const MAX_DEVIATION: f32 = 0.35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Symbology {
+    Code128,
+    Code39,
+    Ean13,
+    Qr,
+}
+
+impl Symbology {
+    pub const ALL: [Symbology; 4] = [Symbology::Code128, Symbology::Code39, Symbology::Ean13, Symbology::Qr];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Symbology::Code128 => "Code 128",
+            Symbology::Code39 => "Code 39",
+            Symbology::Ean13 => "EAN-13",
+            Symbology::Qr => "QR code",
+        }
+    }
+
+    /// Name in the extraction JSON and structured export
+    pub fn json_name(&self) -> &'static str {
+        match self {
+            Symbology::Code128 => "code128",
+            Symbology::Code39 => "code39",
+            Symbology::Ean13 => "ean13",
+            Symbology::Qr => "qr",
+        }
+    }
+
+    pub fn from_json_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.json_name() == name)
+    }
+}
+
+/// A decoded code with its bounds in image pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Barcode {
+    pub symbology: Symbology,
+    pub payload: String,
+    /// Left, top, right, bottom
+    pub bounds: [f32; 4],
+}
+
+impl Barcode {
+    fn touches(&self, other: &Barcode, slack: f32) -> bool {
+        self.bounds[0] - slack <= other.bounds[2] && other.bounds[0] - slack <= self.bounds[2]
+            && self.bounds[1] - slack <= other.bounds[3] && other.bounds[1] - slack <= self.bounds[3]
+    }
+}
+
+/// Every barcode and QR code found on a rendered page
+pub fn decode_image(image: &GrayImage) -> Vec<Barcode> {
+    let threshold = otsu_threshold(image);
+    let mut found = decode_qr_codes(image, threshold);
+    found.extend(decode_linear(image, threshold));
+    found
+}
+
+// ---------------------------------------------------------------------------
+// Linear barcodes
+// ---------------------------------------------------------------------------
+
+/// Code 128 bar and space widths of symbols 0-105, in modules
+pub const CODE128_PATTERNS: [[u8; 6]; 106] = [
+    [2, 1, 2, 2, 2, 2], [2, 2, 2, 1, 2, 2], [2, 2, 2, 2, 2, 1], [1, 2, 1, 2, 2, 3], [1, 2, 1, 3, 2, 2],
+    [1, 3, 1, 2, 2, 2], [1, 2, 2, 2, 1, 3], [1, 2, 2, 3, 1, 2], [1, 3, 2, 2, 1, 2], [2, 2, 1, 2, 1, 3],
+    [2, 2, 1, 3, 1, 2], [2, 3, 1, 2, 1, 2], [1, 1, 2, 2, 3, 2], [1, 2, 2, 1, 3, 2], [1, 2, 2, 2, 3, 1],
+    [1, 1, 3, 2, 2, 2], [1, 2, 3, 1, 2, 2], [1, 2, 3, 2, 2, 1], [2, 2, 3, 2, 1, 1], [2, 2, 1, 1, 3, 2],
+    [2, 2, 1, 2, 3, 1], [2, 1, 3, 2, 1, 2], [2, 2, 3, 1, 1, 2], [3, 1, 2, 1, 3, 1], [3, 1, 1, 2, 2, 2],
+    [3, 2, 1, 1, 2, 2], [3, 2, 1, 2, 2, 1], [3, 1, 2, 2, 1, 2], [3, 2, 2, 1, 1, 2], [3, 2, 2, 2, 1, 1],
+    [2, 1, 2, 1, 2, 3], [2, 1, 2, 3, 2, 1], [2, 3, 2, 1, 2, 1], [1, 1, 1, 3, 2, 3], [1, 3, 1, 1, 2, 3],
+    [1, 3, 1, 3, 2, 1], [1, 1, 2, 3, 1, 3], [1, 3, 2, 1, 1, 3], [1, 3, 2, 3, 1, 1], [2, 1, 1, 3, 1, 3],
+    [2, 3, 1, 1, 1, 3], [2, 3, 1, 3, 1, 1], [1, 1, 2, 1, 3, 3], [1, 1, 2, 3, 3, 1], [1, 3, 2, 1, 3, 1],
+    [1, 1, 3, 1, 2, 3], [1, 1, 3, 3, 2, 1], [1, 3, 3, 1, 2, 1], [3, 1, 3, 1, 2, 1], [2, 1, 1, 3, 3, 1],
+    [2, 3, 1, 1, 3, 1], [2, 1, 3, 1, 1, 3], [2, 1, 3, 3, 1, 1], [2, 1, 3, 1, 3, 1], [3, 1, 1, 1, 2, 3],
+    [3, 1, 1, 3, 2, 1], [3, 3, 1, 1, 2, 1], [3, 1, 2, 1, 1, 3], [3, 1, 2, 3, 1, 1], [3, 3, 2, 1, 1, 1],
+    [3, 1, 4, 1, 1, 1], [2, 2, 1, 4, 1, 1], [4, 3, 1, 1, 1, 1], [1, 1, 1, 2, 2, 4], [1, 1, 1, 4, 2, 2],
+    [1, 2, 1, 1, 2, 4], [1, 2, 1, 4, 2, 1], [1, 4, 1, 1, 2, 2], [1, 4, 1, 2, 2, 1], [1, 1, 2, 2, 1, 4],
+    [1, 1, 2, 4, 1, 2], [1, 2, 2, 1, 1, 4], [1, 2, 2, 4, 1, 1], [1, 4, 2, 1, 1, 2], [1, 4, 2, 2, 1, 1],
+    [2, 4, 1, 2, 1, 1], [2, 2, 1, 1, 1, 4], [4, 1, 3, 1, 1, 1], [2, 4, 1, 1, 1, 2], [1, 3, 4, 1, 1, 1],
+    [1, 1, 1, 2, 4, 2], [1, 2, 1, 1, 4, 2], [1, 2, 1, 2, 4, 1], [1, 1, 4, 2, 1, 2], [1, 2, 4, 1, 1, 2],
+    [1, 2, 4, 2, 1, 1], [4, 1, 1, 2, 1, 2], [4, 2, 1, 1, 1, 2], [4, 2, 1, 2, 1, 1], [2, 1, 2, 1, 4, 1],
+    [2, 1, 4, 1, 2, 1], [4, 1, 2, 1, 2, 1], [1, 1, 1, 1, 4, 3], [1, 1, 1, 3, 4, 1], [1, 3, 1, 1, 4, 1],
+    [1, 1, 4, 1, 1, 3], [1, 1, 4, 3, 1, 1], [4, 1, 1, 1, 1, 3], [4, 1, 1, 3, 1, 1], [1, 1, 3, 1, 4, 1],
+    [1, 1, 4, 1, 3, 1], [3, 1, 1, 1, 4, 1], [4, 1, 1, 1, 3, 1], [2, 1, 1, 4, 1, 2], [2, 1, 1, 2, 1, 4],
+    [2, 1, 1, 2, 3, 2],
+];
+
+/// Code 128 stop symbol, including its final bar
+pub const CODE128_STOP: [u8; 7] = [2, 3, 3, 1, 1, 1, 2];
+
+/// Code 128 start symbols for code sets A, B and C
+pub const CODE128_START: [u8; 3] = [103, 104, 105];
+
+/// Characters Code 39 encodes, in the order of `CODE39_PATTERNS`
+pub const CODE39_ALPHABET: &[u8; 44] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%*";
+
+/// Code 39 patterns: nine elements, bar first, most significant bit first; 1 is wide
+pub const CODE39_PATTERNS: [u16; 44] = [
+    0x034, 0x121, 0x061, 0x160, 0x031, 0x130, 0x070, 0x025, 0x124, 0x064,
+    0x109, 0x049, 0x148, 0x019, 0x118, 0x058, 0x00D, 0x10C, 0x04C, 0x01C,
+    0x103, 0x043, 0x142, 0x013, 0x112, 0x052, 0x007, 0x106, 0x046, 0x016,
+    0x181, 0x0C1, 0x1C0, 0x091, 0x190, 0x0D0, 0x085, 0x184, 0x0C4, 0x0A8,
+    0x0A2, 0x08A, 0x02A, 0x094,
+];
+
+/// EAN-13 left-hand odd-parity digit widths, space first; even parity is the reverse,
+/// and right-hand digits have the same widths starting with a bar
+pub const EAN_DIGITS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1], [2, 2, 2, 1], [2, 1, 2, 2], [1, 4, 1, 1], [1, 1, 3, 2],
+    [1, 2, 3, 1], [1, 1, 1, 4], [1, 3, 1, 2], [1, 2, 1, 3], [3, 1, 1, 2],
+];
+
+/// Parity of the six left-hand digits (1 is even) that encodes the first digit
+pub const EAN_FIRST_DIGIT: [u8; 10] = [0x00, 0x0B, 0x0D, 0x0E, 0x13, 0x19, 0x1C, 0x15, 0x16, 0x1A];
+
+/// Alternating light and dark run lengths along a scanline, starting with a
+/// light run that is empty when the line starts dark
+pub fn runs(dark: impl IntoIterator<Item = bool>) -> Vec<u32> {
+    let mut runs = vec![0u32];
+    let mut current = false;
+    for pixel in dark {
+        if pixel != current {
+            runs.push(0);
+            current = pixel;
+        }
+        *runs.last_mut().unwrap() += 1;
+    }
+    runs
+}
+
+/// A barcode read along one scanline, from the run of its first bar to that of its last
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanlineMatch {
+    pub symbology: Symbology,
+    pub payload: String,
+    pub first_run: usize,
+    pub last_run: usize,
+}
+
+/// Every linear barcode along a scanline, given as `runs`
+pub fn decode_scanline(runs: &[u32]) -> Vec<ScanlineMatch> {
+    let mut found = Vec::new();
+    let mut start = 1;
+    while start < runs.len() {
+        let decoded = decode_code128(runs, start)
+            .or_else(|| decode_ean13(runs, start))
+            .or_else(|| decode_code39(runs, start));
+        match decoded {
+            Some(decoded) => {
+                start = decoded.last_run + 2;
+                found.push(decoded);
+            }
+            None => start += 2,
+        }
+    }
+    found
+}
+
+/// Average difference in modules between measured widths and a pattern
+fn deviation(widths: &[u32], pattern: &[u8]) -> f32 {
+    let modules: u32 = pattern.iter().map(|&p| p as u32).sum();
+    let unit = widths.iter().sum::<u32>() as f32 / modules as f32;
+    if unit == 0.0 {
+        return f32::MAX;
+    }
+    widths.iter().zip(pattern).map(|(&w, &p)| (w as f32 / unit - p as f32).abs()).sum::<f32>() / pattern.len() as f32
+}
+
+/// Index of the closest pattern, if it's close enough
+fn best_pattern<'a>(widths: &[u32], patterns: impl IntoIterator<Item = &'a [u8]>) -> Option<usize> {
+    patterns.into_iter()
+        .map(|pattern| deviation(widths, pattern))
+        .enumerate()
+        .filter(|(_, d)| *d <= MAX_DEVIATION)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Whether there's enough light space before `first` and after `last`; line ends count
+fn quiet_zones(runs: &[u32], first: usize, last: usize, module: f32) -> bool {
+    let before = first == 1 || runs[first - 1] as f32 >= QUIET_ZONE * module;
+    let after = runs.get(last + 1).is_none_or(|&r| r as f32 >= QUIET_ZONE * module) || last + 2 == runs.len();
+    before && after
+}
+
+fn decode_code128(runs: &[u32], start: usize) -> Option<ScanlineMatch> {
+    let symbol = |at: usize| {
+        let widths = runs.get(at..at + 6)?;
+        best_pattern(widths, CODE128_PATTERNS.iter().map(|p| &p[..])).map(|v| (v as u8, deviation(widths, &CODE128_PATTERNS[v])))
+    };
+    let (start_value, _) = symbol(start).filter(|(v, _)| CODE128_START.contains(v))?;
+
+    let mut values = Vec::new();
+    let mut at = start + 6;
+    loop {
+        // The stop symbol's first six elements come close to some data symbols
+        let data = symbol(at);
+        let stop = runs.get(at..at + 7).map(|widths| deviation(widths, &CODE128_STOP)).filter(|&d| d <= MAX_DEVIATION);
+        match (data, stop) {
+            (Some((_, data)), Some(stop)) if stop < data => break,
+            (None, Some(_)) => break,
+            (Some((value, _)), _) => values.push(value),
+            (None, None) => return None,
+        }
+        at += 6;
+    }
+    let last = at + 6;
+    let module = runs[start..start + 6].iter().sum::<u32>() as f32 / 11.0;
+    let (&check, data) = values.split_last()?;
+    if data.is_empty() || !quiet_zones(runs, start, last, module) {
+        return None;
+    }
+    let sum = data.iter().enumerate().fold(start_value as usize, |sum, (i, &v)| sum + (i + 1) * v as usize);
+    if sum % 103 != check as usize {
+        return None;
+    }
+    Some(ScanlineMatch { symbology: Symbology::Code128, payload: code128_text(start_value, data)?, first_run: start, last_run: last })
+}
+
+/// Text of Code 128 data symbols, following code set switches and shifts
+fn code128_text(start: u8, values: &[u8]) -> Option<String> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Set { A, B, C }
+    let mut set = match start {
+        103 => Set::A,
+        104 => Set::B,
+        _ => Set::C,
+    };
+    let mut shift = false;
+    let mut text = String::new();
+    for &value in values {
+        let current = match (shift, set) {
+            (true, Set::A) => Set::B,
+            (true, Set::B) => Set::A,
+            _ => set,
+        };
+        shift = false;
+        match (current, value) {
+            (Set::C, 0..=99) => text.push_str(&format!("{:02}", value)),
+            // FNC1-FNC4 carry no text
+            (_, 102) | (Set::A | Set::B, 96 | 97) | (Set::B, 100) | (Set::A, 101) => {}
+            (Set::A | Set::C, 100) => set = Set::B,
+            (Set::B | Set::C, 101) => set = Set::A,
+            (Set::A | Set::B, 99) => set = Set::C,
+            (Set::A | Set::B, 98) => shift = true,
+            (Set::A, 0..=63) | (Set::B, 0..=95) => text.push((b' ' + value) as char),
+            (Set::A, 64..=95) => text.push((value - 64) as char),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+/// A Code 39 character from its nine element widths
+fn code39_char(widths: &[u32]) -> Option<u8> {
+    let mut sorted = widths.to_vec();
+    sorted.sort_unstable();
+    let (narrow, wide) = (sorted[5], sorted[6]);
+    if (wide as f32) < narrow as f32 * 1.3 {
+        return None;
+    }
+    let pattern = widths.iter().fold(0u16, |bits, &w| bits << 1 | (w >= wide) as u16);
+    CODE39_PATTERNS.iter().position(|&p| p == pattern).map(|index| CODE39_ALPHABET[index])
+}
+
+fn decode_code39(runs: &[u32], start: usize) -> Option<ScanlineMatch> {
+    let character = |at: usize| code39_char(runs.get(at..at + 9)?);
+    character(start).filter(|&c| c == b'*')?;
+
+    let mut text = String::new();
+    let mut at = start + 10;
+    loop {
+        let c = character(at)?;
+        if c == b'*' {
+            break;
+        }
+        text.push(c as char);
+        at += 10;
+    }
+    let last = at + 8;
+    // Nine elements with three wide ones at about 2.5:1 come to some 13 modules
+    let module = runs[start..start + 9].iter().sum::<u32>() as f32 / 13.0;
+    if text.is_empty() || !quiet_zones(runs, start, last, module) {
+        return None;
+    }
+    Some(ScanlineMatch { symbology: Symbology::Code39, payload: text, first_run: start, last_run: last })
+}
+
+fn decode_ean13(runs: &[u32], start: usize) -> Option<ScanlineMatch> {
+    let guard = |at: usize| runs.get(at..at + 3).is_some_and(|widths| deviation(widths, &[1, 1, 1]) <= MAX_DEVIATION);
+    if !guard(start) {
+        return None;
+    }
+    let module = runs[start..start + 3].iter().sum::<u32>() as f32 / 3.0;
+
+    let mut digits = Vec::with_capacity(13);
+    let mut parity = 0u8;
+    let mut at = start + 3;
+    for _ in 0..6 {
+        let widths = runs.get(at..at + 4)?;
+        let reversed: Vec<[u8; 4]> = EAN_DIGITS.iter().map(|p| [p[3], p[2], p[1], p[0]]).collect();
+        let best = best_pattern(widths, EAN_DIGITS.iter().map(|p| &p[..]).chain(reversed.iter().map(|p| &p[..])))?;
+        digits.push((best % 10) as u8);
+        parity = parity << 1 | (best >= 10) as u8;
+        at += 4;
+    }
+    if !runs.get(at..at + 5).is_some_and(|widths| deviation(widths, &[1, 1, 1, 1, 1]) <= MAX_DEVIATION) {
+        return None;
+    }
+    at += 5;
+    for _ in 0..6 {
+        digits.push(best_pattern(runs.get(at..at + 4)?, EAN_DIGITS.iter().map(|p| &p[..]))? as u8);
+        at += 4;
+    }
+    if !guard(at) {
+        return None;
+    }
+    let last = at + 2;
+    let first = EAN_FIRST_DIGIT.iter().position(|&p| p == parity)? as u8;
+    digits.insert(0, first);
+    if !quiet_zones(runs, start, last, module) || !ean13_checksum(&digits) {
+        return None;
+    }
+    let payload = digits.iter().map(|d| (b'0' + d) as char).collect();
+    Some(ScanlineMatch { symbology: Symbology::Ean13, payload, first_run: start, last_run: last })
+}
+
+/// Whether the last of 13 digits is the check digit of the others
+pub fn ean13_checksum(digits: &[u8]) -> bool {
+    let Some((&check, digits)) = digits.split_last() else { return false };
+    let sum: u32 = digits.iter().enumerate().map(|(i, &d)| d as u32 * if i % 2 == 0 { 1 } else { 3 }).sum();
+    digits.len() == 12 && (10 - sum % 10) % 10 == check as u32
+}
+
+/// Linear barcodes read along rows and columns in both directions; codes read
+/// on fewer than `MIN_LINES` scanlines are dropped as chance matches
+fn decode_linear(image: &GrayImage, threshold: u8) -> Vec<Barcode> {
+    let (width, height) = image.dimensions();
+    let mut hits = Vec::new();
+    let mut scan = |line: Vec<bool>, bounds: &dyn Fn(u32, u32) -> [f32; 4]| {
+        for reversed in [false, true] {
+            let runs = if reversed { runs(line.iter().rev().copied()) } else { runs(line.iter().copied()) };
+            for decoded in decode_scanline(&runs) {
+                let from = runs[..decoded.first_run].iter().sum::<u32>();
+                let to = runs[..=decoded.last_run].iter().sum::<u32>();
+                let (from, to) = if reversed { (line.len() as u32 - to, line.len() as u32 - from) } else { (from, to) };
+                hits.push(Barcode { symbology: decoded.symbology, payload: decoded.payload, bounds: bounds(from, to) });
+            }
+        }
+    };
+    for y in (0..height).step_by(LINE_STEP as usize) {
+        let line = (0..width).map(|x| image.get_pixel(x, y)[0] <= threshold).collect();
+        scan(line, &|from, to| [from as f32, y as f32, to as f32, (y + 1) as f32]);
+    }
+    for x in (0..width).step_by(LINE_STEP as usize) {
+        let line = (0..height).map(|y| image.get_pixel(x, y)[0] <= threshold).collect();
+        scan(line, &|from, to| [x as f32, from as f32, (x + 1) as f32, to as f32]);
+    }
+
+    // Scanlines through the same code are merged into one box
+    let mut groups: Vec<(Barcode, usize)> = Vec::new();
+    for hit in hits {
+        let group = groups.iter_mut().find(|(group, _)| {
+            group.symbology == hit.symbology && group.payload == hit.payload && group.touches(&hit, LINE_STEP as f32 * 2.0)
+        });
+        match group {
+            Some((group, count)) => {
+                group.bounds = [
+                    group.bounds[0].min(hit.bounds[0]),
+                    group.bounds[1].min(hit.bounds[1]),
+                    group.bounds[2].max(hit.bounds[2]),
+                    group.bounds[3].max(hit.bounds[3]),
+                ];
+                *count += 1;
+            }
+            None => groups.push((hit, 1)),
+        }
+    }
+    groups.into_iter().filter(|(_, count)| *count >= MIN_LINES).map(|(barcode, _)| barcode).collect()
+}
+
+// ---------------------------------------------------------------------------
+// QR codes
+// ---------------------------------------------------------------------------
+
+/// Largest QR version read
+pub const MAX_QR_VERSION: usize = 10;
+
+/// Error correction levels in the order of `QR_BLOCKS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+/// EC codewords per block, then the count and data codewords of the blocks in each of two groups
+type BlockLayout = (u8, u8, u8, u8, u8);
+
+/// Block layouts per version and level (L, M, Q, H)
+const QR_BLOCKS: [[BlockLayout; 4]; MAX_QR_VERSION] = [
+    [(7, 1, 19, 0, 0), (10, 1, 16, 0, 0), (13, 1, 13, 0, 0), (17, 1, 9, 0, 0)],
+    [(10, 1, 34, 0, 0), (16, 1, 28, 0, 0), (22, 1, 22, 0, 0), (28, 1, 16, 0, 0)],
+    [(15, 1, 55, 0, 0), (26, 1, 44, 0, 0), (18, 2, 17, 0, 0), (22, 2, 13, 0, 0)],
+    [(20, 1, 80, 0, 0), (18, 2, 32, 0, 0), (26, 2, 24, 0, 0), (16, 4, 9, 0, 0)],
+    [(26, 1, 108, 0, 0), (24, 2, 43, 0, 0), (18, 2, 15, 2, 16), (22, 2, 11, 2, 12)],
+    [(18, 2, 68, 0, 0), (16, 4, 27, 0, 0), (24, 4, 19, 0, 0), (28, 4, 15, 0, 0)],
+    [(20, 2, 78, 0, 0), (18, 4, 31, 0, 0), (18, 2, 14, 4, 15), (26, 4, 13, 1, 14)],
+    [(24, 2, 97, 0, 0), (22, 2, 38, 2, 39), (22, 4, 18, 2, 19), (26, 4, 14, 2, 15)],
+    [(30, 2, 116, 0, 0), (22, 3, 36, 2, 37), (20, 4, 16, 4, 17), (24, 4, 12, 4, 13)],
+    [(18, 2, 68, 2, 69), (26, 4, 43, 1, 44), (24, 6, 19, 2, 20), (28, 6, 15, 2, 16)],
+];
+
+/// Alignment pattern centers per version, along both axes
+const QR_ALIGNMENT: [&[usize]; MAX_QR_VERSION] = [
+    &[], &[6, 18], &[6, 22], &[6, 26], &[6, 30], &[6, 34], &[6, 22, 38], &[6, 24, 42], &[6, 26, 46], &[6, 28, 50],
+];
+
+const QR_ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// The 15 format bits for 5 data bits: BCH code, then the fixed mask
+fn format_bits(data: u32) -> u32 {
+    let mut remainder = data << 10;
+    for bit in (10..15).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= 0x537 << (bit - 10);
+        }
+    }
+    (data << 10 | remainder) ^ 0x5412
+}
+
+/// Error correction level and mask from the two copies of the format bits
+fn read_format(modules: &[Vec<bool>]) -> Option<(EcLevel, u8)> {
+    let dim = modules.len();
+    let bit = |bits: u32, row: usize, col: usize| bits << 1 | modules[row][col] as u32;
+    let mut first = 0;
+    for col in 0..6 {
+        first = bit(first, 8, col);
+    }
+    first = bit(first, 8, 7);
+    first = bit(first, 8, 8);
+    first = bit(first, 7, 8);
+    for row in (0..6).rev() {
+        first = bit(first, row, 8);
+    }
+    let mut second = 0;
+    for row in (dim - 7..dim).rev() {
+        second = bit(second, row, 8);
+    }
+    for col in dim - 8..dim {
+        second = bit(second, 8, col);
+    }
+
+    let (data, distance) = (0..32u32)
+        .map(|data| {
+            let encoded = format_bits(data);
+            (data, (encoded ^ first).count_ones().min((encoded ^ second).count_ones()))
+        })
+        .min_by_key(|(_, distance)| *distance)?;
+    if distance > 3 {
+        return None;
+    }
+    let level = [EcLevel::M, EcLevel::L, EcLevel::H, EcLevel::Q][(data >> 3) as usize];
+    Some((level, (data & 7) as u8))
+}
+
+fn masked(mask: u8, row: usize, col: usize) -> bool {
+    let (i, j) = (row, col);
+    match mask {
+        0 => (i + j) % 2 == 0,
+        1 => i % 2 == 0,
+        2 => j % 3 == 0,
+        3 => (i + j) % 3 == 0,
+        4 => (i / 2 + j / 3) % 2 == 0,
+        5 => (i * j) % 2 + (i * j) % 3 == 0,
+        6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+        _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
+    }
+}
+
+/// Modules of finder, timing, alignment, format and version patterns
+fn function_modules(version: usize) -> Vec<Vec<bool>> {
+    let dim = 17 + 4 * version;
+    let mut function = vec![vec![false; dim]; dim];
+    let mut mark = |rows: std::ops::Range<usize>, cols: std::ops::Range<usize>| {
+        for row in rows {
+            for col in cols.clone() {
+                function[row][col] = true;
+            }
+        }
+    };
+    // Finders with their separators and format bits
+    mark(0..9, 0..9);
+    mark(0..9, dim - 8..dim);
+    mark(dim - 8..dim, 0..9);
+    // Timing
+    mark(6..7, 0..dim);
+    mark(0..dim, 6..7);
+    let centers = QR_ALIGNMENT[version - 1];
+    if let (Some(&first), Some(&last)) = (centers.first(), centers.last()) {
+        for &row in centers {
+            for &col in centers {
+                let on_finder = (row == first && (col == first || col == last)) || (row == last && col == first);
+                if !on_finder {
+                    mark(row - 2..row + 3, col - 2..col + 3);
+                }
+            }
+        }
+    }
+    if version >= 7 {
+        mark(0..6, dim - 11..dim - 8);
+        mark(dim - 11..dim - 8, 0..6);
+    }
+    function
+}
+
+/// GF(256) with the QR polynomial: exponent and logarithm tables
+fn galois_tables() -> ([u8; 256], [u8; 256]) {
+    let (mut exp, mut log) = ([0u8; 256], [0u8; 256]);
+    let mut x = 1u16;
+    for (i, e) in exp.iter_mut().enumerate().take(255) {
+        *e = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11D;
+        }
+    }
+    exp[255] = exp[0];
+    (exp, log)
+}
+
+/// Whether a block's data and error correction codewords are consistent
+fn block_intact(codewords: &[u8], ec_count: usize) -> bool {
+    let (exp, log) = galois_tables();
+    let multiply = |a: u8, b: u8| if a == 0 || b == 0 { 0 } else { exp[(log[a as usize] as usize + log[b as usize] as usize) % 255] };
+    (0..ec_count).all(|i| {
+        let root = exp[i % 255];
+        codewords.iter().fold(0u8, |syndrome, &c| multiply(syndrome, root) ^ c) == 0
+    })
+}
+
+/// Read a QR code from its module grid (`modules[row][col]`, true for dark)
+pub fn decode_qr_modules(modules: &[Vec<bool>]) -> Option<String> {
+    let dim = modules.len();
+    if dim < 21 || !(dim - 17).is_multiple_of(4) || modules.iter().any(|row| row.len() != dim) {
+        return None;
+    }
+    let version = (dim - 17) / 4;
+    if version > MAX_QR_VERSION {
+        return None;
+    }
+    let (level, mask) = read_format(modules)?;
+    let function = function_modules(version);
+
+    // Two-module columns from the right, zigzagging up and down, skipping the timing column
+    let mut codewords = Vec::new();
+    let (mut byte, mut bits) = (0u8, 0);
+    let mut upward = true;
+    let mut col = dim - 1;
+    while col > 0 {
+        if col == 6 {
+            col -= 1;
+        }
+        for count in 0..dim {
+            let row = if upward { dim - 1 - count } else { count };
+            for c in [col, col - 1] {
+                if function[row][c] {
+                    continue;
+                }
+                byte = byte << 1 | (modules[row][c] ^ masked(mask, row, c)) as u8;
+                bits += 1;
+                if bits == 8 {
+                    codewords.push(byte);
+                    (byte, bits) = (0, 0);
+                }
+            }
+        }
+        upward = !upward;
+        col = col.saturating_sub(2);
+    }
+
+    let (ec, count1, data1, count2, data2) = QR_BLOCKS[version - 1][level as usize];
+    let data_lengths: Vec<usize> = std::iter::repeat_n(data1 as usize, count1 as usize)
+        .chain(std::iter::repeat_n(data2 as usize, count2 as usize))
+        .collect();
+    let total: usize = data_lengths.iter().map(|d| d + ec as usize).sum();
+    if codewords.len() != total {
+        return None;
+    }
+
+    // Codewords are interleaved across blocks
+    let mut blocks: Vec<Vec<u8>> = data_lengths.iter().map(|d| Vec::with_capacity(d + ec as usize)).collect();
+    let mut next = codewords.iter().copied();
+    let longest = data_lengths.iter().copied().max().unwrap_or(0);
+    for i in 0..longest {
+        for (block, &length) in blocks.iter_mut().zip(&data_lengths) {
+            if i < length {
+                block.push(next.next()?);
+            }
+        }
+    }
+    for _ in 0..ec {
+        for block in &mut blocks {
+            block.push(next.next()?);
+        }
+    }
+    if !blocks.iter().all(|block| block_intact(block, ec as usize)) {
+        return None;
+    }
+    let data: Vec<u8> = blocks.iter().zip(&data_lengths).flat_map(|(block, &length)| block[..length].iter().copied()).collect();
+    qr_payload(&data, version)
+}
+
+/// Decode the segments of a QR data bitstream
+fn qr_payload(data: &[u8], version: usize) -> Option<String> {
+    let mut position = 0;
+    let mut read = |count: usize| -> Option<u32> {
+        if position + count > data.len() * 8 {
+            return None;
+        }
+        let value = (position..position + count)
+            .fold(0u32, |value, bit| value << 1 | ((data[bit / 8] >> (7 - bit % 8)) & 1) as u32);
+        position += count;
+        Some(value)
+    };
+    let large = version >= 10;
+    let mut bytes = Vec::new();
+    while let Some(mode) = read(4) {
+        match mode {
+            0 => break,
+            // Numeric: three digits in 10 bits
+            1 => {
+                let mut count = read(if large { 12 } else { 10 })?;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = read([0, 4, 7, 10][digits as usize])?;
+                    bytes.extend(format!("{:0width$}", value, width = digits as usize).bytes());
+                    count -= digits;
+                }
+            }
+            // Alphanumeric: two characters in 11 bits
+            2 => {
+                let mut count = read(if large { 11 } else { 9 })?;
+                while count > 0 {
+                    if count >= 2 {
+                        let value = read(11)? as usize;
+                        bytes.push(*QR_ALPHANUMERIC.get(value / 45)?);
+                        bytes.push(*QR_ALPHANUMERIC.get(value % 45)?);
+                        count -= 2;
+                    } else {
+                        bytes.push(*QR_ALPHANUMERIC.get(read(6)? as usize)?);
+                        count -= 1;
+                    }
+                }
+            }
+            4 => {
+                let count = read(if large { 16 } else { 8 })?;
+                for _ in 0..count {
+                    bytes.push(read(8)? as u8);
+                }
+            }
+            // ECI designator; the payload is taken as UTF-8 regardless
+            7 => {
+                let first = read(8)?;
+                if first & 0x80 != 0 {
+                    read(if first & 0x40 != 0 { 16 } else { 8 })?;
+                }
+            }
+            _ => return None,
+        }
+    }
+    // Byte mode is Latin-1 by the standard, but most encoders write UTF-8
+    Some(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    })
+}
+
+/// A finder pattern center with its module size, in pixels
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f32,
+    y: f32,
+    module: f32,
+    hits: usize,
+}
+
+/// Module size if the five runs are in the 1:1:3:1:1 ratio of a finder pattern
+fn finder_module(counts: &[u32]) -> Option<f32> {
+    let total: u32 = counts.iter().sum();
+    if total < 7 {
+        return None;
+    }
+    let module = total as f32 / 7.0;
+    let tolerance = module / 2.0;
+    let fits = counts.iter().zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&count, size)| (count as f32 - module * size).abs() < tolerance * size);
+    fits.then_some(module)
+}
+
+/// Re-check a finder candidate along its column; gives the vertical center and module size
+fn vertical_finder(image: &GrayImage, threshold: u8, x: u32, y: u32) -> Option<(f32, f32)> {
+    let height = image.height() as i64;
+    let dark = |y: i64| y >= 0 && y < height && image.get_pixel(x, y as u32)[0] <= threshold;
+    let light = |y: i64| y >= 0 && y < height && !dark(y);
+    let mut counts = [0u32; 5];
+    let mut row = y as i64;
+    while dark(row) {
+        counts[2] += 1;
+        row -= 1;
+    }
+    while light(row) {
+        counts[1] += 1;
+        row -= 1;
+    }
+    while dark(row) {
+        counts[0] += 1;
+        row -= 1;
+    }
+    let mut row = y as i64 + 1;
+    while dark(row) {
+        counts[2] += 1;
+        row += 1;
+    }
+    let center = row as f32 - counts[2] as f32 / 2.0;
+    while light(row) {
+        counts[3] += 1;
+        row += 1;
+    }
+    while dark(row) {
+        counts[4] += 1;
+        row += 1;
+    }
+    finder_module(&counts).map(|module| (center, module))
+}
+
+/// Finder pattern centers, found along rows and confirmed along columns
+fn find_finders(image: &GrayImage, threshold: u8) -> Vec<Finder> {
+    let mut finders: Vec<Finder> = Vec::new();
+    for y in 0..image.height() {
+        let runs = runs((0..image.width()).map(|x| image.get_pixel(x, y)[0] <= threshold));
+        let mut x = 0;
+        for (index, window) in runs.windows(5).enumerate() {
+            if index % 2 == 1 {
+                if let Some(module) = finder_module(window) {
+                    let center_x = x as f32 + (window[0] + window[1]) as f32 + window[2] as f32 / 2.0;
+                    if let Some((center_y, vertical)) = vertical_finder(image, threshold, center_x as u32, y) {
+                        let module = (module + vertical) / 2.0;
+                        let near = finders.iter_mut().find(|f| (f.x - center_x).abs() < f.module * 2.0 && (f.y - center_y).abs() < f.module * 2.0);
+                        match near {
+                            Some(finder) => {
+                                let hits = finder.hits as f32;
+                                finder.x = (finder.x * hits + center_x) / (hits + 1.0);
+                                finder.y = (finder.y * hits + center_y) / (hits + 1.0);
+                                finder.module = (finder.module * hits + module) / (hits + 1.0);
+                                finder.hits += 1;
+                            }
+                            None => finders.push(Finder { x: center_x, y: center_y, module, hits: 1 }),
+                        }
+                    }
+                }
+            }
+            x += window[0];
+        }
+    }
+    finders.retain(|f| f.hits >= 2);
+    finders
+}
+
+/// Sample the module grid of a code whose top-left, top-right and bottom-left finders are given
+fn sample_modules(image: &GrayImage, threshold: u8, corners: [Finder; 3], dim: usize) -> (Vec<Vec<bool>>, [f32; 4]) {
+    let [a, b, c] = corners;
+    let span = (dim - 7) as f32;
+    let (ux, uy) = ((b.x - a.x) / span, (b.y - a.y) / span);
+    let (vx, vy) = ((c.x - a.x) / span, (c.y - a.y) / span);
+    let position = |row: f32, col: f32| (a.x + (col - 3.0) * ux + (row - 3.0) * vx, a.y + (col - 3.0) * uy + (row - 3.0) * vy);
+    let modules = (0..dim)
+        .map(|row| (0..dim)
+            .map(|col| {
+                let (x, y) = position(row as f32, col as f32);
+                x >= 0.0 && y >= 0.0 && (x as u32) < image.width() && (y as u32) < image.height()
+                    && image.get_pixel(x as u32, y as u32)[0] <= threshold
+            })
+            .collect())
+        .collect();
+    let edge = dim as f32 - 0.5;
+    let outline = [position(-0.5, -0.5), position(-0.5, edge), position(edge, -0.5), position(edge, edge)];
+    let bounds = outline.iter().fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |b, &(x, y)| [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)]);
+    (modules, bounds)
+}
+
+/// The finders as top-left, top-right and bottom-left if they form a code's corners
+fn qr_corners(finders: [Finder; 3]) -> Option<[Finder; 3]> {
+    let largest = finders.iter().map(|f| f.module).fold(f32::MIN, f32::max);
+    let smallest = finders.iter().map(|f| f.module).fold(f32::MAX, f32::min);
+    if largest > smallest * 1.5 {
+        return None;
+    }
+    (0..3).find_map(|corner| {
+        let a = finders[corner];
+        let (mut b, mut c) = (finders[(corner + 1) % 3], finders[(corner + 2) % 3]);
+        let (ab, ac) = ((b.x - a.x, b.y - a.y), (c.x - a.x, c.y - a.y));
+        let (lab, lac) = (ab.0.hypot(ab.1), ac.0.hypot(ac.1));
+        let square = (lab - lac).abs() <= lab.max(lac) * 0.2;
+        let right_angle = (ab.0 * ac.0 + ab.1 * ac.1).abs() <= lab * lac * 0.2;
+        if !square || !right_angle {
+            return None;
+        }
+        // Top-right follows top-left clockwise (y points down)
+        if ab.0 * ac.1 - ab.1 * ac.0 < 0.0 {
+            std::mem::swap(&mut b, &mut c);
+        }
+        Some([a, b, c])
+    })
+}
+
+/// Finders that close enough to be one code's are tried this many at most
+const MAX_FINDERS: usize = 30;
+
+fn decode_qr_codes(image: &GrayImage, threshold: u8) -> Vec<Barcode> {
+    let mut finders = find_finders(image, threshold);
+    finders.truncate(MAX_FINDERS);
+    let mut used = vec![false; finders.len()];
+    let mut found = Vec::new();
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                if used[i] || used[j] || used[k] {
+                    continue;
+                }
+                let Some(corners) = qr_corners([finders[i], finders[j], finders[k]]) else { continue };
+                let [a, b, c] = corners;
+                let module = (a.module + b.module + c.module) / 3.0;
+                let span = ((b.x - a.x).hypot(b.y - a.y) + (c.x - a.x).hypot(c.y - a.y)) / 2.0;
+                let estimate = ((span / module + 7.0 - 17.0) / 4.0).round() as i64;
+                let decoded = [estimate, estimate - 1, estimate + 1].into_iter()
+                    .filter(|v| (1..=MAX_QR_VERSION as i64).contains(v))
+                    .find_map(|version| {
+                        let (modules, bounds) = sample_modules(image, threshold, corners, 17 + 4 * version as usize);
+                        decode_qr_modules(&modules).map(|payload| Barcode { symbology: Symbology::Qr, payload, bounds })
+                    });
+                if let Some(barcode) = decoded {
+                    used[i] = true;
+                    used[j] = true;
+                    used[k] = true;
+                    found.push(barcode);
+                }
+            }
+        }
+    }
+    found
+}
+
+// ---------------------------------------------------------------------------
+// Pages and extractions
+// ---------------------------------------------------------------------------
+
+/// A code found on a (zero-based) page, boxed in PDF points from the top left
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageBarcode {
+    pub page: usize,
+    pub symbology: Symbology,
+    pub payload: String,
+    pub bbox: BoundingBox,
+}
+
+/// Render every page and decode the codes on it
+pub fn detect_pdf(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, job: &JobHandle) -> Result<Vec<PageBarcode>> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let page_count = document.pages().len() as usize;
+    job.set_total(page_count);
+
+    let scale = RENDER_DPI / 72.0;
+    let config = PdfRenderConfig::new()
+        .scale_page_by_factor(scale)
+        .render_form_data(true);
+    let mut found = Vec::new();
+    for (index, page) in document.pages().iter().enumerate() {
+        job.begin_step(format!("Page {} of {}", index + 1, page_count))?;
+        let bitmap = page.render_with_config(&config)
+            .map_err(|e| anyhow!("Failed to render page {}: {}", index + 1, e))?;
+        let rendered = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+            .ok_or_else(|| anyhow!("Page {} rendered to an unexpected size", index + 1))?;
+        let gray = image::DynamicImage::ImageRgba8(rendered).to_luma8();
+        found.extend(decode_image(&gray).into_iter().map(|barcode| {
+            let [left, top, right, bottom] = barcode.bounds.map(|v| (v / scale) as f64);
+            PageBarcode {
+                page: index,
+                symbology: barcode.symbology,
+                payload: barcode.payload,
+                bbox: BoundingBox { left, top, width: right - left, height: bottom - top },
+            }
+        }));
+        job.finish_step();
+    }
+    Ok(found)
+}
+
+/// Replace the extraction's barcode items with the codes found; returns how many were added
+pub fn add_to_extraction(data: &mut Value, found: &[PageBarcode]) -> usize {
+    let mut items: Vec<Value> = data.get("items").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    items.retain(|item| item.get("type").and_then(|v| v.as_str()) != Some("BarcodeItem"));
+    for barcode in found {
+        let b = &barcode.bbox;
+        items.push(json!({
+            "type": "BarcodeItem",
+            "page": barcode.page + 1,
+            "content": barcode.payload,
+            "symbology": barcode.symbology.json_name(),
+            "provenance": [ProvenanceStep::new(ProvenanceStage::Extractor, "barcode decoder")],
+            "bbox": {
+                "left": b.left,
+                "top": b.top,
+                "right": b.left + b.width,
+                "bottom": b.top + b.height,
+                "width": b.width,
+                "height": b.height,
+                "coord_origin": "TOPLEFT",
+            },
+        }));
+    }
+    for (index, item) in items.iter_mut().enumerate() {
+        item["index"] = json!(index);
+    }
+    data["items"] = Value::Array(items);
+    found.len()
+}
+
+/// A barcode item of an extraction, with edits applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedBarcode {
+    /// Zero-based
+    pub page: usize,
+    pub item_id: String,
+    /// None when the extractor didn't say
+    pub symbology: Option<Symbology>,
+    pub payload: String,
+}
+
+/// The extraction's barcode items in document order, skipping deleted and retyped ones
+pub fn extracted_barcodes(data: &Value, edits: &EditPatch) -> Vec<ExtractedBarcode> {
+    let page_heights: Vec<f64> = data.get("pages").and_then(|v| v.as_array()).into_iter().flatten()
+        .map(|page| page.get("height").and_then(|v| v.as_f64()).unwrap_or(792.0))
+        .collect();
+    data.get("items").and_then(|v| v.as_array()).into_iter().flatten()
+        .filter(|item| item.get("type").and_then(|v| v.as_str()) == Some("BarcodeItem"))
+        .filter_map(|item| {
+            let page = item.get("page").and_then(|v| v.as_u64())?.checked_sub(1)? as usize;
+            let bbox = crate::lines::item_box(item, page_heights.get(page).copied().unwrap_or(792.0))?;
+            let item_id = types::item_id(page, bbox.left, bbox.top);
+            let retyped = edits.type_changes.get(&item_id).is_some_and(|t| *t != types::ItemType::Barcode);
+            if edits.deletions.contains(&item_id) || retyped {
+                return None;
+            }
+            let content = item.get("content").or_else(|| item.get("text")).and_then(|v| v.as_str()).unwrap_or("");
+            Some(ExtractedBarcode {
+                page,
+                symbology: item.get("symbology").and_then(|v| v.as_str()).and_then(Symbology::from_json_name),
+                payload: edits.text_overrides.get(&item_id).cloned().unwrap_or_else(|| content.to_string()),
+                item_id,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,90 @@
+//! Bates numbers and similar page stamps
+//!
+//! Legal productions stamp every page with an identifier: a short uppercase
+//! prefix and a zero-padded counter ("ABC0001234", "DEF-000567"), usually in
+//! a bottom or top margin. Each page's stamp is found among its items, and the
+//! page-to-stamp mapping can be exported as CSV.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::patch::EditPatch;
+use crate::types::DocumentItem;
+
+/// Prefix length range, in letters
+const PREFIX_LETTERS: std::ops::RangeInclusive<usize> = 2..=8;
+
+/// Counter length range, in digits
+const COUNTER_DIGITS: std::ops::RangeInclusive<usize> = 6..=10;
+
+/// Stamps are looked for first in this share of the page height at the top and bottom
+const MARGIN_SHARE: f64 = 0.12;
+
+/// The first Bates-style identifier in the text: 2-8 capitals, an optional
+/// space, hyphen or underscore, then 6-10 digits, standing alone
+pub fn find_bates(text: &str) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    for start in 0..chars.len() {
+        if start > 0 && chars[start - 1].is_alphanumeric() {
+            continue;
+        }
+        let letters = chars[start..].iter().take_while(|c| c.is_ascii_uppercase()).count();
+        if !PREFIX_LETTERS.contains(&letters) {
+            continue;
+        }
+        let mut end = start + letters;
+        let separator = chars.get(end).filter(|c| matches!(c, ' ' | '-' | '_')).copied();
+        if separator.is_some() {
+            end += 1;
+        }
+        let digits = chars[end..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let followed_by_word = chars.get(end + digits).is_some_and(|c| c.is_alphanumeric());
+        if COUNTER_DIGITS.contains(&digits) && !followed_by_word {
+            return Some(chars[start..end + digits].iter().collect());
+        }
+    }
+    None
+}
+
+/// A page's stamp, preferring items in the top and bottom margins
+pub fn page_bates(items: &[DocumentItem], page_height: f64) -> Option<String> {
+    let in_margin = |item: &&DocumentItem| {
+        let band = page_height * MARGIN_SHARE;
+        item.bbox.top + item.bbox.height <= band || item.bbox.top >= page_height - band
+    };
+    items.iter().filter(in_margin).find_map(|item| find_bates(&item.content))
+        .or_else(|| items.iter().find_map(|item| find_bates(&item.content)))
+}
+
+/// Stamps by (zero-based) page, with edits applied; pages without one are left out
+pub fn bates_numbers(data: &Value, edits: &EditPatch) -> BTreeMap<usize, String> {
+    let pages = data.get("pages").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    pages.iter()
+        .enumerate()
+        .filter_map(|(page_index, page)| {
+            let page_height = page.get("height").and_then(|v| v.as_f64()).unwrap_or(792.0);
+            let items = crate::document::edited_page_items(data, page_index, edits);
+            page_bates(&items, page_height).map(|bates| (page_index, bates))
+        })
+        .collect()
+}
+
+/// One row per page, one-based, with an empty stamp where none was found
+pub fn bates_csv(numbers: &BTreeMap<usize, String>, page_count: usize) -> String {
+    let mut out = String::from("page,bates\n");
+    for page in 0..page_count {
+        out.push_str(&format!("{},{}\n", page + 1, numbers.get(&page).map(String::as_str).unwrap_or("")));
+    }
+    out
+}
+
+/// Write the page-to-stamp mapping; returns how many pages have a stamp
+pub fn write_bates_csv(path: &Path, data: &Value, edits: &EditPatch) -> Result<usize> {
+    let numbers = bates_numbers(data, edits);
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    std::fs::write(path, bates_csv(&numbers, page_count))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(numbers.len())
+}
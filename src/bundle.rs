@@ -0,0 +1,66 @@
+//! Document bundle export
+//!
+//! A single zip to hand off downstream: the original PDF, the raw extraction
+//! JSON, the edit patch, the edited document as Markdown and HTML, an audit
+//! report of every edit, any summaries, and optionally each page rendered to PNG.
+
+use std::io::Write;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
+
+use crate::export;
+use crate::patch::EditPatch;
+
+/// One file in the bundle, by its path inside the zip
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+impl BundleFile {
+    pub fn new(name: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self { name: name.into(), bytes: bytes.into() }
+    }
+}
+
+/// Everything but the page images
+pub fn document_files(pdf_name: &str, pdf_bytes: &[u8], data: &Value, edits: &EditPatch) -> Result<Vec<BundleFile>> {
+    let stem = Path::new(pdf_name).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "document".to_string());
+    Ok(vec![
+        BundleFile::new(pdf_name, pdf_bytes),
+        BundleFile::new("extraction.json", serde_json::to_string_pretty(data)?),
+        BundleFile::new("edits.json", serde_json::to_string_pretty(edits)?),
+        BundleFile::new(format!("{}.md", stem), export::markdown_export(data, edits)),
+        BundleFile::new(format!("{}.html", stem), export::html_export(data, edits)),
+        BundleFile::new("audit.md", export::audit_report(data, edits)),
+    ])
+}
+
+/// Page images as `pages/page_NNN.png`, in page order
+pub fn page_image_files(pngs: Vec<Vec<u8>>) -> Vec<BundleFile> {
+    pngs.into_iter()
+        .enumerate()
+        .map(|(index, png)| BundleFile::new(format!("pages/page_{:03}.png", index + 1), png))
+        .collect()
+}
+
+/// Write the files into a zip. PDFs and PNGs are already compressed and are stored as is.
+pub fn write_zip(path: &Path, files: &[BundleFile]) -> Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut zip = zip::ZipWriter::new(file);
+    for entry in files {
+        let compressed = !entry.name.ends_with(".pdf") && !entry.name.ends_with(".png");
+        let options = SimpleFileOptions::default()
+            .compression_method(if compressed { CompressionMethod::Deflated } else { CompressionMethod::Stored })
+            .large_file(entry.bytes.len() >= u32::MAX as usize);
+        zip.start_file(entry.name.as_str(), options)
+            .with_context(|| format!("Failed to add {}", entry.name))?;
+        zip.write_all(&entry.bytes)?;
+    }
+    zip.finish().with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,10 @@
+//! SHA-256 digests
+//!
+//! Documents, caches, stamps and downloads are all identified or verified by
+//! the lowercase hex SHA-256 of their bytes.
+
+use sha2::{Digest, Sha256};
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,210 @@
+//! Copy-as formats for extracted items
+//!
+//! The canvas copies items as plain text by default; holding a modifier picks
+//! another format (see `settings::CopySettings`). Items are expected in the
+//! canvas' TOPLEFT coordinates with any text override already applied.
+//! Cmd+Shift and Cmd+Alt clicks in a table copy just the clicked row or column.
+
+use serde::{Deserialize, Serialize};
+
+use crate::tables;
+use crate::types::{DocumentItem, ItemType};
+
+/// Approximate width of one monospace column, as a fraction of the font size
+const CHAR_WIDTH_RATIO: f64 = 0.5;
+
+/// Font size assumed for items that don't report one
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CopyFormat {
+    /// The item text as-is
+    #[default]
+    Plain,
+    /// Text padded with spaces and blank lines to match item positions
+    Layout,
+    /// Headings and tables as Markdown, other items as plain text
+    Markdown,
+    /// Tab-separated cells, one row per line
+    Tsv,
+}
+
+impl CopyFormat {
+    pub const ALL: [CopyFormat; 4] = [CopyFormat::Plain, CopyFormat::Layout, CopyFormat::Markdown, CopyFormat::Tsv];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CopyFormat::Plain => "Plain text",
+            CopyFormat::Layout => "Text with layout",
+            CopyFormat::Markdown => "Markdown",
+            CopyFormat::Tsv => "TSV",
+        }
+    }
+}
+
+/// Which part of a table a modified click copies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TablePart {
+    Row,
+    Column,
+}
+
+impl TablePart {
+    /// Cmd+Shift copies the row, Cmd+Alt the column
+    pub fn for_modifiers(modifiers: egui::Modifiers) -> Option<Self> {
+        match (modifiers.command, modifiers.shift, modifiers.alt) {
+            (true, true, _) => Some(TablePart::Row),
+            (true, false, true) => Some(TablePart::Column),
+            _ => None,
+        }
+    }
+}
+
+/// (row, column) of the cell at `char_index` of a table's tab-separated text,
+/// counting rows as `tables::parse_rows` does
+pub fn table_cell_at(text: &str, char_index: usize) -> (usize, usize) {
+    let mut row = 0;
+    let mut start = 0;
+    for line in text.split('\n') {
+        let len = line.chars().count();
+        let filled = !line.trim().is_empty();
+        if char_index <= start + len {
+            let column = line.chars().take(char_index - start).filter(|&c| c == '\t').count();
+            return (if filled { row } else { row.saturating_sub(1) }, column);
+        }
+        if filled {
+            row += 1;
+        }
+        start += len + 1;
+    }
+    (row.saturating_sub(1), 0)
+}
+
+/// A row of the table as one tab-separated line, or a column as one cell per
+/// line; rows missing the column give empty lines
+pub fn table_part(text: &str, (row, column): (usize, usize), part: TablePart) -> String {
+    let rows = tables::parse_rows(text);
+    match part {
+        TablePart::Row => rows.get(row).map(|cells| cells.join("\t")).unwrap_or_default(),
+        TablePart::Column => rows.iter()
+            .map(|cells| cells.get(column).map_or("", String::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Format items for the clipboard
+pub fn format_items(items: &[DocumentItem], format: CopyFormat) -> String {
+    match format {
+        CopyFormat::Plain => items.iter()
+            .map(|item| item.content.trim())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        CopyFormat::Layout => layout_text(items),
+        CopyFormat::Markdown => items.iter()
+            .map(markdown_item)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        CopyFormat::Tsv => tsv_text(items),
+    }
+}
+
+fn markdown_item(item: &DocumentItem) -> String {
+    let text = item.content.trim();
+    match item.item_type {
+        ItemType::Title => format!("# {}", text),
+        ItemType::Header => format!("## {}", text),
+        ItemType::Table => crate::export::markdown_table(text).trim_end().to_string(),
+        ItemType::Code => crate::export::code_fence(&item.content),
+        ItemType::Formula => crate::export::formula_markdown(crate::export::formula_source(item.latex.as_deref(), text)),
+        ItemType::Barcode => crate::export::barcode_markdown(text),
+        _ => text.to_string(),
+    }
+}
+
+/// Group items into visual lines: items whose vertical centers are within half
+/// a line height of each other share a line. Lines are sorted top to bottom and
+/// their items left to right.
+fn visual_lines(items: &[DocumentItem]) -> Vec<Vec<&DocumentItem>> {
+    let mut sorted: Vec<&DocumentItem> = items.iter().collect();
+    sorted.sort_by(|a, b| a.bbox.top.total_cmp(&b.bbox.top));
+
+    let mut lines: Vec<Vec<&DocumentItem>> = Vec::new();
+    for item in sorted {
+        let center = item.bbox.top + item.bbox.height / 2.0;
+        let same_line = lines.last().and_then(|line| line.first()).is_some_and(|first| {
+            let first_center = first.bbox.top + first.bbox.height / 2.0;
+            (center - first_center).abs() <= first.bbox.height.max(item.bbox.height) / 2.0
+        });
+        match lines.last_mut() {
+            Some(line) if same_line => line.push(item),
+            _ => lines.push(vec![item]),
+        }
+    }
+    for line in &mut lines {
+        line.sort_by(|a, b| a.bbox.left.total_cmp(&b.bbox.left));
+    }
+    lines
+}
+
+fn font_size(item: &DocumentItem) -> f64 {
+    if item.font_size > 0.0 { item.font_size as f64 } else { DEFAULT_FONT_SIZE }
+}
+
+/// Plain text laid out on a character grid so columns and gaps survive a paste
+fn layout_text(items: &[DocumentItem]) -> String {
+    let lines = visual_lines(items);
+    let Some(origin) = items.iter().map(|i| i.bbox.left).min_by(f64::total_cmp) else { return String::new() };
+    let char_width = items.iter().map(font_size).sum::<f64>() / items.len() as f64 * CHAR_WIDTH_RATIO;
+
+    let mut out = String::new();
+    let mut previous_bottom: Option<(f64, f64)> = None;
+    for line in lines {
+        let top = line.iter().map(|i| i.bbox.top).min_by(f64::total_cmp).unwrap_or(0.0);
+        let height = line.iter().map(|i| i.bbox.height).max_by(f64::total_cmp).unwrap_or(0.0);
+
+        // One blank line for every line-height of vertical gap
+        if let Some((bottom, line_height)) = previous_bottom {
+            let blank_lines = ((top - bottom) / line_height.max(1.0)).floor().max(0.0) as usize;
+            out.push_str(&"\n".repeat(blank_lines));
+        }
+
+        let mut row = String::new();
+        for item in line {
+            let column = ((item.bbox.left - origin) / char_width).round().max(0.0) as usize;
+            let used = row.chars().count();
+            // Always keep a space between neighbours that would collide
+            let padding = if used == 0 { column } else { column.saturating_sub(used).max(1) };
+            row.push_str(&" ".repeat(padding));
+            row.push_str(&item.content.trim().replace('\n', " "));
+        }
+        out.push_str(row.trim_end());
+        out.push('\n');
+        previous_bottom = Some((top + height, height));
+    }
+    out
+}
+
+/// Table items keep their own rows and cells; other items become one cell per
+/// item, one row per visual line
+fn tsv_text(items: &[DocumentItem]) -> String {
+    let mut rows = Vec::new();
+    for line in visual_lines(items) {
+        let mut cells = Vec::new();
+        for item in line {
+            if item.item_type == ItemType::Table {
+                if !cells.is_empty() {
+                    rows.push(std::mem::take(&mut cells).join("\t"));
+                }
+                rows.extend(item.content.lines()
+                    .map(|row| row.split('\t').map(str::trim).collect::<Vec<_>>().join("\t")));
+            } else {
+                cells.push(item.content.trim().replace(['\t', '\n'], " "));
+            }
+        }
+        if !cells.is_empty() {
+            rows.push(cells.join("\t"));
+        }
+    }
+    rows.join("\n")
+}
@@ -0,0 +1,549 @@
+//! Experimental LAN collaboration
+//!
+//! Edits are replicated with a last-writer-wins map CRDT: every (field, item) pair
+//! holds a value stamped with a Lamport clock and site ID, and merging keeps the
+//! highest stamp, so peers converge regardless of message order. Peers talk over
+//! plain TCP with newline-delimited JSON; the host relays messages between joiners.
+//!
+//! A joiner has to quote the session code the host shows and have the same PDF
+//! open (by SHA-256) before it sees or sends any edits. Each connection has its
+//! own writer thread, so a peer that stops reading never stalls the UI.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::patch::EditPatch;
+
+pub const DEFAULT_PORT: u16 = 47470;
+/// Longest message taken from a peer; a new peer gets the whole state in one
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+/// Messages waiting for a peer before it's dropped as stalled
+const SEND_QUEUE: usize = 1024;
+/// How long a peer gets to introduce itself, and to take a write
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EditField {
+    Text,
+    Offset,
+    Deleted,
+    Type,
+    Annotation,
+    /// A whole comment thread; the latest change to it wins
+    Comment,
+    /// Whether a table is stitched onto the one on the previous page
+    TableStitch,
+}
+
+/// Lamport timestamp; ties are broken by site ID so every peer picks the same winner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Stamp {
+    pub clock: u64,
+    pub site: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Op {
+    pub field: EditField,
+    pub item_id: String,
+    /// `Null` removes the edit
+    pub value: Value,
+    pub stamp: Stamp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Message {
+    /// First message each way; the host answers only a joiner with the right code and PDF
+    Hello { site: u64, name: String, token: String, document: String },
+    Refused { reason: String },
+    Ops(Vec<Op>),
+    Presence { site: u64, name: String, item_id: Option<String> },
+    Left { site: u64 },
+}
+
+/// Events from the network threads to the UI thread
+enum NetEvent {
+    /// A peer got through the handshake
+    Connected { conn_id: usize, site: u64, name: String },
+    Received(usize, Message),
+    Disconnected(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Peer {
+    pub name: String,
+    pub editing: Option<String>,
+}
+
+pub struct CollabSession {
+    pub site: u64,
+    pub name: String,
+    pub is_host: bool,
+    pub address: String,
+    /// Port we listen on when hosting
+    pub port: Option<u16>,
+    /// Session code joiners must quote
+    pub token: String,
+    /// SHA-256 of the PDF being edited; peers must have the same one open
+    pub document: String,
+    clock: u64,
+    entries: BTreeMap<(EditField, String), (Value, Stamp)>,
+    connections: Connections,
+    events: Receiver<NetEvent>,
+    peers: HashMap<u64, Peer>,
+    // Site IDs of the peers directly behind each connection
+    conn_sites: HashMap<usize, u64>,
+    presence: Option<String>,
+}
+
+impl CollabSession {
+    /// Listen for peers on the given port (0 picks a free one), editing the
+    /// PDF with SHA-256 `document`
+    pub fn host(port: u16, name: String, document: String) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .with_context(|| format!("Could not listen on port {}", port))?;
+        let port = listener.local_addr()?.port();
+        let (tx, rx) = channel();
+        let mut session = Self::new(true, format!("port {}", port), name, new_token(), document, rx);
+        session.port = Some(port);
+
+        let connections = session.connections.clone();
+        let hello = session.hello();
+        std::thread::spawn(move || {
+            for (conn_id, stream) in listener.incoming().flatten().enumerate() {
+                let (connections, events, hello) = (connections.clone(), tx.clone(), hello.clone());
+                // Handshakes run off the accept loop so a silent peer holds up no one else
+                std::thread::spawn(move || {
+                    let address = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+                    match accept_peer(&stream, &hello) {
+                        Ok((reader, site, name)) => spawn_connection(conn_id, stream, reader, site, name, connections, events),
+                        Err(e) => log::warn!("Refused collab peer {}: {:#}", address, e),
+                    }
+                });
+            }
+        });
+
+        Ok(session)
+    }
+
+    /// Connect to a hosting peer at `host:port` with its session code
+    pub fn join(address: &str, token: &str, name: String, document: String) -> Result<Self> {
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("{}:{}", address, DEFAULT_PORT)
+        };
+        let stream = TcpStream::connect(&address)
+            .with_context(|| format!("Could not connect to {}", address))?;
+        let (tx, rx) = channel();
+        let session = Self::new(false, address, name, normalize_token(token), document, rx);
+        let (reader, site, name) = greet_host(&stream, &session.hello())?;
+        spawn_connection(0, stream, reader, site, name, session.connections.clone(), tx);
+        Ok(session)
+    }
+
+    fn new(is_host: bool, address: String, name: String, token: String, document: String, events: Receiver<NetEvent>) -> Self {
+        // No RNG dependency; time and PID are unique enough for a LAN session
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self {
+            site: nanos ^ ((std::process::id() as u64) << 32),
+            name,
+            is_host,
+            address,
+            port: None,
+            token,
+            document,
+            clock: 0,
+            entries: BTreeMap::new(),
+            connections: Arc::new(Mutex::new(Vec::new())),
+            events,
+            peers: HashMap::new(),
+            conn_sites: HashMap::new(),
+            presence: None,
+        }
+    }
+
+    fn hello(&self) -> Message {
+        Message::Hello {
+            site: self.site,
+            name: self.name.clone(),
+            token: self.token.clone(),
+            document: self.document.clone(),
+        }
+    }
+
+    pub fn peers(&self) -> impl Iterator<Item = &Peer> {
+        self.peers.values()
+    }
+
+    /// Items being edited by peers, mapped to the peer's name
+    pub fn remote_editing(&self) -> HashMap<String, String> {
+        self.peers.values()
+            .filter_map(|p| p.editing.clone().map(|id| (id, p.name.clone())))
+            .collect()
+    }
+
+    /// Publish local edits: diff the app's current edits against the replicated state
+    pub fn sync_local(&mut self, edits: &EditPatch) {
+        let current = flatten_patch(edits);
+        let mut ops = Vec::new();
+
+        for (key, value) in &current {
+            let changed = self.entries.get(key).map(|(v, _)| v != value).unwrap_or(true);
+            if changed {
+                ops.push(self.local_op(key.clone(), value.clone()));
+            }
+        }
+        let removed: Vec<_> = self.entries.iter()
+            .filter(|(key, (value, _))| !value.is_null() && !current.contains_key(*key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in removed {
+            ops.push(self.local_op(key, Value::Null));
+        }
+
+        if !ops.is_empty() {
+            self.broadcast(&Message::Ops(ops), None);
+        }
+    }
+
+    /// Announce which item we are editing
+    pub fn set_presence(&mut self, item_id: Option<String>) {
+        if self.presence != item_id {
+            self.presence = item_id.clone();
+            let message = Message::Presence { site: self.site, name: self.name.clone(), item_id };
+            self.broadcast(&message, None);
+        }
+    }
+
+    /// Process network events. Returns the merged edits if remote changes arrived.
+    pub fn poll(&mut self) -> Option<EditPatch> {
+        let mut changed = false;
+
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                NetEvent::Connected { conn_id, site, name } => {
+                    self.conn_sites.insert(conn_id, site);
+                    self.peers.insert(site, Peer { name, editing: None });
+                    // Bring the new peer up to date with everything we know
+                    let state = Message::Ops(self.all_ops());
+                    let presence = Message::Presence {
+                        site: self.site,
+                        name: self.name.clone(),
+                        item_id: self.presence.clone(),
+                    };
+                    for message in [state, presence] {
+                        self.send_to(conn_id, &message);
+                    }
+                }
+                NetEvent::Received(conn_id, message) => {
+                    match &message {
+                        // Handshakes are done before a connection gets here
+                        Message::Hello { .. } | Message::Refused { .. } => continue,
+                        Message::Ops(ops) => {
+                            for op in ops {
+                                changed |= self.merge(op.clone());
+                            }
+                        }
+                        Message::Presence { site, name, item_id } => {
+                            // Relayed presence may come from a peer we only know through the host
+                            let peer = self.peers.entry(*site).or_insert(Peer {
+                                name: name.clone(),
+                                editing: None,
+                            });
+                            peer.editing = item_id.clone();
+                        }
+                        Message::Left { site } => {
+                            self.peers.remove(site);
+                        }
+                    }
+                    // The host relays everything to the other peers
+                    if self.is_host {
+                        self.broadcast(&message, Some(conn_id));
+                    }
+                }
+                NetEvent::Disconnected(conn_id) => {
+                    if let Some(site) = self.conn_sites.remove(&conn_id) {
+                        self.peers.remove(&site);
+                        if self.is_host {
+                            self.broadcast(&Message::Left { site }, Some(conn_id));
+                        }
+                    }
+                }
+            }
+        }
+
+        if changed {
+            Some(self.edits())
+        } else {
+            None
+        }
+    }
+
+    fn local_op(&mut self, key: (EditField, String), value: Value) -> Op {
+        self.clock += 1;
+        let stamp = Stamp { clock: self.clock, site: self.site };
+        self.entries.insert(key.clone(), (value.clone(), stamp));
+        Op { field: key.0, item_id: key.1, value, stamp }
+    }
+
+    /// Last-writer-wins merge; returns true if the op changed our state
+    fn merge(&mut self, op: Op) -> bool {
+        self.clock = self.clock.max(op.stamp.clock);
+        let key = (op.field, op.item_id);
+        match self.entries.get(&key) {
+            Some((_, stamp)) if *stamp >= op.stamp => false,
+            _ => {
+                self.entries.insert(key, (op.value, op.stamp));
+                true
+            }
+        }
+    }
+
+    fn all_ops(&self) -> Vec<Op> {
+        self.entries.iter()
+            .map(|((field, item_id), (value, stamp))| Op {
+                field: *field,
+                item_id: item_id.clone(),
+                value: value.clone(),
+                stamp: *stamp,
+            })
+            .collect()
+    }
+
+    /// Current replicated edits as a patch
+    pub fn edits(&self) -> EditPatch {
+        let mut patch = EditPatch::new(None);
+        for ((field, id), (value, _)) in &self.entries {
+            if value.is_null() {
+                continue;
+            }
+            let id = id.clone();
+            match field {
+                EditField::Text => if let Some(text) = value.as_str() {
+                    patch.text_overrides.insert(id, text.to_string());
+                },
+                EditField::Offset => if let Ok(offset) = serde_json::from_value(value.clone()) {
+                    patch.offsets.insert(id, offset);
+                },
+                EditField::Deleted => {
+                    patch.deletions.insert(id);
+                }
+                EditField::Type => if let Ok(item_type) = serde_json::from_value(value.clone()) {
+                    patch.type_changes.insert(id, item_type);
+                },
+                EditField::Annotation => if let Some(text) = value.as_str() {
+                    patch.annotations.insert(id, text.to_string());
+                },
+                EditField::Comment => if let Ok(thread) = serde_json::from_value(value.clone()) {
+                    patch.comments.insert(id, thread);
+                },
+                EditField::TableStitch => if let Some(stitched) = value.as_bool() {
+                    patch.table_stitches.insert(id, stitched);
+                },
+            }
+        }
+        patch
+    }
+
+    /// Queue a message for every peer; only hands lines to the writer
+    /// threads, so it never waits on the network
+    fn broadcast(&self, message: &Message, except: Option<usize>) {
+        let Ok(line) = serde_json::to_string(message) else { return };
+        let line: Arc<str> = line.into();
+        let mut connections = self.connections.lock().unwrap();
+        connections.retain(|(conn_id, queue)| Some(*conn_id) == except || queue_line(queue, &line));
+    }
+
+    fn send_to(&self, target: usize, message: &Message) {
+        let Ok(line) = serde_json::to_string(message) else { return };
+        let line: Arc<str> = line.into();
+        let mut connections = self.connections.lock().unwrap();
+        connections.retain(|(conn_id, queue)| *conn_id != target || queue_line(queue, &line));
+    }
+}
+
+/// Each connection's queue of outgoing lines, drained by its writer thread
+type Connections = Arc<Mutex<Vec<(usize, SyncSender<Arc<str>>)>>>;
+
+/// Hand a line to a writer thread; false once the peer has gone or fallen too
+/// far behind, and dropping its queue then closes the connection
+fn queue_line(queue: &SyncSender<Arc<str>>, line: &Arc<str>) -> bool {
+    match queue.try_send(line.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            log::warn!("Dropping a collab peer that stopped reading");
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    }
+}
+
+/// Random session code, grouped for reading out; the std hasher is keyed
+/// from the OS's random source, which saves an RNG dependency
+fn new_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0));
+    let hex = format!("{:016x}", hasher.finish());
+    format!("{}-{}-{}-{}", &hex[..4], &hex[4..8], &hex[8..12], &hex[12..])
+}
+
+/// A typed-in session code, forgiving of case and surrounding space
+fn normalize_token(token: &str) -> String {
+    token.trim().to_lowercase()
+}
+
+/// Check a peer's handshake against ours; returns its site ID and name
+fn check_hello(message: Message, ours: &Message) -> Result<(u64, String)> {
+    let Message::Hello { token: our_token, document: our_document, .. } = ours else { bail!("Not a handshake") };
+    match message {
+        Message::Hello { site, name, token, document } => {
+            if token != *our_token {
+                bail!("Wrong session code");
+            }
+            if document != *our_document {
+                bail!("The other side has a different PDF open");
+            }
+            Ok((site, name))
+        }
+        Message::Refused { reason } => bail!("{}", reason),
+        _ => bail!("Peer didn't start with a handshake"),
+    }
+}
+
+/// Host side of the handshake: the joiner speaks first, and gets our hello
+/// (with the session code) only if it already knew the code and has the same PDF
+fn accept_peer(stream: &TcpStream, hello: &Message) -> Result<(BufReader<TcpStream>, u64, String)> {
+    stream.set_read_timeout(Some(NETWORK_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let line = read_line(&mut reader)?.context("Peer hung up")?;
+    let checked = serde_json::from_str(&line).map_err(anyhow::Error::from).and_then(|message| check_hello(message, hello));
+    let mut writer = stream;
+    let (site, name) = match checked {
+        Ok(peer) => peer,
+        Err(e) => {
+            let refusal = Message::Refused { reason: e.to_string() };
+            let _ = writeln!(writer, "{}", serde_json::to_string(&refusal)?);
+            return Err(e);
+        }
+    };
+    writeln!(writer, "{}", serde_json::to_string(hello)?)?;
+    stream.set_read_timeout(None)?;
+    Ok((reader, site, name))
+}
+
+/// Joiner side of the handshake
+fn greet_host(stream: &TcpStream, hello: &Message) -> Result<(BufReader<TcpStream>, u64, String)> {
+    stream.set_read_timeout(Some(NETWORK_TIMEOUT))?;
+    let mut writer = stream;
+    writeln!(writer, "{}", serde_json::to_string(hello)?)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let line = read_line(&mut reader)?.context("Host hung up")?;
+    let (site, name) = check_hello(serde_json::from_str(&line)?, hello).context("Host refused to collaborate")?;
+    stream.set_read_timeout(None)?;
+    Ok((reader, site, name))
+}
+
+/// Next line from a peer, without its newline; `None` at the end of the
+/// stream. A line longer than `MAX_MESSAGE_BYTES` is an error rather than
+/// something to buffer without bound.
+pub fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    reader.by_ref().take(MAX_MESSAGE_BYTES as u64 + 1).read_line(&mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() > MAX_MESSAGE_BYTES {
+        bail!("Message over {} bytes", MAX_MESSAGE_BYTES);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Flatten a patch into CRDT keys and JSON values
+fn flatten_patch(patch: &EditPatch) -> BTreeMap<(EditField, String), Value> {
+    let mut map = BTreeMap::new();
+    for (id, text) in &patch.text_overrides {
+        map.insert((EditField::Text, id.clone()), Value::from(text.clone()));
+    }
+    for (id, offset) in &patch.offsets {
+        map.insert((EditField::Offset, id.clone()), serde_json::to_value(offset).unwrap_or(Value::Null));
+    }
+    for id in &patch.deletions {
+        map.insert((EditField::Deleted, id.clone()), Value::Bool(true));
+    }
+    for (id, item_type) in &patch.type_changes {
+        map.insert((EditField::Type, id.clone()), serde_json::to_value(item_type).unwrap_or(Value::Null));
+    }
+    for (id, text) in &patch.annotations {
+        map.insert((EditField::Annotation, id.clone()), Value::from(text.clone()));
+    }
+    for (id, thread) in &patch.comments {
+        map.insert((EditField::Comment, id.clone()), serde_json::to_value(thread).unwrap_or(Value::Null));
+    }
+    for (id, stitched) in &patch.table_stitches {
+        map.insert((EditField::TableStitch, id.clone()), Value::Bool(*stitched));
+    }
+    map
+}
+
+/// Register a connection that got through the handshake, with a writer thread
+/// draining its queue and a reader thread feeding the UI's event channel
+fn spawn_connection(
+    conn_id: usize,
+    stream: TcpStream,
+    mut reader: BufReader<TcpStream>,
+    site: u64,
+    name: String,
+    connections: Connections,
+    events: Sender<NetEvent>,
+) {
+    let (queue, lines) = sync_channel::<Arc<str>>(SEND_QUEUE);
+    connections.lock().unwrap().push((conn_id, queue));
+    let _ = events.send(NetEvent::Connected { conn_id, site, name });
+
+    std::thread::spawn(move || {
+        let _ = stream.set_write_timeout(Some(NETWORK_TIMEOUT));
+        let mut writer = &stream;
+        for line in lines {
+            if writeln!(writer, "{}", line).is_err() {
+                break;
+            }
+        }
+        // Dropped queue or failed write: hang up, which also ends the reader
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    });
+
+    std::thread::spawn(move || {
+        loop {
+            let line = match read_line(&mut reader) {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    log::warn!("Closing collab connection: {:#}", e);
+                    break;
+                }
+            };
+            match serde_json::from_str::<Message>(&line) {
+                Ok(message) => {
+                    if events.send(NetEvent::Received(conn_id, message)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => log::warn!("Ignoring malformed collab message: {}", e),
+            }
+        }
+        let _ = reader.get_ref().shutdown(std::net::Shutdown::Both);
+        connections.lock().unwrap().retain(|(id, _)| *id != conn_id);
+        let _ = events.send(NetEvent::Disconnected(conn_id));
+    });
+}
@@ -0,0 +1,67 @@
+//! Comment threads on items
+//!
+//! Reviewers can discuss an item without changing it: each item has at most
+//! one thread, which is marked resolved once settled and reopens when someone
+//! replies. Threads travel in the edit patch, are kept per document in the
+//! workspace, sync with collaborators and are listed in the audit report.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub author: String,
+    /// RFC 3339
+    pub timestamp: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommentThread {
+    pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+impl CommentThread {
+    /// Add a comment signed now; a reply reopens a resolved thread. Blank
+    /// text is ignored and returns false.
+    pub fn add(&mut self, author: &str, text: &str) -> bool {
+        let text = text.trim();
+        if text.is_empty() {
+            return false;
+        }
+        self.comments.push(Comment {
+            author: author.trim().to_string(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+            text: text.to_string(),
+        });
+        self.resolved = false;
+        true
+    }
+
+    pub fn last(&self) -> Option<&Comment> {
+        self.comments.last()
+    }
+
+    /// One line per comment, for hover text and the audit report
+    pub fn summary(&self) -> String {
+        self.comments.iter()
+            .map(|c| format!("{} ({}): {}", c.author, short_time(&c.timestamp), c.text.replace('\n', " ")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Name comments are signed with when none is set: the login name
+pub fn default_author() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "chonker".to_string())
+}
+
+/// "2024-05-01 14:03" from an RFC 3339 timestamp, or the timestamp as is
+pub fn short_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
@@ -0,0 +1,89 @@
+//! Continuous page view
+//!
+//! Instead of one page at a time, the PDF panel can stack every page in a
+//! single vertical scroll like other PDF readers. The stack is laid out from
+//! the page sizes alone, so the scroll height is right before any page is
+//! rendered; only the pages in view, plus one either side, get textures. The
+//! page counter follows whichever page fills the upper part of the view.
+
+use std::ops::Range;
+use egui::{Rect, Vec2};
+
+/// Space between stacked pages, in points
+pub const PAGE_GAP: f32 = 12.0;
+
+/// Pages rendered beyond either edge of the view, so scrolling doesn't reveal blanks
+pub const OVERSCAN: usize = 1;
+
+/// Share of the view height, from its top, where the current page is read
+const CURRENT_PAGE_LINE: f32 = 1.0 / 3.0;
+
+/// Pages stacked top to bottom at their on-screen sizes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageStack {
+    sizes: Vec<Vec2>,
+    tops: Vec<f32>,
+}
+
+impl PageStack {
+    pub fn new(sizes: impl IntoIterator<Item = Vec2>) -> Self {
+        let sizes: Vec<Vec2> = sizes.into_iter().collect();
+        let mut top = 0.0;
+        let tops = sizes.iter().map(|size| {
+            let this = top;
+            top += size.y + PAGE_GAP;
+            this
+        }).collect();
+        Self { sizes, tops }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// Size of the whole stack: the widest page by every page and the gaps between
+    pub fn size(&self) -> Vec2 {
+        let width = self.sizes.iter().map(|size| size.x).fold(0.0, f32::max);
+        let height = match (self.tops.last(), self.sizes.last()) {
+            (Some(top), Some(size)) => top + size.y,
+            _ => 0.0,
+        };
+        Vec2::new(width, height)
+    }
+
+    /// Offset of a page's top edge from the top of the stack
+    pub fn top(&self, page: usize) -> f32 {
+        self.tops.get(page).copied().unwrap_or_else(|| self.size().y)
+    }
+
+    /// Where a page sits, relative to the stack's top-left corner; pages are centred across
+    pub fn rect(&self, page: usize) -> Rect {
+        let size = self.sizes.get(page).copied().unwrap_or(Vec2::ZERO);
+        let left = (self.size().x - size.x) / 2.0;
+        Rect::from_min_size(egui::pos2(left, self.top(page)), size)
+    }
+
+    /// Page at an offset down the stack; a gap belongs to the page above it
+    pub fn page_at(&self, offset: f32) -> usize {
+        self.tops.partition_point(|top| *top <= offset).saturating_sub(1)
+    }
+
+    /// Pages worth rendering for a view showing `top..bottom` of the stack
+    pub fn visible(&self, top: f32, bottom: f32) -> Range<usize> {
+        if self.is_empty() {
+            return 0..0;
+        }
+        let first = self.page_at(top).saturating_sub(OVERSCAN);
+        let last = (self.page_at(bottom) + OVERSCAN).min(self.len() - 1);
+        first..last + 1
+    }
+
+    /// The page a view starting `top` down the stack, `height` tall, is on
+    pub fn current_page(&self, top: f32, height: f32) -> usize {
+        self.page_at(top + height * CURRENT_PAGE_LINE)
+    }
+}
@@ -0,0 +1,950 @@
+//! Headless application core
+//!
+//! `Session` owns everything about the open document that isn't UI: the PDF
+//! bytes, the extraction, the current page, the search query and the user's
+//! edits. The egui app drives a session; CLI and server modes can do the same.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{anyhow, Context, Result};
+use pdfium_render::prelude::*;
+use serde_json::Value;
+
+use crate::comments::CommentThread;
+use crate::regions::{self, NamedRegion, RegionField};
+use crate::extractor::{Capabilities, ExtractOptions, ExtractedDocument, Extractor};
+use crate::normalize::{self, NumberLocale};
+use crate::patch::EditPatch;
+use crate::pdf_output::PdfOutput;
+use crate::pii::{self, PiiDetector, PiiHit, RedactionBox};
+use crate::stamp::{Stamp, StampMode};
+use crate::reflow::{self, PrintLayout};
+use crate::search::{self, Matcher, SearchHit};
+use crate::summary::{self, Summaries};
+use crate::translation::Translation;
+use crate::types::{self, BoundingBox, DocumentItem, DocumentState, ItemType};
+use crate::{barcodes, bates, bundle, document, export, lines, references, reocr, searchable, snap, stats, tables, transcript, validation};
+
+/// Bind pdfium from PDFIUM_DYNAMIC_LIB_PATH (default ./lib), falling back to the system library
+pub fn bind_pdfium() -> Result<Pdfium> {
+    bind_pdfium_from(None)
+}
+
+/// Bind pdfium from a library file the user picked, if any, else as `bind_pdfium`
+pub fn bind_pdfium_from(library: Option<&Path>) -> Result<Pdfium> {
+    if let Some(library) = library {
+        let bindings = Pdfium::bind_to_library(library)
+            .map_err(|e| anyhow!("Failed to load pdfium from {}: {}", library.display(), e))?;
+        return Ok(Pdfium::new(bindings));
+    }
+
+    let lib_path = std::env::var("PDFIUM_DYNAMIC_LIB_PATH")
+        .unwrap_or_else(|_| "./lib".to_string());
+
+    let bindings = Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&lib_path))
+        .or_else(|_| Pdfium::bind_to_system_library())
+        .map_err(|e| anyhow!("Failed to load pdfium: {}", e))?;
+    Ok(Pdfium::new(bindings))
+}
+
+/// The open PDF's bytes, memory-mapped when possible. Clones share the data.
+///
+/// A mapping reflects later changes to the file on disk, so files are treated
+/// as read-only while open; `PdfBytes::read` copies the file instead.
+#[derive(Clone)]
+pub enum PdfBytes {
+    Mapped(Arc<memmap2::Mmap>),
+    Owned(Arc<Vec<u8>>),
+}
+
+impl PdfBytes {
+    /// Map the file, falling back to reading it (e.g. empty files can't be mapped)
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        // SAFETY: the mapping is read-only; see the type docs on concurrent modification
+        match unsafe { memmap2::Mmap::map(&file) } {
+            Ok(map) => Ok(PdfBytes::Mapped(Arc::new(map))),
+            Err(_) => Self::read(path),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(PdfBytes::Owned(Arc::new(bytes)))
+    }
+
+    /// Bytes held on the heap; mapped pages belong to the OS page cache
+    pub fn heap_bytes(&self) -> usize {
+        match self {
+            PdfBytes::Mapped(_) => 0,
+            PdfBytes::Owned(bytes) => bytes.len(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for PdfBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        PdfBytes::Owned(Arc::new(bytes))
+    }
+}
+
+impl std::ops::Deref for PdfBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            PdfBytes::Mapped(map) => map,
+            PdfBytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// The user's edits on top of an extraction, keyed by item ID
+#[derive(Debug, Clone, Default)]
+pub struct Edits {
+    pub text_overrides: HashMap<String, String>,
+    pub offsets: HashMap<String, (f32, f32)>,
+    pub boxes: HashMap<String, BoundingBox>,
+    pub deletions: HashSet<String>,
+    pub type_overrides: HashMap<String, ItemType>,
+    pub annotations: HashMap<String, String>,
+    /// Bookmarked pages, by zero-based index
+    pub bookmarks: BTreeSet<usize>,
+    /// Free-text notes on whole pages, by zero-based index
+    pub page_notes: BTreeMap<usize, String>,
+    pub comments: HashMap<String, CommentThread>,
+    /// Table continuation decisions, by the continuing table's ID
+    pub table_stitches: HashMap<String, bool>,
+    /// Named regions, in the order they were drawn
+    pub regions: Vec<NamedRegion>,
+}
+
+impl Edits {
+    pub fn to_patch(&self, source_file: Option<String>) -> EditPatch {
+        let mut patch = EditPatch::new(source_file);
+        patch.text_overrides = self.text_overrides.clone().into_iter().collect();
+        patch.offsets = self.offsets.clone().into_iter().collect();
+        patch.boxes = self.boxes.clone().into_iter().collect();
+        patch.deletions = self.deletions.iter().cloned().collect();
+        patch.type_changes = self.type_overrides.clone().into_iter().collect();
+        patch.annotations = self.annotations.clone().into_iter().collect();
+        patch.bookmarks = self.bookmarks.clone();
+        patch.page_notes = self.page_notes.clone();
+        patch.comments = self.comments.clone().into_iter().collect();
+        patch.table_stitches = self.table_stitches.clone().into_iter().collect();
+        patch.regions = self.regions.clone();
+        patch
+    }
+
+    /// Merge a patch in; patch entries win
+    pub fn merge(&mut self, patch: EditPatch) {
+        self.text_overrides.extend(patch.text_overrides);
+        self.offsets.extend(patch.offsets);
+        self.boxes.extend(patch.boxes);
+        self.deletions.extend(patch.deletions);
+        self.type_overrides.extend(patch.type_changes);
+        self.annotations.extend(patch.annotations);
+        self.bookmarks.extend(patch.bookmarks);
+        self.page_notes.extend(patch.page_notes);
+        self.comments.extend(patch.comments);
+        self.table_stitches.extend(patch.table_stitches);
+        for region in patch.regions {
+            match self.regions.iter_mut().find(|r| r.name.eq_ignore_ascii_case(&region.name)) {
+                Some(existing) => *existing = region,
+                None => self.regions.push(region),
+            }
+        }
+    }
+
+    /// Replace all edits with the patch's
+    pub fn replace(&mut self, patch: EditPatch) {
+        *self = Edits::default();
+        self.merge(patch);
+    }
+
+    /// Re-key every edit, dropping edits whose item no longer exists
+    pub fn remap(&mut self, remap: impl Fn(&str) -> Option<String>) {
+        fn remap_map<V>(map: &mut HashMap<String, V>, remap: &impl Fn(&str) -> Option<String>) {
+            *map = std::mem::take(map).into_iter()
+                .filter_map(|(k, v)| remap(&k).map(|k| (k, v)))
+                .collect();
+        }
+        remap_map(&mut self.text_overrides, &remap);
+        remap_map(&mut self.offsets, &remap);
+        remap_map(&mut self.boxes, &remap);
+        remap_map(&mut self.type_overrides, &remap);
+        remap_map(&mut self.annotations, &remap);
+        remap_map(&mut self.comments, &remap);
+        remap_map(&mut self.table_stitches, &remap);
+        self.deletions = std::mem::take(&mut self.deletions).into_iter()
+            .filter_map(|k| remap(&k))
+            .collect();
+    }
+
+    /// Move bookmarks, page notes and regions to new page indexes, dropping deleted pages
+    pub fn remap_pages(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        self.regions = std::mem::take(&mut self.regions).into_iter()
+            .filter_map(|region| remap(region.page).map(|page| NamedRegion { page, ..region }))
+            .collect();
+        self.bookmarks = std::mem::take(&mut self.bookmarks).into_iter()
+            .filter_map(&remap)
+            .collect();
+        self.page_notes = std::mem::take(&mut self.page_notes).into_iter()
+            .filter_map(|(page, note)| remap(page).map(|page| (page, note)))
+            .collect();
+    }
+}
+
+#[derive(Default)]
+pub struct Session {
+    /// None when the library couldn't be loaded; extraction still works without it
+    pub pdfium: Option<Arc<Pdfium>>,
+    /// Library file the user located by hand, tried instead of the default locations
+    pub pdfium_library: Option<PathBuf>,
+    /// Why pdfium couldn't be loaded
+    pub pdfium_error: Option<String>,
+    pub pdf_path: Option<PathBuf>,
+    pub pdf_bytes: Option<PdfBytes>,
+    /// Password the open PDF was unlocked with, if it is encrypted
+    pub pdf_password: Option<String>,
+    pub page_count: usize,
+    /// Zero-based current page
+    pub page: usize,
+    pub extracted_json: Option<PathBuf>,
+    pub extracted_data: Option<Value>,
+    pub search_query: String,
+    pub edits: Edits,
+    /// Decimal mark used when normalizing extracted numbers
+    pub number_locale: NumberLocale,
+    /// Image compression and linearization of exported PDFs
+    pub pdf_output: PdfOutput,
+    /// Whether exports record their source checksum and provenance
+    pub stamping: StampMode,
+    /// Page and document summaries of the current extraction
+    pub summaries: Summaries,
+}
+
+impl Session {
+    /// Open a PDF, dropping any previous extraction. Edits are kept so a patch
+    /// can be loaded before the PDF. Without pdfium the PDF still opens for
+    /// extraction; the page count then comes from the extraction.
+    pub fn open_pdf(&mut self, path: &Path) -> Result<()> {
+        let bytes = PdfBytes::open(path)?;
+
+        self.ensure_pdfium();
+
+        self.pdf_path = Some(path.to_path_buf());
+        self.pdf_bytes = Some(bytes);
+        self.pdf_password = None;
+        self.page_count = self.count_pages();
+        self.page = 0;
+        self.extracted_json = None;
+        self.extracted_data = None;
+        self.summaries = Summaries::default();
+        Ok(())
+    }
+
+    /// An empty session for another document, sharing this one's pdfium and settings
+    pub fn sibling(&self) -> Session {
+        Session {
+            pdfium: self.pdfium.clone(),
+            pdfium_library: self.pdfium_library.clone(),
+            pdfium_error: self.pdfium_error.clone(),
+            number_locale: self.number_locale,
+            pdf_output: self.pdf_output,
+            stamping: self.stamping,
+            ..Default::default()
+        }
+    }
+
+    /// Bind pdfium if it isn't loaded yet; false (with `pdfium_error` set) if it can't be
+    pub fn ensure_pdfium(&mut self) -> bool {
+        if self.pdfium.is_none() {
+            match bind_pdfium_from(self.pdfium_library.as_deref()) {
+                Ok(pdfium) => {
+                    #[allow(clippy::arc_with_non_send_sync)]
+                    let pdfium = Arc::new(pdfium);
+                    self.pdfium = Some(pdfium);
+                    self.pdfium_error = None;
+                }
+                Err(e) => self.pdfium_error = Some(e.to_string()),
+            }
+        }
+        self.pdfium.is_some()
+    }
+
+    /// Whether the open PDF is encrypted and `pdf_password` doesn't open it
+    pub fn is_locked(&self) -> bool {
+        let (Some(pdfium), Some(bytes)) = (self.pdfium.as_ref(), self.pdf_bytes.as_ref()) else { return false };
+        matches!(
+            pdfium.load_pdf_from_byte_slice(bytes, self.pdf_password.as_deref()),
+            Err(PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError))
+        )
+    }
+
+    /// Open the encrypted PDF with `password`; false if it's the wrong one
+    pub fn unlock(&mut self, password: &str) -> bool {
+        self.pdf_password = Some(password.to_string());
+        if self.is_locked() {
+            self.pdf_password = None;
+            return false;
+        }
+        self.page_count = self.count_pages();
+        true
+    }
+
+    fn count_pages(&self) -> usize {
+        self.with_document(|document| document.pages().len() as usize).unwrap_or(0)
+    }
+
+    /// Load pdfium from a library file the user located
+    pub fn set_pdfium_library(&mut self, library: &Path) -> Result<()> {
+        #[allow(clippy::arc_with_non_send_sync)]
+        let pdfium = Arc::new(bind_pdfium_from(Some(library))?);
+        self.pdfium = Some(pdfium);
+        self.pdfium_library = Some(library.to_path_buf());
+        self.pdfium_error = None;
+        if self.pdf_bytes.is_some() {
+            self.page_count = self.count_pages().max(self.page_count);
+        }
+        Ok(())
+    }
+
+    /// File name of the open PDF, as recorded in patches and exports
+    pub fn source_file_name(&self) -> Option<String> {
+        self.pdf_path.as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+    }
+
+    /// Run `f` on the open PDF document
+    pub fn with_document<T>(&self, f: impl FnOnce(&PdfDocument) -> T) -> Option<T> {
+        let (pdfium, bytes) = (self.pdfium.as_ref()?, self.pdf_bytes.as_ref()?);
+        let document = pdfium.load_pdf_from_byte_slice(bytes, self.pdf_password.as_deref()).ok()?;
+        Some(f(&document))
+    }
+
+    /// Page sizes of the open PDF in points
+    pub fn page_sizes(&self) -> Vec<(f64, f64)> {
+        self.with_document(|document| {
+            document.pages().iter()
+                .map(|page| (page.width().value as f64, page.height().value as f64))
+                .collect()
+        }).unwrap_or_default()
+    }
+
+    /// Use an extraction for the open PDF; returns its item count
+    pub fn set_extraction(&mut self, mut data: Value, json_path: Option<PathBuf>) -> usize {
+        normalize::normalize_extraction(&mut data, self.number_locale);
+        let item_count = data.get("items").and_then(|v| v.as_array()).map(|a| a.len()).unwrap_or(0);
+        if self.page_count == 0 {
+            // Nothing counted the PDF's pages (no pdfium); trust the extraction
+            self.page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+        }
+        self.extracted_data = Some(data);
+        self.extracted_json = json_path;
+        self.summaries = Summaries::default();
+        item_count
+    }
+
+    /// Extract the open PDF with `extractor`, blocking until it finishes.
+    /// The GUI runs extractors on a worker thread and calls `set_extraction`.
+    pub fn extract(&mut self, extractor: &dyn Extractor, opts: &ExtractOptions) -> Result<usize> {
+        let pdf_path = self.pdf_path.clone().ok_or_else(|| anyhow!("No PDF open"))?;
+        let opts = ExtractOptions { pdfium_library: opts.pdfium_library.clone().or_else(|| self.pdfium_library.clone()), ..opts.clone() };
+        let document = extractor.extract(&pdf_path, &opts)?;
+        let item_count = self.set_extraction(document.data, Some(document.json_path));
+        if opts.line_items && self.split_into_lines()? > 0 {
+            let items = self.extracted_data.as_ref().and_then(|data| data.get("items")).and_then(|v| v.as_array());
+            return Ok(items.map_or(0, |items| items.len()));
+        }
+        Ok(item_count)
+    }
+
+    /// Split multi-line items into per-line items using pdfium's word boxes and
+    /// write the result back to the extraction JSON; returns how many items were split
+    pub fn split_into_lines(&mut self) -> Result<usize> {
+        let mut data = self.extracted_data.take().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let split = self.with_document(|document| {
+            lines::split_into_lines(&mut data, |page_index| {
+                document.pages().get(page_index as u16)
+                    .map(|page| lines::page_word_boxes(&page))
+                    .unwrap_or_default()
+            })
+        });
+        if split.is_some_and(|split| split > 0) {
+            // Lines need their own normalized values
+            normalize::normalize_extraction(&mut data, self.number_locale);
+        }
+        self.extracted_data = Some(data);
+        let split = split.ok_or_else(|| anyhow!("Splitting lines needs the pdfium library"))?;
+        if let (Some(path), Some(data), true) = (&self.extracted_json, &self.extracted_data, split > 0) {
+            let json = serde_json::to_string_pretty(data)?;
+            std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(split)
+    }
+
+    pub fn load_extraction(&mut self, json_path: &Path) -> Result<usize> {
+        let document = ExtractedDocument::load(json_path)?;
+        Ok(self.set_extraction(document.data, Some(document.json_path)))
+    }
+
+    /// Which extractor or importer produced the extraction
+    pub fn extraction_backend(&self) -> Option<String> {
+        let metadata = self.extracted_data.as_ref()?.get("metadata")?;
+        if let Some(importer) = metadata.get("importer").and_then(|v| v.as_str()) {
+            return Some(format!("{} import", importer));
+        }
+        if let Some(extractor) = metadata.get("extractor").and_then(|v| v.as_str()) {
+            return Some(extractor.to_string());
+        }
+        metadata.get("docling_version").map(|_| "docling".to_string())
+    }
+
+    /// What the current extraction can contain; text only without one
+    pub fn extraction_capabilities(&self) -> Capabilities {
+        self.extracted_data.as_ref().map(Capabilities::of_extraction).unwrap_or_default()
+    }
+
+    pub fn set_number_locale(&mut self, locale: NumberLocale) {
+        self.number_locale = locale;
+        if let Some(data) = self.extracted_data.as_mut() {
+            normalize::normalize_extraction(data, locale);
+        }
+    }
+
+    /// Bookmark the page, or remove its bookmark; returns whether it's now bookmarked
+    pub fn toggle_bookmark(&mut self, page: usize) -> bool {
+        if self.edits.bookmarks.remove(&page) {
+            false
+        } else {
+            self.edits.bookmarks.insert(page);
+            true
+        }
+    }
+
+    /// Set a page's note; blank notes remove it
+    pub fn set_page_note(&mut self, page: usize, note: &str) {
+        if note.trim().is_empty() {
+            self.edits.page_notes.remove(&page);
+        } else {
+            self.edits.page_notes.insert(page, note.to_string());
+        }
+    }
+
+    /// Bookmarked pages and pages with notes, in page order
+    pub fn marked_pages(&self) -> Vec<usize> {
+        let pages: BTreeSet<usize> = self.edits.bookmarks.iter()
+            .chain(self.edits.page_notes.keys())
+            .copied()
+            .collect();
+        pages.into_iter().collect()
+    }
+
+    pub fn go_to_page(&mut self, page: usize) -> bool {
+        if page < self.page_count && page != self.page {
+            self.page = page;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Document state for the current page, if there is an extraction
+    pub fn document_state(&self) -> Option<DocumentState> {
+        let data = self.extracted_data.as_ref()?;
+        Some(document::document_state(data, self.page, &self.to_patch(), &self.search_query))
+    }
+
+    /// An item of any page, with edits applied
+    pub fn item(&self, item_id: &str) -> Option<DocumentItem> {
+        let data = self.extracted_data.as_ref()?;
+        let page = types::item_page(item_id)?;
+        document::page_items(data, page, &self.to_patch()).into_iter().find(|item| item.id == item_id)
+    }
+
+    /// Search every page; returns (zero-based page, item ID) pairs in page order
+    pub fn search_all(&self, query: &str) -> Vec<(usize, String)> {
+        let Some(data) = &self.extracted_data else { return Vec::new() };
+        let patch = self.to_patch();
+        let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+        (0..page_count.max(self.page_count))
+            .flat_map(|page| {
+                let items = document::page_items(data, page, &patch);
+                document::search_matches(&items, query).into_iter().map(move |id| (page, id))
+            })
+            .collect()
+    }
+
+    /// Search every page; returns the hits best first, with snippets
+    pub fn search_ranked(&self, query: &str) -> Vec<SearchHit> {
+        self.search_with(&Matcher::Words(query.to_string()))
+    }
+
+    /// Like `search_ranked`, for words or a pattern
+    pub fn search_with(&self, matcher: &Matcher) -> Vec<SearchHit> {
+        let Some(data) = &self.extracted_data else { return Vec::new() };
+        search::document_hits(data, &self.to_patch(), self.page_count, matcher)
+    }
+
+    pub fn set_text(&mut self, item_id: &str, text: String) {
+        self.edits.text_overrides.insert(item_id.to_string(), text);
+    }
+
+    /// Override an item's type, or clear the override with None
+    pub fn set_type(&mut self, item_id: &str, item_type: Option<ItemType>) {
+        match item_type {
+            Some(item_type) => self.edits.type_overrides.insert(item_id.to_string(), item_type),
+            None => self.edits.type_overrides.remove(item_id),
+        };
+    }
+
+    /// Set an item's annotation; blank text removes it
+    pub fn set_annotation(&mut self, item_id: &str, note: &str) {
+        if note.trim().is_empty() {
+            self.edits.annotations.remove(item_id);
+        } else {
+            self.edits.annotations.insert(item_id.to_string(), note.to_string());
+        }
+    }
+
+    /// Comment on an item, starting its thread if it has none; false for blank text
+    pub fn add_comment(&mut self, item_id: &str, author: &str, text: &str) -> bool {
+        let mut thread = self.edits.comments.get(item_id).cloned().unwrap_or_default();
+        let added = thread.add(author, text);
+        if added {
+            self.edits.comments.insert(item_id.to_string(), thread);
+        }
+        added
+    }
+
+    pub fn set_comments_resolved(&mut self, item_id: &str, resolved: bool) {
+        if let Some(thread) = self.edits.comments.get_mut(item_id) {
+            thread.resolved = resolved;
+        }
+    }
+
+    /// Snap item boxes on one page, or every page, to where pdfium finds their
+    /// text; returns how many boxes moved. Snapped items lose any manual offset.
+    pub fn snap_boxes(&mut self, page: Option<usize>) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        if self.pdfium.is_none() {
+            return Err(anyhow!("Snapping needs the pdfium library"));
+        }
+        let patch = self.to_patch();
+        let pages: Vec<usize> = match page {
+            Some(page) => vec![page],
+            None => (0..self.page_count).collect(),
+        };
+        let snapped = self.with_document(|document| {
+            let mut snapped = BTreeMap::new();
+            for index in pages {
+                let Ok(pdf_page) = document.pages().get(index as u16) else { continue };
+                let items = document::page_items(data, index, &patch);
+                snapped.extend(snap::snap_items(&pdf_page, &items));
+            }
+            snapped
+        }).ok_or_else(|| anyhow!("Failed to open the PDF"))?;
+
+        let count = snapped.len();
+        for (id, bbox) in snapped {
+            self.edits.offsets.remove(&id);
+            self.edits.boxes.insert(id, bbox);
+        }
+        Ok(count)
+    }
+
+    pub fn delete_item(&mut self, item_id: &str) {
+        self.edits.deletions.insert(item_id.to_string());
+    }
+
+    pub fn to_patch(&self) -> EditPatch {
+        self.edits.to_patch(self.source_file_name())
+    }
+
+    /// Merge a patch into the current edits; returns a status line
+    pub fn apply_patch(&mut self, patch: EditPatch) -> String {
+        let edit_count = patch.edit_count();
+        let source = patch.source_file.clone();
+        self.edits.merge(patch);
+
+        match (source, self.source_file_name()) {
+            (Some(source), Some(current)) if source != current => {
+                format!("Applied {} edits (patch was made for {})", edit_count, source)
+            }
+            _ => format!("Applied {} edits from patch", edit_count),
+        }
+    }
+
+    /// Stamp of the exports of the current extraction
+    pub fn stamp(&self) -> Option<Stamp> {
+        let data = self.extracted_data.as_ref()?;
+        Some(Stamp::new(self.pdf_path.as_deref(), self.pdf_bytes.as_deref(), data))
+    }
+
+    /// Stamp an export just written, as the settings ask
+    fn stamp_export(&self, path: &Path) -> Result<()> {
+        if let Some(stamp) = self.stamp() {
+            stamp.apply(path, self.stamping)?;
+        }
+        Ok(())
+    }
+
+    pub fn export_markdown(&self, path: &Path) -> Result<()> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        export::write_markdown(path, data, &self.to_patch())?;
+        self.stamp_export(path)
+    }
+
+    /// Write the document as Markdown with a translation's text in place of the original
+    pub fn export_translation(&self, path: &Path, translation: &Translation) -> Result<()> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        export::write_markdown(path, data, &translation.patch(&self.to_patch()))?;
+        self.stamp_export(path)
+    }
+
+    /// Write the page and document summaries as Markdown
+    pub fn export_summaries(&self, path: &Path) -> Result<()> {
+        let name = self.source_file_name().unwrap_or_else(|| "document.pdf".to_string());
+        std::fs::write(path, self.summaries.markdown(&name)).with_context(|| format!("Failed to write {}", path.display()))?;
+        self.stamp_export(path)
+    }
+
+    /// Write the structured JSON export; returns the number of items written
+    pub fn export_structured(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let count = export::write_structured(path, data, &self.to_patch(), self.number_locale)?;
+        self.stamp_export(path)?;
+        Ok(count)
+    }
+
+    /// Name a region of a page; names must be unique
+    pub fn add_region(&mut self, name: &str, page: usize, bbox: BoundingBox) -> Result<()> {
+        let name = regions::check_name(name, &self.edits.regions, None)?;
+        self.edits.regions.push(NamedRegion { name, page, bbox });
+        Ok(())
+    }
+
+    pub fn rename_region(&mut self, index: usize, name: &str) -> Result<()> {
+        let name = regions::check_name(name, &self.edits.regions, Some(index))?;
+        let region = self.edits.regions.get_mut(index).ok_or_else(|| anyhow!("No such region"))?;
+        region.name = name;
+        Ok(())
+    }
+
+    pub fn remove_region(&mut self, index: usize) {
+        if index < self.edits.regions.len() {
+            self.edits.regions.remove(index);
+        }
+    }
+
+    /// Each named region's current contents
+    pub fn region_fields(&self) -> Vec<RegionField> {
+        self.extracted_data.as_ref()
+            .map(|data| regions::fields(data, &self.to_patch(), &self.edits.regions))
+            .unwrap_or_default()
+    }
+
+    /// The rules the named regions' contents break
+    pub fn validate(&self, rules: &[validation::Rule]) -> Vec<validation::Violation> {
+        validation::evaluate(rules, &self.region_fields(), self.number_locale)
+    }
+
+    /// Personal data on every page, in page order
+    pub fn pii_hits(&self, detector: &PiiDetector) -> Vec<PiiHit> {
+        self.pii_pages(detector).into_iter().flat_map(|(_, hits)| hits).collect()
+    }
+
+    /// Items and personal data of each page with any
+    fn pii_pages(&self, detector: &PiiDetector) -> Vec<(Vec<DocumentItem>, Vec<PiiHit>)> {
+        let Some(data) = &self.extracted_data else { return Vec::new() };
+        let patch = self.to_patch();
+        let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+        (0..page_count.max(self.page_count))
+            .map(|page| {
+                let items = document::edited_page_items(data, page, &patch);
+                let hits = detector.scan(page, &items);
+                (items, hits)
+            })
+            .filter(|(_, hits)| !hits.is_empty())
+            .collect()
+    }
+
+    /// Mask personal data in the items' text, as text edits; returns how many items changed
+    pub fn mask_pii(&mut self, detector: &PiiDetector) -> usize {
+        let mut masked = 0;
+        for (items, hits) in self.pii_pages(detector) {
+            for item in &items {
+                let ranges: Vec<_> = hits.iter().filter(|hit| hit.item_id == item.id).map(|hit| hit.range.clone()).collect();
+                if !ranges.is_empty() {
+                    self.set_text(&item.id, pii::mask(&item.content, &ranges));
+                    masked += 1;
+                }
+            }
+        }
+        masked
+    }
+
+    /// Redaction boxes over the items holding personal data
+    pub fn redaction_boxes(&self, detector: &PiiDetector) -> Vec<RedactionBox> {
+        self.pii_pages(detector).iter()
+            .flat_map(|(items, hits)| pii::redaction_boxes(hits, items))
+            .collect()
+    }
+
+    /// Write the redaction boxes as JSON; returns how many there are
+    pub fn export_redactions(&self, path: &Path, detector: &PiiDetector) -> Result<usize> {
+        self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let boxes = self.redaction_boxes(detector);
+        pii::write_redactions(path, self.source_file_name().as_deref(), &boxes)?;
+        self.stamp_export(path)?;
+        Ok(boxes.len())
+    }
+
+    /// Write the named regions' contents as JSON fields; returns the number of fields
+    pub fn export_fields(&self, path: &Path) -> Result<usize> {
+        self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let fields = self.region_fields();
+        let source_file = self.pdf_path.as_ref().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().to_string());
+        regions::write_fields(path, source_file.as_deref(), &fields)?;
+        self.stamp_export(path)?;
+        Ok(fields.len())
+    }
+
+    /// Tables continuing from the previous page
+    pub fn table_continuations(&self) -> Vec<tables::Continuation> {
+        self.extracted_data.as_ref()
+            .map(|data| tables::detect_continuations(data, &self.to_patch()))
+            .unwrap_or_default()
+    }
+
+    /// Accept or reject stitching a table onto the one on the previous page
+    pub fn set_table_stitch(&mut self, table_id: &str, stitched: bool) {
+        self.edits.table_stitches.insert(table_id.to_string(), stitched);
+    }
+
+    /// Write the tables, stitched across pages, as CSV; returns the number of tables
+    pub fn export_tables_csv(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let tables = tables::logical_tables(data, &self.to_patch());
+        tables::write_csv(path, &tables)?;
+        self.stamp_export(path)?;
+        Ok(tables.len())
+    }
+
+    /// Write the tables, stitched across pages, as an XLSX workbook with a
+    /// sheet per table; returns the number of tables
+    pub fn export_tables_xlsx(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let tables = tables::logical_tables(data, &self.to_patch());
+        tables::write_xlsx(path, &tables, self.number_locale)?;
+        self.stamp_export(path)?;
+        Ok(tables.len())
+    }
+
+    pub fn export_csv(&self, path: &Path) -> Result<()> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        export::write_csv(path, data, &self.to_patch(), self.number_locale)?;
+        self.stamp_export(path)
+    }
+
+    /// Items as JSONL; returns how many were written
+    pub fn export_jsonl(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let count = export::write_jsonl(path, data, &self.to_patch(), self.number_locale)?;
+        self.stamp_export(path)?;
+        Ok(count)
+    }
+
+    /// Reference entries and the citations linked to them
+    pub fn references(&self) -> references::References {
+        self.extracted_data.as_ref()
+            .map(|data| references::collect(data, &self.to_patch()))
+            .unwrap_or_default()
+    }
+
+    /// Write the reference list as BibTeX; returns the number of entries
+    pub fn export_bibtex(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let count = references::write_bibtex(path, data, &self.to_patch())?;
+        self.stamp_export(path)?;
+        Ok(count)
+    }
+
+    /// Barcode and QR code items, in document order
+    pub fn barcodes(&self) -> Vec<barcodes::ExtractedBarcode> {
+        self.extracted_data.as_ref()
+            .map(|data| barcodes::extracted_barcodes(data, &self.to_patch()))
+            .unwrap_or_default()
+    }
+
+    /// Replace the barcode items with the codes detected on the pages and write
+    /// the result back to the extraction JSON; returns how many were added
+    pub fn add_barcodes(&mut self, found: &[barcodes::PageBarcode]) -> Result<usize> {
+        let data = self.extracted_data.as_mut().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let added = barcodes::add_to_extraction(data, found);
+        self.write_extraction()?;
+        Ok(added)
+    }
+
+    /// Use re-OCR'd text for an item: the text becomes an edit, and the read is
+    /// added to the item's provenance in the extraction JSON
+    pub fn apply_reocr(&mut self, item_id: &str, ocr: &reocr::OcrText) -> Result<()> {
+        let data = self.extracted_data.as_mut().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let index = document::find_json_item(data, item_id).ok_or_else(|| anyhow!("The item is no longer in the extraction"))?;
+        document::record_provenance(&mut data["items"][index], ocr.provenance());
+        self.set_text(item_id, ocr.text.clone());
+        self.write_extraction()
+    }
+
+    /// Add the text read from a region of a (zero-based) page as a new item;
+    /// returns its ID
+    pub fn add_ocr_item(&mut self, page_index: usize, bbox: &BoundingBox, ocr: &reocr::OcrText) -> Result<String> {
+        let data = self.extracted_data.as_mut().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let items = data.get_mut("items").and_then(|v| v.as_array_mut()).ok_or_else(|| anyhow!("The extraction has no items"))?;
+        items.push(serde_json::json!({
+            "index": items.len(),
+            "type": "TextItem",
+            "page": page_index + 1,
+            "content": ocr.text,
+            "confidence": ocr.confidence,
+            "provenance": [ocr.provenance()],
+            "bbox": {
+                "left": bbox.left,
+                "top": bbox.top,
+                "right": bbox.left + bbox.width,
+                "bottom": bbox.top + bbox.height,
+                "width": bbox.width,
+                "height": bbox.height,
+                "coord_origin": "TOPLEFT",
+            },
+        }));
+        self.write_extraction()?;
+        Ok(types::item_id(page_index, bbox.left, bbox.top))
+    }
+
+    /// Write the extraction back to its JSON file, if it has one
+    fn write_extraction(&self) -> Result<()> {
+        if let (Some(data), Some(path)) = (&self.extracted_data, &self.extracted_json) {
+            let json = serde_json::to_string_pretty(data)?;
+            std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// The Bates number stamped on a (zero-based) page, if any
+    pub fn page_bates(&self, page_index: usize) -> Option<String> {
+        let data = self.extracted_data.as_ref()?;
+        let page_height = data.get("pages")
+            .and_then(|pages| pages.get(page_index))
+            .and_then(|page| page.get("height"))
+            .and_then(|h| h.as_f64())
+            .unwrap_or(792.0);
+        let items = document::edited_page_items(data, page_index, &self.to_patch());
+        bates::page_bates(&items, page_height)
+    }
+
+    /// Write the page-to-Bates mapping as CSV; returns how many pages have a number
+    pub fn export_bates_csv(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let count = bates::write_bates_csv(path, data, &self.to_patch())?;
+        self.stamp_export(path)?;
+        Ok(count)
+    }
+
+    /// Item, word and confidence statistics of the extraction as edited
+    pub fn stats(&self) -> Option<stats::DocumentStats> {
+        self.extracted_data.as_ref().map(|data| stats::document_stats(data, &self.to_patch()))
+    }
+
+    /// Non-whitespace characters in each page's text layer; None for pages without one
+    pub fn text_layer_chars(&self) -> Vec<Option<usize>> {
+        self.with_document(|document| {
+            document.pages().iter()
+                .map(|page| {
+                    let count = page.text().map_or(0, |text| text.all().chars().filter(|c| !c.is_whitespace()).count());
+                    (count > 0).then_some(count)
+                })
+                .collect()
+        }).unwrap_or_default()
+    }
+
+    pub fn page_heat(&self, text_layer_chars: &[Option<usize>]) -> Vec<stats::PageHeat> {
+        self.extracted_data.as_ref()
+            .map(|data| stats::page_heat(data, &self.to_patch(), text_layer_chars))
+            .unwrap_or_default()
+    }
+
+    pub fn export_stats(&self, path: &Path) -> Result<()> {
+        let stats = self.stats().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        stats::write_stats(path, &stats)?;
+        self.stamp_export(path)
+    }
+
+    /// Summarize a page with TextRank, replacing its cached summary
+    pub fn summarize_page(&mut self, page: usize) -> Result<&summary::Summary> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let page_summary = summary::page_summary(data, page, &self.to_patch());
+        self.summaries.pages.insert(page, page_summary);
+        Ok(&self.summaries.pages[&page])
+    }
+
+    /// Summarize the whole document with TextRank, replacing its cached summary
+    pub fn summarize_document(&mut self) -> Result<&summary::Summary> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let document_summary = summary::document_summary(data, &self.to_patch(), self.page_count);
+        Ok(self.summaries.document.insert(document_summary))
+    }
+
+    /// The bundle's files apart from page images: PDF, extraction, edits,
+    /// Markdown, HTML, audit and any summaries made
+    pub fn bundle_files(&self) -> Result<Vec<bundle::BundleFile>> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let pdf_bytes = self.pdf_bytes.as_ref().ok_or_else(|| anyhow!("No PDF open"))?;
+        let pdf_name = self.source_file_name().unwrap_or_else(|| "document.pdf".to_string());
+        let mut files = bundle::document_files(&pdf_name, pdf_bytes, data, &self.to_patch())?;
+        if !self.summaries.is_empty() {
+            files.push(bundle::BundleFile::new("summary.md", self.summaries.markdown(&pdf_name)));
+        }
+        Ok(files)
+    }
+
+    /// Word and character error rates of each page against a reference transcript
+    pub fn transcript_scores(&self, reference: &transcript::Transcript) -> Vec<transcript::PageScore> {
+        self.extracted_data.as_ref()
+            .map(|data| transcript::score_pages(data, &self.to_patch(), reference))
+            .unwrap_or_default()
+    }
+
+    /// Items on the current page whose text isn't in the transcript's page
+    pub fn divergent_items(&self, reference: &transcript::Transcript) -> Vec<String> {
+        let (Some(data), Some(page_text)) = (&self.extracted_data, reference.pages.get(self.page)) else {
+            return Vec::new();
+        };
+        transcript::divergent_items(&document::edited_page_items(data, self.page, &self.to_patch()), page_text)
+    }
+
+    /// Write the edited document reflowed onto fresh pages; returns the number of pages
+    pub fn export_reflowed_pdf(&self, path: &Path, layout: &PrintLayout) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let pdfium = self.pdfium.as_ref().ok_or_else(|| anyhow!("pdfium is not available"))?;
+        let pages = reflow::paginate(&reflow::blocks(data, &self.to_patch()), layout);
+        let count = reflow::write_pdf(pdfium, &pages, layout, path, &self.pdf_output)?;
+        self.stamp_export(path)?;
+        Ok(count)
+    }
+
+    /// Write a copy of the PDF with the edited text as an invisible layer on
+    /// its scanned pages; returns the number of pages that got text
+    pub fn export_searchable_pdf(&self, path: &Path) -> Result<usize> {
+        let data = self.extracted_data.as_ref().ok_or_else(|| anyhow!("Nothing extracted yet"))?;
+        let pdfium = self.pdfium.as_ref().ok_or_else(|| anyhow!("pdfium is not available"))?;
+        let pdf_bytes = self.pdf_bytes.as_ref().ok_or_else(|| anyhow!("No PDF open"))?;
+        let plan = searchable::plan(data, &self.to_patch(), &self.text_layer_chars());
+        let count = searchable::write_pdf(pdfium, pdf_bytes, self.pdf_password.as_deref(), &plan, path, &self.pdf_output)?;
+        self.stamp_export(path)?;
+        Ok(count)
+    }
+}
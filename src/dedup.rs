@@ -0,0 +1,132 @@
+//! Duplicate and near-duplicate detection for workspace documents
+//!
+//! Each document's extracted text is fingerprinted with a 64-bit simhash over
+//! word shingles. Documents whose fingerprints differ by only a few bits are
+//! near-duplicates; identical fingerprints are treated as exact duplicates.
+
+use serde_json::Value;
+
+/// Fingerprints within this many differing bits count as near-duplicates
+pub const NEAR_DUPLICATE_BITS: u32 = 3;
+
+const SHINGLE_SIZE: usize = 3;
+
+/// All item text of an extraction, in document order
+pub fn document_text(data: &Value) -> String {
+    data.get("items")
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter()
+            .filter_map(|item| item.get("content").or_else(|| item.get("text")).and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"))
+        .unwrap_or_default()
+}
+
+/// FNV-1a, stable across runs so fingerprints can be stored
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text.split_whitespace()
+        .map(|w| w.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return 0;
+    }
+
+    let mut weights = [0i64; 64];
+    let shingle_count = words.len().saturating_sub(SHINGLE_SIZE - 1).max(1);
+    for i in 0..shingle_count {
+        let end = (i + SHINGLE_SIZE).min(words.len());
+        let hash = fnv1a(words[i..end].join(" ").as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights.iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0u64, |acc, (bit, _)| acc | (1 << bit))
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Duplicate groups awaiting user review, with the documents chosen for exclusion
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateReview {
+    pub groups: Vec<DuplicateGroup>,
+    pub excluded: std::collections::BTreeSet<usize>,
+    /// Continue with the manifest export once reviewed
+    pub export_after: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Document indices as given alongside each fingerprint
+    pub members: Vec<usize>,
+    /// Largest pairwise distance in the group (0 = exact duplicates)
+    pub max_distance: u32,
+}
+
+impl DuplicateGroup {
+    pub fn is_exact(&self) -> bool {
+        self.max_distance == 0
+    }
+}
+
+/// Group fingerprints that are within `NEAR_DUPLICATE_BITS` of each other
+pub fn find_duplicates(fingerprints: &[(usize, u64)]) -> Vec<DuplicateGroup> {
+    // Union-find over every close pair
+    let mut parent: Vec<usize> = (0..fingerprints.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for a in 0..fingerprints.len() {
+        for b in (a + 1)..fingerprints.len() {
+            if hamming_distance(fingerprints[a].1, fingerprints[b].1) <= NEAR_DUPLICATE_BITS {
+                let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                parent[ra] = rb;
+            }
+        }
+    }
+
+    let mut groups: std::collections::BTreeMap<usize, Vec<usize>> = std::collections::BTreeMap::new();
+    for i in 0..fingerprints.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+
+    groups.into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let max_distance = members.iter()
+                .flat_map(|a| members.iter().map(move |b| (a, b)))
+                .map(|(a, b)| hamming_distance(fingerprints[*a].1, fingerprints[*b].1))
+                .max()
+                .unwrap_or(0);
+            DuplicateGroup {
+                members: members.iter().map(|i| fingerprints[*i].0).collect(),
+                max_distance,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,164 @@
+//! Links to a spot in a document
+//!
+//! `chonker3://open?doc=<sha256>&page=3&item=<id>` points at an item, and
+//! `&bbox=left,top,width,height` (PDF points) at a region; with neither it
+//! points at the page. The document is named by the SHA-256 of its PDF, so a
+//! link pasted into notes or a ticket keeps working after the file moves, as
+//! long as the file is in the workspace. Pages count from 1, as they're shown.
+//! Opening a link launches Chonker3 or hands it to the running instance (see
+//! `launch` and `instance`), which opens the document and selects the spot.
+
+use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::checksum::sha256_hex;
+use crate::types::{BoundingBox, DocumentItem};
+
+pub const SCHEME: &str = "chonker3://";
+
+/// What on the page a link points at
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    Page,
+    Item(String),
+    Region(BoundingBox),
+}
+
+impl LinkTarget {
+    /// The page for no items, the item for one, the region around them for more
+    pub fn for_items(items: &[&DocumentItem]) -> Self {
+        match items {
+            [] => LinkTarget::Page,
+            [item] => LinkTarget::Item(item.id.clone()),
+            _ => {
+                let left = items.iter().map(|i| i.bbox.left).fold(f64::INFINITY, f64::min);
+                let top = items.iter().map(|i| i.bbox.top).fold(f64::INFINITY, f64::min);
+                let right = items.iter().map(|i| i.bbox.left + i.bbox.width).fold(f64::NEG_INFINITY, f64::max);
+                let bottom = items.iter().map(|i| i.bbox.top + i.bbox.height).fold(f64::NEG_INFINITY, f64::max);
+                LinkTarget::Region(BoundingBox { left, top, width: right - left, height: bottom - top })
+            }
+        }
+    }
+
+    /// IDs of the page's items it points at, and the box to bring into view
+    pub fn resolve(&self, items: &[DocumentItem]) -> (Vec<String>, Option<BoundingBox>) {
+        match self {
+            LinkTarget::Page => (Vec::new(), None),
+            LinkTarget::Item(id) => (vec![id.clone()], items.iter().find(|i| &i.id == id).map(|i| i.bbox.clone())),
+            LinkTarget::Region(region) => {
+                let inside = items.iter()
+                    .filter(|i| {
+                        i.bbox.left < region.left + region.width && region.left < i.bbox.left + i.bbox.width
+                            && i.bbox.top < region.top + region.height && region.top < i.bbox.top + i.bbox.height
+                    })
+                    .map(|i| i.id.clone())
+                    .collect();
+                (inside, Some(region.clone()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepLink {
+    /// SHA-256 of the PDF, in hex
+    pub document: String,
+    /// Zero-based page
+    pub page: usize,
+    pub target: LinkTarget,
+}
+
+impl DeepLink {
+    pub fn new(pdf_bytes: &[u8], page: usize, target: LinkTarget) -> Self {
+        Self { document: document_hash(pdf_bytes), page, target }
+    }
+
+    pub fn to_uri(&self) -> String {
+        let mut uri = format!("{}open?doc={}&page={}", SCHEME, self.document, self.page + 1);
+        match &self.target {
+            LinkTarget::Page => {}
+            LinkTarget::Item(id) => uri.push_str(&format!("&item={}", encode(id))),
+            LinkTarget::Region(b) => uri.push_str(&format!("&bbox={},{},{},{}", round(b.left), round(b.top), round(b.width), round(b.height))),
+        }
+        uri
+    }
+
+    pub fn parse(uri: &str) -> Result<Self> {
+        let rest = uri.trim().strip_prefix(SCHEME).ok_or_else(|| anyhow!("Not a {} link: {}", SCHEME, uri))?;
+        let query = rest.strip_prefix("open").map(|r| r.trim_start_matches('/'))
+            .and_then(|r| r.strip_prefix('?'))
+            .ok_or_else(|| anyhow!("Unknown link: {}", uri))?;
+        let (mut document, mut page, mut target) = (None, None, LinkTarget::Page);
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = decode(value)?;
+            match key {
+                "doc" => {
+                    if value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        bail!("Malformed document hash: {}", value);
+                    }
+                    document = Some(value.to_ascii_lowercase());
+                }
+                "page" => {
+                    let number: usize = value.parse().with_context(|| format!("Malformed page: {}", value))?;
+                    page = Some(number.checked_sub(1).ok_or_else(|| anyhow!("Pages count from 1"))?);
+                }
+                "item" => target = LinkTarget::Item(value),
+                "bbox" => {
+                    let numbers = value.split(',').map(|n| n.trim().parse::<f64>()).collect::<std::result::Result<Vec<_>, _>>()
+                        .ok().filter(|n| n.len() == 4)
+                        .ok_or_else(|| anyhow!("Malformed region: {}", value))?;
+                    target = LinkTarget::Region(BoundingBox { left: numbers[0], top: numbers[1], width: numbers[2], height: numbers[3] });
+                }
+                // Newer versions may add parameters
+                _ => {}
+            }
+        }
+        Ok(Self {
+            document: document.ok_or_else(|| anyhow!("Link names no document"))?,
+            page: page.unwrap_or(0),
+            target,
+        })
+    }
+}
+
+/// The hash a link names a PDF by
+pub fn document_hash(pdf_bytes: &[u8]) -> String {
+    sha256_hex(pdf_bytes)
+}
+
+/// The first of `candidates` whose contents have `hash`; missing files are skipped
+pub fn find_document(hash: &str, candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    candidates.into_iter().find(|path| {
+        std::fs::read(path).map(|bytes| document_hash(&bytes) == hash).unwrap_or(false)
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Percent-encode everything but unreserved characters
+fn encode(text: &str) -> String {
+    text.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+fn decode(text: &str) -> Result<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3).ok_or_else(|| anyhow!("Malformed escape in {}", text))?;
+            decoded.push(u8::from_str_radix(hex, 16).with_context(|| format!("Malformed escape in {}", text))?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).context("Link isn't UTF-8")
+}
@@ -0,0 +1,372 @@
+//! Offscreen document construction
+//!
+//! Turns extraction JSON plus the user's edits into the `DocumentState` the
+//! canvas draws. Nothing here touches egui, so conversion, search and column
+//! handling can be exercised from tests and command-line tools.
+
+use serde_json::Value;
+
+use crate::patch::EditPatch;
+use crate::search;
+use crate::types::{self, BoundingBox, DocumentItem, DocumentState, DropCap, ItemType, ProvenanceStage, ProvenanceStep};
+
+/// Build the state for one (zero-based) page of an extraction
+pub fn document_state(data: &Value, page_index: usize, edits: &EditPatch, search_query: &str) -> DocumentState {
+    let items = page_items(data, page_index, edits);
+    let search_results = search_matches(&items, search_query);
+    let (column_count, column_boundaries) = page_columns(data, page_index);
+
+    DocumentState {
+        items,
+        search_query: search_query.to_string(),
+        search_results,
+        item_offsets: edits.offsets.clone().into_iter().collect(),
+        item_text_overrides: edits.text_overrides.clone().into_iter().collect(),
+        item_annotations: edits.annotations.clone().into_iter().collect(),
+        item_comments: edits.comments.clone().into_iter().collect(),
+        named_regions: edits.regions.iter()
+            .filter(|region| region.page == page_index)
+            .map(|region| (region.name.clone(), region.bbox.clone()))
+            .collect(),
+        column_count,
+        column_boundaries,
+        ..DocumentState::default()
+    }
+}
+
+/// Items on one page with deletions and type changes applied
+pub fn page_items(data: &Value, page_index: usize, edits: &EditPatch) -> Vec<DocumentItem> {
+    let mut items = Vec::new();
+
+    let Some(json_items) = data.get("items").and_then(|v| v.as_array()) else {
+        return items;
+    };
+
+    for json_item in json_items {
+        // Filter by page
+        let page = json_item.get("page").and_then(|v| v.as_u64()).unwrap_or(0);
+        if page != page_index as u64 + 1 {
+            continue;
+        }
+
+        let Some(bbox) = json_item.get("bbox") else { continue };
+        let (Some(left), Some(top), Some(width), Some(height)) = (
+            bbox.get("left").and_then(|v| v.as_f64()),
+            bbox.get("top").and_then(|v| v.as_f64()),
+            bbox.get("width").and_then(|v| v.as_f64()),
+            bbox.get("height").and_then(|v| v.as_f64()),
+        ) else {
+            continue;
+        };
+
+        // Check coordinate origin
+        let coord_origin = bbox.get("coord_origin")
+            .and_then(|v| v.as_str())
+            .unwrap_or("TOPLEFT");
+
+        // Convert coordinates if needed
+        let final_top = if coord_origin.contains("BOTTOMLEFT") {
+            // In BOTTOMLEFT, top is the upper edge measured from the page bottom
+            let page_height = data.get("pages")
+                .and_then(|pages| pages.as_array())
+                .and_then(|pages| pages.get(page_index))
+                .and_then(|page| page.get("height"))
+                .and_then(|h| h.as_f64())
+                .unwrap_or(792.0);
+            page_height - top
+        } else {
+            top
+        };
+
+        // Extract content
+        let content = json_item.get("content")
+            .or_else(|| json_item.get("text"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let style = json_item.get("attributes").and_then(|a| a.get("style"));
+        let font_name = style.and_then(|s| s.get("font")).and_then(|v| v.as_str()).unwrap_or("");
+        let mut provenance = item_provenance(json_item);
+        let item_type = match ItemType::from_json_type(json_item.get("type").and_then(|v| v.as_str()).unwrap_or("TextItem")) {
+            // Extractors without formula or code detection hand them over as plain text
+            ItemType::Text if looks_like_formula(&content) => {
+                provenance.push(ProvenanceStep::new(ProvenanceStage::Postprocess, "formula detection"));
+                ItemType::Formula
+            }
+            ItemType::Text if is_monospace_font(font_name) || looks_like_code(&content) => {
+                provenance.push(ProvenanceStep::new(ProvenanceStage::Postprocess, "code detection"));
+                ItemType::Code
+            }
+            item_type => item_type,
+        };
+
+        // Pictures and formulas may have no text; keep them so they can be seen and filtered
+        let content = match item_type {
+            ItemType::Picture if content.trim().is_empty() => "[Picture]".to_string(),
+            ItemType::Formula if content.trim().is_empty() => "[Formula]".to_string(),
+            _ => content,
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+
+        // Font size and style from attributes.style, else defaults by item type
+        let (font_size, bold, italic) = match style {
+            Some(style) => (
+                style.get("font_size").and_then(|v| v.as_f64()).unwrap_or(12.0) as f32,
+                style.get("bold").and_then(|v| v.as_bool()).unwrap_or(false),
+                style.get("italic").and_then(|v| v.as_bool()).unwrap_or(false),
+            ),
+            None => match item_type {
+                ItemType::Title => (16.0, true, false),
+                ItemType::Header => (14.0, true, false),
+                ItemType::FormLabel => (11.0, false, false),
+                ItemType::FormField => (10.0, false, false),
+                _ => (11.0, false, false),
+            },
+        };
+
+        let item_id = types::item_id(page_index, left, final_top);
+
+        // Apply user deletions and type changes
+        if edits.deletions.contains(&item_id) {
+            continue;
+        }
+        let item_type = edits.type_changes.get(&item_id).copied().unwrap_or(item_type);
+
+        // Recorded baselines follow the same origin as the box
+        let baseline = json_item.get("baseline").and_then(|v| v.as_f64()).map(|baseline| {
+            if coord_origin.contains("BOTTOMLEFT") { final_top + top - baseline } else { baseline }
+        });
+
+        let extracted = BoundingBox {
+            left,
+            top: final_top,
+            width,
+            height: height.abs(),
+        };
+        let bbox = edits.boxes.get(&item_id).cloned().unwrap_or_else(|| extracted.clone());
+        // A corrected box carries the baseline along with it
+        let baseline = baseline.map(|baseline| baseline + bbox.top - extracted.top);
+
+        let latex = if item_type == ItemType::Formula { formula_latex(json_item, &content) } else { None };
+
+        items.push(DocumentItem {
+            id: item_id,
+            bbox,
+            content,
+            font_size,
+            color: match item_type {
+                ItemType::Title | ItemType::Header => (0, 100, 200),
+                _ => (0, 0, 0),
+            },
+            item_type,
+            bold,
+            italic,
+            confidence: json_item.get("confidence").and_then(|v| v.as_f64()).map(|c| c as f32),
+            baseline,
+            latex,
+            drop_cap: None,
+            provenance,
+        });
+    }
+
+    merge_drop_caps(&mut items);
+    items
+}
+
+/// The provenance chain an extraction item records; malformed steps are skipped
+pub fn item_provenance(json_item: &Value) -> Vec<ProvenanceStep> {
+    json_item.get("provenance").and_then(|v| v.as_array()).into_iter().flatten()
+        .filter_map(|step| serde_json::from_value(step.clone()).ok())
+        .collect()
+}
+
+/// Position among the extraction's items of the one with this ID
+pub fn find_json_item(data: &Value, item_id: &str) -> Option<usize> {
+    let page_index = types::item_page(item_id)?;
+    let page_height = data.get("pages")
+        .and_then(|pages| pages.get(page_index))
+        .and_then(|page| page.get("height"))
+        .and_then(|h| h.as_f64())
+        .unwrap_or(792.0);
+    data.get("items")?.as_array()?.iter().position(|item| {
+        item.get("page").and_then(|v| v.as_u64()) == Some(page_index as u64 + 1)
+            && crate::lines::item_box(item, page_height)
+                .is_some_and(|bbox| types::item_id(page_index, bbox.left, bbox.top) == item_id)
+    })
+}
+
+/// Append a step to an extraction item's provenance chain
+pub fn record_provenance(json_item: &mut Value, step: ProvenanceStep) {
+    let Ok(step) = serde_json::to_value(step) else { return };
+    match json_item.get_mut("provenance").and_then(|v| v.as_array_mut()) {
+        Some(chain) => chain.push(step),
+        None => json_item["provenance"] = Value::Array(vec![step]),
+    }
+}
+
+/// Put a step at the start of every item's chain, for work done before extraction
+pub fn record_preprocessing(data: &mut Value, step: ProvenanceStep) {
+    let Ok(step) = serde_json::to_value(step) else { return };
+    for item in data.get_mut("items").and_then(|v| v.as_array_mut()).into_iter().flatten() {
+        match item.get_mut("provenance").and_then(|v| v.as_array_mut()) {
+            Some(chain) => chain.insert(0, step.clone()),
+            None => item["provenance"] = Value::Array(vec![step.clone()]),
+        }
+    }
+}
+
+/// A drop cap's box is at least this many of the paragraph's line heights tall
+const DROP_CAP_LINES: f64 = 1.8;
+
+/// Whether an item is a lone oversized glyph: one letter, or a short ornament
+fn drop_cap_glyph(item: &DocumentItem) -> Option<(String, bool)> {
+    let glyph = item.content.trim();
+    let decorative = !glyph.chars().any(char::is_alphanumeric);
+    let fits = if decorative { (1..=3).contains(&glyph.chars().count()) } else { glyph.chars().count() == 1 };
+    let allowed = !matches!(item.item_type, ItemType::Table | ItemType::Picture | ItemType::Formula | ItemType::Code | ItemType::Checkbox);
+    (fits && allowed).then(|| (glyph.to_string(), decorative))
+}
+
+/// The paragraph a drop cap starts: the nearest item just right of it whose first line
+/// is level with the glyph's top and set much smaller than the glyph
+fn drop_cap_paragraph(cap: &DocumentItem, items: &[DocumentItem]) -> Option<usize> {
+    let cap_right = cap.bbox.left + cap.bbox.width;
+    items.iter()
+        .enumerate()
+        .filter(|(_, item)| item.id != cap.id && drop_cap_glyph(item).is_none())
+        .filter(|(_, item)| {
+            let line_height = (item.font_size as f64 * 1.2).max(1.0);
+            let gap = item.bbox.left - cap_right;
+            cap.bbox.height >= line_height * DROP_CAP_LINES
+                && (-cap.bbox.width * 0.5..=line_height * 3.0).contains(&gap)
+                && (item.bbox.top - cap.bbox.top).abs() <= line_height
+        })
+        .min_by(|(_, a), (_, b)| a.bbox.left.total_cmp(&b.bbox.left))
+        .map(|(index, _)| index)
+}
+
+/// Fold drop caps and decorative initials into the paragraphs they start, so the
+/// text reads "Once upon" rather than "O" and "nce upon"
+pub fn merge_drop_caps(items: &mut Vec<DocumentItem>) {
+    let mut index = 0;
+    while index < items.len() {
+        let Some((glyph, decorative)) = drop_cap_glyph(&items[index]) else {
+            index += 1;
+            continue;
+        };
+        let Some(paragraph) = drop_cap_paragraph(&items[index], items) else {
+            index += 1;
+            continue;
+        };
+        let cap = items.remove(index);
+        let paragraph = &mut items[if paragraph > index { paragraph - 1 } else { paragraph }];
+        if !decorative {
+            paragraph.content = format!("{}{}", glyph, paragraph.content.trim_start());
+        }
+        paragraph.drop_cap = Some(DropCap { glyph, bbox: cap.bbox, decorative });
+        paragraph.provenance.push(ProvenanceStep::new(ProvenanceStage::Postprocess, "drop cap merge"));
+    }
+}
+
+/// Characters that are rare in prose but common in typeset math
+const MATH_SYMBOLS: &str = "=+−×÷±≤≥≠≈≡∞∑∏∫∮√∂∇∈∉⊂⊆∪∩∀∃→←↔⇒⇔^_αβγδεζηθικλμνξπρστυφχψωΓΔΘΛΞΠΣΦΨΩ";
+
+/// Whether text reads like a formula rather than prose: few words, many math symbols
+pub fn looks_like_formula(text: &str) -> bool {
+    let text = text.trim();
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    if !(3..=300).contains(&chars) {
+        return false;
+    }
+    let words = text.split_whitespace()
+        .filter(|word| word.chars().filter(|c| c.is_alphabetic()).count() >= 4)
+        .count();
+    let symbols = text.chars().filter(|c| MATH_SYMBOLS.contains(*c)).count();
+    let relation = text.contains(['=', '≤', '≥', '≠', '≈', '∑', '∏', '∫', '√']);
+    relation && words <= 1 && symbols * 6 >= chars
+}
+
+/// Whether text is LaTeX source: backslash commands or braced sub/superscripts
+pub fn looks_like_latex(text: &str) -> bool {
+    text.contains("^{") || text.contains("_{")
+        || text.split('\\').skip(1).any(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// LaTeX for a formula item: a `latex` field (at the top or in attributes), else
+/// content that is already LaTeX, as formula enrichment produces
+pub fn formula_latex(json_item: &Value, content: &str) -> Option<String> {
+    json_item.get("latex")
+        .or_else(|| json_item.get("attributes").and_then(|a| a.get("latex")))
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .or_else(|| looks_like_latex(content).then(|| content.trim().to_string()))
+        .filter(|latex| !latex.trim().is_empty())
+}
+
+/// Font names of common monospace faces
+const MONOSPACE_FONTS: [&str; 8] = ["mono", "courier", "consolas", "menlo", "monaco", "inconsolata", "code", "typewriter"];
+
+pub fn is_monospace_font(name: &str) -> bool {
+    let name = name.to_lowercase();
+    MONOSPACE_FONTS.iter().any(|font| name.contains(font))
+}
+
+/// Whether text reads like source code: several lines, consistently indented
+/// blocks or most lines ending like statements
+pub fn looks_like_code(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < 3 {
+        return false;
+    }
+    let statement_like = lines.iter()
+        .filter(|line| {
+            let line = line.trim_end();
+            line.ends_with([';', '{', '}', ')', ':']) || line.trim_start().starts_with(['#', '}', '/'])
+        })
+        .count();
+    let indented = lines.iter().skip(1).filter(|line| line.starts_with("  ") || line.starts_with('\t')).count();
+    let symbols = text.chars().filter(|c| "{}();=<>[]".contains(*c)).count();
+    statement_like * 2 >= lines.len() && (indented > 0 || symbols >= lines.len())
+}
+
+/// A page's items as the canvas shows them: edited text, moved boxes
+pub fn edited_page_items(data: &Value, page_index: usize, edits: &EditPatch) -> Vec<DocumentItem> {
+    let mut items = page_items(data, page_index, edits);
+    for item in &mut items {
+        if let Some(text) = edits.text_overrides.get(&item.id) {
+            item.content = text.clone();
+        }
+        if let Some((dx, dy)) = edits.offsets.get(&item.id) {
+            item.bbox.left += *dx as f64;
+            item.bbox.top += *dy as f64;
+        }
+    }
+    items
+}
+
+/// IDs of items matching the query, typos included (see `search`)
+pub fn search_matches(items: &[DocumentItem], query: &str) -> Vec<String> {
+    search::page_matches(items, &search::Matcher::Words(query.to_string())).into_iter()
+        .map(|(item, _)| item.id.clone())
+        .collect()
+}
+
+/// Column count and boundaries detected for a page (one column if unknown)
+pub fn page_columns(data: &Value, page_index: usize) -> (usize, Vec<f32>) {
+    let Some(page) = data.get("pages")
+        .and_then(|v| v.as_array())
+        .and_then(|pages| pages.get(page_index))
+    else {
+        return (1, Vec::new());
+    };
+
+    let count = page.get("columns").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let boundaries = page.get("column_boundaries")
+        .and_then(|v| v.as_array())
+        .map(|bounds| bounds.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect())
+        .unwrap_or_default();
+    (count, boundaries)
+}
@@ -0,0 +1,200 @@
+//! ZUGFeRD / Factur-X e-invoice support
+//!
+//! Hybrid e-invoices embed a UN/CEFACT Cross Industry Invoice (CII) XML file in
+//! the PDF. We read the key fields from it and check that each one actually
+//! appears in the extracted visual text, flagging fields that don't.
+
+use anyhow::{anyhow, Context, Result};
+use pdfium_render::prelude::*;
+use serde_json::Value;
+
+use crate::normalize::{self, NumberLocale};
+
+/// Attachment names used by ZUGFeRD 1/2, Factur-X and XRechnung
+const KNOWN_NAMES: [&str; 4] = ["factur-x.xml", "zugferd-invoice.xml", "xrechnung.xml", "order-x.xml"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Text,
+    Amount,
+    Date,
+    Iban,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvoiceField {
+    pub label: &'static str,
+    pub kind: FieldKind,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct EInvoice {
+    pub attachment_name: String,
+    pub currency: Option<String>,
+    pub fields: Vec<InvoiceField>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckStatus {
+    /// Found on the given (1-based) page
+    Matched(usize),
+    NotFound,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldCheck {
+    pub label: &'static str,
+    pub value: String,
+    pub status: CheckStatus,
+}
+
+/// Look for an embedded CII invoice among the PDF's attachments
+pub fn find_embedded_invoice(document: &PdfDocument) -> Option<EInvoice> {
+    for attachment in document.attachments().iter() {
+        let name = attachment.name();
+        let lower = name.to_lowercase();
+        if !lower.ends_with(".xml") {
+            continue;
+        }
+        let Ok(bytes) = attachment.save_to_bytes() else { continue };
+        let xml = String::from_utf8_lossy(&bytes);
+        if KNOWN_NAMES.contains(&lower.as_str()) || xml.contains("CrossIndustryInvoice") || xml.contains("CrossIndustryDocument") {
+            match parse_invoice(&xml, &name) {
+                Ok(invoice) => return Some(invoice),
+                Err(e) => log::warn!("Failed to parse embedded invoice {}: {}", name, e),
+            }
+        }
+    }
+    None
+}
+
+/// First descendant with the given local name (CII namespaces vary by version)
+fn find_text<'a>(node: roxmltree::Node<'a, 'a>, path: &[&str]) -> Option<String> {
+    let mut current = node;
+    for name in path {
+        current = current.descendants().find(|n| n.tag_name().name() == *name)?;
+    }
+    current.text().map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+pub fn parse_invoice(xml: &str, attachment_name: &str) -> Result<EInvoice> {
+    let doc = roxmltree::Document::parse(xml).context("Invalid invoice XML")?;
+    let root = doc.root_element();
+    if !matches!(root.tag_name().name(), "CrossIndustryInvoice" | "CrossIndustryDocument") {
+        return Err(anyhow!("Not a Cross Industry Invoice"));
+    }
+
+    let mut fields = Vec::new();
+    let mut push = |label, kind, value: Option<String>| {
+        if let Some(value) = value {
+            fields.push(InvoiceField { label, kind, value });
+        }
+    };
+
+    push("Invoice number", FieldKind::Text, find_text(root, &["ExchangedDocument", "ID"])
+        .or_else(|| find_text(root, &["HeaderExchangedDocument", "ID"])));
+    push("Issue date", FieldKind::Date, find_text(root, &["IssueDateTime", "DateTimeString"]));
+    push("Due date", FieldKind::Date, find_text(root, &["SpecifiedTradePaymentTerms", "DueDateDateTime", "DateTimeString"]));
+    push("Seller", FieldKind::Text, find_text(root, &["SellerTradeParty", "Name"]));
+    push("Buyer", FieldKind::Text, find_text(root, &["BuyerTradeParty", "Name"]));
+    push("IBAN", FieldKind::Iban, find_text(root, &["PayeePartyCreditorFinancialAccount", "IBANID"]));
+    push("Net total", FieldKind::Amount, find_text(root, &["SpecifiedTradeSettlementHeaderMonetarySummation", "TaxBasisTotalAmount"]));
+    push("Tax total", FieldKind::Amount, find_text(root, &["SpecifiedTradeSettlementHeaderMonetarySummation", "TaxTotalAmount"]));
+    push("Grand total", FieldKind::Amount, find_text(root, &["SpecifiedTradeSettlementHeaderMonetarySummation", "GrandTotalAmount"]));
+    push("Amount due", FieldKind::Amount, find_text(root, &["SpecifiedTradeSettlementHeaderMonetarySummation", "DuePayableAmount"]));
+
+    Ok(EInvoice {
+        attachment_name: attachment_name.to_string(),
+        currency: find_text(root, &["InvoiceCurrencyCode"]),
+        fields,
+    })
+}
+
+/// Check every invoice field against the extracted text of all pages
+pub fn cross_check(invoice: &EInvoice, extraction: &Value) -> Vec<FieldCheck> {
+    // Text per page, in page order
+    let mut pages: std::collections::BTreeMap<usize, String> = std::collections::BTreeMap::new();
+    if let Some(items) = extraction.get("items").and_then(|v| v.as_array()) {
+        for item in items {
+            let page = item.get("page").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            let content = item.get("content").or_else(|| item.get("text")).and_then(|v| v.as_str()).unwrap_or("");
+            let text = pages.entry(page).or_default();
+            text.push_str(content);
+            text.push('\n');
+        }
+    }
+
+    invoice.fields.iter()
+        .map(|field| {
+            let status = pages.iter()
+                .find(|(_, text)| field_in_text(field, text))
+                .map(|(page, _)| CheckStatus::Matched(*page))
+                .unwrap_or(CheckStatus::NotFound);
+            FieldCheck { label: field.label, value: display_value(field), status }
+        })
+        .collect()
+}
+
+fn field_in_text(field: &InvoiceField, text: &str) -> bool {
+    match field.kind {
+        FieldKind::Text => text.to_lowercase().contains(&field.value.to_lowercase()),
+        FieldKind::Iban => {
+            let compact = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+            compact(text).contains(&compact(&field.value))
+        }
+        FieldKind::Amount => {
+            let Ok(expected) = field.value.parse::<f64>() else { return false };
+            tokens(text)
+                .filter_map(|t| normalize::parse_number(t, NumberLocale::Auto))
+                .any(|n| (n.value - expected).abs() < 0.005)
+        }
+        FieldKind::Date => {
+            let Some(expected) = parse_cii_date(&field.value) else { return false };
+            tokens(text).filter_map(parse_date).any(|d| d == expected)
+        }
+    }
+}
+
+fn display_value(field: &InvoiceField) -> String {
+    match field.kind {
+        FieldKind::Date => parse_cii_date(&field.value)
+            .map(|(y, m, d)| format!("{:04}-{:02}-{:02}", y, m, d))
+            .unwrap_or_else(|| field.value.clone()),
+        _ => field.value.clone(),
+    }
+}
+
+/// Whitespace-separated tokens with surrounding punctuation and currency symbols removed
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .map(|t| t.trim_matches(|c: char| !c.is_ascii_digit()))
+        .filter(|t| !t.is_empty())
+}
+
+/// CII dates use format 102 (YYYYMMDD)
+fn parse_cii_date(value: &str) -> Option<(u32, u32, u32)> {
+    if value.len() != 8 {
+        return None;
+    }
+    Some((value[0..4].parse().ok()?, value[4..6].parse().ok()?, value[6..8].parse().ok()?))
+}
+
+/// Parse common visual date notations into (year, month, day)
+fn parse_date(token: &str) -> Option<(u32, u32, u32)> {
+    let parts: Vec<u32> = token.split(['.', '/', '-'])
+        .map(|p| p.parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.len() != 3 {
+        return None;
+    }
+    let (a, b, c) = (parts[0], parts[1], parts[2]);
+    let year = |y: u32| if y < 100 { 2000 + y } else { y };
+    if a > 999 {
+        Some((a, b, c)) // ISO yyyy-mm-dd
+    } else if token.contains('/') && a <= 12 && b > 12 {
+        Some((year(c), a, b)) // US mm/dd/yyyy when unambiguous
+    } else {
+        Some((year(c), b, a)) // dd.mm.yyyy / dd/mm/yyyy
+    }
+}
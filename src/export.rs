@@ -0,0 +1,461 @@
+//! Structured export of an extraction with the user's edits applied
+//!
+//! Unlike the raw extraction JSON, the structured export has one flat record per
+//! surviving item with its stable ID, final text and type, TOPLEFT bbox and the
+//! canonical numeric value from `normalize`. The same records can be written
+//! as JSONL, one item per line with its box relative to the page, the usual
+//! input for training and evaluating document-AI models.
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::normalize::{self, NumberLocale};
+use crate::patch::EditPatch;
+use crate::types::{self, ItemType};
+
+pub const STRUCTURED_FORMAT: &str = "chonker3-structured";
+pub const STRUCTURED_VERSION: u32 = 1;
+
+/// Build the structured export. Edited text is normalized again with the document's locale.
+pub fn structured_export(data: &Value, edits: &EditPatch, locale: NumberLocale) -> Value {
+    let mut data = data.clone();
+    let locale = normalize::normalize_extraction(&mut data, locale);
+
+    let pages = data.get("pages").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    let page_height = |page_index: usize| pages.get(page_index)
+        .and_then(|p| p.get("height"))
+        .and_then(|v| v.as_f64())
+        .unwrap_or(792.0);
+
+    let mut items = Vec::new();
+    for item in data.get("items").and_then(|v| v.as_array()).into_iter().flatten() {
+        let Some(page) = item.get("page").and_then(|v| v.as_u64()) else { continue };
+        let page_index = page.saturating_sub(1) as usize;
+        let Some(bbox) = item.get("bbox") else { continue };
+        let get = |key: &str| bbox.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let left = get("left");
+        let top = if bbox.get("coord_origin").and_then(|v| v.as_str()).unwrap_or("TOPLEFT").contains("BOTTOMLEFT") {
+            page_height(page_index) - get("top")
+        } else {
+            get("top")
+        };
+
+        let id = types::item_id(page_index, left, top);
+        if edits.deletions.contains(&id) {
+            continue;
+        }
+
+        let original = item.get("content").or_else(|| item.get("text")).and_then(|v| v.as_str()).unwrap_or("");
+        let text = edits.text_overrides.get(&id).map(String::as_str).unwrap_or(original);
+        if text.trim().is_empty() {
+            continue;
+        }
+        let item_type = edits.type_changes.get(&id).copied()
+            .unwrap_or_else(|| ItemType::from_json_type(item.get("type").and_then(|v| v.as_str()).unwrap_or("")));
+        let (dx, dy) = edits.offsets.get(&id).copied().unwrap_or((0.0, 0.0));
+        let (left, top, width, height) = match edits.boxes.get(&id) {
+            Some(b) => (b.left, b.top, b.width, b.height),
+            None => (left, top, get("width"), get("height").abs()),
+        };
+
+        let mut record = json!({
+            "id": id,
+            "page": page,
+            "type": item_type.json_type(),
+            "text": text,
+            "bbox": {
+                "left": left + dx as f64,
+                "top": top + dy as f64,
+                "width": width,
+                "height": height,
+            },
+        });
+        if let Some(number) = normalize::parse_number(text, locale) {
+            record["normalized"] = number.to_json();
+        }
+        if let Some(note) = edits.annotations.get(&id) {
+            record["annotation"] = json!(note);
+        }
+        if item_type == ItemType::Formula {
+            if let Some(latex) = crate::document::formula_latex(item, text) {
+                record["latex"] = json!(latex);
+            }
+        }
+        if let (ItemType::Barcode, Some(symbology)) = (item_type, item.get("symbology")) {
+            record["symbology"] = symbology.clone();
+        }
+        if let Some(provenance) = item.get("provenance") {
+            record["provenance"] = provenance.clone();
+        }
+        if let Some(confidence) = item.get("confidence").filter(|c| c.is_number()) {
+            record["confidence"] = confidence.clone();
+        }
+        items.push(record);
+    }
+
+    let bates = crate::bates::bates_numbers(&data, edits);
+    let mut export = json!({
+        "format": STRUCTURED_FORMAT,
+        "version": STRUCTURED_VERSION,
+        "source_file": edits.source_file,
+        "generated": chrono::Local::now().to_rfc3339(),
+        "number_locale": locale,
+        "pages": pages.iter().enumerate().map(|(index, p)| {
+            let mut page = json!({
+                "page_number": p.get("page_number"),
+                "width": p.get("width"),
+                "height": p.get("height"),
+            });
+            if let Some(bates) = bates.get(&index) {
+                page["bates"] = json!(bates);
+            }
+            page
+        }).collect::<Vec<_>>(),
+        "items": items,
+    });
+    // Page-level marks use one-based page numbers like the items
+    if !edits.bookmarks.is_empty() {
+        export["bookmarks"] = json!(edits.bookmarks.iter().map(|page| page + 1).collect::<Vec<_>>());
+    }
+    if !edits.page_notes.is_empty() {
+        export["page_notes"] = json!(edits.page_notes.iter()
+            .map(|(page, note)| json!({ "page": page + 1, "note": note }))
+            .collect::<Vec<_>>());
+    }
+    export
+}
+
+pub fn write_structured(path: &Path, data: &Value, edits: &EditPatch, locale: NumberLocale) -> Result<usize> {
+    let export = structured_export(data, edits, locale);
+    let count = export["items"].as_array().map(|a| a.len()).unwrap_or(0);
+    std::fs::write(path, serde_json::to_string_pretty(&export)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(count)
+}
+
+/// One line per item of the structured export: its text, type, one-based
+/// page, confidence (null when unknown) and bbox as fractions of the page size
+pub fn items_jsonl(export: &Value) -> String {
+    let pages = export["pages"].as_array().cloned().unwrap_or_default();
+    let mut out = String::new();
+    for item in export["items"].as_array().into_iter().flatten() {
+        let page = item["page"].as_u64().unwrap_or(1);
+        // Pages are numbered by position in a structured export, except when cut down to one page
+        let size = pages.iter()
+            .find(|p| p["page_number"].as_u64() == Some(page))
+            .or_else(|| pages.get(page.saturating_sub(1) as usize))
+            .or_else(|| pages.first().filter(|_| pages.len() == 1));
+        let width = size.and_then(|p| p["width"].as_f64()).filter(|w| *w > 0.0).unwrap_or(612.0);
+        let height = size.and_then(|p| p["height"].as_f64()).filter(|h| *h > 0.0).unwrap_or(792.0);
+        let bbox = |key: &str| item["bbox"][key].as_f64().unwrap_or(0.0);
+        let line = json!({
+            "source_file": export["source_file"],
+            "id": item["id"],
+            "page": page,
+            "type": item["type"],
+            "text": item["text"],
+            "bbox": {
+                "left": bbox("left") / width,
+                "top": bbox("top") / height,
+                "width": bbox("width") / width,
+                "height": bbox("height") / height,
+            },
+            "confidence": item["confidence"],
+        });
+        out.push_str(&line.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// Write the items as JSONL; returns how many were written
+pub fn write_jsonl(path: &Path, data: &Value, edits: &EditPatch, locale: NumberLocale) -> Result<usize> {
+    let export = structured_export(data, edits, locale);
+    let count = export["items"].as_array().map(|a| a.len()).unwrap_or(0);
+    std::fs::write(path, items_jsonl(&export))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(count)
+}
+
+/// Render the extraction as Markdown, page by page, with edits applied
+pub fn markdown_export(data: &Value, edits: &EditPatch) -> String {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    let mut out = String::new();
+
+    for page_index in 0..page_count {
+        if page_index > 0 {
+            out.push_str("---\n\n");
+        }
+        out.push_str(&markdown_page(data, page_index, edits));
+    }
+    out
+}
+
+/// One page of the Markdown export, starting with its page marker
+pub fn markdown_page(data: &Value, page_index: usize, edits: &EditPatch) -> String {
+    let mut out = format!("<!-- Page {} -->\n\n", page_index + 1);
+    if edits.bookmarks.contains(&page_index) {
+        out.push_str("> 🔖 Bookmarked\n\n");
+    }
+    if let Some(note) = edits.page_notes.get(&page_index) {
+        for line in note.trim().lines() {
+            out.push_str(&format!("> {}\n", line));
+        }
+        out.push('\n');
+    }
+
+    for item in crate::document::page_items(data, page_index, edits) {
+        let text = edits.text_overrides.get(&item.id).unwrap_or(&item.content).trim();
+        if text.is_empty() {
+            continue;
+        }
+        match item.item_type {
+            ItemType::Title => out.push_str(&format!("# {}\n\n", text)),
+            ItemType::Header => out.push_str(&format!("## {}\n\n", text)),
+            ItemType::Table => out.push_str(&markdown_table(text)),
+            ItemType::FormLabel => out.push_str(&format!("**{}**\n\n", text)),
+            ItemType::Checkbox => out.push_str(&format!("- [ ] {}\n\n", text)),
+            ItemType::Text | ItemType::FormField | ItemType::Reference => out.push_str(&format!("{}\n\n", text)),
+            ItemType::Picture => out.push_str(&format!("*{}*\n\n", text)),
+            ItemType::Code => out.push_str(&format!("{}\n\n", code_fence(edits.text_overrides.get(&item.id).unwrap_or(&item.content)))),
+            ItemType::Formula => out.push_str(&format!("{}\n\n", formula_markdown(formula_source(item.latex.as_deref(), text)))),
+            ItemType::Barcode => out.push_str(&format!("{}\n\n", barcode_markdown(text))),
+        }
+    }
+    out
+}
+
+/// A fenced code block, fenced with more backticks than the code contains.
+/// Whitespace is kept as extracted.
+pub fn code_fence(code: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in code.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}\n{}\n{}", fence, code.trim_end_matches('\n'), fence)
+}
+
+/// A barcode's payload as inline code, fenced with more backticks than it contains
+pub fn barcode_markdown(payload: &str) -> String {
+    let fence = "`".repeat(payload.split(|c| c != '`').map(str::len).max().unwrap_or(0) + 1);
+    let padding = if payload.starts_with('`') || payload.ends_with('`') { " " } else { "" };
+    format!("**Barcode:** {}{}{}{}{}", fence, padding, payload, padding, fence)
+}
+
+/// A formula's LaTeX: the extractor's, else text the user entered as LaTeX
+pub fn formula_source<'a>(latex: Option<&'a str>, text: &'a str) -> Option<&'a str> {
+    latex.or_else(|| crate::document::looks_like_latex(text).then_some(text))
+}
+
+/// A display-math block, or a placeholder when the formula's LaTeX is unknown
+pub fn formula_markdown(latex: Option<&str>) -> String {
+    match latex {
+        Some(latex) => format!("$$\n{}\n$$", latex.trim()),
+        None => "*[Formula]*".to_string(),
+    }
+}
+
+/// Tab-separated table text as a Markdown table; anything else stays a paragraph
+pub fn markdown_table(text: &str) -> String {
+    let rows: Vec<Vec<&str>> = text.lines().map(|line| line.split('\t').map(str::trim).collect()).collect();
+    let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    if columns < 2 {
+        return format!("{}\n\n", text);
+    }
+
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = (0..columns)
+            .map(|c| row.get(c).map(|cell| cell.replace('|', "\\|")).unwrap_or_default())
+            .collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if i == 0 {
+            out.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+pub fn write_markdown(path: &Path, data: &Value, edits: &EditPatch) -> Result<()> {
+    std::fs::write(path, markdown_export(data, edits))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Escape text for HTML element content and attribute values
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render the extraction as a standalone HTML page, one section per page, with edits applied
+pub fn html_export(data: &Value, edits: &EditPatch) -> String {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    let title = edits.source_file.as_deref().unwrap_or("Document");
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n",
+        html_escape(title),
+    );
+
+    for page_index in 0..page_count {
+        out.push_str(&format!("<section class=\"page\" id=\"page-{}\">\n", page_index + 1));
+        if edits.bookmarks.contains(&page_index) {
+            out.push_str("<aside class=\"bookmark\">Bookmarked</aside>\n");
+        }
+        if let Some(note) = edits.page_notes.get(&page_index) {
+            out.push_str(&format!("<aside class=\"note\">{}</aside>\n", html_escape(note.trim())));
+        }
+
+        for item in crate::document::page_items(data, page_index, edits) {
+            let raw = edits.text_overrides.get(&item.id).unwrap_or(&item.content);
+            let text = raw.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let escaped = html_escape(text);
+            match item.item_type {
+                ItemType::Title => out.push_str(&format!("<h1>{}</h1>\n", escaped)),
+                ItemType::Header => out.push_str(&format!("<h2>{}</h2>\n", escaped)),
+                ItemType::Table => out.push_str(&html_table(text)),
+                ItemType::FormLabel => out.push_str(&format!("<p><strong>{}</strong></p>\n", escaped)),
+                ItemType::Checkbox => out.push_str(&format!("<p><label><input type=\"checkbox\" disabled> {}</label></p>\n", escaped)),
+                ItemType::Text | ItemType::FormField | ItemType::Reference => out.push_str(&format!("<p>{}</p>\n", escaped)),
+                ItemType::Picture => out.push_str(&format!("<figure><figcaption>{}</figcaption></figure>\n", escaped)),
+                ItemType::Code => out.push_str(&format!("<pre><code>{}</code></pre>\n", html_escape(raw.trim_end_matches('\n')))),
+                ItemType::Formula => match formula_source(item.latex.as_deref(), text) {
+                    Some(latex) => out.push_str(&format!("<div class=\"formula\">\\[{}\\]</div>\n", html_escape(latex.trim()))),
+                    None => out.push_str("<p><em>[Formula]</em></p>\n"),
+                },
+                ItemType::Barcode => out.push_str(&format!("<p class=\"barcode\"><strong>Barcode:</strong> <code>{}</code></p>\n", escaped)),
+            }
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Tab-separated table text as an HTML table, first row as the header
+fn html_table(text: &str) -> String {
+    if !text.contains('\t') {
+        return format!("<p>{}</p>\n", html_escape(text));
+    }
+    let mut out = String::from("<table>\n");
+    for (i, line) in text.lines().enumerate() {
+        let tag = if i == 0 { "th" } else { "td" };
+        let cells: String = line.split('\t')
+            .map(|cell| format!("<{}>{}</{}>", tag, html_escape(cell.trim()), tag))
+            .collect();
+        out.push_str(&format!("<tr>{}</tr>\n", cells));
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+/// Every edit in the patch, page by page, against the extracted item it changed
+pub fn audit_report(data: &Value, edits: &EditPatch) -> String {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    let original = EditPatch::default();
+    let mut out = String::from("# Edit audit\n\n");
+    if let Some(source) = &edits.source_file {
+        out.push_str(&format!("Source: {}\n\n", source));
+    }
+    out.push_str(&format!("{} edits\n\n", edits.edit_count()));
+
+    for page_index in 0..page_count {
+        let mut lines = Vec::new();
+        if edits.bookmarks.contains(&page_index) {
+            lines.push("Page bookmarked".to_string());
+        }
+        if let Some(note) = edits.page_notes.get(&page_index) {
+            lines.push(format!("Page note: {}", note.trim()));
+        }
+        for item in crate::document::page_items(data, page_index, &original) {
+            let quoted = |text: &str| format!("\"{}\"", text.trim().replace('\n', " "));
+            if edits.deletions.contains(&item.id) {
+                lines.push(format!("`{}` deleted: {}", item.id, quoted(&item.content)));
+                continue;
+            }
+            if let Some(text) = edits.text_overrides.get(&item.id) {
+                lines.push(format!("`{}` text: {} → {}", item.id, quoted(&item.content), quoted(text)));
+            }
+            if let Some(item_type) = edits.type_changes.get(&item.id).filter(|t| **t != item.item_type) {
+                lines.push(format!("`{}` type: {} → {}", item.id, item.item_type.label(), item_type.label()));
+            }
+            if let Some((dx, dy)) = edits.offsets.get(&item.id) {
+                lines.push(format!("`{}` moved by ({:.1}, {:.1}) pt", item.id, dx, dy));
+            }
+            if let Some(b) = edits.boxes.get(&item.id) {
+                lines.push(format!("`{}` box: ({:.1}, {:.1}, {:.1} × {:.1}) pt", item.id, b.left, b.top, b.width, b.height));
+            }
+            if let Some(note) = edits.annotations.get(&item.id) {
+                lines.push(format!("`{}` annotated: {}", item.id, quoted(note)));
+            }
+            if let Some(thread) = edits.comments.get(&item.id) {
+                for comment in &thread.comments {
+                    let time = crate::comments::short_time(&comment.timestamp);
+                    lines.push(format!("`{}` comment by {} ({}): {}", item.id, comment.author, time, quoted(&comment.text)));
+                }
+                if thread.resolved {
+                    lines.push(format!("`{}` comments resolved", item.id));
+                }
+            }
+        }
+        if !lines.is_empty() {
+            out.push_str(&format!("## Page {}\n\n", page_index + 1));
+            for line in lines {
+                out.push_str(&format!("- {}\n", line));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One CSV row per item of the structured export
+pub fn csv_export(data: &Value, edits: &EditPatch, locale: NumberLocale) -> String {
+    structured_csv(&structured_export(data, edits, locale))
+}
+
+/// CSV rows for the items of an already built structured export
+pub fn structured_csv(export: &Value) -> String {
+    let mut out = String::from("id,page,type,text,left,top,width,height,value,currency\n");
+    for item in export["items"].as_array().into_iter().flatten() {
+        let number = |key: &str| item["bbox"][key].as_f64().map(|v| format!("{:.2}", v)).unwrap_or_default();
+        let row = [
+            item["id"].as_str().unwrap_or("").to_string(),
+            item["page"].to_string(),
+            item["type"].as_str().unwrap_or("").to_string(),
+            item["text"].as_str().unwrap_or("").to_string(),
+            number("left"),
+            number("top"),
+            number("width"),
+            number("height"),
+            item["normalized"]["value"].as_f64().map(|v| v.to_string()).unwrap_or_default(),
+            item["normalized"]["currency"].as_str().unwrap_or("").to_string(),
+        ];
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_csv(path: &Path, data: &Value, edits: &EditPatch, locale: NumberLocale) -> Result<()> {
+    std::fs::write(path, csv_export(data, edits, locale))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,141 @@
+//! Extraction results cached by file hash
+//!
+//! Extracting a PDF that was extracted before, in this session or an earlier
+//! one, loads the earlier result instead of running the extractor again.
+//! Results are kept as `<sha256>.json` in `~/.cache/chonker3`, or the cache
+//! directory when one is configured (see `storage`), named for the SHA-256 of
+//! the PDF's bytes, so a moved or renamed copy still hits and an edited one
+//! doesn't. The extractor and options a result came from are
+//! recorded in its metadata; one from other settings is a miss, and the new
+//! extraction replaces it. A hit is copied to the temp directory before the
+//! app uses it, so edits written back to the extraction JSON (barcodes,
+//! re-OCR'd items, lines) don't change the cached result.
+
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::extractor::{ExtractOptions, ExtractedDocument, ExtractorKind};
+use crate::checksum::sha256_hex;
+use crate::storage::{self, ResolvedDir, StorageKind};
+
+/// Metadata key of the fingerprint of the extractor and options
+pub const CACHE_KEY: &str = "cache_key";
+
+/// `~/.cache/chonker3`, or the platform's equivalent
+pub fn default_dir() -> PathBuf {
+    dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("chonker3")
+}
+
+/// The cache directory, if the settings or `CHONKER3_CACHE_DIR` moved it
+/// from its default, else `default_dir`
+pub fn dir_for(cache: &ResolvedDir) -> PathBuf {
+    if cache.path == StorageKind::Cache.default_dir() {
+        default_dir()
+    } else {
+        cache.path.clone()
+    }
+}
+
+/// Fingerprint of what shapes an extraction besides the PDF: the extractor
+/// and its options, including per-page backends
+pub fn cache_key(kind: ExtractorKind, options: &ExtractOptions) -> String {
+    let settings = json!({ "extractor": kind, "options": options, "page_backends": options.page_backends });
+    sha256_hex(settings.to_string().as_bytes())
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractionCache {
+    dir: PathBuf,
+}
+
+impl Default for ExtractionCache {
+    fn default() -> Self {
+        Self::new(dir_for(&storage::resolved(StorageKind::Cache)))
+    }
+}
+
+impl ExtractionCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the result for a PDF with this SHA-256 is kept
+    pub fn path(&self, pdf_sha256: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", pdf_sha256))
+    }
+
+    /// Where a hit is copied for the app to work on
+    pub fn working_copy_path(pdf_sha256: &str) -> PathBuf {
+        storage::temp_dir().join(format!("chonker3_cached_{}.json", pdf_sha256))
+    }
+
+    /// The cached extraction of a PDF, if there is one from the same extractor
+    /// and options, as a working copy (see `working_copy_path`)
+    pub fn get(&self, pdf_sha256: &str, key: &str) -> Option<ExtractedDocument> {
+        let path = self.path(pdf_sha256);
+        if !path.exists() {
+            return None;
+        }
+        let document = ExtractedDocument::load(&path)
+            .map_err(|e| log::warn!("Ignoring unreadable cached extraction: {:#}", e))
+            .ok()?;
+        if document.data["metadata"][CACHE_KEY].as_str() != Some(key) {
+            return None;
+        }
+        let json_path = Self::working_copy_path(pdf_sha256);
+        std::fs::copy(&path, &json_path)
+            .map_err(|e| log::warn!("Ignoring cached extraction that couldn't be copied to {}: {}", json_path.display(), e))
+            .ok()?;
+        Some(ExtractedDocument { json_path, data: document.data })
+    }
+
+    /// Keep an extraction of a PDF, replacing any earlier one; returns the cached copy
+    pub fn put(&self, pdf_sha256: &str, key: &str, document: &ExtractedDocument) -> Result<ExtractedDocument> {
+        let mut data = document.data.clone();
+        if let Some(data) = data.as_object_mut() {
+            let metadata = data.entry("metadata").or_insert_with(|| json!({}));
+            if let Some(metadata) = metadata.as_object_mut() {
+                metadata.insert(CACHE_KEY.to_string(), Value::from(key));
+            }
+        }
+        std::fs::create_dir_all(&self.dir).with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let json_path = self.path(pdf_sha256);
+        std::fs::write(&json_path, serde_json::to_string_pretty(&data)?)
+            .with_context(|| format!("Failed to write {}", json_path.display()))?;
+        Ok(ExtractedDocument { json_path, data })
+    }
+
+    /// Cached results and the bytes they take up
+    pub fn usage(&self) -> (usize, u64) {
+        self.entries().iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .fold((0, 0), |(count, bytes), metadata| (count + 1, bytes + metadata.len()))
+    }
+
+    /// Delete every cached result; returns how many there were
+    pub fn clear(&self) -> Result<usize> {
+        let entries = self.entries();
+        for path in &entries {
+            std::fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Files in the directory named like cached results, leaving anything else there alone
+    fn entries(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return Vec::new() };
+        entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().is_some_and(|ext| ext == "json")
+                    && path.file_stem().and_then(|stem| stem.to_str())
+                        .is_some_and(|stem| stem.len() == 64 && stem.bytes().all(|b| b.is_ascii_hexdigit()))
+            })
+            .collect()
+    }
+}
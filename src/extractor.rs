@@ -1,187 +1,1000 @@
-use std::process::Command;
-use std::path::Path;
-use anyhow::Result;
+//! PDF extraction backends
+//!
+//! Every backend implements `Extractor`: it turns a PDF into the extraction
+//! JSON the rest of the app works on, written to a temp file and loaded. The
+//! settings pick the backend; `ExtractorKind` builds it.
+//!
+//! The Python backends talk to Rust in newline-delimited JSON on stdout: progress
+//! events, one event per finished page, and a closing summary or error (see
+//! `ExtractorEvent`). Pages are extracted one at a time, so the viewer can show
+//! the first pages of a long document while the rest are still running.
+//!
+//! The native backend needs no Python at all: it reads the PDF's text layer
+//! with pdfium in-process and writes the same JSON. Pages it can't read (scans
+//! without a text layer) and pages marked for a Python backend are handed to
+//! the Python pipeline when one is set up.
 
-pub struct ExtractionResult {
-    pub success: bool,
-    pub json_path: String,
-    pub items: usize,
-    pub message: String,
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::lines::{self, WordBox};
+use crate::python_env;
+use crate::scan_cleanup::ScanCleanup;
+use crate::types::{ProvenanceStage, ProvenanceStep};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractOptions {
+    /// Let Docling clean up page images before OCR
+    pub preprocess: bool,
+    /// Split paragraph items into one item per line afterwards (see `lines`)
+    #[serde(default)]
+    pub line_items: bool,
+    /// Straighten and clean scanned pages in Rust before extraction (see `scan_cleanup`)
+    #[serde(default)]
+    pub scan_cleanup: ScanCleanup,
+    /// Python backends the Docling extractor tries, in order, until one succeeds
+    #[serde(default = "default_fallback_chain")]
+    pub fallback_chain: Vec<PythonBackend>,
+    /// Further tries of a backend that failed for a reason other than not being installed
+    #[serde(default)]
+    pub retries: usize,
+    /// Backends for particular pages (zero-based) of the PDF being extracted;
+    /// set per document before extracting, not saved with the settings
+    #[serde(skip)]
+    pub page_backends: BTreeMap<usize, PythonBackend>,
+    /// Password of the encrypted PDF being extracted; set per document, never saved
+    #[serde(skip)]
+    pub password: Option<String>,
+    /// pdfium library the native extractor binds, if the user picked one; set
+    /// per run like the password
+    #[serde(skip)]
+    pub pdfium_library: Option<PathBuf>,
+    /// Stops the run when the user cancels it; set per run
+    #[serde(skip)]
+    pub cancel: CancelToken,
 }
 
-pub fn extract_pdf(pdf_path: &Path) -> Result<ExtractionResult> {
-    // Ensure we have absolute path
-    let pdf_path = pdf_path.canonicalize().unwrap_or_else(|_| pdf_path.to_path_buf());
-    // Python code that extracts PDF with image preprocessing
-    let python_code = r#"
+/// Shared flag the UI sets to stop a running extraction. The Python process
+/// is killed; the native extractor stops before its next page.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Error of a cancelled extraction
+pub const CANCELLED: &str = "Extraction cancelled";
+
+/// How often a running Python extractor is checked for cancellation
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+fn default_fallback_chain() -> Vec<PythonBackend> {
+    PythonBackend::ALL.to_vec()
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            preprocess: true,
+            line_items: false,
+            scan_cleanup: ScanCleanup::default(),
+            fallback_chain: default_fallback_chain(),
+            retries: 0,
+            page_backends: BTreeMap::new(),
+            password: None,
+            pdfium_library: None,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+/// A backend of the embedded Python extractor script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PythonBackend {
+    /// Docling with image preprocessing and Apple Vision OCR (`enhanced_chonker2.py`)
+    Enhanced,
+    /// Plain Docling (`chonker2.py`)
+    Docling,
+    /// pypdfium2 text with fonts (`simple_extractor.py`)
+    Simple,
+}
+
+impl PythonBackend {
+    pub const ALL: [PythonBackend; 3] = [PythonBackend::Enhanced, PythonBackend::Docling, PythonBackend::Simple];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PythonBackend::Enhanced => "Enhanced Docling",
+            PythonBackend::Docling => "Docling",
+            PythonBackend::Simple => "Simple (pypdfium2)",
+        }
+    }
+
+    /// Name the script takes on its command line and records as the extractor
+    pub fn arg(&self) -> &'static str {
+        match self {
+            PythonBackend::Enhanced => "enhanced",
+            PythonBackend::Docling => "docling",
+            PythonBackend::Simple => "simple",
+        }
+    }
+
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.arg() == arg)
+    }
+}
+
+/// Pages (zero-based) a run of the extractor script covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageSelection {
+    All,
+    Only(Vec<usize>),
+    Skip(Vec<usize>),
+}
+
+impl PageSelection {
+    /// The script's page argument, with one-based pages
+    fn arg(&self) -> String {
+        let one_based = |pages: &[usize]| pages.iter().map(|p| p + 1).collect::<Vec<_>>();
+        match self {
+            PageSelection::All => serde_json::json!({}),
+            PageSelection::Only(pages) => serde_json::json!({ "only": one_based(pages) }),
+            PageSelection::Skip(pages) => serde_json::json!({ "skip": one_based(pages) }),
+        }
+        .to_string()
+    }
+}
+
+/// Merge extractions of disjoint pages into one, in page order. `extra` are the
+/// pages extracted with other backends; the metadata records which they were.
+pub fn merge_page_extractions(base: Value, extra: Vec<(PythonBackend, Value)>) -> Value {
+    let mut metadata = base.get("metadata").cloned().unwrap_or_else(|| serde_json::json!({}));
+    let mut pages = Vec::new();
+    let mut items = Vec::new();
+    let mut page_backends = serde_json::Map::new();
+    let default_backend = metadata.get("extractor").cloned().unwrap_or(Value::Null);
+    for (backend, data) in std::iter::once((None, base)).chain(extra.into_iter().map(|(b, d)| (Some(b), d))) {
+        for page in data.get("pages").and_then(|v| v.as_array()).into_iter().flatten() {
+            let number = page.get("page_number").and_then(|v| v.as_u64()).unwrap_or(0);
+            let name = backend.map_or(default_backend.clone(), |b| Value::from(b.arg()));
+            page_backends.insert(number.to_string(), name);
+            pages.push(page.clone());
+        }
+        items.extend(data.get("items").and_then(|v| v.as_array()).into_iter().flatten().cloned());
+    }
+    let page_number = |value: &Value, key: &str| value.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    pages.sort_by_key(|page| page_number(page, "page_number"));
+    items.sort_by_key(|item| page_number(item, "page"));
+    for (index, item) in items.iter_mut().enumerate() {
+        item["index"] = Value::from(index);
+    }
+    metadata["page_backends"] = Value::Object(page_backends);
+    serde_json::json!({ "metadata": metadata, "pages": pages, "items": items })
+}
+
+/// Why one try of a backend failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendFailure {
+    pub backend: PythonBackend,
+    pub error: String,
+    /// The backend isn't installed; retrying won't help
+    pub missing: bool,
+}
+
+/// Try each backend of `chain` in order, up to `1 + retries` times unless it's
+/// missing, until `attempt` succeeds. `on_failure` hears about each failed try;
+/// if all fail, every failure is returned.
+pub fn run_fallback_chain<T>(
+    chain: &[PythonBackend],
+    retries: usize,
+    mut attempt: impl FnMut(PythonBackend) -> std::result::Result<T, BackendFailure>,
+    mut on_failure: impl FnMut(&BackendFailure),
+) -> std::result::Result<T, Vec<BackendFailure>> {
+    let mut failures = Vec::new();
+    for &backend in chain {
+        for _ in 0..=retries {
+            match attempt(backend) {
+                Ok(value) => return Ok(value),
+                Err(failure) => {
+                    on_failure(&failure);
+                    let missing = failure.missing;
+                    failures.push(failure);
+                    if missing {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Err(failures)
+}
+
+/// One line per backend that failed, with its last error
+pub fn describe_failures(failures: &[BackendFailure]) -> String {
+    if failures.is_empty() {
+        return "No extraction backends are enabled".to_string();
+    }
+    let mut lines: Vec<String> = Vec::new();
+    for failure in failures {
+        let line = format!("{}: {}", failure.backend.label(), failure.error);
+        // Retries of the same backend only show their last error
+        if lines.last().is_some_and(|last| last.starts_with(failure.backend.label())) {
+            lines.pop();
+        }
+        lines.push(line);
+    }
+    format!("Every extraction backend failed:\n{}", lines.join("\n"))
+}
+
+/// An extraction, as written to disk and loaded
+#[derive(Debug, Clone)]
+pub struct ExtractedDocument {
+    pub json_path: PathBuf,
+    pub data: Value,
+}
+
+impl ExtractedDocument {
+    pub fn load(json_path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(json_path)
+            .with_context(|| format!("Failed to read {}", json_path.display()))?;
+        let data = serde_json::from_str(&contents).context("Invalid extraction JSON")?;
+        Ok(Self { json_path: json_path.to_path_buf(), data })
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.data.get("items").and_then(|v| v.as_array()).map(|a| a.len()).unwrap_or(0)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.data.get("pages").and_then(|v| v.as_array()).map(|a| a.len()).unwrap_or(0)
+    }
+}
+
+/// One line of an extractor's output stream
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExtractorEvent {
+    /// What the extractor is doing; `page` and `pages` while it works through the pages
+    Progress {
+        message: String,
+        #[serde(default)]
+        page: Option<usize>,
+        #[serde(default)]
+        pages: Option<usize>,
+    },
+    /// A page's extraction JSON was written
+    Page { page: usize, pages: usize, json_path: PathBuf },
+    /// The whole extraction was written
+    Done {
+        json_path: PathBuf,
+        #[serde(default)]
+        items: usize,
+        #[serde(default)]
+        pages: usize,
+        #[serde(default)]
+        extractor_used: Option<String>,
+    },
+    Error {
+        error: String,
+        /// The backend isn't installed
+        #[serde(default)]
+        missing: bool,
+    },
+}
+
+/// What the app hears while an extraction runs
+#[derive(Debug, Clone)]
+pub enum ExtractionUpdate {
+    Progress { message: String, page: Option<usize>, pages: Option<usize> },
+    Page(ExtractedPage),
+    /// A backend in the fallback chain failed; the next one is tried
+    BackendFailed(BackendFailure),
+}
+
+/// How an extractor's event stream ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEnd {
+    /// The extraction JSON from the closing summary
+    Done(PathBuf),
+    /// The extractor reported an error
+    Failed { error: String, missing: bool },
+    /// The stream stopped without a summary, e.g. Python crashed
+    Ended,
+}
+
+/// Read an extractor's event stream, passing progress and finished pages to
+/// `on_update` as they arrive, up to the closing summary or error. Lines that
+/// aren't events are logged and skipped.
+pub fn read_stream(reader: impl BufRead, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<StreamEnd> {
+    let mut outcome = StreamEnd::Ended;
+    for line in reader.lines() {
+        let line = line?;
+        let event = match serde_json::from_str::<ExtractorEvent>(&line) {
+            Ok(event) => event,
+            Err(_) => {
+                log::debug!("Extractor output: {}", line);
+                continue;
+            }
+        };
+        match event {
+            ExtractorEvent::Progress { message, page, pages } => on_update(ExtractionUpdate::Progress { message, page, pages }),
+            ExtractorEvent::Page { page, pages, json_path } => match ExtractedPage::load(page, pages, &json_path) {
+                Ok(extracted) => on_update(ExtractionUpdate::Page(extracted)),
+                Err(e) => log::warn!("Couldn't load extracted page {}: {}", page, e),
+            },
+            ExtractorEvent::Done { json_path, items, pages, extractor_used } => {
+                log::info!("{} extracted {} items from {} pages", extractor_used.as_deref().unwrap_or("Extractor"), items, pages);
+                outcome = StreamEnd::Done(json_path);
+            }
+            ExtractorEvent::Error { error, missing } => outcome = StreamEnd::Failed { error, missing },
+        }
+    }
+    Ok(outcome)
+}
+
+/// One finished page of an extraction that is still running
+#[derive(Debug, Clone)]
+pub struct ExtractedPage {
+    /// One-based
+    pub page: usize,
+    pub page_count: usize,
+    /// The page's extraction JSON: one `pages` entry and its items
+    pub data: Value,
+}
+
+impl ExtractedPage {
+    pub fn load(page: usize, page_count: usize, json_path: &Path) -> Result<Self> {
+        Ok(Self { page, page_count, data: ExtractedDocument::load(json_path)?.data })
+    }
+}
+
+/// Pages reported so far, merged into an extraction of the leading pages
+#[derive(Debug, Clone, Default)]
+pub struct PartialExtraction {
+    pages: BTreeMap<usize, Value>,
+    page_count: usize,
+}
+
+impl PartialExtraction {
+    pub fn add(&mut self, page: ExtractedPage) {
+        self.page_count = page.page_count;
+        self.pages.insert(page.page, page.data);
+    }
+
+    /// Number of pages from the first on that have arrived without a gap
+    pub fn ready_pages(&self) -> usize {
+        (1..).take_while(|page| self.pages.contains_key(page)).count()
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.page_count
+    }
+
+    /// The leading pages as one extraction. Pages are looked up by position,
+    /// so a page that arrived ahead of a missing one waits for it.
+    pub fn data(&self) -> Value {
+        let mut metadata = Value::Null;
+        let (mut pages, mut items) = (Vec::new(), Vec::new());
+        for page in 1..=self.ready_pages() {
+            let data = &self.pages[&page];
+            if metadata.is_null() {
+                metadata = data.get("metadata").cloned().unwrap_or(Value::Null);
+            }
+            pages.extend(data.get("pages").and_then(|v| v.as_array()).into_iter().flatten().take(1).cloned());
+            items.extend(data.get("items").and_then(|v| v.as_array()).into_iter().flatten().cloned());
+        }
+        for (index, item) in items.iter_mut().enumerate() {
+            item["index"] = Value::from(index);
+        }
+        serde_json::json!({ "metadata": metadata, "pages": pages, "items": items })
+    }
+}
+
+/// What a backend's extraction contains, so the UI can hide features it can't feed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Table items with cell structure
+    pub tables: bool,
+    /// Text recognized from scanned pages
+    pub ocr: bool,
+    /// Boxes for individual words, not just lines or paragraphs
+    pub word_boxes: bool,
+    /// Picture items for figures and photos
+    pub images: bool,
+}
+
+impl Capabilities {
+    /// "tables, OCR" style list, or "text only"
+    pub fn summary(&self) -> String {
+        let names: Vec<&str> = [
+            (self.tables, "tables"),
+            (self.ocr, "OCR"),
+            (self.word_boxes, "word boxes"),
+            (self.images, "images"),
+        ]
+        .iter()
+        .filter(|(supported, _)| *supported)
+        .map(|(_, name)| *name)
+        .collect();
+        if names.is_empty() { "text only".to_string() } else { names.join(", ") }
+    }
+
+    /// Capabilities of whatever produced an extraction, from its metadata
+    pub fn of_extraction(data: &Value) -> Self {
+        let metadata = &data["metadata"];
+        if let Some(importer) = metadata["importer"].as_str() {
+            return Capabilities { ocr: true, tables: importer == "Textract", ..Default::default() };
+        }
+        match metadata["extractor"].as_str() {
+            Some("simple") => SimpleExtractor.capabilities(),
+            Some("native") => NativeExtractor.capabilities(),
+            Some(_) => DoclingExtractor.capabilities(),
+            None if metadata.get("docling_version").is_some() => DoclingExtractor.capabilities(),
+            None => Capabilities::default(),
+        }
+    }
+}
+
+pub trait Extractor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn capabilities(&self) -> Capabilities;
+    fn extract(&self, pdf: &Path, opts: &ExtractOptions) -> Result<ExtractedDocument>;
+
+    /// Extract, passing progress and finished pages to `on_update` for backends
+    /// that report them as they go; others only return the whole document
+    fn extract_streaming(&self, pdf: &Path, opts: &ExtractOptions, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<ExtractedDocument> {
+        let _ = on_update;
+        self.extract(pdf, opts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExtractorKind {
+    /// Docling via Python, falling back to the simple extractor if it isn't installed
+    #[default]
+    Docling,
+    /// pypdfium2 text with fonts via Python; fast, no OCR or table structure
+    Simple,
+    /// The text layer read with pdfium in-process; works without Python
+    Native,
+}
+
+impl ExtractorKind {
+    pub const ALL: [ExtractorKind; 3] = [ExtractorKind::Docling, ExtractorKind::Simple, ExtractorKind::Native];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExtractorKind::Docling => "Docling",
+            ExtractorKind::Simple => "Simple (pypdfium2)",
+            ExtractorKind::Native => "Native (pdfium, no Python)",
+        }
+    }
+
+    pub fn extractor(&self) -> Box<dyn Extractor> {
+        match self {
+            ExtractorKind::Docling => Box::new(DoclingExtractor),
+            ExtractorKind::Simple => Box::new(SimpleExtractor),
+            ExtractorKind::Native => Box::new(NativeExtractor),
+        }
+    }
+
+    /// Whether it can't run at all without the Python environment
+    pub fn needs_python(&self) -> bool {
+        !matches!(self, ExtractorKind::Native)
+    }
+}
+
+/// Docling in the app's Python environment, through the configured fallback chain
+pub struct DoclingExtractor;
+
+impl Extractor for DoclingExtractor {
+    fn name(&self) -> &'static str {
+        "docling"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { tables: true, ocr: true, word_boxes: false, images: true }
+    }
+
+    fn extract(&self, pdf: &Path, opts: &ExtractOptions) -> Result<ExtractedDocument> {
+        run_with_page_backends(pdf, &opts.fallback_chain, opts, &mut |_| {})
+    }
+
+    fn extract_streaming(&self, pdf: &Path, opts: &ExtractOptions, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<ExtractedDocument> {
+        run_with_page_backends(pdf, &opts.fallback_chain, opts, on_update)
+    }
+}
+
+/// The pypdfium2-based `simple_extractor.py`
+pub struct SimpleExtractor;
+
+impl Extractor for SimpleExtractor {
+    fn name(&self) -> &'static str {
+        "simple"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        // Line boxes from the PDF's text layer only
+        Capabilities::default()
+    }
+
+    fn extract(&self, pdf: &Path, opts: &ExtractOptions) -> Result<ExtractedDocument> {
+        run_with_page_backends(pdf, &[PythonBackend::Simple], opts, &mut |_| {})
+    }
+
+    fn extract_streaming(&self, pdf: &Path, opts: &ExtractOptions, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<ExtractedDocument> {
+        run_with_page_backends(pdf, &[PythonBackend::Simple], opts, on_update)
+    }
+}
+
+/// The PDF's text layer through pdfium, in-process. Pages without one, and
+/// pages marked for a Python backend, go to the Python pipeline if it's set up.
+pub struct NativeExtractor;
+
+impl Extractor for NativeExtractor {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        // Line boxes from the PDF's text layer only
+        Capabilities::default()
+    }
+
+    fn extract(&self, pdf: &Path, opts: &ExtractOptions) -> Result<ExtractedDocument> {
+        run_native(pdf, opts, &mut |_| {})
+    }
+
+    fn extract_streaming(&self, pdf: &Path, opts: &ExtractOptions, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<ExtractedDocument> {
+        run_native(pdf, opts, on_update)
+    }
+}
+
+/// Words further apart than this many line heights belong to separate items
+/// (columns, table cells)
+pub const COLUMN_GAP: f64 = 2.0;
+
+/// A row's words, split where the gap to the next word is wider than `COLUMN_GAP`
+fn split_at_gaps(row: Vec<WordBox>) -> Vec<Vec<WordBox>> {
+    let mut runs: Vec<Vec<WordBox>> = Vec::new();
+    for word in row {
+        let gap = runs.last().and_then(|run| run.last()).map(|last| {
+            let line_height = last.bbox.height.max(word.bbox.height);
+            word.bbox.left - (last.bbox.left + last.bbox.width) > COLUMN_GAP * line_height
+        });
+        match (gap, runs.last_mut()) {
+            (Some(false), Some(run)) => run.push(word),
+            _ => runs.push(vec![word]),
+        }
+    }
+    runs
+}
+
+/// One page's extraction from its text layer's word boxes (TOPLEFT), in the
+/// schema the Python extractors write: a text item per run of words on a line
+pub fn text_layer_page(page_number: usize, width: f64, height: f64, words: Vec<WordBox>) -> Value {
+    let items: Vec<Value> = lines::group_rows(words).into_iter()
+        .flat_map(split_at_gaps)
+        .filter_map(|run| lines::join_words(&run))
+        .enumerate()
+        .map(|(index, line)| {
+            let b = &line.bbox;
+            let kind = if line.text.ends_with(':') { "FormLabel" } else { "TextItem" };
+            let mut item = serde_json::json!({
+                "index": index,
+                "type": kind,
+                "level": 1,
+                "content": line.text,
+                "bbox": {
+                    "left": b.left,
+                    "top": b.top,
+                    "right": b.left + b.width,
+                    "bottom": b.top + b.height,
+                    "width": b.width,
+                    "height": b.height,
+                    "coord_origin": "TOPLEFT",
+                },
+                "page": page_number,
+                // pdfium's text boxes span the font's ascent and descent
+                "attributes": { "style": { "font_size": (b.height * 10.0).round() / 10.0 } },
+            });
+            crate::document::record_provenance(&mut item, ProvenanceStep::new(ProvenanceStage::Extractor, "native"));
+            item
+        })
+        .collect();
+    serde_json::json!({
+        "metadata": { "extractor": "native" },
+        "pages": [{ "page_number": page_number, "width": width, "height": height }],
+        "items": items,
+    })
+}
+
+/// Read every page's text layer, then hand the pages that need more to the
+/// Python pipeline: marked pages to their backends, pages without text to the
+/// fallback chain. Pages Python can't do keep what pdfium read.
+fn run_native(pdf_path: &Path, opts: &ExtractOptions, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<ExtractedDocument> {
+    let started = Instant::now();
+    let pdfium = crate::core::bind_pdfium_from(opts.pdfium_library.as_deref())?;
+    let document = pdfium.load_pdf_from_file(pdf_path, opts.password.as_deref())
+        .map_err(|e| anyhow!("Failed to open {}: {}", pdf_path.display(), e))?;
+    let page_count = document.pages().len() as usize;
+    let mut pages: BTreeMap<usize, Value> = BTreeMap::new();
+    for (index, page) in document.pages().iter().enumerate() {
+        if opts.cancel.is_cancelled() {
+            bail!(CANCELLED);
+        }
+        on_update(ExtractionUpdate::Progress {
+            message: format!("Reading page {} of {}", index + 1, page_count),
+            page: Some(index + 1),
+            pages: Some(page_count),
+        });
+        let data = text_layer_page(index + 1, page.width().value as f64, page.height().value as f64, lines::page_word_boxes(&page));
+        on_update(ExtractionUpdate::Page(ExtractedPage { page: index + 1, page_count, data: data.clone() }));
+        pages.insert(index, data);
+    }
+    drop(document);
+
+    let mut runs: BTreeMap<Vec<PythonBackend>, Vec<usize>> = BTreeMap::new();
+    for (&page, &backend) in opts.page_backends.iter().filter(|(page, _)| pages.contains_key(page)) {
+        runs.entry(vec![backend]).or_default().push(page);
+    }
+    let textless: Vec<usize> = pages.iter()
+        .filter(|(page, data)| !opts.page_backends.contains_key(page) && data["items"].as_array().is_none_or(|items| items.is_empty()))
+        .map(|(&page, _)| page)
+        .collect();
+    if !textless.is_empty() && !opts.fallback_chain.is_empty() {
+        runs.entry(opts.fallback_chain.clone()).or_default().extend(textless);
+    }
+    let mut extra = Vec::new();
+    if !runs.is_empty() && !python_env::venv_python(&python_env::venv_dir()).exists() {
+        log::info!("No Python environment; {} pages keep their text layer only", runs.values().map(Vec::len).sum::<usize>());
+        runs.clear();
+    }
+    for (chain, selected) in runs {
+        match run_python_chain(pdf_path, &chain, &PageSelection::Only(selected.clone()), opts, on_update) {
+            Err(_) if opts.cancel.is_cancelled() => bail!(CANCELLED),
+            Ok(python) => {
+                let backend = python.data["metadata"]["extractor"].as_str().and_then(PythonBackend::from_arg).unwrap_or(chain[0]);
+                for page in &selected {
+                    pages.remove(page);
+                }
+                extra.push((backend, python.data));
+            }
+            Err(e) => log::warn!("Python extraction of pages {:?} failed, keeping the text layer: {}", selected, e),
+        }
+    }
+
+    let items: Vec<Value> = pages.values().flat_map(|data| data["items"].as_array().cloned().unwrap_or_default()).collect();
+    let base = serde_json::json!({
+        "metadata": {
+            "source_file": pdf_path.display().to_string(),
+            "file_name": pdf_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            "extractor": "native",
+            "extractor_version": env!("CARGO_PKG_VERSION"),
+            "extraction_seconds": (started.elapsed().as_secs_f64() * 1000.0).round() / 1000.0,
+        },
+        "pages": pages.values().flat_map(|data| data["pages"].as_array().cloned().unwrap_or_default()).collect::<Vec<_>>(),
+        "items": items,
+    });
+    let data = merge_page_extractions(base, extra);
+    let stem = pdf_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let json_path = crate::storage::temp_dir().join(format!("{}_chonker3_native.json", stem));
+    std::fs::write(&json_path, serde_json::to_string_pretty(&data)?)
+        .with_context(|| format!("Failed to write {}", json_path.display()))?;
+    Ok(ExtractedDocument { json_path, data })
+}
+
+// Python code that extracts PDF with image preprocessing
+const PYTHON_EXTRACTOR: &str = r#"
 import sys
 import json
 import tempfile
 import os
+import time
 
+def emit(event, **fields):
+    # One event per line; flushed so the app sees it right away
+    print(json.dumps({'event': event, **fields}), flush=True)
+
+started = time.time()
 try:
     # Add current directory to path to use local scripts
     sys.path.insert(0, os.getcwd())
     
-    # Get PDF path from command line
+    # PDF path, backend ("enhanced", "docling" or "simple") and preprocessing flag.
+    # Only the named backend is tried; the app walks its fallback chain.
     pdf_path = sys.argv[1]
+    backend = sys.argv[2] if len(sys.argv) > 2 else 'docling'
+    preprocess = len(sys.argv) <= 3 or sys.argv[3] != 'no-preprocess'
+    # Pages to extract (one-based): {"only": [...]}, {"skip": [...]} or everything
+    selection = json.loads(sys.argv[4]) if len(sys.argv) > 4 else {}
+    only_pages = set(selection.get('only', []))
+    skip_pages = set(selection.get('skip', []))
     
-    # Try to use enhanced chonker2 with Apple Vision forced
+    emit('progress', message='Loading extractor')
     try:
-        # Hide EasyOCR to force Apple Vision
-        import sys
-        class HideEasyOCR:
-            def find_module(self, fullname, path=None):
-                if fullname == 'easyocr' or fullname.startswith('easyocr.'):
-                    return self
-                return None
-            def load_module(self, fullname):
-                raise ImportError(f"EasyOCR hidden to force Apple Vision usage")
-        sys.meta_path.insert(0, HideEasyOCR())
-        
-        from enhanced_chonker2 import EnhancedChonker2
-        use_enhanced = True
-        print(f"DEBUG: Using Enhanced Docling with Apple Vision (EasyOCR hidden)", file=sys.stderr)
-    except ImportError as e1:
-        # Try regular enhanced chonker2
-        try:
+        if backend == 'enhanced':
+            # Hide EasyOCR to force Apple Vision
+            class HideEasyOCR:
+                def find_module(self, fullname, path=None):
+                    if fullname == 'easyocr' or fullname.startswith('easyocr.'):
+                        return self
+                    return None
+                def load_module(self, fullname):
+                    raise ImportError(f"EasyOCR hidden to force Apple Vision usage")
+            sys.meta_path.insert(0, HideEasyOCR())
             from enhanced_chonker2 import EnhancedChonker2
-            use_enhanced = True
-            print(f"DEBUG: Using Enhanced Docling extractor with preprocessing", file=sys.stderr)
-        except ImportError as e2:
-            # Try regular chonker2
-            try:
-                from chonker2 import Chonker2
-                use_enhanced = False
-                use_docling = True
-                print(f"DEBUG: Using regular Docling extractor", file=sys.stderr)
-            except ImportError as e3:
-                # Fall back to simple extractor
-                print(f"DEBUG: Docling import failed: {e3}", file=sys.stderr)
-                from simple_extractor import extract_pdf_with_fonts
-                use_enhanced = False
-                use_docling = False
-                print(f"DEBUG: Using simple extractor", file=sys.stderr)
-    
-    # TEMPORARY: Force simple extractor for testing
-    if '--force-simple' in str(pdf_path):
-        from simple_extractor import extract_pdf_with_fonts
-        use_enhanced = False
-        use_docling = False
-        print(f"DEBUG: FORCED simple extractor", file=sys.stderr)
+            extractor = EnhancedChonker2(verbose=False, preprocess=preprocess)
+            extract_file = extractor.extract_to_json
+        elif backend == 'docling':
+            from chonker2 import Chonker2
+            extractor = Chonker2(verbose=False)
+            extract_file = extractor.extract_to_json
+        else:
+            from simple_extractor import extract_pdf_with_fonts
+            def extract_file(path, json_path):
+                return extract_pdf_with_fonts(path)
+    except (ImportError, SystemExit) as e:
+        # chonker2 exits on import when Docling is missing
+        if isinstance(e, SystemExit) or 'docling' in str(e).lower():
+            emit('error', error='Docling not installed. Open Settings > Python environment to install it.', missing=True)
+        else:
+            emit('error', error=str(e), missing=True)
+        sys.exit(0)
+    print(f"DEBUG: Using {backend} extractor", file=sys.stderr)
     
     # No preprocessing - use original PDF directly
     pdf_to_extract = pdf_path
     
+    # Provenance chain every item starts with, ahead of any steps the backend recorded
+    # itself (such as the OCR engine that read it)
+    steps = [{'stage': 'extractor', 'name': backend}]
+    if backend == 'enhanced' and preprocess:
+        steps.insert(0, {'stage': 'preprocess', 'name': 'image preprocessing'})
+    def record_provenance(items):
+        for item in items:
+            item['provenance'] = steps + item.get('provenance', [])
+    
     # Extract from PDF
     temp_json = tempfile.mktemp(suffix='_chonker3.json')
     
-    if use_enhanced:
-        # Use Enhanced Docling extractor with preprocessing
-        extractor = EnhancedChonker2(verbose=False, preprocess=True)
-        data = extractor.extract_to_json(pdf_to_extract, temp_json)
-    elif use_docling:
-        # Use regular Docling extractor
-        extractor = Chonker2(verbose=False)
-        data = extractor.extract_to_json(pdf_to_extract, temp_json)
-    else:
-        # Use simple pypdfium2 extractor
-        data = extract_pdf_with_fonts(pdf_to_extract)
-        with open(temp_json, 'w') as f:
-            json.dump(data, f, indent=2)
-    
-    
-    # Output results as JSON for Rust to parse
-    result = {
-        'success': True,
-        'json_path': temp_json,
-        'items': len(data.get('items', [])),
-        'pages': len(data.get('pages', [])),
-        'tables': len(data.get('tables', [])),
-        'extractor_used': 'enhanced' if use_enhanced else ('docling' if use_docling else 'simple')
-    }
-    
-    print(json.dumps(result))
-except ImportError as e:
-    if 'docling' in str(e).lower():
-        print(json.dumps({
-            'success': False,
-            'error': 'Docling not installed. Please run: pip install docling'
-        }))
+    # Encrypted PDFs are opened with the password the app passed along; the
+    # single-page copies below are written without encryption for the backends
+    password = os.environ.get('CHONKER3_PDF_PASSWORD') or None
+    
+    # Extract page by page, announcing each page's JSON on its own line as soon
+    # as it's written so the viewer can show it while later pages run
+    try:
+        import pypdfium2
+        source = pypdfium2.PdfDocument(pdf_to_extract, password=password)
+        page_count = len(source)
+    except Exception as e:
+        print(f"DEBUG: Can't split pages ({e}); extracting in one go", file=sys.stderr)
+        source, page_count = None, 0
+    
+    if source is None and (only_pages or skip_pages):
+        emit('error', error="Can't pick pages out of this PDF for another backend")
+        sys.exit(0)
+    if source is None:
+        emit('progress', message='Extracting')
+        data = extract_file(pdf_to_extract, temp_json)
+        record_provenance(data.get('items', []))
     else:
-        print(json.dumps({
-            'success': False,
-            'error': str(e)
-        }))
+        page_dir = tempfile.mkdtemp(suffix='_chonker3_pages')
+        data = {'metadata': {}, 'pages': [], 'items': [], 'tables': []}
+        for index in range(page_count):
+            if (only_pages and index + 1 not in only_pages) or index + 1 in skip_pages:
+                continue
+            single = pypdfium2.PdfDocument.new()
+            single.import_pages(source, [index])
+            emit('progress', message=f'Extracting page {index + 1} of {page_count}', page=index + 1, pages=page_count)
+            page_pdf = os.path.join(page_dir, f'page_{index + 1:04d}.pdf')
+            single.save(page_pdf)
+            page_json = os.path.join(page_dir, f'page_{index + 1:04d}.json')
+            page_data = extract_file(page_pdf, page_json)
+            
+            # Number everything by the page's place in the whole document
+            width, height = source[index].get_size()
+            pages = page_data.get('pages') or [{'width': width, 'height': height}]
+            pages[0]['page_number'] = index + 1
+            page_data['pages'] = pages[:1]
+            for item in page_data.get('items', []):
+                item['page'] = index + 1
+            record_provenance(page_data.get('items', []))
+            for table in page_data.get('tables', []):
+                if 'page' in table:
+                    table['page'] = index + 1
+            with open(page_json, 'w') as f:
+                json.dump(page_data, f)
+            emit('page', page=index + 1, pages=page_count, json_path=page_json)
+            
+            if not data['metadata']:
+                data['metadata'] = page_data.get('metadata', {})
+            data['pages'] += page_data['pages']
+            data['items'] += page_data.get('items', [])
+            data['tables'] += page_data.get('tables', [])
+        for index, item in enumerate(data['items']):
+            item['index'] = index
+        data['metadata']['source_file'] = str(pdf_path)
+        data['metadata']['file_name'] = os.path.basename(pdf_path)
+    
+    # Record which extractor produced the JSON
+    extractor_used = backend
+    data.setdefault('metadata', {})['extractor'] = extractor_used
+    data['metadata']['extraction_seconds'] = round(time.time() - started, 3)
+    with open(temp_json, 'w') as f:
+        json.dump(data, f, indent=2)
+    
+    # Closing summary for Rust
+    emit('done',
+        json_path=temp_json,
+        items=len(data.get('items', [])),
+        pages=len(data.get('pages', [])),
+        tables=len(data.get('tables', [])),
+        extractor_used=extractor_used)
 except Exception as e:
-    print(json.dumps({
-        'success': False,
-        'error': str(e)
-    }))
+    emit('error', error=str(e))
 "#;
 
+/// Extract the pages marked for other backends with those, then the rest with
+/// `chain`, and merge the two
+fn run_with_page_backends(pdf_path: &Path, chain: &[PythonBackend], opts: &ExtractOptions, on_update: &mut dyn FnMut(ExtractionUpdate)) -> Result<ExtractedDocument> {
+    if opts.page_backends.is_empty() {
+        return run_python_chain(pdf_path, chain, &PageSelection::All, opts, on_update);
+    }
+    let mut groups: BTreeMap<PythonBackend, Vec<usize>> = BTreeMap::new();
+    for (&page, &backend) in &opts.page_backends {
+        groups.entry(backend).or_default().push(page);
+    }
+    
+    // Marked pages go first: there are usually few, and the rest then streams in order.
+    // Pages whose backend fails are left to the main chain.
+    let mut extra = Vec::new();
+    let mut done = Vec::new();
+    for (backend, pages) in groups {
+        match run_python_chain(pdf_path, &[backend], &PageSelection::Only(pages.clone()), opts, on_update) {
+            Err(_) if opts.cancel.is_cancelled() => bail!(CANCELLED),
+            Ok(document) => {
+                done.extend(pages);
+                extra.push((backend, document.data));
+            }
+            Err(e) => on_update(ExtractionUpdate::BackendFailed(BackendFailure { backend, error: e.to_string(), missing: false })),
+        }
+    }
+    let base = run_python_chain(pdf_path, chain, &PageSelection::Skip(done), opts, on_update)?;
+    let data = merge_page_extractions(base.data, extra);
+    std::fs::write(&base.json_path, serde_json::to_string_pretty(&data)?)
+        .with_context(|| format!("Failed to write {}", base.json_path.display()))?;
+    Ok(ExtractedDocument { json_path: base.json_path, data })
+}
+
+/// Run the backends of `chain` in turn until one extracts the selected pages
+fn run_python_chain(
+    pdf_path: &Path,
+    chain: &[PythonBackend],
+    selection: &PageSelection,
+    opts: &ExtractOptions,
+    on_update: &mut dyn FnMut(ExtractionUpdate),
+) -> Result<ExtractedDocument> {
     // IMPORTANT: Always use the chonker3 virtual environment's Python!
     // This venv has all required dependencies (docling, pypdfium2, etc.)
-    // DO NOT use system python or create new venvs
-    let venv_python = std::env::current_dir()
-        .unwrap()
-        .join(".venv")
-        .join("bin")
-        .join("python");
-    
-    // Run Python with our embedded code
-    let output = Command::new(venv_python)
+    // DO NOT use system python; python_env::setup creates the venv if it's missing
+    let venv_python = python_env::venv_python(&python_env::venv_dir());
+    if !venv_python.exists() {
+        bail!("Python environment not set up. Open Settings > Python environment to create it.");
+    }
+    
+    let failed: std::cell::RefCell<Vec<BackendFailure>> = Default::default();
+    let result = run_fallback_chain(
+        chain,
+        opts.retries,
+        |backend| {
+            // Later backends needn't start once the user cancelled
+            if opts.cancel.is_cancelled() {
+                return Err(BackendFailure { backend, error: CANCELLED.to_string(), missing: true });
+            }
+            // Report the previous try's failure before this one's progress
+            for failure in failed.borrow_mut().drain(..) {
+                on_update(ExtractionUpdate::BackendFailed(failure));
+            }
+            run_python_extractor(&venv_python, pdf_path, backend, selection, opts, on_update)
+        },
+        |failure| failed.borrow_mut().push(failure.clone()),
+    );
+    // A cancelled run isn't a backend failure worth listing
+    if opts.cancel.is_cancelled() {
+        bail!(CANCELLED);
+    }
+    result.map_err(|failures| anyhow!("{}", describe_failures(&failures)))
+}
+
+fn run_python_extractor(
+    venv_python: &Path,
+    pdf_path: &Path,
+    backend: PythonBackend,
+    selection: &PageSelection,
+    opts: &ExtractOptions,
+    on_update: &mut dyn FnMut(ExtractionUpdate),
+) -> std::result::Result<ExtractedDocument, BackendFailure> {
+    let fail = |error: String, missing: bool| BackendFailure { backend, error, missing };
+    
+    // Ensure we have absolute path
+    let pdf_path = pdf_path.canonicalize().unwrap_or_else(|_| pdf_path.to_path_buf());
+    
+    // Run Python with our embedded code; a password goes in the environment,
+    // where other users can't read it from the process list
+    let mut command = Command::new(venv_python);
+    if let Some(password) = &opts.password {
+        command.env("CHONKER3_PDF_PASSWORD", password);
+    }
+    let mut child = command
         .arg("-c")
-        .arg(python_code)
+        .arg(PYTHON_EXTRACTOR)
         .arg(&pdf_path)
-        .output()?;
-
-    if output.status.success() {
-        // Parse the JSON output from Python
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        println!("Python output: {}", stdout); // Debug print
-        
-        let result: serde_json::Value = serde_json::from_str(&stdout)?;
-        
-        // Check if it's an error response
-        if let Some(false) = result["success"].as_bool() {
-            return Ok(ExtractionResult {
-                success: false,
-                json_path: String::new(),
-                items: 0,
-                message: result["error"].as_str().unwrap_or("Unknown error").to_string(),
-            });
-        }
-        
-        Ok(ExtractionResult {
-            success: true,
-            json_path: result["json_path"].as_str().unwrap_or("").to_string(),
-            items: result["items"].as_u64().unwrap_or(0) as usize,
-            message: format!("Extracted {} items from {} pages", 
-                result["items"].as_u64().unwrap_or(0),
-                result["pages"].as_u64().unwrap_or(0)),
-        })
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        
-        // Check if error was returned as JSON
-        if let Ok(error_result) = serde_json::from_str::<serde_json::Value>(&stdout) {
-            if let Some(error) = error_result.get("error").and_then(|v| v.as_str()) {
-                return Ok(ExtractionResult {
-                    success: false,
-                    json_path: String::new(),
-                    items: 0,
-                    message: format!("Extraction failed: {}", error),
-                });
+        .arg(backend.arg())
+        .arg(if opts.preprocess { "preprocess" } else { "no-preprocess" })
+        .arg(selection.arg())
+        // Python's tempfile puts the extraction JSON and page files here
+        .env("TMPDIR", crate::storage::temp_dir())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| fail(format!("Couldn't start Python: {}", e), true))?;
+    
+    // Drain stderr on the side so a chatty extractor can't block on a full pipe
+    let stderr_reader = child.stderr.take().map(|mut pipe| std::thread::spawn(move || {
+        let mut stderr = String::new();
+        let _ = pipe.read_to_string(&mut stderr);
+        stderr
+    }));
+    
+    // Kill the process if the user cancels; its stdout then closes and the
+    // stream below ends
+    let stdout = child.stdout.take();
+    let cancel = opts.cancel.clone();
+    let watcher = std::thread::spawn(move || {
+        while matches!(child.try_wait(), Ok(None)) {
+            if cancel.is_cancelled() {
+                let _ = child.kill();
+                break;
             }
+            std::thread::sleep(CANCEL_POLL);
+        }
+        let _ = child.wait();
+    });
+    
+    let outcome = match stdout {
+        Some(stdout) => read_stream(BufReader::new(stdout), on_update),
+        None => Err(anyhow!("Extractor stdout unavailable")),
+    };
+    let _ = watcher.join();
+    let stderr = stderr_reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    if !stderr.trim().is_empty() {
+        log::debug!("{} extractor stderr:\n{}", backend.label(), stderr);
+    }
+    
+    match outcome.map_err(|e| fail(e.to_string(), false))? {
+        StreamEnd::Done(json_path) => ExtractedDocument::load(&json_path).map_err(|e| fail(e.to_string(), false)),
+        StreamEnd::Failed { error, missing } => Err(fail(error, missing)),
+        // Python itself died before it could report anything; its last words say why
+        StreamEnd::Ended => {
+            let last_line = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("Python exited without a result");
+            Err(fail(last_line.trim().to_string(), false))
         }
-        
-        Ok(ExtractionResult {
-            success: false,
-            json_path: String::new(),
-            items: 0,
-            message: format!("Extraction failed: {} | {}", stderr, stdout),
-        })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,150 @@
+//! Output mapping of named fields to spreadsheet columns
+//!
+//! The final step of data capture: each column of the mapping takes its value
+//! from a named region, the document's file name, path or page count, or a
+//! workspace metadata key, and every processed document becomes one CSV row.
+//! Batch export writes the sheet next to its manifest.
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::export::csv_field;
+use crate::extractor::ExtractedDocument;
+use crate::patch::EditPatch;
+use crate::regions::{self, RegionField};
+use crate::workspace::WorkspaceDocument;
+
+fn default_file_name() -> String {
+    "fields.csv".to_string()
+}
+
+/// Where a column's values come from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", content = "name", rename_all = "snake_case")]
+pub enum ColumnSource {
+    /// A named region's contents, by name ignoring case
+    Field(String),
+    FileName,
+    Path,
+    Pages,
+    /// A workspace metadata value
+    Metadata(String),
+}
+
+impl ColumnSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColumnSource::Field(_) => "Field",
+            ColumnSource::FileName => "File name",
+            ColumnSource::Path => "Path",
+            ColumnSource::Pages => "Pages",
+            ColumnSource::Metadata(_) => "Metadata",
+        }
+    }
+
+    /// The field or metadata key it names, if it takes one
+    pub fn name_mut(&mut self) -> Option<&mut String> {
+        match self {
+            ColumnSource::Field(name) | ColumnSource::Metadata(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappedColumn {
+    pub header: String,
+    pub source: ColumnSource,
+}
+
+impl MappedColumn {
+    /// A column headed by the field's own name
+    pub fn field(name: &str) -> Self {
+        Self { header: name.to_string(), source: ColumnSource::Field(name.to_string()) }
+    }
+}
+
+/// Columns of the per-document sheet; nothing is written while it has none
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldMapping {
+    #[serde(default)]
+    pub columns: Vec<MappedColumn>,
+    /// Sheet name in the batch export folder
+    #[serde(default = "default_file_name")]
+    pub file_name: String,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        Self { columns: Vec::new(), file_name: default_file_name() }
+    }
+}
+
+/// What one document offers the mapping
+#[derive(Debug, Clone)]
+pub struct DocumentFields {
+    pub document: WorkspaceDocument,
+    pub pages: usize,
+    pub fields: Vec<RegionField>,
+}
+
+impl DocumentFields {
+    /// Read the regions of a document's latest extraction
+    pub fn load(doc: &WorkspaceDocument) -> Result<Self> {
+        let json_path = doc.extracted_json.as_ref()
+            .with_context(|| format!("{} has not been extracted", doc.display_name()))?;
+        let extracted = ExtractedDocument::load(json_path)?;
+        let edits = EditPatch::new(Some(doc.path.display().to_string()));
+        Ok(Self {
+            document: doc.clone(),
+            pages: extracted.page_count(),
+            fields: regions::fields(&extracted.data, &edits, &doc.regions),
+        })
+    }
+}
+
+impl FieldMapping {
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// One document's row; fields it lacks are left empty
+    pub fn row(&self, doc: &DocumentFields) -> Vec<String> {
+        self.columns.iter().map(|column| match &column.source {
+            ColumnSource::Field(name) => doc.fields.iter()
+                .find(|f| f.name.eq_ignore_ascii_case(name.trim()))
+                .map(|f| f.text.clone())
+                .unwrap_or_default(),
+            ColumnSource::FileName => doc.document.display_name(),
+            ColumnSource::Path => doc.document.path.display().to_string(),
+            ColumnSource::Pages => doc.pages.to_string(),
+            ColumnSource::Metadata(key) => doc.document.metadata.get(key.trim()).cloned().unwrap_or_default(),
+        }).collect()
+    }
+
+    /// The sheet: a header row, then a row per document
+    pub fn csv(&self, documents: &[DocumentFields]) -> String {
+        let line = |values: Vec<String>| values.iter().map(|v| csv_field(v)).collect::<Vec<_>>().join(",") + "\n";
+        let mut out = line(self.columns.iter().map(|c| c.header.clone()).collect());
+        for doc in documents {
+            out.push_str(&line(self.row(doc)));
+        }
+        out
+    }
+
+    pub fn write(&self, path: &Path, documents: &[DocumentFields]) -> Result<()> {
+        std::fs::write(path, self.csv(documents)).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Names of the regions drawn on any of the documents, first seen first
+pub fn field_names(documents: &[WorkspaceDocument]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for region in documents.iter().flat_map(|doc| &doc.regions) {
+        if !names.iter().any(|name| name.eq_ignore_ascii_case(&region.name)) {
+            names.push(region.name.clone());
+        }
+    }
+    names
+}
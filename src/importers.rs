@@ -0,0 +1,485 @@
+//! Importers for third-party OCR output (hOCR, ALTO, AWS Textract JSON) and
+//! Label Studio tasks
+//!
+//! Each importer converts its source format into the same JSON item schema the
+//! Python extractors write (`pages` + `items` with TOPLEFT bboxes in PDF points),
+//! so imported results can be viewed and corrected exactly like an extraction.
+
+use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use crate::types::{ProvenanceStage, ProvenanceStep};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Hocr,
+    Alto,
+    Textract,
+    LabelStudio,
+}
+
+impl ImportFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportFormat::Hocr => "hOCR",
+            ImportFormat::Alto => "ALTO",
+            ImportFormat::Textract => "Textract",
+            ImportFormat::LabelStudio => "Label Studio",
+        }
+    }
+
+    /// Guess the format from the file contents (extensions are unreliable for these)
+    pub fn detect(contents: &str) -> Option<Self> {
+        let head: String = contents.chars().take(4096).collect::<String>().to_lowercase();
+        if head.trim_start().starts_with('{') && contents.contains("\"Blocks\"") {
+            Some(ImportFormat::Textract)
+        } else if head.trim_start().starts_with(['[', '{']) && contents.contains("\"from_name\"") {
+            Some(ImportFormat::LabelStudio)
+        } else if head.contains("<alto") {
+            Some(ImportFormat::Alto)
+        } else if head.contains("ocr_page") || head.contains("ocr-system") || head.contains("<html") {
+            Some(ImportFormat::Hocr)
+        } else {
+            None
+        }
+    }
+}
+
+/// Import an OCR result file. `page_sizes` are the PDF page sizes in points
+/// (width, height); source coordinates are scaled onto them when available.
+pub fn import_file(path: &Path, page_sizes: &[(f64, f64)]) -> Result<(ImportFormat, Value)> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let format = ImportFormat::detect(&contents)
+        .ok_or_else(|| anyhow!("Unrecognized OCR format (expected hOCR, ALTO, Textract JSON or Label Studio tasks)"))?;
+
+    let mut data = match format {
+        ImportFormat::Hocr => import_hocr(&contents, page_sizes)?,
+        ImportFormat::Alto => import_alto(&contents, page_sizes)?,
+        ImportFormat::Textract => import_textract(&contents, page_sizes)?,
+        ImportFormat::LabelStudio => import_label_studio(&contents, page_sizes)?,
+    };
+
+    let step = ProvenanceStep::new(ProvenanceStage::Ocr, &format!("{} import", format.label()));
+    for item in data.get_mut("items").and_then(|v| v.as_array_mut()).into_iter().flatten() {
+        crate::document::record_provenance(item, step.clone());
+    }
+    data["metadata"] = json!({
+        "source_file": path.display().to_string(),
+        "file_name": path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        "importer": format.label(),
+    });
+
+    Ok((format, data))
+}
+
+/// Builds up the output document while converting source units to PDF points
+struct DocumentBuilder<'a> {
+    page_sizes: &'a [(f64, f64)],
+    pages: Vec<Value>,
+    items: Vec<Value>,
+    // Scale from source units to PDF points for the current page
+    scale: (f64, f64),
+    page_number: usize,
+}
+
+impl<'a> DocumentBuilder<'a> {
+    fn new(page_sizes: &'a [(f64, f64)]) -> Self {
+        Self {
+            page_sizes,
+            pages: Vec::new(),
+            items: Vec::new(),
+            scale: (1.0, 1.0),
+            page_number: 0,
+        }
+    }
+
+    /// Start a new page whose source coordinate space is `source_size`.
+    /// `natural_size` is the page size in points if no PDF page size is known.
+    fn begin_page(&mut self, source_size: (f64, f64), natural_size: (f64, f64)) {
+        let target = self.page_sizes.get(self.pages.len()).copied().unwrap_or(natural_size);
+        self.scale = if source_size.0 > 0.0 && source_size.1 > 0.0 {
+            (target.0 / source_size.0, target.1 / source_size.1)
+        } else {
+            (1.0, 1.0)
+        };
+        self.pages.push(json!({
+            "page_number": self.pages.len() + 1,
+            "width": target.0,
+            "height": target.1,
+        }));
+        self.page_number = self.pages.len();
+    }
+
+    /// Add an item with a bbox in source units (top-left origin)
+    fn add_item(&mut self, item_type: &str, content: String, bbox: (f64, f64, f64, f64), confidence: f64) {
+        if content.trim().is_empty() {
+            return;
+        }
+        let left = bbox.0 * self.scale.0;
+        let top = bbox.1 * self.scale.1;
+        let width = bbox.2 * self.scale.0;
+        let height = bbox.3 * self.scale.1;
+        self.items.push(json!({
+            "index": self.items.len(),
+            "type": item_type,
+            "content": content,
+            "bbox": {
+                "left": left,
+                "top": top,
+                "right": left + width,
+                "bottom": top + height,
+                "width": width,
+                "height": height,
+                "coord_origin": "TOPLEFT",
+            },
+            "page": self.page_number,
+            "confidence": confidence,
+            "attributes": {},
+        }));
+    }
+
+    fn finish(self) -> Value {
+        json!({
+            "pages": self.pages,
+            "items": self.items,
+        })
+    }
+}
+
+fn parse_xml(contents: &str) -> Result<roxmltree::Document<'_>> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(contents, options).context("Failed to parse XML")
+}
+
+// ---------------------------------------------------------------------------
+// hOCR
+// ---------------------------------------------------------------------------
+
+/// Read a `key v1 v2 ...` property out of an hOCR title attribute
+fn hocr_property<'a>(title: &'a str, key: &str) -> Option<Vec<&'a str>> {
+    title.split(';')
+        .map(|part| part.trim())
+        .find(|part| part.split_whitespace().next() == Some(key))
+        .map(|part| part.split_whitespace().skip(1).collect())
+}
+
+fn hocr_bbox(title: &str) -> Option<(f64, f64, f64, f64)> {
+    let values: Vec<f64> = hocr_property(title, "bbox")?
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    if values.len() == 4 {
+        Some((values[0], values[1], values[2] - values[0], values[3] - values[1]))
+    } else {
+        None
+    }
+}
+
+fn has_class(node: &roxmltree::Node, class: &str) -> bool {
+    node.attribute("class")
+        .map(|c| c.split_whitespace().any(|c| c == class))
+        .unwrap_or(false)
+}
+
+fn import_hocr(contents: &str, page_sizes: &[(f64, f64)]) -> Result<Value> {
+    let doc = parse_xml(contents)?;
+    let mut builder = DocumentBuilder::new(page_sizes);
+
+    const LINE_CLASSES: [&str; 5] = ["ocr_line", "ocrx_line", "ocr_header", "ocr_caption", "ocr_textfloat"];
+
+    for page in doc.descendants().filter(|n| has_class(n, "ocr_page")) {
+        let page_bbox = page.attribute("title").and_then(hocr_bbox).unwrap_or((0.0, 0.0, 0.0, 0.0));
+        // hOCR is in image pixels; without a PDF page assume 300 DPI
+        builder.begin_page(
+            (page_bbox.2, page_bbox.3),
+            (page_bbox.2 * 72.0 / 300.0, page_bbox.3 * 72.0 / 300.0),
+        );
+
+        for line in page.descendants().filter(|n| LINE_CLASSES.iter().any(|c| has_class(n, c))) {
+            let Some(bbox) = line.attribute("title").and_then(hocr_bbox) else { continue };
+
+            let mut words = Vec::new();
+            let mut confidences = Vec::new();
+            for word in line.descendants().filter(|n| has_class(n, "ocrx_word")) {
+                let text: String = word.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect();
+                if !text.trim().is_empty() {
+                    words.push(text.trim().to_string());
+                }
+                if let Some(conf) = word.attribute("title")
+                    .and_then(|t| hocr_property(t, "x_wconf"))
+                    .and_then(|v| v.first().and_then(|v| v.parse::<f64>().ok()))
+                {
+                    confidences.push(conf / 100.0);
+                }
+            }
+
+            // Lines without word spans just carry their text directly
+            let content = if words.is_empty() {
+                line.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect::<String>().trim().to_string()
+            } else {
+                words.join(" ")
+            };
+            let confidence = if confidences.is_empty() {
+                1.0
+            } else {
+                confidences.iter().sum::<f64>() / confidences.len() as f64
+            };
+            let item_type = if has_class(&line, "ocr_header") { "SectionHeaderItem" } else { "TextItem" };
+
+            builder.add_item(item_type, content, bbox, confidence);
+        }
+    }
+
+    if builder.pages.is_empty() {
+        return Err(anyhow!("No ocr_page elements found in hOCR file"));
+    }
+    Ok(builder.finish())
+}
+
+// ---------------------------------------------------------------------------
+// ALTO
+// ---------------------------------------------------------------------------
+
+fn alto_f64(node: &roxmltree::Node, attr: &str) -> f64 {
+    node.attribute(attr).and_then(|v| v.parse().ok()).unwrap_or(0.0)
+}
+
+fn import_alto(contents: &str, page_sizes: &[(f64, f64)]) -> Result<Value> {
+    let doc = parse_xml(contents)?;
+    let mut builder = DocumentBuilder::new(page_sizes);
+
+    // Conversion from the declared measurement unit to PDF points
+    let unit = doc.descendants()
+        .find(|n| n.tag_name().name() == "MeasurementUnit")
+        .and_then(|n| n.text())
+        .unwrap_or("pixel")
+        .trim()
+        .to_string();
+    let points_per_unit = match unit.as_str() {
+        "mm10" => 72.0 / 254.0,
+        "inch1200" => 72.0 / 1200.0,
+        _ => 72.0 / 300.0, // pixel, assume 300 DPI scans
+    };
+
+    for page in doc.descendants().filter(|n| n.tag_name().name() == "Page") {
+        let size = (alto_f64(&page, "WIDTH"), alto_f64(&page, "HEIGHT"));
+        builder.begin_page(size, (size.0 * points_per_unit, size.1 * points_per_unit));
+
+        for line in page.descendants().filter(|n| n.tag_name().name() == "TextLine") {
+            let strings: Vec<roxmltree::Node> = line.children()
+                .filter(|n| n.tag_name().name() == "String")
+                .collect();
+            let content = strings.iter()
+                .filter_map(|s| s.attribute("CONTENT"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let confidences: Vec<f64> = strings.iter()
+                .filter_map(|s| s.attribute("WC").and_then(|v| v.parse().ok()))
+                .collect();
+            let confidence = if confidences.is_empty() {
+                1.0
+            } else {
+                confidences.iter().sum::<f64>() / confidences.len() as f64
+            };
+            let bbox = (
+                alto_f64(&line, "HPOS"),
+                alto_f64(&line, "VPOS"),
+                alto_f64(&line, "WIDTH"),
+                alto_f64(&line, "HEIGHT"),
+            );
+
+            builder.add_item("TextItem", content, bbox, confidence);
+        }
+    }
+
+    if builder.pages.is_empty() {
+        return Err(anyhow!("No Page elements found in ALTO file"));
+    }
+    Ok(builder.finish())
+}
+
+// ---------------------------------------------------------------------------
+// AWS Textract
+// ---------------------------------------------------------------------------
+
+fn import_textract(contents: &str, page_sizes: &[(f64, f64)]) -> Result<Value> {
+    let root: Value = serde_json::from_str(contents).context("Failed to parse Textract JSON")?;
+    let blocks = root.get("Blocks")
+        .and_then(|b| b.as_array())
+        .ok_or_else(|| anyhow!("Textract JSON has no Blocks array"))?;
+
+    let by_id: std::collections::HashMap<&str, &Value> = blocks.iter()
+        .filter_map(|b| b.get("Id").and_then(|id| id.as_str()).map(|id| (id, b)))
+        .collect();
+
+    // Textract geometry is normalized (0..1), so the source page size is 1x1
+    let page_count = blocks.iter()
+        .filter(|b| b["BlockType"] == "PAGE")
+        .count()
+        .max(1);
+    let mut builder = DocumentBuilder::new(page_sizes);
+
+    for page in 1..=page_count {
+        builder.begin_page((1.0, 1.0), (612.0, 792.0));
+
+        for block in blocks.iter().filter(|b| b.get("Page").and_then(|p| p.as_u64()).unwrap_or(1) as usize == page) {
+            let item_type = match block["BlockType"].as_str() {
+                Some("LINE") => "TextItem",
+                Some("TABLE") => "TableItem",
+                _ => continue,
+            };
+
+            let bbox = &block["Geometry"]["BoundingBox"];
+            let bbox = (
+                bbox["Left"].as_f64().unwrap_or(0.0),
+                bbox["Top"].as_f64().unwrap_or(0.0),
+                bbox["Width"].as_f64().unwrap_or(0.0),
+                bbox["Height"].as_f64().unwrap_or(0.0),
+            );
+
+            let content = if item_type == "TableItem" {
+                textract_table_text(block, &by_id)
+            } else {
+                block["Text"].as_str().unwrap_or("").to_string()
+            };
+            let confidence = block["Confidence"].as_f64().unwrap_or(100.0) / 100.0;
+
+            builder.add_item(item_type, content, bbox, confidence);
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+// ---------------------------------------------------------------------------
+// Label Studio
+// ---------------------------------------------------------------------------
+
+/// Regions of a task: the latest annotation that wasn't skipped, else the
+/// first prediction, with the prediction's score as confidence
+fn label_studio_results(task: &Value) -> (Vec<Value>, f64) {
+    let annotation = task["annotations"].as_array().into_iter().flatten()
+        .rfind(|a| !a["was_cancelled"].as_bool().unwrap_or(false));
+    if let Some(annotation) = annotation {
+        return (annotation["result"].as_array().cloned().unwrap_or_default(), 1.0);
+    }
+    let prediction = task["predictions"].as_array().and_then(|p| p.first());
+    (
+        prediction.and_then(|p| p["result"].as_array()).cloned().unwrap_or_default(),
+        prediction.and_then(|p| p["score"].as_f64()).unwrap_or(1.0),
+    )
+}
+
+fn import_label_studio(contents: &str, page_sizes: &[(f64, f64)]) -> Result<Value> {
+    let root: Value = serde_json::from_str(contents).context("Failed to parse Label Studio JSON")?;
+    let tasks = match root {
+        Value::Array(tasks) => tasks,
+        task => vec![task],
+    };
+    // Tasks are pages, in `data.page` order where given
+    let mut pages: Vec<(usize, &Value)> = tasks.iter().enumerate()
+        .map(|(index, task)| (task["data"]["page"].as_u64().map_or(index + 1, |p| p as usize), task))
+        .collect();
+    pages.sort_by_key(|(page, _)| *page);
+    let page_count = pages.last().map_or(0, |(page, _)| *page);
+    if page_count == 0 {
+        return Err(anyhow!("No tasks found in Label Studio JSON"));
+    }
+
+    // Boxes are in percent of the image, so the source page is 100x100
+    let mut builder = DocumentBuilder::new(page_sizes);
+    for page in 1..=page_count {
+        let task = pages.iter().find(|(p, _)| *p == page).map(|(_, task)| *task);
+        let (results, confidence) = task.map(label_studio_results).unwrap_or_default();
+        // Points from the image size, for when there's no PDF to take them from
+        let natural = results.first()
+            .and_then(|r| Some((r["original_width"].as_f64()?, r["original_height"].as_f64()?)))
+            .map_or((612.0, 792.0), |(w, h)| (w / crate::annotations::IMAGE_SCALE as f64, h / crate::annotations::IMAGE_SCALE as f64));
+        builder.begin_page((100.0, 100.0), natural);
+
+        // A region's label and text come as separate results sharing its ID
+        let mut regions: Vec<(&str, &Value, Option<&str>, Option<String>)> = Vec::new();
+        for result in &results {
+            let Some(id) = result["id"].as_str() else { continue };
+            let index = match regions.iter().position(|(region, ..)| *region == id) {
+                Some(index) => index,
+                None => {
+                    regions.push((id, &result["value"], None, None));
+                    regions.len() - 1
+                }
+            };
+            let value = &result["value"];
+            if let Some(label) = value["rectanglelabels"].as_array().or(value["labels"].as_array()).and_then(|l| l.first()).and_then(|l| l.as_str()) {
+                regions[index].2 = Some(label);
+            }
+            if let Some(lines) = value["text"].as_array() {
+                regions[index].3 = Some(lines.iter().filter_map(|l| l.as_str()).collect::<Vec<_>>().join("\n"));
+            }
+        }
+        for (_, value, label, text) in regions {
+            let item_type = label.and_then(crate::label_studio::item_type_for_label).unwrap_or(crate::types::ItemType::Text);
+            let bbox = (
+                value["x"].as_f64().unwrap_or(0.0),
+                value["y"].as_f64().unwrap_or(0.0),
+                value["width"].as_f64().unwrap_or(0.0),
+                value["height"].as_f64().unwrap_or(0.0),
+            );
+            builder.add_item(item_type.json_type(), text.unwrap_or_default(), bbox, confidence);
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+/// Flatten a TABLE block into tab-separated rows using its CELL children
+fn textract_table_text(table: &Value, by_id: &std::collections::HashMap<&str, &Value>) -> String {
+    let child_ids = |block: &Value| -> Vec<String> {
+        block["Relationships"].as_array()
+            .map(|rels| rels.iter()
+                .filter(|r| r["Type"] == "CHILD")
+                .filter_map(|r| r["Ids"].as_array())
+                .flatten()
+                .filter_map(|id| id.as_str().map(|s| s.to_string()))
+                .collect())
+            .unwrap_or_default()
+    };
+
+    let mut cells: Vec<(u64, u64, String)> = Vec::new();
+    for cell_id in child_ids(table) {
+        let Some(cell) = by_id.get(cell_id.as_str()) else { continue };
+        if cell["BlockType"] != "CELL" {
+            continue;
+        }
+        let text = child_ids(cell).iter()
+            .filter_map(|id| by_id.get(id.as_str()))
+            .filter_map(|w| w["Text"].as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        cells.push((
+            cell["RowIndex"].as_u64().unwrap_or(0),
+            cell["ColumnIndex"].as_u64().unwrap_or(0),
+            text,
+        ));
+    }
+    cells.sort_by_key(|(row, col, _)| (*row, *col));
+
+    let mut rows: Vec<String> = Vec::new();
+    let mut current_row = None;
+    for (row, _, text) in cells {
+        if current_row != Some(row) {
+            rows.push(String::new());
+            current_row = Some(row);
+        } else if let Some(last) = rows.last_mut() {
+            last.push('\t');
+        }
+        if let Some(last) = rows.last_mut() {
+            last.push_str(&text);
+        }
+    }
+    rows.join("\n")
+}
@@ -0,0 +1,421 @@
+//! Inputs other than PDFs
+//!
+//! Scanned images (PNG, single- or multi-page TIFF) are wrapped into a PDF
+//! with one page per image, so extraction runs OCR on them like on any scanned
+//! PDF. Emails (`.eml`) and Outlook messages (`.msg`) are opened through their
+//! PDF attachments. Everything is written to a temp folder and opened from
+//! there.
+
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use image::DynamicImage;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType;
+
+/// Resolution assumed for images that don't record one
+pub const DEFAULT_DPI: f32 = 300.0;
+
+/// Resolutions outside this range are treated as missing
+const DPI_RANGE: std::ops::RangeInclusive<f32> = 36.0..=2400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    Pdf,
+    /// A PNG or TIFF scan
+    Scan,
+    /// A MIME email
+    Email,
+    /// An Outlook message
+    OutlookMessage,
+}
+
+impl InputKind {
+    /// File extensions the Open dialog accepts
+    pub const EXTENSIONS: [&'static str; 6] = ["pdf", "png", "tif", "tiff", "eml", "msg"];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(InputKind::Pdf),
+            "png" | "tif" | "tiff" => Some(InputKind::Scan),
+            "eml" => Some(InputKind::Email),
+            "msg" => Some(InputKind::OutlookMessage),
+            _ => None,
+        }
+    }
+}
+
+/// Where converted scans and extracted attachments are written
+pub fn default_output_dir() -> PathBuf {
+    crate::storage::cache_dir()
+}
+
+/// The PDFs to open for an input: the file itself, its scans as one PDF, or
+/// its PDF attachments written to `out_dir`
+pub fn prepare(path: &Path, out_dir: &Path) -> Result<Vec<PathBuf>> {
+    let kind = InputKind::from_path(path)
+        .ok_or_else(|| anyhow!("Unsupported file type: {}", path.display()))?;
+    if kind == InputKind::Pdf {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "input".to_string());
+    std::fs::create_dir_all(out_dir).with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if kind == InputKind::Scan {
+        let pages = read_scans(path, &bytes)?;
+        let pdf_path = out_dir.join(format!("{}.pdf", stem));
+        std::fs::write(&pdf_path, images_to_pdf(&pages)?)
+            .with_context(|| format!("Failed to write {}", pdf_path.display()))?;
+        return Ok(vec![pdf_path]);
+    }
+
+    let attachments = match kind {
+        InputKind::Email => email_pdf_attachments(&bytes)?,
+        _ => msg_pdf_attachments(&bytes)?,
+    };
+    if attachments.is_empty() {
+        bail!("No PDF attachments in {}", path.display());
+    }
+    let mut used = HashSet::new();
+    let mut paths = Vec::new();
+    for (index, attachment) in attachments.iter().enumerate() {
+        let name = attachment_file_name(&attachment.name, index);
+        let mut file_name = format!("{}_{}", stem, name);
+        let mut copy = 1;
+        while !used.insert(file_name.clone()) {
+            copy += 1;
+            file_name = format!("{}_{}_{}", stem, copy, name);
+        }
+        let pdf_path = out_dir.join(file_name);
+        std::fs::write(&pdf_path, &attachment.bytes)
+            .with_context(|| format!("Failed to write {}", pdf_path.display()))?;
+        paths.push(pdf_path);
+    }
+    Ok(paths)
+}
+
+/// A safe file name for an attachment: its own name without any folders, else a numbered one
+fn attachment_file_name(name: &str, index: usize) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or("").trim();
+    if name.is_empty() || name.starts_with('.') {
+        format!("attachment_{}.pdf", index + 1)
+    } else if name.to_lowercase().ends_with(".pdf") {
+        name.to_string()
+    } else {
+        format!("{}.pdf", name)
+    }
+}
+
+/// One scanned page and the resolution it was scanned at
+#[derive(Debug, Clone)]
+pub struct ScanPage {
+    pub image: DynamicImage,
+    pub dpi: f32,
+}
+
+/// Every page of a PNG or TIFF
+pub fn read_scans(path: &Path, bytes: &[u8]) -> Result<Vec<ScanPage>> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if extension == "tif" || extension == "tiff" {
+        return read_tiff(bytes);
+    }
+    let image = image::load_from_memory(bytes).with_context(|| format!("Failed to decode {}", path.display()))?;
+    Ok(vec![ScanPage { image, dpi: DEFAULT_DPI }])
+}
+
+/// Every frame of a TIFF; the image crate only reads the first
+pub fn read_tiff(bytes: &[u8]) -> Result<Vec<ScanPage>> {
+    let mut decoder = Decoder::new(Cursor::new(bytes)).context("Not a TIFF file")?;
+    let mut pages = Vec::new();
+    loop {
+        let page = pages.len() + 1;
+        let image = tiff_frame(&mut decoder).with_context(|| format!("Failed to decode TIFF page {}", page))?;
+        pages.push(ScanPage { image, dpi: tiff_dpi(&mut decoder) });
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().with_context(|| format!("Failed to read TIFF page {}", page + 1))?;
+    }
+    Ok(pages)
+}
+
+fn tiff_frame(decoder: &mut Decoder<Cursor<&[u8]>>) -> Result<DynamicImage> {
+    let (width, height) = decoder.dimensions()?;
+    let color = decoder.colortype()?;
+    // Photometric interpretation 0: fax-style scans where 0 is white
+    let white_is_zero = decoder.get_tag_unsigned::<u16>(Tag::PhotometricInterpretation).ok() == Some(0);
+    let size_error = || anyhow!("TIFF page data doesn't match its {}×{} size", width, height);
+
+    let mut image = match (color, decoder.read_image()?) {
+        (ColorType::Gray(1), DecodingResult::U8(packed)) => {
+            // Rows are packed eight pixels to a byte, set bits are white
+            let row_bytes = (width as usize).div_ceil(8);
+            let pixels = (0..height as usize)
+                .flat_map(|y| (0..width as usize).map(move |x| (y, x)))
+                .map(|(y, x)| packed.get(y * row_bytes + x / 8).map_or(0, |byte| if byte & (0x80 >> (x % 8)) != 0 { 255 } else { 0 }))
+                .collect();
+            DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, pixels).ok_or_else(size_error)?)
+        }
+        (ColorType::Gray(8), DecodingResult::U8(data)) => DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, data).ok_or_else(size_error)?),
+        (ColorType::Gray(16), DecodingResult::U16(data)) => DynamicImage::ImageLuma16(image::ImageBuffer::from_raw(width, height, data).ok_or_else(size_error)?),
+        (ColorType::GrayA(8), DecodingResult::U8(data)) => DynamicImage::ImageLumaA8(image::ImageBuffer::from_raw(width, height, data).ok_or_else(size_error)?),
+        (ColorType::RGB(8), DecodingResult::U8(data)) => DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, data).ok_or_else(size_error)?),
+        (ColorType::RGB(16), DecodingResult::U16(data)) => DynamicImage::ImageRgb16(image::ImageBuffer::from_raw(width, height, data).ok_or_else(size_error)?),
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, data).ok_or_else(size_error)?),
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => DynamicImage::ImageRgba16(image::ImageBuffer::from_raw(width, height, data).ok_or_else(size_error)?),
+        (color, _) => bail!("Unsupported TIFF color type {:?}", color),
+    };
+    if white_is_zero && matches!(color, ColorType::Gray(_)) {
+        image.invert();
+    }
+    Ok(image)
+}
+
+/// Horizontal resolution of the current TIFF frame, in dots per inch
+fn tiff_dpi(decoder: &mut Decoder<Cursor<&[u8]>>) -> f32 {
+    let Ok(Some(tiff::decoder::ifd::Value::Rational(numerator, denominator))) = decoder.find_tag(Tag::XResolution) else {
+        return DEFAULT_DPI;
+    };
+    if denominator == 0 {
+        return DEFAULT_DPI;
+    }
+    // Resolution unit 3 is centimeters; 2, the default, inches
+    let per_cm = decoder.get_tag_unsigned::<u16>(Tag::ResolutionUnit).ok() == Some(3);
+    let dpi = numerator as f32 / denominator as f32 * if per_cm { 2.54 } else { 1.0 };
+    if DPI_RANGE.contains(&dpi) { dpi } else { DEFAULT_DPI }
+}
+
+/// A PDF with each image as a full page, sized by its resolution.
+/// Grayscale images stay grayscale; alpha is dropped.
+pub fn images_to_pdf(pages: &[ScanPage]) -> Result<Vec<u8>> {
+    if pages.is_empty() {
+        bail!("No pages to convert");
+    }
+    // Objects: catalog, page tree, then page, content stream and image for every page
+    let object_count = 2 + pages.len() * 3;
+    let mut pdf: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(object_count);
+
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 3 + i * 3)).collect();
+    offsets.push(pdf.len());
+    pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+    offsets.push(pdf.len());
+    pdf.extend_from_slice(format!("2 0 obj\n<< /Type /Pages /Kids [{}] /Count {} >>\nendobj\n", kids.join(" "), pages.len()).as_bytes());
+
+    for (index, page) in pages.iter().enumerate() {
+        let first = 3 + index * 3;
+        let (width, height) = (page.image.width(), page.image.height());
+        let points = |pixels: u32| pixels as f32 * 72.0 / page.dpi;
+        let (page_width, page_height) = (points(width), points(height));
+
+        let grayscale = page.image.color().channel_count() <= 2;
+        let pixels = if grayscale { page.image.to_luma8().into_raw() } else { page.image.to_rgb8().into_raw() };
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&pixels)?;
+        let compressed = encoder.finish()?;
+
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!(
+            "{} 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>\nendobj\n",
+            first, page_width, page_height, first + 2, first + 1,
+        ).as_bytes());
+
+        let content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", page_width, page_height);
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n<< /Length {} >>\nstream\n{}\nendstream\nendobj\n", first + 1, content.len(), content).as_bytes());
+
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!(
+            "{} 0 obj\n<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+            first + 2, width, height, if grayscale { "DeviceGray" } else { "DeviceRGB" }, compressed.len(),
+        ).as_bytes());
+        pdf.extend_from_slice(&compressed);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", object_count + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", object_count + 1, xref).as_bytes());
+    Ok(pdf)
+}
+
+/// A file attached to an email or message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// PDF attachments of a MIME email, including those of forwarded messages
+pub fn email_pdf_attachments(raw: &[u8]) -> Result<Vec<Attachment>> {
+    let text = String::from_utf8_lossy(raw).replace("\r\n", "\n");
+    let mut attachments = Vec::new();
+    collect_email_pdfs(&text, &mut attachments)?;
+    Ok(attachments)
+}
+
+fn collect_email_pdfs(part: &str, out: &mut Vec<Attachment>) -> Result<()> {
+    let (headers, body) = split_headers(part);
+    let header = |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+    let content_type = header("content-type").unwrap_or("text/plain");
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+
+    if mime.starts_with("multipart/") {
+        if let Some(boundary) = header_param(content_type, "boundary") {
+            for sub_part in split_multipart(body, &boundary) {
+                collect_email_pdfs(sub_part, out)?;
+            }
+        }
+        return Ok(());
+    }
+    if mime == "message/rfc822" {
+        return collect_email_pdfs(body, out);
+    }
+
+    let name = header("content-disposition").and_then(|d| header_param(d, "filename"))
+        .or_else(|| header_param(content_type, "name"))
+        .unwrap_or_default();
+    if mime != "application/pdf" && !name.to_lowercase().ends_with(".pdf") {
+        return Ok(());
+    }
+    let bytes = match header("content-transfer-encoding").map(|e| e.trim().to_lowercase()).as_deref() {
+        Some("base64") => {
+            let encoded: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+            base64::engine::general_purpose::STANDARD.decode(encoded)
+                .with_context(|| format!("Invalid base64 in attachment {}", name))?
+        }
+        Some("quoted-printable") => quoted_printable(body),
+        _ => body.as_bytes().to_vec(),
+    };
+    out.push(Attachment { name, bytes });
+    Ok(())
+}
+
+/// Header fields, unfolded, and the body after the first blank line
+fn split_headers(part: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = part.split_once("\n\n").unwrap_or((part, ""));
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+/// A `key=value` parameter of a header value, unquoted. RFC 2231 `key*=charset''value` is read as is.
+fn header_param(value: &str, key: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        let name = name.trim().to_lowercase();
+        let value = value.trim();
+        if name == key {
+            Some(value.trim_matches('"').to_string())
+        } else if name == format!("{}*", key) {
+            let value = value.rsplit("''").next().unwrap_or(value);
+            Some(value.trim_matches('"').replace("%20", " "))
+        } else {
+            None
+        }
+    })
+}
+
+/// The parts between `--boundary` lines, up to the closing `--boundary--`
+fn split_multipart<'a>(body: &'a str, boundary: &str) -> Vec<&'a str> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed.starts_with(&delimiter) {
+            if let Some(start) = start {
+                parts.push(&body[start..offset]);
+            }
+            if trimmed == format!("{}--", delimiter) {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+fn quoted_printable(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'=' if bytes.get(i + 1) == Some(&b'\n') => i += 2,
+            b'=' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => out.push(byte),
+                    None => out.extend_from_slice(&bytes[i..i + 3]),
+                }
+                i += 3;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// MAPI property streams of an attachment storage
+const ATTACH_LONG_FILENAME: &str = "__substg1.0_3707001F";
+const ATTACH_FILENAME: &str = "__substg1.0_3704001F";
+const ATTACH_MIME_TAG: &str = "__substg1.0_370E001F";
+const ATTACH_DATA: &str = "__substg1.0_37010102";
+
+/// PDF attachments of an Outlook message
+pub fn msg_pdf_attachments(bytes: &[u8]) -> Result<Vec<Attachment>> {
+    let mut file = cfb::CompoundFile::open(Cursor::new(bytes)).context("Not an Outlook message")?;
+    let storages: Vec<PathBuf> = file.read_root_storage()
+        .filter(|entry| entry.is_storage() && entry.name().starts_with("__attach_version1.0_"))
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+
+    let mut attachments = Vec::new();
+    for storage in storages {
+        let mut read = |name: &str| -> Option<Vec<u8>> {
+            let mut stream = file.open_stream(storage.join(name)).ok()?;
+            let mut data = Vec::new();
+            stream.read_to_end(&mut data).ok()?;
+            Some(data)
+        };
+        let Some(data) = read(ATTACH_DATA) else { continue };
+        let name = read(ATTACH_LONG_FILENAME).or_else(|| read(ATTACH_FILENAME)).map(|raw| utf16(&raw)).unwrap_or_default();
+        let mime = read(ATTACH_MIME_TAG).map(|raw| utf16(&raw)).unwrap_or_default();
+        if mime.eq_ignore_ascii_case("application/pdf") || name.to_lowercase().ends_with(".pdf") || data.starts_with(b"%PDF") {
+            attachments.push(Attachment { name, bytes: data });
+        }
+    }
+    Ok(attachments)
+}
+
+/// A UTF-16LE MAPI string, without its terminator
+fn utf16(raw: &[u8]) -> String {
+    let units: Vec<u16> = raw.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&units).trim_end_matches('\0').to_string()
+}
@@ -0,0 +1,116 @@
+//! Single-instance mode
+//!
+//! The first Chonker3 listens on a localhost port. A later launch with files
+//! finds the port taken, hands its files and `chonker3://` links to the
+//! running instance over the socket as one line of JSON, and exits once they're
+//! acknowledged, so opening PDFs from the file manager doesn't pile up windows.
+//! `--new-window` skips this and starts a separate instance.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PORT: u16 = 47471;
+
+/// How long a second instance waits for the running one to answer
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenRequest {
+    files: Vec<PathBuf>,
+    /// Deep links, as URIs
+    #[serde(default)]
+    links: Vec<String>,
+}
+
+/// What a launch should do
+pub enum Launch {
+    /// No other instance is running: serve requests on this listener
+    Primary(TcpListener),
+    /// The files went to the running instance; exit
+    Forwarded,
+    /// Run on our own, without serving requests
+    Standalone,
+}
+
+/// Become the primary instance on `port`, or forward `files` and deep `links`
+/// to the one that already is. Only a launch with files or links is
+/// forwarded; without any it opens a window of its own.
+pub fn claim(port: u16, files: &[PathBuf], links: &[String]) -> Launch {
+    match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => Launch::Primary(listener),
+        Err(_) if files.is_empty() && links.is_empty() => Launch::Standalone,
+        Err(_) => match forward(port, files, links) {
+            Ok(()) => Launch::Forwarded,
+            Err(e) => {
+                log::warn!("Couldn't reach the running instance, opening a new window: {:#}", e);
+                Launch::Standalone
+            }
+        },
+    }
+}
+
+/// Send files and deep links to the instance listening on `port` and wait for
+/// it to accept them
+pub fn forward(port: u16, files: &[PathBuf], links: &[String]) -> Result<()> {
+    // The running instance may have another working directory
+    let files = files.iter()
+        .map(|path| path.canonicalize().with_context(|| format!("Can't find {}", path.display())))
+        .collect::<Result<Vec<_>>>()?;
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&address, FORWARD_TIMEOUT)
+        .with_context(|| format!("Could not connect to port {}", port))?;
+    stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+    let mut line = serde_json::to_string(&OpenRequest { files, links: links.to_vec() })?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).context("No answer from the running instance")?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => Err(anyhow!("The running instance answered {:?}", other)),
+    }
+}
+
+/// Open requests from later launches, received on a background thread
+pub struct InstanceServer {
+    requests: Receiver<OpenRequest>,
+}
+
+impl InstanceServer {
+    /// Serve requests on `listener`, calling `wake` when one arrives
+    pub fn start(listener: TcpListener, wake: impl Fn() + Send + 'static) -> Self {
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = serve(stream, &tx) {
+                    log::warn!("Ignoring an open request: {:#}", e);
+                }
+                wake();
+            }
+        });
+        Self { requests: rx }
+    }
+
+    /// Files and deep links sent since the last call, per request in the
+    /// order they arrived
+    pub fn take_requests(&self) -> Vec<(Vec<PathBuf>, Vec<String>)> {
+        self.requests.try_iter().map(|request| (request.files, request.links)).collect()
+    }
+}
+
+fn serve(stream: TcpStream, requests: &Sender<OpenRequest>) -> Result<()> {
+    stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let request: OpenRequest = serde_json::from_str(&line).context("Malformed open request")?;
+    requests.send(request)?;
+    writer.write_all(b"ok\n")?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Background jobs with progress, ETA and cancellation
+//!
+//! Long exports run on a worker thread. The worker reports per-step progress
+//! through its `JobHandle` and checks `is_cancelled` between steps; the UI
+//! polls the `Job` each frame to draw a progress dialog.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, Default)]
+struct Progress {
+    done: usize,
+    total: usize,
+    /// What the worker is doing right now, e.g. "Page 3"
+    step: String,
+}
+
+/// The worker's side of a job
+#[derive(Clone)]
+pub struct JobHandle {
+    progress: Arc<Mutex<Progress>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    pub fn set_total(&self, total: usize) {
+        self.progress.lock().unwrap().total = total;
+    }
+
+    /// Start a step, failing if the user cancelled
+    pub fn begin_step(&self, step: impl Into<String>) -> Result<()> {
+        if self.is_cancelled() {
+            bail!("Cancelled");
+        }
+        self.progress.lock().unwrap().step = step.into();
+        Ok(())
+    }
+
+    pub fn finish_step(&self) {
+        self.progress.lock().unwrap().done += 1;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The UI's side of a job
+pub struct Job {
+    pub title: String,
+    started: Instant,
+    handle: JobHandle,
+    result: Arc<Mutex<Option<Result<String>>>>,
+}
+
+impl Job {
+    /// Run `work` on a worker thread. It returns a summary for the user.
+    pub fn spawn(title: impl Into<String>, work: impl FnOnce(&JobHandle) -> Result<String> + Send + 'static) -> Self {
+        let handle = JobHandle {
+            progress: Arc::default(),
+            cancelled: Arc::default(),
+        };
+        let result: Arc<Mutex<Option<Result<String>>>> = Arc::default();
+
+        let worker_handle = handle.clone();
+        let worker_result = result.clone();
+        std::thread::spawn(move || {
+            let outcome = work(&worker_handle);
+            *worker_result.lock().unwrap() = Some(outcome);
+        });
+
+        Self {
+            title: title.into(),
+            started: Instant::now(),
+            handle,
+            result,
+        }
+    }
+
+    /// (done, total, current step)
+    pub fn progress(&self) -> (usize, usize, String) {
+        let progress = self.handle.progress.lock().unwrap();
+        (progress.done, progress.total, progress.step.clone())
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let (done, total, _) = self.progress();
+        eta(self.started.elapsed(), done, total)
+    }
+
+    pub fn cancel(&self) {
+        self.handle.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.handle.is_cancelled()
+    }
+
+    /// The worker's outcome once it has finished
+    pub fn take_result(&self) -> Option<Result<String>> {
+        self.result.lock().unwrap().take()
+    }
+}
+
+/// Remaining time, extrapolated from the average time per finished step
+pub fn eta(elapsed: Duration, done: usize, total: usize) -> Option<Duration> {
+    if done == 0 || total == 0 {
+        return None;
+    }
+    let remaining = total.saturating_sub(done) as u32;
+    Some(elapsed / done as u32 * remaining)
+}
+
+/// "1h 02m", "3m 05s" or "12s"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
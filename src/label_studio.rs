@@ -0,0 +1,92 @@
+//! Label Studio tasks
+//!
+//! Each page becomes a Label Studio task over its page image, with the items
+//! as rectangles labeled by type and their text as a per-region transcription,
+//! the layout of Label Studio's OCR template. Tasks annotated in Label Studio
+//! come back in through `importers`, so corrections can go both ways.
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::annotations::{image_size, PageAnnotations, IMAGE_SCALE};
+use crate::document;
+use crate::patch::EditPatch;
+use crate::types::ItemType;
+
+/// Names the labeling config gives the image and its controls
+pub const IMAGE_NAME: &str = "image";
+pub const LABEL_NAME: &str = "label";
+pub const TEXT_NAME: &str = "transcription";
+
+/// The type a Label Studio label stands for, by label or extraction type name
+pub fn item_type_for_label(label: &str) -> Option<ItemType> {
+    ItemType::ALL.into_iter()
+        .find(|t| t.label().eq_ignore_ascii_case(label) || t.json_type().eq_ignore_ascii_case(label))
+}
+
+/// Labeling config for a project that takes these tasks
+pub fn labeling_config() -> String {
+    let labels: String = ItemType::ALL.iter()
+        .map(|t| format!("    <Label value=\"{}\"/>\n", t.label()))
+        .collect();
+    format!(
+        "<View>\n  <Image name=\"{image}\" value=\"${image}\"/>\n  <RectangleLabels name=\"{label}\" toName=\"{image}\">\n{labels}  </RectangleLabels>\n  <TextArea name=\"{text}\" toName=\"{image}\" editable=\"true\" perRegion=\"true\" required=\"false\" rows=\"3\"/>\n</View>\n",
+        image = IMAGE_NAME, label = LABEL_NAME, text = TEXT_NAME, labels = labels,
+    )
+}
+
+/// One task per page. Boxes are in percent of the page as Label Studio keeps
+/// them; each item's rectangle and text share its ID.
+pub fn tasks(data: &Value, edits: &EditPatch, pages: &[PageAnnotations]) -> Value {
+    json!(pages.iter().map(|page| {
+        let (width, height) = page.size;
+        let (original_width, original_height) = image_size(page.size, IMAGE_SCALE);
+        let mut result = Vec::new();
+        for item in document::edited_page_items(data, page.page, edits) {
+            let value = json!({
+                "x": item.bbox.left / width * 100.0,
+                "y": item.bbox.top / height * 100.0,
+                "width": item.bbox.width / width * 100.0,
+                "height": item.bbox.height / height * 100.0,
+                "rotation": 0,
+            });
+            let region = |from_name: &str, kind: &str, key: &str, content: Value| {
+                let mut value = value.clone();
+                value[key] = content;
+                json!({
+                    "id": item.id,
+                    "from_name": from_name,
+                    "to_name": IMAGE_NAME,
+                    "type": kind,
+                    "original_width": original_width,
+                    "original_height": original_height,
+                    "image_rotation": 0,
+                    "value": value,
+                })
+            };
+            result.push(region(LABEL_NAME, "rectanglelabels", "rectanglelabels", json!([item.item_type.label()])));
+            result.push(region(TEXT_NAME, "textarea", "text", json!([item.content])));
+        }
+        json!({
+            "data": {
+                IMAGE_NAME: page.image,
+                "page": page.page + 1,
+                "source_file": edits.source_file,
+            },
+            "annotations": [{ "result": result }],
+        })
+    }).collect::<Vec<_>>())
+}
+
+/// Write the tasks and the labeling config for page images already in `dir`;
+/// returns the number of tasks
+pub fn write(dir: &Path, data: &Value, edits: &EditPatch, pages: &[PageAnnotations], stem: &str) -> Result<usize> {
+    let write_file = |name: String, contents: String| {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    };
+    write_file(format!("{}_label_studio.json", stem), serde_json::to_string_pretty(&tasks(data, edits, pages))?)?;
+    write_file("label_studio_config.xml".to_string(), labeling_config())?;
+    Ok(pages.len())
+}
@@ -0,0 +1,64 @@
+//! Command-line arguments
+//!
+//! `chonker3 [FILE...]` opens the first file and adds the rest to the
+//! workspace. File managers launch the app this way when a PDF is opened with
+//! it, and browsers with a `chonker3://` link (see `deep_link`); the files in
+//! `packaging/` register it for both on each platform. With Chonker3 already
+//! running, the files and links open there instead (see `instance`).
+
+use std::path::PathBuf;
+use anyhow::{bail, Result};
+
+use crate::deep_link::{self, DeepLink};
+use crate::inputs::InputKind;
+
+pub const USAGE: &str = "Usage: chonker3 [FILE...] [LINK...]
+
+Opens the first FILE (PDF, PNG, TIFF, EML or MSG) and adds the others to the
+workspace. A chonker3:// LINK opens the document it names from the workspace
+at the linked page and item.
+
+Options:
+      --new-window Start a separate instance instead of opening the files
+                   in the running one
+      --quick-drop Run as a small drop target that extracts the files
+                   dropped on it in the background
+  -h, --help       Show this help
+  -V, --version    Show the version";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchArgs {
+    pub files: Vec<PathBuf>,
+    pub links: Vec<DeepLink>,
+    pub new_window: bool,
+    pub quick_drop: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+/// Parse the arguments after the program name
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<LaunchArgs> {
+    let mut launch = LaunchArgs::default();
+    let mut only_files = false;
+    for arg in args {
+        match arg.as_str() {
+            "--" if !only_files => only_files = true,
+            "--new-window" if !only_files => launch.new_window = true,
+            "--quick-drop" if !only_files => launch.quick_drop = true,
+            "-h" | "--help" if !only_files => launch.help = true,
+            "-V" | "--version" if !only_files => launch.version = true,
+            // Older macOS passes a process serial number to apps opened from Finder
+            _ if !only_files && arg.starts_with("-psn_") => {}
+            _ if !only_files && arg.starts_with('-') => bail!("Unknown option: {}", arg),
+            _ if arg.starts_with(deep_link::SCHEME) => launch.links.push(DeepLink::parse(&arg)?),
+            _ => {
+                let path = PathBuf::from(arg);
+                if InputKind::from_path(&path).is_none() {
+                    bail!("Unsupported file type: {}", path.display());
+                }
+                launch.files.push(path);
+            }
+        }
+    }
+    Ok(launch)
+}
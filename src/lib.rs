@@ -0,0 +1,75 @@
+//! Chonker3 library
+//!
+//! Everything except the egui application shell lives here so it can be used
+//! from tests and other binaries.
+
+pub mod extractor;
+pub mod types;
+pub mod core;
+pub mod document;
+pub mod search;
+pub mod renderer;
+pub mod alignment;
+pub mod snap;
+pub mod lines;
+pub mod importers;
+pub mod inputs;
+pub mod launch;
+pub mod instance;
+pub mod deep_link;
+pub mod quick_drop;
+pub mod tray;
+pub mod scan_cleanup;
+pub mod separators;
+pub mod barcodes;
+pub mod reocr;
+pub mod repaint;
+pub mod patch;
+pub mod comments;
+pub mod regions;
+pub mod collab;
+pub mod workspace;
+pub mod dedup;
+pub mod page_organizer;
+pub mod einvoice;
+pub mod normalize;
+pub mod export;
+pub mod annotations;
+pub mod label_studio;
+pub mod tables;
+pub mod reflow;
+pub mod pdf_output;
+pub mod searchable;
+pub mod references;
+pub mod bates;
+pub mod stats;
+pub mod transcript;
+pub mod pipeline;
+pub mod bundle;
+pub mod scripting;
+pub mod macros;
+pub mod clipboard;
+pub mod settings;
+pub mod storage;
+pub mod palette;
+pub mod toasts;
+pub mod jobs;
+pub mod memory;
+pub mod checksum;
+pub mod pdfium_bootstrap;
+pub mod passwords;
+pub mod llm;
+pub mod translation;
+pub mod summary;
+pub mod validation;
+pub mod field_mapping;
+pub mod webhook;
+pub mod python_env;
+pub mod scrolling;
+pub mod viewport;
+pub mod tabs;
+pub mod remote;
+pub mod stamp;
+pub mod continuous;
+pub mod extraction_cache;
+pub mod pii;
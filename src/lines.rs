@@ -0,0 +1,174 @@
+//! Line-level items
+//!
+//! Extractors like Docling report whole paragraphs as one item, which the
+//! canvas can only place by the paragraph's box. This post-step splits such
+//! items into one item per line using word boxes (text runs pdfium finds
+//! inside the item's box), so each line sits where it does in the PDF and can
+//! be edited on its own. Split lines get new item IDs.
+
+use pdfium_render::prelude::*;
+use serde_json::{json, Value};
+
+use crate::types::{BoundingBox, ProvenanceStage, ProvenanceStep};
+
+/// A run of text on one line, TOPLEFT
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordBox {
+    pub text: String,
+    pub bbox: BoundingBox,
+}
+
+/// Words share a line when their vertical overlap is at least this share of the shorter one
+const SAME_LINE_OVERLAP: f64 = 0.5;
+
+/// A word box must lie at least this much inside an item to belong to it
+const INSIDE_SHARE: f64 = 0.6;
+
+fn union(a: &BoundingBox, b: &BoundingBox) -> BoundingBox {
+    let left = a.left.min(b.left);
+    let top = a.top.min(b.top);
+    let right = (a.left + a.width).max(b.left + b.width);
+    let bottom = (a.top + a.height).max(b.top + b.height);
+    BoundingBox { left, top, width: right - left, height: bottom - top }
+}
+
+fn vertical_overlap(a: &BoundingBox, b: &BoundingBox) -> f64 {
+    let overlap = (a.top + a.height).min(b.top + b.height) - a.top.max(b.top);
+    overlap.max(0.0) / a.height.min(b.height).max(f64::EPSILON)
+}
+
+/// Group word boxes into rows of words on the same line, top to bottom, each
+/// row's words left to right
+pub fn group_rows(mut words: Vec<WordBox>) -> Vec<Vec<WordBox>> {
+    words.sort_by(|a, b| a.bbox.top.total_cmp(&b.bbox.top));
+    let mut rows: Vec<Vec<WordBox>> = Vec::new();
+    for word in words {
+        match rows.iter_mut().find(|row| vertical_overlap(&row[0].bbox, &word.bbox) >= SAME_LINE_OVERLAP) {
+            Some(row) => row.push(word),
+            None => rows.push(vec![word]),
+        }
+    }
+    for row in &mut rows {
+        row.sort_by(|a, b| a.bbox.left.total_cmp(&b.bbox.left));
+    }
+    rows
+}
+
+/// Words in reading order as one box, or nothing if they have no text
+pub fn join_words(words: &[WordBox]) -> Option<WordBox> {
+    let (first, rest) = words.split_first()?;
+    let bbox = rest.iter().fold(first.bbox.clone(), |bbox, word| union(&bbox, &word.bbox));
+    let text = words.iter().map(|w| w.text.trim()).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(WordBox { text, bbox })
+}
+
+/// Group word boxes into lines, top to bottom, each line's words joined left to right
+pub fn group_lines(words: Vec<WordBox>) -> Vec<WordBox> {
+    group_rows(words).iter().filter_map(|row| join_words(row)).collect()
+}
+
+/// Word boxes on a pdfium page: its text segments, TOPLEFT
+pub fn page_word_boxes(page: &PdfPage) -> Vec<WordBox> {
+    let Ok(text) = page.text() else { return Vec::new() };
+    let page_height = page.height().value as f64;
+    text.segments().iter()
+        .map(|segment| {
+            let bounds = segment.bounds();
+            WordBox {
+                text: segment.text(),
+                bbox: BoundingBox {
+                    left: bounds.left().value as f64,
+                    top: page_height - bounds.top().value as f64,
+                    width: bounds.width().value as f64,
+                    height: bounds.height().value as f64,
+                },
+            }
+        })
+        .collect()
+}
+
+fn inside_share(word: &BoundingBox, item: &BoundingBox) -> f64 {
+    let width = (word.left + word.width).min(item.left + item.width) - word.left.max(item.left);
+    let height = (word.top + word.height).min(item.top + item.height) - word.top.max(item.top);
+    let area = word.width * word.height;
+    if area <= 0.0 {
+        return 0.0;
+    }
+    (width.max(0.0) * height.max(0.0)) / area
+}
+
+/// An item's TOPLEFT box, converting BOTTOMLEFT ones with the page height
+pub(crate) fn item_box(item: &Value, page_height: f64) -> Option<BoundingBox> {
+    let bbox = item.get("bbox")?;
+    let get = |key: &str| bbox.get(key).and_then(|v| v.as_f64());
+    let (left, top, width, height) = (get("left")?, get("top")?, get("width")?, get("height")?.abs());
+    let bottomleft = bbox.get("coord_origin").and_then(|v| v.as_str()).unwrap_or("TOPLEFT").contains("BOTTOMLEFT");
+    let top = if bottomleft { page_height - top } else { top };
+    Some(BoundingBox { left, top, width, height })
+}
+
+/// Split multi-line items into one item per line. `word_boxes(page_index)` gives
+/// a page's word boxes. Tables, pictures, formulas and code stay whole. Returns how many items were split.
+pub fn split_into_lines(data: &mut Value, mut word_boxes: impl FnMut(usize) -> Vec<WordBox>) -> usize {
+    let page_heights: Vec<f64> = data.get("pages").and_then(|v| v.as_array()).into_iter().flatten()
+        .map(|page| page.get("height").and_then(|v| v.as_f64()).unwrap_or(792.0))
+        .collect();
+    let Some(items) = data.get("items").and_then(|v| v.as_array()).cloned() else { return 0 };
+
+    let mut pages_words: Vec<Option<Vec<WordBox>>> = vec![None; page_heights.len()];
+    let mut split = 0;
+    let mut result = Vec::with_capacity(items.len());
+    for item in items {
+        let kind = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let page_index = item.get("page").and_then(|v| v.as_u64()).unwrap_or(0).saturating_sub(1) as usize;
+        let page_height = page_heights.get(page_index).copied().unwrap_or(792.0);
+        let bbox = item_box(&item, page_height);
+        let (Some(bbox), false) = (bbox, matches!(kind, "TableItem" | "PictureItem" | "FormulaItem" | "CodeItem" | "BarcodeItem")) else {
+            result.push(item);
+            continue;
+        };
+        let Some(page_words) = pages_words.get_mut(page_index) else {
+            result.push(item);
+            continue;
+        };
+        let words = page_words.get_or_insert_with(|| word_boxes(page_index));
+        let inside: Vec<WordBox> = words.iter()
+            .filter(|word| inside_share(&word.bbox, &bbox) >= INSIDE_SHARE)
+            .cloned()
+            .collect();
+        let lines = group_lines(inside);
+        if lines.len() < 2 {
+            result.push(item);
+            continue;
+        }
+
+        split += 1;
+        for line in lines {
+            let mut line_item = item.clone();
+            let b = &line.bbox;
+            line_item["content"] = json!(line.text);
+            if let Some(item) = line_item.as_object_mut() {
+                // The paragraph's text and baseline don't describe a single line
+                item.remove("text");
+                item.remove("baseline");
+            }
+            line_item["bbox"] = json!({
+                "left": b.left,
+                "top": b.top,
+                "right": b.left + b.width,
+                "bottom": b.top + b.height,
+                "width": b.width,
+                "height": b.height,
+                "coord_origin": "TOPLEFT",
+            });
+            crate::document::record_provenance(&mut line_item, ProvenanceStep::new(ProvenanceStage::Postprocess, "line split"));
+            result.push(line_item);
+        }
+    }
+
+    for (index, item) in result.iter_mut().enumerate() {
+        item["index"] = json!(index);
+    }
+    data["items"] = Value::Array(result);
+    split
+}
@@ -0,0 +1,257 @@
+//! LLM post-processing
+//!
+//! Optional, and off until an endpoint is set up in Settings: selected items
+//! or a whole page can be sent to any OpenAI-compatible chat completions
+//! endpoint (a hosted API, or a local server such as Ollama or llama.cpp) to
+//! summarize them, pull out key fields or clean up OCR text. Replies are only
+//! shown; cleaned-up text replaces the items' text only once the user accepts
+//! it. The API key is kept in the OS keyring, like PDF passwords.
+
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::passwords::SERVICE;
+use crate::types::DocumentItem;
+
+/// Keyring account of the API key
+const API_KEY_ACCOUNT: &str = "llm-api-key";
+
+fn default_endpoint() -> String {
+    "http://localhost:11434/v1".to_string()
+}
+
+fn default_model() -> String {
+    "llama3.1".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+fn default_translate_to() -> String {
+    "English".to_string()
+}
+
+/// Where post-processing requests go
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Base URL of the API; `/chat/completions` is appended
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Prompts offered on a selection's context menu
+    #[serde(default = "default_templates")]
+    pub templates: Vec<PromptTemplate>,
+    /// Language the translation overlay translates into
+    #[serde(default = "default_translate_to")]
+    pub translate_to: String,
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: default_endpoint(),
+            model: default_model(),
+            timeout_secs: default_timeout_secs(),
+            templates: default_templates(),
+            translate_to: default_translate_to(),
+        }
+    }
+}
+
+impl LlmSettings {
+    pub fn completions_url(&self) -> String {
+        format!("{}/chat/completions", self.endpoint.trim().trim_end_matches('/'))
+    }
+}
+
+/// What to ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmTask {
+    Summarize,
+    /// Key fields as `Field: value` lines
+    KeyFields,
+    /// The same items with OCR errors fixed, which can be applied
+    CleanUp,
+}
+
+impl LlmTask {
+    pub const ALL: [LlmTask; 3] = [LlmTask::Summarize, LlmTask::KeyFields, LlmTask::CleanUp];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LlmTask::Summarize => "Summarize",
+            LlmTask::KeyFields => "Extract key fields",
+            LlmTask::CleanUp => "Clean up text",
+        }
+    }
+
+    pub fn instructions(&self) -> &'static str {
+        match self {
+            LlmTask::Summarize => "Summarize the following document text in a few sentences. Reply with the summary only.",
+            LlmTask::KeyFields => "List the key fields of the following document text (such as names, dates, amounts, reference numbers), one per line as `Field: value`. Reply with the list only.",
+            LlmTask::CleanUp => "The following text blocks were read by OCR. Fix recognition errors, broken words and stray characters without rewording anything. Reply with every block, each starting with its [n] marker on a new line, and nothing else.",
+        }
+    }
+}
+
+/// A named prompt the user can edit, sent ahead of the items' text
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub prompt: String,
+    /// Send the whole page rather than the selection
+    #[serde(default)]
+    pub whole_page: bool,
+}
+
+/// The templates a fresh install starts with
+pub fn default_templates() -> Vec<PromptTemplate> {
+    let template = |name: &str, prompt: &str, whole_page| PromptTemplate { name: name.to_string(), prompt: prompt.to_string(), whole_page };
+    vec![
+        template("Summarize page", "Summarize this page in a few sentences. Reply with the summary only.", true),
+        template(
+            "Extract invoice fields to JSON",
+            "Extract the invoice fields from the following text as a JSON object with the keys invoice_number, invoice_date, due_date, seller, buyer, currency, net_total, tax_total and total. Use null for fields that are missing. Reply with the JSON only.",
+            true,
+        ),
+        template("Translate selection", "Translate the following text into English, keeping the [n] markers. Reply with the translation only.", false),
+    ]
+}
+
+/// The items as `[n] text` blocks, numbered from 1
+pub fn numbered_text(items: &[DocumentItem]) -> String {
+    items.iter().enumerate()
+        .map(|(index, item)| format!("[{}] {}", index + 1, item.content.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Chat completions request for the task on these items
+pub fn request_body(settings: &LlmSettings, task: LlmTask, items: &[DocumentItem]) -> Value {
+    prompt_request_body(settings, task.instructions(), items)
+}
+
+/// Chat completions request for any prompt on these items
+pub fn prompt_request_body(settings: &LlmSettings, prompt: &str, items: &[DocumentItem]) -> Value {
+    text_request_body(settings, prompt, &numbered_text(items))
+}
+
+/// Chat completions request for a prompt on free text
+pub fn text_request_body(settings: &LlmSettings, prompt: &str, text: &str) -> Value {
+    json!({
+        "model": settings.model,
+        "temperature": 0,
+        "messages": [
+            { "role": "system", "content": "You post-process text extracted from PDF documents." },
+            { "role": "user", "content": format!("{}\n\n{}", prompt.trim(), text) },
+        ],
+    })
+}
+
+/// The reply text of a chat completions response
+pub fn reply_text(response: &Value) -> Result<String> {
+    if let Some(message) = response["error"]["message"].as_str() {
+        bail!("{}", message);
+    }
+    response["choices"][0]["message"]["content"].as_str()
+        .map(|text| text.trim().to_string())
+        .ok_or_else(|| anyhow!("The reply has no message"))
+}
+
+/// The `[n] text` blocks of a reply to `numbered_text` of `count` items, as
+/// (zero-based index, text); markers out of range are kept as text
+pub fn numbered_blocks(reply: &str, count: usize) -> Vec<(usize, String)> {
+    let mut blocks: Vec<(usize, String)> = Vec::new();
+    for line in reply.lines() {
+        let marker = line.trim_start().strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .and_then(|(number, text)| Some((number.trim().parse::<usize>().ok()?, text)));
+        if let Some((number, text)) = marker.filter(|(number, _)| (1..=count).contains(number)) {
+            blocks.push((number - 1, text.trim().to_string()));
+        } else if let Some((_, text)) = blocks.last_mut() {
+            // Blocks can run over several lines
+            text.push('\n');
+            text.push_str(line);
+        }
+    }
+    blocks.into_iter()
+        .map(|(index, text)| (index, text.trim_end().to_string()))
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
+
+/// New text per item from a clean-up reply, by position in `items`; blocks
+/// that are missing or unchanged are left out
+pub fn cleaned_texts(reply: &str, items: &[DocumentItem]) -> Vec<(usize, String)> {
+    numbered_blocks(reply, items.len()).into_iter()
+        .filter(|(index, text)| *text != items[*index].content.trim())
+        .collect()
+}
+
+/// Send the task to the endpoint and wait for the reply
+pub fn complete(settings: &LlmSettings, task: LlmTask, items: &[DocumentItem]) -> Result<String> {
+    complete_prompt(settings, task.instructions(), items)
+}
+
+/// Send a prompt and the items' text to the endpoint and wait for the reply
+pub fn complete_prompt(settings: &LlmSettings, prompt: &str, items: &[DocumentItem]) -> Result<String> {
+    complete_text(settings, prompt, &numbered_text(items))
+}
+
+/// Send a prompt and free text to the endpoint and wait for the reply
+pub fn complete_text(settings: &LlmSettings, prompt: &str, text: &str) -> Result<String> {
+    let url = settings.completions_url();
+    let mut request = ureq::post(&url)
+        .set("User-Agent", "chonker3")
+        .set("Content-Type", "application/json")
+        .timeout(Duration::from_secs(settings.timeout_secs.max(1)));
+    if let Some(key) = api_key() {
+        request = request.set("Authorization", &format!("Bearer {}", key));
+    }
+    let body = text_request_body(settings, prompt, text).to_string();
+    let response = match request.send_string(&body) {
+        Ok(response) => response,
+        // Error replies usually say what was wrong
+        Err(ureq::Error::Status(code, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<Value>(&text).ok()
+                .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or(text);
+            bail!("{} replied {}: {}", url, code, message.trim());
+        }
+        Err(e) => bail!("Request to {} failed: {}", url, e),
+    };
+    let text = response.into_string().context("Failed to read the reply")?;
+    reply_text(&serde_json::from_str(&text).context("The reply is not JSON")?)
+}
+
+fn api_key_entry() -> Result<Entry> {
+    Entry::new(SERVICE, API_KEY_ACCOUNT).map_err(|e| anyhow!("Can't use the keyring: {}", e))
+}
+
+/// The API key in the keyring, if one was saved
+pub fn api_key() -> Option<String> {
+    api_key_entry().ok()?.get_password().ok().filter(|key| !key.is_empty())
+}
+
+/// Save the API key in the keyring, or remove it when empty
+pub fn set_api_key(key: &str) -> Result<()> {
+    let entry = api_key_entry()?;
+    if key.trim().is_empty() {
+        return match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!("Couldn't remove the API key from the keyring: {}", e)),
+        };
+    }
+    entry.set_password(key.trim()).map_err(|e| anyhow!("Couldn't save the API key in the keyring: {}", e))
+}
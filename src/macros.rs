@@ -0,0 +1,264 @@
+//! Recorded edit macros
+//!
+//! A macro is a list of steps captured from the user's edits. Each edit step
+//! carries a selector describing the item it was made on (type, text and
+//! region), and how to match it on replay, so "delete this running header" or
+//! "make this region a table" can be repeated on other pages and documents.
+//! Macros are stored by name in the workspace.
+
+use serde::{Deserialize, Serialize};
+
+use anyhow::Result;
+
+use crate::core::Session;
+use crate::document;
+use crate::reflow::PrintLayout;
+use crate::types::{DocumentItem, ItemType};
+
+/// Slack in points when matching regions
+const REGION_TOLERANCE: f64 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchBy {
+    /// Same item type and identical text
+    SameText,
+    /// Every item of the same type
+    SameType,
+    /// Items whose center falls in the recorded region
+    SameRegion,
+}
+
+impl MatchBy {
+    pub const ALL: [MatchBy; 3] = [MatchBy::SameText, MatchBy::SameType, MatchBy::SameRegion];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchBy::SameText => "same text",
+            MatchBy::SameType => "same type",
+            MatchBy::SameRegion => "same region",
+        }
+    }
+}
+
+/// The item a step was recorded on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemSelector {
+    pub item_type: ItemType,
+    pub text: String,
+    /// left, top, right, bottom in TOPLEFT points
+    pub region: (f64, f64, f64, f64),
+    pub match_by: MatchBy,
+}
+
+impl ItemSelector {
+    pub fn from_item(item: &DocumentItem, text: &str, match_by: MatchBy) -> Self {
+        Self {
+            item_type: item.item_type,
+            text: text.to_string(),
+            region: (item.bbox.left, item.bbox.top, item.bbox.left + item.bbox.width, item.bbox.top + item.bbox.height),
+            match_by,
+        }
+    }
+
+    pub fn matches(&self, item: &DocumentItem, text: &str) -> bool {
+        match self.match_by {
+            MatchBy::SameText => item.item_type == self.item_type && text.trim() == self.text.trim(),
+            MatchBy::SameType => item.item_type == self.item_type,
+            MatchBy::SameRegion => {
+                let (left, top, right, bottom) = self.region;
+                let cx = item.bbox.left + item.bbox.width / 2.0;
+                let cy = item.bbox.top + item.bbox.height / 2.0;
+                cx >= left - REGION_TOLERANCE && cx <= right + REGION_TOLERANCE
+                    && cy >= top - REGION_TOLERANCE && cy <= bottom + REGION_TOLERANCE
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.match_by {
+            MatchBy::SameText => format!("{} \"{}\"", self.item_type.label(), self.text),
+            MatchBy::SameType => format!("every {}", self.item_type.label()),
+            MatchBy::SameRegion => {
+                let (left, top, right, bottom) = self.region;
+                format!("items in ({:.0}, {:.0})-({:.0}, {:.0})", left, top, right, bottom)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MacroStep {
+    Delete(ItemSelector),
+    SetType(ItemSelector, ItemType),
+    SetText(ItemSelector, String),
+    Annotate(ItemSelector, String),
+    /// Export paths may use `{name}` for the PDF's file stem
+    ExportCsv(String),
+    ExportMarkdown(String),
+    ExportStructured(String),
+    ExportJsonl(String),
+    ExportBibtex(String),
+    ExportBatesCsv(String),
+    ExportStats(String),
+    /// Reflowed PDF, with the print layout it was recorded with
+    ExportReflowedPdf(String, PrintLayout),
+    ExportSearchablePdf(String),
+    /// Tables stitched across pages
+    ExportTablesCsv(String),
+    ExportTablesXlsx(String),
+    /// Named regions' contents
+    ExportFields(String),
+}
+
+impl MacroStep {
+    pub fn selector_mut(&mut self) -> Option<&mut ItemSelector> {
+        match self {
+            MacroStep::Delete(s) | MacroStep::SetType(s, _) | MacroStep::SetText(s, _) | MacroStep::Annotate(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            MacroStep::Delete(s) => format!("Delete {}", s.describe()),
+            MacroStep::SetType(s, t) => format!("Make {} a {}", s.describe(), t.label()),
+            MacroStep::SetText(s, text) => format!("Set text of {} to \"{}\"", s.describe(), text),
+            MacroStep::Annotate(s, _) => format!("Annotate {}", s.describe()),
+            MacroStep::ExportCsv(path) => format!("Export CSV to {}", path),
+            MacroStep::ExportMarkdown(path) => format!("Export Markdown to {}", path),
+            MacroStep::ExportStructured(path) => format!("Export structured JSON to {}", path),
+            MacroStep::ExportJsonl(path) => format!("Export items as JSONL to {}", path),
+            MacroStep::ExportBibtex(path) => format!("Export BibTeX references to {}", path),
+            MacroStep::ExportBatesCsv(path) => format!("Export Bates numbers to {}", path),
+            MacroStep::ExportStats(path) => format!("Export document statistics to {}", path),
+            MacroStep::ExportReflowedPdf(path, layout) => format!("Export reflowed {} PDF to {}", layout.paper.label(), path),
+            MacroStep::ExportSearchablePdf(path) => format!("Export searchable PDF to {}", path),
+            MacroStep::ExportTablesCsv(path) => format!("Export tables as CSV to {}", path),
+            MacroStep::ExportTablesXlsx(path) => format!("Export tables as XLSX to {}", path),
+            MacroStep::ExportFields(path) => format!("Export named fields to {}", path),
+        }
+    }
+}
+
+/// Replace the PDF's file stem in an export path with `{name}`
+pub fn generalize_path(path: &std::path::Path, session: &Session) -> String {
+    let path = path.display().to_string();
+    match session.pdf_path.as_ref().and_then(|p| p.file_stem()) {
+        Some(stem) if !stem.is_empty() => path.replace(&*stem.to_string_lossy(), "{name}"),
+        _ => path,
+    }
+}
+
+fn expand_path(template: &str, session: &Session) -> std::path::PathBuf {
+    let name = session.pdf_path.as_ref()
+        .and_then(|p| p.file_stem())
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
+    template.replace("{name}", &name).into()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Macro {
+    pub steps: Vec<MacroStep>,
+}
+
+/// Where to replay a macro
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayScope {
+    CurrentPage,
+    AllPages,
+}
+
+impl Macro {
+    /// Replay every step against the session; returns the number of item edits made
+    pub fn replay(&self, session: &mut Session, scope: ReplayScope) -> Result<usize> {
+        let page_count = session.extracted_data.as_ref()
+            .and_then(|d| d.get("pages"))
+            .and_then(|v| v.as_array())
+            .map(|p| p.len())
+            .unwrap_or(0);
+        let pages: Vec<usize> = match scope {
+            ReplayScope::CurrentPage => vec![session.page],
+            ReplayScope::AllPages => (0..page_count).collect(),
+        };
+
+        let mut edits = 0;
+        for step in &self.steps {
+            let selector = match step {
+                MacroStep::ExportCsv(path) => {
+                    session.export_csv(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportMarkdown(path) => {
+                    session.export_markdown(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportStructured(path) => {
+                    session.export_structured(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportJsonl(path) => {
+                    session.export_jsonl(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportBibtex(path) => {
+                    session.export_bibtex(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportBatesCsv(path) => {
+                    session.export_bates_csv(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportStats(path) => {
+                    session.export_stats(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportReflowedPdf(path, layout) => {
+                    session.export_reflowed_pdf(&expand_path(path, session), layout)?;
+                    continue;
+                }
+                MacroStep::ExportSearchablePdf(path) => {
+                    session.export_searchable_pdf(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportTablesCsv(path) => {
+                    session.export_tables_csv(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportTablesXlsx(path) => {
+                    session.export_tables_xlsx(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::ExportFields(path) => {
+                    session.export_fields(&expand_path(path, session))?;
+                    continue;
+                }
+                MacroStep::Delete(s) | MacroStep::SetType(s, _) | MacroStep::SetText(s, _) | MacroStep::Annotate(s, _) => s,
+            };
+
+            // Match against the state left by the previous steps
+            let Some(data) = &session.extracted_data else { continue };
+            let patch = session.to_patch();
+            let targets: Vec<String> = pages.iter()
+                .flat_map(|page| document::page_items(data, *page, &patch))
+                .filter(|item| {
+                    let text = patch.text_overrides.get(&item.id).unwrap_or(&item.content);
+                    selector.matches(item, text)
+                })
+                .map(|item| item.id)
+                .collect();
+
+            for id in &targets {
+                match step {
+                    MacroStep::Delete(_) => session.delete_item(id),
+                    MacroStep::SetType(_, item_type) => session.set_type(id, Some(*item_type)),
+                    MacroStep::SetText(_, text) => session.set_text(id, text.clone()),
+                    MacroStep::Annotate(_, note) => session.set_annotation(id, note),
+                    _ => {}
+                }
+            }
+            edits += targets.len();
+        }
+        Ok(edits)
+    }
+}
@@ -4,400 +4,6201 @@
 // See VERSION.md for details
 
 use eframe::egui;
-use egui::{Color32, RichText, Vec2, ColorImage, TextureHandle, ScrollArea, Pos2};
-use std::path::PathBuf;
+use egui::{Color32, RichText, Vec2, TextureHandle, ScrollArea, Pos2};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use pdfium_render::prelude::*;
 
-mod extractor;
-use extractor::{extract_pdf, ExtractionResult};
+use chonker3::extractor::{CancelToken, ExtractOptions, ExtractedDocument, ExtractionUpdate, ExtractorKind, PartialExtraction, PythonBackend};
+use chonker3::patch::EditPatch;
+use chonker3::collab::{self, CollabSession};
+use chonker3::core::{PdfBytes, Session};
+use chonker3::workspace::{ExtractionRun, Workspace};
+use chonker3::settings::{QualityPreset, Settings};
+use chonker3::palette::{BuiltinPalette, Palette};
+use chonker3::toasts::Toasts;
+use chonker3::jobs::{self, Job};
+use chonker3::memory::{self, GuardAction, MemoryGuard, MemoryUsage};
+use chonker3::scrolling::KineticScroll;
+use chonker3::viewport::{self, ViewCommand, ViewportController};
+use chonker3::repaint::{self, RepaintScheduler};
+use chonker3::instance::{self, InstanceServer};
+use chonker3::deep_link::{self, DeepLink, LinkTarget};
+use chonker3::comments::{self, CommentThread};
+use chonker3::quick_drop::{self, DropQueue, QueueEvent};
+use chonker3::tray::{self, Tray, TrayCommand, TrayImage};
+use chonker3::page_organizer::{self, PageOrganizer};
+use chonker3::macros::{self, ItemSelector, MacroStep, MatchBy, ReplayScope};
+use chonker3::tabs::{Closed, TabStrip};
+use chonker3::remote::{self, RemoteLocation};
+use chonker3::extraction_cache::{self, ExtractionCache};
+use chonker3::pii::{self, PiiDetector};
+use chonker3::continuous::PageStack;
+use chonker3::{annotations, barcodes, checksum, clipboard, dedup, einvoice, field_mapping, importers, inputs, label_studio, llm, normalize, pdfium_bootstrap, python_env, renderer, reocr, scripting, separators, storage, summary, translation, types, validation};
 
-mod types;
+/// Ranked search results listed under the search box
+const SEARCH_RESULTS_SHOWN: usize = 50;
 
-mod renderer;
+#[derive(Clone, Copy)]
+enum ExportKind {
+    Structured,
+    Jsonl,
+    Markdown,
+    Csv,
+    Bibtex,
+    BatesCsv,
+    Stats,
+    ReflowedPdf,
+    SearchablePdf,
+    TablesCsv,
+    TablesXlsx,
+    Fields,
+}
+
+/// What an LLM was asked and what it replied
+struct LlmResult {
+    /// The task or template name
+    title: String,
+    /// Whether the reply is cleaned-up text to apply, rather than just to read
+    clean_up: bool,
+    items: Vec<types::DocumentItem>,
+    reply: String,
+    /// Cleaned-up text per item (index into `items`) and whether to apply it
+    changes: Vec<(usize, String, bool)>,
+}
+
+/// Work that waits on a background job
+#[derive(Default, PartialEq)]
+enum JobFollowup {
+    #[default]
+    None,
+    /// Load the pdfium library the job downloaded
+    LoadPdfium,
+    /// Re-check the Python environment the job set up
+    CheckPython,
+    /// Add the documents split out of batch scans to the workspace
+    AddSplitDocuments,
+    /// Put the barcodes the job decoded into the extraction
+    AddBarcodes,
+    /// Use the text the job re-OCR'd
+    ApplyReocr,
+    /// Show the LLM's reply
+    ShowLlmReply,
+    /// Cache the LLM's page or document summary
+    ApplyLlmSummary,
+    /// Show the files the job listed in the remote browser
+    ShowRemoteListing,
+    /// Open the file the job downloaded from a remote location
+    OpenRemoteDownload,
+}
+
+/// The open-from-remote window
+#[derive(Default)]
+struct RemoteBrowser {
+    /// Index into the settings' remote locations
+    location: usize,
+    /// Files in the location, once listed
+    objects: Vec<remote::RemoteObject>,
+}
+
+/// Pages of the continuous view, rendered as they scroll into sight
+#[derive(Default)]
+struct ContinuousPages {
+    /// Page sizes in points, read from the PDF the first time the view shows
+    sizes: Option<Vec<(f32, f32)>>,
+    /// Rendered pages, with the pixel width each was rendered at
+    textures: HashMap<usize, (TextureHandle, i32)>,
+    /// Page the counter showed last frame; the session on another page means
+    /// the reader went there some other way, so the view scrolls to it
+    shown_page: Option<usize>,
+    /// The view's top and height, and the stack's height, last frame; the
+    /// view moving is scrolling, the stack changing height is zooming
+    view_top: f32,
+    view_height: f32,
+    stack_height: f32,
+}
+
+impl ContinuousPages {
+    fn texture_bytes(&self) -> usize {
+        self.textures.values().map(|(texture, _)| memory::texture_bytes(texture.size())).sum()
+    }
+}
+
+/// Where re-OCR'd text goes
+enum ReocrTarget {
+    /// Replaces this item's text
+    Item(String),
+    /// Becomes a new item at this region of a (zero-based) page
+    Region(usize, types::BoundingBox),
+    /// Shows in the OCR quick tool
+    QuickTool,
+}
 
 const TEAL: Color32 = Color32::from_rgb(0x1A, 0xBC, 0x9C);
 
+/// Red for poorly aligned pages through amber to green for good ones
+fn alignment_color(score: f32) -> Color32 {
+    let poor = Color32::from_rgb(239, 68, 68);
+    let fair = Color32::from_rgb(245, 158, 11);
+    let good = Color32::from_rgb(16, 185, 129);
+    let score = score.clamp(0.0, 1.0);
+    if score < 0.5 {
+        lerp_color(poor, fair, score * 2.0)
+    } else {
+        lerp_color(fair, good, score * 2.0 - 1.0)
+    }
+}
+
+fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgb(mix(a.r(), b.r()), mix(a.g(), b.g()), mix(a.b(), b.b()))
+}
+
+/// What the app keeps per open document; the active one's lives in the app,
+/// the rest are parked in the tab strip
+#[derive(Default)]
+struct DocumentTab {
+    session: Session,
+    status_message: String,
+    view: ViewportController,
+    pdf_texture: Option<TextureHandle>,
+    prefetcher: Option<renderer::PagePrefetcher>,
+    pdf_page_size: (f32, f32),
+    pdf_scroll_offset: Vec2,
+    continuous: ContinuousPages,
+    selected_items: Vec<String>,
+    einvoice: Option<einvoice::EInvoice>,
+    password_prompt: bool,
+    page_organizer: Option<PageOrganizer>,
+    llm_result: Option<LlmResult>,
+    reocr_target: Option<ReocrTarget>,
+    transcript: Option<(PathBuf, chonker3::transcript::Transcript)>,
+    transcript_scores: Vec<chonker3::transcript::PageScore>,
+}
+
+impl DocumentTab {
+    /// Estimated memory a parked document holds on to
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            page_textures: self.pdf_texture.as_ref().map_or(0, |t| memory::texture_bytes(t.size()))
+                + self.prefetcher.as_ref().map_or(0, |p| p.bytes())
+                + self.continuous.texture_bytes(),
+            thumbnails: self.page_organizer.as_ref()
+                .map_or(0, |o| o.thumbnails.values().map(|t| memory::texture_bytes(t.size())).sum()),
+            galleys: 0,
+            extraction: self.session.extracted_data.as_ref().map_or(0, memory::json_bytes),
+            pdf_bytes: self.session.pdf_bytes.as_ref().map_or(0, |b| b.heap_bytes()),
+        }
+    }
+    
+    /// Drop the rendered pages; they render again when the tab is shown
+    fn evict_textures(&mut self) {
+        self.pdf_texture = None;
+        if let Some(prefetcher) = self.prefetcher.as_mut() {
+            prefetcher.clear();
+        }
+        self.continuous.textures.clear();
+        if let Some(organizer) = self.page_organizer.as_mut() {
+            organizer.thumbnails.clear();
+        }
+    }
+}
+
 #[derive(Default)]
 struct Chonker3App {
-    current_pdf: Option<PathBuf>,
-    extracted_json: Option<PathBuf>,
-    extracted_data: Option<serde_json::Value>,
+    // Document, extraction and edits
+    session: Session,
+    // The other open documents, one tab each
+    tabs: TabStrip<DocumentTab>,
     status_message: String,
+    toasts: Toasts,
+    // Long export running on a worker thread
+    job: Option<Job>,
+    // What to do once the running job finishes, and the PDF it works on when
+    // that touches a document
+    job_followup: JobFollowup,
+    job_document: Option<PathBuf>,
+    // First-run offer to download pdfium
+    show_pdfium_download: bool,
+    // Python environment setup; the status is checked on a worker thread
+    show_python_setup: bool,
+    python_status: Arc<Mutex<Option<python_env::EnvStatus>>>,
+    // Estimated memory use, checked every few seconds against the settings budget
+    memory_guard: MemoryGuard,
+    memory_usage: MemoryUsage,
+    memory_checked: Option<std::time::Instant>,
     is_extracting: bool,
-    extraction_result: Arc<Mutex<Option<ExtractionResult>>>,
-    pdf_page: usize,
-    pdf_bytes: Option<Vec<u8>>,
-    pdfium: Option<Arc<Pdfium>>,
+    extraction_result: Arc<Mutex<Option<anyhow::Result<ExtractedDocument>>>>,
+    // Progress and finished pages of the running extraction, handed over by the
+    // extraction thread; the pages are shown until the whole result arrives
+    extraction_updates: Arc<Mutex<Vec<ExtractionUpdate>>>,
+    partial_extraction: PartialExtraction,
+    extraction_progress: Option<(usize, usize)>,
+    // Set by the Cancel button; the extraction thread stops when it sees it
+    extraction_cancel: CancelToken,
+    // Lets background threads wake the UI when they have something to show
+    egui_ctx: egui::Context,
+    // Collects this frame's requests for the next one
+    repaint: RepaintScheduler,
+    // Files sent by later launches while this is the running instance
+    instance_server: Option<InstanceServer>,
+    // Set in quick-drop mode, where the window is only a drop target
+    drop_queue: Option<DropQueue>,
+    drop_queue_log: Vec<String>,
+    // Quick-drop icon in the system tray; while it's up, closing the window only minimizes it
+    tray: Option<Tray>,
     pdf_texture: Option<TextureHandle>,
-    pdf_page_count: usize,
-    zoom_level: f32,
-    pan_offset: egui::Vec2,
-    search_query: String,
+    // Renders the pages around the current one in the background; one per open PDF
+    prefetcher: Option<renderer::PagePrefetcher>,
+    // Panel size and DPI the texture was rendered for; re-rendered once a change settles
+    pdf_render_debounce: renderer::ResizeDebounce,
+    // Size of the rendered page in points
+    pdf_page_size: (f32, f32),
+    // Zoom, pan and rotation shared by both panels
+    view: ViewportController,
+    // Toolbar view buttons pressed this frame, applied once the panel size is known
+    view_commands: Vec<ViewCommand>,
+    // Eased wheel scrolling and drag momentum for the page and canvas panels
+    pdf_scroll: KineticScroll,
+    pdf_scroll_offset: Vec2,
+    canvas_scroll: KineticScroll,
+    // Every page stacked in one scroll, when the continuous view is on
+    continuous: ContinuousPages,
+    // Selected item IDs and pointer position over the page, for the status bar
+    selected_items: Vec<String>,
+    pointer_position: Option<(f32, f32)>,
     show_search: bool,
     show_help: bool,
+    /// Item categories hidden from both panels
+    item_filter: types::ItemFilter,
+    /// Draw the PDF page faintly under the extracted text
+    ghost_overlay: bool,
+    // Per-page alignment scores, filled in by a job, for the (PDF, extraction) in alignment_for
+    show_alignment: bool,
+    alignment_scores: Arc<Mutex<BTreeMap<usize, f32>>>,
+    alignment_for: Option<(Option<PathBuf>, Option<PathBuf>)>,
+    // Items on the current page whose text overflowed their box, as the canvas last drew them
+    show_diagnostics: bool,
+    overflow_items: Vec<(String, renderer::Overflow)>,
+    show_references: bool,
+    show_stats: bool,
+    // Page and document summaries; an LLM summary comes back from its job for
+    // the page in llm_summary_page, or the document when None
+    show_summaries: bool,
+    show_problems: bool,
+    // Personal data panel; hits are outlined on both panels while it's open
+    show_pii: bool,
+    /// Detector for `settings.pii` and its hits across the document, for the
+    /// settings, document and edits in `pii_for`; None when they changed
+    pii_detector: Option<anyhow::Result<PiiDetector>>,
+    pii_hits: Vec<pii::PiiHit>,
+    pii_for: Option<PiiKey>,
+    // Output mapping designer, with a few documents loaded for its preview
+    show_field_mapping: bool,
+    field_mapping_preview: Vec<field_mapping::DocumentFields>,
+    summary_method: summary::SummaryMethod,
+    llm_summary: Arc<Mutex<Option<String>>>,
+    llm_summary_page: Option<usize>,
+    // Heatmap strip under the toolbar; text-layer character counts are cached per PDF
+    show_heatmap: bool,
+    text_layer_chars: Option<(PathBuf, Vec<Option<usize>>)>,
+    // Barcodes panel; the detection job leaves what it decoded in detected_barcodes
+    show_barcodes: bool,
+    // Confirming tables stitched across page breaks
+    show_table_stitching: bool,
+    // Named regions: in draw mode the next dragged-out region is named in the panel
+    show_regions: bool,
+    region_draw_mode: bool,
+    pending_region: Option<(usize, types::BoundingBox)>,
+    region_name_buffer: String,
+    renaming_region: Option<(usize, String)>,
+    /// The open PDF is encrypted and waiting for its password
+    password_prompt: bool,
+    password_buffer: String,
+    password_error: Option<String>,
+    show_inspector: bool,
+    /// Dragging out a region re-OCRs it instead of only selecting
+    reocr_region_mode: bool,
+    reocr_target: Option<ReocrTarget>,
+    reocr_result: Arc<Mutex<Option<reocr::OcrText>>>,
+    show_quick_ocr: bool,
+    quick_ocr_image: Option<egui::TextureHandle>,
+    quick_ocr_text: Option<reocr::OcrText>,
+    detected_barcodes: Arc<Mutex<Vec<barcodes::PageBarcode>>>,
+    // LLM post-processing: the panel, the reply the job brings back and the
+    // one shown, and the API key typed in Settings before it goes to the keyring
+    show_llm: bool,
+    llm_reply: Arc<Mutex<Option<String>>>,
+    llm_result: Option<LlmResult>,
+    llm_api_key_buffer: String,
+    webhook_authorization_buffer: String,
+    // Opening from S3 or WebDAV: the browser window, the listing and download
+    // its jobs bring back, and the secrets typed in Settings, by location
+    remote_browser: Option<RemoteBrowser>,
+    remote_listing: Arc<Mutex<Option<Vec<remote::RemoteObject>>>>,
+    remote_download: Arc<Mutex<Option<PathBuf>>>,
+    remote_secret_buffers: BTreeMap<usize, String>,
+    // Machine translation shown in place of the original text, filled in by a
+    // job, for the (PDF, extraction) in translation_for
+    show_translation: bool,
+    translation: Arc<Mutex<translation::Translation>>,
+    translation_for: Option<(Option<PathBuf>, Option<PathBuf>)>,
+    // Reference transcript loaded for accuracy scoring, and the scores last computed from it
+    show_transcript: bool,
+    transcript: Option<(PathBuf, chonker3::transcript::Transcript)>,
+    transcript_scores: Vec<chonker3::transcript::PageScore>,
+    highlight_divergent: bool,
+    // Page bookmarks and notes panel; the buffer holds the note of page_note_page
+    show_page_notes: bool,
+    page_note_buffer: String,
+    page_note_page: Option<usize>,
+    // Comments panel: the item whose thread is open and the reply being written
+    show_comments: bool,
+    comment_item: Option<String>,
+    comment_buffer: String,
+    show_resolved_comments: bool,
+    settings: Settings,
+    show_settings: bool,
+    show_collab: bool,
+    collab: Option<CollabSession>,
+    collab_port: u16,
+    collab_join_address: String,
+    collab_join_code: String,
+    // The PDF the session edits; edits pause while another tab is showing
+    collab_pdf: Option<PathBuf>,
+    // Workspace of documents with tags/metadata
+    workspace: Workspace,
+    show_workspace: bool,
+    show_extraction_history: bool,
+    /// Ranked hits across the document for `search_hits_query`; None when
+    /// the document changed under them
+    search_hits: Vec<chonker3::search::SearchHit>,
+    search_hits_query: Option<String>,
+    show_saved_searches: bool,
+    /// The search being named in the saved searches window
+    saved_search_draft: chonker3::search::SavedSearch,
+    saved_search_error: Option<String>,
+    /// The saved search last run across the workspace, and its hits by document index
+    saved_search_results: Option<(String, Vec<chonker3::workspace::DocumentHits>)>,
+    /// The extraction under way, for the history, and when it started
+    running_extraction: Option<(ExtractionRun, std::time::Instant)>,
+    workspace_tag_filter: std::collections::BTreeSet<String>,
+    workspace_selected: Option<usize>,
+    // PDFs written by the scan-splitting job, added to the workspace when it finishes
+    split_documents: Arc<Mutex<Vec<PathBuf>>>,
+    new_tag_buffer: String,
+    new_metadata_key: String,
+    new_metadata_value: String,
+    duplicate_review: Option<dedup::DuplicateReview>,
+    page_organizer: Option<PageOrganizer>,
+    // Embedded ZUGFeRD/Factur-X invoice, if the PDF carries one
+    einvoice: Option<einvoice::EInvoice>,
+    show_einvoice: bool,
+    // Script console; the autorun script runs after every extraction
+    // Macro recorder; recorded macros live in the workspace
+    macro_recording: Option<macros::Macro>,
+    show_macros: bool,
+    macro_name_buffer: String,
+    macro_all_pages: bool,
+    show_script_console: bool,
+    script_source: String,
+    script_output: Vec<String>,
+    script_autorun: bool,
     editing_item_id: Option<String>,
     edit_text_buffer: String,
-    // Text customization support
-    item_offsets: std::collections::HashMap<String, egui::Vec2>,
-    item_text_overrides: std::collections::HashMap<String, String>,
+    edit_type_buffer: Option<types::ItemType>,
+    edit_annotation_buffer: String,
+}
+
+/// What the cached personal data hits were found in
+struct PiiKey {
+    settings: pii::PiiSettings,
+    pdf_path: Option<PathBuf>,
+    extracted_json: Option<PathBuf>,
+    text_overrides: HashMap<String, String>,
+    deletions: HashSet<String>,
 }
 
 impl Chonker3App {
-    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        let mut app = Self::default();
-        app.status_message = "Drop a PDF or click 'Open' to begin".to_string();
-        app.zoom_level = 0.86; // Default zoom to fit page nicely
-        
+    fn new(cc: &eframe::CreationContext<'_>, launch: chonker3::launch::LaunchArgs, listener: Option<std::net::TcpListener>) -> Self {
+        let settings = Settings::load_default();
+        let storage_warnings = storage::configure(&settings.storage);
+        let autorun_script = std::fs::read_to_string(scripting::autorun_path()).ok();
+        let mut memory_guard = MemoryGuard::default();
+        memory_guard.set_budget_mb(settings.memory_budget_mb);
+        let mut app = Self {
+            session: Session {
+                // A library the user located wins over one we downloaded
+                pdfium_library: settings.pdfium_library.clone().or_else(pdfium_bootstrap::installed_library),
+                pdf_output: settings.pdf_output,
+                stamping: settings.stamping,
+                ..Default::default()
+            },
+            status_message: "Drop a PDF or click 'Open' to begin".to_string(),
+            egui_ctx: cc.egui_ctx.clone(),
+            instance_server: listener.map(|listener| {
+                let ctx = cc.egui_ctx.clone();
+                InstanceServer::start(listener, move || ctx.request_repaint())
+            }),
+            workspace: Workspace::load_default(),
+            settings,
+            memory_guard,
+            script_autorun: autorun_script.is_some(),
+            script_source: autorun_script.unwrap_or_default(),
+            ..Self::default()
+        };
+        for warning in storage_warnings {
+            app.toasts.error(warning);
+        }
+        // Cmd+Plus/Minus zoom the page, not the whole interface
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        // First run without pdfium: offer to download it, once
+        if !app.session.ensure_pdfium() && !app.settings.pdfium_download_offered && pdfium_bootstrap::pinned_asset().is_some() {
+            app.show_pdfium_download = true;
+        }
+        if launch.quick_drop {
+            let ctx = cc.egui_ctx.clone();
+            let (extractor, mut options) = (app.settings.extractor, app.settings.extract_options.clone());
+            options.pdfium_library = app.session.pdfium_library.clone();
+            app.drop_queue = Some(DropQueue::start(extractor, options, move || ctx.request_repaint()));
+            let icon = load_icon();
+            let image = TrayImage { rgba: icon.rgba, width: icon.width, height: icon.height };
+            let ctx = cc.egui_ctx.clone();
+            match Tray::start(image, move || ctx.request_repaint()) {
+                Ok(tray) => app.tray = Some(tray),
+                Err(e) => log::info!("Quick drop runs without a tray icon: {:#}", e),
+            }
+        }
+        app.open_launch_files(launch.files);
+        for link in launch.links {
+            app.open_deep_link(link);
+        }
         app
     }
     
+    /// Queue a file for background extraction in quick-drop mode
+    fn queue_file(&mut self, path: PathBuf) {
+        let Some(queue) = self.drop_queue.as_mut() else { return };
+        if let Err(e) = queue.push(path) {
+            self.drop_queue_log.push(format!("✖ {:#}", e));
+        }
+    }
+    
+    /// The quick-drop window: a drop target and the queue's progress
+    fn show_quick_drop(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        for path in dropped {
+            self.queue_file(path);
+        }
+        let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+        let mut add_files = false;
+        if let Some(tray) = &self.tray {
+            for command in tray.commands() {
+                match command {
+                    TrayCommand::ShowWindow => {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                    }
+                    TrayCommand::AddFiles => add_files = true,
+                    TrayCommand::Quit => {
+                        self.tray = None;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        return;
+                    }
+                }
+            }
+            // The queue keeps running from the tray. Minimize rather than
+            // hide: a hidden window stops getting frames to poll the queue.
+            if ctx.input(|i| i.viewport().close_requested()) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            }
+        }
+        
+        let Some(queue) = self.drop_queue.as_mut() else { return };
+        let (events, finished) = queue.poll();
+        for event in events {
+            let QueueEvent::Finished { path, result } = event else { continue };
+            match result {
+                Ok(extracted) => for extraction in extracted {
+                    let index = self.workspace.add_document(&extraction.pdf);
+                    self.workspace.set_extraction(index, extraction.json_path);
+                    self.drop_queue_log.push(format!("✔ {} ({} items)", quick_drop::display_name(&extraction.pdf), extraction.items));
+                },
+                Err(e) => self.drop_queue_log.push(format!("✖ {}: {}", quick_drop::display_name(&path), e)),
+            }
+            if let Err(e) = self.workspace.save() {
+                log::warn!("Failed to save workspace: {}", e);
+            }
+        }
+        if let Some(summary) = finished {
+            quick_drop::notify("Chonker3", &summary.describe());
+        }
+        let (pending, current) = (queue.pending, queue.current.clone());
+        if let Some(tray) = self.tray.as_mut() {
+            let name = current.as_deref().map(quick_drop::display_name);
+            tray.set_status(tray::status_text(name.as_deref(), pending));
+        }
+        
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(8.0);
+                let color = if hovering { TEAL } else { Color32::GRAY };
+                ui.label(RichText::new("🐹").size(32.0));
+                ui.label(RichText::new("Drop files to extract").size(16.0).color(color));
+                ui.add_space(4.0);
+                match current {
+                    Some(path) => ui.label(format!("Extracting {} ({} queued)", quick_drop::display_name(&path), pending.saturating_sub(1))),
+                    None => ui.label(RichText::new("Idle").color(Color32::GRAY)),
+                };
+                ui.horizontal(|ui| {
+                    add_files = ui.button("Add files...").clicked();
+                    if ui.button("Open Chonker3").on_hover_text("Open the full window to review the extracted documents").clicked() {
+                        if let Err(e) = std::env::current_exe().and_then(|exe| std::process::Command::new(exe).arg("--new-window").spawn()) {
+                            self.toasts.error(format!("Failed to open Chonker3: {}", e));
+                        }
+                    }
+                });
+            });
+            ui.separator();
+            ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                for line in &self.drop_queue_log {
+                    ui.label(RichText::new(line).small());
+                }
+            });
+        });
+        if add_files {
+            let files = rfd::FileDialog::new()
+                .add_filter("Documents", &inputs::InputKind::EXTENSIONS)
+                .pick_files()
+                .unwrap_or_default();
+            for path in files {
+                self.queue_file(path);
+            }
+        }
+    }
+    
+    /// Open files a later launch handed over, and come to the front
+    fn open_forwarded_files(&mut self, ctx: &egui::Context) {
+        let Some(server) = &self.instance_server else { return };
+        let requests = server.take_requests();
+        if requests.is_empty() {
+            return;
+        }
+        for (files, links) in requests {
+            self.open_launch_files(files);
+            for link in links {
+                match DeepLink::parse(&link) {
+                    Ok(link) => self.open_deep_link(link),
+                    Err(e) => self.toasts.error(format!("{:#}", e)),
+                }
+            }
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+    
+    /// Open the files named on the command line: the first one, with the
+    /// rest added to the workspace
+    fn open_launch_files(&mut self, files: Vec<PathBuf>) {
+        if self.drop_queue.is_some() {
+            for path in files {
+                self.queue_file(path);
+            }
+            return;
+        }
+        let mut files = files.into_iter();
+        let Some(first) = files.next() else { return };
+        for path in files {
+            match inputs::prepare(&path, &inputs::default_output_dir()) {
+                Ok(pdfs) => for pdf in pdfs {
+                    self.workspace.add_document(&pdf);
+                },
+                Err(e) => self.toasts.error(format!("Failed to open {}: {}", path.display(), e)),
+            }
+        }
+        self.open_input(first);
+    }
+    
+    /// Go to the spot a chonker3:// link points at, opening its document from
+    /// the workspace if another one is showing
+    fn open_deep_link(&mut self, link: DeepLink) {
+        let showing = self.session.pdf_bytes.as_ref()
+            .is_some_and(|bytes| deep_link::document_hash(bytes) == link.document);
+        if !showing {
+            let candidates: Vec<PathBuf> = self.workspace.documents.iter().map(|doc| doc.path.clone()).collect();
+            let Some(path) = deep_link::find_document(&link.document, candidates) else {
+                self.toasts.error("The linked document isn't in the workspace; open it once, then follow the link again");
+                return;
+            };
+            self.open_input(path);
+        }
+        if link.page >= self.session.page_count {
+            self.toasts.error(format!("The link points at page {}, but the document has {}", link.page + 1, self.session.page_count));
+            return;
+        }
+        if self.session.go_to_page(link.page) {
+            self.pdf_texture = None;
+        }
+        let items = self.session.document_state().map(|state| state.items).unwrap_or_default();
+        let (selected, spot) = link.target.resolve(&items);
+        self.selected_items = selected;
+        let point = spot.map_or((0.0, 0.0), |b| (b.left as f32, b.top as f32));
+        self.view_commands.push(ViewCommand::Reveal(point));
+    }
+    
+    /// Copy a chonker3:// link to the selected items, or to the page
+    fn copy_deep_link(&mut self, ctx: &egui::Context) {
+        let Some(bytes) = self.session.pdf_bytes.as_ref() else { return };
+        let items = self.session.document_state().map(|state| state.items).unwrap_or_default();
+        let selected: Vec<&types::DocumentItem> = items.iter().filter(|i| self.selected_items.contains(&i.id)).collect();
+        let what = match selected.len() {
+            0 => format!("page {}", self.session.page + 1),
+            1 => "the selected item".to_string(),
+            n => format!("{} selected items", n),
+        };
+        let link = DeepLink::new(bytes, self.session.page, LinkTarget::for_items(&selected));
+        ctx.copy_text(link.to_uri());
+        self.toasts.success(format!("🔗 Copied link to {}", what));
+    }
+    
+    /// Open a PDF in a tab of its own, or switch to the tab already showing it
     fn load_pdf(&mut self, pdf_path: PathBuf) {
-        self.current_pdf = Some(pdf_path.clone());
-        self.extracted_data = None;
-        self.extracted_json = None;
+        if let Some(index) = self.tabs.find(&pdf_path) {
+            self.switch_tab(index);
+            return;
+        }
+        let new_tab = self.session.pdf_path.is_some();
+        if new_tab {
+            let leaving = self.park_document();
+            self.tabs.open(pdf_path.clone(), leaving);
+        }
+        self.load_pdf_here(pdf_path);
+        // Nothing to show in a tab the PDF failed to open in; go back to the one it was opened from
+        if self.session.pdf_path.is_none() {
+            if new_tab {
+                let closed = self.tabs.cancel_open();
+                self.tab_closed(closed);
+            } else if let Some(index) = self.tabs.active() {
+                self.close_tab(index);
+            }
+        }
+    }
+    
+    /// Swap the active document's state with a parked one
+    fn swap_document(&mut self, tab: &mut DocumentTab) {
+        self.swap_state(tab);
+        // Panels that point into a document start over in the other one
+        self.pending_region = None;
+        self.page_note_page = None;
+        self.comment_item = None;
+        self.editing_item_id = None;
+        self.search_hits_query = None;
+        self.pii_for = None;
+        self.workspace_selected = self.session.pdf_path.as_ref().and_then(|path| self.workspace.find(path));
+    }
+    
+    /// Swap the per-document fields only, leaving the panels alone
+    fn swap_state(&mut self, tab: &mut DocumentTab) {
+        std::mem::swap(&mut self.session, &mut tab.session);
+        std::mem::swap(&mut self.status_message, &mut tab.status_message);
+        std::mem::swap(&mut self.view, &mut tab.view);
+        std::mem::swap(&mut self.pdf_texture, &mut tab.pdf_texture);
+        std::mem::swap(&mut self.prefetcher, &mut tab.prefetcher);
+        std::mem::swap(&mut self.pdf_page_size, &mut tab.pdf_page_size);
+        std::mem::swap(&mut self.pdf_scroll_offset, &mut tab.pdf_scroll_offset);
+        std::mem::swap(&mut self.continuous, &mut tab.continuous);
+        std::mem::swap(&mut self.selected_items, &mut tab.selected_items);
+        std::mem::swap(&mut self.einvoice, &mut tab.einvoice);
+        std::mem::swap(&mut self.password_prompt, &mut tab.password_prompt);
+        std::mem::swap(&mut self.page_organizer, &mut tab.page_organizer);
+        std::mem::swap(&mut self.llm_result, &mut tab.llm_result);
+        std::mem::swap(&mut self.reocr_target, &mut tab.reocr_target);
+        std::mem::swap(&mut self.transcript, &mut tab.transcript);
+        std::mem::swap(&mut self.transcript_scores, &mut tab.transcript_scores);
+    }
+    
+    /// Take the active document's state out, leaving an empty document
+    fn park_document(&mut self) -> DocumentTab {
+        let mut tab = DocumentTab { session: self.session.sibling(), ..Default::default() };
+        self.swap_document(&mut tab);
+        tab
+    }
+    
+    fn switch_tab(&mut self, index: usize) {
+        let leaving = self.park_document();
+        let (Ok(mut state) | Err(mut state)) = self.tabs.switch(index, leaving);
+        self.swap_document(&mut state);
+    }
+    
+    fn close_tab(&mut self, index: usize) {
+        // An extraction of the closing document has nowhere to go
+        let closing = self.tabs.tabs().get(index).map(|tab| tab.path.clone());
+        if let (Some((run, _)), Some(closing)) = (&self.running_extraction, closing) {
+            if closing.canonicalize().ok().as_ref() == Some(&run.document) {
+                self.extraction_cancel.cancel();
+            }
+        }
+        let closed = self.tabs.close(index);
+        self.tab_closed(closed);
+    }
+    
+    /// Show whatever document closing a tab left active
+    fn tab_closed(&mut self, closed: Closed<DocumentTab>) {
+        match closed {
+            Closed::Background => {}
+            Closed::Activate(mut state) => self.swap_document(&mut state),
+            Closed::Empty => {
+                self.park_document();
+                self.status_message = "Drop a PDF or click 'Open' to begin".to_string();
+            }
+        }
+    }
+    
+    /// Whether the active tab shows this (canonical) path
+    fn is_showing(&self, path: &Path) -> bool {
+        self.session.pdf_path.as_ref().is_some_and(|open| open.canonicalize().ok().as_deref() == Some(path))
+    }
+    
+    /// Run `f` with a background tab's state in place of the active one's,
+    /// leaving the panels alone; false if no tab shows `path`
+    fn with_parked_document(&mut self, path: &Path, f: impl FnOnce(&mut Self)) -> bool {
+        let Some(mut tab) = self.tabs.parked_for(path).map(std::mem::take) else { return false };
+        self.swap_state(&mut tab);
+        f(self);
+        self.swap_state(&mut tab);
+        if let Some(parked) = self.tabs.parked_for(path) {
+            *parked = tab;
+        }
+        true
+    }
+    
+    /// Hand an extraction to the background tab it was started in
+    fn finish_background_extraction(&mut self, path: &Path, document: ExtractedDocument) {
+        if let Some(index) = self.workspace.find(path) {
+            self.workspace.set_extraction(index, document.json_path.clone());
+            let _ = self.workspace.save();
+        }
+        let items = document.item_count();
+        let line_items = self.settings.extract_options.line_items;
+        let Some(tab) = self.tabs.parked_for(path) else {
+            log::info!("{} was closed before its extraction finished", path.display());
+            return;
+        };
+        tab.session.set_extraction(document.data, Some(document.json_path));
+        if line_items {
+            if let Err(e) = tab.session.split_into_lines() {
+                log::warn!("Line splitting failed: {}", e);
+            }
+        }
+        tab.status_message = format!("Extracted {} items", items);
+        self.with_parked_document(path, Self::run_autorun_script);
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        self.toasts.success(format!("Extracted {} items from {}", items, name));
+    }
+    
+    /// Open documents as tabs under the toolbar
+    fn show_tab_bar(&mut self, ctx: &egui::Context) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        let mut switch_to = None;
+        let mut close = None;
+        egui::TopBottomPanel::top("document_tabs").show(ctx, |ui| {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, tab) in self.tabs.tabs().iter().enumerate() {
+                        let active = self.tabs.active() == Some(index);
+                        let title = RichText::new(tab.title()).size(12.0);
+                        if ui.selectable_label(active, if active { title.strong() } else { title })
+                            .on_hover_text(tab.path.display().to_string())
+                            .clicked() {
+                            switch_to = Some(index);
+                        }
+                        if ui.small_button("✖").on_hover_text("Close tab (Cmd+W)").clicked() {
+                            close = Some(index);
+                        }
+                        ui.separator();
+                    }
+                });
+            });
+        });
+        if let Some(index) = close {
+            self.close_tab(index);
+        } else if let Some(index) = switch_to {
+            self.switch_tab(index);
+        }
+    }
+    
+    /// Open a PDF in the active tab, replacing what it shows
+    fn load_pdf_here(&mut self, pdf_path: PathBuf) {
+        self.einvoice = None;
+        self.pdf_texture = None;
+        self.prefetcher = None;
+        self.continuous = ContinuousPages::default();
+        self.selected_items.clear();
+        if let Err(e) = self.session.open_pdf(&pdf_path) {
+            self.toasts.error(format!("Failed to open PDF: {}", e));
+            return;
+        }
+        self.tabs.retarget(pdf_path.clone());
         self.status_message = "PDF loaded. Click 'Extract' to process.".to_string();
         
-        if self.pdfium.is_none() {
-            let lib_path = std::env::var("PDFIUM_DYNAMIC_LIB_PATH")
-                .unwrap_or_else(|_| "./lib".to_string());
-            
-            match Pdfium::bind_to_library(
-                Pdfium::pdfium_platform_library_name_at_path(&lib_path)
-            ).or_else(|_| Pdfium::bind_to_system_library()) {
-                Ok(bindings) => self.pdfium = Some(Arc::new(Pdfium::new(bindings))),
-                Err(_) => return,
+        // Encrypted PDFs open with the remembered password, or ask for one
+        self.password_prompt = false;
+        if self.session.is_locked() {
+            let remembered = self.session.pdf_bytes.as_ref()
+                .filter(|_| self.settings.remember_pdf_passwords)
+                .and_then(|bytes| chonker3::passwords::remembered(bytes));
+            if !remembered.is_some_and(|password| self.session.unlock(&password)) {
+                self.password_prompt = true;
+                self.password_buffer.clear();
+                self.password_error = None;
+                self.status_message = "PDF is password protected.".to_string();
             }
         }
         
-        if let Ok(bytes) = std::fs::read(&pdf_path) {
-            self.pdf_bytes = Some(bytes);
-            self.pdf_page = 0;
-            self.pdf_texture = None;
+        // Remember the document in the workspace
+        let index = self.workspace.add_document(&pdf_path);
+        self.workspace_selected = Some(index);
+        // Bring back the page bookmarks and notes from earlier sessions
+        let doc = &self.workspace.documents[index];
+        self.session.edits.bookmarks.extend(doc.bookmarks.iter().copied());
+        self.session.edits.page_notes.extend(doc.page_notes.clone());
+        self.session.edits.comments.extend(doc.comments.clone());
+        self.session.edits.regions.extend(doc.regions.clone());
+        self.pending_region = None;
+        self.page_note_page = None;
+        self.comment_item = None;
+        if let Err(e) = self.workspace.save() {
+            log::warn!("Failed to save workspace: {}", e);
+        }
+        
+        // Look for an embedded e-invoice to cross-check after extraction
+        self.einvoice = self.session.with_document(einvoice::find_embedded_invoice).flatten();
+        if let Some(invoice) = &self.einvoice {
+            self.status_message = format!(
+                "PDF loaded with embedded e-invoice ({}). Click 'Extract' to cross-check.",
+                invoice.attachment_name
+            );
+        }
+    }
+    
+    
+    /// Open any supported input: scans are converted to a PDF first, and an
+    /// email's PDF attachments all join the workspace with the first one opened
+    fn open_input(&mut self, path: PathBuf) {
+        match inputs::prepare(&path, &inputs::default_output_dir()) {
+            Ok(pdfs) => {
+                for extra in &pdfs[1..] {
+                    self.workspace.add_document(extra);
+                }
+                if pdfs.len() > 1 {
+                    self.toasts.info(format!("Added {} more PDF attachments to the workspace", pdfs.len() - 1));
+                }
+                self.load_pdf(pdfs[0].clone());
+                self.auto_extract();
+            }
+            Err(e) => self.toasts.error(format!("Failed to open {}: {}", path.display(), e)),
+        }
+    }
+    
+    /// Order of the Python backends Docling extraction falls back through, and retries
+    fn fallback_chain_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let options = &mut self.settings.extract_options;
+        let mut changed = false;
+        ui.label("Try in order:");
+        let mut move_up = None;
+        let mut remove = None;
+        for (index, backend) in options.fallback_chain.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                ui.label(format!("{}. {}", index + 1, backend.label()));
+                if ui.add_enabled(index > 0, egui::Button::new("⬆").small()).on_hover_text("Try earlier").clicked() {
+                    move_up = Some(index);
+                }
+                if ui.small_button("✕").on_hover_text("Don't try this backend").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = move_up {
+            options.fallback_chain.swap(index - 1, index);
+            changed = true;
+        }
+        if let Some(index) = remove {
+            options.fallback_chain.remove(index);
+            changed = true;
+        }
+        let unused: Vec<PythonBackend> = PythonBackend::ALL.into_iter()
+            .filter(|backend| !options.fallback_chain.contains(backend))
+            .collect();
+        if !unused.is_empty() {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                for backend in unused {
+                    if ui.small_button(format!("+ {}", backend.label())).clicked() {
+                        options.fallback_chain.push(backend);
+                        changed = true;
+                    }
+                }
+            });
         }
+        ui.horizontal(|ui| {
+            ui.label("Retries per backend:");
+            changed |= ui.add(egui::DragValue::new(&mut options.retries).range(0..=3)).changed();
+        }).response.on_hover_text("Backends that aren't installed are skipped without retrying");
+        changed
     }
     
+    /// Extract the PDF just opened when the settings ask for it
+    fn auto_extract(&mut self) {
+        let Some(pdf_path) = &self.session.pdf_path else { return };
+        if self.is_extracting || self.session.extracted_data.is_some() || self.password_prompt {
+            return;
+        }
+        let size = std::fs::metadata(pdf_path).map(|m| m.len()).unwrap_or(0);
+        if self.settings.auto_extract.applies_to(size) {
+            self.extract_content();
+        } else if self.settings.auto_extract.enabled {
+            self.status_message = format!(
+                "PDF loaded. Larger than {} MB, so not extracted automatically; click 'Extract' to process.",
+                self.settings.auto_extract.max_size_mb
+            );
+        }
+    }
     
+    /// Extract the open PDF with the settings' extractor and options, loading
+    /// the cached result if it was extracted like this before
     fn extract_content(&mut self) {
-        if let Some(pdf_path) = self.current_pdf.clone() {
+        self.extract_with_settings(true);
+    }
+    
+    /// Extract the open PDF again even if there's a cached result, replacing it
+    fn force_extract(&mut self) {
+        self.extract_with_settings(false);
+    }
+    
+    fn extract_with_settings(&mut self, reuse_cached: bool) {
+        let mut options = self.settings.extract_options.clone();
+        if let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) {
+            options.page_backends = self.workspace.documents[index].page_backends.clone();
+        }
+        self.extract_with(self.settings.extractor, options, reuse_cached);
+    }
+    
+    /// Extract the open PDF, recording the run in the extraction history
+    fn extract_with(&mut self, mut kind: ExtractorKind, mut options: ExtractOptions, reuse_cached: bool) {
+        // Without the venv only the native extractor can run; use it rather than nothing
+        if kind.needs_python() && !python_env::venv_python(&python_env::venv_dir()).exists() {
+            if self.session.pdfium.is_none() {
+                self.open_python_setup();
+                return;
+            }
+            kind = ExtractorKind::Native;
+            self.toasts.info("No Python environment; reading the PDF's text layer natively. Set one up in Settings for layout analysis and OCR.");
+        }
+        if let Some(pdf_path) = self.session.pdf_path.clone() {
             self.is_extracting = true;
             self.status_message = "Extracting...".to_string();
             
             let result_handle = self.extraction_result.clone();
+            let updates_handle = self.extraction_updates.clone();
+            updates_handle.lock().unwrap().clear();
+            self.partial_extraction = PartialExtraction::default();
+            self.extraction_progress = None;
+            let extractor = kind.extractor();
+            self.running_extraction = Some((ExtractionRun {
+                document: pdf_path.canonicalize().unwrap_or_else(|_| pdf_path.clone()),
+                extractor: kind,
+                options: options.clone(),
+                page_backends: options.page_backends.clone(),
+                started: chrono::Local::now().to_rfc3339(),
+                seconds: 0.0,
+                backend: None,
+                items: 0,
+                json_path: None,
+                error: None,
+            }, std::time::Instant::now()));
+            options.password = self.session.pdf_password.clone();
+            options.pdfium_library = self.session.pdfium_library.clone();
+            self.extraction_cancel = CancelToken::default();
+            options.cancel = self.extraction_cancel.clone();
+            // Scan cleanup renders with pdfium on the extraction thread
+            let cleanup_input = self.session.pdf_bytes.clone()
+                .filter(|_| options.scan_cleanup.enabled && self.session.pdfium.is_some());
+            let library = self.session.pdfium_library.clone();
+            let ctx = self.egui_ctx.clone();
+            // Results are cached by the PDF's hash, which is taken on the extraction thread
+            let cache = self.session.pdf_bytes.clone()
+                .filter(|_| self.settings.cache_extractions)
+                .map(|pdf_bytes| (ExtractionCache::default(), extraction_cache::cache_key(kind, &options), pdf_bytes));
             
             std::thread::spawn(move || {
-                let result = extract_pdf(&pdf_path).unwrap_or_else(|e| ExtractionResult {
-                    success: false,
-                    json_path: String::new(),
-                    items: 0,
-                    message: format!("Failed: {}", e),
+                let cache = cache.map(|(cache, key, pdf_bytes)| (cache, key, checksum::sha256_hex(&pdf_bytes)));
+                if let Some(cached) = cache.as_ref().filter(|_| reuse_cached).and_then(|(cache, key, sha256)| cache.get(sha256, key)) {
+                    *result_handle.lock().unwrap() = Some(Ok(cached));
+                    ctx.request_repaint();
+                    return;
+                }
+                let mut input = pdf_path.clone();
+                if let Some(pdf_bytes) = cleanup_input {
+                    let cleaned = chonker3::core::bind_pdfium_from(library.as_deref())
+                        .and_then(|pdfium| chonker3::scan_cleanup::write_cleaned(&pdfium, &pdf_bytes, options.password.as_deref(), &pdf_path, &options.scan_cleanup));
+                    match cleaned {
+                        Ok(Some(cleaned)) => input = cleaned,
+                        Ok(None) => log::info!("PDF has a text layer; skipping scan cleanup"),
+                        Err(e) => log::warn!("Scan cleanup failed, extracting the original: {}", e),
+                    }
+                }
+                let mut result = extractor.extract_streaming(&input, &options, &mut |update| {
+                    updates_handle.lock().unwrap().push(update);
+                    ctx.request_repaint();
                 });
-                
+                if let (true, Ok(document)) = (input != pdf_path, result.as_mut()) {
+                    if let Err(e) = chonker3::scan_cleanup::record_cleanup(document) {
+                        log::warn!("Failed to record scan cleanup in the extraction: {}", e);
+                    }
+                }
+                if let (Some((cache, key, sha256)), Ok(document)) = (&cache, &result) {
+                    if let Err(e) = cache.put(sha256, key, document) {
+                        log::warn!("Failed to cache the extraction: {:#}", e);
+                    }
+                }
                 *result_handle.lock().unwrap() = Some(result);
+                ctx.request_repaint();
             });
         }
     }
     
-    fn load_pdf_page(&mut self, ctx: &egui::Context, target_width: f32) {
-        if let (Some(pdfium), Some(pdf_bytes)) = (&self.pdfium, &self.pdf_bytes) {
-            if let Ok(document) = pdfium.load_pdf_from_byte_slice(pdf_bytes, None) {
-                self.pdf_page_count = document.pages().len() as usize;
+    /// Import hOCR/ALTO/Textract output or Label Studio tasks as the extraction for the current PDF
+    fn import_ocr_results(&mut self, path: PathBuf) {
+        let page_sizes = self.session.page_sizes();
+        
+        match importers::import_file(&path, &page_sizes) {
+            Ok((format, data)) => {
+                // Write alongside regular extractions so the result has a JSON path too
+                let json_path = storage::temp_dir().join(format!(
+                    "{}_chonker3_import.json",
+                    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+                ));
+                let written = serde_json::to_string_pretty(&data).ok()
+                    .filter(|json| std::fs::write(&json_path, json).is_ok())
+                    .map(|_| json_path);
                 
-                if let Ok(page) = document.pages().get(self.pdf_page as u16) {
-                    let page_width = page.width().value;
-                    let page_height = page.height().value;
-                    let scale = (target_width / page_width) * self.zoom_level;
-                    
-                    let render_width = (page_width * scale) as i32;
-                    let render_height = (page_height * scale) as i32;
-                    
-                    let config = PdfRenderConfig::new()
-                        .set_target_size(render_width, render_height)
-                        .render_form_data(true);
-                    
-                    if let Ok(bitmap) = page.render_with_config(&config) {
-                        let image = bitmap.as_image();
-                        let image_buffer = image.as_bytes();
-                        let pixels: Vec<_> = image_buffer
-                            .chunks_exact(4)
-                            .map(|p| Color32::from_rgba_unmultiplied(p[2], p[1], p[0], p[3]))
-                            .collect();
-                        
-                        let color_image = ColorImage {
-                            size: [render_width as usize, render_height as usize],
-                            pixels,
-                        };
-                        
-                        self.pdf_texture = Some(ctx.load_texture(
-                            "pdf_page",
-                            color_image,
-                            Default::default()
-                        ));
-                    }
-                }
+                let item_count = self.session.set_extraction(data, written);
+                self.pii_for = None;
+                self.status_message = format!("Imported {} items from {}", item_count, format.label());
+                self.run_autorun_script();
             }
+            Err(e) => self.toasts.error(format!("Import failed: {}", e)),
         }
     }
     
-}
-
-impl Chonker3App {
-    fn convert_to_document_state(&self, json_data: &serde_json::Value) -> types::DocumentState {
-        use crate::types::{DocumentItem, ItemType, BoundingBox};
+    /// Exchange edits and presence with collaborators
+    fn sync_collab(&mut self) {
+        let local_edits = self.session.to_patch();
+        let editing = self.editing_item_id.clone();
+        
+        if self.collab.is_some() && self.session.pdf_path != self.collab_pdf {
+            return;
+        }
+        if let Some(session) = self.collab.as_mut() {
+            session.sync_local(&local_edits);
+            session.set_presence(editing);
+            if let Some(merged) = session.poll() {
+                self.session.edits.replace(merged);
+                // Pick up a collaborator's page note
+                self.page_note_page = None;
+            }
+            // Keep polling the network while a session is active
+            self.repaint.within(repaint::NETWORK_POLL);
+        }
+    }
+    
+    /// Page textures' resolution relative to the screen: the quality setting,
+    /// lowered by the memory guard when over budget
+    fn render_scale(&self) -> f32 {
+        self.memory_guard.render_scale * self.settings.texture_scale
+    }
+    
+    /// Size of the current page in points, letter size until one is rendered
+    fn page_size(&self) -> (f32, f32) {
+        if self.pdf_page_size.0 > 0.0 { self.pdf_page_size } else { (612.0, 792.0) }
+    }
+    
+    /// Change the view, re-rendering the page at once for a new zoom or
+    /// rotation; wheel zooming waits for the render debounce instead
+    fn apply_view(&mut self, command: ViewCommand, available: Vec2) {
+        let change = self.view.apply(command, self.page_size(), available);
+        if change.resized && !matches!(command, ViewCommand::ZoomBy(_)) {
+            self.pdf_texture = None;
+        }
+        if change.panned {
+            self.canvas_scroll.stop();
+        }
+    }
+    
+    fn load_pdf_page(&mut self, ctx: &egui::Context) {
+        let pixels_per_point = ctx.pixels_per_point();
+        let target_width = self.page_size().0 * self.view.zoom;
+        // Render in physical pixels so the page stays sharp on high-DPI screens
+        let pixel_width = (target_width * self.render_scale() * pixels_per_point) as i32;
+        let page_index = self.session.page;
         
-        let mut items = Vec::new();
+        let prefetched = self.prefetcher.as_mut().and_then(|prefetcher| prefetcher.take(page_index, pixel_width));
+        let rendered = prefetched.or_else(|| {
+            let (pdfium, pdf_bytes) = (self.session.pdfium.as_ref()?, self.session.pdf_bytes.as_ref()?);
+            let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, self.session.pdf_password.as_deref()).ok()?;
+            let page = document.pages().get(page_index as u16).ok()?;
+            let page_size = (page.width().value, page.height().value);
+            let (width, height) = renderer::page_pixel_size(page_size, pixel_width);
+            let image = renderer::render_pdf_page(&page, width, height)?;
+            Some(renderer::PrefetchedPage { image, page_size })
+        });
+        let Some(rendered) = rendered else { return };
+        self.pdf_page_size = rendered.page_size;
+        self.pdf_texture = Some(ctx.load_texture(
+            "pdf_page",
+            rendered.image,
+            Default::default()
+        ));
+        self.pdf_render_debounce.rendered(renderer::RenderTarget { width: target_width, pixels_per_point });
         
-        // Get items array from JSON
-        if let Some(json_items) = json_data.get("items").and_then(|v| v.as_array()) {
-            for json_item in json_items {
-                // Filter by current page
-                let page = json_item.get("page").and_then(|v| v.as_u64()).unwrap_or(0);
-                if page != self.pdf_page as u64 + 1 {
-                    continue;
+        // Queue the pages the reader is likely to turn to next
+        let depth = self.settings.prefetch_depth;
+        if depth == 0 {
+            self.prefetcher = None;
+            return;
+        }
+        if self.prefetcher.is_none() {
+            if let (Some(_), Some(pdf_bytes)) = (&self.session.pdfium, &self.session.pdf_bytes) {
+                self.prefetcher = Some(renderer::PagePrefetcher::new(pdf_bytes.clone(), self.session.pdf_password.clone(), self.session.pdfium_library.clone()));
+            }
+        }
+        if let Some(prefetcher) = self.prefetcher.as_mut() {
+            prefetcher.showing(page_index, pixel_width, self.session.page_count, depth);
+        }
+    }
+    
+    /// Render the continuous view's pages in `pages` that are missing or at
+    /// another zoom, a couple per frame so scrolling stays smooth, and drop
+    /// the textures of pages well out of sight
+    fn render_continuous_pages(&mut self, ctx: &egui::Context, pages: std::ops::Range<usize>) {
+        const PAGES_PER_FRAME: usize = 2;
+        // Pages this far outside the view keep their textures, for scrolling back
+        const KEEP: usize = 4;
+        let Some(sizes) = &self.continuous.sizes else { return };
+        let scale = self.view.zoom * self.render_scale() * ctx.pixels_per_point();
+        // While a zoom settles, stale pages stretch to fit like the single page does
+        let settling = self.pdf_render_debounce.is_pending();
+        let stale: Vec<(usize, i32)> = pages.clone()
+            .filter_map(|page| {
+                let pixel_width = (sizes.get(page)?.0 * scale) as i32;
+                match self.continuous.textures.get(&page) {
+                    Some((_, width)) if *width == pixel_width || settling => None,
+                    _ => Some((page, pixel_width)),
                 }
-                
-                // Extract bbox
-                let bbox = json_item.get("bbox");
-                if let Some(bbox) = bbox {
-                    if let (Some(left), Some(top), Some(width), Some(height)) = (
-                        bbox.get("left").and_then(|v| v.as_f64()),
-                        bbox.get("top").and_then(|v| v.as_f64()),
-                        bbox.get("width").and_then(|v| v.as_f64()),
-                        bbox.get("height").and_then(|v| v.as_f64()),
-                    ) {
-                        // Check coordinate origin
-                        let coord_origin = bbox.get("coord_origin")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("TOPLEFT");
-                        
-                        // Convert coordinates if needed
-                        let (final_top, final_height) = if coord_origin.contains("BOTTOMLEFT") {
-                            // Convert from BOTTOMLEFT to TOPLEFT
-                            // In BOTTOMLEFT: top is the upper edge, bottom is lower edge
-                            // We need to convert to TOPLEFT where Y increases downward
-                            let page_height = json_data.get("pages")
-                                .and_then(|pages| pages.as_array())
-                                .and_then(|pages| pages.get(self.pdf_page))
-                                .and_then(|page| page.get("height"))
-                                .and_then(|h| h.as_f64())
-                                .unwrap_or(792.0);
-                            // top in BOTTOMLEFT is the upper edge, so we convert it
-                            (page_height - top, height)
-                        } else {
-                            (top, height)
-                        };
-                        // Extract content
-                        let content = json_item.get("content")
-                            .or_else(|| json_item.get("text"))
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
+            })
+            .collect();
+        let textures = &mut self.continuous.textures;
+        textures.retain(|page, _| page + KEEP >= pages.start && *page < pages.end + KEEP);
+        // Opening the document reparses the whole PDF, so only do it when there's work
+        if stale.is_empty() {
+            return;
+        }
+        if stale.len() > PAGES_PER_FRAME {
+            self.repaint.animate();
+        }
+        self.session.with_document(|document| {
+            for &(index, pixel_width) in stale.iter().take(PAGES_PER_FRAME) {
+                let Ok(page) = document.pages().get(index as u16) else { continue };
+                let (width, height) = renderer::page_pixel_size((page.width().value, page.height().value), pixel_width);
+                if let Some(image) = renderer::render_pdf_page(&page, width, height) {
+                    textures.insert(index, (ctx.load_texture(format!("pdf_page_{}", index), image, Default::default()), pixel_width));
+                }
+            }
+        });
+    }
+    
+}
+
+impl Chonker3App {
+    /// Canvas state for the current page, with the view and collaborator overlays
+    fn document_state(&self) -> Option<types::DocumentState> {
+        let mut state = self.session.document_state()?;
+        self.item_filter.apply(&mut state.items);
+        state.zoom = self.view.zoom;
+        state.offset = (self.view.pan.x, self.view.pan.y);
+        state.rotation = self.view.rotation;
+        state.selected_items = self.selected_items.clone();
+        state.remote_editing = self.collab.as_ref()
+            .map(|c| c.remote_editing())
+            .unwrap_or_default();
+        if let Some((_, transcript)) = self.transcript.as_ref().filter(|_| self.highlight_divergent) {
+            state.divergent_items = self.session.divergent_items(transcript);
+        }
+        if self.show_pii {
+            state.pii_items = self.pii_hits.iter()
+                .filter(|hit| hit.page == self.session.page)
+                .map(|hit| hit.item_id.clone())
+                .collect();
+        }
+        if self.show_translation && self.translation_language().is_some() {
+            self.translation.lock().unwrap().apply(&mut state);
+        }
+        Some(state)
+    }
+}
+
+impl Chonker3App {
+    /// Alignment scores for the open PDF and extraction; empty if they were computed for another
+    fn alignment_scores(&self) -> BTreeMap<usize, f32> {
+        let current = (self.session.pdf_path.clone(), self.session.extracted_json.clone());
+        if self.alignment_for.as_ref() != Some(&current) {
+            return BTreeMap::new();
+        }
+        self.alignment_scores.lock().unwrap().clone()
+    }
+    
+    /// The translation of the open PDF and extraction, if it was made for them
+    fn translation(&self) -> Option<translation::Translation> {
+        self.translation_language().map(|_| self.translation.lock().unwrap().clone())
+    }
+    
+    /// Language of the translation, if there is one for the open PDF and extraction
+    fn translation_language(&self) -> Option<String> {
+        let current = (self.session.pdf_path.clone(), self.session.extracted_json.clone());
+        (self.translation_for.as_ref() == Some(&current)).then(|| self.translation.lock().unwrap().language.clone())
+    }
+    
+    /// Translate the current page, or every page, on a worker thread; pages
+    /// show translated as they come back, and pages already done are skipped
+    fn translate(&mut self, all_pages: bool) {
+        let Some(data) = self.session.extracted_data.clone() else { return };
+        if self.job.is_some() {
+            return;
+        }
+        let language = self.settings.llm.translate_to.trim().to_string();
+        if language.is_empty() {
+            self.toasts.info("Pick a language to translate into");
+            return;
+        }
+        let patch = self.session.to_patch();
+        let current = (self.session.pdf_path.clone(), self.session.extracted_json.clone());
+        let existing = self.translation().filter(|t| t.language == language);
+        if existing.is_none() {
+            self.translation = Arc::new(Mutex::new(translation::Translation::new(&language)));
+            self.translation_for = Some(current);
+        }
+        let pages: Vec<usize> = if all_pages { (0..self.session.page_count).collect() } else { vec![self.session.page] };
+        let pages: Vec<usize> = pages.into_iter()
+            .filter(|&page| existing.as_ref().is_none_or(|t| !t.covers(&chonker3::document::edited_page_items(&data, page, &patch))))
+            .collect();
+        self.show_translation = true;
+        if pages.is_empty() {
+            return;
+        }
+        let settings = self.settings.llm.clone();
+        let shared = self.translation.clone();
+        self.job = Some(Job::spawn(format!("Translating into {}", language), move |job| {
+            job.set_total(pages.len());
+            let mut count = 0;
+            for page in pages {
+                job.begin_step(format!("Page {}", page + 1))?;
+                let texts = translation::translate_page(&settings, &language, &data, page, &patch)?;
+                count += texts.len();
+                shared.lock().unwrap().texts.extend(texts);
+                job.finish_step();
+            }
+            Ok(format!("Translated {} items into {}", count, language))
+        }));
+    }
+    
+    /// Write the document as Markdown with the translations in place of the original text
+    fn export_translation(&mut self) {
+        let Some(translation) = self.translation().filter(|_| self.session.extracted_data.is_some()) else { return };
+        let default_name = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| format!("{}.{}.md", s.to_string_lossy(), translation.language.to_lowercase()))
+            .unwrap_or_else(|| "translated.md".to_string());
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Markdown", &["md"])
+            .set_file_name(default_name)
+            .save_file()
+        else {
+            return;
+        };
+        match self.session.export_translation(&path, &translation) {
+            Ok(()) => self.toasts.success(format!("Exported the {} translation to {}", translation.language, path.display())),
+            Err(e) => self.toasts.error(format!("Export failed: {}", e)),
+        }
+    }
+    
+    /// Score every page of the extraction on a worker thread
+    fn score_alignment(&mut self) {
+        let (Some(data), Some(pdf_bytes)) = (&self.session.extracted_data, self.session.pdf_bytes.clone()) else { return };
+        if self.job.is_some() {
+            return;
+        }
+        let patch = self.session.to_patch();
+        let pages: Vec<_> = (0..self.session.page_count)
+            .map(|page| chonker3::document::edited_page_items(data, page, &patch))
+            .collect();
+        let library = self.session.pdfium_library.clone();
+        let password = self.session.pdf_password.clone();
+        let scores = Arc::new(Mutex::new(BTreeMap::new()));
+        self.alignment_scores = scores.clone();
+        self.alignment_for = Some((self.session.pdf_path.clone(), self.session.extracted_json.clone()));
+        self.job = Some(Job::spawn("Scoring page alignment", move |job| {
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            let count = chonker3::alignment::score_pages(&pdfium, &pdf_bytes, password.as_deref(), &pages, &scores, job)?;
+            Ok(format!("Scored {} pages", count))
+        }));
+    }
+    
+    /// Page scrubber colored by alignment score, and the worst pages first
+    fn show_alignment(&mut self, ctx: &egui::Context) {
+        if !self.show_alignment {
+            return;
+        }
+        let scores = self.alignment_scores();
+        let mut open = true;
+        let mut jump_to = None;
+        let mut score = false;
+        egui::Window::new("Page alignment")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.label("How closely each page's extraction matches the rendered PDF page.");
+                let can_score = self.session.extracted_data.is_some() && self.session.pdfium.is_some() && self.job.is_none();
+                if ui.add_enabled(can_score, egui::Button::new(if scores.is_empty() { "Score pages" } else { "Rescore pages" })).clicked() {
+                    score = true;
+                }
+                if scores.is_empty() {
+                    return;
+                }
+                ui.separator();
+                
+                // Scrubber: one cell per page, red for poor alignment
+                let page_count = self.session.page_count.max(1);
+                let (rect, response) = ui.allocate_exact_size(Vec2::new(ui.available_width(), 22.0), egui::Sense::click());
+                let cell = rect.width() / page_count as f32;
+                for page in 0..page_count {
+                    let cell_rect = egui::Rect::from_min_size(Pos2::new(rect.left() + page as f32 * cell, rect.top()), Vec2::new(cell, rect.height()));
+                    let color = scores.get(&page).map_or(Color32::from_gray(60), |&score| alignment_color(score));
+                    ui.painter().rect_filled(cell_rect.shrink(0.5), 0.0, color);
+                    if page == self.session.page {
+                        ui.painter().rect_stroke(cell_rect, 0.0, egui::Stroke::new(2.0, Color32::WHITE));
+                    }
+                }
+                let hovered_page = response.hover_pos().map(|pos| (((pos.x - rect.left()) / cell) as usize).min(page_count - 1));
+                if let Some(page) = hovered_page {
+                    let text = match scores.get(&page) {
+                        Some(score) => format!("Page {}: {:.0}%", page + 1, score * 100.0),
+                        None => format!("Page {}: not scored", page + 1),
+                    };
+                    response.clone().on_hover_text(text);
+                    if response.clicked() {
+                        jump_to = Some(page);
+                    }
+                }
+                ui.separator();
+                
+                let mut worst: Vec<(usize, f32)> = scores.iter().map(|(&page, &score)| (page, score)).collect();
+                worst.sort_by(|a, b| a.1.total_cmp(&b.1));
+                ScrollArea::vertical().max_height(260.0).id_salt("alignment_list").show(ui, |ui| {
+                    for (page, score) in worst {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("■").color(alignment_color(score)));
+                            if ui.selectable_label(page == self.session.page, format!("Page {}: {:.0}%", page + 1, score * 100.0)).clicked() {
+                                jump_to = Some(page);
+                            }
+                        });
+                    }
+                });
+            });
+        self.show_alignment = open;
+        
+        if score {
+            self.score_alignment();
+        }
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    /// Items whose text doesn't fit their box on the current page; clicking one selects it
+    fn show_diagnostics(&mut self, ctx: &egui::Context) {
+        if !self.show_diagnostics {
+            return;
+        }
+        let items = self.session.document_state().map(|state| state.items).unwrap_or_default();
+        let mut open = true;
+        let mut select = None;
+        egui::Window::new("Diagnostics")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                if self.overflow_items.is_empty() {
+                    ui.label(format!("All text on page {} fits its boxes.", self.session.page + 1));
+                    return;
+                }
+                ui.label(format!("{} items on page {} overflow their boxes:", self.overflow_items.len(), self.session.page + 1));
+                ui.separator();
+                ScrollArea::vertical().max_height(300.0).id_salt("overflow_list").show(ui, |ui| {
+                    for (id, overflow) in &self.overflow_items {
+                        let content = items.iter().find(|item| &item.id == id).map_or("", |item| item.content.as_str());
+                        let preview: String = content.chars().take(40).map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("⚠").color(Palette::color(self.settings.palette.overflow)));
+                            if ui.selectable_label(self.selected_items.contains(id), preview).clicked() {
+                                select = Some(id.clone());
+                            }
+                            ui.label(RichText::new(overflow.describe()).weak());
+                        });
+                    }
+                });
+            });
+        self.show_diagnostics = open;
+        
+        if let Some(id) = select {
+            self.selected_items = vec![id];
+        }
+    }
+    
+    /// Reference list with the citations pointing at each entry
+    fn show_references(&mut self, ctx: &egui::Context) {
+        if !self.show_references {
+            return;
+        }
+        let references = self.session.references();
+        let mut open = true;
+        let mut jump_to = None;
+        let mut mark = false;
+        let mut export = false;
+        egui::Window::new("References")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let unresolved = references.citations.iter().filter(|c| c.references.is_empty()).count();
+                ui.label(format!(
+                    "{} references, {} citations ({} unresolved)",
+                    references.references.len(), references.citations.len(), unresolved,
+                ));
+                ui.horizontal(|ui| {
+                    let has_references = !references.references.is_empty();
+                    if ui.add_enabled(has_references, egui::Button::new("Export BibTeX...")).clicked() {
+                        export = true;
+                    }
+                    if ui.add_enabled(has_references, egui::Button::new("Mark as references"))
+                        .on_hover_text("Set the type of the items holding the entries to Reference")
+                        .clicked() {
+                        mark = true;
+                    }
+                });
+                if references.references.is_empty() {
+                    ui.label(RichText::new("No reference section found. Mark entries as Reference, or give the list a \"References\" heading.").weak());
+                    return;
+                }
+                ui.separator();
+                
+                ScrollArea::vertical().max_height(320.0).id_salt("references_list").show(ui, |ui| {
+                    for (index, reference) in references.references.iter().enumerate() {
+                        let label = reference.label.map(|l| format!("[{}] ", l)).unwrap_or_default();
+                        let author = reference.first_surname().unwrap_or("?");
+                        let year = reference.year.as_deref().unwrap_or("n.d.");
+                        let title = reference.title.as_deref().unwrap_or("");
+                        ui.horizontal(|ui| {
+                            let selected = self.selected_items.contains(&reference.item_id);
+                            if ui.selectable_label(selected, format!("{}{} ({}) {}", label, author, year, title))
+                                .on_hover_text(&reference.text)
+                                .clicked() {
+                                jump_to = Some((reference.page, reference.item_id.clone()));
+                            }
+                            let cited = references.citations_of(index).count();
+                            ui.label(RichText::new(format!("cited {}×", cited)).weak());
+                        });
+                    }
+                    
+                    if unresolved > 0 {
+                        ui.separator();
+                        ui.collapsing(format!("Unresolved citations ({})", unresolved), |ui| {
+                            for citation in references.citations.iter().filter(|c| c.references.is_empty()) {
+                                if ui.selectable_label(false, format!("Page {}: {}", citation.page + 1, citation.text)).clicked() {
+                                    jump_to = Some((citation.page, citation.item_id.clone()));
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+        self.show_references = open;
+        
+        if mark {
+            let ids: std::collections::BTreeSet<&String> = references.references.iter().map(|r| &r.item_id).collect();
+            for id in &ids {
+                self.session.set_type(id, Some(types::ItemType::Reference));
+            }
+            self.toasts.success(format!("Marked {} items as references", ids.len()));
+        }
+        if export {
+            self.export_with_dialog(ExportKind::Bibtex);
+        }
+        if let Some((page, item_id)) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+            self.selected_items = vec![item_id];
+        }
+    }
+    
+    /// Details of the selected item and the steps that produced it, for telling
+    /// OCR mistakes from extractor and post-processing ones
+    fn show_inspector(&mut self, ctx: &egui::Context) {
+        if !self.show_inspector {
+            return;
+        }
+        let item = self.selected_items.first().and_then(|id| self.session.item(id));
+        let mut open = true;
+        let mut reocr_item = None;
+        egui::Window::new("Item inspector")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                let Some(item) = item else {
+                    ui.label(RichText::new("Select an item to inspect it").color(Color32::GRAY));
+                    return;
+                };
+                egui::Grid::new("inspector_grid").num_columns(2).striped(true).show(ui, |ui| {
+                    let page = types::item_page(&item.id).map_or("?".to_string(), |page| (page + 1).to_string());
+                    let b = &item.bbox;
+                    let confidence = item.confidence.map_or("not reported".to_string(), |c| format!("{:.1}%", c * 100.0));
+                    for (label, value) in [
+                        ("Type", item.item_type.label().to_string()),
+                        ("Page", page),
+                        ("Box", format!("{:.1}, {:.1}  {:.1} × {:.1} pt", b.left, b.top, b.width, b.height)),
+                        ("Font size", format!("{:.1} pt", item.font_size)),
+                        ("Confidence", confidence),
+                    ] {
+                        ui.label(label);
+                        ui.label(RichText::new(value).strong());
+                        ui.end_row();
+                    }
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Provenance").strong());
+                if item.provenance.is_empty() {
+                    ui.label(RichText::new("Not recorded; re-extract to see which backend and steps produced this item").color(Color32::GRAY));
+                }
+                for (step_number, step) in item.provenance.iter().enumerate() {
+                    ui.label(format!("{}. {}: {}", step_number + 1, step.stage.label(), step.name));
+                }
+                
+                let edits = &self.session.edits;
+                let changes: Vec<&str> = [
+                    (edits.text_overrides.contains_key(&item.id), "text edited"),
+                    (edits.type_overrides.contains_key(&item.id), "type changed"),
+                    (edits.offsets.contains_key(&item.id), "moved"),
+                    (edits.boxes.contains_key(&item.id), "box corrected"),
+                    (edits.annotations.contains_key(&item.id), "annotated"),
+                ].into_iter().filter_map(|(edited, change)| edited.then_some(change)).collect();
+                if !changes.is_empty() {
+                    ui.separator();
+                    ui.label(RichText::new("Your edits").strong());
+                    ui.label(changes.join(", "));
+                }
+                
+                ui.separator();
+                if ui.add_enabled(self.job.is_none(), egui::Button::new(format!("🔍 Re-OCR at {} dpi", reocr::REOCR_DPI)))
+                    .on_hover_text("Read just this item's box again and replace its text")
+                    .clicked() {
+                    reocr_item = Some(item.id.clone());
+                }
+            });
+        self.show_inspector = open;
+        
+        if let Some(item_id) = reocr_item {
+            self.reocr_item(&item_id);
+        }
+    }
+    
+    /// Summarize the page, or the document when None, with the LLM on a worker
+    /// thread. Documents are summarized from their page summaries, which keeps
+    /// long documents within the model's context.
+    fn summarize_with_llm(&mut self, page: Option<usize>) {
+        let Some(data) = &self.session.extracted_data else { return };
+        if self.job.is_some() {
+            return;
+        }
+        let patch = self.session.to_patch();
+        let (prompt, text) = match page {
+            Some(page) => (llm::LlmTask::Summarize.instructions().to_string(), summary::page_text(data, page, &patch)),
+            None => {
+                let pages: Vec<String> = (0..self.session.page_count).map(|page| {
+                    let text = self.session.summaries.pages.get(&page)
+                        .map(|s| s.text.clone())
+                        .unwrap_or_else(|| summary::page_summary(data, page, &patch).text);
+                    format!("Page {}: {}", page + 1, text)
+                }).collect();
+                ("Summarize the document from the following page summaries in a few sentences. Reply with the summary only.".to_string(), pages.join("\n"))
+            }
+        };
+        if text.trim().is_empty() {
+            self.toasts.info("There is no text to summarize");
+            return;
+        }
+        let settings = self.settings.llm.clone();
+        let reply = self.llm_summary.clone();
+        self.llm_summary_page = page;
+        self.job_followup = JobFollowup::ApplyLlmSummary;
+        self.job_document = self.session.pdf_path.clone();
+        self.job = Some(Job::spawn("Summarizing", move |job| {
+            job.set_total(1);
+            job.begin_step(format!("Asking {}", settings.model))?;
+            let text = llm::complete_text(&settings, &prompt, &text)?;
+            job.finish_step();
+            *reply.lock().unwrap() = Some(text);
+            Ok("Summarized".to_string())
+        }));
+    }
+    
+    fn apply_llm_summary(&mut self) {
+        let Some(text) = self.llm_summary.lock().unwrap().take() else { return };
+        let llm_summary = summary::Summary { method: summary::SummaryMethod::Llm, text };
+        match self.llm_summary_page {
+            Some(page) => { self.session.summaries.pages.insert(page, llm_summary); }
+            None => self.session.summaries.document = Some(llm_summary),
+        }
+        self.show_summaries = true;
+    }
+    
+    /// Summaries of the current page and the document, made on request
+    fn show_summaries(&mut self, ctx: &egui::Context) {
+        if !self.show_summaries {
+            return;
+        }
+        if !self.settings.llm.enabled {
+            self.summary_method = summary::SummaryMethod::TextRank;
+        }
+        let page = self.session.page;
+        let mut open = true;
+        let mut summarize = None;
+        let mut summarize_all = false;
+        let mut save = false;
+        egui::Window::new("Summaries")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Method:");
+                    ui.radio_value(&mut self.summary_method, summary::SummaryMethod::TextRank, "TextRank")
+                        .on_hover_text("Picks the most central sentences, locally");
+                    ui.add_enabled_ui(self.settings.llm.enabled, |ui| {
+                        ui.radio_value(&mut self.summary_method, summary::SummaryMethod::Llm, "LLM")
+                    }).response.on_disabled_hover_text("Set up an endpoint under Settings → LLM post-processing");
+                });
+                let can_run = self.job.is_none() && self.session.extracted_data.is_some();
+                for (heading, target) in [(format!("Page {}", page + 1), Some(page)), ("Document".to_string(), None)] {
+                    ui.separator();
+                    let cached = match target {
+                        Some(page) => self.session.summaries.pages.get(&page),
+                        None => self.session.summaries.document.as_ref(),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&heading).strong());
+                        if let Some(cached) = cached {
+                            ui.label(RichText::new(cached.method.label()).weak());
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.add_enabled(can_run, egui::Button::new(if cached.is_some() { "Redo" } else { "Summarize" })).clicked() {
+                                summarize = Some(target);
+                            }
+                            if let Some(cached) = cached {
+                                if ui.button("Copy").clicked() {
+                                    ui.ctx().copy_text(cached.text.clone());
+                                    self.toasts.success("Copied to clipboard");
+                                }
+                            }
+                        });
+                    });
+                    match cached {
+                        Some(cached) if cached.text.is_empty() => { ui.label(RichText::new("No running text to summarize").weak()); }
+                        Some(cached) => { ui.label(&cached.text); }
+                        None => { ui.label(RichText::new("Not summarized yet").weak()); }
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(can_run, egui::Button::new("Summarize every page"))
+                        .on_hover_text("With TextRank; pages summarized by the LLM are kept")
+                        .clicked() {
+                        summarize_all = true;
+                    }
+                    if ui.add_enabled(!self.session.summaries.is_empty(), egui::Button::new("Save as Markdown...")).clicked() {
+                        save = true;
+                    }
+                });
+                ui.label(RichText::new("Summaries go into exported bundles").weak().small());
+            });
+        self.show_summaries = open;
+        
+        if let Some(target) = summarize {
+            if self.summary_method == summary::SummaryMethod::Llm {
+                self.summarize_with_llm(target);
+            } else {
+                let result = match target {
+                    Some(page) => self.session.summarize_page(page).map(|_| ()),
+                    None => self.session.summarize_document().map(|_| ()),
+                };
+                if let Err(e) = result {
+                    self.toasts.error(format!("Summarizing failed: {}", e));
+                }
+            }
+        }
+        if summarize_all {
+            for page in 0..self.session.page_count {
+                let by_llm = self.session.summaries.pages.get(&page).is_some_and(|s| s.method == summary::SummaryMethod::Llm);
+                if !by_llm && self.session.summarize_page(page).is_err() {
+                    break;
+                }
+            }
+        }
+        if save {
+            let stem = self.session.pdf_path.as_ref()
+                .and_then(|p| p.file_stem())
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "document".to_string());
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Markdown", &["md"])
+                .set_file_name(format!("{}.summary.md", stem))
+                .save_file()
+            {
+                match self.session.export_summaries(&path) {
+                    Ok(()) => self.toasts.success(format!("Saved summaries to {}", path.display())),
+                    Err(e) => self.toasts.error(format!("Failed to save {}: {:#}", path.display(), e)),
+                }
+            }
+        }
+    }
+    
+    /// Counts, confidence and problem pages of the current extraction
+    fn show_stats(&mut self, ctx: &egui::Context) {
+        if !self.show_stats {
+            return;
+        }
+        let Some(stats) = self.session.stats() else { return };
+        let mut open = true;
+        let mut export = false;
+        let mut jump_to = None;
+        egui::Window::new("Document statistics")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(340.0)
+            .show(ctx, |ui| {
+                egui::Grid::new("stats_grid").num_columns(2).striped(true).show(ui, |ui| {
+                    let extraction_time = stats.extraction_seconds
+                        .map_or("not recorded".to_string(), |s| jobs::format_duration(std::time::Duration::from_secs_f64(s)));
+                    let confidence = stats.average_confidence
+                        .map_or("not reported".to_string(), |c| format!("{:.1}%", c * 100.0));
+                    for (label, value) in [
+                        ("Extractor", stats.extractor.clone().unwrap_or_else(|| "unknown".to_string())),
+                        ("Extraction time", extraction_time),
+                        ("Pages", stats.pages.to_string()),
+                        ("Items", stats.items.to_string()),
+                        ("Words", stats.words.to_string()),
+                        ("Characters", stats.characters.to_string()),
+                        ("Tables", stats.tables.to_string()),
+                        ("Images", stats.images.to_string()),
+                        ("Average confidence", confidence),
+                        ("Low-confidence items", stats.low_confidence_items.to_string()),
+                    ] {
+                        ui.label(label);
+                        ui.label(RichText::new(value).strong());
+                        ui.end_row();
+                    }
+                });
+                
+                ui.collapsing("Items by type", |ui| {
+                    egui::Grid::new("stats_types").num_columns(2).show(ui, |ui| {
+                        for (label, count) in &stats.items_by_type {
+                            ui.label(label);
+                            ui.label(count.to_string());
+                            ui.end_row();
+                        }
+                    });
+                });
+                
+                ui.separator();
+                ui.label(RichText::new(format!("Pages with issues ({})", stats.pages_with_issues.len())).strong());
+                ScrollArea::vertical().max_height(200.0).id_salt("stats_issues").show(ui, |ui| {
+                    for issues in &stats.pages_with_issues {
+                        let reason = if issues.empty {
+                            "nothing extracted".to_string()
+                        } else {
+                            format!("{} low-confidence items", issues.low_confidence_items)
+                        };
+                        if ui.selectable_label(issues.page == self.session.page + 1, format!("Page {}: {}", issues.page, reason)).clicked() {
+                            jump_to = Some(issues.page - 1);
+                        }
+                    }
+                });
+                
+                ui.separator();
+                if ui.button("Export JSON...").clicked() {
+                    export = true;
+                }
+            });
+        self.show_stats = open;
+        
+        if export {
+            self.export_with_dialog(ExportKind::Stats);
+        }
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    /// Strip with one cell per page showing where edits, low-confidence items and
+    /// text the extraction missed cluster; click a cell to go to the page
+    fn show_heatmap(&mut self, ctx: &egui::Context) {
+        if !self.show_heatmap || self.session.extracted_data.is_none() {
+            return;
+        }
+        let Some(pdf_path) = self.session.pdf_path.clone() else { return };
+        if self.text_layer_chars.as_ref().map(|(path, _)| path) != Some(&pdf_path) {
+            self.text_layer_chars = Some((pdf_path, self.session.text_layer_chars()));
+        }
+        let text_layer = self.text_layer_chars.as_ref().map(|(_, chars)| chars.as_slice()).unwrap_or_default();
+        let heat = self.session.page_heat(text_layer);
+        if heat.is_empty() {
+            return;
+        }
+        let max_edits = heat.iter().map(|h| h.edits).max().unwrap_or(0).max(1) as f32;
+        let max_low = heat.iter().map(|h| h.low_confidence_items).max().unwrap_or(0).max(1) as f32;
+        let bands = [
+            Color32::from_rgb(59, 130, 246),
+            Color32::from_rgb(245, 158, 11),
+            Color32::from_rgb(239, 68, 68),
+        ];
+        let mut jump_to = None;
+        egui::TopBottomPanel::top("heatmap")
+            .exact_height(34.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("■ edits").color(bands[0]).small());
+                    ui.label(RichText::new("■ low confidence").color(bands[1]).small());
+                    ui.label(RichText::new("■ missing text").color(bands[2]).small());
+                    
+                    let (rect, response) = ui.allocate_exact_size(Vec2::new(ui.available_width(), 24.0), egui::Sense::click());
+                    let cell = rect.width() / heat.len() as f32;
+                    let band = rect.height() / bands.len() as f32;
+                    for (page, h) in heat.iter().enumerate() {
+                        let levels = [h.edits as f32 / max_edits, h.low_confidence_items as f32 / max_low, h.coverage_gap()];
+                        for (row, (level, color)) in levels.iter().zip(bands).enumerate() {
+                            let band_rect = egui::Rect::from_min_size(
+                                Pos2::new(rect.left() + page as f32 * cell, rect.top() + row as f32 * band),
+                                Vec2::new(cell, band),
+                            );
+                            ui.painter().rect_filled(band_rect.shrink(0.5), 0.0, lerp_color(Color32::from_gray(45), color, *level));
+                        }
+                        if page == self.session.page {
+                            let cell_rect = egui::Rect::from_min_size(Pos2::new(rect.left() + page as f32 * cell, rect.top()), Vec2::new(cell, rect.height()));
+                            ui.painter().rect_stroke(cell_rect, 0.0, egui::Stroke::new(2.0, Color32::WHITE));
+                        }
+                    }
+                    if let Some(pos) = response.hover_pos() {
+                        let page = (((pos.x - rect.left()) / cell) as usize).min(heat.len() - 1);
+                        let h = &heat[page];
+                        let coverage = h.coverage.map_or("no text layer".to_string(), |c| format!("{:.0}% of the text layer extracted", c * 100.0));
+                        response.clone().on_hover_text(format!(
+                            "Page {}\n{} edits\n{} low-confidence items\n{}",
+                            page + 1, h.edits, h.low_confidence_items, coverage,
+                        ));
+                        if response.clicked() {
+                            jump_to = Some(page);
+                        }
+                    }
+                });
+            });
+        
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    /// Decoded barcodes and QR codes, with detection on the rendered pages
+    /// Ask for the open PDF's password
+    fn show_password_prompt(&mut self, ctx: &egui::Context) {
+        if !self.password_prompt {
+            return;
+        }
+        let name = self.session.source_file_name().unwrap_or_default();
+        let mut open = true;
+        let mut submit = false;
+        egui::Window::new("🔒 Password required")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} is encrypted. Enter its password to open it.", name));
+                let response = ui.add(egui::TextEdit::singleline(&mut self.password_buffer).password(true).hint_text("Password"));
+                response.request_focus();
+                submit |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if let Some(error) = &self.password_error {
+                    ui.colored_label(Color32::from_rgb(220, 38, 38), error);
+                }
+                if ui.checkbox(&mut self.settings.remember_pdf_passwords, "Remember in the system keyring").changed() {
+                    if let Err(e) = self.settings.save() {
+                        self.toasts.error(format!("Failed to save settings: {}", e));
+                    }
+                }
+                submit |= ui.button("Unlock").clicked();
+            });
+        if !open {
+            self.password_prompt = false;
+            self.status_message = "PDF is password protected; reopen it to enter the password.".to_string();
+            return;
+        }
+        if !submit {
+            return;
+        }
+        if !self.session.unlock(&self.password_buffer) {
+            self.password_error = Some("Wrong password".to_string());
+            return;
+        }
+        if self.settings.remember_pdf_passwords {
+            if let Some(bytes) = &self.session.pdf_bytes {
+                if let Err(e) = chonker3::passwords::remember(bytes, &self.password_buffer) {
+                    self.toasts.error(e.to_string());
+                }
+            }
+        }
+        self.password_prompt = false;
+        self.password_buffer.clear();
+        self.password_error = None;
+        self.pdf_texture = None;
+        self.prefetcher = None;
+        self.continuous = ContinuousPages::default();
+        self.status_message = "PDF unlocked. Click 'Extract' to process.".to_string();
+        self.einvoice = self.session.with_document(einvoice::find_embedded_invoice).flatten();
+        self.auto_extract();
+    }
+    
+    /// Past extraction runs, newest first, each one re-runnable with its options
+    fn show_extraction_history(&mut self, ctx: &egui::Context) {
+        if !self.show_extraction_history {
+            return;
+        }
+        let mut open = true;
+        let mut rerun = None;
+        let mut clear = false;
+        egui::Window::new("🕘 Extraction history")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                if self.workspace.extraction_history.is_empty() {
+                    ui.label(RichText::new("No extractions yet").weak());
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} runs", self.workspace.extraction_history.len()));
+                    if ui.small_button("Clear").clicked() {
+                        clear = true;
+                    }
+                });
+                ui.separator();
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("extraction_history").num_columns(5).striped(true).show(ui, |ui| {
+                        for (index, run) in self.workspace.extraction_history.iter().enumerate().rev() {
+                            let when = chrono::DateTime::parse_from_rfc3339(&run.started)
+                                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                                .unwrap_or_else(|_| run.started.clone());
+                            ui.label(when);
+                            let name = run.document.file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_else(|| run.document.display().to_string());
+                            ui.label(name).on_hover_text(run.document.display().to_string());
+                            let backend = run.backend.clone().unwrap_or_else(|| run.extractor.label().to_string());
+                            let mut details = vec![format!("Extractor: {}", run.extractor.label())];
+                            if !run.page_backends.is_empty() {
+                                details.push(format!("{} pages with their own backend", run.page_backends.len()));
+                            }
+                            details.push(format!("Preprocessing: {}", if run.options.preprocess { "on" } else { "off" }));
+                            details.push(format!("Scan cleanup: {}", if run.options.scan_cleanup.enabled { "on" } else { "off" }));
+                            ui.label(backend).on_hover_text(details.join("\n"));
+                            ui.label(format!("{:.1} s", run.seconds));
+                            ui.horizontal(|ui| {
+                                match (&run.json_path, &run.error) {
+                                    (_, Some(error)) => {
+                                        ui.colored_label(Color32::from_rgb(220, 38, 38), "failed").on_hover_text(error);
+                                    }
+                                    (Some(json_path), None) => {
+                                        ui.label(format!("{} items", run.items)).on_hover_text(json_path.display().to_string());
+                                    }
+                                    (None, None) => {}
+                                }
+                                if ui.add_enabled(!self.is_extracting && run.document.exists(), egui::Button::new("↻ Re-run").small())
+                                    .on_hover_text("Extract this document again with the same options")
+                                    .clicked() {
+                                    rerun = Some(index);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_extraction_history = open;
+        
+        if clear {
+            self.workspace.extraction_history.clear();
+            if let Err(e) = self.workspace.save() {
+                log::warn!("Failed to save workspace: {}", e);
+            }
+        }
+        if let Some(run) = rerun.and_then(|index| self.workspace.extraction_history.get(index).cloned()) {
+            let is_open = |app: &Self| app.session.pdf_path.as_ref()
+                .is_some_and(|path| path.canonicalize().ok().as_ref() == Some(&run.document));
+            if !is_open(self) {
+                self.load_pdf(run.document.clone());
+            }
+            if is_open(self) && !self.password_prompt {
+                self.extract_with(run.extractor, run.rerun_options(), false);
+            }
+        }
+    }
+    
+    /// Remember the search bar's query for the open document
+    fn remember_search(&mut self) {
+        let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) else { return };
+        self.workspace.remember_search(index, &self.session.search_query);
+        if let Err(e) = self.workspace.save() {
+            log::warn!("Failed to save workspace: {}", e);
+        }
+    }
+    
+    /// Run a saved search on the workspace documents the tag filter lets through
+    fn run_saved_search(&mut self, saved: &chonker3::search::SavedSearch) {
+        let matcher = match saved.matcher() {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.toasts.error(format!("\"{}\": {}", saved.name, e));
+                return;
+            }
+        };
+        let current = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p));
+        let others: Vec<usize> = (0..self.workspace.documents.len())
+            .filter(|&i| Some(i) != current && self.workspace.documents[i].matches_tags(&self.workspace_tag_filter))
+            .collect();
+        let mut results = self.workspace.search(&others, &matcher);
+        // The open document is searched as edited
+        if let Some(index) = current {
+            let hits = self.session.search_with(&matcher);
+            if !hits.is_empty() {
+                results.insert(0, (index, hits));
+            }
+        }
+        self.saved_search_results = Some((saved.name.clone(), results));
+    }
+    
+    fn show_saved_searches(&mut self, ctx: &egui::Context) {
+        if !self.show_saved_searches {
+            return;
+        }
+        let mut open = true;
+        let mut run = None;
+        let mut remove = None;
+        let mut save = false;
+        let mut jump_to = None;
+        egui::Window::new("☆ Saved searches")
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if self.workspace.saved_searches.is_empty() {
+                    ui.label(RichText::new("No saved searches yet").weak());
+                }
+                egui::Grid::new("saved_searches").num_columns(3).striped(true).show(ui, |ui| {
+                    for (index, saved) in self.workspace.saved_searches.iter().enumerate() {
+                        ui.label(RichText::new(&saved.name).strong());
+                        let query = RichText::new(&saved.query).monospace();
+                        ui.label(if saved.regex { query.italics() } else { query })
+                            .on_hover_text(if saved.regex { "Regular expression" } else { "Words" });
+                        ui.horizontal(|ui| {
+                            if ui.small_button("Run").on_hover_text("Search the workspace documents").clicked() {
+                                run = Some(index);
+                            }
+                            if ui.small_button("✕").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add_sized(Vec2::new(120.0, 20.0), egui::TextEdit::singleline(&mut self.saved_search_draft.name).hint_text("name"));
+                    ui.add_sized(Vec2::new(180.0, 20.0), egui::TextEdit::singleline(&mut self.saved_search_draft.query).hint_text("search"));
+                    ui.checkbox(&mut self.saved_search_draft.regex, "Regex")
+                        .on_hover_text("Match a regular expression, e.g. INV-\\d{6}");
+                    if ui.button("Save").clicked() {
+                        save = true;
+                    }
+                });
+                if let Some(error) = &self.saved_search_error {
+                    ui.colored_label(Color32::from_rgb(220, 38, 38), error);
+                }
+                
+                let Some((name, results)) = &self.saved_search_results else { return };
+                ui.separator();
+                let total: usize = results.iter().map(|(_, hits)| hits.len()).sum();
+                ui.label(format!("\"{}\": {} matches in {} documents", name, total, results.len()));
+                ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (index, hits) in results {
+                        let Some(doc) = self.workspace.documents.get(*index) else { continue };
+                        egui::CollapsingHeader::new(format!("{} ({})", doc.display_name(), hits.len()))
+                            .id_salt(("saved_search_document", index))
+                            .default_open(results.len() == 1)
+                            .show(ui, |ui| {
+                                for hit in hits.iter().take(SEARCH_RESULTS_SHOWN) {
+                                    if ui.selectable_label(false, format!("p. {}  {}", hit.page + 1, hit.snippet)).clicked() {
+                                        jump_to = Some((doc.path.clone(), hit.clone()));
+                                    }
+                                }
+                                if hits.len() > SEARCH_RESULTS_SHOWN {
+                                    ui.label(RichText::new(format!("{} more", hits.len() - SEARCH_RESULTS_SHOWN)).weak());
+                                }
+                            });
+                    }
+                });
+            });
+        self.show_saved_searches = open;
+        
+        if save {
+            let mut draft = self.saved_search_draft.clone();
+            draft.name = draft.name.trim().to_string();
+            self.saved_search_error = if draft.name.is_empty() {
+                Some("Give the search a name".to_string())
+            } else {
+                draft.matcher().err().map(|e| e.to_string())
+            };
+            if self.saved_search_error.is_none() {
+                self.workspace.save_search(draft);
+                self.saved_search_draft = Default::default();
+                if let Err(e) = self.workspace.save() {
+                    self.toasts.error(format!("Failed to save workspace: {}", e));
+                }
+            }
+        }
+        if let Some(index) = remove {
+            self.workspace.saved_searches.remove(index);
+            if let Err(e) = self.workspace.save() {
+                self.toasts.error(format!("Failed to save workspace: {}", e));
+            }
+        }
+        if let Some(saved) = run.and_then(|index| self.workspace.saved_searches.get(index).cloned()) {
+            self.run_saved_search(&saved);
+        }
+        if let Some((path, hit)) = jump_to {
+            let is_open = self.session.pdf_path.as_ref()
+                .is_some_and(|open| open.canonicalize().ok().as_ref() == Some(&path));
+            if !is_open {
+                self.load_pdf(path);
+                self.auto_extract();
+            }
+            if self.session.go_to_page(hit.page) {
+                self.pdf_texture = None;
+            }
+            self.selected_items = vec![hit.item_id];
+        }
+    }
+    
+    fn show_barcodes(&mut self, ctx: &egui::Context) {
+        if !self.show_barcodes {
+            return;
+        }
+        let found = self.session.barcodes();
+        let mut open = true;
+        let mut detect = false;
+        let mut jump_to = None;
+        egui::Window::new("Barcodes")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                ui.label("Code 128, Code 39, EAN-13/UPC-A and QR codes (up to version 10) are decoded from the rendered pages.");
+                ui.horizontal(|ui| {
+                    let can_detect = self.session.pdfium.is_some() && self.session.pdf_bytes.is_some() && self.job.is_none();
+                    if ui.add_enabled(can_detect, egui::Button::new("Detect barcodes"))
+                        .on_hover_text("Replaces the barcode items of the extraction; needs pdfium")
+                        .clicked() {
+                        detect = true;
+                    }
+                    ui.label(RichText::new(format!("{} found", found.len())).weak());
+                });
+                if found.is_empty() {
+                    return;
+                }
+                ui.separator();
+                
+                ScrollArea::vertical().max_height(320.0).id_salt("barcodes_list").show(ui, |ui| {
+                    egui::Grid::new("barcodes_grid").striped(true).show(ui, |ui| {
+                        ui.label(RichText::new("Page").strong());
+                        ui.label(RichText::new("Type").strong());
+                        ui.label(RichText::new("Payload").strong());
+                        ui.end_row();
+                        for barcode in &found {
+                            let selected = self.selected_items.contains(&barcode.item_id);
+                            if ui.selectable_label(selected, format!("Page {}", barcode.page + 1)).clicked() {
+                                jump_to = Some((barcode.page, barcode.item_id.clone()));
+                            }
+                            ui.label(barcode.symbology.map_or("Barcode", |s| s.label()));
+                            ui.label(RichText::new(&barcode.payload).monospace());
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_barcodes = open;
+        
+        if detect {
+            self.detect_barcodes();
+        }
+        if let Some((page, item_id)) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+            self.selected_items = vec![item_id];
+        }
+    }
+    
+    fn show_table_stitching(&mut self, ctx: &egui::Context) {
+        if !self.show_table_stitching {
+            return;
+        }
+        let continuations = self.session.table_continuations();
+        let patch = self.session.to_patch();
+        let mut open = true;
+        let mut decision = None;
+        let mut jump_to = None;
+        egui::Window::new("Table continuations")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("Tables that carry on across a page break are joined into one for the table CSV and XLSX exports. Reject any that aren't one table.");
+                if continuations.is_empty() {
+                    ui.label(RichText::new("No tables continue across pages").color(Color32::GRAY));
+                    return;
+                }
+                ui.separator();
+                
+                ScrollArea::vertical().max_height(320.0).id_salt("table_stitching_list").show(ui, |ui| {
+                    egui::Grid::new("table_stitching_grid").striped(true).show(ui, |ui| {
+                        ui.label(RichText::new("Pages").strong());
+                        ui.label(RichText::new("Why").strong());
+                        ui.label(RichText::new("Stitch").strong());
+                        ui.end_row();
+                        for continuation in &continuations {
+                            let selected = self.selected_items.contains(&continuation.to);
+                            if ui.selectable_label(selected, format!("{} → {}", continuation.page, continuation.page + 1)).clicked() {
+                                jump_to = Some((continuation.page, continuation.to.clone()));
+                            }
+                            let signs: Vec<&str> = continuation.signs.iter().map(|s| s.label()).collect();
+                            ui.label(format!("{} columns, {}", continuation.columns, signs.join(", ")));
+                            let decided = patch.table_stitches.get(&continuation.to).copied();
+                            ui.horizontal(|ui| {
+                                if ui.selectable_label(decided == Some(true), "Confirm").clicked() {
+                                    decision = Some((continuation.to.clone(), true));
+                                }
+                                if ui.selectable_label(decided == Some(false), "Reject").clicked() {
+                                    decision = Some((continuation.to.clone(), false));
+                                }
+                                if decided.is_none() {
+                                    ui.label(RichText::new("proposed").weak());
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_table_stitching = open;
+        
+        if let Some((table_id, stitched)) = decision {
+            self.session.set_table_stitch(&table_id, stitched);
+        }
+        if let Some((page, item_id)) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+            self.selected_items = vec![item_id];
+        }
+    }
+    
+    /// Decode the barcodes on every page on a worker thread
+    fn detect_barcodes(&mut self) {
+        let Some(pdf_bytes) = self.session.pdf_bytes.clone() else { return };
+        let password = self.session.pdf_password.clone();
+        let library = self.session.pdfium_library.clone();
+        let detected = self.detected_barcodes.clone();
+        detected.lock().unwrap().clear();
+        
+        self.job_followup = JobFollowup::AddBarcodes;
+        self.job_document = self.session.pdf_path.clone();
+        self.job = Some(Job::spawn("Detecting barcodes", move |job| {
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            let found = barcodes::detect_pdf(&pdfium, &pdf_bytes, password.as_deref(), job)?;
+            let count = found.len();
+            *detected.lock().unwrap() = found;
+            Ok(format!("Found {} barcodes", count))
+        }));
+    }
+    
+    /// Re-OCR a region of a (zero-based) page at a high resolution on a worker thread
+    fn reocr(&mut self, page: usize, region: types::BoundingBox, target: ReocrTarget) {
+        if !python_env::venv_python(&python_env::venv_dir()).exists() {
+            self.open_python_setup();
+            return;
+        }
+        let Some(pdf_bytes) = self.session.pdf_bytes.clone() else { return };
+        let password = self.session.pdf_password.clone();
+        let library = self.session.pdfium_library.clone();
+        let result = self.reocr_result.clone();
+        
+        self.reocr_target = Some(target);
+        self.job_followup = JobFollowup::ApplyReocr;
+        self.job_document = self.session.pdf_path.clone();
+        self.job = Some(Job::spawn("Re-running OCR", move |job| {
+            job.set_total(1);
+            job.begin_step(format!("Reading page {} at {} dpi", page + 1, reocr::REOCR_DPI))?;
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            let ocr = reocr::reocr_region(&pdfium, &pdf_bytes, password.as_deref(), page, &region)?;
+            job.finish_step();
+            let message = format!("Read {} characters with {}", ocr.text.chars().count(), ocr.engine);
+            *result.lock().unwrap() = Some(ocr);
+            Ok(message)
+        }));
+    }
+    
+    /// Re-OCR the item's box
+    fn reocr_item(&mut self, item_id: &str) {
+        let Some(item) = self.session.item(item_id) else { return };
+        let Some(page) = types::item_page(item_id) else { return };
+        self.reocr(page, item.bbox, ReocrTarget::Item(item_id.to_string()));
+    }
+    
+    /// Re-OCR a region the user dragged out: the text replaces the one item it
+    /// covers, or becomes a new item over empty space
+    fn reocr_region(&mut self, region: types::BoundingBox) {
+        match self.selected_items.as_slice() {
+            [] => self.reocr(self.session.page, region.clone(), ReocrTarget::Region(self.session.page, region)),
+            [item_id] => {
+                let target = ReocrTarget::Item(item_id.clone());
+                self.reocr(self.session.page, region, target);
+            }
+            items => self.toasts.error(format!("The region covers {} items; draw around one item or empty space", items.len())),
+        }
+    }
+    
+    /// Read the image on the clipboard on a worker thread
+    fn quick_ocr_paste(&mut self, ctx: &egui::Context) {
+        if !python_env::venv_python(&python_env::venv_dir()).exists() {
+            self.open_python_setup();
+            return;
+        }
+        let image = match reocr::clipboard_image() {
+            Ok(image) => image,
+            Err(e) => {
+                self.toasts.error(e.to_string());
+                return;
+            }
+        };
+        let rgba = image.to_rgba8();
+        let preview = egui::ColorImage::from_rgba_unmultiplied([rgba.width() as usize, rgba.height() as usize], rgba.as_raw());
+        self.quick_ocr_image = Some(ctx.load_texture("quick_ocr_image", preview, egui::TextureOptions::LINEAR));
+        self.quick_ocr_text = None;
+        
+        let result = self.reocr_result.clone();
+        self.reocr_target = Some(ReocrTarget::QuickTool);
+        self.job_followup = JobFollowup::ApplyReocr;
+        self.job_document = self.session.pdf_path.clone();
+        self.job = Some(Job::spawn("Reading pasted image", move |job| {
+            job.set_total(1);
+            job.begin_step("Running OCR")?;
+            let ocr = reocr::recognize(&image)?;
+            job.finish_step();
+            let message = format!("Read {} characters with {}", ocr.text.chars().count(), ocr.engine);
+            *result.lock().unwrap() = Some(ocr);
+            Ok(message)
+        }));
+    }
+    
+    /// Utility window that OCRs a pasted image, no PDF needed
+    /// List the files in the remote browser's location on a worker thread
+    fn list_remote(&mut self) {
+        let Some(browser) = &self.remote_browser else { return };
+        let Some(location) = self.settings.remote_locations.get(browser.location).cloned() else { return };
+        if self.job.is_some() {
+            return;
+        }
+        let listing = self.remote_listing.clone();
+        self.job_followup = JobFollowup::ShowRemoteListing;
+        self.job = Some(Job::spawn(format!("Listing {}", location.name), move |_| {
+            let objects = location.connect().list()?;
+            let message = format!("{} files in {}", objects.len(), location.name);
+            *listing.lock().unwrap() = Some(objects);
+            Ok(message)
+        }));
+    }
+    
+    fn show_remote_listing(&mut self) {
+        let Some(objects) = self.remote_listing.lock().unwrap().take() else { return };
+        if let Some(browser) = &mut self.remote_browser {
+            browser.objects = objects;
+        }
+    }
+    
+    /// Download a remote file into the temp directory on a worker thread; it
+    /// opens once the job is done
+    fn download_remote(&mut self, location: RemoteLocation, key: String) {
+        if self.job.is_some() {
+            return;
+        }
+        let download = self.remote_download.clone();
+        self.job_followup = JobFollowup::OpenRemoteDownload;
+        self.job = Some(Job::spawn(format!("Downloading from {}", location.name), move |_| {
+            let path = location.connect().download(&key, &storage::temp_dir().join("remote"))?;
+            let message = format!("Downloaded {}", path.display());
+            *download.lock().unwrap() = Some(path);
+            Ok(message)
+        }));
+    }
+    
+    fn open_remote_download(&mut self) {
+        let Some(path) = self.remote_download.lock().unwrap().take() else { return };
+        self.remote_browser = None;
+        self.open_input(path);
+    }
+    
+    /// Files in an S3 bucket or WebDAV folder, to open one
+    fn show_remote_browser(&mut self, ctx: &egui::Context) {
+        let Some(browser) = &mut self.remote_browser else { return };
+        let locations = &self.settings.remote_locations;
+        let idle = self.job.is_none();
+        let mut open = true;
+        let mut list = false;
+        let mut download = None;
+        egui::Window::new("Open from remote")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                if locations.is_empty() {
+                    ui.label("Set up an S3 bucket or WebDAV folder under Settings → Remote locations to use this.");
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Location:");
+                    let selected = locations.get(browser.location).map_or("", |location| location.name.as_str());
+                    egui::ComboBox::from_id_salt("remote_browser_location")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for (index, location) in locations.iter().enumerate() {
+                                if ui.selectable_value(&mut browser.location, index, &location.name).changed() {
+                                    browser.objects.clear();
+                                    list = true;
+                                }
+                            }
+                        });
+                    if ui.add_enabled(idle, egui::Button::new("⟳")).on_hover_text("List the files again").clicked() {
+                        list = true;
+                    }
+                });
+                ui.separator();
+                if browser.objects.is_empty() {
+                    ui.label(RichText::new("No files listed").weak());
+                }
+                ScrollArea::vertical().max_height(360.0).id_salt("remote_browser_files").show(ui, |ui| {
+                    for object in &browser.objects {
+                        let supported = inputs::InputKind::from_path(Path::new(object.name())).is_some();
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(idle && supported, egui::Button::new(object.name()))
+                                .on_disabled_hover_text("Not a document Chonker3 opens")
+                                .clicked() {
+                                download = Some(object.key.clone());
+                            }
+                            if let Some(size) = object.size {
+                                ui.label(RichText::new(format!("{:.0} KB", size as f64 / 1024.0)).weak());
+                            }
+                        });
+                    }
+                });
+            });
+        let location = locations.get(browser.location).cloned();
+        if !open {
+            self.remote_browser = None;
+        } else if list {
+            self.list_remote();
+        } else if let (Some(key), Some(location)) = (download, location) {
+            self.download_remote(location, key);
+        }
+    }
+    
+    fn show_quick_ocr(&mut self, ctx: &egui::Context) {
+        if !self.show_quick_ocr {
+            return;
+        }
+        let mut open = true;
+        let mut paste = false;
+        egui::Window::new("OCR quick tool")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.label("Copy a screenshot or image, then paste it here to read its text.");
+                if ui.add_enabled(self.job.is_none(), egui::Button::new("📋 Paste image")).clicked() {
+                    paste = true;
+                }
+                
+                if let Some(texture) = &self.quick_ocr_image {
+                    ui.separator();
+                    let size = texture.size_vec2();
+                    let scale = (ui.available_width() / size.x).min(1.0);
+                    ui.image((texture.id(), size * scale));
+                }
+                
+                if let Some(ocr) = &mut self.quick_ocr_text {
+                    ui.separator();
+                    let confidence = ocr.confidence.map_or(String::new(), |c| format!(", {:.0}% confidence", c * 100.0));
+                    ui.label(RichText::new(format!("Read with {}{}", ocr.engine, confidence)).color(Color32::GRAY));
+                    ScrollArea::vertical().max_height(240.0).id_salt("quick_ocr_text").show(ui, |ui| {
+                        ui.add(egui::TextEdit::multiline(&mut ocr.text).desired_width(f32::INFINITY));
+                    });
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().copy_text(ocr.text.clone());
+                        self.toasts.success("Copied to clipboard");
+                    }
+                }
+            });
+        self.show_quick_ocr = open;
+        
+        if paste {
+            self.quick_ocr_paste(ctx);
+        }
+    }
+    
+    /// Send a prompt with the given items, or the whole page when None, to the
+    /// LLM on a worker thread
+    fn run_llm(&mut self, title: String, prompt: String, clean_up: bool, item_ids: Option<Vec<String>>) {
+        let Some(data) = &self.session.extracted_data else { return };
+        if self.job.is_some() {
+            return;
+        }
+        let items: Vec<types::DocumentItem> = chonker3::document::edited_page_items(data, self.session.page, &self.session.to_patch())
+            .into_iter()
+            .filter(|item| item_ids.as_ref().is_none_or(|ids| ids.contains(&item.id)))
+            .collect();
+        if items.is_empty() {
+            self.toasts.info("Select some items first");
+            return;
+        }
+        let settings = self.settings.llm.clone();
+        let reply = self.llm_reply.clone();
+        self.llm_result = Some(LlmResult { title: title.clone(), clean_up, items: items.clone(), reply: String::new(), changes: Vec::new() });
+        self.job_followup = JobFollowup::ShowLlmReply;
+        self.job_document = self.session.pdf_path.clone();
+        self.job = Some(Job::spawn(title, move |job| {
+            job.set_total(1);
+            job.begin_step(format!("Asking {}", settings.model))?;
+            let text = llm::complete_prompt(&settings, &prompt, &items)?;
+            job.finish_step();
+            *reply.lock().unwrap() = Some(text);
+            Ok("The model replied".to_string())
+        }));
+    }
+    
+    /// Run a prompt template on the selection, or on the whole page if the
+    /// template asks for it
+    fn run_llm_template(&mut self, index: usize, item_ids: Vec<String>) {
+        let Some(template) = self.settings.llm.templates.get(index).cloned() else { return };
+        let item_ids = (!template.whole_page).then_some(item_ids);
+        self.run_llm(template.name, template.prompt, false, item_ids);
+    }
+    
+    fn show_llm_reply(&mut self) {
+        let (Some(reply), Some(result)) = (self.llm_reply.lock().unwrap().take(), self.llm_result.as_mut()) else { return };
+        if result.clean_up {
+            result.changes = llm::cleaned_texts(&reply, &result.items).into_iter()
+                .map(|(index, text)| (index, text, true))
+                .collect();
+        }
+        result.reply = reply;
+        self.show_llm = true;
+    }
+    
+    /// Tasks and prompt templates for the LLM, and its replies; nothing changes
+    /// until a clean-up is applied
+    fn show_llm(&mut self, ctx: &egui::Context) {
+        if !self.show_llm {
+            return;
+        }
+        let mut open = true;
+        let mut run = None;
+        let mut run_template = None;
+        let mut apply = false;
+        let mut templates_changed = false;
+        egui::Window::new("LLM post-processing")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if !self.settings.llm.enabled {
+                    ui.label("Set up an OpenAI-compatible endpoint under Settings → LLM post-processing to use this.");
+                    return;
+                }
+                ui.label(RichText::new(format!("{} at {}", self.settings.llm.model, self.settings.llm.endpoint)).weak());
+                let can_run = self.job.is_none() && self.session.extracted_data.is_some();
+                let selected = format!("{} selected", self.selected_items.len());
+                egui::Grid::new("llm_tasks").num_columns(3).show(ui, |ui| {
+                    for task in llm::LlmTask::ALL {
+                        ui.label(task.label());
+                        if ui.add_enabled(can_run && !self.selected_items.is_empty(), egui::Button::new(&selected)).clicked() {
+                            run = Some((task, false));
+                        }
+                        if ui.add_enabled(can_run, egui::Button::new("Whole page")).clicked() {
+                            run = Some((task, true));
+                        }
+                        ui.end_row();
+                    }
+                    for (index, template) in self.settings.llm.templates.iter().enumerate() {
+                        ui.label(&template.name);
+                        let (enabled, scope) = if template.whole_page { (can_run, "Whole page") } else { (can_run && !self.selected_items.is_empty(), selected.as_str()) };
+                        if ui.add_enabled(enabled, egui::Button::new(scope)).clicked() {
+                            run_template = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                
+                egui::CollapsingHeader::new("Prompt templates").id_salt("llm_templates").show(ui, |ui| {
+                    ui.label(RichText::new("Also offered when you right-click an item").weak());
+                    let mut remove = None;
+                    for (index, template) in self.settings.llm.templates.iter_mut().enumerate() {
+                        ui.push_id(index, |ui| {
+                            ui.horizontal(|ui| {
+                                templates_changed |= ui.add(egui::TextEdit::singleline(&mut template.name).hint_text("Name").desired_width(200.0)).lost_focus();
+                                templates_changed |= ui.checkbox(&mut template.whole_page, "Whole page").changed();
+                                if ui.small_button("🗑").on_hover_text("Remove this template").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                            templates_changed |= ui.add(egui::TextEdit::multiline(&mut template.prompt).hint_text("Prompt").desired_rows(2).desired_width(f32::INFINITY)).lost_focus();
+                        });
+                        ui.add_space(4.0);
+                    }
+                    if let Some(index) = remove {
+                        self.settings.llm.templates.remove(index);
+                        templates_changed = true;
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("+ Add template").clicked() {
+                            self.settings.llm.templates.push(llm::PromptTemplate { name: "New template".to_string(), ..Default::default() });
+                            templates_changed = true;
+                        }
+                        if ui.button("Restore defaults").clicked() {
+                            self.settings.llm.templates = llm::default_templates();
+                            templates_changed = true;
+                        }
+                    });
+                });
+                
+                let Some(result) = &mut self.llm_result else { return };
+                if result.reply.is_empty() {
+                    return;
+                }
+                ui.separator();
+                ui.label(RichText::new(format!("{} of {} items", result.title, result.items.len())).strong());
+                if !result.clean_up {
+                    ScrollArea::vertical().max_height(300.0).id_salt("llm_reply").show(ui, |ui| {
+                        ui.add(egui::TextEdit::multiline(&mut result.reply.as_str()).desired_width(f32::INFINITY));
+                    });
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().copy_text(result.reply.clone());
+                        self.toasts.success("Copied to clipboard");
+                    }
+                    return;
+                }
+                if result.changes.is_empty() {
+                    ui.label(RichText::new("No changes suggested").weak());
+                    return;
+                }
+                ScrollArea::vertical().max_height(300.0).id_salt("llm_changes").show(ui, |ui| {
+                    for (index, text, accept) in &mut result.changes {
+                        ui.checkbox(accept, RichText::new(result.items[*index].content.trim()).strikethrough().color(Color32::GRAY));
+                        ui.label(RichText::new(text.as_str()).color(TEAL));
+                        ui.add_space(4.0);
+                    }
+                });
+                let accepted = result.changes.iter().filter(|(.., accept)| *accept).count();
+                if ui.add_enabled(accepted > 0, egui::Button::new(format!("Apply {} changes", accepted))).clicked() {
+                    apply = true;
+                }
+            });
+        self.show_llm = open;
+        
+        if templates_changed {
+            if let Err(e) = self.settings.save() {
+                self.toasts.error(format!("Failed to save settings: {}", e));
+            }
+        }
+        if let Some((task, whole_page)) = run {
+            let item_ids = (!whole_page).then(|| self.selected_items.clone());
+            self.run_llm(task.label().to_string(), task.instructions().to_string(), task == llm::LlmTask::CleanUp, item_ids);
+        }
+        if let Some(index) = run_template {
+            self.run_llm_template(index, self.selected_items.clone());
+        }
+        if apply {
+            if let Some(result) = self.llm_result.take() {
+                for (index, text, _) in result.changes.into_iter().filter(|(.., accept)| *accept) {
+                    self.session.set_text(&result.items[index].id, text);
+                }
+            }
+        }
+    }
+    
+    fn apply_reocr(&mut self) {
+        let (Some(ocr), Some(target)) = (self.reocr_result.lock().unwrap().take(), self.reocr_target.take()) else { return };
+        if ocr.text.trim().is_empty() {
+            self.toasts.error("OCR found no text there");
+            return;
+        }
+        let applied = match target {
+            ReocrTarget::Item(item_id) => self.session.apply_reocr(&item_id, &ocr).map(|_| item_id),
+            ReocrTarget::Region(page, region) => self.session.add_ocr_item(page, &region, &ocr),
+            ReocrTarget::QuickTool => {
+                self.quick_ocr_text = Some(ocr);
+                return;
+            }
+        };
+        match applied {
+            Ok(item_id) => self.selected_items = vec![item_id],
+            Err(e) => self.toasts.error(format!("Failed to use the OCR text: {}", e)),
+        }
+    }
+    
+    fn add_detected_barcodes(&mut self) {
+        let found = std::mem::take(&mut *self.detected_barcodes.lock().unwrap());
+        if let Err(e) = self.session.add_barcodes(&found) {
+            self.toasts.error(format!("Failed to add barcodes: {}", e));
+        }
+    }
+    
+    /// Per-page word and character error rates against a ground-truth transcript
+    fn show_transcript(&mut self, ctx: &egui::Context) {
+        if !self.show_transcript {
+            return;
+        }
+        let mut open = true;
+        let mut load = false;
+        let mut compare = false;
+        let mut jump_to = None;
+        egui::Window::new("Transcript comparison")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.label("Score the extraction against a ground-truth text file. Pages are split at form feeds or <!-- Page N --> markers.");
+                ui.horizontal(|ui| {
+                    if ui.button("Load transcript...").clicked() {
+                        load = true;
+                    }
+                    if ui.add_enabled(self.transcript.is_some(), egui::Button::new("Compare")).clicked() {
+                        compare = true;
+                    }
+                });
+                let Some((path, transcript)) = &self.transcript else { return };
+                ui.label(RichText::new(format!(
+                    "{} ({} pages)",
+                    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                    transcript.pages.len(),
+                )).weak());
+                ui.checkbox(&mut self.highlight_divergent, "Underline items that differ on this page");
+                if self.transcript_scores.is_empty() {
+                    return;
+                }
+                ui.separator();
+                
+                let count = self.transcript_scores.len() as f32;
+                let mean_wer = self.transcript_scores.iter().map(|s| s.wer).sum::<f32>() / count;
+                let mean_cer = self.transcript_scores.iter().map(|s| s.cer).sum::<f32>() / count;
+                ui.label(RichText::new(format!("Mean WER {:.1}% · CER {:.1}%", mean_wer * 100.0, mean_cer * 100.0)).strong());
+                
+                let mut worst = self.transcript_scores.clone();
+                worst.sort_by(|a, b| b.wer.total_cmp(&a.wer));
+                ScrollArea::vertical().max_height(260.0).id_salt("transcript_scores").show(ui, |ui| {
+                    egui::Grid::new("transcript_grid").striped(true).show(ui, |ui| {
+                        ui.label(RichText::new("Page").strong());
+                        ui.label(RichText::new("WER").strong());
+                        ui.label(RichText::new("CER").strong());
+                        ui.end_row();
+                        for score in worst {
+                            if ui.selectable_label(score.page == self.session.page, format!("Page {}", score.page + 1)).clicked() {
+                                jump_to = Some(score.page);
+                            }
+                            ui.label(format!("{:.1}%", score.wer * 100.0));
+                            ui.label(format!("{:.1}%", score.cer * 100.0));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_transcript = open;
+        
+        if load {
+            if let Some(path) = rfd::FileDialog::new().add_filter("Text", &["txt", "md"]).pick_file() {
+                match chonker3::transcript::Transcript::load(&path) {
+                    Ok(transcript) => {
+                        self.transcript_scores = self.session.transcript_scores(&transcript);
+                        self.transcript = Some((path, transcript));
+                        self.highlight_divergent = true;
+                    }
+                    Err(e) => self.toasts.error(format!("Failed to load transcript: {}", e)),
+                }
+            }
+        }
+        if compare {
+            if let Some((_, transcript)) = &self.transcript {
+                self.transcript_scores = self.session.transcript_scores(transcript);
+            }
+        }
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    /// Keep the open document's page bookmarks and notes in the workspace
+    fn save_page_marks(&mut self) {
+        let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) else { return };
+        self.workspace.set_page_marks(index, &self.session.edits.bookmarks, &self.session.edits.page_notes);
+        if let Err(e) = self.workspace.save() {
+            self.toasts.error(format!("Failed to save workspace: {}", e));
+        }
+    }
+    
+    /// Keep the open document's comment threads in the workspace
+    fn save_comments(&mut self) {
+        let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) else { return };
+        self.workspace.set_comments(index, self.session.edits.comments.clone().into_iter().collect());
+        if let Err(e) = self.workspace.save() {
+            self.toasts.error(format!("Failed to save workspace: {}", e));
+        }
+    }
+    
+    fn comment_author(&self) -> String {
+        self.settings.comment_author.clone()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(comments::default_author)
+    }
+    
+    /// Keep the open document's named regions in the workspace
+    fn save_regions(&mut self) {
+        let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) else { return };
+        self.workspace.set_regions(index, self.session.edits.regions.clone());
+        if let Err(e) = self.workspace.save() {
+            self.toasts.error(format!("Failed to save workspace: {}", e));
+        }
+    }
+    
+    /// Named regions with their contents, and naming the one just drawn
+    fn show_regions(&mut self, ctx: &egui::Context) {
+        if !self.show_regions {
+            return;
+        }
+        let fields = self.session.region_fields();
+        let mut open = true;
+        let mut changed = false;
+        let mut jump_to = None;
+        let mut remove = None;
+        egui::Window::new("Named regions")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(self.region_draw_mode, "🔲 Draw region")
+                        .on_hover_text("Drag out a region on the extracted page, then name it here")
+                        .clicked() {
+                        self.region_draw_mode = !self.region_draw_mode;
+                        self.reocr_region_mode = false;
+                    }
+                    if self.region_draw_mode {
+                        ui.label(RichText::new("Drag around the field on the page").weak());
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Output mapping...")
+                            .on_hover_text("Put the fields of every document into one spreadsheet, a row per document")
+                            .clicked() {
+                            self.show_field_mapping = true;
+                        }
+                    });
+                });
+                
+                if let Some((page, bbox)) = self.pending_region.clone() {
+                    ui.separator();
+                    ui.label(format!("New region on page {}", page + 1));
+                    ui.horizontal(|ui| {
+                        let response = ui.add(egui::TextEdit::singleline(&mut self.region_name_buffer).hint_text("Invoice number"));
+                        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if ui.button("Save").clicked() || submitted {
+                            match self.session.add_region(&self.region_name_buffer, page, bbox) {
+                                Ok(()) => {
+                                    self.pending_region = None;
+                                    self.region_name_buffer.clear();
+                                    changed = true;
+                                }
+                                Err(e) => self.toasts.error(e.to_string()),
+                            }
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.pending_region = None;
+                        }
+                    });
+                }
+                
+                ui.separator();
+                if fields.is_empty() {
+                    ui.label(RichText::new("No regions yet").color(Color32::GRAY));
+                    return;
+                }
+                ScrollArea::vertical().max_height(320.0).id_salt("regions_list").show(ui, |ui| {
+                    egui::Grid::new("regions_grid").striped(true).show(ui, |ui| {
+                        ui.label(RichText::new("Name").strong());
+                        ui.label(RichText::new("Page").strong());
+                        ui.label(RichText::new("Contents").strong());
+                        ui.end_row();
+                        for (index, field) in fields.iter().enumerate() {
+                            match self.renaming_region.as_mut().filter(|(renaming, _)| *renaming == index) {
+                                Some((_, name)) => {
+                                    let response = ui.text_edit_singleline(name);
+                                    if response.lost_focus() {
+                                        let name = name.clone();
+                                        match self.session.rename_region(index, &name) {
+                                            Ok(()) => changed = true,
+                                            Err(e) => self.toasts.error(e.to_string()),
+                                        }
+                                        self.renaming_region = None;
+                                    }
+                                }
+                                None => {
+                                    let response = ui.selectable_label(false, &field.name).on_hover_text("Double-click to rename");
+                                    if response.double_clicked() {
+                                        self.renaming_region = Some((index, field.name.clone()));
+                                    } else if response.clicked() {
+                                        jump_to = Some(field.page);
+                                    }
+                                }
+                            }
+                            ui.label(format!("{}", field.page + 1));
+                            let preview: String = field.text.chars().take(60).map(|c| if c == '\n' { ' ' } else { c }).collect();
+                            ui.label(if preview.is_empty() { RichText::new("(empty)").weak() } else { RichText::new(preview) })
+                                .on_hover_text(&field.text);
+                            if ui.small_button("🗑").on_hover_text("Delete region").clicked() {
+                                remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_regions = open;
+        
+        if let Some(index) = remove {
+            self.session.remove_region(index);
+            self.renaming_region = None;
+            changed = true;
+        }
+        if changed {
+            self.save_regions();
+        }
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    /// Extracted workspace documents the tag filter lets through
+    fn mapped_documents(&self) -> Vec<chonker3::workspace::WorkspaceDocument> {
+        self.workspace.documents.iter()
+            .filter(|d| d.matches_tags(&self.workspace_tag_filter) && d.extracted_json.is_some())
+            .cloned()
+            .collect()
+    }
+    
+    /// Write the field sheet for the workspace documents on a worker thread
+    fn export_field_sheet(&mut self) {
+        if self.job.is_some() {
+            return;
+        }
+        let documents = self.mapped_documents();
+        if documents.is_empty() {
+            self.toasts.info("No extracted documents to export");
+            return;
+        }
+        let mapping = self.settings.export_pipeline.field_mapping.clone();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name(&mapping.file_name)
+            .save_file()
+        else {
+            return;
+        };
+        self.job = Some(Job::spawn("Exporting field sheet", move |job| {
+            job.set_total(documents.len());
+            let mut rows = Vec::new();
+            for doc in &documents {
+                job.begin_step(doc.display_name())?;
+                rows.push(field_mapping::DocumentFields::load(doc)?);
+                job.finish_step();
+            }
+            mapping.write(&path, &rows)?;
+            Ok(format!("Wrote {} rows to {}", rows.len(), path.display()))
+        }));
+    }
+    
+    /// Assign named fields and document details to the columns of the field sheet
+    fn show_field_mapping(&mut self, ctx: &egui::Context) {
+        if !self.show_field_mapping {
+            return;
+        }
+        // Region names from the workspace and the open document, and metadata keys, to pick from
+        let mut field_names = field_mapping::field_names(&self.workspace.documents);
+        for region in &self.session.edits.regions {
+            if !field_names.iter().any(|name| name.eq_ignore_ascii_case(&region.name)) {
+                field_names.push(region.name.clone());
+            }
+        }
+        let metadata_keys: std::collections::BTreeSet<String> = self.workspace.documents.iter()
+            .flat_map(|d| d.metadata.keys().cloned())
+            .collect();
+        let mut open = true;
+        let mut changed = false;
+        let mut preview = false;
+        let mut export = false;
+        egui::Window::new("Output mapping")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("Each column takes a named region or a detail of the document; every document becomes one row.").weak());
+                let mapping = &mut self.settings.export_pipeline.field_mapping;
+                let mut remove = None;
+                let mut swap = None;
+                let column_count = mapping.columns.len();
+                egui::Grid::new("field_mapping_columns").num_columns(4).striped(true).show(ui, |ui| {
+                    ui.label(RichText::new("Column").strong());
+                    ui.label(RichText::new("Source").strong());
+                    ui.label("");
+                    ui.label("");
+                    ui.end_row();
+                    for (index, column) in mapping.columns.iter_mut().enumerate() {
+                        changed |= ui.add(egui::TextEdit::singleline(&mut column.header).desired_width(130.0)).lost_focus();
+                        let selected = match &column.source {
+                            field_mapping::ColumnSource::Field(name) => format!("Field: {}", name),
+                            source => source.label().to_string(),
+                        };
+                        egui::ComboBox::from_id_salt(("field_mapping_source", index))
+                            .selected_text(selected)
+                            .width(180.0)
+                            .show_ui(ui, |ui| {
+                                for name in &field_names {
+                                    let source = field_mapping::ColumnSource::Field(name.clone());
+                                    changed |= ui.selectable_value(&mut column.source, source, format!("Field: {}", name)).changed();
+                                }
+                                ui.separator();
+                                for source in [field_mapping::ColumnSource::FileName, field_mapping::ColumnSource::Path, field_mapping::ColumnSource::Pages] {
+                                    let label = source.label();
+                                    changed |= ui.selectable_value(&mut column.source, source, label).changed();
+                                }
+                                for key in &metadata_keys {
+                                    let source = field_mapping::ColumnSource::Metadata(key.clone());
+                                    changed |= ui.selectable_value(&mut column.source, source, format!("Metadata: {}", key)).changed();
+                                }
+                                if !matches!(column.source, field_mapping::ColumnSource::Metadata(_)) && ui.selectable_label(false, "Metadata key...").clicked() {
+                                    column.source = field_mapping::ColumnSource::Metadata(String::new());
+                                    changed = true;
+                                }
+                            });
+                        match column.source.name_mut() {
+                            Some(name) => { changed |= ui.add(egui::TextEdit::singleline(name).hint_text("Name").desired_width(110.0)).lost_focus(); }
+                            None => { ui.label(""); }
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(index > 0, egui::Button::new("⬆").small()).clicked() {
+                                swap = Some((index - 1, index));
+                            }
+                            if ui.add_enabled(index + 1 < column_count, egui::Button::new("⬇").small()).clicked() {
+                                swap = Some((index, index + 1));
+                            }
+                            if ui.small_button("✖").on_hover_text("Remove column").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                if let Some((a, b)) = swap {
+                    mapping.columns.swap(a, b);
+                    changed = true;
+                }
+                if let Some(index) = remove {
+                    mapping.columns.remove(index);
+                    changed = true;
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add column").clicked() {
+                        mapping.columns.push(field_mapping::MappedColumn {
+                            header: "File".to_string(),
+                            source: field_mapping::ColumnSource::FileName,
+                        });
+                        changed = true;
+                    }
+                    let unmapped: Vec<&String> = field_names.iter()
+                        .filter(|name| !mapping.columns.iter().any(|c| matches!(&c.source, field_mapping::ColumnSource::Field(f) if f.eq_ignore_ascii_case(name))))
+                        .collect();
+                    if ui.add_enabled(!unmapped.is_empty(), egui::Button::new(format!("Add all fields ({})", unmapped.len()))).clicked() {
+                        mapping.columns.extend(unmapped.into_iter().map(|name| field_mapping::MappedColumn::field(name)));
+                        changed = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Sheet name in batch exports:");
+                    changed |= ui.add(egui::TextEdit::singleline(&mut mapping.file_name).desired_width(140.0)).lost_focus();
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Preview").on_hover_text("Fill in the first few workspace documents").clicked() {
+                        preview = true;
+                    }
+                    if ui.add_enabled(self.job.is_none() && !mapping.is_empty(), egui::Button::new("Export sheet...")).clicked() {
+                        export = true;
+                    }
+                });
+                if !self.field_mapping_preview.is_empty() && !mapping.is_empty() {
+                    ScrollArea::both().max_height(180.0).id_salt("field_mapping_preview").show(ui, |ui| {
+                        egui::Grid::new("field_mapping_preview_grid").striped(true).show(ui, |ui| {
+                            for column in &mapping.columns {
+                                ui.label(RichText::new(&column.header).strong());
+                            }
+                            ui.end_row();
+                            for doc in &self.field_mapping_preview {
+                                for value in mapping.row(doc) {
+                                    let preview: String = value.chars().take(40).map(|c| if c == '\n' { ' ' } else { c }).collect();
+                                    ui.label(preview).on_hover_text(value);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+        self.show_field_mapping = open;
+        
+        if changed {
+            if let Err(e) = self.settings.save() {
+                self.toasts.error(format!("Failed to save settings: {}", e));
+            }
+        }
+        if preview {
+            let documents = self.mapped_documents();
+            self.field_mapping_preview = documents.iter()
+                .take(5)
+                .filter_map(|doc| field_mapping::DocumentFields::load(doc).ok())
+                .collect();
+            if self.field_mapping_preview.is_empty() {
+                self.toasts.info("No extracted documents in the workspace");
+            }
+        }
+        if export {
+            self.export_field_sheet();
+        }
+    }
+    
+    /// Validation rules on the named regions, and the problems they find in this document
+    fn show_problems(&mut self, ctx: &egui::Context) {
+        if !self.show_problems {
+            return;
+        }
+        let violations = self.session.validate(&self.workspace.validation_rules);
+        let mut open = true;
+        let mut changed = false;
+        let mut jump_to = None;
+        let mut remove = None;
+        egui::Window::new("Problems")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if self.workspace.validation_rules.is_empty() {
+                    ui.label(RichText::new("No rules yet. Add some below to check the named regions' contents.").weak());
+                } else if violations.is_empty() {
+                    ui.label(RichText::new(format!("✔ All {} rules pass", self.workspace.validation_rules.len())).color(TEAL));
+                } else {
+                    ui.label(RichText::new(format!("{} problems", violations.len())).strong());
+                    ScrollArea::vertical().max_height(220.0).id_salt("problems_list").show(ui, |ui| {
+                        for violation in &violations {
+                            let field = violation.field.as_deref().unwrap_or("");
+                            let page = violation.page.map(|page| format!(" (page {})", page + 1)).unwrap_or_default();
+                            let rule = &self.workspace.validation_rules[violation.rule];
+                            if ui.selectable_label(false, format!("⚠ {}{}: {}", field, page, violation.message))
+                                .on_hover_text(rule.describe())
+                                .clicked() {
+                                jump_to = violation.page;
+                            }
+                        }
+                    });
+                }
+                
+                ui.separator();
+                egui::CollapsingHeader::new(format!("Rules ({})", self.workspace.validation_rules.len()))
+                    .id_salt("validation_rules")
+                    .default_open(self.workspace.validation_rules.is_empty())
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Fields are named regions (🔲); a line item name ending in * matches every field it starts").weak().small());
+                        for (index, rule) in self.workspace.validation_rules.iter_mut().enumerate() {
+                            ui.push_id(index, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(RichText::new(rule.label()).strong());
+                                    match rule {
+                                        validation::Rule::Pattern { field, pattern } => {
+                                            changed |= ui.add(egui::TextEdit::singleline(field).hint_text("Field").desired_width(110.0)).lost_focus();
+                                            changed |= ui.add(egui::TextEdit::singleline(pattern).hint_text(r"INV-\d{6}").desired_width(160.0)).lost_focus();
+                                        }
+                                        validation::Rule::Sum { items, total, tolerance } => {
+                                            let mut joined = items.join(",");
+                                            let response = ui.add(egui::TextEdit::singleline(&mut joined).hint_text("Line *").desired_width(110.0))
+                                                .on_hover_text("Line item fields, separated by commas");
+                                            if response.changed() {
+                                                *items = joined.split(',').map(str::to_string).collect();
+                                            }
+                                            changed |= response.lost_focus();
+                                            ui.label("=");
+                                            changed |= ui.add(egui::TextEdit::singleline(total).hint_text("Total").desired_width(90.0)).lost_focus();
+                                            ui.label("±");
+                                            changed |= ui.add(egui::DragValue::new(tolerance).speed(0.01).range(0.0..=f64::MAX)).changed();
+                                        }
+                                        validation::Rule::DateRange { field, earliest, latest } => {
+                                            changed |= ui.add(egui::TextEdit::singleline(field).hint_text("Field").desired_width(110.0)).lost_focus();
+                                            for (bound, hint) in [(earliest, "from YYYY-MM-DD"), (latest, "to YYYY-MM-DD")] {
+                                                let mut text = bound.clone().unwrap_or_default();
+                                                let response = ui.add(egui::TextEdit::singleline(&mut text).hint_text(hint).desired_width(100.0));
+                                                if response.changed() {
+                                                    *bound = (!text.trim().is_empty()).then_some(text);
+                                                }
+                                                changed |= response.lost_focus();
+                                            }
+                                        }
+                                    }
+                                    if ui.small_button("🗑").on_hover_text("Remove this rule").clicked() {
+                                        remove = Some(index);
+                                    }
+                                });
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Add:");
+                            let new_rule = if ui.button("Pattern").clicked() {
+                                Some(validation::Rule::Pattern { field: String::new(), pattern: String::new() })
+                            } else if ui.button("Sum").clicked() {
+                                Some(validation::Rule::Sum { items: Vec::new(), total: String::new(), tolerance: validation::DEFAULT_TOLERANCE })
+                            } else if ui.button("Date range").clicked() {
+                                Some(validation::Rule::DateRange { field: String::new(), earliest: None, latest: None })
+                            } else {
+                                None
+                            };
+                            if let Some(rule) = new_rule {
+                                self.workspace.validation_rules.push(rule);
+                                changed = true;
+                            }
+                        });
+                    });
+            });
+        self.show_problems = open;
+        
+        if let Some(index) = remove {
+            self.workspace.validation_rules.remove(index);
+            changed = true;
+        }
+        if changed {
+            if let Err(e) = self.workspace.save() {
+                self.toasts.error(format!("Failed to save workspace: {}", e));
+            }
+        }
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    /// Rebuild the personal data detector and its hits if the settings, document or edits changed
+    fn refresh_pii(&mut self) {
+        let current = self.pii_for.as_ref().is_some_and(|key| {
+            key.settings == self.settings.pii
+                && key.pdf_path == self.session.pdf_path
+                && key.extracted_json == self.session.extracted_json
+                && key.text_overrides == self.session.edits.text_overrides
+                && key.deletions == self.session.edits.deletions
+        });
+        if current && self.pii_detector.is_some() {
+            return;
+        }
+        let detector = PiiDetector::new(&self.settings.pii);
+        self.pii_hits = detector.as_ref().map(|detector| self.session.pii_hits(detector)).unwrap_or_default();
+        self.pii_detector = Some(detector);
+        self.pii_for = Some(PiiKey {
+            settings: self.settings.pii.clone(),
+            pdf_path: self.session.pdf_path.clone(),
+            extracted_json: self.session.extracted_json.clone(),
+            text_overrides: self.session.edits.text_overrides.clone(),
+            deletions: self.session.edits.deletions.clone(),
+        });
+    }
+    
+    /// What to look for as personal data, the hits in the document, and masking or saving redaction boxes for them
+    fn show_pii(&mut self, ctx: &egui::Context) {
+        if !self.show_pii {
+            return;
+        }
+        let Some(detector) = self.pii_detector.take() else { return };
+        let hits = std::mem::take(&mut self.pii_hits);
+        let mut open = true;
+        let mut changed = false;
+        let mut jump_to = None;
+        let mut mask = false;
+        let mut save_redactions = false;
+        egui::Window::new("Personal data")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                let settings = &mut self.settings.pii;
+                ui.horizontal(|ui| {
+                    changed |= ui.checkbox(&mut settings.ssn, "SSNs").changed();
+                    changed |= ui.checkbox(&mut settings.credit_cards, "Card numbers").changed();
+                    changed |= ui.checkbox(&mut settings.emails, "Emails").changed();
+                });
+                egui::CollapsingHeader::new(format!("Names ({})", settings.names.iter().filter(|name| !name.trim().is_empty()).count()))
+                    .id_salt("pii_names")
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("One per line; matched as whole words in any case").weak().small());
+                        let mut names = settings.names.join("\n");
+                        let response = ui.add(egui::TextEdit::multiline(&mut names).desired_rows(4).desired_width(f32::INFINITY));
+                        if response.changed() {
+                            settings.names = names.split('\n').map(str::to_string).collect();
+                        }
+                        changed |= response.lost_focus();
+                    });
+                let mut remove = None;
+                egui::CollapsingHeader::new(format!("Patterns ({})", settings.patterns.len()))
+                    .id_salt("pii_patterns")
+                    .show(ui, |ui| {
+                        for (index, custom) in settings.patterns.iter_mut().enumerate() {
+                            ui.push_id(index, |ui| {
+                                ui.horizontal(|ui| {
+                                    changed |= ui.add(egui::TextEdit::singleline(&mut custom.label).hint_text("Label").desired_width(110.0)).lost_focus();
+                                    changed |= ui.add(egui::TextEdit::singleline(&mut custom.pattern).hint_text(r"\bA\d{8}\b").desired_width(200.0)).lost_focus();
+                                    if ui.small_button("🗑").on_hover_text("Remove this pattern").clicked() {
+                                        remove = Some(index);
+                                    }
+                                });
+                            });
+                        }
+                        if ui.button("Add pattern").clicked() {
+                            settings.patterns.push(pii::CustomPattern::default());
+                            changed = true;
+                        }
+                    });
+                if let Some(index) = remove {
+                    settings.patterns.remove(index);
+                    changed = true;
+                }
+                
+                ui.separator();
+                match &detector {
+                    Err(e) => { ui.label(RichText::new(format!("{:#}", e)).color(Color32::RED)); }
+                    Ok(detector) if detector.is_empty() => { ui.label(RichText::new("Nothing to look for; turn on a kind above or add names or patterns").weak()); }
+                    Ok(_) if hits.is_empty() => { ui.label(RichText::new("✔ No personal data found").color(TEAL)); }
+                    Ok(_) => {
+                        ui.label(RichText::new(format!("{} hits in {} items", hits.len(), hits.iter().map(|hit| &hit.item_id).collect::<HashSet<_>>().len())).strong());
+                        ScrollArea::vertical().max_height(220.0).id_salt("pii_hits").show(ui, |ui| {
+                            for hit in &hits {
+                                let selected = self.selected_items.contains(&hit.item_id);
+                                if ui.selectable_label(selected, format!("Page {} · {}: {}", hit.page + 1, hit.label, hit.text)).clicked() {
+                                    jump_to = Some((hit.page, hit.item_id.clone()));
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            mask |= ui.button("Mask in text").on_hover_text(format!("Replace the characters of each hit with {} in the extraction", pii::MASK)).clicked();
+                            save_redactions |= ui.button("Save redaction boxes...").on_hover_text("The box of each item holding personal data, as JSON for redacting the PDF").clicked();
+                        });
+                    }
+                }
+            });
+        self.show_pii = open;
+        self.pii_detector = Some(detector);
+        self.pii_hits = hits;
+        
+        if changed {
+            if let Err(e) = self.settings.save() {
+                self.toasts.error(format!("Failed to save settings: {}", e));
+            }
+        }
+        if let Some((page, item_id)) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+            self.selected_items = vec![item_id];
+        }
+        let Some(Ok(detector)) = &self.pii_detector else { return };
+        if mask {
+            let masked = self.session.mask_pii(detector);
+            self.toasts.success(format!("Masked personal data in {} items", masked));
+        }
+        if save_redactions {
+            let default_name = self.session.pdf_path.as_ref()
+                .and_then(|p| p.file_stem())
+                .map(|s| format!("{}.redactions.json", s.to_string_lossy()))
+                .unwrap_or_else(|| "redactions.json".to_string());
+            if let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).set_file_name(default_name).save_file() {
+                match self.session.export_redactions(&path, detector) {
+                    Ok(count) => self.toasts.success(format!("Saved {} redaction boxes to {}", count, path.display())),
+                    Err(e) => self.toasts.error(format!("Export failed: {}", e)),
+                }
+            }
+        }
+    }
+    
+    /// Open the comments panel on an item's thread
+    fn open_comments(&mut self, item_id: String) {
+        self.show_comments = true;
+        if self.comment_item.as_ref() != Some(&item_id) {
+            self.comment_buffer.clear();
+        }
+        self.selected_items = vec![item_id.clone()];
+        self.comment_item = Some(item_id);
+    }
+    
+    /// Side panel with the open item's thread and a list of the document's threads
+    fn show_comments_panel(&mut self, ctx: &egui::Context) {
+        let preview = |text: &str| {
+            let line = text.lines().next().unwrap_or("").trim();
+            let ellipsis = if line.chars().count() > 40 || text.trim().lines().count() > 1 { "..." } else { "" };
+            format!("{}{}", line.chars().take(40).collect::<String>(), ellipsis)
+        };
+        // Open threads first, then by page
+        let mut threads: Vec<(String, CommentThread)> = self.session.edits.comments.iter()
+            .filter(|(_, thread)| !thread.comments.is_empty())
+            .map(|(id, thread)| (id.clone(), thread.clone()))
+            .collect();
+        threads.sort_by_key(|(id, thread)| (thread.resolved, types::item_page(id), id.clone()));
+        
+        let author = self.comment_author();
+        let mut jump_to = None;
+        let mut save = false;
+        egui::SidePanel::right("comments_panel")
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.heading("Comments");
+                
+                if let Some(item_id) = self.comment_item.clone() {
+                    let subject = self.session.item(&item_id).map_or_else(|| item_id.clone(), |item| preview(&item.content));
+                    ui.label(RichText::new(format!("On \"{}\"", subject)).strong());
+                    let thread = self.session.edits.comments.get(&item_id).cloned().unwrap_or_default();
+                    ScrollArea::vertical().id_salt("comment_thread").max_height(240.0).show(ui, |ui| {
+                        for comment in &thread.comments {
+                            ui.label(RichText::new(format!("{} · {}", comment.author, comments::short_time(&comment.timestamp))).small().color(Color32::GRAY));
+                            ui.label(&comment.text);
+                            ui.add_space(4.0);
+                        }
+                    });
+                    ui.add(egui::TextEdit::multiline(&mut self.comment_buffer)
+                        .hint_text(if thread.comments.is_empty() { "Start a thread" } else { "Reply" })
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY));
+                    ui.horizontal(|ui| {
+                        let blank = self.comment_buffer.trim().is_empty();
+                        if ui.add_enabled(!blank, egui::Button::new("Comment"))
+                            .on_hover_text(format!("Signed as {} (change in ⚙)", author))
+                            .clicked() && self.session.add_comment(&item_id, &author, &self.comment_buffer) {
+                            self.comment_buffer.clear();
+                            save = true;
+                        }
+                        if !thread.comments.is_empty() {
+                            let (label, resolved) = if thread.resolved { ("Reopen", false) } else { ("✔ Resolve", true) };
+                            if ui.button(label).clicked() {
+                                self.session.set_comments_resolved(&item_id, resolved);
+                                save = true;
+                            }
+                        }
+                        if ui.button("Close").clicked() {
+                            self.comment_item = None;
+                        }
+                    });
+                } else {
+                    ui.label(RichText::new("Select an item and click 💬, or click an item's 💬 marker, to comment on it").color(Color32::GRAY));
+                }
+                ui.separator();
+                
+                let resolved_count = threads.iter().filter(|(_, thread)| thread.resolved).count();
+                ui.checkbox(&mut self.show_resolved_comments, format!("Show resolved ({})", resolved_count));
+                if threads.is_empty() {
+                    ui.label(RichText::new("No comments yet").color(Color32::GRAY));
+                }
+                ScrollArea::vertical().id_salt("comment_threads").show(ui, |ui| {
+                    for (item_id, thread) in &threads {
+                        if thread.resolved && !self.show_resolved_comments {
+                            continue;
+                        }
+                        let Some(last) = thread.last() else { continue };
+                        let status = if thread.resolved { "✔ " } else { "" };
+                        let page = types::item_page(item_id).map(|p| format!("p{} ", p + 1)).unwrap_or_default();
+                        let label = format!("{}{}{} ({}): {}", status, page, last.author, thread.comments.len(), preview(&last.text));
+                        let response = ui.selectable_label(self.comment_item.as_ref() == Some(item_id), label)
+                            .on_hover_text(thread.summary());
+                        if response.clicked() {
+                            jump_to = Some(item_id.clone());
+                        }
+                    }
+                });
+            });
+        
+        if save {
+            self.save_comments();
+        }
+        if let Some(item_id) = jump_to {
+            if let Some(page) = types::item_page(&item_id) {
+                if self.session.go_to_page(page) {
+                    self.pdf_texture = None;
+                }
+            }
+            self.open_comments(item_id);
+        }
+    }
+    
+    /// Side panel with the current page's note and a jump list of marked pages
+    fn show_page_notes_panel(&mut self, ctx: &egui::Context) {
+        let page = self.session.page;
+        if self.page_note_page != Some(page) {
+            self.page_note_buffer = self.session.edits.page_notes.get(&page).cloned().unwrap_or_default();
+            self.page_note_page = Some(page);
+        }
+        
+        let mut jump_to = None;
+        let mut save = false;
+        egui::SidePanel::right("page_notes_panel")
+            .default_width(240.0)
+            .show(ctx, |ui| {
+                ui.heading("Page notes");
+                
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("Page {}", page + 1)).strong());
+                    let bookmarked = self.session.edits.bookmarks.contains(&page);
+                    let label = if bookmarked { "🔖 Bookmarked" } else { "Bookmark" };
+                    if ui.selectable_label(bookmarked, label).clicked() {
+                        self.session.toggle_bookmark(page);
+                        save = true;
+                    }
+                });
+                if let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) {
+                    let current = self.workspace.documents[index].page_backends.get(&page).copied();
+                    let mut selected = current;
+                    ui.horizontal(|ui| {
+                        ui.label("Extract with:");
+                        egui::ComboBox::from_id_salt("page_backend")
+                            .selected_text(selected.map_or("Default", |b| b.label()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut selected, None, "Default");
+                                for backend in PythonBackend::ALL {
+                                    ui.selectable_value(&mut selected, Some(backend), backend.label());
+                                }
+                            });
+                    });
+                    if selected != current {
+                        self.workspace.set_page_backend(index, page, selected);
+                        save = true;
+                    }
+                }
+                let response = ui.add(egui::TextEdit::multiline(&mut self.page_note_buffer)
+                    .hint_text("Note for this page")
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY));
+                if response.changed() {
+                    self.session.set_page_note(page, &self.page_note_buffer);
+                }
+                // Write the workspace when the user is done typing, not on every key
+                save |= response.lost_focus();
+                ui.separator();
+                
+                let marked = self.session.marked_pages();
+                if marked.is_empty() {
+                    ui.label(RichText::new("No bookmarks or notes yet").color(Color32::GRAY));
+                }
+                ScrollArea::vertical().id_salt("page_notes_list").show(ui, |ui| {
+                    for marked_page in marked {
+                        let bookmark = if self.session.edits.bookmarks.contains(&marked_page) { "🔖 " } else { "" };
+                        let mut label = format!("{}Page {}", bookmark, marked_page + 1);
+                        if let Some(note) = self.session.edits.page_notes.get(&marked_page) {
+                            let first_line = note.lines().next().unwrap_or("").trim();
+                            let preview: String = first_line.chars().take(40).collect();
+                            let ellipsis = if first_line.chars().count() > 40 || note.trim().lines().count() > 1 { "..." } else { "" };
+                            label.push_str(&format!(": {}{}", preview, ellipsis));
+                        }
+                        let response = ui.selectable_label(marked_page == page, label);
+                        let response = match self.session.edits.page_notes.get(&marked_page) {
+                            Some(note) => response.on_hover_text(note),
+                            None => response,
+                        };
+                        if response.clicked() {
+                            jump_to = Some(marked_page);
+                        }
+                    }
+                });
+            });
+        
+        if save {
+            self.save_page_marks();
+        }
+        if let Some(page) = jump_to {
+            if self.session.go_to_page(page) {
+                self.pdf_texture = None;
+            }
+        }
+    }
+    
+    fn show_workspace_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("workspace_panel")
+            .default_width(240.0)
+            .show(ctx, |ui| {
+                ui.heading("Documents");
+                
+                // Tag filter chips
+                let all_tags = self.workspace.all_tags();
+                if !all_tags.is_empty() {
+                    ui.label(RichText::new("Filter by tag:").small());
+                    ui.horizontal_wrapped(|ui| {
+                        for tag in &all_tags {
+                            let mut active = self.workspace_tag_filter.contains(tag);
+                            if ui.toggle_value(&mut active, tag.as_str()).changed() {
+                                if active {
+                                    self.workspace_tag_filter.insert(tag.clone());
+                                } else {
+                                    self.workspace_tag_filter.remove(tag);
+                                }
+                            }
+                        }
+                    });
+                }
+                ui.separator();
+                
+                // Document list
+                let mut open_path = None;
+                ScrollArea::vertical().id_salt("workspace_documents").max_height(260.0).show(ui, |ui| {
+                    for (index, doc) in self.workspace.documents.iter().enumerate() {
+                        if !doc.matches_tags(&self.workspace_tag_filter) {
+                            continue;
+                        }
+                        let is_current = self.session.pdf_path.as_ref()
+                            .map(|p| self.workspace.find(p) == Some(index))
+                            .unwrap_or(false);
+                        let label = if doc.tags.is_empty() {
+                            doc.display_name()
+                        } else {
+                            format!("{}  [{}]", doc.display_name(), doc.tags.iter().cloned().collect::<Vec<_>>().join(", "))
+                        };
+                        let response = ui.selectable_label(self.workspace_selected == Some(index), label);
+                        if response.clicked() {
+                            self.workspace_selected = Some(index);
+                        }
+                        if response.double_clicked() && !is_current {
+                            open_path = Some(doc.path.clone());
+                        }
+                    }
+                });
+                if let Some(path) = open_path {
+                    self.load_pdf(path);
+                    self.auto_extract();
+                }
+                
+                // Selected document's tags and metadata
+                let mut changed = false;
+                if let Some(doc) = self.workspace_selected.and_then(|i| self.workspace.documents.get_mut(i)) {
+                    ui.separator();
+                    ui.label(RichText::new(doc.display_name()).strong());
+                    
+                    ui.label("Tags:");
+                    let mut remove_tag = None;
+                    ui.horizontal_wrapped(|ui| {
+                        for tag in &doc.tags {
+                            if ui.small_button(format!("{} ✕", tag)).clicked() {
+                                remove_tag = Some(tag.clone());
+                            }
+                        }
+                    });
+                    if let Some(tag) = remove_tag {
+                        doc.tags.remove(&tag);
+                        changed = true;
+                    }
+                    ui.horizontal(|ui| {
+                        let response = ui.add_sized(
+                            Vec2::new(140.0, 20.0),
+                            egui::TextEdit::singleline(&mut self.new_tag_buffer).hint_text("new tag")
+                        );
+                        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if (ui.button("Add").clicked() || submitted) && !self.new_tag_buffer.trim().is_empty() {
+                            doc.tags.insert(self.new_tag_buffer.trim().to_string());
+                            self.new_tag_buffer.clear();
+                            changed = true;
+                        }
+                    });
+                    
+                    ui.add_space(6.0);
+                    ui.label("Metadata:");
+                    let mut remove_key = None;
+                    egui::Grid::new("workspace_metadata").num_columns(3).show(ui, |ui| {
+                        for (key, value) in doc.metadata.iter_mut() {
+                            ui.label(key);
+                            changed |= ui.text_edit_singleline(value).changed();
+                            if ui.small_button("✕").clicked() {
+                                remove_key = Some(key.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(key) = remove_key {
+                        doc.metadata.remove(&key);
+                        changed = true;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add_sized(Vec2::new(70.0, 20.0), egui::TextEdit::singleline(&mut self.new_metadata_key).hint_text("field"));
+                        ui.add_sized(Vec2::new(90.0, 20.0), egui::TextEdit::singleline(&mut self.new_metadata_value).hint_text("value"));
+                        if ui.button("Add").clicked() && !self.new_metadata_key.trim().is_empty() {
+                            doc.metadata.insert(self.new_metadata_key.trim().to_string(), self.new_metadata_value.clone());
+                            self.new_metadata_key.clear();
+                            self.new_metadata_value.clear();
+                            changed = true;
+                        }
+                    });
+                    
+                    ui.add_space(6.0);
+                    if ui.button("Remove from workspace").clicked() {
+                        if let Some(index) = self.workspace_selected.take() {
+                            self.workspace.documents.remove(index);
+                            changed = true;
+                        }
+                    }
+                }
+                if changed {
+                    if let Err(e) = self.workspace.save() {
+                        self.toasts.error(format!("Failed to save workspace: {}", e));
+                    }
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Find duplicates").clicked() {
+                        self.start_duplicate_review(false);
+                    }
+                    if ui.add_enabled(self.job.is_none() && self.session.pdfium.is_some(), egui::Button::new("Split scans..."))
+                        .on_hover_text("Split the listed PDFs at separator sheets and drop blank pages")
+                        .clicked() {
+                        self.split_scans();
+                    }
+                    if ui.add_enabled(self.job.is_none(), egui::Button::new("Batch export..."))
+                        .on_hover_text("Export the listed documents with the layout from Settings, plus a manifest")
+                        .clicked() {
+                        // Review duplicates first; export directly if there are none
+                        self.start_duplicate_review(true);
+                        if self.duplicate_review.is_none() {
+                            self.batch_export(&Default::default());
+                        }
+                    }
+                });
+            });
+    }
+    
+    /// Split the filtered workspace PDFs into their logical documents on a worker thread
+    fn split_scans(&mut self) {
+        let documents: Vec<PathBuf> = self.workspace.documents.iter()
+            .filter(|d| d.matches_tags(&self.workspace_tag_filter))
+            .map(|d| d.path.clone())
+            .filter(|p| inputs::InputKind::from_path(p) == Some(inputs::InputKind::Pdf))
+            .collect();
+        if documents.is_empty() {
+            self.toasts.info("No PDFs to split");
+            return;
+        }
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else { return };
+        let library = self.session.pdfium_library.clone();
+        let written = self.split_documents.clone();
+        written.lock().unwrap().clear();
+        
+        self.job_followup = JobFollowup::AddSplitDocuments;
+        self.job = Some(Job::spawn("Splitting scans", move |job| {
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            job.set_total(documents.len());
+            let (mut split, mut blank) = (0, 0);
+            for path in &documents {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                job.begin_step(name)?;
+                let bytes = std::fs::read(path)?;
+                let kinds = separators::classify_pdf(&pdfium, &bytes)?;
+                // Scans with nothing to drop or split stay as they are
+                if kinds.iter().all(|k| *k == separators::PageKind::Content) {
+                    job.finish_step();
+                    continue;
+                }
+                blank += kinds.iter().filter(|k| **k == separators::PageKind::Blank).count();
+                let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "scan".to_string());
+                let parts = separators::split_documents(&kinds);
+                let paths = separators::write_documents(&pdfium, &bytes, &parts, &dir, &stem)?;
+                split += 1;
+                written.lock().unwrap().extend(paths);
+                job.finish_step();
+            }
+            Ok(format!(
+                "Split {} of {} scans into {} documents, dropping {} blank pages",
+                split, documents.len(), written.lock().unwrap().len(), blank,
+            ))
+        }));
+    }
+    
+    fn add_split_documents(&mut self) {
+        let paths = std::mem::take(&mut *self.split_documents.lock().unwrap());
+        for path in &paths {
+            self.workspace.add_document(path);
+        }
+        if !paths.is_empty() {
+            if let Err(e) = self.workspace.save() {
+                self.toasts.error(format!("Failed to save workspace: {}", e));
+            }
+        }
+    }
+    
+    /// Fingerprint the filtered documents and open the review dialog if any are duplicates
+    fn start_duplicate_review(&mut self, export_after: bool) {
+        let fingerprints: Vec<_> = self.workspace.fingerprints().into_iter()
+            .filter(|(i, _)| self.workspace.documents[*i].matches_tags(&self.workspace_tag_filter))
+            .collect();
+        let _ = self.workspace.save();
+        
+        let groups = dedup::find_duplicates(&fingerprints);
+        if groups.is_empty() {
+            self.duplicate_review = None;
+            if !export_after {
+                self.toasts.info(format!("No duplicates among {} extracted documents", fingerprints.len()));
+            }
+        } else {
+            // Suggest keeping the first document of each group
+            let excluded = groups.iter()
+                .flat_map(|g| g.members.iter().skip(1).copied())
+                .collect();
+            self.duplicate_review = Some(dedup::DuplicateReview { groups, excluded, export_after });
+        }
+    }
+    
+    /// Export the filtered, extracted documents into a folder on a worker thread
+    fn batch_export(&mut self, excluded: &std::collections::BTreeSet<usize>) {
+        if self.job.is_some() {
+            return;
+        }
+        let documents: Vec<_> = self.workspace.documents.iter()
+            .enumerate()
+            .filter(|(i, d)| !excluded.contains(i) && d.matches_tags(&self.workspace_tag_filter))
+            .filter(|(_, d)| d.extracted_json.is_some())
+            .map(|(_, d)| d.clone())
+            .collect();
+        if documents.is_empty() {
+            self.toasts.info("No extracted documents to export");
+            return;
+        }
+        let mut pipeline = self.settings.export_pipeline.clone();
+        let upload = match &pipeline.upload_to {
+            Some(name) => match self.settings.remote_locations.iter().find(|location| &location.name == name) {
+                Some(location) => Some(location.clone()),
+                None => {
+                    self.toasts.error(format!("The upload location {} is no longer set up; pick another under Settings → Batch export", name));
+                    return;
+                }
+            },
+            None => None,
+        };
+        pipeline.stamping = self.settings.stamping;
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else { return };
+        self.job = Some(Job::spawn("Batch export", move |job| {
+            pipeline.remote = upload.map(|location| location.connect());
+            Ok(chonker3::pipeline::run(&dir, &documents, &pipeline, job)?.describe())
+        }));
+    }
+    
+    fn show_page_organizer(&mut self, ctx: &egui::Context) {
+        let render_scale = self.render_scale();
+        let Some(organizer) = self.page_organizer.as_mut() else { return };
+        
+        // Render a few missing thumbnails per frame so the window opens immediately
+        let missing: Vec<usize> = organizer.slots.iter()
+            .filter_map(|s| s.source_index())
+            .filter(|i| !organizer.thumbnails.contains_key(i))
+            .take(4)
+            .collect();
+        if !missing.is_empty() {
+            if let (Some(pdfium), Some(pdf_bytes)) = (&self.session.pdfium, &self.session.pdf_bytes) {
+                if let Ok(document) = pdfium.load_pdf_from_byte_slice(pdf_bytes, self.session.pdf_password.as_deref()) {
+                    for index in missing {
+                        let Ok(page) = document.pages().get(index as u16) else { continue };
+                        let width = (110.0 * render_scale) as i32;
+                        let height = (width as f32 * page.height().value / page.width().value) as i32;
+                        if let Some(image) = renderer::render_pdf_page(&page, width, height) {
+                            let texture = ctx.load_texture(format!("thumb_{}", index), image, Default::default());
+                            organizer.thumbnails.insert(index, texture);
+                        }
+                    }
+                }
+            }
+            self.repaint.animate();
+        }
+        
+        let mut close = false;
+        let mut save = false;
+        let mut move_request = None;
+        let mut rotate_request = None;
+        let mut delete_request = None;
+        let mut insert_request = None;
+        
+        egui::Window::new("Organize pages")
+            .collapsible(false)
+            .resizable(true)
+            .default_size(Vec2::new(620.0, 480.0))
+            .show(ctx, |ui| {
+                ui.label("Drag thumbnails to reorder. Changes are written to a new PDF.");
+                ui.separator();
+                
+                ScrollArea::vertical().max_height(380.0).show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (slot_index, slot) in organizer.slots.iter_mut().enumerate() {
+                            let frame = egui::Frame::group(ui.style()).inner_margin(4.0);
+                            let response = frame.show(ui, |ui| {
+                                ui.vertical(|ui| {
+                                    ui.dnd_drag_source(egui::Id::new(("organizer_slot", slot_index)), slot_index, |ui| {
+                                        match &mut slot.source {
+                                            page_organizer::PageSource::Original(index) => {
+                                                if let Some(texture) = organizer.thumbnails.get(index) {
+                                                    // Thumbnails may be rendered smaller under memory pressure
+                                                    let size = texture.size_vec2() * (110.0 / texture.size_vec2().x);
+                                                    let image = egui::Image::new((texture.id(), size))
+                                                        .rotate(slot.rotation as f32 * std::f32::consts::PI / 180.0, Vec2::splat(0.5));
+                                                    ui.add_sized(Vec2::splat(size.x.max(size.y)), image);
+                                                } else {
+                                                    ui.add_sized(Vec2::new(110.0, 140.0), egui::Spinner::new());
+                                                }
+                                            }
+                                            page_organizer::PageSource::Blank { note, .. } => {
+                                                // Blank pages show their note instead of a thumbnail
+                                                ui.add_sized(
+                                                    Vec2::new(110.0, 140.0),
+                                                    egui::TextEdit::multiline(note).hint_text("Note for this page")
+                                                );
+                                            }
+                                        }
+                                    });
+                                    ui.horizontal(|ui| {
+                                        let label = match (slot.source_index(), slot.rotation) {
+                                            (None, _) => "blank".to_string(),
+                                            (Some(index), 0) => format!("p. {}", index + 1),
+                                            (Some(index), rotation) => format!("p. {} ⟳{}°", index + 1, rotation),
+                                        };
+                                        ui.label(RichText::new(label).small());
+                                        if slot.source_index().is_some() && ui.small_button("⟳").on_hover_text("Rotate 90°").clicked() {
+                                            rotate_request = Some(slot_index);
+                                        }
+                                        if ui.small_button("＋").on_hover_text("Insert blank page after").clicked() {
+                                            insert_request = Some(slot_index);
+                                        }
+                                        if ui.small_button("🗑").on_hover_text("Delete page").clicked() {
+                                            delete_request = Some(slot_index);
+                                        }
+                                    });
+                                });
+                            }).response;
+                            
+                            if let Some(from) = response.dnd_release_payload::<usize>() {
+                                move_request = Some((*from, slot_index));
+                            }
+                        }
+                    });
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save as new PDF...").clicked() {
+                        save = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                    ui.label(format!("{} pages", organizer.slots.len()));
+                });
+            });
+        
+        if let Some((from, to)) = move_request {
+            organizer.move_slot(from, to);
+        }
+        if let Some(index) = rotate_request {
+            organizer.rotate(index);
+        }
+        if let Some(index) = delete_request {
+            organizer.delete(index);
+        }
+        if let Some(index) = insert_request {
+            organizer.insert_blank(index);
+        }
+        if close {
+            self.page_organizer = None;
+        } else if save {
+            self.save_organized_pdf();
+        }
+    }
+    
+    /// Write the organized PDF and its remapped extraction, then open them
+    fn save_organized_pdf(&mut self) {
+        let (Some(organizer), Some(pdfium), Some(pdf_bytes)) = (&self.page_organizer, &self.session.pdfium, &self.session.pdf_bytes) else {
+            return;
+        };
+        if organizer.is_unchanged(self.session.page_count) {
+            self.toasts.info("No page changes to save");
+            return;
+        }
+        
+        let default_name = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| format!("{}_organized.pdf", s.to_string_lossy()))
+            .unwrap_or_else(|| "organized.pdf".to_string());
+        let Some(out_path) = rfd::FileDialog::new()
+            .add_filter("PDF", &["pdf"])
+            .set_file_name(default_name)
+            .save_file()
+        else {
+            return;
+        };
+        
+        // Overwriting the open file would change the mapped bytes mid-write; work from a copy
+        let same_file = self.session.pdf_path.as_ref()
+            .is_some_and(|p| p.canonicalize().ok() == out_path.canonicalize().ok());
+        let pdf_bytes = if same_file {
+            PdfBytes::from(pdf_bytes.to_vec())
+        } else {
+            pdf_bytes.clone()
+        };
+        
+        if let Err(e) = organizer.write_pdf(pdfium, &pdf_bytes, self.session.pdf_password.as_deref(), &out_path, &self.session.pdf_output) {
+            self.toasts.error(format!("Failed to write PDF: {}", e));
+            return;
+        }
+        
+        // Carry the extraction and edits over to the new page order
+        let remapped = self.session.extracted_data.as_ref().map(|data| organizer.remap_extraction(data));
+        let Some(organizer) = self.page_organizer.take() else { return };
+        self.session.edits.remap(|id| organizer.remap_item_id(id));
+        self.session.edits.remap_pages(|page| organizer.remap_page(page));
+        
+        self.load_pdf_here(out_path.clone());
+        if let Some(data) = remapped {
+            let json_path = out_path.with_extension("json");
+            let written = serde_json::to_string_pretty(&data).ok()
+                .filter(|json| std::fs::write(&json_path, json).is_ok())
+                .map(|_| json_path);
+            if let (Some(json_path), Some(index)) = (&written, self.workspace.find(&out_path)) {
+                self.workspace.set_extraction(index, json_path.clone());
+                let _ = self.workspace.save();
+            }
+            self.session.set_extraction(data, written);
+            self.pii_for = None;
+        }
+        self.toasts.success(format!("Saved reorganized PDF to {}", out_path.display()));
+    }
+    
+    /// Ask for a path and run an export, recording it to an active macro
+    fn export_with_dialog(&mut self, kind: ExportKind) {
+        let (suffix, filter, extensions): (&str, &str, &[&str]) = match kind {
+            ExportKind::Structured => ("structured.json", "JSON", &["json"]),
+            ExportKind::Jsonl => ("items.jsonl", "JSON Lines", &["jsonl"]),
+            ExportKind::Markdown => ("md", "Markdown", &["md"]),
+            ExportKind::Csv => ("csv", "CSV", &["csv"]),
+            ExportKind::Bibtex => ("bib", "BibTeX", &["bib"]),
+            ExportKind::BatesCsv => ("bates.csv", "CSV", &["csv"]),
+            ExportKind::Stats => ("stats.json", "JSON", &["json"]),
+            ExportKind::ReflowedPdf => ("reflowed.pdf", "PDF", &["pdf"]),
+            ExportKind::SearchablePdf => ("searchable.pdf", "PDF", &["pdf"]),
+            ExportKind::TablesCsv => ("tables.csv", "CSV", &["csv"]),
+            ExportKind::TablesXlsx => ("tables.xlsx", "Excel workbook", &["xlsx"]),
+            ExportKind::Fields => ("fields.json", "JSON", &["json"]),
+        };
+        let default_name = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| format!("{}.{}", s.to_string_lossy(), suffix))
+            .unwrap_or_else(|| format!("export.{}", suffix));
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(filter, extensions)
+            .set_file_name(default_name)
+            .save_file()
+        else {
+            return;
+        };
+        
+        let result = match kind {
+            ExportKind::Structured => self.session.export_structured(&path)
+                .map(|count| format!("Exported {} items to {}", count, path.display())),
+            ExportKind::Jsonl => self.session.export_jsonl(&path)
+                .map(|count| format!("Exported {} items to {}", count, path.display())),
+            ExportKind::Markdown => self.session.export_markdown(&path)
+                .map(|()| format!("Exported Markdown to {}", path.display())),
+            ExportKind::Csv => self.session.export_csv(&path)
+                .map(|()| format!("Exported CSV to {}", path.display())),
+            ExportKind::Bibtex => self.session.export_bibtex(&path)
+                .map(|count| format!("Exported {} references to {}", count, path.display())),
+            ExportKind::BatesCsv => self.session.export_bates_csv(&path)
+                .map(|count| format!("Exported Bates numbers of {} pages to {}", count, path.display())),
+            ExportKind::Stats => self.session.export_stats(&path)
+                .map(|()| format!("Exported statistics to {}", path.display())),
+            ExportKind::ReflowedPdf => self.session.export_reflowed_pdf(&path, &self.settings.print_layout)
+                .map(|pages| format!("Exported {} reflowed pages to {}", pages, path.display())),
+            ExportKind::SearchablePdf => self.session.export_searchable_pdf(&path)
+                .map(|pages| format!("Added a text layer to {} pages in {}", pages, path.display())),
+            ExportKind::TablesCsv => self.session.export_tables_csv(&path)
+                .map(|count| format!("Exported {} tables to {}", count, path.display())),
+            ExportKind::TablesXlsx => self.session.export_tables_xlsx(&path)
+                .map(|count| format!("Exported {} tables to {}", count, path.display())),
+            ExportKind::Fields => self.session.export_fields(&path)
+                .map(|count| format!("Exported {} named fields to {}", count, path.display())),
+        };
+        match result {
+            Ok(message) => {
+                self.toasts.success(message);
+                if let Some(recording) = self.macro_recording.as_mut() {
+                    let template = macros::generalize_path(&path, &self.session);
+                    recording.steps.push(match kind {
+                        ExportKind::Structured => MacroStep::ExportStructured(template),
+                        ExportKind::Jsonl => MacroStep::ExportJsonl(template),
+                        ExportKind::Markdown => MacroStep::ExportMarkdown(template),
+                        ExportKind::Csv => MacroStep::ExportCsv(template),
+                        ExportKind::Bibtex => MacroStep::ExportBibtex(template),
+                        ExportKind::BatesCsv => MacroStep::ExportBatesCsv(template),
+                        ExportKind::Stats => MacroStep::ExportStats(template),
+                        ExportKind::ReflowedPdf => MacroStep::ExportReflowedPdf(template, self.settings.print_layout),
+                        ExportKind::SearchablePdf => MacroStep::ExportSearchablePdf(template),
+                        ExportKind::TablesCsv => MacroStep::ExportTablesCsv(template),
+                        ExportKind::TablesXlsx => MacroStep::ExportTablesXlsx(template),
+                        ExportKind::Fields => MacroStep::ExportFields(template),
+                    });
+                }
+            }
+            Err(e) => self.toasts.error(format!("Export failed: {}", e)),
+        }
+    }
+    
+    /// Render every page to PNG in a folder, on a worker thread
+    fn export_page_images(&mut self) {
+        let Some(pdf_bytes) = self.session.pdf_bytes.clone() else { return };
+        let password = self.session.pdf_password.clone();
+        let library = self.session.pdfium_library.clone();
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else { return };
+        let stem = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "page".to_string());
+        let (stamp, stamping) = (self.session.stamp(), self.session.stamping);
+        
+        self.job = Some(Job::spawn("Exporting pages to PNG", move |job| {
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            // 2x scale is 144 DPI
+            let count = renderer::write_page_pngs(&pdfium, &pdf_bytes, password.as_deref(), &dir, &stem, 2.0, job)?;
+            if let Some(stamp) = stamp {
+                stamp.apply_folder(&dir, stamping)?;
+            }
+            Ok(format!("Exported {} pages to {}", count, dir.display()))
+        }));
+    }
+    
+    /// Render the pages to PNG and write the item boxes as layout annotations
+    /// for them, on a worker thread
+    fn export_layout_annotations(&mut self, format: annotations::AnnotationFormat) {
+        let Some(pdf_bytes) = self.session.pdf_bytes.clone() else { return };
+        let Some(data) = self.session.extracted_data.as_ref() else { return };
+        let stem = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "page".to_string());
+        let pages = annotations::collect(data, &self.session.to_patch(), &self.session.page_sizes(), &stem);
+        let password = self.session.pdf_password.clone();
+        let library = self.session.pdfium_library.clone();
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else { return };
+        let (stamp, stamping) = (self.session.stamp(), self.session.stamping);
+        
+        self.job = Some(Job::spawn("Exporting layout annotations", move |job| {
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            let count = renderer::write_page_pngs(&pdfium, &pdf_bytes, password.as_deref(), &dir, &stem, annotations::IMAGE_SCALE, job)?;
+            let boxes = annotations::write(&dir, &pages, &stem, format)?;
+            if let Some(stamp) = stamp {
+                stamp.apply_folder(&dir, stamping)?;
+            }
+            Ok(format!("Exported {} pages with {} {} boxes to {}", count, boxes, format.label(), dir.display()))
+        }));
+    }
+    
+    /// Render the pages to PNG and write a Label Studio task for each, on a worker thread
+    fn export_label_studio(&mut self) {
+        let Some(pdf_bytes) = self.session.pdf_bytes.clone() else { return };
+        let Some(data) = self.session.extracted_data.clone() else { return };
+        let stem = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "page".to_string());
+        let edits = self.session.to_patch();
+        let pages = annotations::collect(&data, &edits, &self.session.page_sizes(), &stem);
+        let password = self.session.pdf_password.clone();
+        let library = self.session.pdfium_library.clone();
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else { return };
+        let (stamp, stamping) = (self.session.stamp(), self.session.stamping);
+        
+        self.job = Some(Job::spawn("Exporting Label Studio tasks", move |job| {
+            let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+            renderer::write_page_pngs(&pdfium, &pdf_bytes, password.as_deref(), &dir, &stem, annotations::IMAGE_SCALE, job)?;
+            let count = label_studio::write(&dir, &data, &edits, &pages, &stem)?;
+            if let Some(stamp) = stamp {
+                stamp.apply_folder(&dir, stamping)?;
+            }
+            Ok(format!("Exported {} Label Studio tasks to {}", count, dir.display()))
+        }));
+    }
+    
+    /// Zip up the document package on a worker thread; page images need pdfium
+    fn export_bundle(&mut self) {
+        let files = match self.session.bundle_files() {
+            Ok(files) => files,
+            Err(e) => {
+                self.toasts.error(format!("Export failed: {}", e));
+                return;
+            }
+        };
+        let stem = self.session.pdf_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "document".to_string());
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Zip", &["zip"])
+            .set_file_name(format!("{}_bundle.zip", stem))
+            .save_file()
+        else { return };
+        let pdf_bytes = self.session.pdf_bytes.clone().filter(|_| self.session.pdfium.is_some());
+        let password = self.session.pdf_password.clone();
+        let library = self.session.pdfium_library.clone();
+        let (stamp, stamping) = (self.session.stamp(), self.session.stamping);
+        
+        self.job = Some(Job::spawn("Exporting bundle", move |job| {
+            let mut files = files;
+            if let Some(pdf_bytes) = pdf_bytes {
+                let pdfium = chonker3::core::bind_pdfium_from(library.as_deref())?;
+                files.extend(chonker3::bundle::page_image_files(renderer::page_pngs(&pdfium, &pdf_bytes, password.as_deref(), 2.0, job)?));
+            }
+            chonker3::bundle::write_zip(&path, &files)?;
+            if let Some(stamp) = stamp {
+                stamp.apply(&path, stamping)?;
+            }
+            Ok(format!("Wrote {} files to {}", files.len(), path.display()))
+        }));
+    }
+    
+    /// Download pdfium for this platform on a worker thread
+    fn download_pdfium(&mut self) {
+        if self.job.is_some() {
+            return;
+        }
+        self.job_followup = JobFollowup::LoadPdfium;
+        self.job = Some(Job::spawn("Downloading pdfium", |job| {
+            let library = pdfium_bootstrap::download(job)?;
+            Ok(format!("Installed pdfium to {}", library.display()))
+        }));
+    }
+    
+    /// Load the library a finished download installed
+    fn load_downloaded_pdfium(&mut self) {
+        let Some(library) = pdfium_bootstrap::installed_library() else { return };
+        match self.session.set_pdfium_library(&library) {
+            Ok(()) => self.pdf_texture = None,
+            Err(e) => self.toasts.error(e.to_string()),
+        }
+    }
+    
+    /// First-run dialog offering to download pdfium
+    fn show_pdfium_download(&mut self, ctx: &egui::Context) {
+        if !self.show_pdfium_download {
+            return;
+        }
+        let mut answered = None;
+        egui::Window::new("Download pdfium?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Chonker3 renders PDF pages with the pdfium library, which wasn't found on this system.");
+                ui.label("It can download a prebuilt copy from github.com/bblanchon/pdfium-binaries (about 5 MB) and verify its checksum.");
+                if let Some(dir) = pdfium_bootstrap::install_dir() {
+                    ui.label(RichText::new(format!("Installs to {}", dir.display())).small().color(Color32::GRAY));
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Download").clicked() {
+                        answered = Some(true);
+                    }
+                    if ui.button("Not now").clicked() {
+                        answered = Some(false);
+                    }
+                });
+            });
+        
+        if let Some(download) = answered {
+            self.show_pdfium_download = false;
+            self.settings.pdfium_download_offered = true;
+            if let Err(e) = self.settings.save() {
+                self.toasts.error(format!("Failed to save settings: {}", e));
+            }
+            if download {
+                self.download_pdfium();
+            }
+        }
+    }
+    
+    fn open_python_setup(&mut self) {
+        self.show_python_setup = true;
+        self.check_python_env();
+    }
+    
+    /// Check the venv on a worker thread; importing docling takes a few seconds
+    fn check_python_env(&mut self) {
+        let status = self.python_status.clone();
+        *status.lock().unwrap() = None;
+        std::thread::spawn(move || {
+            let result = python_env::check(&python_env::venv_dir());
+            *status.lock().unwrap() = Some(result);
+        });
+    }
+    
+    /// Guided setup of the Python environment extraction runs in
+    fn show_python_setup(&mut self, ctx: &egui::Context) {
+        if !self.show_python_setup {
+            return;
+        }
+        let status = self.python_status.lock().unwrap().clone();
+        let mut open = true;
+        let mut set_up = false;
+        let mut recheck = false;
+        egui::Window::new("Python environment")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Extraction runs Docling in a Python virtual environment:");
+                ui.label(RichText::new(python_env::venv_dir().display().to_string()).monospace());
+                ui.add_space(4.0);
+                
+                match &status {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Checking...");
+                        });
+                    }
+                    Some(python_env::EnvStatus::Ready) => {
+                        ui.label(RichText::new("✔ Ready: docling and pypdfium2 import").color(Color32::from_rgb(16, 185, 129)));
+                    }
+                    Some(status) => {
+                        ui.label(RichText::new(format!("⚠ {}", status.describe())).color(Color32::from_rgb(245, 158, 11)));
+                        let packages: Vec<&str> = python_env::REQUIRED_PACKAGES.iter().map(|(package, _)| *package).collect();
+                        ui.label(format!(
+                            "Set up creates the environment if needed and installs {}. This downloads several hundred MB.",
+                            packages.join(" and ")
+                        ));
+                    }
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let can_set_up = self.job.is_none() && matches!(status, Some(ref s) if *s != python_env::EnvStatus::Ready);
+                    if ui.add_enabled(can_set_up, egui::Button::new("Set up")).clicked() {
+                        set_up = true;
+                    }
+                    if ui.add_enabled(status.is_some() && self.job.is_none(), egui::Button::new("Check again")).clicked() {
+                        recheck = true;
+                    }
+                });
+            });
+        self.show_python_setup = open;
+        
+        if set_up {
+            let venv = python_env::venv_dir();
+            self.job_followup = JobFollowup::CheckPython;
+            *self.python_status.lock().unwrap() = None;
+            self.job = Some(Job::spawn("Setting up Python environment", move |job| python_env::setup(&venv, job)));
+        } else if recheck {
+            self.check_python_env();
+        }
+        if status.is_none() {
+            self.repaint.within(repaint::PROGRESS_POLL);
+        }
+    }
+    
+    /// Progress dialog for the running job; reports the outcome when it finishes
+    fn show_job_progress(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.job else { return };
+        
+        if let Some(result) = job.take_result() {
+            match result {
+                Ok(message) => self.toasts.success(message),
+                Err(_) if job.is_cancelled() => self.toasts.info(format!("{} cancelled", job.title)),
+                Err(e) => self.toasts.error(format!("{} failed: {}", job.title, e)),
+            }
+            self.job = None;
+            let followup = std::mem::take(&mut self.job_followup);
+            match self.job_document.take() {
+                Some(path) if self.session.pdf_path.as_ref() != Some(&path) => self.follow_up_in_background(&path, followup),
+                _ => self.follow_up(followup),
+            }
+            return;
+        }
+        
+        let (done, total, step) = job.progress();
+        let mut cancel = false;
+        egui::Window::new(&job.title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let fraction = if total > 0 { done as f32 / total as f32 } else { 0.0 };
+                ui.add(egui::ProgressBar::new(fraction)
+                    .desired_width(300.0)
+                    .text(format!("{} / {}", done, total)));
+                ui.label(step);
+                match job.eta() {
+                    Some(eta) => ui.label(format!("About {} left", jobs::format_duration(eta))),
+                    None => ui.label("Estimating time left..."),
+                };
+                
+                ui.separator();
+                if job.is_cancelled() {
+                    ui.label(RichText::new("Cancelling...").color(Color32::GRAY));
+                } else if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        if cancel {
+            job.cancel();
+        }
+        self.repaint.within(repaint::PROGRESS_POLL);
+    }
+    
+    /// What to do once a job finishes, on the active document
+    fn follow_up(&mut self, followup: JobFollowup) {
+        match followup {
+            JobFollowup::LoadPdfium => self.load_downloaded_pdfium(),
+            JobFollowup::CheckPython => self.check_python_env(),
+            JobFollowup::AddSplitDocuments => self.add_split_documents(),
+            JobFollowup::AddBarcodes => self.add_detected_barcodes(),
+            JobFollowup::ApplyReocr => self.apply_reocr(),
+            JobFollowup::ShowLlmReply => self.show_llm_reply(),
+            JobFollowup::ApplyLlmSummary => self.apply_llm_summary(),
+            JobFollowup::ShowRemoteListing => self.show_remote_listing(),
+            JobFollowup::OpenRemoteDownload => self.open_remote_download(),
+            JobFollowup::None => {}
+        }
+    }
+    
+    /// Run a job's followup on the background tab the job was started in
+    fn follow_up_in_background(&mut self, path: &Path, followup: JobFollowup) {
+        if !self.with_parked_document(path, |app| app.follow_up(followup)) {
+            log::info!("{} was closed before its job finished", path.display());
+        }
+    }
+    
+    /// Record the edit dialog's pending changes to an active macro
+    fn record_item_edit(&mut self, item_id: &str, deleted: bool) {
+        let Some(recording) = self.macro_recording.as_mut() else { return };
+        let Some(item) = self.session.document_state()
+            .and_then(|state| state.items.into_iter().find(|i| i.id == item_id))
+        else {
+            return;
+        };
+        let text = self.session.edits.text_overrides.get(item_id).cloned().unwrap_or_else(|| item.content.clone());
+        let selector = |match_by| ItemSelector::from_item(&item, &text, match_by);
+        
+        // Repeated headers are matched by text, retyped blocks by where they sit on the page
+        if deleted {
+            recording.steps.push(MacroStep::Delete(selector(MatchBy::SameText)));
+            return;
+        }
+        if self.edit_text_buffer != text {
+            recording.steps.push(MacroStep::SetText(selector(MatchBy::SameText), self.edit_text_buffer.clone()));
+        }
+        if let Some(item_type) = self.edit_type_buffer.filter(|t| *t != item.item_type) {
+            recording.steps.push(MacroStep::SetType(selector(MatchBy::SameRegion), item_type));
+        }
+        let note = self.edit_annotation_buffer.trim();
+        if !note.is_empty() && self.session.edits.annotations.get(item_id).map(|n| n.trim()) != Some(note) {
+            recording.steps.push(MacroStep::Annotate(selector(MatchBy::SameText), note.to_string()));
+        }
+    }
+    
+    fn show_macros(&mut self, ctx: &egui::Context) {
+        if !self.show_macros {
+            return;
+        }
+        
+        let mut open = true;
+        let mut run = None;
+        let mut remove = None;
+        // Some(true) saves the recording, Some(false) discards it
+        let mut stop = None;
+        egui::Window::new("Macros")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                // Recorder
+                if let Some(recording) = self.macro_recording.as_mut() {
+                    ui.label(RichText::new(format!("⏺ Recording ({} steps)", recording.steps.len())).color(Color32::RED));
+                    for step in &recording.steps {
+                        ui.label(format!("• {}", step.describe()));
+                    }
+                    let can_save = !self.macro_name_buffer.trim().is_empty() && !recording.steps.is_empty();
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.macro_name_buffer).hint_text("Macro name"));
+                        if ui.add_enabled(can_save, egui::Button::new("Stop and save")).clicked() {
+                            stop = Some(true);
+                        } else if ui.button("Discard").clicked() {
+                            stop = Some(false);
+                        }
+                    });
+                } else if ui.button("⏺ Record").on_hover_text("Record item edits and exports").clicked() {
+                    self.macro_recording = Some(macros::Macro::default());
+                }
+                
+                ui.separator();
+                ui.checkbox(&mut self.macro_all_pages, "Replay on all pages");
+                
+                // Saved macros
+                let mut changed = false;
+                for (name, recorded) in self.workspace.macros.iter_mut() {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(name).strong());
+                        if ui.button("▶ Run").clicked() {
+                            run = Some(name.clone());
+                        }
+                        if ui.button("🗑").on_hover_text("Delete macro").clicked() {
+                            remove = Some(name.clone());
+                        }
+                    });
+                    egui::CollapsingHeader::new(format!("{} steps", recorded.steps.len()))
+                        .id_salt(("macro_steps", name.as_str()))
+                        .show(ui, |ui| {
+                            for (index, step) in recorded.steps.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label(step.describe());
+                                    if let Some(selector) = step.selector_mut() {
+                                        egui::ComboBox::from_id_salt(("macro_match", name.as_str(), index))
+                                            .selected_text(selector.match_by.label())
+                                            .show_ui(ui, |ui| {
+                                                for match_by in MatchBy::ALL {
+                                                    changed |= ui.selectable_value(&mut selector.match_by, match_by, match_by.label()).changed();
+                                                }
+                                            });
+                                    }
+                                });
+                            }
+                        });
+                }
+                if changed {
+                    let _ = self.workspace.save();
+                }
+            });
+        self.show_macros = open;
+        
+        match (stop, self.macro_recording.take()) {
+            (Some(true), Some(recording)) => {
+                let name = self.macro_name_buffer.trim().to_string();
+                self.workspace.macros.insert(name.clone(), recording);
+                if let Err(e) = self.workspace.save() {
+                    log::warn!("Failed to save workspace: {}", e);
+                }
+                self.toasts.success(format!("Saved macro '{}'", name));
+                self.macro_name_buffer.clear();
+            }
+            (Some(_), _) => {}
+            (None, recording) => self.macro_recording = recording,
+        }
+        if let Some(name) = remove {
+            self.workspace.macros.remove(&name);
+            let _ = self.workspace.save();
+        }
+        if let Some(name) = run {
+            let scope = if self.macro_all_pages { ReplayScope::AllPages } else { ReplayScope::CurrentPage };
+            if let Some(recorded) = self.workspace.macros.get(&name).cloned() {
+                match recorded.replay(&mut self.session, scope) {
+                    Ok(edits) => self.toasts.success(format!("Macro '{}' made {} edits", name, edits)),
+                    Err(e) => self.toasts.error(format!("Macro '{}' failed: {}", name, e)),
+                }
+            }
+        }
+    }
+    
+    /// Run the console script against the current session
+    fn run_script(&mut self) {
+        // Keep the autorun copy in sync with what was last run
+        if self.script_autorun {
+            if let Err(e) = std::fs::write(scripting::autorun_path(), &self.script_source) {
+                log::warn!("Failed to update {}: {}", scripting::AUTORUN_FILE, e);
+            }
+        }
+        match scripting::run_script(&self.script_source, &mut self.session) {
+            Ok(outcome) => {
+                self.toasts.success(format!("Script made {} edits", outcome.edits));
+                self.script_output = outcome.log;
+            }
+            Err(e) => {
+                self.toasts.error(e.to_string());
+                self.script_output = vec![e.to_string()];
+            }
+        }
+    }
+    
+    fn run_autorun_script(&mut self) {
+        if self.script_autorun && !self.script_source.trim().is_empty() {
+            self.run_script();
+        }
+    }
+    
+    fn show_script_console(&mut self, ctx: &egui::Context) {
+        if !self.show_script_console {
+            return;
+        }
+        
+        let mut open = true;
+        egui::Window::new("Script console")
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("Rhai script: iterate `items`, call set_text/set_type/delete/annotate/export_markdown/export_structured").color(Color32::GRAY));
+                ScrollArea::vertical()
+                    .id_salt("script_source")
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.script_source)
+                                .code_editor()
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(12)
+                        );
+                    });
+                
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new("▶ Run")).clicked() {
+                        self.run_script();
+                    }
+                    if ui.button("Load...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new().add_filter("Rhai script", &["rhai"]).pick_file() {
+                            match std::fs::read_to_string(&path) {
+                                Ok(source) => self.script_source = source,
+                                Err(e) => self.toasts.error(format!("Failed to read script: {}", e)),
+                            }
+                        }
+                    }
+                    if ui.button("Save...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Rhai script", &["rhai"])
+                            .set_file_name("script.rhai")
+                            .save_file()
+                        {
+                            if let Err(e) = std::fs::write(&path, &self.script_source) {
+                                self.toasts.error(format!("Failed to save script: {}", e));
+                            }
+                        }
+                    }
+                    
+                    // The autorun script is kept in the session directory
+                    if ui.checkbox(&mut self.script_autorun, "Run after each extraction").changed() {
+                        let result = if self.script_autorun {
+                            std::fs::write(scripting::autorun_path(), &self.script_source)
+                        } else {
+                            std::fs::remove_file(scripting::autorun_path())
+                        };
+                        if let Err(e) = result {
+                            log::warn!("Failed to update {}: {}", scripting::AUTORUN_FILE, e);
+                        }
+                    }
+                });
+                
+                ui.separator();
+                ScrollArea::vertical()
+                    .id_salt("script_output")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for line in &self.script_output {
+                            ui.label(RichText::new(line).monospace());
+                        }
+                    });
+            });
+        self.show_script_console = open;
+    }
+    
+    /// Compare the embedded e-invoice with the extracted text
+    fn show_einvoice_check(&mut self, ctx: &egui::Context) {
+        let Some(invoice) = &self.einvoice else { return };
+        if !self.show_einvoice {
+            return;
+        }
+        
+        let mut open = true;
+        egui::Window::new("E-invoice cross-check")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Embedded file: {}", invoice.attachment_name));
+                if let Some(currency) = &invoice.currency {
+                    ui.label(format!("Currency: {}", currency));
+                }
+                ui.separator();
+                
+                let Some(data) = &self.session.extracted_data else {
+                    ui.label(RichText::new("Extract the PDF to compare against its visible text.").color(Color32::GRAY));
+                    return;
+                };
+                
+                let checks = einvoice::cross_check(invoice, data);
+                let mismatches = checks.iter().filter(|c| c.status == einvoice::CheckStatus::NotFound).count();
+                egui::Grid::new("einvoice_checks").num_columns(3).striped(true).show(ui, |ui| {
+                    for check in &checks {
+                        match check.status {
+                            einvoice::CheckStatus::Matched(page) => {
+                                ui.label(RichText::new("✔").color(TEAL));
+                                ui.label(check.label);
+                                ui.label(&check.value).on_hover_text(format!("Found on page {}", page));
+                            }
+                            einvoice::CheckStatus::NotFound => {
+                                ui.label(RichText::new("⚠").color(Color32::from_rgb(230, 126, 34)));
+                                ui.label(check.label);
+                                ui.label(&check.value).on_hover_text("Not found in the extracted text");
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+                
+                ui.separator();
+                if mismatches == 0 {
+                    ui.label(RichText::new("All fields match the visible invoice").color(TEAL));
+                } else {
+                    ui.label(RichText::new(format!("{} field(s) not found in the visible invoice", mismatches))
+                        .color(Color32::from_rgb(230, 126, 34)));
+                }
+            });
+        self.show_einvoice = open;
+    }
+    
+    /// Estimate memory use and evict caches or lower the render resolution when over budget
+    fn check_memory(&mut self) {
+        let due = self.memory_checked.is_none_or(|t| t.elapsed() >= std::time::Duration::from_secs(2));
+        if !due {
+            return;
+        }
+        self.memory_checked = Some(std::time::Instant::now());
+        
+        let galleys = self.document_state().map(|state| {
+            memory::galley_bytes(state.items.iter()
+                .map(|item| state.item_text_overrides.get(&item.id).unwrap_or(&item.content).as_str()))
+        });
+        self.memory_usage = MemoryUsage {
+            page_textures: self.pdf_texture.as_ref().map(|t| memory::texture_bytes(t.size())).unwrap_or(0)
+                + self.prefetcher.as_ref().map_or(0, |p| p.bytes())
+                + self.continuous.texture_bytes(),
+            thumbnails: self.page_organizer.as_ref()
+                .map(|o| o.thumbnails.values().map(|t| memory::texture_bytes(t.size())).sum())
+                .unwrap_or(0),
+            galleys: galleys.unwrap_or(0),
+            extraction: self.session.extracted_data.as_ref().map(memory::json_bytes).unwrap_or(0),
+            pdf_bytes: self.session.pdf_bytes.as_ref().map(|b| b.heap_bytes()).unwrap_or(0),
+        };
+        // Background tabs keep their pages and extraction too
+        for tab in self.tabs.tabs().iter().filter_map(|tab| tab.parked()) {
+            self.memory_usage += tab.memory_usage();
+        }
+        
+        match self.memory_guard.update(self.memory_usage) {
+            GuardAction::None => {}
+            GuardAction::Evict { render_scale } => {
+                self.pdf_texture = None;
+                if let Some(prefetcher) = self.prefetcher.as_mut() {
+                    prefetcher.clear();
+                }
+                self.continuous.textures.clear();
+                if let Some(organizer) = self.page_organizer.as_mut() {
+                    organizer.thumbnails.clear();
+                }
+                for tab in self.tabs.parked_mut() {
+                    tab.evict_textures();
+                }
+                self.toasts.info(format!(
+                    "Memory use {} is over budget; rendering pages at {:.0}%",
+                    memory::format_mb(self.memory_usage.total()),
+                    render_scale * 100.0
+                ));
+            }
+            GuardAction::Restore { .. } => {
+                self.pdf_texture = None;
+                if let Some(organizer) = self.page_organizer.as_mut() {
+                    organizer.thumbnails.clear();
+                }
+                // Background tabs render at the new scale when they're shown
+                for tab in self.tabs.parked_mut() {
+                    tab.evict_textures();
+                }
+            }
+        }
+    }
+    
+    /// Banner explaining that pages can't be rendered without pdfium
+    fn show_pdfium_banner(&mut self, ctx: &egui::Context) {
+        if self.session.pdf_path.is_none() || self.session.pdfium.is_some() {
+            return;
+        }
+        egui::TopBottomPanel::top("pdfium_banner").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(RichText::new("⚠ PDF pages can't be shown: the pdfium library wasn't found.").color(Color32::from_rgb(245, 158, 11)).strong());
+                ui.label("Extraction and the extracted view still work. Install pdfium into ./lib or set PDFIUM_DYNAMIC_LIB_PATH, download it, or locate the library file:");
+                if pdfium_bootstrap::pinned_asset().is_some()
+                    && ui.add_enabled(self.job.is_none(), egui::Button::new("Download pdfium")).clicked()
+                {
+                    self.download_pdfium();
+                }
+                if ui.button("Locate pdfium library...").clicked() {
+                    if let Some(library) = rfd::FileDialog::new()
+                        .add_filter("pdfium library", &["dylib", "so", "dll"])
+                        .pick_file()
+                    {
+                        match self.session.set_pdfium_library(&library) {
+                            Ok(()) => {
+                                self.pdf_texture = None;
+                                self.settings.pdfium_library = Some(library);
+                                if let Err(e) = self.settings.save() {
+                                    self.toasts.error(format!("Failed to save settings: {}", e));
+                                }
+                                self.toasts.success("Loaded pdfium");
+                            }
+                            Err(e) => self.toasts.error(e.to_string()),
+                        }
+                    }
+                }
+            });
+            if let Some(error) = &self.session.pdfium_error {
+                ui.label(RichText::new(error).small().color(Color32::GRAY));
+            }
+        });
+    }
+    
+    /// Stops the running extraction; Python is killed mid-run
+    fn cancel_extraction_button(&mut self, ui: &mut egui::Ui) {
+        let cancelling = self.extraction_cancel.is_cancelled();
+        if ui.add_enabled(!cancelling, egui::Button::new(if cancelling { "Cancelling..." } else { "Cancel" }).small()).clicked() {
+            self.extraction_cancel.cancel();
+            self.status_message = "Cancelling extraction...".to_string();
+        }
+    }
+    
+    /// Bottom bar with the status message, pointer position, selection and backend
+    fn show_status_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status_bar")
+            .exact_height(24.0)
+            .show(ctx, |ui| {
+                ui.horizontal_centered(|ui| {
+                    ui.label(RichText::new(&self.status_message).size(12.0));
+                    if self.is_extracting {
+                        ui.label(RichText::new("🐹 *chomping*").size(12.0).color(TEAL));
+                        if let Some((done, total)) = self.extraction_progress.filter(|(_, total)| *total > 0) {
+                            ui.add(egui::ProgressBar::new(done as f32 / total as f32)
+                                .desired_width(120.0)
+                                .text(format!("{} / {} pages", done, total)));
+                        }
+                        self.cancel_extraction_button(ui);
+                    }
+                    
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        self.toasts.history_button(ui);
+                        ui.separator();
+                        
+                        if let Some(bates) = self.session.page_bates(self.session.page) {
+                            ui.label(RichText::new(format!("Bates {}", bates)).size(12.0).monospace())
+                                .on_hover_text("Bates number stamped on this page");
+                            ui.separator();
+                        }
+                        
+                        if let Some(backend) = self.session.extraction_backend() {
+                            ui.label(RichText::new(format!("Backend: {}", backend)).size(12.0))
+                                .on_hover_text(format!("Extraction has: {}", self.session.extraction_capabilities().summary()));
+                            ui.separator();
+                        }
+                        
+                        // Selection stats, counting edited text
+                        if let Some(state) = self.document_state() {
+                            let texts: Vec<&str> = state.items.iter()
+                                .filter(|item| state.selected_items.contains(&item.id))
+                                .map(|item| state.item_text_overrides.get(&item.id).unwrap_or(&item.content).as_str())
+                                .collect();
+                            if !texts.is_empty() {
+                                let chars: usize = texts.iter().map(|t| t.chars().count()).sum();
+                                let words: usize = texts.iter().map(|t| t.split_whitespace().count()).sum();
+                                ui.label(RichText::new(format!(
+                                    "{} selected · {} chars · {} words",
+                                    texts.len(), chars, words
+                                )).size(12.0));
+                                ui.separator();
+                            }
+                        }
+                        
+                        let memory_text = RichText::new(format!("Mem {}", memory::format_mb(self.memory_usage.total()))).size(12.0);
+                        let memory_text = if self.memory_guard.is_degraded() {
+                            memory_text.color(Color32::from_rgb(245, 158, 11))
+                        } else {
+                            memory_text
+                        };
+                        ui.label(memory_text).on_hover_text(format!(
+                            "{}\nBudget: {}\nRender scale: {:.0}%",
+                            self.memory_usage.breakdown(),
+                            memory::format_mb(self.memory_guard.budget_bytes),
+                            self.memory_guard.render_scale * 100.0
+                        ));
+                        ui.separator();
+                        
+                        if let Some((x, y)) = self.pointer_position {
+                            ui.label(RichText::new(format!("x {:.1} pt, y {:.1} pt", x, y)).size(12.0).monospace());
+                        }
+                    });
+                });
+            });
+    }
+    
+    fn show_settings(&mut self, ctx: &egui::Context) {
+        if !self.show_settings {
+            return;
+        }
+        let mut open = true;
+        let mut changed = false;
+        let mut open_python_setup = false;
+        let storage_before = self.settings.storage.clone();
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("Copy on click").strong());
+                let copy = &mut self.settings.copy;
+                egui::Grid::new("copy_settings_grid").num_columns(2).show(ui, |ui| {
+                    for (label, format) in [
+                        ("Click", &mut copy.click),
+                        ("Shift+click", &mut copy.shift_click),
+                        ("Alt+click", &mut copy.alt_click),
+                        ("Cmd/Ctrl+click", &mut copy.command_click),
+                    ] {
+                        ui.label(label);
+                        egui::ComboBox::from_id_salt(label)
+                            .selected_text(format.label())
+                            .show_ui(ui, |ui| {
+                                for option in clipboard::CopyFormat::ALL {
+                                    changed |= ui.selectable_value(format, option, option.label()).changed();
+                                }
+                            });
+                        ui.end_row();
+                    }
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Quality").strong());
+                let preset = self.settings.quality_preset();
+                let mut quality_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Preset:");
+                    egui::ComboBox::from_id_salt("quality_preset")
+                        .selected_text(preset.map_or("Custom", |p| p.label()))
+                        .show_ui(ui, |ui| {
+                            for option in QualityPreset::ALL {
+                                if ui.selectable_label(preset == Some(option), option.label()).clicked() {
+                                    self.settings.apply_quality_preset(option);
+                                    quality_changed = true;
+                                }
+                            }
+                        });
+                }).response.on_hover_text("Battery saver renders less and repaints less often; Quality renders sharper pages further ahead");
+                ui.horizontal(|ui| {
+                    ui.label("Page sharpness:");
+                    quality_changed |= ui.add(egui::DragValue::new(&mut self.settings.texture_scale)
+                        .range(0.5..=2.0)
+                        .speed(0.05)
+                        .suffix("×")).changed();
+                }).response.on_hover_text("Page image resolution relative to the screen");
+                ui.horizontal(|ui| {
+                    ui.label("Frame interval:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.settings.repaint_interval_ms)
+                        .range(0..=200)
+                        .suffix(" ms")).changed();
+                }).response.on_hover_text("Shortest time between frames while scrolling or extracting; 0 draws every frame");
+                
+                ui.separator();
+                ui.label(RichText::new("Memory").strong());
+                ui.horizontal(|ui| {
+                    ui.label("Budget:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.settings.memory_budget_mb)
+                        .range(128..=65536)
+                        .suffix(" MB")).changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Render ahead:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.settings.prefetch_depth)
+                        .range(0..=10)
+                        .suffix(" pages")).changed();
+                }).response.on_hover_text("Pages rendered in the background in the direction you're reading, so page turns are instant");
+                if quality_changed {
+                    // Re-render the page at the new resolution
+                    self.pdf_texture = None;
+                    changed = true;
+                }
+                
+                ui.separator();
+                ui.label(RichText::new("Scrolling").strong());
+                changed |= ui.checkbox(&mut self.settings.smooth_scrolling, "Smooth scrolling with drag momentum").changed();
+                
+                ui.separator();
+                ui.label(RichText::new("Comments").strong());
+                ui.horizontal(|ui| {
+                    ui.label("Sign comments as:");
+                    let mut name = self.settings.comment_author.clone().unwrap_or_default();
+                    let response = ui.add(egui::TextEdit::singleline(&mut name).hint_text(comments::default_author()));
+                    if response.changed() {
+                        self.settings.comment_author = Some(name).filter(|name| !name.trim().is_empty());
+                    }
+                    changed |= response.lost_focus();
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Encrypted PDFs").strong());
+                changed |= ui.checkbox(&mut self.settings.remember_pdf_passwords, "Remember passwords in the system keyring")
+                    .on_hover_text("Passwords are kept by the OS (Keychain, Credential Manager or Secret Service), never in Chonker3's own files")
+                    .changed();
+                
+                ui.separator();
+                ui.label(RichText::new("LLM post-processing").strong());
+                changed |= ui.checkbox(&mut self.settings.llm.enabled, "Send text to a language model on request")
+                    .on_hover_text("Only what you send from the LLM panel leaves the app, and nothing is changed without your say-so")
+                    .changed();
+                ui.add_enabled_ui(self.settings.llm.enabled, |ui| {
+                    egui::Grid::new("llm_settings").num_columns(2).show(ui, |ui| {
+                        ui.label("Endpoint:");
+                        changed |= ui.text_edit_singleline(&mut self.settings.llm.endpoint)
+                            .on_hover_text("OpenAI-compatible base URL, e.g. https://api.openai.com/v1")
+                            .lost_focus();
+                        ui.end_row();
+                        ui.label("Model:");
+                        changed |= ui.text_edit_singleline(&mut self.settings.llm.model).lost_focus();
+                        ui.end_row();
+                        ui.label("Timeout (s):");
+                        changed |= ui.add(egui::DragValue::new(&mut self.settings.llm.timeout_secs).range(5..=600)).changed();
+                        ui.end_row();
+                        ui.label("API key:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.llm_api_key_buffer).password(true).hint_text("kept in the keyring").desired_width(160.0));
+                            if ui.button("Save").on_hover_text("Saving an empty key removes it").clicked() {
+                                match llm::set_api_key(&self.llm_api_key_buffer) {
+                                    Ok(()) => self.toasts.success("API key saved"),
+                                    Err(e) => self.toasts.error(e.to_string()),
+                                }
+                                self.llm_api_key_buffer.clear();
+                            }
+                        });
+                        ui.end_row();
+                    });
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Text layout").strong());
+                ui.horizontal(|ui| {
+                    ui.label("Fit lines to box width:");
+                    egui::ComboBox::from_id_salt("width_fitting")
+                        .selected_text(self.settings.width_fitting.label())
+                        .show_ui(ui, |ui| {
+                            for fitting in chonker3::settings::WidthFitting::ALL {
+                                changed |= ui.selectable_value(&mut self.settings.width_fitting, fitting, fitting.label()).changed();
+                            }
+                        });
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Reflowed PDF export").strong());
+                let layout = &mut self.settings.print_layout;
+                ui.horizontal(|ui| {
+                    ui.label("Paper:");
+                    egui::ComboBox::from_id_salt("print_paper")
+                        .selected_text(layout.paper.label())
+                        .show_ui(ui, |ui| {
+                            for paper in chonker3::reflow::PaperSize::ALL {
+                                changed |= ui.selectable_value(&mut layout.paper, paper, paper.label()).changed();
+                            }
+                        });
+                });
+                changed |= ui.add(egui::Slider::new(&mut layout.margin, 18.0..=144.0).suffix(" pt").text("Margins")).changed();
+                changed |= ui.add(egui::Slider::new(&mut layout.font_size, 8.0..=16.0).suffix(" pt").text("Body text")).changed();
+                
+                ui.separator();
+                ui.label(RichText::new("PDF export size").strong());
+                let output = &mut self.settings.pdf_output;
+                changed |= ui.checkbox(&mut output.recompress_images, "Recompress images as JPEG").changed();
+                ui.add_enabled_ui(output.recompress_images, |ui| {
+                    changed |= ui.add(egui::Slider::new(&mut output.jpeg_quality, 20..=95).text("JPEG quality")).changed();
+                    changed |= ui.add(egui::Slider::new(&mut output.max_dpi, 72..=600).suffix(" dpi").text("Downsample above")).changed();
+                });
+                let qpdf = chonker3::pdf_output::linearizer_available();
+                ui.add_enabled_ui(qpdf || output.linearize, |ui| {
+                    changed |= ui.checkbox(&mut output.linearize, "Linearize for fast web view").changed();
+                });
+                if !qpdf {
+                    ui.label(RichText::new("Linearizing needs qpdf on the PATH").weak());
+                }
+                
+                ui.separator();
+                ui.label(RichText::new("Export stamps").strong());
+                ui.horizontal(|ui| {
+                    ui.label("Source checksum and provenance:");
+                    egui::ComboBox::from_id_salt("stamping")
+                        .selected_text(self.settings.stamping.label())
+                        .show_ui(ui, |ui| {
+                            for mode in chonker3::stamp::StampMode::ALL {
+                                changed |= ui.selectable_value(&mut self.settings.stamping, mode, mode.label()).changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text("Records the source PDF's SHA-256, the extractor and its version, the app version and the time. \
+                            JSON and Markdown exports carry it inside; other formats, or all with sidecar files, get a .stamp.json next to them.");
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Remote locations").strong());
+                ui.label(RichText::new("S3 buckets and WebDAV folders to open documents from (☁) and upload batch exports to").weak());
+                let mut remove = None;
+                for (index, location) in self.settings.remote_locations.iter_mut().enumerate() {
+                    let title = if location.name.is_empty() { "Unnamed location".to_string() } else { location.name.clone() };
+                    egui::CollapsingHeader::new(title).id_salt(("remote_location", index)).show(ui, |ui| {
+                        egui::Grid::new(("remote_location_settings", index)).num_columns(2).show(ui, |ui| {
+                            ui.label("Name:");
+                            changed |= ui.text_edit_singleline(&mut location.name)
+                                .on_hover_text("Renaming a location forgets its saved secret")
+                                .lost_focus();
+                            ui.end_row();
+                            ui.label("Kind:");
+                            egui::ComboBox::from_id_salt(("remote_kind", index))
+                                .selected_text(location.kind.label())
+                                .show_ui(ui, |ui| {
+                                    for kind in remote::RemoteKind::ALL {
+                                        changed |= ui.selectable_value(&mut location.kind, kind, kind.label()).changed();
+                                    }
+                                });
+                            ui.end_row();
+                            ui.label("URL:");
+                            changed |= ui.text_edit_singleline(&mut location.url)
+                                .on_hover_text("e.g. https://s3.eu-west-1.amazonaws.com, or the WebDAV root")
+                                .lost_focus();
+                            ui.end_row();
+                            if location.kind == remote::RemoteKind::S3 {
+                                ui.label("Bucket:");
+                                changed |= ui.text_edit_singleline(&mut location.bucket).lost_focus();
+                                ui.end_row();
+                                ui.label("Region:");
+                                changed |= ui.text_edit_singleline(&mut location.region).lost_focus();
+                                ui.end_row();
+                            }
+                            ui.label("Folder:");
+                            changed |= ui.text_edit_singleline(&mut location.prefix).lost_focus();
+                            ui.end_row();
+                            let s3 = location.kind == remote::RemoteKind::S3;
+                            ui.label(if s3 { "Access key ID:" } else { "User:" });
+                            changed |= ui.text_edit_singleline(&mut location.user).lost_focus();
+                            ui.end_row();
+                            ui.label(if s3 { "Secret key:" } else { "Password:" });
+                            ui.horizontal(|ui| {
+                                let buffer = self.remote_secret_buffers.entry(index).or_default();
+                                ui.add(egui::TextEdit::singleline(buffer).password(true).hint_text("kept in the keyring").desired_width(160.0));
+                                if ui.button("Save").on_hover_text("Saving an empty secret removes it").clicked() {
+                                    match location.set_secret(buffer) {
+                                        Ok(()) => self.toasts.success("Secret saved"),
+                                        Err(e) => self.toasts.error(e.to_string()),
+                                    }
+                                    buffer.clear();
+                                }
+                            });
+                            ui.end_row();
+                        });
+                        if ui.button("Remove location").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = remove {
+                    let location = self.settings.remote_locations.remove(index);
+                    let _ = location.set_secret("");
+                    self.remote_secret_buffers.clear();
+                    changed = true;
+                }
+                if ui.button("Add location").clicked() {
+                    self.settings.remote_locations.push(RemoteLocation {
+                        name: format!("Location {}", self.settings.remote_locations.len() + 1),
+                        ..Default::default()
+                    });
+                    changed = true;
+                }
+                
+                ui.separator();
+                ui.label(RichText::new("Batch export").strong());
+                ui.label(RichText::new(format!(
+                    "Output paths are relative to the chosen folder. Placeholders: {}; {{page}} writes one file per page.",
+                    chonker3::pipeline::PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", "),
+                )).weak());
+                let pipeline = &mut self.settings.export_pipeline;
+                let mut remove = None;
+                egui::Grid::new("pipeline_outputs").num_columns(4).show(ui, |ui| {
+                    for (index, output) in pipeline.outputs.iter_mut().enumerate() {
+                        egui::ComboBox::from_id_salt(("pipeline_format", index))
+                            .selected_text(output.format.label())
+                            .show_ui(ui, |ui| {
+                                for format in chonker3::pipeline::OutputFormat::ALL {
+                                    changed |= ui.selectable_value(&mut output.format, format, format.label()).changed();
+                                }
+                            });
+                        let response = ui.text_edit_singleline(&mut output.template);
+                        changed |= response.changed();
+                        if let Err(e) = chonker3::pipeline::expand_template(&output.template, "stem", Some(0)) {
+                            response.on_hover_text(e.to_string());
+                            ui.colored_label(Color32::from_rgb(220, 38, 38), "⚠");
+                        } else {
+                            ui.label("");
+                        }
+                        if ui.small_button("✖").on_hover_text("Remove output").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = remove {
+                    pipeline.outputs.remove(index);
+                    changed = true;
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add output").clicked() {
+                        pipeline.outputs.push(chonker3::pipeline::PipelineOutput {
+                            format: chonker3::pipeline::OutputFormat::Markdown,
+                            template: "{stem}/{page}.md".to_string(),
+                        });
+                        changed = true;
+                    }
+                    ui.label("Manifest:");
+                    egui::ComboBox::from_id_salt("pipeline_manifest")
+                        .selected_text(pipeline.manifest.label())
+                        .show_ui(ui, |ui| {
+                            for format in chonker3::pipeline::ManifestFormat::ALL {
+                                changed |= ui.selectable_value(&mut pipeline.manifest, format, format.label()).changed();
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Upload to:");
+                    egui::ComboBox::from_id_salt("pipeline_upload")
+                        .selected_text(pipeline.upload_to.as_deref().unwrap_or("Nowhere"))
+                        .show_ui(ui, |ui| {
+                            changed |= ui.selectable_value(&mut pipeline.upload_to, None, "Nowhere").changed();
+                            for location in &self.settings.remote_locations {
+                                changed |= ui.selectable_value(&mut pipeline.upload_to, Some(location.name.clone()), &location.name).changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text("Every file written, and the manifest, is also uploaded under the location's folder");
+                });
+                ui.horizontal(|ui| {
+                    let columns = pipeline.field_mapping.columns.len();
+                    ui.label(if columns == 0 {
+                        "Field sheet: off".to_string()
+                    } else {
+                        format!("Field sheet: {} columns in {}", columns, pipeline.field_mapping.file_name)
+                    });
+                    if ui.button("Design...").clicked() {
+                        self.show_field_mapping = true;
+                    }
+                });
+                changed |= ui.checkbox(&mut pipeline.webhook.enabled, "Post each document to a URL")
+                    .on_hover_text("Sends the structured JSON, named fields and manifest entry of every exported document")
+                    .changed();
+                ui.add_enabled_ui(pipeline.webhook.enabled, |ui| {
+                    let webhook = &mut pipeline.webhook;
+                    let mut remove = None;
+                    egui::Grid::new("webhook_settings").num_columns(2).show(ui, |ui| {
+                        ui.label("URL:");
+                        changed |= ui.text_edit_singleline(&mut webhook.url).lost_focus();
+                        ui.end_row();
+                        ui.label("Timeout (s):");
+                        changed |= ui.add(egui::DragValue::new(&mut webhook.timeout_secs).range(1..=600)).changed();
+                        ui.end_row();
+                        ui.label("Authorization:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.webhook_authorization_buffer).password(true).hint_text("kept in the keyring").desired_width(160.0));
+                            if ui.button("Save").on_hover_text("e.g. Bearer <token>; saving an empty value removes it").clicked() {
+                                match chonker3::webhook::set_authorization(&self.webhook_authorization_buffer) {
+                                    Ok(()) => self.toasts.success("Authorization header saved"),
+                                    Err(e) => self.toasts.error(e.to_string()),
+                                }
+                                self.webhook_authorization_buffer.clear();
+                            }
+                        });
+                        ui.end_row();
+                        for (index, header) in webhook.headers.iter_mut().enumerate() {
+                            changed |= ui.add(egui::TextEdit::singleline(&mut header.name).hint_text("Header").desired_width(100.0)).lost_focus();
+                            ui.horizontal(|ui| {
+                                changed |= ui.text_edit_singleline(&mut header.value).lost_focus();
+                                if ui.small_button("✖").on_hover_text("Remove header").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(index) = remove {
+                        webhook.headers.remove(index);
+                        changed = true;
+                    }
+                    if ui.button("Add header").clicked() {
+                        webhook.headers.push(Default::default());
+                        changed = true;
+                    }
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Colors").strong());
+                let palette = &mut self.settings.palette;
+                ui.horizontal(|ui| {
+                    ui.label("Palette:");
+                    let selected = palette.builtin().map_or("Custom", |builtin| builtin.label());
+                    egui::ComboBox::from_id_salt("highlight_palette")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for builtin in BuiltinPalette::ALL {
+                                if ui.selectable_label(palette.builtin() == Some(builtin), builtin.label()).clicked() {
+                                    *palette = builtin.palette();
+                                    changed = true;
+                                }
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Ghost page opacity:");
+                    changed |= ui.add(egui::Slider::new(&mut self.settings.ghost_opacity, 0.05..=0.8)).changed();
+                });
+                ui.collapsing("Customize", |ui| {
+                    egui::Grid::new("palette_grid").num_columns(2).show(ui, |ui| {
+                        for (label, color) in [
+                            ("Search match", &mut palette.search_highlight),
+                            ("Search match text", &mut palette.search_text),
+                            ("Selection", &mut palette.selection),
+                            ("Hover", &mut palette.hover),
+                            ("Text", &mut palette.text),
+                            ("Form label", &mut palette.form_label),
+                            ("Form field", &mut palette.form_field),
+                            ("Checkbox", &mut palette.checkbox),
+                            ("Annotation", &mut palette.annotation),
+                            ("Collaborator editing", &mut palette.peer_editing),
+                            ("Overflow warning", &mut palette.overflow),
+                            ("Transcript difference", &mut palette.divergent),
+                            ("Personal data", &mut palette.pii),
+                        ] {
+                            ui.label(label);
+                            changed |= ui.color_edit_button_srgba_unmultiplied(color).changed();
+                            ui.end_row();
+                        }
+                    });
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Extraction").strong());
+                ui.horizontal(|ui| {
+                    ui.label("Backend:");
+                    egui::ComboBox::from_id_salt("extractor_backend")
+                        .selected_text(self.settings.extractor.label())
+                        .show_ui(ui, |ui| {
+                            for kind in chonker3::extractor::ExtractorKind::ALL {
+                                changed |= ui.selectable_value(&mut self.settings.extractor, kind, kind.label()).changed();
+                            }
+                        });
+                });
+                let capabilities = self.settings.extractor.extractor().capabilities();
+                ui.label(RichText::new(format!("Provides: {}", capabilities.summary())).small().color(Color32::GRAY));
+                // The native extractor hands pages without a text layer to the chain
+                if self.settings.extractor != chonker3::extractor::ExtractorKind::Simple {
+                    changed |= self.fallback_chain_ui(ui);
+                }
+                // Preprocessing only helps OCR
+                changed |= ui.add_enabled(capabilities.ocr, egui::Checkbox::new(&mut self.settings.extract_options.preprocess, "Preprocess page images for OCR")).changed();
+                changed |= ui.checkbox(&mut self.settings.extract_options.line_items, "Split paragraphs into line items")
+                    .on_hover_text("Uses the PDF's word boxes; needs pdfium").changed();
+                let cleanup = &mut self.settings.extract_options.scan_cleanup;
+                changed |= ui.add_enabled(capabilities.ocr, egui::Checkbox::new(&mut cleanup.enabled, "Clean up scans before OCR"))
+                    .on_hover_text("Renders scanned PDFs (no text layer) and cleans each page; needs pdfium").changed();
+                ui.add_enabled_ui(capabilities.ocr && cleanup.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        changed |= ui.checkbox(&mut cleanup.deskew, "Deskew").changed();
+                        changed |= ui.checkbox(&mut cleanup.despeckle, "Despeckle").changed();
+                        changed |= ui.checkbox(&mut cleanup.normalize_contrast, "Normalize contrast").changed();
+                    });
+                });
+                let auto_extract = &mut self.settings.auto_extract;
+                changed |= ui.checkbox(&mut auto_extract.enabled, "Extract automatically when a PDF is opened").changed();
+                ui.add_enabled_ui(auto_extract.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        changed |= ui.checkbox(&mut auto_extract.limit_size, "Only files up to").changed();
+                        changed |= ui.add_enabled(auto_extract.limit_size, egui::DragValue::new(&mut auto_extract.max_size_mb)
+                            .range(1..=2048)
+                            .suffix(" MB")).changed();
+                    });
+                });
+                changed |= ui.checkbox(&mut self.settings.cache_extractions, "Reuse earlier extractions of the same file")
+                    .on_hover_text("Results are cached by the PDF's SHA-256, per extractor and options").changed();
+                ui.horizontal(|ui| {
+                    let cache = ExtractionCache::default();
+                    let (count, bytes) = cache.usage();
+                    ui.add_space(16.0);
+                    ui.label(RichText::new(format!("{} cached in {} ({:.1} MB)", count, cache.dir().display(), bytes as f64 / 1_048_576.0)).weak());
+                    if count > 0 && ui.small_button("Clear").clicked() {
+                        match cache.clear() {
+                            Ok(cleared) => self.toasts.success(format!("Cleared {} cached extractions", cleared)),
+                            Err(e) => self.toasts.error(format!("{:#}", e)),
+                        }
+                    }
+                });
+                
+                ui.separator();
+                ui.label(RichText::new("Storage").strong());
+                egui::Grid::new("storage_dirs").num_columns(3).show(ui, |ui| {
+                    for kind in storage::StorageKind::ALL {
+                        let resolved = storage::resolved(kind);
+                        ui.label(format!("{}:", kind.label()));
+                        let path = ui.label(RichText::new(resolved.path.display().to_string()).monospace());
+                        if resolved.from_env {
+                            path.on_hover_text(format!("Set by {}", kind.env_var()));
+                        }
+                        ui.add_enabled_ui(!resolved.from_env, |ui| {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Choose...").clicked() {
+                                    if let Some(dir) = rfd::FileDialog::new().set_directory(&resolved.path).pick_folder() {
+                                        match storage::validate_dir(&dir) {
+                                            Ok(()) => *self.settings.storage.get_mut(kind) = Some(dir),
+                                            Err(e) => self.toasts.error(format!("{:#}", e)),
+                                        }
+                                    }
+                                }
+                                if self.settings.storage.get(kind).is_some() && ui.small_button("Default").clicked() {
+                                    *self.settings.storage.get_mut(kind) = None;
+                                }
+                            });
+                        });
+                        ui.end_row();
+                    }
+                });
+                ui.label(RichText::new(format!(
+                    "{} override these. Session files move the next time Chonker3 starts.",
+                    storage::StorageKind::ALL.map(|kind| kind.env_var()).join(", "),
+                )).weak());
+                
+                ui.separator();
+                ui.label(RichText::new("Python environment").strong());
+                if ui.button("Set up or check...").clicked() {
+                    open_python_setup = true;
+                }
+                
+                ui.separator();
+                if ui.button("Restore defaults").clicked() {
+                    let file_path = self.settings.file_path.take();
+                    let pdfium_library = self.settings.pdfium_library.take();
+                    let pdfium_download_offered = self.settings.pdfium_download_offered;
+                    self.settings = Settings { file_path, pdfium_library, pdfium_download_offered, ..Default::default() };
+                    changed = true;
+                }
+            });
+        self.show_settings = open;
+        if open_python_setup {
+            self.open_python_setup();
+        }
+        
+        if self.settings.storage != storage_before {
+            for warning in storage::configure(&self.settings.storage) {
+                self.toasts.error(warning);
+            }
+            changed = true;
+        }
+        
+        if changed {
+            self.memory_guard.set_budget_mb(self.settings.memory_budget_mb);
+            self.session.pdf_output = self.settings.pdf_output;
+            self.session.stamping = self.settings.stamping;
+            if let Err(e) = self.settings.save() {
+                self.toasts.error(format!("Failed to save settings: {}", e));
+            }
+        }
+    }
+    
+    fn show_duplicate_review(&mut self, ctx: &egui::Context) {
+        let Some(review) = self.duplicate_review.as_mut() else { return };
+        let mut close = false;
+        let mut export = false;
+        
+        egui::Window::new("Duplicate documents")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("Checked documents are excluded from export.");
+                ui.separator();
+                
+                ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    for (group_index, group) in review.groups.iter().enumerate() {
+                        let kind = if group.is_exact() {
+                            "Exact duplicates".to_string()
+                        } else {
+                            format!("Near-duplicates (≤{} bits apart)", group.max_distance)
+                        };
+                        ui.label(RichText::new(format!("Group {}: {}", group_index + 1, kind)).strong());
+                        for member in &group.members {
+                            let name = self.workspace.documents.get(*member)
+                                .map(|d| d.display_name())
+                                .unwrap_or_default();
+                            let mut exclude = review.excluded.contains(member);
+                            if ui.checkbox(&mut exclude, name).changed() {
+                                if exclude {
+                                    review.excluded.insert(*member);
+                                } else {
+                                    review.excluded.remove(member);
+                                }
+                            }
+                        }
+                        ui.add_space(6.0);
+                    }
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if review.export_after {
+                        if ui.button("Continue export").clicked() {
+                            export = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            close = true;
+                        }
+                    } else if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+            });
+        
+        if export {
+            let excluded = review.excluded.clone();
+            self.duplicate_review = None;
+            self.batch_export(&excluded);
+        } else if close {
+            self.duplicate_review = None;
+        }
+    }
+}
+
+impl eframe::App for Chonker3App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Handle keyboard shortcuts
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::F)) {
+            self.show_search = true;
+        }
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::T)) && self.translation_language().is_some() {
+            self.show_translation = !self.show_translation;
+        }
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::W)) {
+            if let Some(index) = self.tabs.active() {
+                self.close_tab(index);
+            }
+        }
+        if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Tab)) {
+            if let Some(index) = self.tabs.next() {
+                self.switch_tab(index);
+            }
+        }
+        
+        
+        self.repaint.frame_interval = std::time::Duration::from_millis(self.settings.repaint_interval_ms);
+        self.sync_collab();
+        if self.show_pii {
+            self.refresh_pii();
+        }
+        self.open_forwarded_files(ctx);
+        if self.drop_queue.is_some() {
+            self.show_quick_drop(ctx);
+            self.toasts.show(ctx);
+            self.repaint.finish_frame(ctx);
+            return;
+        }
+        self.check_memory();
+        
+        // Show the pages extracted so far while the rest are still running
+        let updates: Vec<ExtractionUpdate> = std::mem::take(&mut *self.extraction_updates.lock().unwrap());
+        if self.is_extracting && !updates.is_empty() {
+            let mut arrived = false;
+            for update in updates {
+                match update {
+                    ExtractionUpdate::Progress { message, page, pages } => {
+                        self.status_message = format!("{}...", message);
+                        if let (Some(page), Some(pages)) = (page, pages) {
+                            self.extraction_progress = Some((page.saturating_sub(1), pages));
+                        }
+                    }
+                    ExtractionUpdate::Page(page) => {
+                        self.extraction_progress = Some((page.page, page.page_count));
+                        self.partial_extraction.add(page);
+                        arrived = true;
+                    }
+                    ExtractionUpdate::BackendFailed(failure) => {
+                        // The next backend redoes the failed one's pages, replacing what it sent
+                        self.toasts.info(format!("{} failed: {}. Trying the next backend.", failure.backend.label(), failure.error));
+                        self.extraction_progress = None;
+                    }
+                }
+            }
+            // Only while its tab is showing; the whole result finds its tab either way
+            let showing = self.running_extraction.as_ref().is_some_and(|(run, _)| self.is_showing(&run.document));
+            if arrived && showing && self.partial_extraction.ready_pages() > 0 {
+                self.session.set_extraction(self.partial_extraction.data(), None);
+                self.pii_for = None;
+            }
+        }
+        
+        // Check extraction result
+        let result_to_process = self.extraction_result.lock().unwrap().take();
+        if let Some(result) = result_to_process {
+            self.is_extracting = false;
+            let ready_pages = self.partial_extraction.ready_pages();
+            self.partial_extraction = PartialExtraction::default();
+            self.extraction_progress = None;
+            // The user may have moved to another tab while it ran
+            let background = self.running_extraction.as_ref()
+                .map(|(run, _)| run.document.clone())
+                .filter(|document| !self.is_showing(document));
+            
+            // Log the run, however it went
+            if let Some((mut run, started)) = self.running_extraction.take() {
+                run.seconds = started.elapsed().as_secs_f64();
+                match &result {
+                    Ok(document) => {
+                        run.items = document.item_count();
+                        run.json_path = Some(document.json_path.clone());
+                        run.backend = document.data.pointer("/metadata/extractor")
+                            .and_then(|v| v.as_str())
+                            .map(str::to_string);
+                    }
+                    Err(e) => run.error = Some(e.to_string()),
+                }
+                self.workspace.record_run(run);
+                if let Err(e) = self.workspace.save() {
+                    log::warn!("Failed to save workspace: {}", e);
+                }
+            }
+            match result {
+                Ok(document) if background.is_some() => {
+                    if let Some(path) = background {
+                        self.finish_background_extraction(&path, document);
+                    }
+                }
+                Ok(document) => {
+                    // Fresh results are cached as a copy, so only cached ones carry the key
+                    self.status_message = if document.data["metadata"].get(extraction_cache::CACHE_KEY).is_some() {
+                        format!("Loaded {} items from the extraction cache; ⟲ extracts again", document.item_count())
+                    } else {
+                        format!("Extracted {} items", document.item_count())
+                    };
+                    
+                    // Record the extraction on the workspace document
+                    if let Some(index) = self.session.pdf_path.as_ref().and_then(|p| self.workspace.find(p)) {
+                        self.workspace.set_extraction(index, document.json_path.clone());
+                        let _ = self.workspace.save();
+                    }
+                    
+                    self.session.set_extraction(document.data, Some(document.json_path));
+                    self.search_hits_query = None;
+                    self.pii_for = None;
+                    if self.settings.extract_options.line_items {
+                        match self.session.split_into_lines() {
+                            Ok(0) => {}
+                            Ok(split) => self.status_message.push_str(&format!(", {} split into lines", split)),
+                            Err(e) => self.toasts.error(format!("Line splitting failed: {}", e)),
+                        }
+                    }
+                    self.run_autorun_script();
+                }
+                Err(_) if self.extraction_cancel.is_cancelled() => {
+                    // Pages that arrived before the cancel stay on screen
+                    self.status_message = match ready_pages {
+                        0 => "Extraction cancelled".to_string(),
+                        pages => format!("Extraction cancelled; showing the {} pages extracted so far", pages),
+                    };
+                }
+                Err(e) => {
+                    self.status_message = "Extraction failed".to_string();
+                    self.toasts.error(e.to_string());
+                }
+            }
+        }
+        
+        // Top panel
+        egui::TopBottomPanel::top("top_panel")
+            .exact_height(40.0)
+            .show(ctx, |ui| {
+            // Teal background
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, TEAL);
+            
+            ui.horizontal_centered(|ui| {
+                ui.add_space(5.0);
+                
+                // Hamster emoji - will display with proper colors
+                ui.label(RichText::new("🐹").size(24.0));
+                
+                ui.label(RichText::new("CHONKER3").size(16.0).strong().color(Color32::WHITE));
+                
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_space(5.0);
+                    
+                    // Controls
+                    if self.session.pdf_path.is_some() {
+                        // Extract button
+                        let extractor = self.settings.extractor.extractor();
+                        if !self.is_extracting && ui.button(RichText::new("Extract").color(Color32::WHITE).strong().size(14.0))
+                            .on_hover_text(format!("Extract with {} ({})", self.settings.extractor.label(), extractor.capabilities().summary()))
+                            .clicked() 
+                        {
+                            self.extract_content();
+                        }
+                        if !self.is_extracting && self.settings.cache_extractions && ui.button(RichText::new("⟲").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Extract again, ignoring and replacing the cached result")
+                            .clicked()
+                        {
+                            self.force_extract();
+                        }
+                        
+                        // Import third-party OCR results instead of extracting
+                        if !self.is_extracting && ui.button(RichText::new("Import").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Import hOCR, ALTO, Textract JSON or Label Studio tasks")
+                            .clicked()
+                        {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("OCR results", &["hocr", "html", "xhtml", "xml", "json"])
+                                .pick_file()
+                            {
+                                self.import_ocr_results(path);
+                            }
+                        }
+                        
+                        if self.settings.llm.enabled && ui.button(RichText::new("LLM").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Summarize, pull out key fields or clean up text with a language model")
+                            .clicked()
+                        {
+                            self.show_llm = !self.show_llm;
+                        }
+                        
+                        // Machine translation in place of the original text
+                        if self.settings.llm.enabled {
+                            let translated = self.translation_language();
+                            let label = if self.show_translation && translated.is_some() { "🌐 Translated" } else { "🌐" };
+                            ui.menu_button(RichText::new(label).size(14.0).color(Color32::WHITE), |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Into:");
+                                    if ui.add(egui::TextEdit::singleline(&mut self.settings.llm.translate_to).desired_width(120.0)).lost_focus() {
+                                        if let Err(e) = self.settings.save() {
+                                            self.toasts.error(format!("Failed to save settings: {}", e));
+                                        }
+                                    }
+                                });
+                                let can_run = self.job.is_none() && self.session.extracted_data.is_some();
+                                if ui.add_enabled(can_run, egui::Button::new("Translate this page")).clicked() {
+                                    ui.close_menu();
+                                    self.translate(false);
+                                }
+                                if ui.add_enabled(can_run, egui::Button::new("Translate all pages")).clicked() {
+                                    ui.close_menu();
+                                    self.translate(true);
+                                }
+                                ui.separator();
+                                if let Some(language) = &translated {
+                                    ui.checkbox(&mut self.show_translation, format!("Show the {} translation (Cmd+T)", language));
+                                } else {
+                                    ui.label(RichText::new("Nothing translated yet").weak());
+                                }
+                                if ui.add_enabled(translated.is_some(), egui::Button::new("Export translated Markdown...")).clicked() {
+                                    ui.close_menu();
+                                    self.export_translation();
+                                }
+                            });
+                        }
+                        
+                        // Share edits as a patch file
+                        ui.menu_button(RichText::new("Patch").size(14.0).color(Color32::WHITE), |ui| {
+                            if ui.button("Export patch...").clicked() {
+                                ui.close_menu();
+                                let default_name = self.session.pdf_path.as_ref()
+                                    .and_then(|p| p.file_stem())
+                                    .map(|s| format!("{}.chonkpatch.json", s.to_string_lossy()))
+                                    .unwrap_or_else(|| "edits.chonkpatch.json".to_string());
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Chonker3 patch", &["json"])
+                                    .set_file_name(default_name)
+                                    .save_file()
+                                {
+                                    let patch = self.session.to_patch();
+                                    match patch.save(&path) {
+                                        Ok(()) => self.toasts.success(format!("Exported {} edits", patch.edit_count())),
+                                        Err(e) => self.toasts.error(format!("Patch export failed: {}", e)),
+                                    }
+                                }
+                            }
+                            if ui.button("Import patch...").clicked() {
+                                ui.close_menu();
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Chonker3 patch", &["json"])
+                                    .pick_file()
+                                {
+                                    match EditPatch::load(&path) {
+                                        Ok(patch) => {
+                                            let message = self.session.apply_patch(patch);
+                                            self.page_note_page = None;
+                                            self.toasts.success(message);
+                                        }
+                                        Err(e) => self.toasts.error(format!("Patch import failed: {}", e)),
+                                    }
+                                }
+                            }
+                        });
+                        
+                        // Structured export with normalized numbers
+                        ui.menu_button(RichText::new("Export").size(14.0).color(Color32::WHITE), |ui| {
+                            ui.label("Number format:");
+                            for locale in normalize::NumberLocale::ALL {
+                                if ui.radio(self.session.number_locale == locale, locale.label()).clicked() {
+                                    self.session.set_number_locale(locale);
+                                }
+                            }
+                            ui.separator();
+                            let has_extraction = self.session.extracted_data.is_some();
+                            if has_extraction && !self.session.extraction_capabilities().tables {
+                                ui.label(RichText::new("No table structure in this extraction").small().color(Color32::GRAY));
+                            }
+                            for (kind, label) in [
+                                (ExportKind::Structured, "Structured JSON..."),
+                                (ExportKind::Jsonl, "Items as JSONL..."),
+                                (ExportKind::Markdown, "Markdown..."),
+                                (ExportKind::Csv, "CSV..."),
+                                (ExportKind::TablesCsv, "Tables as CSV..."),
+                                (ExportKind::TablesXlsx, "Tables as XLSX..."),
+                                (ExportKind::Fields, "Named regions as fields..."),
+                                (ExportKind::Bibtex, "References as BibTeX..."),
+                                (ExportKind::BatesCsv, "Page to Bates number CSV..."),
+                                (ExportKind::Stats, "Document statistics JSON..."),
+                                (ExportKind::ReflowedPdf, "Reflowed PDF..."),
+                                (ExportKind::SearchablePdf, "Searchable PDF..."),
+                            ] {
+                                let needs_pdfium = matches!(kind, ExportKind::ReflowedPdf | ExportKind::SearchablePdf);
+                                let enabled = has_extraction && (!needs_pdfium || self.session.pdfium.is_some());
+                                if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                                    ui.close_menu();
+                                    self.export_with_dialog(kind);
+                                }
+                            }
+                            if ui.add_enabled(has_extraction, egui::Button::new("Table continuations...")).clicked() {
+                                ui.close_menu();
+                                self.show_table_stitching = true;
+                            }
+                            ui.separator();
+                            if ui.add_enabled(self.job.is_none() && self.session.pdfium.is_some(), egui::Button::new("All pages to PNG...")).clicked() {
+                                ui.close_menu();
+                                self.export_page_images();
+                            }
+                            for format in annotations::AnnotationFormat::ALL {
+                                if ui.add_enabled(self.job.is_none() && has_extraction && self.session.pdfium.is_some(), egui::Button::new(format!("Layout annotations ({})...", format.label())))
+                                    .on_hover_text("Page images with the item boxes and types as labels, for training layout detection")
+                                    .clicked() {
+                                    ui.close_menu();
+                                    self.export_layout_annotations(format);
+                                }
+                            }
+                            if ui.add_enabled(self.job.is_none() && has_extraction && self.session.pdfium.is_some(), egui::Button::new("Label Studio tasks..."))
+                                .on_hover_text("Page images with a task per page and the labeling config; import the annotated tasks back with Import")
+                                .clicked() {
+                                ui.close_menu();
+                                self.export_label_studio();
+                            }
+                            if ui.add_enabled(self.job.is_none() && has_extraction, egui::Button::new("Bundle (zip)..."))
+                                .on_hover_text("PDF, extraction, edits, Markdown, HTML, audit report and page images in one zip")
+                                .clicked() {
+                                ui.close_menu();
+                                self.export_bundle();
+                            }
+                        });
+                        
+                        ui.separator();
+                        
+                        // Search button
+                        if ui.button(RichText::new("🔍").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Search (Ctrl+F)")
+                            .clicked() {
+                            self.show_search = !self.show_search;
+                        }
+                        
+                        // Item category filters
+                        let filter_color = if self.item_filter.hidden.is_empty() { Color32::WHITE } else { TEAL };
+                        ui.menu_button(RichText::new("👁").size(14.0).color(filter_color), |ui| {
+                            let capabilities = self.session.extraction_capabilities();
+                            for category in types::ItemCategory::ALL {
+                                let available = match category {
+                                    types::ItemCategory::Tables => capabilities.tables,
+                                    types::ItemCategory::Images => capabilities.images,
+                                    _ => true,
+                                };
+                                let mut shown = !self.item_filter.is_hidden(category);
+                                if ui.add_enabled(available, egui::Checkbox::new(&mut shown, category.label())).changed() {
+                                    self.item_filter.set_hidden(category, !shown);
+                                }
+                            }
+                            ui.separator();
+                            if ui.add_enabled(!self.item_filter.hidden.is_empty(), egui::Button::new("Show all")).clicked() {
+                                self.item_filter = types::ItemFilter::default();
+                            }
+                        }).response.on_hover_text("Show or hide item categories");
+                        
+                        let can_snap = self.session.extracted_data.is_some() && self.session.pdfium.is_some();
+                        ui.add_enabled_ui(can_snap, |ui| {
+                            ui.menu_button(RichText::new("🧲").size(14.0).color(Color32::WHITE), |ui| {
+                                for (label, page) in [("This page", Some(self.session.page)), ("All pages", None)] {
+                                    if ui.button(label).clicked() {
+                                        ui.close_menu();
+                                        match self.session.snap_boxes(page) {
+                                            Ok(0) => self.toasts.info("All boxes already match the PDF"),
+                                            Ok(count) => self.toasts.success(format!("Snapped {} boxes to the PDF text", count)),
+                                            Err(e) => self.toasts.error(format!("Snapping failed: {}", e)),
+                                        }
+                                    }
+                                }
+                            }).response.on_hover_text("Snap boxes to PDF: move items onto the glyphs pdfium finds for their text");
+                        });
+                        
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("📐").size(14.0).color(Color32::WHITE)))
+                            .on_hover_text("Page alignment scores")
+                            .clicked() {
+                            self.show_alignment = !self.show_alignment;
+                        }
+                        
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("📚").size(14.0).color(Color32::WHITE)))
+                            .on_hover_text("References and citations")
+                            .clicked() {
+                            self.show_references = !self.show_references;
+                        }
+                        
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🎯").size(14.0).color(Color32::WHITE)))
+                            .on_hover_text("Compare against a reference transcript")
+                            .clicked() {
+                            self.show_transcript = !self.show_transcript;
+                        }
+                        
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("📊").size(14.0).color(Color32::WHITE)))
+                            .on_hover_text("Document statistics")
+                            .clicked() {
+                            self.show_stats = !self.show_stats;
+                        }
+                        
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("📝").size(14.0).color(Color32::WHITE)))
+                            .on_hover_text("Page and document summaries")
+                            .clicked() {
+                            self.show_summaries = !self.show_summaries;
+                        }
+                        
+                        let heatmap_color = if self.show_heatmap { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🔥").size(14.0).color(heatmap_color)))
+                            .on_hover_text("Review heatmap: edits, low-confidence items and missing text per page")
+                            .clicked() {
+                            self.show_heatmap = !self.show_heatmap;
+                        }
+                        
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🏷").size(14.0).color(Color32::WHITE)))
+                            .on_hover_text("Barcodes and QR codes")
+                            .clicked() {
+                            self.show_barcodes = !self.show_barcodes;
+                        }
+                        
+                        let inspector_color = if self.show_inspector { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🔬").size(14.0).color(inspector_color)))
+                            .on_hover_text("Item inspector: where the selected item came from")
+                            .clicked() {
+                            self.show_inspector = !self.show_inspector;
+                        }
+                        
+                        let reocr_color = if self.reocr_region_mode { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🔍").size(14.0).color(reocr_color)))
+                            .on_hover_text("Re-OCR region: drag around a garbled item, or empty space, to read it again at high resolution")
+                            .clicked() {
+                            self.reocr_region_mode = !self.reocr_region_mode;
+                            self.region_draw_mode = false;
+                        }
+                        
+                        let regions_color = if self.show_regions { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🔲").size(14.0).color(regions_color)))
+                            .on_hover_text("Named regions: name areas of the page such as an invoice number and export their contents as fields")
+                            .clicked() {
+                            self.show_regions = !self.show_regions;
+                        }
+                        
+                        let problems_color = if self.show_problems { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("✔").size(14.0).color(problems_color)))
+                            .on_hover_text("Problems: check the named regions against validation rules such as patterns, totals and date ranges")
+                            .clicked() {
+                            self.show_problems = !self.show_problems;
+                        }
+                        
+                        let pii_color = if self.show_pii { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("🛡").size(14.0).color(pii_color)))
+                            .on_hover_text("Personal data: find SSNs, card numbers, emails and names, then mask them or save redaction boxes")
+                            .clicked() {
+                            self.show_pii = !self.show_pii;
+                        }
                         
-                        if content.trim().is_empty() {
-                            continue;
+                        let diagnostics_color = if self.overflow_items.is_empty() { Color32::WHITE } else { Palette::color(self.settings.palette.overflow) };
+                        if ui.add_enabled(self.session.extracted_data.is_some(), egui::Button::new(RichText::new("⚠").size(14.0).color(diagnostics_color)))
+                            .on_hover_text(format!("Diagnostics: {} items overflow their boxes on this page", self.overflow_items.len()))
+                            .clicked() {
+                            self.show_diagnostics = !self.show_diagnostics;
                         }
                         
-                        // Determine item type
-                        let item_type_str = json_item.get("type").and_then(|v| v.as_str()).unwrap_or("TextItem");
-                        let item_type = match item_type_str {
-                            "TitleItem" => ItemType::Title,
-                            "SectionHeaderItem" => ItemType::Header,
-                            "TableItem" => ItemType::Table,
-                            "FormLabel" => ItemType::FormLabel,
-                            "FormField" => ItemType::FormField,
-                            "Checkbox" => ItemType::Checkbox,
-                            _ => ItemType::Text,
-                        };
+                        // Ghost overlay toggle
+                        let ghost_color = if self.ghost_overlay { TEAL } else { Color32::WHITE };
+                        if ui.add_enabled(self.pdf_texture.is_some() || self.ghost_overlay, egui::Button::new(RichText::new("👻").size(14.0).color(ghost_color)))
+                            .on_hover_text("Show the PDF page faintly under the extracted text")
+                            .clicked() {
+                            self.ghost_overlay = !self.ghost_overlay;
+                        }
                         
-                        // Extract font size and style from attributes.style if available
-                        let (font_size, bold, italic) = if let Some(attributes) = json_item.get("attributes") {
-                            if let Some(style) = attributes.get("style") {
-                                let fs = style.get("font_size").and_then(|v| v.as_f64()).unwrap_or(12.0) as f32;
-                                let b = style.get("bold").and_then(|v| v.as_bool()).unwrap_or(false);
-                                let i = style.get("italic").and_then(|v| v.as_bool()).unwrap_or(false);
-                                (fs, b, i)
-                            } else {
-                                // Use reasonable defaults based on item type
-                                match item_type {
-                                    ItemType::Title => (16.0, true, false),
-                                    ItemType::Header => (14.0, true, false),
-                                    ItemType::FormLabel => (11.0, false, false),
-                                    ItemType::FormField => (10.0, false, false),
-                                    _ => (11.0, false, false),
-                                }
-                            }
-                        } else {
-                            // Use reasonable defaults based on item type
-                            match item_type {
-                                ItemType::Title => (16.0, true, false),
-                                ItemType::Header => (14.0, true, false),
-                                ItemType::FormLabel => (11.0, false, false),
-                                ItemType::FormField => (10.0, false, false),
-                                _ => (11.0, false, false),
-                            }
-                        };
+                        ui.separator();
                         
-                        // Generate item ID
-                        let item_id = format!("item_{}_{}_{}", 
-                            self.pdf_page,
-                            (left * 1000.0) as i32,
-                            (final_top * 1000.0) as i32
-                        );
+                        // E-invoice cross-check button
+                        if self.einvoice.is_some() && ui.button(RichText::new("🧾").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Embedded e-invoice")
+                            .clicked() {
+                            self.show_einvoice = !self.show_einvoice;
+                        }
                         
-                        // Create document item
-                        let doc_item = DocumentItem {
-                            id: item_id,
-                            bbox: BoundingBox {
-                                left,
-                                top: final_top,
-                                width,
-                                height: final_height.abs(),
-                            },
-                            content,
-                            font_size,
-                            color: match item_type {
-                                ItemType::Title | ItemType::Header => (0, 100, 200),
-                                _ => (0, 0, 0),
-                            },
-                            item_type,
-                            bold,
-                            italic,
-                        };
+                        // Page organizer button
+                        if ui.button(RichText::new("📑").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Organize pages")
+                            .clicked() && self.session.page_count > 0 && self.session.pdfium.is_some() {
+                            let page_sizes = self.session.page_sizes().into_iter()
+                                .map(|(w, h)| (w as f32, h as f32))
+                                .collect();
+                            self.page_organizer = Some(PageOrganizer::new(page_sizes));
+                        }
                         
-                        items.push(doc_item);
-                    }
-                }
-            }
-        }
-        
-        let search_results = self.find_search_matches(&items);
-        
-        // Extract column info for current page
-        let (column_count, column_boundaries) = if let Some(pages) = json_data.get("pages").and_then(|v| v.as_array()) {
-            if let Some(page) = pages.get(self.pdf_page) {
-                let count = page.get("columns").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
-                let boundaries = if let Some(bounds) = page.get("column_boundaries").and_then(|v| v.as_array()) {
-                    bounds.iter()
-                        .filter_map(|v| v.as_f64().map(|f| f as f32))
-                        .collect()
-                } else {
-                    Vec::new()
-                };
-                (count, boundaries)
-            } else {
-                (1, Vec::new())
-            }
-        } else {
-            (1, Vec::new())
-        };
-        
-        types::DocumentState {
-            items,
-            page_size: (612.0, 792.0), // Standard US Letter
-            zoom: self.zoom_level,
-            offset: (self.pan_offset.x, self.pan_offset.y),
-            selected_item: None,
-            editing_item: None,
-            search_query: self.search_query.clone(),
-            search_results,
-            item_offsets: self.item_offsets.iter()
-                .map(|(k, v)| (k.clone(), (v.x, v.y)))
-                .collect(),
-            item_text_overrides: self.item_text_overrides.clone(),
-            text_padding_factor: 1.0,
-            edit_mode: false,
-            dragging_item: None,
-            column_count,
-            column_boundaries,
-        }
-    }
-    
-    fn find_search_matches(&self, items: &[types::DocumentItem]) -> Vec<String> {
-        if self.search_query.is_empty() {
-            return Vec::new();
-        }
-        
-        let query = self.search_query.to_lowercase();
-        items.iter()
-            .filter(|item| item.content.to_lowercase().contains(&query))
-            .map(|item| item.id.clone())
-            .collect()
-    }
-}
-
-impl eframe::App for Chonker3App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Handle keyboard shortcuts
-        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::F)) {
-            self.show_search = true;
-        }
-        
-        
-        // Check extraction result
-        let result_to_process = self.extraction_result.lock().unwrap().take();
-        if let Some(result) = result_to_process {
-            self.is_extracting = false;
-            if result.success {
-                self.status_message = format!("Extracted {} items", result.items);
-                self.extracted_json = Some(PathBuf::from(&result.json_path));
-                
-                if let Ok(json_content) = std::fs::read_to_string(&result.json_path) {
-                    if let Ok(data) = serde_json::from_str(&json_content) {
-                        self.extracted_data = Some(data);
-                    }
-                }
-            } else {
-                self.status_message = result.message.clone();
-            }
-        }
-        
-        // Top panel
-        egui::TopBottomPanel::top("top_panel")
-            .exact_height(40.0)
-            .show(ctx, |ui| {
-            // Teal background
-            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, TEAL);
-            
-            ui.horizontal_centered(|ui| {
-                ui.add_space(5.0);
-                
-                // Hamster emoji - will display with proper colors
-                ui.label(RichText::new("🐹").size(24.0));
-                
-                ui.label(RichText::new("CHONKER3").size(16.0).strong().color(Color32::WHITE));
-                
-                // Status message
-                ui.separator();
-                ui.label(RichText::new(&self.status_message).size(14.0).color(Color32::WHITE));
-                if self.is_extracting {
-                    ui.label(RichText::new(" 🐹 *chomping*").size(14.0));
-                    ctx.request_repaint();
-                }
-                
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add_space(5.0);
-                    
-                    // Controls
-                    if self.current_pdf.is_some() {
-                        // Extract button
-                        if !self.is_extracting {
-                            if ui.button(RichText::new("Extract").color(Color32::WHITE).strong().size(14.0))
-                                .clicked() 
-                            {
-                                self.extract_content();
-                            }
+                        // Workspace button
+                        if ui.button(RichText::new("📁").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Workspace documents")
+                            .clicked() {
+                            self.show_workspace = !self.show_workspace;
                         }
                         
-                        ui.separator();
+                        // Extraction history button
+                        if ui.button(RichText::new("🕘").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Extraction history")
+                            .clicked() {
+                            self.show_extraction_history = !self.show_extraction_history;
+                        }
                         
-                        // Search button
-                        if ui.button(RichText::new("🔍").size(14.0).color(Color32::WHITE))
-                            .on_hover_text("Search (Ctrl+F)")
+                        // Collaboration button
+                        if ui.button(RichText::new("👥").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Collaborate over LAN (experimental)")
                             .clicked() {
-                            self.show_search = !self.show_search;
+                            self.show_collab = !self.show_collab;
                         }
                         
-                        ui.separator();
+                        // Macro recorder button
+                        let macro_icon = if self.macro_recording.is_some() {
+                            RichText::new("⏺").size(14.0).color(Color32::RED)
+                        } else {
+                            RichText::new("⏺").size(14.0).color(Color32::WHITE)
+                        };
+                        if ui.button(macro_icon)
+                            .on_hover_text("Macros")
+                            .clicked() {
+                            self.show_macros = !self.show_macros;
+                        }
+                        
+                        // Script console button
+                        if ui.button(RichText::new("📜").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Script console")
+                            .clicked() {
+                            self.show_script_console = !self.show_script_console;
+                        }
+                        
+                        // Settings button
+                        if ui.button(RichText::new("⚙").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Settings")
+                            .clicked() {
+                            self.show_settings = !self.show_settings;
+                        }
                         
                         // Help button
                         if ui.button(RichText::new("?").size(14.0).color(Color32::WHITE))
@@ -408,50 +6209,108 @@ impl eframe::App for Chonker3App {
                         
                         ui.separator();
                         
-                        // Zoom controls
-                        if ui.button(RichText::new("🔍+").size(14.0).color(Color32::WHITE)).clicked() {
-                            self.zoom_level = (self.zoom_level * 1.2).min(3.0);
-                            self.pdf_texture = None;
-                        }
-                        ui.label(RichText::new(format!("{}%", (self.zoom_level * 100.0) as i32)).size(12.0).color(Color32::WHITE));
-                        if ui.button(RichText::new("🔍-").size(14.0).color(Color32::WHITE)).clicked() {
-                            self.zoom_level = (self.zoom_level / 1.2).max(0.5);
-                            self.pdf_texture = None;
+                        // Zoom, fit and rotate controls
+                        let view_buttons = [
+                            ("🔍+", "Zoom in (⌘+)", ViewCommand::ZoomIn),
+                            ("🔍-", "Zoom out (⌘-)", ViewCommand::ZoomOut),
+                            ("↔", "Fit width (⌘1)", ViewCommand::FitWidth),
+                            ("⛶", "Fit page (⌘2)", ViewCommand::FitPage),
+                            ("⟳", "Rotate clockwise (⌘R)", ViewCommand::RotateClockwise),
+                            ("🏠", "Reset view (⌘0)", ViewCommand::Reset),
+                        ];
+                        for (icon, hint, command) in view_buttons {
+                            if icon == "↔" {
+                                ui.label(RichText::new(format!("{}%", (self.view.zoom * 100.0) as i32)).size(12.0).color(Color32::WHITE));
+                            }
+                            if ui.button(RichText::new(icon).size(14.0).color(Color32::WHITE))
+                                .on_hover_text(hint)
+                                .clicked() {
+                                self.view_commands.push(command);
+                            }
                         }
-                        
-                        // Reset view button
-                        if ui.button(RichText::new("🏠").size(14.0).color(Color32::WHITE))
-                            .on_hover_text("Reset view")
+                        let continuous_color = if self.settings.continuous_pages { TEAL } else { Color32::WHITE };
+                        if ui.button(RichText::new("📜").size(14.0).color(continuous_color))
+                            .on_hover_text("Continuous scroll: all pages in one column instead of one at a time")
                             .clicked() {
-                            self.zoom_level = 1.0;
-                            self.pan_offset = egui::Vec2::ZERO;
+                            self.settings.continuous_pages = !self.settings.continuous_pages;
+                            self.continuous.shown_page = None;
+                            if let Err(e) = self.settings.save() {
+                                self.toasts.error(format!("Failed to save settings: {}", e));
+                            }
                         }
                         
                         ui.separator();
                         
+                        // Page notes panel and bookmark toggle for the current page
+                        if ui.button(RichText::new("🗒").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Page notes and bookmarks")
+                            .clicked() {
+                            self.show_page_notes = !self.show_page_notes;
+                        }
+                        if ui.button(RichText::new("💬").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Comments: discuss the selected item, or list the document's threads")
+                            .clicked() {
+                            match self.selected_items.as_slice() {
+                                [item_id] if !self.show_comments || self.comment_item.as_ref() != Some(item_id) => self.open_comments(item_id.clone()),
+                                _ => self.show_comments = !self.show_comments,
+                            }
+                        }
+                        let bookmarked = self.session.edits.bookmarks.contains(&self.session.page);
+                        let bookmark_color = if bookmarked { Color32::from_rgb(250, 204, 21) } else { Color32::WHITE };
+                        if ui.button(RichText::new("🔖").size(14.0).color(bookmark_color))
+                            .on_hover_text(if bookmarked { "Remove bookmark" } else { "Bookmark this page" })
+                            .clicked() {
+                            self.session.toggle_bookmark(self.session.page);
+                            self.save_page_marks();
+                        }
+                        if ui.button(RichText::new("🔗").size(14.0).color(Color32::WHITE))
+                            .on_hover_text("Copy a link to the selected items, or the page, for notes and tickets")
+                            .clicked() {
+                            self.copy_deep_link(ui.ctx());
+                        }
+                        
                         // Page controls
-                        if ui.button(RichText::new("▶").size(16.0).color(Color32::WHITE)).clicked() && self.pdf_page + 1 < self.pdf_page_count {
-                            self.pdf_page += 1;
+                        if ui.button(RichText::new("▶").size(16.0).color(Color32::WHITE)).clicked() && self.session.page + 1 < self.session.page_count {
+                            self.session.page += 1;
                             self.pdf_texture = None;
                         }
-                        ui.label(RichText::new(format!("{}/{}", self.pdf_page + 1, self.pdf_page_count)).size(14.0).color(Color32::WHITE));
-                        if ui.button(RichText::new("◀").size(16.0).color(Color32::WHITE)).clicked() && self.pdf_page > 0 {
-                            self.pdf_page -= 1;
+                        ui.label(RichText::new(format!("{}/{}", self.session.page + 1, self.session.page_count)).size(14.0).color(Color32::WHITE));
+                        if ui.button(RichText::new("◀").size(16.0).color(Color32::WHITE)).clicked() && self.session.page > 0 {
+                            self.session.page -= 1;
                             self.pdf_texture = None;
                         }
                     }
                     
-                    if ui.button(RichText::new("Open").size(14.0).color(Color32::WHITE)).clicked() {
+                    if ui.button(RichText::new("Open").size(14.0).color(Color32::WHITE))
+                        .on_hover_text("Open a PDF, a PNG or TIFF scan, or an email with PDF attachments")
+                        .clicked() {
                         if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Documents", &inputs::InputKind::EXTENSIONS)
                             .add_filter("PDF", &["pdf"])
+                            .add_filter("Scans", &["png", "tif", "tiff"])
+                            .add_filter("Email", &["eml", "msg"])
                             .pick_file()
                         {
-                            self.load_pdf(path);
+                            self.open_input(path);
                         }
                     }
+                    if !self.settings.remote_locations.is_empty() && ui.button(RichText::new("☁").size(14.0).color(Color32::WHITE))
+                        .on_hover_text("Open a document from an S3 bucket or WebDAV folder")
+                        .clicked() {
+                        self.remote_browser = Some(RemoteBrowser::default());
+                        self.list_remote();
+                    }
+                    
+                    let quick_ocr_color = if self.show_quick_ocr { Color32::from_gray(40) } else { Color32::WHITE };
+                    if ui.button(RichText::new("📷").size(14.0).color(quick_ocr_color))
+                        .on_hover_text("OCR quick tool: read the text in a pasted image")
+                        .clicked() {
+                        self.show_quick_ocr = !self.show_quick_ocr;
+                    }
                 });
             });
         });
+        self.show_tab_bar(ctx);
         
         // Search bar (appears below toolbar when active)
         if self.show_search {
@@ -464,7 +6323,7 @@ impl eframe::App for Chonker3App {
                         
                         let response = ui.add_sized(
                             Vec2::new(200.0, 20.0),
-                            egui::TextEdit::singleline(&mut self.search_query)
+                            egui::TextEdit::singleline(&mut self.session.search_query)
                         );
                         
                         // Focus on search box when it appears
@@ -472,39 +6331,125 @@ impl eframe::App for Chonker3App {
                             response.request_focus();
                         }
                         
-                        // Handle Enter key
+                        // Rank the whole document's matches when the query changes
+                        let results_id = ui.make_persistent_id("search_results");
+                        if self.search_hits_query.as_ref() != Some(&self.session.search_query) {
+                            self.search_hits_query = Some(self.session.search_query.clone());
+                            self.search_hits = self.session.search_ranked(&self.session.search_query);
+                            if !self.search_hits.is_empty() {
+                                ui.memory_mut(|m| m.open_popup(results_id));
+                            }
+                        }
+                        
+                        // Enter jumps to the best match; the dropdown lists the rest
+                        let mut jump_to = None;
                         if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                            // Search is automatically updated through the binding
+                            jump_to = self.search_hits.first().cloned();
+                        }
+                        
+                        // Recent queries for this document, and the saved searches
+                        let mut recall = None;
+                        ui.menu_button("▾", |ui| {
+                            let recent = self.session.pdf_path.as_ref()
+                                .and_then(|p| self.workspace.find(p))
+                                .map(|index| self.workspace.documents[index].recent_searches.clone())
+                                .unwrap_or_default();
+                            ui.label(RichText::new("Recent").small().weak());
+                            if recent.is_empty() {
+                                ui.label(RichText::new("Nothing searched yet").weak());
+                            }
+                            for query in recent {
+                                if ui.button(&query).clicked() {
+                                    recall = Some(query);
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            ui.label(RichText::new("Saved").small().weak());
+                            let mut run = None;
+                            for saved in &self.workspace.saved_searches {
+                                let hover = if saved.regex { "Search the workspace for this pattern" } else { "Search this document" };
+                                if ui.button(&saved.name).on_hover_text(hover).clicked() {
+                                    run = Some(saved.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                            if let Some(saved) = run {
+                                // Patterns can't go in the search bar, so they list their hits across the workspace
+                                if saved.regex {
+                                    self.run_saved_search(&saved);
+                                    self.show_saved_searches = true;
+                                } else {
+                                    recall = Some(saved.query);
+                                }
+                            }
+                            ui.separator();
+                            if ui.add_enabled(!self.session.search_query.trim().is_empty(), egui::Button::new("Save this search..."))
+                                .clicked() {
+                                self.saved_search_draft = chonker3::search::SavedSearch {
+                                    name: String::new(),
+                                    query: self.session.search_query.trim().to_string(),
+                                    regex: false,
+                                };
+                                self.saved_search_error = None;
+                                self.show_saved_searches = true;
+                                ui.close_menu();
+                            }
+                            if ui.button("Manage saved searches...").clicked() {
+                                self.show_saved_searches = true;
+                                ui.close_menu();
+                            }
+                        });
+                        if let Some(query) = recall {
+                            self.session.search_query = query;
+                        }
+                        egui::popup::popup_below_widget(ui, results_id, &response, egui::PopupCloseBehavior::CloseOnClickOutside, |ui| {
+                            ui.set_min_width(360.0);
+                            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                                for hit in self.search_hits.iter().take(SEARCH_RESULTS_SHOWN) {
+                                    let label = RichText::new(format!("p. {}  {}", hit.page + 1, hit.snippet));
+                                    let label = if hit.kind == chonker3::search::MatchKind::Fuzzy { label.italics() } else { label };
+                                    if ui.selectable_label(false, label).on_hover_text(format!("{} match", hit.kind.label())).clicked() {
+                                        jump_to = Some(hit.clone());
+                                    }
+                                }
+                                if self.search_hits.len() > SEARCH_RESULTS_SHOWN {
+                                    ui.label(RichText::new(format!("{} more", self.search_hits.len() - SEARCH_RESULTS_SHOWN)).weak());
+                                }
+                            });
+                        });
+                        if let Some(hit) = jump_to {
+                            self.remember_search();
+                            ui.memory_mut(|m| m.close_popup());
+                            if self.session.go_to_page(hit.page) {
+                                self.pdf_texture = None;
+                            }
+                            self.selected_items = vec![hit.item_id];
                         }
                         
                         // Handle Escape key to close search
                         if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
                             self.show_search = false;
-                            self.search_query.clear();
+                            self.session.search_query.clear();
                         }
                         
                         // Clear button
-                        if !self.search_query.is_empty() {
-                            if ui.button("✕").clicked() {
-                                self.search_query.clear();
-                            }
+                        if !self.session.search_query.is_empty() && ui.button("✕").clicked() {
+                            self.session.search_query.clear();
                         }
                         
                         // Match count
-                        if !self.search_query.is_empty() {
-                            let match_count = if let Some(data) = &self.extracted_data {
-                                let document_state = self.convert_to_document_state(data);
-                                document_state.search_results.len()
-                            } else {
-                                0
-                            };
-                            ui.label(format!("{} matches", match_count));
+                        if !self.session.search_query.is_empty() {
+                            let match_count = self.document_state()
+                                .map(|state| state.search_results.len())
+                                .unwrap_or(0);
+                            ui.label(format!("{} matches on this page, {} in all", match_count, self.search_hits.len()));
                         }
                         
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             if ui.button("Close").clicked() {
                                 self.show_search = false;
-                                self.search_query.clear();
+                                self.session.search_query.clear();
                             }
                             ui.add_space(10.0);
                         });
@@ -512,6 +6457,146 @@ impl eframe::App for Chonker3App {
                 });
         }
         
+        self.show_heatmap(ctx);
+        self.show_pdfium_banner(ctx);
+        self.show_status_bar(ctx);
+        self.toasts.show(ctx);
+        self.show_job_progress(ctx);
+        self.show_pdfium_download(ctx);
+        self.show_python_setup(ctx);
+        
+        // Workspace sidebar
+        if self.show_workspace {
+            self.show_workspace_panel(ctx);
+        }
+        if self.show_page_notes && self.session.pdf_path.is_some() {
+            self.show_page_notes_panel(ctx);
+        }
+        if self.show_comments && self.session.pdf_path.is_some() {
+            self.show_comments_panel(ctx);
+        }
+        
+        self.show_duplicate_review(ctx);
+        self.show_page_organizer(ctx);
+        self.show_alignment(ctx);
+        self.show_diagnostics(ctx);
+        self.show_references(ctx);
+        self.show_transcript(ctx);
+        self.show_password_prompt(ctx);
+        self.show_extraction_history(ctx);
+        self.show_saved_searches(ctx);
+        self.show_barcodes(ctx);
+        self.show_table_stitching(ctx);
+        self.show_regions(ctx);
+        self.show_stats(ctx);
+        self.show_summaries(ctx);
+        self.show_problems(ctx);
+        self.show_pii(ctx);
+        self.show_field_mapping(ctx);
+        self.show_inspector(ctx);
+        self.show_quick_ocr(ctx);
+        self.show_llm(ctx);
+        self.show_remote_browser(ctx);
+        self.show_einvoice_check(ctx);
+        self.show_script_console(ctx);
+        self.show_macros(ctx);
+        self.show_settings(ctx);
+        
+        // Collaboration window
+        if self.show_collab {
+            let mut open = true;
+            egui::Window::new("Collaborate (experimental)")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    if let Some(session) = &self.collab {
+                        let role = if session.is_host { "Hosting on" } else { "Connected to" };
+                        ui.label(format!("{} {}", role, session.address));
+                        if session.is_host {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("Session code: {}", session.token));
+                                if ui.small_button("📋").on_hover_text("Copy the code for the people joining").clicked() {
+                                    ui.ctx().copy_text(session.token.clone());
+                                }
+                            });
+                        }
+                        if self.session.pdf_path != self.collab_pdf {
+                            ui.label(RichText::new("Paused: switch back to the shared PDF to keep editing together").color(Color32::GRAY));
+                        }
+                        ui.separator();
+                        
+                        ui.label(RichText::new("Peers:").strong());
+                        let mut peer_count = 0;
+                        for peer in session.peers() {
+                            peer_count += 1;
+                            match &peer.editing {
+                                Some(item_id) => ui.label(format!("• {} (editing {})", peer.name, item_id)),
+                                None => ui.label(format!("• {}", peer.name)),
+                            };
+                        }
+                        if peer_count == 0 {
+                            ui.label(RichText::new("Waiting for peers...").color(Color32::GRAY));
+                        }
+                        
+                        ui.separator();
+                        if ui.button("Leave session").clicked() {
+                            self.collab = None;
+                            self.toasts.info("Left collaboration session");
+                        }
+                    } else {
+                        let user_name = std::env::var("USER").unwrap_or_else(|_| "chonker".to_string());
+                        if self.collab_port == 0 {
+                            self.collab_port = collab::DEFAULT_PORT;
+                        }
+                        
+                        ui.label("Both users need the same PDF and extraction open.");
+                        ui.separator();
+                        let document = self.session.pdf_bytes.as_ref().map(|bytes| deep_link::document_hash(bytes));
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Port:");
+                            ui.add(egui::DragValue::new(&mut self.collab_port).range(1024..=65535));
+                            if ui.add_enabled(document.is_some(), egui::Button::new("Host")).clicked() {
+                                match CollabSession::host(self.collab_port, user_name.clone(), document.clone().unwrap_or_default()) {
+                                    Ok(session) => {
+                                        self.collab = Some(session);
+                                        self.collab_pdf = self.session.pdf_path.clone();
+                                    }
+                                    Err(e) => self.toasts.error(format!("Collab failed: {:#}", e)),
+                                }
+                            }
+                        });
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Join:");
+                            ui.add_sized(
+                                Vec2::new(160.0, 20.0),
+                                egui::TextEdit::singleline(&mut self.collab_join_address).hint_text("192.168.1.20:47470")
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Code:");
+                            ui.add_sized(
+                                Vec2::new(160.0, 20.0),
+                                egui::TextEdit::singleline(&mut self.collab_join_code).hint_text("Session code from the host")
+                            );
+                            let ready = document.is_some() && !self.collab_join_address.trim().is_empty() && !self.collab_join_code.trim().is_empty();
+                            if ui.add_enabled(ready, egui::Button::new("Connect")).clicked() {
+                                match CollabSession::join(self.collab_join_address.trim(), &self.collab_join_code, user_name, document.clone().unwrap_or_default()) {
+                                    Ok(session) => {
+                                        self.collab = Some(session);
+                                        self.collab_pdf = self.session.pdf_path.clone();
+                                    }
+                                    Err(e) => self.toasts.error(format!("Collab failed: {:#}", e)),
+                                }
+                            }
+                        });
+                    }
+                });
+            self.show_collab = open;
+        }
+        
         // Text edit dialog
         if let Some(item_id) = &self.editing_item_id.clone() {
             egui::Window::new("Edit Text")
@@ -537,17 +6622,55 @@ impl eframe::App for Chonker3App {
                         response.request_focus();
                     }
                     
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Type:");
+                        let selected = self.edit_type_buffer.map(|t| t.label()).unwrap_or("Unchanged");
+                        egui::ComboBox::from_id_salt("edit_item_type")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.edit_type_buffer, None, "Unchanged");
+                                for item_type in types::ItemType::ALL {
+                                    ui.selectable_value(&mut self.edit_type_buffer, Some(item_type), item_type.label());
+                                }
+                            });
+                    });
+                    
+                    ui.add_space(6.0);
+                    ui.label("Annotation:");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.edit_annotation_buffer)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(2)
+                    );
+                    
                     ui.separator();
                     ui.horizontal(|ui| {
+                        let mut close = false;
+                        
                         if ui.button("Save").clicked() {
-                            self.item_text_overrides.insert(item_id.clone(), self.edit_text_buffer.clone());
-                            self.editing_item_id = None;
-                            self.edit_text_buffer.clear();
+                            self.record_item_edit(item_id, false);
+                            self.session.set_text(item_id, self.edit_text_buffer.clone());
+                            self.session.set_type(item_id, self.edit_type_buffer);
+                            self.session.set_annotation(item_id, &self.edit_annotation_buffer);
+                            close = true;
+                        }
+                        
+                        if ui.button("Delete item").clicked() {
+                            self.record_item_edit(item_id, true);
+                            self.session.delete_item(item_id);
+                            close = true;
                         }
                         
                         if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                            close = true;
+                        }
+                        
+                        if close {
                             self.editing_item_id = None;
                             self.edit_text_buffer.clear();
+                            self.edit_annotation_buffer.clear();
+                            self.edit_type_buffer = None;
                         }
                     });
                 });
@@ -565,20 +6688,54 @@ impl eframe::App for Chonker3App {
                     
                     ui.label(RichText::new("Features:").strong());
                     ui.label("• Click once: Copy text to clipboard");
+                    ui.label("• Shift/Alt/Cmd+click: Copy with layout, as Markdown or as TSV (see ⚙)");
+                    ui.label("• Cmd+Shift/Cmd+Alt+click in a table: Copy the row/column as TSV");
                     ui.label("• Double-click: Edit text content");
-                    ui.label("• Use search to find text (highlights in yellow)");
-                    ui.label("• Zoom with buttons or Cmd+scroll");
+                    ui.label("• Drag on empty space: Select items (Cmd+C copies them)");
+                    ui.label("• Drag across an item's text: Select part of it (Cmd+C copies it)");
+                    ui.label("• Use search to find text (highlight colors are set in ⚙)");
+                    ui.label("• ▾ beside the search box: Recent searches, and saved ones to run across the workspace");
+                    ui.label("• 👁: Hide tables, headers, form fields, images or low-confidence items");
+                    ui.label("• 👻: Show the PDF page under the extracted text to spot misalignment");
+                    ui.label("• 📐: Score every page's alignment and jump to the worst ones");
+                    ui.label("• 🔥: Show where edits, low-confidence items and missing text cluster");
+                    ui.label("• 🧲: Snap boxes to the PDF's own text positions");
+                    ui.label("• 🏷: Decode barcodes and QR codes on the pages");
+                    ui.label("• 🔬: See which backend and processing steps produced the selected item");
+                    ui.label("• 🔍: Drag around an item or empty space to re-OCR just that region");
+                    ui.label("• 📷: Read the text in a pasted screenshot or image, no PDF needed");
+                    ui.label("• 🕘: Past extractions with their options and timings; re-run any of them");
+                    ui.label("• 🛡: Find personal data (SSNs, card numbers, emails, names, your patterns), mask it or save redaction boxes");
+                    ui.label("• LLM: Summarize, pull out key fields or clean up text with a model set up in ⚙");
+                    ui.label("• Right-click an item: Run an LLM prompt template on it or the selection");
+                    ui.label("• 📝: Summarize the page or the whole document (TextRank, or the LLM)");
+                    ui.label("• 🌐: Machine-translate pages in place; Cmd+T flips between original and translated");
+                    ui.label("• Zoom with buttons, Cmd+scroll or Cmd+Plus/Minus (Cmd+0 resets)");
+                    ui.label("• Cmd+1: Fit width, Cmd+2: fit page");
+                    ui.label("• Cmd+R / Cmd+Shift+R: Rotate the view clockwise / counter-clockwise");
+                    ui.label("• Arrow keys pan the extracted view (Shift for bigger steps)");
                     ui.label("• Scroll to move around the document");
+                    ui.label("• 📜: Continuous scroll through every page; the page counter follows along");
+                    ui.label("• Drag the page, or middle-drag the extracted view, to pan");
                     ui.separator();
                     
                     ui.label(RichText::new("Keyboard Shortcuts:").strong());
                     ui.label("• Cmd+F: Open search");
                     ui.label("• Escape: Close search");
                     ui.label("• ▶/◀: Navigate pages");
+                    ui.label("• 🔖: Bookmark the page, 🗒: page notes and bookmarks");
+                    ui.label("• 🔗: Copy a chonker3:// link to the selection or page");
+                    ui.label("• 💬: Comment on the selected item; click an item's 💬 marker to open its thread");
+                    ui.label("• 🔲: Named regions; draw one around a field, name it, and export all regions as named fields");
+                    ui.label("• ✔: Validation rules on the named regions, with the problems they find");
+                    ui.label("• Every PDF you open gets a tab; Cmd+W closes it, Ctrl+Tab moves to the next");
+                    ui.label("• ☁: Open a document from an S3 bucket or WebDAV folder set up in ⚙");
                     ui.separator();
                     
                     ui.label(RichText::new("Tips:").strong());
                     ui.label("• Extract before viewing for best results");
+                    ui.label("• Without Python, the native extractor reads the PDF's text layer");
+                    ui.label("• A PDF extracted before loads from the cache; ⟲ extracts it again");
                     ui.label("• Some PDFs may have text rendering issues");
                     ui.label("• Copy text that appears misplaced");
                     
@@ -591,26 +6748,173 @@ impl eframe::App for Chonker3App {
         
         // Central area
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.current_pdf.is_some() {
+            self.pointer_position = None;
+            if self.session.pdf_path.is_some() {
                 let available = ui.available_size();
                 let panel_width = available.x * 0.5;
                 
-                if self.pdf_texture.is_none() && self.pdf_bytes.is_some() {
-                    self.load_pdf_page(ctx, panel_width);
+                // Fit the page inside a panel, less the canvas's padding
+                let fit_area = Vec2::new(panel_width - 42.0, available.y - 80.0);
+                let mut commands = std::mem::take(&mut self.view_commands);
+                commands.extend(viewport::keyboard_commands(ctx));
+                for command in commands {
+                    self.apply_view(command, fit_area);
+                }
+                
+                if self.pdf_scroll.is_active() || self.canvas_scroll.is_active() {
+                    self.repaint.animate();
+                }
+                
+                // Re-render once wheel zooming or a DPI change has settled
+                let target = renderer::RenderTarget { width: self.page_size().0 * self.view.zoom, pixels_per_point: ctx.pixels_per_point() };
+                if self.pdf_texture.is_some() && self.pdf_render_debounce.update(target, std::time::Instant::now()) {
+                    self.pdf_texture = None;
+                }
+                if self.pdf_render_debounce.is_pending() {
+                    self.repaint.within(std::time::Duration::from_millis(50));
+                }
+                
+                if self.pdf_texture.is_none() && self.session.pdf_bytes.is_some() {
+                    self.load_pdf_page(ctx);
                 }
                 
+                let smooth_scrolling = self.settings.smooth_scrolling;
+                let palette = self.settings.palette;
+                let document_state = self.document_state();
+                let (time, dt) = ctx.input(|i| (i.time, i.stable_dt.min(0.1)));
+                
+                // The continuous view stacks every page at the current zoom and rotation
+                let stack = (self.settings.continuous_pages && self.session.pdfium.is_some())
+                    .then(|| {
+                        let sizes = self.continuous.sizes.get_or_insert_with(|| {
+                            self.session.page_sizes().into_iter().map(|(w, h)| (w as f32, h as f32)).collect()
+                        });
+                        PageStack::new(sizes.iter().map(|size| self.view.transform(Pos2::ZERO, *size).page_rect().size()))
+                    })
+                    .filter(|stack| !stack.is_empty());
+                // Scroll to the current page when it was changed some other way, or the zoom changed
+                let jump = stack.as_ref()
+                    .filter(|stack| self.continuous.shown_page != Some(self.session.page) || stack.size().y != self.continuous.stack_height)
+                    .map(|stack| stack.top(self.session.page).min((stack.size().y - self.continuous.view_height).max(0.0)));
+                
                 ui.horizontal(|ui| {
                     // Left panel - PDF
                     ui.allocate_ui(Vec2::new(panel_width - 2.0, available.y), |ui| {
-                        ScrollArea::both().id_salt("pdf_scroll").show(ui, |ui| {
-                            if let Some(texture) = &self.pdf_texture {
-                                ui.image(texture);
+                        let mut scroll_area = ScrollArea::both().id_salt("pdf_scroll");
+                        if let Some(top) = jump {
+                            self.pdf_scroll.stop();
+                            self.pdf_scroll_offset.y = top;
+                            scroll_area = scroll_area.vertical_scroll_offset(top);
+                        }
+                        if smooth_scrolling {
+                            // Wheel and drag go through pdf_scroll; the area only shows the offset
+                            self.pdf_scroll_offset -= self.pdf_scroll.step(dt);
+                            scroll_area = scroll_area.drag_to_scroll(false).scroll_offset(self.pdf_scroll_offset);
+                        }
+                        let output = scroll_area.show(ui, |ui| {
+                            let sense = if smooth_scrolling { egui::Sense::drag() } else { egui::Sense::hover() };
+                            let full = egui::Rect::from_min_max(Pos2::ZERO, egui::pos2(1.0, 1.0));
+                            // Where the current page went, and the page and view top the continuous view scrolled to
+                            let (transform, response, scrolled) = if let Some(stack) = &stack {
+                                let (rect, response) = ui.allocate_exact_size(stack.size(), sense);
+                                let view = ui.clip_rect().translate(-rect.min.to_vec2());
+                                let visible = stack.visible(view.top(), view.bottom());
+                                self.render_continuous_pages(ui.ctx(), visible.clone());
+                                let sizes = self.continuous.sizes.as_deref().unwrap_or_default();
+                                for page in visible {
+                                    let page_rect = stack.rect(page).translate(rect.min.to_vec2());
+                                    match (self.continuous.textures.get(&page), sizes.get(page)) {
+                                        (Some((texture, _)), Some(&size)) => {
+                                            let local = egui::Rect::from_min_size(Pos2::ZERO, Vec2::from(size) * self.view.zoom);
+                                            self.view.transform(page_rect.min, size).paint_image(ui.painter(), texture.id(), local, full, Color32::WHITE);
+                                        }
+                                        // Not rendered yet
+                                        _ => {
+                                            ui.painter().rect_filled(page_rect, 0.0, Color32::from_gray(230));
+                                        }
+                                    }
+                                }
+                                let page = self.session.page;
+                                let size = sizes.get(page).copied().unwrap_or_else(|| self.page_size());
+                                let transform = self.view.transform(stack.rect(page).min + rect.min.to_vec2(), size);
+                                (transform, response, Some((stack.current_page(view.top(), view.height()), view.top(), view.height())))
+                            } else if let Some(texture) = &self.pdf_texture {
+                                // Drawn at the current zoom, so a texture still awaiting a re-render stretches to fit
+                                let page_size = self.page_size();
+                                let page_rect = self.view.transform(Pos2::ZERO, page_size).page_rect();
+                                let (rect, response) = ui.allocate_exact_size(page_rect.size(), sense);
+                                let transform = self.view.transform(rect.min, page_size);
+                                let local = egui::Rect::from_min_size(Pos2::ZERO, Vec2::from(page_size) * self.view.zoom);
+                                transform.paint_image(ui.painter(), texture.id(), local, full, Color32::WHITE);
+                                (transform, response, None)
+                            } else if self.session.pdfium.is_none() {
+                                ui.centered_and_justified(|ui| {
+                                    ui.label(RichText::new("Page preview unavailable without pdfium").color(Color32::GRAY).size(14.0));
+                                });
+                                return None;
                             } else {
                                 ui.centered_and_justified(|ui| {
                                     ui.label(RichText::new("Loading...").color(Color32::GRAY).size(14.0));
                                 });
+                                return None;
+                            };
+                            
+                            if response.dragged() {
+                                self.pdf_scroll_offset -= self.pdf_scroll.drag(response.drag_delta(), time);
+                            } else if response.drag_stopped() {
+                                self.pdf_scroll.release(time);
+                            }
+                            
+                            // Mark selected items, search matches and personal data on the page too
+                            if let Some(state) = &document_state {
+                                for item in &state.items {
+                                    let fill = if state.search_results.contains(&item.id) {
+                                        Palette::color(palette.search_highlight)
+                                    } else if state.selected_items.contains(&item.id) {
+                                        Palette::color(palette.selection)
+                                    } else if state.pii_items.contains(&item.id) {
+                                        Palette::with_alpha(palette.pii, 70)
+                                    } else {
+                                        continue;
+                                    };
+                                    ui.painter().rect_filled(transform.bbox_to_screen(&item.bbox, Vec2::ZERO), 0.0, fill);
+                                }
                             }
+                            
+                            // Map the pointer back onto the page
+                            if let Some(pos) = response.hover_pos().filter(|pos| transform.page_rect().contains(*pos)) {
+                                self.pointer_position = Some(transform.to_page(pos));
+                            }
+                            scrolled
                         });
+                        
+                        // The page counter follows scrolling through the continuous view
+                        if let (Some(stack), Some((page, view_top, view_height))) = (&stack, output.inner) {
+                            let scrolled = jump.is_none() && (view_top - self.continuous.view_top).abs() > 0.5;
+                            if scrolled && self.session.go_to_page(page) {
+                                self.pdf_texture = None;
+                            }
+                            self.continuous.shown_page = Some(self.session.page);
+                            self.continuous.view_top = view_top;
+                            self.continuous.view_height = view_height;
+                            self.continuous.stack_height = stack.size().y;
+                        }
+                        
+                        if smooth_scrolling {
+                            if ui.rect_contains_pointer(output.inner_rect) {
+                                let wheel = ui.input(|i| if i.modifiers.command { Vec2::ZERO } else { i.raw_scroll_delta });
+                                if wheel != Vec2::ZERO {
+                                    self.pdf_scroll.scroll(wheel);
+                                }
+                            }
+                            // Stop at the page edges
+                            let max_offset = (output.content_size - output.inner_rect.size()).max(Vec2::ZERO);
+                            let clamped = self.pdf_scroll_offset.clamp(Vec2::ZERO, max_offset);
+                            if clamped != self.pdf_scroll_offset {
+                                self.pdf_scroll.stop();
+                                self.pdf_scroll_offset = clamped;
+                            }
+                        }
                     });
                     
                     ui.separator();
@@ -624,40 +6928,115 @@ impl eframe::App for Chonker3App {
                             Color32::WHITE
                         );
                         
-                        if let Some(data) = self.extracted_data.clone() {
+                        if let Some(document_state) = document_state {
                             use crate::renderer::DocumentCanvas;
                             
-                            let document_state = self.convert_to_document_state(&data);
                             
                             // Wrap canvas in scroll area to prevent overflow
                             ScrollArea::both()
                                 .id_salt("extracted_content_scroll")
                                 .auto_shrink([false, false])
                                 .show(ui, |ui| {
-                                    let canvas = DocumentCanvas::new(document_state)
-                                        .with_zoom(self.zoom_level);
+                                    let mut canvas = DocumentCanvas::new(document_state)
+                                        .with_zoom(self.view.zoom)
+                                        .with_rotation(self.view.rotation)
+                                        .with_copy_settings(self.settings.copy)
+                                        .with_palette(palette)
+                                        .with_width_fitting(self.settings.width_fitting)
+                                        .with_text_selection(!self.region_draw_mode && !self.reocr_region_mode);
+                                    if self.settings.llm.enabled && self.job.is_none() {
+                                        canvas = canvas.with_context_actions(self.settings.llm.templates.iter().map(|t| t.name.clone()).collect());
+                                    }
+                                    if let Some(texture) = &self.pdf_texture {
+                                        canvas = canvas.with_page_image(texture.id());
+                                        if self.ghost_overlay {
+                                            canvas = canvas.with_ghost_page(texture.id(), self.settings.ghost_opacity);
+                                        }
+                                    }
                                     
                                     let canvas_response = ui.add(canvas);
                                     
+                                    if let Some((format, text)) = DocumentCanvas::take_copied(ui.ctx()) {
+                                        let preview: String = text.chars().take(50).map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+                                        let ellipsis = if text.chars().count() > 50 { "..." } else { "" };
+                                        self.toasts.success(format!("📋 Copied as {}: {}{}", format.label(), preview, ellipsis));
+                                    }
+                                    if let Some(selection) = DocumentCanvas::take_selection(ui.ctx()) {
+                                        self.selected_items = selection;
+                                    }
+                                    // A prompt template picked on an item's context menu runs on the
+                                    // selection when the item is part of it, else on the item alone
+                                    if let Some((item_id, index)) = DocumentCanvas::take_context_action(ui.ctx()) {
+                                        if !self.selected_items.contains(&item_id) {
+                                            self.selected_items = vec![item_id];
+                                        }
+                                        self.run_llm_template(index, self.selected_items.clone());
+                                    }
+                                    if let Some(item_id) = DocumentCanvas::take_comment_request(ui.ctx()) {
+                                        self.open_comments(item_id);
+                                    }
+                                    if let Some(region) = DocumentCanvas::take_region(ui.ctx()) {
+                                        if self.region_draw_mode {
+                                            self.pending_region = Some((self.session.page, region));
+                                            self.region_name_buffer.clear();
+                                            self.region_draw_mode = false;
+                                            self.show_regions = true;
+                                        } else if self.reocr_region_mode && self.job.is_none() {
+                                            self.reocr_region(region);
+                                        }
+                                    }
+                                    if let Some(position) = DocumentCanvas::take_pointer_position(ui.ctx()) {
+                                        self.pointer_position = Some(position);
+                                    }
+                                    if let Some(overflow) = DocumentCanvas::take_overflow(ui.ctx()) {
+                                        self.overflow_items = overflow;
+                                    }
+                                    if let Some((dx, dy)) = DocumentCanvas::take_pan_request(ui.ctx()) {
+                                        self.apply_view(ViewCommand::Pan(Vec2::new(dx, dy)), fit_area);
+                                    }
+                                    
+                                    // Double-click on an item opens the edit dialog
+                                    if let Some((item_id, text)) = DocumentCanvas::take_edit_request(ui.ctx()) {
+                                        self.edit_type_buffer = self.session.edits.type_overrides.get(&item_id).copied();
+                                        self.edit_annotation_buffer = self.session.edits.annotations.get(&item_id).cloned().unwrap_or_default();
+                                        // Edits apply to the original text, even with the translation shown
+                                        self.edit_text_buffer = if self.show_translation && self.translation_language().is_some() {
+                                            self.session.edits.text_overrides.get(&item_id).cloned()
+                                                .or_else(|| self.session.item(&item_id).map(|item| item.content))
+                                                .unwrap_or(text)
+                                        } else {
+                                            text
+                                        };
+                                        self.editing_item_id = Some(item_id);
+                                    }
+                                    
                                     // Handle zoom with mouse wheel
                                     if canvas_response.hovered() {
-                                        ui.input(|i| {
-                                            // Check for Ctrl/Cmd + scroll for zoom
-                                            if i.modifiers.command {
-                                                let scroll_delta = i.raw_scroll_delta.y;
-                                                if scroll_delta != 0.0 {
-                                                    // Positive scroll = zoom in, negative = zoom out
-                                                    let zoom_factor = 1.0 + (scroll_delta * 0.001);
-                                                    self.zoom_level = (self.zoom_level * zoom_factor).clamp(0.5, 3.0);
-                                                }
-                                            } else {
-                                                // Regular scroll for panning
-                                                self.pan_offset += i.raw_scroll_delta;
+                                        let (command, scroll_delta) = ui.input(|i| (i.modifiers.command, i.raw_scroll_delta));
+                                        // Check for Ctrl/Cmd + scroll for zoom
+                                        if command {
+                                            if scroll_delta.y != 0.0 {
+                                                // Positive scroll = zoom in, negative = zoom out
+                                                self.apply_view(ViewCommand::ZoomBy(1.0 + (scroll_delta.y * 0.001)), fit_area);
                                             }
-                                        });
+                                        } else if smooth_scrolling {
+                                            // Regular scroll for panning, eased in
+                                            self.canvas_scroll.scroll(scroll_delta);
+                                        } else if scroll_delta != Vec2::ZERO {
+                                            // Regular scroll for panning
+                                            self.apply_view(ViewCommand::Pan(scroll_delta), fit_area);
+                                        }
                                     }
                                     
-                                    // Panning removed - use scroll only
+                                    // Middle-drag pans; primary drag selects
+                                    if canvas_response.dragged_by(egui::PointerButton::Middle) {
+                                        let delta = canvas_response.drag_delta();
+                                        self.view.pan += if smooth_scrolling { self.canvas_scroll.drag(delta, time) } else { delta };
+                                    } else if canvas_response.drag_stopped_by(egui::PointerButton::Middle) && smooth_scrolling {
+                                        self.canvas_scroll.release(time);
+                                    }
+                                    // Kinetic scrolling moves the view directly, keeping its momentum
+                                    self.view.pan += self.canvas_scroll.step(dt);
                                 });
                         } else {
                             ui.centered_and_justified(|ui| {
@@ -665,6 +7044,7 @@ impl eframe::App for Chonker3App {
                                     ui.vertical_centered(|ui| {
                                         ui.label(RichText::new("🐹").size(48.0));
                                         ui.label(RichText::new("*chomp chomp*").size(16.0).color(TEAL));
+                                        self.cancel_extraction_button(ui);
                                     });
                                 } else {
                                     ui.label(RichText::new("No content extracted yet").color(Color32::GRAY).size(14.0));
@@ -685,17 +7065,52 @@ impl eframe::App for Chonker3App {
                 });
             }
         });
+        
+        self.repaint.finish_frame(ctx);
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
     
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
+    let launch = match chonker3::launch::parse_args(std::env::args().skip(1)) {
+        Ok(launch) => launch,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, chonker3::launch::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if launch.help {
+        println!("{}", chonker3::launch::USAGE);
+        return Ok(());
+    }
+    if launch.version {
+        println!("chonker3 {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let listener = if launch.new_window {
+        None
+    } else {
+        let links: Vec<String> = launch.links.iter().map(DeepLink::to_uri).collect();
+        match instance::claim(instance::DEFAULT_PORT, &launch.files, &links) {
+            instance::Launch::Primary(listener) => Some(listener),
+            instance::Launch::Forwarded => return Ok(()),
+            instance::Launch::Standalone => None,
+        }
+    };
+    
+    let viewport = if launch.quick_drop {
+        egui::ViewportBuilder::default()
+            .with_inner_size([300.0, 260.0])
+            .with_min_inner_size([220.0, 180.0])
+            .with_always_on_top()
+    } else {
+        egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
             .with_min_inner_size([800.0, 600.0])
-            .with_icon(load_icon()),
+    };
+    let options = eframe::NativeOptions {
+        viewport: viewport.with_icon(load_icon()),
         ..Default::default()
     };
     
@@ -704,7 +7119,7 @@ fn main() -> Result<(), eframe::Error> {
         options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(Chonker3App::new(cc)))
+            Ok(Box::new(Chonker3App::new(cc, launch, listener)))
         }),
     )
 }
@@ -0,0 +1,140 @@
+//! Memory usage guardrails
+//!
+//! The app keeps rendered page textures, organizer thumbnails, laid-out text
+//! and the extraction JSON in memory. `MemoryUsage` estimates their size and
+//! `MemoryGuard` decides when to evict caches and drop the render resolution so
+//! very large PDFs stay within the user's budget.
+
+use serde_json::Value;
+
+pub const DEFAULT_BUDGET_MB: usize = 1024;
+
+/// Lowest render scale the guard will fall back to
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+
+/// Rough heap cost of one laid-out glyph (mesh vertices plus glyph info)
+const GALLEY_BYTES_PER_CHAR: usize = 160;
+
+/// Restore full resolution only well below the budget, so the guard doesn't flap
+const RESTORE_FRACTION: f64 = 0.6;
+
+pub fn texture_bytes(size: [usize; 2]) -> usize {
+    size[0] * size[1] * 4
+}
+
+/// Approximate in-memory size of a JSON value
+pub fn json_bytes(value: &Value) -> usize {
+    let node = std::mem::size_of::<Value>();
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) => node,
+        Value::String(s) => node + s.capacity(),
+        Value::Array(items) => node + items.iter().map(json_bytes).sum::<usize>(),
+        Value::Object(map) => node + map.iter().map(|(k, v)| k.capacity() + json_bytes(v)).sum::<usize>(),
+    }
+}
+
+/// Approximate size of the galleys laid out for a page of text
+pub fn galley_bytes<'a>(texts: impl IntoIterator<Item = &'a str>) -> usize {
+    texts.into_iter().map(|t| t.chars().count() * GALLEY_BYTES_PER_CHAR).sum()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub page_textures: usize,
+    pub thumbnails: usize,
+    pub galleys: usize,
+    pub extraction: usize,
+    pub pdf_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.page_textures + self.thumbnails + self.galleys + self.extraction + self.pdf_bytes
+    }
+
+    /// One line per category, in MB
+    pub fn breakdown(&self) -> String {
+        [
+            ("Page textures", self.page_textures),
+            ("Thumbnails", self.thumbnails),
+            ("Text layout", self.galleys),
+            ("Extraction", self.extraction),
+            ("PDF file", self.pdf_bytes),
+        ]
+        .iter()
+        .map(|(label, bytes)| format!("{}: {}", label, format_mb(*bytes)))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+impl std::ops::AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.page_textures += other.page_textures;
+        self.thumbnails += other.thumbnails;
+        self.galleys += other.galleys;
+        self.extraction += other.extraction;
+        self.pdf_bytes += other.pdf_bytes;
+    }
+}
+
+pub fn format_mb(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuardAction {
+    None,
+    /// Drop cached textures and re-render at the new scale
+    Evict { render_scale: f32 },
+    /// Back under budget: re-render at the new, higher scale
+    Restore { render_scale: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryGuard {
+    pub budget_bytes: usize,
+    /// Multiplier on the resolution pages are rendered at
+    pub render_scale: f32,
+}
+
+impl Default for MemoryGuard {
+    fn default() -> Self {
+        Self {
+            budget_bytes: DEFAULT_BUDGET_MB * 1024 * 1024,
+            render_scale: 1.0,
+        }
+    }
+}
+
+impl MemoryGuard {
+    pub fn set_budget_mb(&mut self, budget_mb: usize) {
+        self.budget_bytes = budget_mb * 1024 * 1024;
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.render_scale < 1.0
+    }
+
+    /// Decide what to do about the current usage. Texture memory scales with
+    /// the square of the render scale; halving it quarters the textures.
+    pub fn update(&mut self, usage: MemoryUsage) -> GuardAction {
+        let total = usage.total();
+        if total > self.budget_bytes && self.render_scale > MIN_RENDER_SCALE {
+            self.render_scale = (self.render_scale / 2.0).max(MIN_RENDER_SCALE);
+            return GuardAction::Evict { render_scale: self.render_scale };
+        }
+
+        // Judge by the re-rendered textures; right after an eviction there are none
+        let textures = usage.page_textures + usage.thumbnails;
+        if self.is_degraded() && textures > 0 {
+            // What usage would be with textures at double the current scale
+            let restored = total - textures + textures * 4;
+            if (restored as f64) < self.budget_bytes as f64 * RESTORE_FRACTION {
+                self.render_scale = (self.render_scale * 2.0).min(1.0);
+                return GuardAction::Restore { render_scale: self.render_scale };
+            }
+        }
+        GuardAction::None
+    }
+}
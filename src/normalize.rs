@@ -0,0 +1,221 @@
+//! Locale-aware number and currency normalization
+//!
+//! Extracted numbers keep whatever notation the document used ("1.234,56 €",
+//! "$1,234.56", "12,5 %"). This module parses them into canonical values, which
+//! are stored on items under `normalized` and carried into structured exports.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Which character marks the decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NumberLocale {
+    /// Guess per document from its unambiguous numbers
+    #[default]
+    Auto,
+    /// 1,234.56 (English, Swiss with apostrophes)
+    DecimalPoint,
+    /// 1.234,56 (German, French with spaces, ...)
+    DecimalComma,
+}
+
+impl NumberLocale {
+    pub const ALL: [NumberLocale; 3] = [NumberLocale::Auto, NumberLocale::DecimalPoint, NumberLocale::DecimalComma];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NumberLocale::Auto => "Auto-detect",
+            NumberLocale::DecimalPoint => "1,234.56",
+            NumberLocale::DecimalComma => "1.234,56",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedNumber {
+    pub value: f64,
+    /// ISO 4217 code when a currency symbol or code was present
+    pub currency: Option<&'static str>,
+    pub percent: bool,
+}
+
+impl NormalizedNumber {
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({ "value": self.value });
+        if let Some(currency) = self.currency {
+            value["currency"] = json!(currency);
+        }
+        if self.percent {
+            value["percent"] = json!(true);
+        }
+        value
+    }
+}
+
+/// Symbols and codes we recognize, longest first so "US$" wins over "$"
+const CURRENCIES: [(&str, &str); 14] = [
+    ("US$", "USD"), ("CHF", "CHF"), ("EUR", "EUR"), ("USD", "USD"), ("GBP", "GBP"),
+    ("JPY", "JPY"), ("SEK", "SEK"), ("NOK", "NOK"), ("DKK", "DKK"),
+    ("Fr.", "CHF"), ("€", "EUR"), ("$", "USD"), ("£", "GBP"), ("¥", "JPY"),
+];
+
+/// Thousands separators that are never decimal marks
+fn is_group_only(c: char) -> bool {
+    matches!(c, '\'' | '’' | ' ' | '\u{a0}' | '\u{202f}')
+}
+
+/// Parse a whole string as a single (possibly currency or percent) number
+pub fn parse_number(text: &str, locale: NumberLocale) -> Option<NormalizedNumber> {
+    let mut rest = text.trim();
+    let mut currency = None;
+    let mut percent = false;
+    let mut negative = false;
+
+    if rest.starts_with('(') && rest.ends_with(')') {
+        negative = true;
+        rest = rest[1..rest.len() - 1].trim();
+    }
+    if let Some(stripped) = rest.strip_suffix('%') {
+        percent = true;
+        rest = stripped.trim_end();
+    }
+    for (symbol, code) in CURRENCIES {
+        if let Some(stripped) = rest.strip_prefix(symbol) {
+            currency = Some(code);
+            rest = stripped.trim_start();
+            break;
+        }
+        if let Some(stripped) = rest.strip_suffix(symbol) {
+            currency = Some(code);
+            rest = stripped.trim_end();
+            break;
+        }
+    }
+    if let Some(stripped) = rest.strip_prefix(['-', '−']) {
+        negative = !negative;
+        rest = stripped.trim_start();
+    } else if let Some(stripped) = rest.strip_suffix('-') {
+        // Accounting style trailing minus
+        negative = !negative;
+        rest = stripped.trim_end();
+    }
+
+    if rest.is_empty()
+        || !rest.starts_with(|c: char| c.is_ascii_digit())
+        || !rest.ends_with(|c: char| c.is_ascii_digit())
+        || !rest.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',' || is_group_only(c))
+    {
+        return None;
+    }
+    // Spaces and apostrophes must separate groups of three, or "2023 2024" would become one number
+    if rest.split(is_group_only).skip(1).any(|group| {
+        group.find(['.', ',']).unwrap_or(group.len()) != 3
+    }) {
+        return None;
+    }
+
+    let decimal = decimal_mark(rest, locale)?;
+    let mut canonical = String::with_capacity(rest.len());
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            canonical.push(c);
+        } else if Some(c) == decimal {
+            canonical.push('.');
+        }
+    }
+    let value: f64 = canonical.parse().ok()?;
+
+    Some(NormalizedNumber {
+        value: if negative { -value } else { value },
+        currency,
+        percent,
+    })
+}
+
+/// Work out which separator (if any) is the decimal mark of a digits-and-separators string
+fn decimal_mark(digits: &str, locale: NumberLocale) -> Option<Option<char>> {
+    let dots = digits.matches('.').count();
+    let commas = digits.matches(',').count();
+
+    match (dots, commas) {
+        (0, 0) => Some(None),
+        // Both present: the last one is the decimal mark and may appear only once
+        (_, _) if dots > 0 && commas > 0 => {
+            let last = if digits.rfind('.') > digits.rfind(',') { '.' } else { ',' };
+            let last_count = if last == '.' { dots } else { commas };
+            (last_count == 1).then_some(Some(last))
+        }
+        // One kind repeated: thousands grouping
+        (n, 0) | (0, n) if n > 1 => Some(None),
+        // A single separator
+        _ => {
+            let sep = if dots == 1 { '.' } else { ',' };
+            let digits_after = digits.len() - digits.rfind(sep)? - 1;
+            match locale {
+                NumberLocale::DecimalPoint => Some((sep == '.').then_some('.')),
+                NumberLocale::DecimalComma => Some((sep == ',').then_some(',')),
+                // Without a hint, exactly three trailing digits reads as grouping
+                NumberLocale::Auto => Some((digits_after != 3).then_some(sep)),
+            }
+        }
+    }
+}
+
+/// Pick a locale for a document from the numbers that are unambiguous
+pub fn detect_locale<'a>(texts: impl Iterator<Item = &'a str>) -> NumberLocale {
+    let (mut point, mut comma) = (0, 0);
+    for token in texts.flat_map(|t| t.split_whitespace()) {
+        let token = token.trim_matches(|c: char| !c.is_ascii_digit());
+        let (dot, com) = (token.rfind('.'), token.rfind(','));
+        match (dot, com) {
+            (Some(d), Some(c)) if d > c => point += 1,
+            (Some(_), Some(_)) => comma += 1,
+            // A lone separator followed by 1-2 digits can only be a decimal mark
+            (Some(d), None) if (1..=2).contains(&(token.len() - d - 1)) => point += 1,
+            (None, Some(c)) if (1..=2).contains(&(token.len() - c - 1)) => comma += 1,
+            _ => {}
+        }
+    }
+    match (point, comma) {
+        (0, 0) => NumberLocale::Auto,
+        (p, c) if c > p => NumberLocale::DecimalComma,
+        _ => NumberLocale::DecimalPoint,
+    }
+}
+
+/// Store canonical values on every purely numeric item of an extraction.
+/// Returns the locale that was used, which is recorded in the metadata.
+pub fn normalize_extraction(data: &mut Value, locale: NumberLocale) -> NumberLocale {
+    let locale = match locale {
+        NumberLocale::Auto => detect_locale(
+            data.get("items")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|item| item.get("content").or_else(|| item.get("text")).and_then(|v| v.as_str())),
+        ),
+        other => other,
+    };
+
+    if let Some(items) = data.get_mut("items").and_then(|v| v.as_array_mut()) {
+        for item in items.iter_mut() {
+            let content = item.get("content")
+                .or_else(|| item.get("text"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            match parse_number(content, locale) {
+                Some(number) => item["normalized"] = number.to_json(),
+                None => {
+                    if let Some(item) = item.as_object_mut() {
+                        item.remove("normalized");
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(metadata) = data.get_mut("metadata").and_then(|v| v.as_object_mut()) {
+        metadata.insert("number_locale".to_string(), json!(locale));
+    }
+    locale
+}
@@ -0,0 +1,291 @@
+//! Page organizer: reorder, rotate, delete and insert pages
+//!
+//! The organizer keeps an ordered list of slots pointing at source pages or at
+//! inserted blank pages. Writing it out produces a new PDF plus a copy of the
+//! extraction JSON whose page numbers (and, for rotated pages, bboxes) match the
+//! new document. Blank pages are marked `synthetic` in the JSON.
+
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use egui::TextureHandle;
+use pdfium_render::prelude::*;
+use serde_json::{json, Value};
+
+use crate::pdf_output::{self, PdfOutput};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageSource {
+    /// Zero-based page index in the source PDF
+    Original(usize),
+    /// Inserted blank page carrying a free-text note
+    Blank { note: String, size: (f32, f32) },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSlot {
+    pub source: PageSource,
+    /// Clockwise rotation in degrees (0, 90, 180, 270) applied on top of the source
+    pub rotation: u16,
+}
+
+impl PageSlot {
+    pub fn source_index(&self) -> Option<usize> {
+        match self.source {
+            PageSource::Original(index) => Some(index),
+            PageSource::Blank { .. } => None,
+        }
+    }
+}
+
+/// Layout of the note text on a blank page
+const NOTE_FONT_SIZE: f32 = 12.0;
+const NOTE_MARGIN: f32 = 72.0;
+
+pub struct PageOrganizer {
+    pub slots: Vec<PageSlot>,
+    /// Source page sizes in points, used to size inserted blank pages
+    page_sizes: Vec<(f32, f32)>,
+    /// Thumbnails by source page index
+    pub thumbnails: HashMap<usize, TextureHandle>,
+}
+
+impl PageOrganizer {
+    pub fn new(page_sizes: Vec<(f32, f32)>) -> Self {
+        Self {
+            slots: (0..page_sizes.len())
+                .map(|index| PageSlot { source: PageSource::Original(index), rotation: 0 })
+                .collect(),
+            page_sizes,
+            thumbnails: HashMap::new(),
+        }
+    }
+
+    pub fn move_slot(&mut self, from: usize, to: usize) {
+        if from < self.slots.len() && to < self.slots.len() && from != to {
+            let slot = self.slots.remove(from);
+            self.slots.insert(to, slot);
+        }
+    }
+
+    pub fn rotate(&mut self, index: usize) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            slot.rotation = (slot.rotation + 90) % 360;
+        }
+    }
+
+    /// Insert a blank page after the given slot, sized like that slot's page
+    pub fn insert_blank(&mut self, after: usize) {
+        let size = match self.slots.get(after).map(|s| &s.source) {
+            Some(PageSource::Original(index)) => self.page_sizes.get(*index).copied(),
+            Some(PageSource::Blank { size, .. }) => Some(*size),
+            None => None,
+        }.unwrap_or((612.0, 792.0));
+        let index = (after + 1).min(self.slots.len());
+        self.slots.insert(index, PageSlot {
+            source: PageSource::Blank { note: String::new(), size },
+            rotation: 0,
+        });
+    }
+
+    pub fn delete(&mut self, index: usize) {
+        if index < self.slots.len() {
+            self.slots.remove(index);
+        }
+    }
+
+    /// True if writing out would change nothing
+    pub fn is_unchanged(&self, page_count: usize) -> bool {
+        self.slots.len() == page_count
+            && self.slots.iter().enumerate().all(|(i, s)| s.source_index() == Some(i) && s.rotation == 0)
+    }
+
+    /// Write the organized pages to a new PDF
+    pub fn write_pdf(&self, pdfium: &Pdfium, source_bytes: &[u8], password: Option<&str>, out: &Path, options: &PdfOutput) -> Result<()> {
+        if self.slots.is_empty() {
+            return Err(anyhow!("Cannot write a PDF with no pages"));
+        }
+        let source = pdfium.load_pdf_from_byte_slice(source_bytes, password)?;
+        let mut output = pdfium.create_new_pdf()?;
+
+        let font = output.fonts_mut().helvetica();
+
+        for (dest_index, slot) in self.slots.iter().enumerate() {
+            match &slot.source {
+                PageSource::Original(index) => {
+                    output.pages_mut().copy_page_from_document(&source, *index as u16, dest_index as u16)?;
+                }
+                PageSource::Blank { note, size } => {
+                    let paper = PdfPagePaperSize::from_points(PdfPoints::new(size.0), PdfPoints::new(size.1));
+                    let mut page = output.pages_mut().create_page_at_index(paper, dest_index as u16)?;
+                    for (line_index, line) in note_lines(note).iter().enumerate() {
+                        let y = size.1 - NOTE_MARGIN - line_index as f32 * NOTE_FONT_SIZE * 1.4;
+                        page.objects_mut().create_text_object(
+                            PdfPoints::new(NOTE_MARGIN),
+                            PdfPoints::new(y),
+                            line,
+                            font,
+                            PdfPoints::new(NOTE_FONT_SIZE),
+                        )?;
+                    }
+                }
+            }
+            if slot.rotation != 0 {
+                let mut page = output.pages().get(dest_index as u16)?;
+                let current = page.rotation().map(rotation_degrees).unwrap_or(0);
+                page.set_rotation(degrees_rotation((current + slot.rotation) % 360));
+            }
+        }
+
+        pdf_output::save(&output, out, options)
+    }
+
+    /// Rewrite an extraction so its pages and items follow the new page order
+    pub fn remap_extraction(&self, data: &Value) -> Value {
+        let mut result = data.clone();
+        let source_pages = data.get("pages").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        let source_items = data.get("items").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+
+        let mut pages = Vec::new();
+        let mut items = Vec::new();
+        for (dest_index, slot) in self.slots.iter().enumerate() {
+            let source_index = match &slot.source {
+                PageSource::Original(index) => *index,
+                PageSource::Blank { note, size } => {
+                    // Synthetic page: its note lines become items so they show up in exports
+                    pages.push(json!({
+                        "page_number": dest_index + 1,
+                        "width": size.0,
+                        "height": size.1,
+                        "synthetic": true,
+                        "note": note,
+                    }));
+                    for (line_index, line) in note_lines(note).iter().enumerate() {
+                        let top = (NOTE_MARGIN + line_index as f32 * NOTE_FONT_SIZE * 1.4 - NOTE_FONT_SIZE) as f64;
+                        let width = (size.0 - 2.0 * NOTE_MARGIN) as f64;
+                        items.push(json!({
+                            "type": "TextItem",
+                            "content": line,
+                            "bbox": {
+                                "left": NOTE_MARGIN,
+                                "top": top,
+                                "right": NOTE_MARGIN as f64 + width,
+                                "bottom": top + NOTE_FONT_SIZE as f64,
+                                "width": width,
+                                "height": NOTE_FONT_SIZE,
+                                "coord_origin": "TOPLEFT",
+                            },
+                            "page": dest_index + 1,
+                            "synthetic": true,
+                            "attributes": { "style": { "font_size": NOTE_FONT_SIZE } },
+                        }));
+                    }
+                    continue;
+                }
+            };
+            let mut page = source_pages.get(source_index).cloned().unwrap_or_else(|| json!({}));
+            let width = page.get("width").and_then(|v| v.as_f64()).unwrap_or(612.0);
+            let height = page.get("height").and_then(|v| v.as_f64()).unwrap_or(792.0);
+            page["page_number"] = json!(dest_index + 1);
+            if slot.rotation % 180 == 90 {
+                page["width"] = json!(height);
+                page["height"] = json!(width);
+            }
+            if slot.rotation != 0 {
+                // Column boundaries no longer apply to a rotated page
+                if let Some(page) = page.as_object_mut() {
+                    page.remove("columns");
+                    page.remove("column_boundaries");
+                }
+            }
+            pages.push(page);
+
+            for item in source_items.iter()
+                .filter(|item| item.get("page").and_then(|v| v.as_u64()) == Some(source_index as u64 + 1))
+            {
+                let mut item = item.clone();
+                item["page"] = json!(dest_index + 1);
+                if slot.rotation != 0 {
+                    if let Some(bbox) = item.get("bbox").cloned() {
+                        item["bbox"] = rotate_bbox(&bbox, width, height, slot.rotation);
+                    }
+                }
+                items.push(item);
+            }
+        }
+
+        for (index, item) in items.iter_mut().enumerate() {
+            item["index"] = json!(index);
+        }
+        result["pages"] = json!(pages);
+        result["items"] = json!(items);
+        result
+    }
+
+    /// Where a source page ends up in the new order; None if it was deleted
+    pub fn remap_page(&self, page: usize) -> Option<usize> {
+        self.slots.iter().position(|s| s.source_index() == Some(page))
+    }
+
+    /// Map an item ID (`item_<page>_<x>_<y>`) onto the new page order.
+    /// Items on rotated or deleted pages get new coordinates or vanish, so they map to None.
+    pub fn remap_item_id(&self, item_id: &str) -> Option<String> {
+        let rest = item_id.strip_prefix("item_")?;
+        let (page, coords) = rest.split_once('_')?;
+        let page: usize = page.parse().ok()?;
+        let dest_index = self.slots.iter()
+            .position(|s| s.source_index() == Some(page) && s.rotation == 0)?;
+        Some(format!("item_{}_{}", dest_index, coords))
+    }
+}
+
+/// Non-empty note lines, in order
+fn note_lines(note: &str) -> Vec<&str> {
+    note.lines().filter(|l| !l.trim().is_empty()).collect()
+}
+
+/// Rotate a bbox clockwise on a page of the given (unrotated) size, returning a TOPLEFT bbox
+fn rotate_bbox(bbox: &Value, page_width: f64, page_height: f64, rotation: u16) -> Value {
+    let get = |key: &str| bbox.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+    let (left, width, height) = (get("left"), get("width"), get("height").abs());
+    let top = if bbox.get("coord_origin").and_then(|v| v.as_str()).unwrap_or("TOPLEFT").contains("BOTTOMLEFT") {
+        page_height - get("top")
+    } else {
+        get("top")
+    };
+
+    let (left, top, width, height) = match rotation {
+        90 => (page_height - top - height, left, height, width),
+        180 => (page_width - left - width, page_height - top - height, width, height),
+        270 => (top, page_width - left - width, height, width),
+        _ => (left, top, width, height),
+    };
+
+    json!({
+        "left": left,
+        "top": top,
+        "right": left + width,
+        "bottom": top + height,
+        "width": width,
+        "height": height,
+        "coord_origin": "TOPLEFT",
+    })
+}
+
+fn rotation_degrees(rotation: PdfPageRenderRotation) -> u16 {
+    match rotation {
+        PdfPageRenderRotation::None => 0,
+        PdfPageRenderRotation::Degrees90 => 90,
+        PdfPageRenderRotation::Degrees180 => 180,
+        PdfPageRenderRotation::Degrees270 => 270,
+    }
+}
+
+fn degrees_rotation(degrees: u16) -> PdfPageRenderRotation {
+    match degrees {
+        90 => PdfPageRenderRotation::Degrees90,
+        180 => PdfPageRenderRotation::Degrees180,
+        270 => PdfPageRenderRotation::Degrees270,
+        _ => PdfPageRenderRotation::None,
+    }
+}
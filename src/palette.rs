@@ -0,0 +1,162 @@
+//! Highlight colors
+//!
+//! Everything the canvases paint to mark state (search matches, selection,
+//! hover, item types, annotations, collaborators' edits, overflowing text,
+//! differences from a transcript and personal data)
+//! takes its color from a `Palette`. Built-in palettes include ones that stay distinguishable
+//! with color vision deficiencies; any color can then be changed by hand and
+//! is saved with the settings.
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// Unmultiplied sRGBA, as stored in the settings file
+pub type Rgba = [u8; 4];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    /// Background behind search matches
+    pub search_highlight: Rgba,
+    /// Text of search matches
+    pub search_text: Rgba,
+    /// Background behind selected items
+    pub selection: Rgba,
+    /// Outline around the hovered item, the marquee and column lines
+    pub hover: Rgba,
+    pub text: Rgba,
+    pub form_label: Rgba,
+    pub form_field: Rgba,
+    pub checkbox: Rgba,
+    /// Marker on items carrying an annotation
+    pub annotation: Rgba,
+    /// Outline and name on items a collaborator is editing
+    pub peer_editing: Rgba,
+    /// Marker on items whose text overflows their box
+    #[serde(default = "default_overflow")]
+    pub overflow: Rgba,
+    /// Underline on items that differ from a reference transcript
+    #[serde(default = "default_divergent")]
+    pub divergent: Rgba,
+    /// Outline around items holding personal data
+    #[serde(default = "default_pii")]
+    pub pii: Rgba,
+}
+
+fn default_overflow() -> Rgba {
+    BuiltinPalette::Standard.palette().overflow
+}
+
+fn default_divergent() -> Rgba {
+    BuiltinPalette::Standard.palette().divergent
+}
+
+fn default_pii() -> Rgba {
+    BuiltinPalette::Standard.palette().pii
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        BuiltinPalette::Standard.palette()
+    }
+}
+
+impl Palette {
+    pub fn color(rgba: Rgba) -> Color32 {
+        Color32::from_rgba_unmultiplied(rgba[0], rgba[1], rgba[2], rgba[3])
+    }
+
+    /// The same color with a different opacity
+    pub fn with_alpha(rgba: Rgba, alpha: u8) -> Color32 {
+        Color32::from_rgba_unmultiplied(rgba[0], rgba[1], rgba[2], alpha)
+    }
+
+    /// Text color for an item of this type
+    pub fn item_type_color(&self, item_type: &crate::types::ItemType) -> Color32 {
+        use crate::types::ItemType;
+        Self::color(match item_type {
+            ItemType::FormLabel => self.form_label,
+            ItemType::FormField => self.form_field,
+            ItemType::Checkbox => self.checkbox,
+            _ => self.text,
+        })
+    }
+
+    /// The built-in palette these colors match, if they haven't been customized
+    pub fn builtin(&self) -> Option<BuiltinPalette> {
+        BuiltinPalette::ALL.into_iter().find(|builtin| builtin.palette() == *self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinPalette {
+    Standard,
+    /// Okabe-Ito colors; no red/green pairs
+    Deuteranopia,
+    HighContrast,
+}
+
+impl BuiltinPalette {
+    pub const ALL: [BuiltinPalette; 3] = [
+        BuiltinPalette::Standard,
+        BuiltinPalette::Deuteranopia,
+        BuiltinPalette::HighContrast,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuiltinPalette::Standard => "Standard",
+            BuiltinPalette::Deuteranopia => "Deuteranopia-safe",
+            BuiltinPalette::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            BuiltinPalette::Standard => Palette {
+                search_highlight: [255, 255, 0, 60],
+                search_text: [255, 165, 0, 255],
+                selection: [59, 130, 246, 40],
+                hover: [59, 130, 246, 255],
+                text: [20, 20, 20, 255],
+                form_label: [0, 0, 139, 255],
+                form_field: [60, 60, 60, 255],
+                checkbox: [40, 40, 40, 255],
+                annotation: [234, 179, 8, 255],
+                peer_editing: [168, 85, 247, 255],
+                overflow: [234, 88, 12, 255],
+                divergent: [220, 38, 38, 255],
+                pii: [190, 18, 60, 255],
+            },
+            BuiltinPalette::Deuteranopia => Palette {
+                search_highlight: [240, 228, 66, 90],
+                search_text: [213, 94, 0, 255],
+                selection: [0, 114, 178, 50],
+                hover: [0, 114, 178, 255],
+                text: [20, 20, 20, 255],
+                form_label: [0, 114, 178, 255],
+                form_field: [60, 60, 60, 255],
+                checkbox: [40, 40, 40, 255],
+                annotation: [230, 159, 0, 255],
+                peer_editing: [204, 121, 167, 255],
+                overflow: [213, 94, 0, 255],
+                divergent: [204, 121, 167, 255],
+                pii: [0, 158, 115, 255],
+            },
+            BuiltinPalette::HighContrast => Palette {
+                search_highlight: [255, 255, 0, 160],
+                search_text: [0, 0, 0, 255],
+                selection: [0, 0, 255, 70],
+                hover: [0, 0, 0, 255],
+                text: [0, 0, 0, 255],
+                form_label: [0, 0, 160, 255],
+                form_field: [0, 0, 0, 255],
+                checkbox: [0, 0, 0, 255],
+                annotation: [200, 0, 0, 255],
+                peer_editing: [128, 0, 128, 255],
+                overflow: [200, 0, 0, 255],
+                divergent: [255, 0, 255, 255],
+                pii: [255, 0, 0, 255],
+            },
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Remembered passwords for encrypted PDFs
+//!
+//! Opt-in from Settings: the password that unlocked a PDF is kept in the OS
+//! keyring (Keychain, Credential Manager or the Secret Service) rather than in
+//! any of the app's own files, so reopening the document doesn't ask again.
+//! Entries are keyed by a hash of the file's contents, so renamed or moved
+//! copies are recognized too.
+
+use anyhow::{anyhow, Result};
+use keyring::Entry;
+use sha2::{Digest, Sha256};
+
+/// Keyring service the entries are filed under
+pub const SERVICE: &str = "chonker3";
+
+/// Keyring account name for a PDF with these contents
+pub fn account(pdf_bytes: &[u8]) -> String {
+    let digest = Sha256::digest(pdf_bytes);
+    let hex: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("pdf-{}", hex)
+}
+
+fn entry(pdf_bytes: &[u8]) -> Result<Entry> {
+    Entry::new(SERVICE, &account(pdf_bytes)).map_err(|e| anyhow!("Can't use the keyring: {}", e))
+}
+
+/// The password remembered for this PDF, if any
+pub fn remembered(pdf_bytes: &[u8]) -> Option<String> {
+    entry(pdf_bytes).ok()?.get_password().ok()
+}
+
+pub fn remember(pdf_bytes: &[u8], password: &str) -> Result<()> {
+    entry(pdf_bytes)?.set_password(password)
+        .map_err(|e| anyhow!("Couldn't save the password in the keyring: {}", e))
+}
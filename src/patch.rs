@@ -0,0 +1,100 @@
+//! Portable edit patches
+//!
+//! A patch holds only the user's deltas on top of an extraction (text overrides,
+//! offsets, corrected boxes, deletions, type changes, annotations) keyed by item ID, plus page
+//! bookmarks and notes, named regions, table stitching decisions and the
+//! comment threads on items, so corrections can be shared and applied to the same PDF's extraction on another machine.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::comments::CommentThread;
+use crate::regions::NamedRegion;
+use crate::types::{BoundingBox, ItemType};
+
+pub const PATCH_FORMAT: &str = "chonker3-patch";
+pub const PATCH_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditPatch {
+    pub format: String,
+    pub version: u32,
+    /// File name of the PDF the edits were made against
+    pub source_file: Option<String>,
+    pub created: String,
+    #[serde(default)]
+    pub text_overrides: BTreeMap<String, String>,
+    #[serde(default)]
+    pub offsets: BTreeMap<String, (f32, f32)>,
+    /// Boxes replacing the extracted ones (TOPLEFT), e.g. snapped to the PDF's glyphs
+    #[serde(default)]
+    pub boxes: BTreeMap<String, BoundingBox>,
+    #[serde(default)]
+    pub deletions: BTreeSet<String>,
+    #[serde(default)]
+    pub type_changes: BTreeMap<String, ItemType>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    /// Bookmarked pages, by zero-based index
+    #[serde(default)]
+    pub bookmarks: BTreeSet<usize>,
+    /// Notes on whole pages, by zero-based index
+    #[serde(default)]
+    pub page_notes: BTreeMap<usize, String>,
+    /// Discussion of items; not counted as edits
+    #[serde(default)]
+    pub comments: BTreeMap<String, CommentThread>,
+    /// Accepted (true) or rejected table continuations, by the continuing
+    /// table's ID; see `tables`
+    #[serde(default)]
+    pub table_stitches: BTreeMap<String, bool>,
+    /// Named regions drawn on pages; see `regions`
+    #[serde(default)]
+    pub regions: Vec<NamedRegion>,
+}
+
+impl EditPatch {
+    pub fn new(source_file: Option<String>) -> Self {
+        Self {
+            format: PATCH_FORMAT.to_string(),
+            version: PATCH_VERSION,
+            source_file,
+            created: chrono::Local::now().to_rfc3339(),
+            ..Default::default()
+        }
+    }
+
+    /// Total number of individual edits in the patch
+    pub fn edit_count(&self) -> usize {
+        self.text_overrides.len()
+            + self.offsets.len()
+            + self.boxes.len()
+            + self.deletions.len()
+            + self.type_changes.len()
+            + self.annotations.len()
+            + self.bookmarks.len()
+            + self.page_notes.len()
+            + self.table_stitches.len()
+            + self.regions.len()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let patch: EditPatch = serde_json::from_str(&contents).context("Invalid patch file")?;
+
+        if patch.format != PATCH_FORMAT {
+            return Err(anyhow!("Not a Chonker3 patch file"));
+        }
+        if patch.version > PATCH_VERSION {
+            return Err(anyhow!("Patch version {} is newer than supported ({})", patch.version, PATCH_VERSION));
+        }
+        Ok(patch)
+    }
+}
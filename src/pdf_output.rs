@@ -0,0 +1,159 @@
+//! Size options for exported PDFs
+//!
+//! Scans make big PDFs. Before an export is written its images can be
+//! downsampled to a resolution cap and re-encoded as JPEG, and the written
+//! file can be linearized ("fast web view") so viewers show the first page
+//! before the rest has loaded. pdfium can't linearize, so that step runs
+//! `qpdf` when it is installed.
+
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use anyhow::{anyhow, bail, Result};
+use image::codecs::jpeg::JpegEncoder;
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+
+fn default_jpeg_quality() -> u8 {
+    75
+}
+
+fn default_max_dpi() -> u32 {
+    150
+}
+
+/// How exported PDFs are written
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PdfOutput {
+    /// Re-encode images as JPEG, downsampled to `max_dpi`
+    #[serde(default)]
+    pub recompress_images: bool,
+    /// JPEG quality, 1-100
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+    /// Images placed at a higher resolution than this are scaled down
+    #[serde(default = "default_max_dpi")]
+    pub max_dpi: u32,
+    /// Rewrite the file for fast web view; needs qpdf
+    #[serde(default)]
+    pub linearize: bool,
+}
+
+impl Default for PdfOutput {
+    fn default() -> Self {
+        Self {
+            recompress_images: false,
+            jpeg_quality: default_jpeg_quality(),
+            max_dpi: default_max_dpi(),
+            linearize: false,
+        }
+    }
+}
+
+/// Pixel size of an image of `pixels` placed at `points` on the page once
+/// capped at `max_dpi`; images at or below the cap keep their size
+pub fn target_pixels(pixels: (u32, u32), points: (f32, f32), max_dpi: u32) -> (u32, u32) {
+    let inches = (points.0.abs() / 72.0, points.1.abs() / 72.0);
+    if inches.0 <= 0.0 || inches.1 <= 0.0 || max_dpi == 0 {
+        return pixels;
+    }
+    let dpi = (pixels.0 as f32 / inches.0).max(pixels.1 as f32 / inches.1);
+    if dpi <= max_dpi as f32 {
+        return pixels;
+    }
+    let scale = max_dpi as f32 / dpi;
+    (
+        ((pixels.0 as f32 * scale).round() as u32).max(1),
+        ((pixels.1 as f32 * scale).round() as u32).max(1),
+    )
+}
+
+/// Apply the options to the document and save it to `path`
+pub fn save(document: &PdfDocument, path: &Path, output: &PdfOutput) -> Result<()> {
+    if output.recompress_images {
+        recompress_images(document, output)?;
+    }
+    document.save_to_file(path).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    if output.linearize {
+        linearize(path)?;
+    }
+    Ok(())
+}
+
+/// A JPEG of the image, resized to `size`, unless it is better left alone:
+/// bilevel scans compress better as they are, and JPEGs that don't need
+/// downsampling would only lose quality
+fn recompressed(image: &PdfPageImageObject, size: (u32, u32), output: &PdfOutput) -> Result<Option<Vec<u8>>> {
+    let pixels = (image.width()? as u32, image.height()? as u32);
+    let is_jpeg = image.filters().iter().any(|filter| filter.name() == "DCTDecode");
+    if image.bits_per_pixel()? == 1 || (is_jpeg && size == pixels) {
+        return Ok(None);
+    }
+    let bitmap = image.get_raw_bitmap()?;
+    let rgba = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+        .ok_or_else(|| anyhow!("Image has an unexpected size"))?;
+    let mut resized = image::DynamicImage::ImageRgba8(rgba);
+    if size != pixels {
+        resized = resized.resize_exact(size.0, size.1, image::imageops::FilterType::Triangle);
+    }
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, output.jpeg_quality.clamp(1, 100))
+        .encode_image(&image::DynamicImage::ImageRgb8(resized.to_rgb8()))?;
+    Ok(Some(jpeg))
+}
+
+/// Re-encode the document's images as JPEG; returns how many were replaced
+pub fn recompress_images(document: &PdfDocument, output: &PdfOutput) -> Result<usize> {
+    let mut replaced = 0;
+    for mut page in document.pages().iter() {
+        let mut replacements = Vec::new();
+        for (index, object) in page.objects().iter().enumerate() {
+            let Some(image) = object.as_image_object() else { continue };
+            let bounds = object.bounds()?;
+            let pixels = (image.width()? as u32, image.height()? as u32);
+            let size = target_pixels(pixels, (bounds.width().value, bounds.height().value), output.max_dpi);
+            if let Some(jpeg) = recompressed(image, size, output)? {
+                replacements.push((index, jpeg));
+            }
+        }
+        if replacements.is_empty() {
+            continue;
+        }
+        // Objects can only be appended, so take each one off the front and
+        // put it (or its replacement) back at the end to keep their order
+        page.set_content_regeneration_strategy(PdfPageContentRegenerationStrategy::Manual);
+        let count = page.objects().len();
+        let mut replacements = replacements.into_iter().peekable();
+        for index in 0..count {
+            let mut object = page.objects_mut().remove_object_at_index(0)?;
+            if replacements.peek().is_some_and(|(next, _)| *next == index) {
+                let (_, jpeg) = replacements.next().expect("peeked");
+                let mut image = PdfPageImageObject::new_from_jpeg_reader(document, Cursor::new(jpeg))?;
+                image.apply_matrix(object.matrix()?)?;
+                object = PdfPageObject::Image(image);
+                replaced += 1;
+            }
+            page.objects_mut().add_object(object)?;
+        }
+        page.regenerate_content()?;
+    }
+    Ok(replaced)
+}
+
+/// Whether qpdf is on the PATH to linearize with; checked once
+pub fn linearizer_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| Command::new("qpdf").arg("--version").output().is_ok_and(|output| output.status.success()))
+}
+
+/// Linearize the PDF at `path` in place with qpdf
+pub fn linearize(path: &Path) -> Result<()> {
+    let output = Command::new("qpdf").arg("--linearize").arg("--replace-input").arg(path).output()
+        .map_err(|e| anyhow!("Saved {}, but couldn't linearize it: qpdf is not available ({})", path.display(), e))?;
+    // Exit code 3 means it succeeded with warnings
+    if !output.status.success() && output.status.code() != Some(3) {
+        bail!("Saved {}, but couldn't linearize it: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
@@ -0,0 +1,142 @@
+//! First-run pdfium download
+//!
+//! Fetches the prebuilt pdfium for this OS and architecture from the
+//! pdfium-binaries project into the app data directory. The release is pinned
+//! to the one pdfium-render's bindings are built for, and the archive is
+//! checked against the SHA-256 recorded in `packaging/pdfium-binaries.sha256`
+//! before anything is unpacked; nothing the download server says is trusted.
+//! `packaging/pin-pdfium.sh` re-records the checksums after a version bump.
+//! Platforms without a recorded checksum get no download offer.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::checksum::sha256_hex;
+use crate::jobs::JobHandle;
+
+/// pdfium-binaries release tag; pdfium-render's default bindings target it
+pub const PDFIUM_RELEASE: &str = "chromium/7543";
+
+/// `sha256sum` output for the pinned release's archives
+const PINNED_CHECKSUMS: &str = include_str!("../packaging/pdfium-binaries.sha256");
+
+/// Release archive for this platform, e.g. `pdfium-linux-x64.tgz`
+pub fn platform_asset() -> Option<String> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "mac",
+        "windows" => "win",
+        _ => return None,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        "x86" => "x86",
+        _ => return None,
+    };
+    Some(format!("pdfium-{}-{}.tgz", os, arch))
+}
+
+/// Where the library sits inside the release archive
+fn archive_library_path() -> &'static str {
+    match std::env::consts::OS {
+        "windows" => "bin/pdfium.dll",
+        "macos" => "lib/libpdfium.dylib",
+        _ => "lib/libpdfium.so",
+    }
+}
+
+pub fn install_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("chonker3").join("pdfium"))
+}
+
+/// The downloaded library, if a previous bootstrap installed one
+pub fn installed_library() -> Option<PathBuf> {
+    let file_name = Path::new(archive_library_path()).file_name()?;
+    let path = install_dir()?.join(file_name);
+    path.exists().then_some(path)
+}
+
+/// Recorded SHA-256 of a release archive, from `sha256sum` output
+pub fn pinned_checksum<'a>(checksums: &'a str, asset: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (hex, name) = line.split_once(char::is_whitespace)?;
+        // sha256sum marks binary mode with a '*' before the name
+        (name.trim_start().trim_start_matches('*') == asset).then_some(hex)
+    })
+}
+
+/// This platform's archive and its pinned checksum, if it can be downloaded
+pub fn pinned_asset() -> Option<(String, &'static str)> {
+    let asset = platform_asset()?;
+    let checksum = pinned_checksum(PINNED_CHECKSUMS, &asset)?;
+    Some((asset, checksum))
+}
+
+/// Download URL of an archive in the pinned release
+pub fn release_url(asset: &str) -> String {
+    format!(
+        "https://github.com/bblanchon/pdfium-binaries/releases/download/{}/{}",
+        PDFIUM_RELEASE.replace('/', "%2F"),
+        asset
+    )
+}
+
+/// Check bytes against a hex SHA-256
+pub fn verify_sha256(bytes: &[u8], expected: &str) -> Result<()> {
+    let actual = sha256_hex(bytes);
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("Checksum mismatch: expected {}, got {}", expected, actual);
+    }
+    Ok(())
+}
+
+/// Unpack the platform library from a .tgz into `dest`; returns its path
+pub fn extract_library(archive: &[u8], dest: &Path) -> Result<PathBuf> {
+    let wanted = Path::new(archive_library_path());
+    let file_name = wanted.file_name().ok_or_else(|| anyhow!("Bad library path"))?;
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in tar.entries().context("Invalid archive")? {
+        let mut entry = entry.context("Invalid archive")?;
+        let path = entry.path().context("Invalid archive")?.into_owned();
+        // Entries may be prefixed with "./"
+        if path.components().filter(|c| !matches!(c, std::path::Component::CurDir)).eq(wanted.components()) {
+            std::fs::create_dir_all(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+            let out = dest.join(file_name);
+            entry.unpack(&out).with_context(|| format!("Failed to write {}", out.display()))?;
+            return Ok(out);
+        }
+    }
+    bail!("{} not found in archive", archive_library_path())
+}
+
+fn get(url: &str) -> Result<ureq::Response> {
+    ureq::get(url)
+        .set("User-Agent", "chonker3")
+        .call()
+        .map_err(|e| anyhow!("Request to {} failed: {}", url, e))
+}
+
+/// Download, verify and install pdfium; returns the library path
+pub fn download(job: &JobHandle) -> Result<PathBuf> {
+    let (asset_name, checksum) = pinned_asset().ok_or_else(|| {
+        anyhow!("No pinned pdfium build for {}/{}", std::env::consts::OS, std::env::consts::ARCH)
+    })?;
+    let dest = install_dir().ok_or_else(|| anyhow!("No app data directory"))?;
+    job.set_total(3);
+
+    job.begin_step(format!("Downloading {} ({})", asset_name, PDFIUM_RELEASE))?;
+    let mut archive = Vec::new();
+    get(&release_url(&asset_name))?.into_reader().read_to_end(&mut archive).context("Download interrupted")?;
+    job.finish_step();
+
+    job.begin_step("Verifying checksum")?;
+    verify_sha256(&archive, checksum)?;
+    job.finish_step();
+
+    job.begin_step("Installing")?;
+    let library = extract_library(&archive, &dest)?;
+    job.finish_step();
+    Ok(library)
+}
@@ -0,0 +1,279 @@
+//! Personal data detection and masking
+//!
+//! Finds PII in the extracted text: US social security numbers, payment card
+//! numbers (Luhn-checked, so order and account numbers mostly don't count),
+//! email addresses, names from a dictionary the user keeps, and any patterns
+//! they add. Hits are highlighted on both panels, can be masked in the
+//! extraction's text, and become redaction boxes: the box of each item
+//! holding a hit, written as JSON for a redaction step to black out on the
+//! PDF. Positions within an item aren't known, so a box covers the whole
+//! item; redacting too much is the safe side.
+
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::types::{BoundingBox, DocumentItem};
+
+pub const REDACTIONS_FORMAT: &str = "chonker3-redactions";
+
+/// Drawn in place of each masked character
+pub const MASK: char = '█';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PiiKind {
+    Ssn,
+    CreditCard,
+    Email,
+    Name,
+    /// One of the user's patterns
+    Custom,
+}
+
+impl PiiKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PiiKind::Ssn => "SSN",
+            PiiKind::CreditCard => "Card number",
+            PiiKind::Email => "Email",
+            PiiKind::Name => "Name",
+            PiiKind::Custom => "Pattern",
+        }
+    }
+}
+
+/// A regular expression the user looks for, with the label its hits get
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomPattern {
+    pub label: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PiiSettings {
+    pub ssn: bool,
+    pub credit_cards: bool,
+    pub emails: bool,
+    /// Names to find, as whole words in any case
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub patterns: Vec<CustomPattern>,
+}
+
+impl Default for PiiSettings {
+    fn default() -> Self {
+        Self { ssn: true, credit_cards: true, emails: true, names: Vec::new(), patterns: Vec::new() }
+    }
+}
+
+/// Personal data found in an item's text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiiHit {
+    pub item_id: String,
+    pub page: usize,
+    pub kind: PiiKind,
+    /// The kind's label, or the custom pattern's
+    pub label: String,
+    pub text: String,
+    /// Byte range in the item's text
+    pub range: Range<usize>,
+}
+
+/// A box to black out on the PDF
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionBox {
+    /// Zero-based
+    pub page: usize,
+    /// TOPLEFT, in PDF points
+    pub bbox: BoundingBox,
+    pub item_id: String,
+    /// Labels of the hits in the item
+    pub reasons: Vec<String>,
+}
+
+/// Whether a card number's digits pass the Luhn check
+pub fn luhn(digits: &str) -> bool {
+    let mut sum = 0;
+    for (index, c) in digits.chars().rev().enumerate() {
+        let Some(mut digit) = c.to_digit(10) else { return false };
+        if index % 2 == 1 {
+            digit *= 2;
+            if digit > 9 {
+                digit -= 9;
+            }
+        }
+        sum += digit;
+    }
+    !digits.is_empty() && sum % 10 == 0
+}
+
+/// Digits a payment card number can have
+const CARD_LENGTHS: RangeInclusive<usize> = 13..=19;
+
+/// Where the card number is in a run of digit groups: the longest span of
+/// whole groups with a card's length that passes the Luhn check. The run can
+/// carry a date, an amount or a reference on either side of the number.
+fn card_number(run: &str) -> Option<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for (index, c) in run.char_indices() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        match groups.last_mut() {
+            Some(group) if group.end == index => group.end = index + 1,
+            _ => groups.push(index..index + 1),
+        }
+    }
+    let mut best: Option<Range<usize>> = None;
+    for start in 0..groups.len() {
+        let mut digits = String::new();
+        for group in &groups[start..] {
+            digits.push_str(&run[group.clone()]);
+            let span = groups[start].start..group.end;
+            if CARD_LENGTHS.contains(&digits.len()) && luhn(&digits) && best.as_ref().is_none_or(|best| span.len() > best.len()) {
+                best = Some(span);
+            }
+        }
+    }
+    best
+}
+
+/// Whether the parts of an SSN could have been issued
+fn valid_ssn(area: &str, group: &str, serial: &str) -> bool {
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+/// The detector for some settings, with its expressions compiled
+#[derive(Debug, Clone)]
+pub struct PiiDetector {
+    matchers: Vec<(PiiKind, String, Regex)>,
+}
+
+impl PiiDetector {
+    /// Fails on a custom pattern that isn't a valid regular expression
+    pub fn new(settings: &PiiSettings) -> Result<Self> {
+        let mut matchers = Vec::new();
+        let builtin = [
+            (settings.ssn, PiiKind::Ssn, r"\b(\d{3})[- ](\d{2})[- ](\d{4})\b"),
+            // Runs of up to two cards' worth of digits; `card_number` picks the card out
+            (settings.credit_cards, PiiKind::CreditCard, r"\b\d(?:[ -]?\d){12,37}\b"),
+            (settings.emails, PiiKind::Email, r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b"),
+        ];
+        for (enabled, kind, pattern) in builtin {
+            if enabled {
+                matchers.push((kind, kind.label().to_string(), Regex::new(pattern)?));
+            }
+        }
+        let names: Vec<String> = settings.names.iter()
+            .map(|name| name.split_whitespace().map(regex::escape).collect::<Vec<_>>().join(r"\s+"))
+            .filter(|name| !name.is_empty())
+            .collect();
+        if !names.is_empty() {
+            let pattern = format!(r"(?i)\b(?:{})\b", names.join("|"));
+            matchers.push((PiiKind::Name, PiiKind::Name.label().to_string(), Regex::new(&pattern)?));
+        }
+        for custom in settings.patterns.iter().filter(|custom| !custom.pattern.trim().is_empty()) {
+            let regex = Regex::new(&custom.pattern)
+                .with_context(|| format!("Invalid pattern for \"{}\"", custom.label))?;
+            let label = if custom.label.trim().is_empty() { PiiKind::Custom.label().to_string() } else { custom.label.clone() };
+            matchers.push((PiiKind::Custom, label, regex));
+        }
+        Ok(Self { matchers })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    /// Hits in a text, in order; where two overlap the earlier, then longer, one wins
+    pub fn find(&self, text: &str) -> Vec<(PiiKind, String, Range<usize>)> {
+        let mut found: Vec<(PiiKind, String, Range<usize>)> = Vec::new();
+        for (kind, label, regex) in &self.matchers {
+            for captures in regex.captures_iter(text) {
+                let Some(whole) = captures.get(0) else { continue };
+                let range = match kind {
+                    PiiKind::Ssn => valid_ssn(&captures[1], &captures[2], &captures[3]).then(|| whole.range()),
+                    PiiKind::CreditCard => card_number(whole.as_str()).map(|card| whole.start() + card.start..whole.start() + card.end),
+                    _ => Some(whole.range()),
+                };
+                if let Some(range) = range {
+                    found.push((*kind, label.clone(), range));
+                }
+            }
+        }
+        found.sort_by_key(|(.., range)| (range.start, std::cmp::Reverse(range.end)));
+        let mut kept: Vec<(PiiKind, String, Range<usize>)> = Vec::new();
+        for hit in found {
+            if kept.last().is_none_or(|last| hit.2.start >= last.2.end) {
+                kept.push(hit);
+            }
+        }
+        kept
+    }
+
+    /// Hits in the items of a page
+    pub fn scan(&self, page: usize, items: &[DocumentItem]) -> Vec<PiiHit> {
+        items.iter()
+            .flat_map(|item| {
+                self.find(&item.content).into_iter().map(move |(kind, label, range)| PiiHit {
+                    item_id: item.id.clone(),
+                    page,
+                    kind,
+                    label,
+                    text: item.content[range.clone()].to_string(),
+                    range,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A text with the characters of each hit masked, keeping spaces and
+/// separators so the layout and the kind of value stay recognizable
+pub fn mask(text: &str, ranges: &[Range<usize>]) -> String {
+    text.char_indices()
+        .map(|(index, c)| {
+            let hidden = ranges.iter().any(|range| range.contains(&index));
+            if hidden && c.is_alphanumeric() { MASK } else { c }
+        })
+        .collect()
+}
+
+/// One box per item holding hits, in the order of the hits
+pub fn redaction_boxes(hits: &[PiiHit], items: &[DocumentItem]) -> Vec<RedactionBox> {
+    let mut boxes: Vec<RedactionBox> = Vec::new();
+    for hit in hits {
+        if let Some(existing) = boxes.iter_mut().find(|b| b.item_id == hit.item_id) {
+            if !existing.reasons.contains(&hit.label) {
+                existing.reasons.push(hit.label.clone());
+            }
+            continue;
+        }
+        let Some(item) = items.iter().find(|item| item.id == hit.item_id) else { continue };
+        boxes.push(RedactionBox {
+            page: hit.page,
+            bbox: item.bbox.clone(),
+            item_id: hit.item_id.clone(),
+            reasons: vec![hit.label.clone()],
+        });
+    }
+    boxes
+}
+
+/// `{"format": ..., "source_file": ..., "redactions": [...]}`
+pub fn redactions_json(source_file: Option<&str>, boxes: &[RedactionBox]) -> Value {
+    json!({
+        "format": REDACTIONS_FORMAT,
+        "source_file": source_file,
+        "redactions": boxes,
+    })
+}
+
+pub fn write_redactions(path: &Path, source_file: Option<&str>, boxes: &[RedactionBox]) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(&redactions_json(source_file, boxes))?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,464 @@
+//! Batch export pipeline with a manifest
+//!
+//! Workspace documents are exported into a folder, each output laid out by a
+//! path template such as `{stem}.md` or `{stem}/{page}.md`; a template with
+//! `{page}` writes one file per page. A manifest (JSON or CSV) next to the
+//! outputs records every input with its checksum, page and item counts and
+//! the extractor that produced it, and every file written with its checksum.
+//! With a field mapping set up, the documents' named fields also go into one
+//! sheet, a row per document; with a webhook, each document is also posted
+//! to a URL (see `webhook`). With an upload location, every file written is
+//! also uploaded to an S3 bucket or WebDAV folder (see `remote`). Outputs
+//! are stamped with their source and provenance as the settings ask (see
+//! `stamp`).
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::export::{self, csv_field};
+use crate::extractor::ExtractedDocument;
+use crate::field_mapping::{DocumentFields, FieldMapping};
+use crate::jobs::JobHandle;
+use crate::normalize::NumberLocale;
+use crate::patch::EditPatch;
+use crate::checksum::sha256_hex;
+use crate::remote::Remote;
+use crate::stamp::{Stamp, StampMode, SIDECAR_SUFFIX};
+use crate::webhook::{self, Delivery, Webhook};
+use crate::workspace::WorkspaceDocument;
+
+/// Placeholders a layout template may use
+pub const PLACEHOLDERS: [&str; 2] = ["stem", "page"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputFormat {
+    Markdown,
+    /// The structured JSON export
+    Structured,
+    Csv,
+    /// One JSON object per item and line
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [OutputFormat::Markdown, OutputFormat::Structured, OutputFormat::Csv, OutputFormat::Jsonl];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "Markdown",
+            OutputFormat::Structured => "Structured JSON",
+            OutputFormat::Csv => "CSV",
+            OutputFormat::Jsonl => "Items JSONL",
+        }
+    }
+
+    /// The whole document, or only one (zero-based) page
+    pub fn render(&self, data: &Value, edits: &EditPatch, page: Option<usize>) -> Result<String> {
+        if *self == OutputFormat::Markdown {
+            return Ok(match page {
+                Some(page) => export::markdown_page(data, page, edits),
+                None => export::markdown_export(data, edits),
+            });
+        }
+        let mut structured = export::structured_export(data, edits, NumberLocale::Auto);
+        if let Some(page) = page {
+            if let Some(items) = structured["items"].as_array_mut() {
+                items.retain(|item| item["page"].as_u64() == Some(page as u64 + 1));
+            }
+            if let Some(pages) = structured["pages"].as_array_mut() {
+                *pages = pages.get(page).cloned().into_iter().collect();
+            }
+        }
+        Ok(match self {
+            OutputFormat::Csv => export::structured_csv(&structured),
+            OutputFormat::Jsonl => export::items_jsonl(&structured),
+            _ => serde_json::to_string_pretty(&structured)?,
+        })
+    }
+}
+
+/// One file (or one file per page) written for every document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineOutput {
+    pub format: OutputFormat,
+    /// Path relative to the export folder, e.g. `{stem}/{page}.md`
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManifestFormat {
+    #[default]
+    Json,
+    /// One row per output file
+    Csv,
+}
+
+impl ManifestFormat {
+    pub const ALL: [ManifestFormat; 2] = [ManifestFormat::Json, ManifestFormat::Csv];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ManifestFormat::Json => "JSON",
+            ManifestFormat::Csv => "CSV",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ManifestFormat::Json => "manifest.json",
+            ManifestFormat::Csv => "manifest.csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPipeline {
+    #[serde(default = "default_outputs")]
+    pub outputs: Vec<PipelineOutput>,
+    #[serde(default)]
+    pub manifest: ManifestFormat,
+    /// Columns of the per-document field sheet
+    #[serde(default)]
+    pub field_mapping: FieldMapping,
+    /// Where each document is posted after it's exported
+    #[serde(default)]
+    pub webhook: Webhook,
+    /// Name of the remote location the outputs are uploaded to
+    #[serde(default)]
+    pub upload_to: Option<String>,
+    /// The `upload_to` location with its secret, looked up for each run
+    #[serde(skip)]
+    pub remote: Option<Remote>,
+    /// How outputs are stamped; the app's setting, passed in for each run
+    #[serde(skip)]
+    pub stamping: StampMode,
+}
+
+fn default_outputs() -> Vec<PipelineOutput> {
+    vec![
+        PipelineOutput { format: OutputFormat::Markdown, template: "{stem}.md".to_string() },
+        PipelineOutput { format: OutputFormat::Structured, template: "{stem}.json".to_string() },
+    ]
+}
+
+impl Default for ExportPipeline {
+    fn default() -> Self {
+        Self {
+            outputs: default_outputs(),
+            manifest: ManifestFormat::default(),
+            field_mapping: FieldMapping::default(),
+            webhook: Webhook::default(),
+            upload_to: None,
+            remote: None,
+            stamping: StampMode::default(),
+        }
+    }
+}
+
+/// Whether a template writes one file per page
+pub fn is_per_page(template: &str) -> bool {
+    template.contains("{page}")
+}
+
+/// Fill in a layout template; `page` is zero-based and written one-based.
+/// The result must stay inside the export folder.
+pub fn expand_template(template: &str, stem: &str, page: Option<usize>) -> Result<PathBuf> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else { bail!("Unclosed {{ in {}", template) };
+        let name = &rest[open + 1..open + close];
+        match (name, page) {
+            ("stem", _) => out.push_str(stem),
+            ("page", Some(page)) => out.push_str(&(page + 1).to_string()),
+            ("page", None) => bail!("{{page}} needs a page"),
+            _ => bail!("Unknown placeholder {{{}}} in {}", name, template),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+
+    let path = PathBuf::from(out);
+    let escapes = path.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if path.as_os_str().is_empty() || escapes {
+        bail!("{} must be a relative path inside the export folder", template);
+    }
+    Ok(path)
+}
+
+/// One file written for a document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestOutput {
+    /// Relative to the export folder, with forward slashes
+    pub path: String,
+    pub format: OutputFormat,
+    /// One-based page for per-page outputs
+    pub page: Option<usize>,
+    pub bytes: usize,
+    pub sha256: String,
+    /// Its stamp's sidecar file, relative like `path`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sidecar: Option<String>,
+    /// Where it was uploaded, when an upload location is set up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded: Option<String>,
+}
+
+/// What was exported for one input document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    pub input: String,
+    /// None when the PDF is no longer where the workspace says
+    pub input_sha256: Option<String>,
+    pub extraction: String,
+    pub extractor: String,
+    pub pages: usize,
+    pub items: usize,
+    /// Extraction items by JSON `type`
+    pub item_counts: BTreeMap<String, usize>,
+    pub outputs: Vec<ManifestOutput>,
+    /// How posting it to the webhook went, when one is set up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<Delivery>,
+}
+
+/// Name of whatever produced an extraction, from its metadata
+pub fn extractor_name(data: &Value) -> String {
+    let metadata = &data["metadata"];
+    metadata["importer"].as_str()
+        .or_else(|| metadata["extractor"].as_str())
+        .or_else(|| metadata.get("docling_version").map(|_| "docling"))
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Extraction items by JSON `type`
+pub fn item_counts(data: &Value) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for item in data.get("items").and_then(|v| v.as_array()).into_iter().flatten() {
+        let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("TextItem");
+        *counts.entry(item_type.to_string()).or_default() += 1;
+    }
+    counts
+}
+
+/// Write one document's outputs into `dir`. `written` holds every path written
+/// so far, so templates that would overwrite another document's files fail.
+pub fn export_document(
+    dir: &Path,
+    doc: &WorkspaceDocument,
+    pipeline: &ExportPipeline,
+    written: &mut HashSet<PathBuf>,
+) -> Result<ManifestEntry> {
+    let Some(json_path) = &doc.extracted_json else { bail!("{} has not been extracted", doc.display_name()) };
+    let extracted = ExtractedDocument::load(json_path)?;
+    let stem = doc.path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "document".to_string());
+
+    let mut edits = EditPatch::new(Some(doc.path.display().to_string()));
+    edits.bookmarks = doc.bookmarks.clone();
+    edits.page_notes = doc.page_notes.clone();
+    let source = std::fs::read(&doc.path).ok();
+    let stamp = Stamp::new(Some(&doc.path), source.as_deref(), &extracted.data);
+
+    let mut outputs = Vec::new();
+    for output in &pipeline.outputs {
+        let pages: Vec<Option<usize>> = if is_per_page(&output.template) {
+            (0..extracted.page_count()).map(Some).collect()
+        } else {
+            vec![None]
+        };
+        for page in pages {
+            let relative = expand_template(&output.template, &stem, page)?;
+            let path = dir.join(&relative);
+            if !written.insert(path.clone()) {
+                bail!("{} would be written twice; add {{stem}} or {{page}} to {}", relative.display(), output.template);
+            }
+            let contents = output.format.render(&extracted.data, &edits, page)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            let (contents, sidecar) = stamp.write(&path, contents.into_bytes(), pipeline.stamping)?;
+            let relative_path = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            outputs.push(ManifestOutput {
+                sidecar: sidecar.map(|_| format!("{}{}", relative_path, SIDECAR_SUFFIX)),
+                path: relative_path,
+                format: output.format,
+                page: page.map(|p| p + 1),
+                bytes: contents.len(),
+                sha256: sha256_hex(&contents),
+                uploaded: None,
+            });
+        }
+    }
+
+    Ok(ManifestEntry {
+        input: doc.path.display().to_string(),
+        input_sha256: stamp.source_sha256.clone(),
+        extraction: json_path.display().to_string(),
+        extractor: extractor_name(&extracted.data),
+        pages: extracted.page_count(),
+        items: extracted.item_count(),
+        item_counts: item_counts(&extracted.data),
+        outputs,
+        webhook: None,
+    })
+}
+
+pub fn manifest_json(entries: &[ManifestEntry], pipeline: &ExportPipeline) -> Value {
+    json!({
+        "generated": chrono::Local::now().to_rfc3339(),
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "layout": pipeline.outputs,
+        "document_count": entries.len(),
+        "documents": entries,
+    })
+}
+
+/// One row per output file, repeating its document's columns
+pub fn manifest_csv(entries: &[ManifestEntry]) -> String {
+    let mut out = String::from("input,input_sha256,extractor,pages,items,output,format,page,bytes,sha256\n");
+    for entry in entries {
+        for output in &entry.outputs {
+            let row = [
+                entry.input.clone(),
+                entry.input_sha256.clone().unwrap_or_default(),
+                entry.extractor.clone(),
+                entry.pages.to_string(),
+                entry.items.to_string(),
+                output.path.clone(),
+                output.format.label().to_string(),
+                output.page.map(|p| p.to_string()).unwrap_or_default(),
+                output.bytes.to_string(),
+                output.sha256.clone(),
+            ];
+            out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// What a batch export did
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchReport {
+    pub manifest: PathBuf,
+    pub documents: usize,
+    /// Documents posted to the webhook
+    pub posted: usize,
+    /// Why posting failed, by document
+    pub post_failures: Vec<String>,
+    /// Files uploaded to the upload location
+    pub uploaded: usize,
+    /// Why uploading failed, by document
+    pub upload_failures: Vec<String>,
+}
+
+impl BatchReport {
+    pub fn describe(&self) -> String {
+        let mut out = format!("Exported {} documents; manifest at {}", self.documents, self.manifest.display());
+        if self.posted > 0 || !self.post_failures.is_empty() {
+            out.push_str(&format!("; posted {} of {}", self.posted, self.posted + self.post_failures.len()));
+        }
+        if self.uploaded > 0 || !self.upload_failures.is_empty() {
+            out.push_str(&format!("; uploaded {} files", self.uploaded));
+        }
+        for failure in self.post_failures.iter().chain(&self.upload_failures) {
+            out.push('\n');
+            out.push_str(failure);
+        }
+        out
+    }
+}
+
+/// Upload a document's outputs, recording where each went; stops at the
+/// first failure
+fn upload_outputs(remote: &Remote, dir: &Path, entry: &mut ManifestEntry) -> Result<usize> {
+    let mut uploaded = 0;
+    for output in &mut entry.outputs {
+        let path = dir.join(&output.path);
+        let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let key = remote.key(&output.path);
+        remote.put(&key, &bytes)?;
+        output.uploaded = Some(remote.url(&key));
+        uploaded += 1;
+        if let Some(sidecar) = &output.sidecar {
+            let path = dir.join(sidecar);
+            let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            remote.put(&remote.key(sidecar), &bytes)?;
+            uploaded += 1;
+        }
+    }
+    Ok(uploaded)
+}
+
+/// Export every document, upload and post it if that's set up, and write
+/// the manifest
+pub fn run(dir: &Path, documents: &[WorkspaceDocument], pipeline: &ExportPipeline, job: &JobHandle) -> Result<BatchReport> {
+    job.set_total(documents.len());
+    let mut written = HashSet::new();
+    let mut entries = Vec::new();
+    let mut rows = Vec::new();
+    let (mut posted, mut post_failures) = (0, Vec::new());
+    let (mut uploaded, mut upload_failures) = (0, Vec::new());
+    for doc in documents {
+        job.begin_step(doc.display_name())?;
+        let mut entry = export_document(dir, doc, pipeline, &mut written)?;
+        if let Some(remote) = &pipeline.remote {
+            match upload_outputs(remote, dir, &mut entry) {
+                Ok(count) => uploaded += count,
+                Err(e) => upload_failures.push(format!("{}: {}", doc.display_name(), e)),
+            }
+        }
+        if pipeline.webhook.is_active() {
+            let delivery = match webhook::payload(doc, &entry) {
+                Ok(payload) => webhook::post(&pipeline.webhook, &payload),
+                Err(e) => Delivery { status: None, error: Some(e.to_string()) },
+            };
+            match &delivery.error {
+                None => posted += 1,
+                Some(error) => post_failures.push(format!("{}: {}", doc.display_name(), error)),
+            }
+            entry.webhook = Some(delivery);
+        }
+        entries.push(entry);
+        if !pipeline.field_mapping.is_empty() {
+            rows.push(DocumentFields::load(doc)?);
+        }
+        job.finish_step();
+    }
+    if !pipeline.field_mapping.is_empty() {
+        let relative = expand_template(&pipeline.field_mapping.file_name, "fields", None)?;
+        let path = dir.join(&relative);
+        if written.contains(&path) {
+            bail!("The field sheet {} would overwrite an output", path.display());
+        }
+        pipeline.field_mapping.write(&path, &rows)?;
+        if let Some(remote) = &pipeline.remote {
+            let key = remote.key(&relative.to_string_lossy().replace('\\', "/"));
+            match std::fs::read(&path).map_err(anyhow::Error::from).and_then(|bytes| remote.put(&key, &bytes)) {
+                Ok(()) => uploaded += 1,
+                Err(e) => upload_failures.push(format!("{}: {}", relative.display(), e)),
+            }
+        }
+    }
+
+    let manifest_path = dir.join(pipeline.manifest.file_name());
+    let contents = match pipeline.manifest {
+        ManifestFormat::Json => serde_json::to_string_pretty(&manifest_json(&entries, pipeline))?,
+        ManifestFormat::Csv => manifest_csv(&entries),
+    };
+    std::fs::write(&manifest_path, &contents)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+    if let Some(remote) = &pipeline.remote {
+        match remote.put(&remote.key(pipeline.manifest.file_name()), contents.as_bytes()) {
+            Ok(()) => uploaded += 1,
+            Err(e) => upload_failures.push(format!("{}: {}", pipeline.manifest.file_name(), e)),
+        }
+    }
+    Ok(BatchReport { manifest: manifest_path, documents: documents.len(), posted, post_failures, uploaded, upload_failures })
+}
@@ -0,0 +1,162 @@
+//! Python environment setup
+//!
+//! Extraction runs Docling in the `.venv` next to the app. This module checks
+//! that environment and, when it's missing or incomplete, creates it and
+//! installs the extractor packages as a background job so users don't have to
+//! set it up by hand.
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::jobs::JobHandle;
+
+pub const VENV_DIR: &str = ".venv";
+
+/// Packages extraction needs, as (pip name, import name)
+pub const REQUIRED_PACKAGES: [(&str, &str); 2] = [("docling", "docling"), ("pypdfium2", "pypdfium2")];
+
+/// The venv the extractor uses, relative to the working directory
+pub fn venv_dir() -> PathBuf {
+    std::env::current_dir().unwrap_or_default().join(VENV_DIR)
+}
+
+/// The interpreter inside a venv
+pub fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+/// A Python 3 on PATH to create the venv with
+pub fn find_system_python() -> Option<PathBuf> {
+    ["python3", "python"].into_iter()
+        .find(|name| Command::new(name)
+            .args(["-c", "import sys; sys.exit(sys.version_info[0] != 3)"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success()))
+        .map(PathBuf::from)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvStatus {
+    /// No interpreter in the venv
+    Missing,
+    /// The venv exists but these packages don't import
+    Incomplete(Vec<String>),
+    Ready,
+}
+
+impl EnvStatus {
+    pub fn describe(&self) -> String {
+        match self {
+            EnvStatus::Missing => "No Python environment".to_string(),
+            EnvStatus::Incomplete(missing) => format!("Missing packages: {}", missing.join(", ")),
+            EnvStatus::Ready => "Ready".to_string(),
+        }
+    }
+}
+
+/// Packages that fail to import with this interpreter
+fn missing_imports(python: &Path) -> Result<Vec<String>> {
+    let mut missing = Vec::new();
+    for (package, module) in REQUIRED_PACKAGES {
+        let status = Command::new(python)
+            .args(["-c", &format!("import {}", module)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .with_context(|| format!("Failed to run {}", python.display()))?;
+        if !status.success() {
+            missing.push(package.to_string());
+        }
+    }
+    Ok(missing)
+}
+
+/// Check that the venv exists and the extractor packages import
+pub fn check(venv: &Path) -> EnvStatus {
+    let python = venv_python(venv);
+    if !python.exists() {
+        return EnvStatus::Missing;
+    }
+    match missing_imports(&python) {
+        Ok(missing) if missing.is_empty() => EnvStatus::Ready,
+        Ok(missing) => EnvStatus::Incomplete(missing),
+        Err(_) => EnvStatus::Missing,
+    }
+}
+
+/// Run a command, showing its latest output line as the job step.
+/// The process is killed if the job is cancelled.
+fn run_with_progress(job: &JobHandle, label: &str, command: &mut Command) -> Result<()> {
+    job.begin_step(label)?;
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("{}: failed to start", label))?;
+
+    // pip reports progress on stdout; keep stderr for the error message
+    let stderr = child.stderr.take().map(|stderr| std::thread::spawn(move || {
+        let mut lines: Vec<String> = BufReader::new(stderr).lines().map_while(Result::ok).collect();
+        lines.retain(|l| !l.trim().is_empty());
+        lines.split_off(lines.len().saturating_sub(5)).join("\n")
+    }));
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if job.begin_step(format!("{}: {}", label, line)).is_err() {
+                child.kill().ok();
+                child.wait().ok();
+                bail!("Cancelled");
+            }
+        }
+    }
+
+    let status = child.wait().with_context(|| format!("{}: failed", label))?;
+    let errors = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+    if !status.success() {
+        bail!("{} failed: {}", label, errors);
+    }
+    job.finish_step();
+    Ok(())
+}
+
+/// Create the venv if needed, install the missing packages and verify they import
+pub fn setup(venv: &Path, job: &JobHandle) -> Result<String> {
+    let python = venv_python(venv);
+    let create = !python.exists();
+    // Create, upgrade pip, one step per package, verify
+    job.set_total(usize::from(create) + 1 + REQUIRED_PACKAGES.len() + 1);
+
+    if create {
+        let system_python = find_system_python()
+            .ok_or_else(|| anyhow!("Python 3 wasn't found on PATH; install it and try again"))?;
+        run_with_progress(job, "Creating virtual environment",
+            Command::new(system_python).args(["-m", "venv"]).arg(venv))?;
+    }
+
+    run_with_progress(job, "Upgrading pip",
+        Command::new(&python).args(["-m", "pip", "install", "--upgrade", "pip"]))?;
+    for (package, _) in REQUIRED_PACKAGES {
+        run_with_progress(job, &format!("Installing {}", package),
+            Command::new(&python).args(["-m", "pip", "install", "--progress-bar", "off", package]))?;
+    }
+
+    job.begin_step("Verifying imports")?;
+    let missing = missing_imports(&python)?;
+    if !missing.is_empty() {
+        bail!("Installed, but these still don't import: {}", missing.join(", "));
+    }
+    job.finish_step();
+    Ok(format!("Python environment ready in {}", venv.display()))
+}
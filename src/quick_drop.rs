@@ -0,0 +1,147 @@
+//! Quick-drop queue
+//!
+//! `chonker3 --quick-drop` runs as a tray icon with a small always-on-top
+//! window that takes files dropped on it, or sent by later launches, and
+//! extracts them one after another in the background (see `tray`). Each result joins the workspace, and a desktop
+//! notification says when the queue has run dry, so documents can be fed in
+//! through the day without opening each one.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use anyhow::{anyhow, Result};
+
+use crate::extractor::{ExtractOptions, ExtractorKind};
+use crate::inputs;
+
+/// A PDF extracted from the queue
+#[derive(Debug, Clone)]
+pub struct QueuedExtraction {
+    pub pdf: PathBuf,
+    pub json_path: PathBuf,
+    pub items: usize,
+}
+
+/// What the worker reports back
+#[derive(Debug)]
+pub enum QueueEvent {
+    Started(PathBuf),
+    /// A queued file is done; an email can give several PDFs
+    Finished { path: PathBuf, result: std::result::Result<Vec<QueuedExtraction>, String> },
+}
+
+/// Tally of one run of the queue, from the first file to the queue running dry
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueSummary {
+    pub extracted: usize,
+    pub failed: usize,
+}
+
+impl QueueSummary {
+    pub fn describe(&self) -> String {
+        match self.failed {
+            0 => format!("Extracted {} document{}", self.extracted, if self.extracted == 1 { "" } else { "s" }),
+            failed => format!("Extracted {}, {} failed", self.extracted, failed),
+        }
+    }
+}
+
+/// Files waiting for extraction, worked off on a background thread
+pub struct DropQueue {
+    files: Sender<PathBuf>,
+    events: Receiver<QueueEvent>,
+    /// Queued files not finished yet
+    pub pending: usize,
+    pub current: Option<PathBuf>,
+    summary: QueueSummary,
+}
+
+impl DropQueue {
+    /// Start the worker with the extractor settings to use; `wake` is called
+    /// whenever there is news
+    pub fn start(extractor: ExtractorKind, options: ExtractOptions, wake: impl Fn() + Send + 'static) -> Self {
+        let (files, receiver) = channel::<PathBuf>();
+        let (events, event_receiver) = channel();
+        std::thread::spawn(move || {
+            let extractor = extractor.extractor();
+            for path in receiver {
+                let _ = events.send(QueueEvent::Started(path.clone()));
+                wake();
+                let result = inputs::prepare(&path, &inputs::default_output_dir()).and_then(|pdfs| {
+                    pdfs.into_iter().map(|pdf| {
+                        let document = extractor.extract(&pdf, &options)?;
+                        Ok(QueuedExtraction { items: document.item_count(), json_path: document.json_path, pdf })
+                    }).collect::<Result<Vec<_>>>()
+                });
+                let result = result.map_err(|e| format!("{:#}", e));
+                if events.send(QueueEvent::Finished { path, result }).is_err() {
+                    break;
+                }
+                wake();
+            }
+        });
+        Self { files, events: event_receiver, pending: 0, current: None, summary: QueueSummary::default() }
+    }
+
+    /// Queue a file; file types that can't be extracted are refused
+    pub fn push(&mut self, path: PathBuf) -> Result<()> {
+        if inputs::InputKind::from_path(&path).is_none() {
+            return Err(anyhow!("Unsupported file type: {}", path.display()));
+        }
+        self.files.send(path).map_err(|_| anyhow!("The extraction queue has stopped"))?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Events since the last call, and the run's tally if the queue just ran dry
+    pub fn poll(&mut self) -> (Vec<QueueEvent>, Option<QueueSummary>) {
+        let events: Vec<QueueEvent> = self.events.try_iter().collect();
+        if events.is_empty() {
+            return (events, None);
+        }
+        for event in &events {
+            match event {
+                QueueEvent::Started(path) => self.current = Some(path.clone()),
+                QueueEvent::Finished { result, .. } => {
+                    self.current = None;
+                    self.pending = self.pending.saturating_sub(1);
+                    match result {
+                        Ok(extracted) => self.summary.extracted += extracted.len(),
+                        Err(_) => self.summary.failed += 1,
+                    }
+                }
+            }
+        }
+        let finished = (self.pending == 0).then(|| std::mem::take(&mut self.summary));
+        (events, finished)
+    }
+}
+
+/// Show a desktop notification; best effort, as not every desktop has one
+pub fn notify(title: &str, body: &str) {
+    let result = if cfg!(target_os = "macos") {
+        let script = format!("display notification {} with title {}", applescript_string(body), applescript_string(title));
+        Command::new("osascript").args(["-e", &script]).spawn()
+    } else if cfg!(windows) {
+        // No notification API without extra dependencies; the window shows the summary
+        return;
+    } else {
+        Command::new("notify-send").args(["--app-name=Chonker3", title, body]).spawn()
+    };
+    match result {
+        // Called from the UI thread: reap the helper elsewhere rather than wait on it
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => log::info!("No desktop notification: {}", e),
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// File name for listing a queued file
+pub fn display_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string())
+}
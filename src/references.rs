@@ -0,0 +1,370 @@
+//! Citations and references in academic documents
+//!
+//! Reference entries are the items marked as references, plus the text items
+//! under a "References" or "Bibliography" heading. In-text citations are
+//! numeric ("[3]", "[1, 4-6]") or author-year ("(Smith et al., 2019)") and are
+//! linked to the entries they point at. Entries can be exported as BibTeX.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::patch::EditPatch;
+use crate::types::ItemType;
+
+/// Headings that open a reference section, compared case-insensitively without numbering
+const SECTION_HEADINGS: [&str; 5] = ["references", "bibliography", "works cited", "literature cited", "literature"];
+
+/// Longest numeric citation range that is expanded ("[1-400]" is more likely a page range)
+const MAX_RANGE: u32 = 50;
+
+/// One entry of the reference list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// Item the entry was read from; an item can hold several entries
+    pub item_id: String,
+    /// Zero-based page
+    pub page: usize,
+    /// The number the entry is cited by, for numbered lists
+    pub label: Option<u32>,
+    pub authors: Vec<String>,
+    pub year: Option<String>,
+    pub title: Option<String>,
+    /// The entry as printed, without its label
+    pub text: String,
+}
+
+impl Reference {
+    /// Surname of the first author
+    pub fn first_surname(&self) -> Option<&str> {
+        self.authors.first().map(|author| surname(author))
+    }
+}
+
+/// What an in-text citation points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CitationKey {
+    Number(u32),
+    AuthorYear { author: String, year: String },
+}
+
+/// A citation found in the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Citation {
+    pub item_id: String,
+    /// Zero-based page
+    pub page: usize,
+    /// The citation as printed, brackets included
+    pub text: String,
+    pub keys: Vec<CitationKey>,
+    /// Indexes of the references the keys resolved to
+    pub references: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct References {
+    pub references: Vec<Reference>,
+    pub citations: Vec<Citation>,
+}
+
+impl References {
+    /// Citations that resolved to the reference at `index`
+    pub fn citations_of(&self, index: usize) -> impl Iterator<Item = &Citation> {
+        self.citations.iter().filter(move |citation| citation.references.contains(&index))
+    }
+}
+
+/// Whether a heading opens the reference section ("7. References", "BIBLIOGRAPHY")
+pub fn is_section_heading(text: &str) -> bool {
+    let heading = text.trim()
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c.is_whitespace())
+        .trim_end_matches(':')
+        .to_lowercase();
+    SECTION_HEADINGS.contains(&heading.as_str())
+}
+
+/// A leading "[12]", "12." or "12)" and the rest of the line
+fn split_label(line: &str) -> (Option<u32>, &str) {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('[') {
+        if let Some((number, rest)) = rest.split_once(']') {
+            if let Ok(number) = number.trim().parse() {
+                return (Some(number), rest.trim_start());
+            }
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && digits < 4 {
+        let rest = &line[digits..];
+        if let Some(rest) = rest.strip_prefix('.').or_else(|| rest.strip_prefix(')')) {
+            if rest.starts_with(char::is_whitespace) {
+                return (line[..digits].parse().ok(), rest.trim_start());
+            }
+        }
+    }
+    (None, line)
+}
+
+/// A plausible publication year ("1998", "2019a") starting at byte `start`
+fn year_at(text: &str, start: usize) -> Option<&str> {
+    let rest = &text[start..];
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits != 4 || !(rest.starts_with("19") || rest.starts_with("20")) {
+        return None;
+    }
+    let preceded_by_digit = text[..start].chars().next_back().is_some_and(|c| c.is_ascii_digit());
+    if preceded_by_digit {
+        return None;
+    }
+    let suffix = rest[4..].chars().next().filter(|c| c.is_ascii_lowercase() && !rest[5..].starts_with(char::is_alphabetic));
+    Some(&rest[..4 + suffix.map_or(0, |c| c.len_utf8())])
+}
+
+/// First year in the text and where it starts
+fn find_year(text: &str) -> Option<(usize, &str)> {
+    text.char_indices().find_map(|(i, _)| year_at(text, i).map(|year| (i, year)))
+}
+
+/// "J." or "K. L." - initials that belong to the author before them
+fn is_initials(part: &str) -> bool {
+    part.split_whitespace().all(|word| {
+        let letters = word.trim_end_matches('.');
+        word.ends_with('.') && letters.chars().count() <= 2 && letters.chars().all(|c| c.is_uppercase() || c == '-')
+    })
+}
+
+/// The family name in "Smith, J." or "J. Smith"
+pub fn surname(author: &str) -> &str {
+    match author.split_once(',') {
+        Some((surname, _)) => surname.trim(),
+        None => author.split_whitespace().last().unwrap_or(author),
+    }
+}
+
+/// Split an author list, keeping "Smith, J." together
+fn parse_authors(text: &str) -> Vec<String> {
+    let text = text.trim().trim_end_matches(['(', ',', ' ']).replace(" & ", ", ").replace(" and ", ", ");
+    let mut authors: Vec<String> = Vec::new();
+    for part in text.split([',', ';']).map(str::trim).filter(|p| !p.is_empty()) {
+        match authors.last_mut() {
+            Some(last) if is_initials(part) && !last.contains(',') => {
+                last.push_str(", ");
+                last.push_str(part);
+            }
+            _ => authors.push(part.to_string()),
+        }
+    }
+    authors.retain(|a| a != "et al." && a != "et al");
+    authors
+}
+
+/// Split "J. Smith, K. Jones. Title..." after the author list: at the first
+/// ". " that doesn't end an initial
+fn split_after_authors(text: &str) -> (&str, &str) {
+    text.match_indices(". ")
+        .find(|(i, _)| text[..*i].split_whitespace().next_back().is_some_and(|word| word.chars().count() > 1))
+        .map_or(("", text), |(i, _)| (&text[..i], &text[i + 2..]))
+}
+
+/// Read authors, year and title out of an entry's text
+pub fn parse_reference(text: &str) -> (Vec<String>, Option<String>, Option<String>) {
+    let text = text.trim();
+    let (authors, year, rest) = match find_year(text) {
+        // Author-year style: "Smith, J. (2019). Title. Journal."
+        Some((start, year)) if start < text.len() / 2 => {
+            let rest = text[start + year.len()..].trim_start_matches([')', '.', ',', ' ']);
+            (parse_authors(&text[..start]), Some(year.to_string()), rest)
+        }
+        // Numbered style: "J. Smith, K. Jones. Title. Journal, 2019."
+        found => {
+            let (authors, rest) = split_after_authors(text);
+            (parse_authors(authors), found.map(|(_, year)| year.to_string()), rest)
+        }
+    };
+
+    let title = if let Some((_, quoted)) = rest.split_once(['"', '“']) {
+        quoted.split(['"', '”']).next()
+    } else {
+        rest.split(". ").next()
+    }
+    .map(|title| title.trim().trim_end_matches(['.', ',']).to_string())
+    .filter(|title| !title.is_empty());
+
+    (authors, year, title)
+}
+
+/// Split an item's text into entries at lines that start with a label
+fn split_entries(text: &str) -> Vec<(Option<u32>, String)> {
+    let mut entries: Vec<(Option<u32>, String)> = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (label, rest) = split_label(line);
+        match entries.last_mut() {
+            Some((_, entry)) if label.is_none() => {
+                entry.push(' ');
+                entry.push_str(rest);
+            }
+            _ => entries.push((label, rest.to_string())),
+        }
+    }
+    entries
+}
+
+fn numbers(list: &str) -> Option<Vec<u32>> {
+    let mut numbers = Vec::new();
+    for part in list.split(',').map(str::trim) {
+        match part.split_once(['-', '–']) {
+            Some((from, to)) => {
+                let (from, to): (u32, u32) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+                if to < from || to - from > MAX_RANGE {
+                    return None;
+                }
+                numbers.extend(from..=to);
+            }
+            None => numbers.push(part.parse().ok()?),
+        }
+    }
+    Some(numbers)
+}
+
+/// "Smith et al., 2019" or "Smith and Jones 2019a"
+fn author_year(part: &str) -> Option<CitationKey> {
+    let part = part.trim();
+    let (start, year) = find_year(part)?;
+    if !part[start + year.len()..].trim().is_empty() {
+        return None;
+    }
+    let author = part[..start].split_whitespace().next()?.trim_end_matches(',');
+    author.chars().next().filter(|c| c.is_uppercase())?;
+    Some(CitationKey::AuthorYear { author: author.to_string(), year: year.to_string() })
+}
+
+/// Citations in a run of text, as (printed text, keys)
+pub fn find_citations(text: &str) -> Vec<(String, Vec<CitationKey>)> {
+    let mut found = Vec::new();
+    for (open, close) in [('[', ']'), ('(', ')')] {
+        let mut rest = text;
+        while let Some(start) = rest.find(open) {
+            let after = &rest[start + 1..];
+            let Some(end) = after.find(close) else { break };
+            let inner = &after[..end];
+            let keys = if open == '[' {
+                numbers(inner).map(|numbers| numbers.into_iter().map(CitationKey::Number).collect())
+            } else {
+                inner.split(';').map(author_year).collect::<Option<Vec<_>>>()
+            };
+            if let Some(keys) = keys.filter(|keys: &Vec<CitationKey>| !keys.is_empty()) {
+                found.push((format!("{}{}{}", open, inner, close), keys));
+            }
+            rest = &after[end + 1..];
+        }
+    }
+    found
+}
+
+fn resolve(key: &CitationKey, references: &[Reference]) -> Option<usize> {
+    references.iter().position(|reference| match key {
+        CitationKey::Number(number) => reference.label == Some(*number),
+        CitationKey::AuthorYear { author, year } => {
+            reference.year.as_deref() == Some(year.as_str())
+                && reference.first_surname().is_some_and(|surname| surname.eq_ignore_ascii_case(author))
+        }
+    })
+}
+
+/// Find the reference list and the citations in the rest of the document, with edits applied
+pub fn collect(data: &Value, edits: &EditPatch) -> References {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    let mut references = Vec::new();
+    let mut cited_text = Vec::new();
+    let mut in_section = false;
+
+    for page in 0..page_count {
+        for item in crate::document::edited_page_items(data, page, edits) {
+            match item.item_type {
+                ItemType::Title | ItemType::Header => {
+                    in_section = is_section_heading(&item.content);
+                    continue;
+                }
+                ItemType::Reference => {}
+                ItemType::Text if in_section => {}
+                _ => {
+                    cited_text.push((item.id, page, item.content));
+                    continue;
+                }
+            }
+            for (label, text) in split_entries(&item.content) {
+                let (authors, year, title) = parse_reference(&text);
+                references.push(Reference { item_id: item.id.clone(), page, label, authors, year, title, text });
+            }
+        }
+    }
+
+    let citations = cited_text.into_iter()
+        .flat_map(|(item_id, page, content)| {
+            find_citations(&content).into_iter().map(move |(text, keys)| (item_id.clone(), page, text, keys))
+        })
+        .map(|(item_id, page, text, keys)| {
+            let resolved: BTreeSet<usize> = keys.iter().filter_map(|key| resolve(key, &references)).collect();
+            Citation { item_id, page, text, keys, references: resolved.into_iter().collect() }
+        })
+        .collect();
+
+    References { references, citations }
+}
+
+/// Keep BibTeX field values from closing their braces early
+fn bibtex_value(text: &str) -> String {
+    text.replace(['{', '}'], "")
+}
+
+/// Citation keys like "smith2019deep", made unique with a letter suffix
+fn bibtex_keys(references: &[Reference]) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for (index, reference) in references.iter().enumerate() {
+        let clean = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+        let surname = reference.first_surname().map(clean).filter(|s| !s.is_empty());
+        let word = reference.title.as_deref()
+            .and_then(|title| title.split_whitespace().map(clean).find(|w| w.len() > 3))
+            .unwrap_or_default();
+        let base = match surname {
+            Some(surname) => format!("{}{}{}", surname, reference.year.as_deref().unwrap_or(""), word),
+            None => format!("ref{}", reference.label.map_or(index + 1, |label| label as usize)),
+        };
+        let mut key = base.clone();
+        let mut suffix = b'a';
+        while keys.contains(&key) {
+            key = format!("{}{}", base, suffix as char);
+            suffix = suffix.saturating_add(1);
+        }
+        keys.push(key);
+    }
+    keys
+}
+
+/// The references as BibTeX `@misc` entries; the printed text goes in `note`
+pub fn bibtex(references: &[Reference]) -> String {
+    let mut out = String::new();
+    for (reference, key) in references.iter().zip(bibtex_keys(references)) {
+        out.push_str(&format!("@misc{{{},\n", key));
+        if !reference.authors.is_empty() {
+            out.push_str(&format!("  author = {{{}}},\n", bibtex_value(&reference.authors.join(" and "))));
+        }
+        if let Some(title) = &reference.title {
+            out.push_str(&format!("  title = {{{}}},\n", bibtex_value(title)));
+        }
+        if let Some(year) = &reference.year {
+            out.push_str(&format!("  year = {{{}}},\n", &year[..4]));
+        }
+        out.push_str(&format!("  note = {{{}}}\n}}\n\n", bibtex_value(&reference.text)));
+    }
+    out
+}
+
+/// Write the document's references as BibTeX; returns how many were written
+pub fn write_bibtex(path: &Path, data: &Value, edits: &EditPatch) -> Result<usize> {
+    let references = collect(data, edits).references;
+    std::fs::write(path, bibtex(&references))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(references.len())
+}
@@ -0,0 +1,250 @@
+//! Reflowed print layout
+//!
+//! Lays the cleaned document out as running text, independent of the
+//! original page geometry: items in reading order, edits applied, wrapped to
+//! the paper's text width and broken into pages with margins. The result is
+//! written as a new PDF with pdfium's built-in Helvetica, and Courier for code.
+
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::patch::EditPatch;
+use crate::pdf_output::{self, PdfOutput};
+use crate::types::ItemType;
+
+/// Line height as a multiple of the font size
+const LINE_SPACING: f32 = 1.3;
+
+/// Space after a block, as a multiple of its font size
+const BLOCK_SPACING: f32 = 0.6;
+
+/// Baseline below the top of a line, as a multiple of the font size
+const ASCENT: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    pub const ALL: [PaperSize; 2] = [PaperSize::A4, PaperSize::Letter];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaperSize::A4 => "A4",
+            PaperSize::Letter => "US Letter",
+        }
+    }
+
+    /// Width and height in points
+    pub fn points(&self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (595.0, 842.0),
+            PaperSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+fn default_margin() -> f32 {
+    56.0
+}
+
+fn default_font_size() -> f32 {
+    11.0
+}
+
+/// Page size, margins and body text size of the reflowed export
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrintLayout {
+    #[serde(default)]
+    pub paper: PaperSize,
+    /// Margin on every side, in points
+    #[serde(default = "default_margin")]
+    pub margin: f32,
+    /// Body text size in points; headings are scaled from it
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+}
+
+impl Default for PrintLayout {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::default(),
+            margin: default_margin(),
+            font_size: default_font_size(),
+        }
+    }
+}
+
+impl PrintLayout {
+    /// Width available to text between the margins
+    pub fn text_width(&self) -> f32 {
+        (self.paper.points().0 - 2.0 * self.margin).max(self.font_size * 10.0)
+    }
+
+    fn text_height(&self) -> f32 {
+        (self.paper.points().1 - 2.0 * self.margin).max(self.font_size * LINE_SPACING)
+    }
+}
+
+/// A paragraph, heading or table of the reflowed document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub text: String,
+    pub item_type: ItemType,
+}
+
+/// One line set on an output page; `top` is measured from the top margin
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedLine {
+    pub text: String,
+    pub top: f32,
+    pub font_size: f32,
+    pub bold: bool,
+    pub monospace: bool,
+}
+
+/// The document's items in reading order with edits applied; pictures are left out
+/// and formulas are set as their LaTeX
+pub fn blocks(data: &Value, edits: &EditPatch) -> Vec<Block> {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    (0..page_count)
+        .flat_map(|page_index| crate::document::edited_page_items(data, page_index, edits))
+        .filter(|item| item.item_type != ItemType::Picture)
+        .map(|item| {
+            // Formulas can't be typeset here; their LaTeX is more useful than garbled glyphs
+            let text = match item.item_type {
+                ItemType::Formula => crate::export::formula_source(item.latex.as_deref(), &item.content)
+                    .unwrap_or("[Formula]")
+                    .to_string(),
+                ItemType::Code => item.content.trim_matches('\n').to_string(),
+                _ => item.content.trim().to_string(),
+            };
+            Block { text, item_type: item.item_type }
+        })
+        .filter(|block| !block.text.is_empty())
+        .collect()
+}
+
+/// Approximate Helvetica advance of a character, in ems
+fn char_width(c: char) -> f32 {
+    match c {
+        ' ' | 'i' | 'j' | 'l' | '.' | ',' | ';' | ':' | '\'' | '|' | '!' => 0.28,
+        'f' | 't' | 'r' | 'I' | '(' | ')' | '-' => 0.35,
+        'm' | 'w' | 'M' | 'W' => 0.85,
+        c if c.is_uppercase() => 0.68,
+        _ => 0.56,
+    }
+}
+
+/// Approximate width of `text` set at `font_size`
+pub fn text_width(text: &str, font_size: f32) -> f32 {
+    text.chars().map(char_width).sum::<f32>() * font_size
+}
+
+/// Break text into lines no wider than `width`, keeping hard line breaks.
+/// Words too long for a line get a line of their own.
+pub fn wrap(text: &str, font_size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if !line.is_empty() && text_width(&candidate, font_size) > width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Font size and weight a block is set in
+fn block_style(item_type: ItemType, body: f32) -> (f32, bool) {
+    match item_type {
+        ItemType::Title => (body * 1.6, true),
+        ItemType::Header => (body * 1.25, true),
+        ItemType::FormLabel => (body, true),
+        ItemType::Code => (body * 0.9, false),
+        _ => (body, false),
+    }
+}
+
+/// Lay the blocks out on pages. Headings are kept with the first line that follows them.
+pub fn paginate(blocks: &[Block], layout: &PrintLayout) -> Vec<Vec<PlacedLine>> {
+    let width = layout.text_width();
+    let height = layout.text_height();
+    let mut pages = vec![Vec::new()];
+    let mut top = 0.0;
+
+    for (index, block) in blocks.iter().enumerate() {
+        let (font_size, bold) = block_style(block.item_type, layout.font_size);
+        let line_height = font_size * LINE_SPACING;
+        // Table cells go side by side and code keeps its indentation, so their rows are set as they are
+        let monospace = block.item_type == ItemType::Code;
+        let lines = match block.item_type {
+            ItemType::Table => wrap(&block.text.replace('\t', "    "), font_size, width),
+            ItemType::Code => block.text.replace('\t', "    ").lines().map(str::to_string).collect(),
+            _ => wrap(&block.text, font_size, width),
+        };
+
+        // A heading at the foot of a page moves over with its paragraph
+        if bold && top > 0.0 {
+            let next_line = blocks.get(index + 1)
+                .map_or(0.0, |next| block_style(next.item_type, layout.font_size).0 * LINE_SPACING);
+            if top + lines.len() as f32 * line_height + next_line > height {
+                pages.push(Vec::new());
+                top = 0.0;
+            }
+        }
+
+        for line in lines {
+            if top > 0.0 && top + line_height > height {
+                pages.push(Vec::new());
+                top = 0.0;
+            }
+            if let Some(page) = pages.last_mut() {
+                page.push(PlacedLine { text: line, top, font_size, bold, monospace });
+            }
+            top += line_height;
+        }
+        top += font_size * BLOCK_SPACING;
+    }
+    pages
+}
+
+/// Write the paginated lines as a PDF; returns the number of pages
+pub fn write_pdf(pdfium: &Pdfium, pages: &[Vec<PlacedLine>], layout: &PrintLayout, path: &Path, output: &PdfOutput) -> Result<usize> {
+    let mut document = pdfium.create_new_pdf().map_err(|e| anyhow!("Failed to create PDF: {}", e))?;
+    let regular = document.fonts_mut().helvetica();
+    let bold = document.fonts_mut().helvetica_bold();
+    let monospace = document.fonts_mut().courier();
+    let (paper_width, paper_height) = layout.paper.points();
+    let size = PdfPagePaperSize::from_points(PdfPoints::new(paper_width), PdfPoints::new(paper_height));
+
+    for lines in pages {
+        let mut page = document.pages_mut().create_page_at_end(size)
+            .map_err(|e| anyhow!("Failed to create page: {}", e))?;
+        for line in lines.iter().filter(|line| !line.text.is_empty()) {
+            let baseline = paper_height - layout.margin - line.top - line.font_size * ASCENT;
+            page.objects_mut()
+                .create_text_object(
+                    PdfPoints::new(layout.margin),
+                    PdfPoints::new(baseline),
+                    &line.text,
+                    if line.monospace { monospace } else if line.bold { bold } else { regular },
+                    PdfPoints::new(line.font_size),
+                )
+                .map_err(|e| anyhow!("Failed to place text: {}", e))?;
+        }
+    }
+    pdf_output::save(&document, path, output)?;
+    Ok(pages.len())
+}
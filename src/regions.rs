@@ -0,0 +1,110 @@
+//! Named regions
+//!
+//! A named region is a box the user drew on a page and gave a name, such as
+//! "Invoice number" or "Signature block". Whatever the extraction finds inside
+//! it is that field's value, so a document's key fields can be read off and
+//! exported by name without building a full template. Regions are kept per
+//! document in the workspace and travel in the edit patch.
+
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::document;
+use crate::patch::EditPatch;
+use crate::types::{BoundingBox, DocumentItem};
+
+pub const FIELDS_FORMAT: &str = "chonker3-fields";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedRegion {
+    pub name: String,
+    /// Zero-based page
+    pub page: usize,
+    /// TOPLEFT, in PDF points
+    pub bbox: BoundingBox,
+}
+
+/// A region's name and what lies inside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionField {
+    pub name: String,
+    pub page: usize,
+    pub text: String,
+}
+
+/// The name trimmed, or why it can't be used: names must be unique, ignoring case
+pub fn check_name(name: &str, regions: &[NamedRegion], renaming: Option<usize>) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Give the region a name");
+    }
+    let taken = regions.iter().enumerate()
+        .any(|(index, region)| Some(index) != renaming && region.name.eq_ignore_ascii_case(name));
+    if taken {
+        bail!("There is already a region named \"{}\"", name);
+    }
+    Ok(name.to_string())
+}
+
+/// Items whose center lies in the region, in reading order
+pub fn items_in<'a>(items: &'a [DocumentItem], region: &BoundingBox) -> Vec<&'a DocumentItem> {
+    let mut inside: Vec<&DocumentItem> = items.iter().filter(|item| {
+        let (x, y) = (item.bbox.left + item.bbox.width / 2.0, item.bbox.top + item.bbox.height / 2.0);
+        x >= region.left && x <= region.left + region.width && y >= region.top && y <= region.top + region.height
+    }).collect();
+    inside.sort_by(|a, b| a.bbox.top.total_cmp(&b.bbox.top).then(a.bbox.left.total_cmp(&b.bbox.left)));
+    inside
+}
+
+/// Text of the items, a space between items on a line and a line break
+/// between lines
+pub fn join_items(items: &[&DocumentItem]) -> String {
+    let mut text = String::new();
+    let mut line_bottom = f64::NEG_INFINITY;
+    for item in items {
+        let bottom = item.bbox.top + item.bbox.height;
+        if item.bbox.top + item.bbox.height / 2.0 < line_bottom {
+            text.push(' ');
+            line_bottom = line_bottom.max(bottom);
+        } else {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            line_bottom = bottom;
+        }
+        text.push_str(item.content.trim());
+    }
+    text
+}
+
+/// Each region's value, with edits applied
+pub fn fields(data: &Value, edits: &EditPatch, regions: &[NamedRegion]) -> Vec<RegionField> {
+    regions.iter().map(|region| {
+        let items = document::edited_page_items(data, region.page, edits);
+        RegionField {
+            name: region.name.clone(),
+            page: region.page,
+            text: join_items(&items_in(&items, &region.bbox)),
+        }
+    }).collect()
+}
+
+/// `{"format": ..., "source_file": ..., "fields": {name: text}}`, with the
+/// pages the fields were read from
+pub fn fields_json(source_file: Option<&str>, fields: &[RegionField]) -> Value {
+    let values: Map<String, Value> = fields.iter().map(|f| (f.name.clone(), json!(f.text))).collect();
+    let pages: Map<String, Value> = fields.iter().map(|f| (f.name.clone(), json!(f.page + 1))).collect();
+    json!({
+        "format": FIELDS_FORMAT,
+        "source_file": source_file,
+        "fields": values,
+        "pages": pages,
+    })
+}
+
+pub fn write_fields(path: &Path, source_file: Option<&str>, fields: &[RegionField]) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(&fields_json(source_file, fields))?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
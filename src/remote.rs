@@ -0,0 +1,478 @@
+//! Remote locations: S3-compatible buckets and WebDAV folders
+//!
+//! For teams whose documents live in object storage rather than on disk,
+//! locations set up in Settings can be opened from and exported to. A PDF
+//! opened from a location is downloaded into the temp directory first; batch
+//! export uploads every file it writes, and the manifest, under the
+//! location's prefix. The password or secret access key is kept in the OS
+//! keyring, like the LLM API key.
+//!
+//! S3 requests are signed with AWS Signature Version 4 and use path-style
+//! URLs (`{url}/{bucket}/{key}`), which MinIO, Ceph and the other
+//! S3-compatible stores accept. WebDAV uses basic auth.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::passwords::SERVICE;
+use crate::checksum::sha256_hex;
+
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemoteKind {
+    #[default]
+    WebDav,
+    S3,
+}
+
+impl RemoteKind {
+    pub const ALL: [RemoteKind; 2] = [RemoteKind::WebDav, RemoteKind::S3];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RemoteKind::WebDav => "WebDAV",
+            RemoteKind::S3 => "S3-compatible",
+        }
+    }
+}
+
+/// A bucket or folder documents are opened from and exported to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteLocation {
+    /// Shown in menus; also names the location's secret in the keyring
+    pub name: String,
+    #[serde(default)]
+    pub kind: RemoteKind,
+    /// Server base URL, e.g. `https://s3.eu-west-1.amazonaws.com` or
+    /// `https://cloud.example.com/remote.php/dav/files/me`
+    pub url: String,
+    /// S3 only
+    #[serde(default)]
+    pub bucket: String,
+    /// S3 only; signed into every request
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Folder inside the bucket or WebDAV root, e.g. `invoices/2026`
+    #[serde(default)]
+    pub prefix: String,
+    /// WebDAV user name, or S3 access key ID
+    #[serde(default)]
+    pub user: String,
+}
+
+impl Default for RemoteLocation {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: RemoteKind::default(),
+            url: String::new(),
+            bucket: String::new(),
+            region: default_region(),
+            prefix: String::new(),
+            user: String::new(),
+        }
+    }
+}
+
+impl RemoteLocation {
+    fn secret_entry(&self) -> Result<Entry> {
+        Entry::new(SERVICE, &format!("remote:{}", self.name)).map_err(|e| anyhow!("Can't use the keyring: {}", e))
+    }
+
+    /// The password or secret access key in the keyring, if one was saved
+    pub fn secret(&self) -> Option<String> {
+        self.secret_entry().ok()?.get_password().ok().filter(|secret| !secret.is_empty())
+    }
+
+    /// Save the password or secret access key in the keyring, or remove it when empty
+    pub fn set_secret(&self, secret: &str) -> Result<()> {
+        let entry = self.secret_entry()?;
+        if secret.is_empty() {
+            return match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(anyhow!("Couldn't remove the secret of {} from the keyring: {}", self.name, e)),
+            };
+        }
+        entry.set_password(secret).map_err(|e| anyhow!("Couldn't save the secret of {} in the keyring: {}", self.name, e))
+    }
+
+    /// The location with its secret from the keyring, ready for requests
+    pub fn connect(&self) -> Remote {
+        Remote::new(self.clone(), self.secret())
+    }
+}
+
+/// A file in a remote location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteObject {
+    /// Full key, prefix included
+    pub key: String,
+    pub size: Option<u64>,
+}
+
+impl RemoteObject {
+    pub fn name(&self) -> &str {
+        self.key.rsplit('/').next().unwrap_or(&self.key)
+    }
+}
+
+/// A location and its secret
+#[derive(Clone, PartialEq)]
+pub struct Remote {
+    pub location: RemoteLocation,
+    secret: Option<String>,
+}
+
+impl std::fmt::Debug for Remote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Remote").field("location", &self.location).finish_non_exhaustive()
+    }
+}
+
+/// A safe local file name for an object: the last part of its key, without
+/// folders (of either slash) or drive prefixes; hidden, `.` and `..` names
+/// fall back to a fixed one so a key can't write outside the download folder
+pub fn download_file_name(key: &str) -> String {
+    let name = key.rsplit(['/', '\\']).next().unwrap_or("").trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(':') {
+        "download.pdf".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Percent-encode all but the unreserved characters (and `/` if kept), as
+/// Signature V4 wants them
+pub fn uri_encode(text: &str, keep_slash: bool) -> String {
+    let mut out = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            b'/' if keep_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn uri_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Host and port a URL names, without a default port, as the Host header carries it
+fn url_host(url: &str) -> Result<String> {
+    let (scheme, rest) = url.split_once("://").ok_or_else(|| anyhow!("{} is not an http(s) URL", url))?;
+    let authority = rest.split('/').next().unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    if authority.is_empty() {
+        bail!("{} has no host", url);
+    }
+    let default_port = if scheme.eq_ignore_ascii_case("https") { ":443" } else { ":80" };
+    Ok(authority.strip_suffix(default_port).unwrap_or(authority).to_string())
+}
+
+/// Path part of a URL, without the trailing slash
+fn url_path(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.find('/').map_or("", |slash| rest[slash..].trim_end_matches('/'))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// What a Signature V4 request signs
+pub struct SignedRequest<'a> {
+    pub method: &'a str,
+    /// URI-encoded path, e.g. `/bucket/some%20file.pdf`
+    pub path: &'a str,
+    /// Unencoded query parameters
+    pub query: &'a [(&'a str, &'a str)],
+    /// Lowercase names; `host`, `x-amz-date` and `x-amz-content-sha256` at least
+    pub headers: &'a [(&'a str, &'a str)],
+    pub payload_sha256: &'a str,
+    /// `YYYYMMDDTHHMMSSZ`, the `x-amz-date` header
+    pub amz_date: &'a str,
+}
+
+/// Canonical query string: encoded pairs sorted by name
+pub fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = query.iter()
+        .map(|(name, value)| (uri_encode(name, false), uri_encode(value, false)))
+        .collect();
+    pairs.sort();
+    pairs.into_iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&")
+}
+
+/// The Authorization header of an S3 request, signed with AWS Signature V4
+pub fn sign_v4(request: &SignedRequest, access_key: &str, secret_key: &str, region: &str) -> String {
+    let mut headers: Vec<(&str, &str)> = request.headers.to_vec();
+    headers.sort();
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method, request.path, canonical_query(request.query), canonical_headers, signed_headers, request.payload_sha256,
+    );
+
+    let date = &request.amz_date[..8.min(request.amz_date.len())];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", request.amz_date, scope, sha256_hex(canonical_request.as_bytes()));
+    let mut key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date);
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac_sha256(&key, part);
+    }
+    let signature: String = hmac_sha256(&key, &string_to_sign).iter().map(|b| format!("{:02x}", b)).collect();
+    format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", access_key, scope, signed_headers, signature)
+}
+
+fn read_body(response: ureq::Response) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn send(request: ureq::Request, body: Option<&[u8]>) -> Result<ureq::Response> {
+    let url = request.url().to_string();
+    let result = match body {
+        Some(body) => request.send_bytes(body),
+        None => request.call(),
+    };
+    checked(&url, result)
+}
+
+/// Replies outside 2xx become errors with the server's message
+fn checked(url: &str, result: Result<ureq::Response, ureq::Error>) -> Result<ureq::Response> {
+    match result {
+        Ok(response) => Ok(response),
+        Err(ureq::Error::Status(code, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            bail!("{} replied {}: {}", url, code, text.trim())
+        }
+        Err(e) => bail!("Request to {} failed: {}", url, e),
+    }
+}
+
+impl Remote {
+    pub fn new(location: RemoteLocation, secret: Option<String>) -> Self {
+        Self { location, secret }
+    }
+
+    fn prefix(&self) -> &str {
+        self.location.prefix.trim_matches('/')
+    }
+
+    /// Key of a path relative to the location's prefix
+    pub fn key(&self, relative: &str) -> String {
+        let relative = relative.trim_start_matches('/');
+        match self.prefix() {
+            "" => relative.to_string(),
+            prefix => format!("{}/{}", prefix, relative),
+        }
+    }
+
+    fn base_url(&self) -> &str {
+        self.location.url.trim().trim_end_matches('/')
+    }
+
+    /// URI-encoded request path of a key
+    fn path(&self, key: &str) -> String {
+        let base = url_path(self.base_url());
+        match self.location.kind {
+            RemoteKind::S3 if key.is_empty() => format!("{}/{}", base, uri_encode(self.location.bucket.trim(), false)),
+            RemoteKind::S3 => format!("{}/{}/{}", base, uri_encode(self.location.bucket.trim(), false), uri_encode(key, true)),
+            RemoteKind::WebDav => format!("{}/{}", base, uri_encode(key, true)),
+        }
+    }
+
+    /// Where a key lives, for the manifest and messages
+    pub fn url(&self, key: &str) -> String {
+        let origin = self.base_url().len() - url_path(self.base_url()).len();
+        format!("{}{}", &self.base_url()[..origin], self.path(key))
+    }
+
+    fn request(&self, method: &str, key: &str, query: &[(&str, &str)], body: &[u8]) -> Result<ureq::Request> {
+        let path = self.path(key);
+        let mut url = self.url(key);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query(query));
+        }
+        let request = ureq::request(method, &url).set("User-Agent", "chonker3").timeout(TIMEOUT);
+
+        match self.location.kind {
+            RemoteKind::WebDav => {
+                if self.location.user.is_empty() && self.secret.is_none() {
+                    return Ok(request);
+                }
+                let credentials = format!("{}:{}", self.location.user, self.secret.as_deref().unwrap_or_default());
+                let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+                Ok(request.set("Authorization", &format!("Basic {}", encoded)))
+            }
+            RemoteKind::S3 => {
+                let Some(secret) = &self.secret else {
+                    bail!("No secret access key saved for {}", self.location.name);
+                };
+                let host = url_host(self.base_url())?;
+                let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+                let payload_sha256 = sha256_hex(body);
+                let headers = [("host", host.as_str()), ("x-amz-content-sha256", payload_sha256.as_str()), ("x-amz-date", amz_date.as_str())];
+                let signed = SignedRequest { method, path: &path, query, headers: &headers, payload_sha256: &payload_sha256, amz_date: &amz_date };
+                let authorization = sign_v4(&signed, self.location.user.trim(), secret, self.location.region.trim());
+                Ok(request
+                    .set("x-amz-content-sha256", &payload_sha256)
+                    .set("x-amz-date", &amz_date)
+                    .set("Authorization", &authorization))
+            }
+        }
+    }
+
+    /// Files directly inside the location's prefix, by key
+    pub fn list(&self) -> Result<Vec<RemoteObject>> {
+        let mut objects = match self.location.kind {
+            RemoteKind::S3 => self.list_s3()?,
+            RemoteKind::WebDav => self.list_webdav()?,
+        };
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn list_s3(&self) -> Result<Vec<RemoteObject>> {
+        let prefix = match self.prefix() {
+            "" => String::new(),
+            prefix => format!("{}/", prefix),
+        };
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("delimiter", "/"), ("list-type", "2"), ("prefix", prefix.as_str())];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let response = send(self.request("GET", "", &query, &[])?, None)?;
+            let xml = String::from_utf8(read_body(response)?).context("Listing is not UTF-8")?;
+            let doc = roxmltree::Document::parse(&xml).context("Listing is not valid XML")?;
+            let text = |node: roxmltree::Node, name: &str| {
+                node.children().find(|child| child.tag_name().name() == name).and_then(|child| child.text()).map(str::to_string)
+            };
+            for contents in doc.root_element().children().filter(|node| node.tag_name().name() == "Contents") {
+                let Some(key) = text(contents, "Key") else { continue };
+                if key.ends_with('/') {
+                    continue;
+                }
+                objects.push(RemoteObject { key, size: text(contents, "Size").and_then(|size| size.parse().ok()) });
+            }
+            token = (text(doc.root_element(), "IsTruncated").as_deref() == Some("true"))
+                .then(|| text(doc.root_element(), "NextContinuationToken"))
+                .flatten();
+            if token.is_none() {
+                return Ok(objects);
+            }
+        }
+    }
+
+    fn list_webdav(&self) -> Result<Vec<RemoteObject>> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?><propfind xmlns="DAV:"><prop><resourcetype/><getcontentlength/></prop></propfind>"#;
+        let folder = match self.prefix() {
+            "" => String::new(),
+            prefix => format!("{}/", prefix),
+        };
+        let request = self.request("PROPFIND", &folder, &[], body.as_bytes())?
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml");
+        let response = send(request, Some(body.as_bytes()))?;
+        let xml = String::from_utf8(read_body(response)?).context("Listing is not UTF-8")?;
+        let doc = roxmltree::Document::parse(&xml).context("Listing is not valid XML")?;
+
+        let folder_path = uri_decode(&self.path(&folder));
+        let mut objects = Vec::new();
+        for response in doc.descendants().filter(|node| node.tag_name().name() == "response") {
+            let find = |name: &str| response.descendants().find(|node| node.tag_name().name() == name);
+            if find("collection").is_some() {
+                continue;
+            }
+            let Some(href) = find("href").and_then(|node| node.text()) else { continue };
+            let href = uri_decode(url_path(href.trim()));
+            let Some(name) = href.strip_prefix(folder_path.trim_end_matches('/')).map(|name| name.trim_matches('/')) else { continue };
+            if name.is_empty() || name.contains('/') {
+                continue;
+            }
+            let size = find("getcontentlength").and_then(|node| node.text()).and_then(|size| size.trim().parse().ok());
+            objects.push(RemoteObject { key: format!("{}{}", folder, name), size });
+        }
+        Ok(objects)
+    }
+
+    pub fn get(&self, key: &str) -> Result<Vec<u8>> {
+        read_body(send(self.request("GET", key, &[], &[])?, None)?)
+    }
+
+    pub fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let result = self.request("PUT", key, &[], bytes)?.send_bytes(bytes);
+        if self.location.kind == RemoteKind::WebDav && matches!(result, Err(ureq::Error::Status(409, _))) {
+            // The folder isn't there yet; WebDAV doesn't create it on upload
+            self.make_folders(key)?;
+            send(self.request("PUT", key, &[], bytes)?, Some(bytes))?;
+            return Ok(());
+        }
+        checked(&self.url(key), result)?;
+        Ok(())
+    }
+
+    /// Create the WebDAV folders above a key, outermost first
+    fn make_folders(&self, key: &str) -> Result<()> {
+        let mut folder = String::new();
+        let parts: Vec<&str> = key.split('/').collect();
+        for part in &parts[..parts.len() - 1] {
+            folder.push_str(part);
+            folder.push('/');
+            match self.request("MKCOL", &folder, &[], &[])?.call() {
+                // 405: it exists already
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                result => {
+                    checked(&self.url(&folder), result)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Download a key into `dir`, keeping its file name
+    pub fn download(&self, key: &str, dir: &Path) -> Result<PathBuf> {
+        let bytes = self.get(key)?;
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(download_file_name(key));
+        std::fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
@@ -1,18 +1,202 @@
 //! Document canvas widget for egui
 
-use egui::{Widget, Response, Ui, Sense, Color32, FontId, Pos2, Align2};
-use crate::types::DocumentState;
+use egui::{Widget, Response, Ui, Sense, Color32, FontId, Pos2, Align2, Rect, Vec2};
+use crate::clipboard::{self, CopyFormat, TablePart};
+use crate::palette::Palette;
+use crate::settings::{CopySettings, WidthFitting};
+use crate::types::{BoundingBox, DocumentState};
+use crate::viewport::{PageTransform, Rotation};
+
+/// Temp-data key the canvas uses to hand a double-clicked item (id, text) to the app
+const EDIT_REQUEST_ID: &str = "document_canvas_edit_request";
+
+/// Temp-data key for the item whose comment marker was clicked this frame
+const COMMENT_REQUEST_ID: &str = "document_canvas_comment_request";
+
+/// Temp-data key for the context menu action picked on an item this frame (id, action index)
+const CONTEXT_ACTION_ID: &str = "document_canvas_context_action";
+
+/// Temp-data key for a selection the user changed this frame (item IDs)
+const SELECTION_ID: &str = "document_canvas_selection";
+
+/// Temp-data key for the pointer position over the page, in PDF points
+const POINTER_ID: &str = "document_canvas_pointer";
+
+/// Temp-data key for text the canvas copied this frame (format, text)
+const COPIED_ID: &str = "document_canvas_copied";
+
+/// Temp-data key for a pan the position indicators asked for (dx, dy)
+const PAN_REQUEST_ID: &str = "document_canvas_pan_request";
+
+/// Temp-data keys for the page region being dragged out, and the one finished
+/// this frame (TOPLEFT PDF points)
+const DRAG_REGION_ID: &str = "document_canvas_drag_region";
+const REGION_ID: &str = "document_canvas_region";
+
+/// Temp-data key for the characters selected within an item's text
+const TEXT_SELECTION_ID: &str = "document_canvas_text_selection";
+
+/// Temp-data key for the items whose text overflowed their box this frame
+const OVERFLOW_ID: &str = "document_canvas_overflow";
+
+/// Capital letter height as a share of the font size, for sizing drop caps to their box
+const CAP_HEIGHT: f32 = 0.72;
+
+/// Thickness of the position indicator tracks
+const INDICATOR_WIDTH: f32 = 8.0;
+
+/// The visible part of a page along one axis, as (start, end) fractions of the
+/// page. None when the whole page is visible along that axis.
+pub fn visible_span(page_start: f32, page_len: f32, view_start: f32, view_len: f32) -> Option<(f32, f32)> {
+    if page_len <= 0.0 || (view_start <= page_start && view_start + view_len >= page_start + page_len) {
+        return None;
+    }
+    let start = ((view_start - page_start) / page_len).clamp(0.0, 1.0);
+    let end = ((view_start + view_len - page_start) / page_len).clamp(0.0, 1.0);
+    Some((start, end))
+}
+
+/// How far to pan so the view is centered on `fraction` of the page
+pub fn jump_delta(page_start: f32, page_len: f32, view_start: f32, view_len: f32, fraction: f32) -> f32 {
+    let target = page_start + fraction.clamp(0.0, 1.0) * page_len;
+    view_start + view_len / 2.0 - target
+}
+
+/// Characters selected by dragging across one item's text, as char indexes
+/// into the text as laid out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSelection {
+    pub item_id: String,
+    pub text: String,
+    /// Where the drag started
+    pub anchor: usize,
+    /// Where it is now
+    pub cursor: usize,
+}
+
+impl TextSelection {
+    /// (start, end) char indexes, in text order
+    pub fn range(&self) -> (usize, usize) {
+        (self.anchor.min(self.cursor), self.anchor.max(self.cursor))
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.cursor
+    }
+    
+    /// The selected part of the text
+    pub fn selected(&self) -> &str {
+        let (start, end) = self.range();
+        let byte = |chars: usize| self.text.char_indices().nth(chars).map_or(self.text.len(), |(i, _)| i);
+        &self.text[byte(start)..byte(end)]
+    }
+}
+
+/// Shade the characters in `range` of a galley laid out at `origin` (local)
+fn paint_char_range(painter: &egui::Painter, transform: &PageTransform, galley: &egui::Galley, origin: Vec2, range: (usize, usize), color: Color32) {
+    let mut row_start = 0;
+    for row in &galley.rows {
+        let row_end = row_start + row.char_count_excluding_newline();
+        let (from, to) = (range.0.max(row_start), range.1.min(row_end));
+        if from < to {
+            let local = Rect::from_x_y_ranges(
+                origin.x + row.x_offset(from - row_start)..=origin.x + row.x_offset(to - row_start),
+                origin.y + row.rect.top()..=origin.y + row.rect.bottom(),
+            );
+            painter.rect_filled(transform.local_rect_to_screen(local), 0.0, color);
+        }
+        row_start += row.char_count_including_newline();
+    }
+}
+
+/// On-screen font sizes (px) below which text is drawn as blocks, and as
+/// greeked lines, instead of being laid out
+const BLOCK_BELOW_PX: f32 = 2.5;
+const GREEK_BELOW_PX: f32 = 6.0;
+
+/// How much of an item's text is drawn at the current zoom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDetail {
+    /// A filled rectangle over the item's box
+    Block,
+    /// A bar per line of text, like a design tool's greeking
+    Lines,
+    /// The text itself
+    Full,
+}
+
+impl TextDetail {
+    /// Detail for text `font_px` tall on screen
+    pub fn for_font_px(font_px: f32) -> Self {
+        if font_px < BLOCK_BELOW_PX {
+            TextDetail::Block
+        } else if font_px < GREEK_BELOW_PX {
+            TextDetail::Lines
+        } else {
+            TextDetail::Full
+        }
+    }
+}
+
+/// Laid-out text taller than its box by more than this factor counts as overflowing
+const OVERFLOW_SLACK: f32 = 1.25;
+
+/// How an item's laid-out text fails to fit its box
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Soft-wrapped onto more rows than the text has lines, past the box's bottom
+    Wrapped { rows: usize },
+    /// Cut off at the row limit
+    Clipped,
+}
+
+impl Overflow {
+    /// Classify a layout of `lines` hard lines into `rows` rows of `text_height`,
+    /// against a box `box_height` tall (same units); None when it fits
+    pub fn detect(lines: usize, rows: usize, elided: bool, text_height: f32, box_height: f32) -> Option<Self> {
+        if elided {
+            Some(Overflow::Clipped)
+        } else if rows > lines.max(1) && text_height > box_height * OVERFLOW_SLACK {
+            Some(Overflow::Wrapped { rows })
+        } else {
+            None
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Overflow::Wrapped { rows } => format!("wraps to {} rows, past its box", rows),
+            Overflow::Clipped => "cut off, too long to show".to_string(),
+        }
+    }
+}
 
 pub struct DocumentCanvas {
     document_state: DocumentState,
-    copied_text: Option<String>,
+    copy_settings: CopySettings,
+    palette: Palette,
+    width_fitting: WidthFitting,
+    /// Whether dragging across an item selects part of its text
+    text_selection: bool,
+    /// Rendered PDF page shown faintly under the text, and its opacity
+    ghost_page: Option<(egui::TextureId, f32)>,
+    /// Rendered PDF page that formulas are cropped from
+    page_image: Option<egui::TextureId>,
+    /// Labels of the actions offered when an item is right-clicked
+    context_actions: Vec<String>,
 }
 
 impl DocumentCanvas {
     pub fn new(document_state: DocumentState) -> Self {
         Self {
             document_state,
-            copied_text: None,
+            copy_settings: CopySettings::default(),
+            palette: Palette::default(),
+            width_fitting: WidthFitting::default(),
+            text_selection: true,
+            ghost_page: None,
+            page_image: None,
+            context_actions: Vec::new(),
         }
     }
     
@@ -20,26 +204,275 @@ impl DocumentCanvas {
         self.document_state.zoom = zoom;
         self
     }
+    
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.document_state.rotation = rotation;
+        self
+    }
+    
+    /// Which copy format each click modifier uses
+    pub fn with_copy_settings(mut self, copy_settings: CopySettings) -> Self {
+        self.copy_settings = copy_settings;
+        self
+    }
+    
+    /// Colors for highlights, item types and review markers
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+    
+    /// Stretch or squeeze single-line items to their box width
+    pub fn with_width_fitting(mut self, width_fitting: WidthFitting) -> Self {
+        self.width_fitting = width_fitting;
+        self
+    }
+    
+    /// Let drags that start on an item select part of its text; when off they
+    /// select items and drag out regions like drags on empty space
+    pub fn with_text_selection(mut self, enabled: bool) -> Self {
+        self.text_selection = enabled;
+        self
+    }
+    
+    /// Draw the rendered page under the extracted text so misplaced or missing text stands out
+    pub fn with_ghost_page(mut self, texture: egui::TextureId, opacity: f32) -> Self {
+        self.ghost_page = Some((texture, opacity.clamp(0.0, 1.0)));
+        self
+    }
+    
+    /// Show formulas as their region of the rendered page instead of their (often garbled) text
+    pub fn with_page_image(mut self, texture: egui::TextureId) -> Self {
+        self.page_image = Some(texture);
+        self
+    }
+    
+    /// Offer these actions on a right-click menu over items
+    pub fn with_context_actions(mut self, actions: Vec<String>) -> Self {
+        self.context_actions = actions;
+        self
+    }
+    
+    /// Take the item the user double-clicked this frame, if any
+    pub fn take_edit_request(ctx: &egui::Context) -> Option<(String, String)> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(EDIT_REQUEST_ID)))
+    }
+    
+    /// Take the item whose comment marker the user clicked this frame, if any
+    pub fn take_comment_request(ctx: &egui::Context) -> Option<String> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(COMMENT_REQUEST_ID)))
+    }
+    
+    /// Take the context menu action picked this frame: the right-clicked item and the action's index
+    pub fn take_context_action(ctx: &egui::Context) -> Option<(String, usize)> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(CONTEXT_ACTION_ID)))
+    }
+    
+    /// Take the new selection if the user changed it this frame
+    pub fn take_selection(ctx: &egui::Context) -> Option<Vec<String>> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(SELECTION_ID)))
+    }
+    
+    /// Take the pointer position over the page (TOPLEFT PDF points), if it was over the page this frame
+    pub fn take_pointer_position(ctx: &egui::Context) -> Option<(f32, f32)> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(POINTER_ID)))
+    }
+    
+    /// Take what the canvas copied to the clipboard this frame
+    pub fn take_copied(ctx: &egui::Context) -> Option<(CopyFormat, String)> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(COPIED_ID)))
+    }
+    
+    /// The characters currently selected within an item, if any
+    pub fn text_selection(ctx: &egui::Context) -> Option<TextSelection> {
+        ctx.data(|d| d.get_temp(egui::Id::new(TEXT_SELECTION_ID)))
+    }
+    
+    /// Take the items whose text didn't fit their box when the page was last drawn
+    pub fn take_overflow(ctx: &egui::Context) -> Option<Vec<(String, Overflow)>> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(OVERFLOW_ID)))
+    }
+    
+    /// Take the page region the user finished dragging out this frame
+    pub fn take_region(ctx: &egui::Context) -> Option<BoundingBox> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(REGION_ID)))
+    }
+    
+    /// Take the pan the user asked for by clicking a position indicator
+    pub fn take_pan_request(ctx: &egui::Context) -> Option<(f32, f32)> {
+        ctx.data_mut(|d| d.remove_temp(egui::Id::new(PAN_REQUEST_ID)))
+    }
+    
+    fn copy(ctx: &egui::Context, format: CopyFormat, text: String) {
+        ctx.copy_text(text.clone());
+        ctx.data_mut(|d| d.insert_temp(egui::Id::new(COPIED_ID), (format, text)));
+    }
+    
+    /// Where the page sits in the canvas rectangle
+    fn transform(&self, rect: Rect) -> PageTransform {
+        let origin = Pos2::new(
+            rect.left() + 20.0 + self.document_state.offset.0,
+            rect.top() + 50.0 + self.document_state.offset.1,
+        );
+        PageTransform {
+            origin,
+            scale: self.document_state.zoom,
+            rotation: self.document_state.rotation,
+            page_size: self.document_state.page_size,
+        }
+    }
+    
+    /// IDs of the items whose boxes intersect a screen rectangle, in page order
+    fn items_in(&self, rect: Rect, screen: Rect) -> Vec<String> {
+        let transform = self.transform(rect);
+        self.document_state.items.iter()
+            .filter(|item| {
+                let offset = self.document_state.item_offsets.get(&item.id).copied().unwrap_or((0.0, 0.0));
+                transform.bbox_to_screen(&item.bbox, Vec2::from(offset)).intersects(screen)
+            })
+            .map(|item| item.id.clone())
+            .collect()
+    }
+    
+    /// Tracks along the right and bottom of the visible area showing which part
+    /// of the page is in view; clicking or dragging one jumps there
+    fn position_indicators(&self, ui: &mut Ui, rect: Rect) {
+        let view = rect.intersect(ui.clip_rect());
+        if !view.is_positive() {
+            return;
+        }
+        let page = self.transform(rect).page_rect();
+        
+        for vertical in [true, false] {
+            let (page_start, page_len, view_start, view_len) = if vertical {
+                (page.top(), page.height(), view.top(), view.height())
+            } else {
+                (page.left(), page.width(), view.left(), view.width())
+            };
+            let Some((start, end)) = visible_span(page_start, page_len, view_start, view_len) else { continue };
+            
+            let track = if vertical {
+                Rect::from_min_max(
+                    Pos2::new(view.right() - INDICATOR_WIDTH - 2.0, view.top() + 2.0),
+                    Pos2::new(view.right() - 2.0, view.bottom() - INDICATOR_WIDTH - 4.0),
+                )
+            } else {
+                Rect::from_min_max(
+                    Pos2::new(view.left() + 2.0, view.bottom() - INDICATOR_WIDTH - 2.0),
+                    Pos2::new(view.right() - INDICATOR_WIDTH - 4.0, view.bottom() - 2.0),
+                )
+            };
+            let response = ui.interact(track, ui.id().with(("position_indicator", vertical)), Sense::click_and_drag());
+            
+            if response.clicked() || response.dragged() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let fraction = if vertical {
+                        (pos.y - track.top()) / track.height()
+                    } else {
+                        (pos.x - track.left()) / track.width()
+                    };
+                    let delta = jump_delta(page_start, page_len, view_start, view_len, fraction);
+                    let pan = if vertical { (0.0, delta) } else { (delta, 0.0) };
+                    ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(PAN_REQUEST_ID), pan));
+                }
+            }
+            
+            let thumb = if vertical {
+                Rect::from_x_y_ranges(track.x_range(), track.top() + start * track.height()..=track.top() + end * track.height())
+            } else {
+                Rect::from_x_y_ranges(track.left() + start * track.width()..=track.left() + end * track.width(), track.y_range())
+            };
+            let active = response.hovered() || response.dragged();
+            ui.painter().rect_filled(track, INDICATOR_WIDTH / 2.0, Color32::from_black_alpha(if active { 30 } else { 15 }));
+            ui.painter().rect_filled(thumb, INDICATOR_WIDTH / 2.0, Color32::from_black_alpha(if active { 140 } else { 90 }));
+        }
+    }
+    
+    /// Copy the selected items in the plain-click format
+    fn copy_selection(&self, ctx: &egui::Context) {
+        let items: Vec<_> = self.document_state.items.iter()
+            .filter(|item| self.document_state.selected_items.contains(&item.id))
+            .map(|item| {
+                let mut item = item.clone();
+                if let Some(text) = self.document_state.item_text_overrides.get(&item.id) {
+                    item.content = text.clone();
+                }
+                item
+            })
+            .collect();
+        if items.is_empty() {
+            return;
+        }
+        let format = self.copy_settings.click;
+        Self::copy(ctx, format, clipboard::format_items(&items, format));
+    }
 }
 
 impl Widget for DocumentCanvas {
     fn ui(mut self, ui: &mut Ui) -> Response {
-        // Calculate the actual size needed for the PDF page
-        let page_width = self.document_state.page_size.0 * self.document_state.zoom;
-        let page_height = self.document_state.page_size.1 * self.document_state.zoom;
+        // Calculate the actual size needed for the PDF page, as turned on screen
+        let page = self.transform(Rect::NOTHING).page_rect();
         
         // Add some padding
         let canvas_size = egui::Vec2::new(
-            page_width + 40.0,  // 20px padding on each side
-            page_height + 80.0  // Extra padding for status text
+            page.width() + 40.0,  // 20px padding on each side
+            page.height() + 80.0  // Extra padding for status text
         );
         
         // Allocate the full size needed for the page
         let (rect, response) = ui.allocate_exact_size(
             canvas_size, 
-            Sense::hover()
+            Sense::click_and_drag()
         );
         
+        // Report where the pointer is on the page
+        if ui.rect_contains_pointer(rect) {
+            if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
+                let transform = self.transform(rect);
+                let point = transform.to_page(pos);
+                if transform.contains(point) {
+                    ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(POINTER_ID), point));
+                }
+            }
+        }
+        
+        // Drag on the background to select items, click on it to clear the selection.
+        // Other buttons are left to the app for panning.
+        let mut marquee = None;
+        if response.dragged_by(egui::PointerButton::Primary) {
+            if let (Some(start), Some(end)) = (ui.input(|i| i.pointer.press_origin()), response.interact_pointer_pos()) {
+                let screen = Rect::from_two_pos(start, end);
+                self.document_state.selected_items = self.items_in(rect, screen);
+                ui.ctx().data_mut(|d| d.insert_temp(
+                    egui::Id::new(SELECTION_ID),
+                    self.document_state.selected_items.clone(),
+                ));
+                marquee = Some(screen);
+                
+                let region = self.transform(rect).screen_rect_to_bbox(screen);
+                ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(DRAG_REGION_ID), region));
+            }
+        } else if response.drag_stopped_by(egui::PointerButton::Primary) {
+            if let Some(region) = ui.ctx().data_mut(|d| d.remove_temp::<BoundingBox>(egui::Id::new(DRAG_REGION_ID))) {
+                ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(REGION_ID), region));
+            }
+        } else if response.clicked() {
+            ui.ctx().data_mut(|d| d.remove::<TextSelection>(egui::Id::new(TEXT_SELECTION_ID)));
+            if !self.document_state.selected_items.is_empty() {
+                self.document_state.selected_items.clear();
+                ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(SELECTION_ID), Vec::<String>::new()));
+            }
+        }
+        
+        // Cmd+C copies the text selected within an item, else the whole selection
+        let copy_requested = ui.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        if copy_requested && !ui.ctx().wants_keyboard_input() {
+            match Self::text_selection(ui.ctx()).filter(|s| !s.is_empty()) {
+                Some(selected) => Self::copy(ui.ctx(), CopyFormat::Plain, selected.selected().to_string()),
+                None => self.copy_selection(ui.ctx()),
+            }
+        }
+        
         if ui.is_rect_visible(rect) {
             // Draw white background
             ui.painter().rect_filled(
@@ -48,6 +481,17 @@ impl Widget for DocumentCanvas {
                 Color32::from_gray(250),
             );
             
+            if let Some((texture, opacity)) = self.ghost_page {
+                let page = Rect::from_min_size(Pos2::ZERO, Vec2::from(self.document_state.page_size) * self.document_state.zoom);
+                self.transform(rect).paint_image(
+                    ui.painter(),
+                    texture,
+                    page,
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::from_white_alpha((opacity * 255.0) as u8),
+                );
+            }
+            
             // Draw status overlay
             let status_color = if response.hovered() {
                 Color32::from_gray(80) // Darker when hovering
@@ -75,29 +519,37 @@ impl Widget for DocumentCanvas {
             
             // Draw column boundaries if in multi-column layout
             if self.document_state.column_count > 1 && !self.document_state.column_boundaries.is_empty() {
-                let scale = self.document_state.zoom;
-                let base_offset = (20.0 + self.document_state.offset.0, 50.0 + self.document_state.offset.1);
+                let transform = self.transform(rect);
                 
-                for boundary_x in &self.document_state.column_boundaries {
-                    let x = base_offset.0 + (boundary_x * scale) + rect.left();
-                    
-                    // Draw subtle vertical line
+                for &boundary_x in &self.document_state.column_boundaries {
+                    // Draw subtle line down the page
                     ui.painter().line_segment(
                         [
-                            Pos2::new(x, rect.top() + 50.0),
-                            Pos2::new(x, rect.bottom() - 20.0)
+                            transform.to_screen((boundary_x, 0.0)),
+                            transform.to_screen((boundary_x, self.document_state.page_size.1)),
                         ],
-                        egui::Stroke::new(1.0, Color32::from_rgba_premultiplied(59, 130, 246, 60))
+                        egui::Stroke::new(1.0, Palette::with_alpha(self.palette.hover, 60))
                     );
                 }
             }
             
+            // Outline named regions, labelled with their names
+            if !self.document_state.named_regions.is_empty() {
+                let transform = self.transform(rect);
+                let color = Palette::color(self.palette.annotation);
+                for (name, bbox) in &self.document_state.named_regions {
+                    let region = transform.bbox_to_screen(bbox, Vec2::ZERO);
+                    ui.painter().rect_stroke(region, 2.0, egui::Stroke::new(1.5, color));
+                    ui.painter().text(region.left_top() + Vec2::new(2.0, -2.0), Align2::LEFT_BOTTOM, name, FontId::proportional(10.0), color);
+                }
+            }
+            
             // Show interaction hint when hovering
             if response.hovered() {
                 ui.painter().text(
                     Pos2::new(rect.left() + 10.0, rect.top() + 25.0),
                     Align2::LEFT_TOP,
-                    "Click to copy • Cmd+scroll to zoom",
+                    "Click to copy (Shift/Alt/Cmd for other formats) • Cmd+scroll to zoom",
                     FontId::proportional(10.0),
                     Color32::from_gray(120),
                 );
@@ -106,20 +558,14 @@ impl Widget for DocumentCanvas {
             // Render text items
             self.render_text_overlay(ui, rect);
             
-            // Show copied text notification
-            if let Some(copy_text) = &self.copied_text {
-                let preview = if copy_text.len() > 50 {
-                    format!("{}...", &copy_text[..50])
-                } else {
-                    copy_text.clone()
-                };
-                
-                ui.painter().text(
-                    Pos2::new(rect.center().x, rect.bottom() - 30.0),
-                    Align2::CENTER_BOTTOM,
-                    format!("📋 Copied: {}", preview),
-                    FontId::proportional(12.0),
-                    Color32::from_rgb(16, 185, 129),
+            self.position_indicators(ui, rect);
+            
+            if let Some(marquee) = marquee {
+                ui.painter().rect(
+                    marquee,
+                    0.0,
+                    Palette::with_alpha(self.palette.hover, 30),
+                    egui::Stroke::new(1.0, Palette::color(self.palette.hover)),
                 );
             }
         }
@@ -130,10 +576,14 @@ impl Widget for DocumentCanvas {
 
 impl DocumentCanvas {
     fn render_text_overlay(&mut self, ui: &mut Ui, rect: egui::Rect) {
-        // Use zoom directly as scale since we're allocating the proper size
+        // Use zoom directly as scale since we're allocating the proper size.
+        // Positions below are local: unrotated screen offsets from the page origin.
         let scale = self.document_state.zoom;
-        let offset = self.document_state.offset;
-        let base_offset = (20.0 + offset.0, 50.0 + offset.1);
+        let transform = self.transform(rect);
+        let angle = transform.rotation.radians();
+        let mut selection = None;
+        let mut text_selection = Self::text_selection(ui.ctx());
+        let mut overflowing = Vec::new();
         
         for (idx, item) in self.document_state.items.iter().enumerate() {
             ui.push_id(format!("text_item_{}_{}", item.id, idx), |ui| {
@@ -143,16 +593,13 @@ impl DocumentCanvas {
                     .unwrap_or((0.0, 0.0));
                 
                 // Calculate position - coordinates are already in top-left origin
-                let x = base_offset.0 + (item.bbox.left as f32 * scale) + item_offset.0;
-                let y = base_offset.1 + (item.bbox.top as f32 * scale) + item_offset.1;
+                let x = (item.bbox.left as f32 * scale) + item_offset.0;
                 
-                // Determine if this needs wrapping
-                let needs_wrapping = item.content.len() > 50 || 
+                // Determine if this needs wrapping; code keeps its lines as they are
+                let is_code = item.item_type == crate::types::ItemType::Code;
+                let needs_wrapping = !is_code && (item.content.len() > 50 || 
                                     item.content.contains(". ") ||
-                                    item.content.contains("must be signed");
-                
-                // Calculate max width for text
-                let available_width = rect.width() - x - 20.0; // Leave right margin
+                                    item.content.contains("must be signed"));
                 
                 // Use bbox width directly for more accurate positioning
                 let bbox_width = item.bbox.width as f32 * scale;
@@ -185,25 +632,12 @@ impl DocumentCanvas {
                     _ => font_size,
                 };
                 
-                // Apply font style
-                let font_id = if item.bold && item.italic {
-                    FontId::new(base_font_size, egui::FontFamily::Proportional)
-                } else if item.bold {
-                    FontId::new(base_font_size, egui::FontFamily::Proportional)
-                } else if item.italic {
-                    FontId::new(base_font_size, egui::FontFamily::Proportional)
-                } else {
-                    FontId::proportional(base_font_size)
-                };
+                // Apply font style (egui has no bold/italic families, italics go through TextFormat)
+                let mut font_id = if is_code { FontId::monospace(base_font_size) } else { FontId::proportional(base_font_size) };
                 let color = if is_search_match {
-                    Color32::from_rgb(255, 165, 0) // Orange for highlights
+                    Palette::color(self.palette.search_text)
                 } else {
-                    match &item.item_type {
-                        crate::types::ItemType::FormLabel => Color32::from_rgb(0, 0, 139), // Dark blue for form labels
-                        crate::types::ItemType::FormField => Color32::from_gray(60), // Dark gray for form fields
-                        crate::types::ItemType::Checkbox => Color32::from_gray(40), // Darker for checkboxes
-                        _ => Color32::from_gray(20),
-                    }
+                    self.palette.item_type_color(&item.item_type)
                 };
                 
                 // Get text to display (with overrides)
@@ -211,20 +645,84 @@ impl DocumentCanvas {
                     .cloned()
                     .unwrap_or_else(|| item.content.clone());
                 
+                // Too small to read: skip the layout and draw its shape
+                let detail = TextDetail::for_font_px(base_font_size_raw);
+                if detail != TextDetail::Full && item.item_type != crate::types::ItemType::Formula {
+                    let box_local = Rect::from_min_size(
+                        Pos2::new(x, item.bbox.top as f32 * scale + item_offset.1),
+                        Vec2::new(bbox_width, item.bbox.height as f32 * scale),
+                    );
+                    if self.document_state.selected_items.contains(&item.id) {
+                        ui.painter().rect_filled(transform.local_rect_to_screen(box_local), 0.0, Palette::color(self.palette.selection));
+                    } else if is_search_match {
+                        ui.painter().rect_filled(transform.local_rect_to_screen(box_local), 0.0, Palette::color(self.palette.search_highlight));
+                    }
+                    let shade = color.gamma_multiply(0.35);
+                    match detail {
+                        TextDetail::Block => {
+                            ui.painter().rect_filled(transform.local_rect_to_screen(box_local), 0.0, shade);
+                        }
+                        _ => {
+                            let line_height = base_font_size_raw * 1.2;
+                            let lines = ((box_local.height() / line_height).round() as usize).max(1);
+                            let pitch = box_local.height() / lines as f32;
+                            for line in 0..lines {
+                                let bar = Rect::from_min_size(
+                                    Pos2::new(box_local.left(), box_local.top() + line as f32 * pitch + pitch * 0.25),
+                                    Vec2::new(box_local.width(), pitch * 0.5),
+                                );
+                                ui.painter().rect_filled(transform.local_rect_to_screen(bar), 0.0, shade);
+                            }
+                        }
+                    }
+                    let item_rect = transform.local_rect_to_screen(box_local);
+                    let response = ui.interact(item_rect, ui.id().with(item.id.clone()), Sense::click());
+                    if response.clicked() {
+                        text_selection = None;
+                        selection = Some(vec![item.id.clone()]);
+                    }
+                    if response.hovered() {
+                        ui.painter().rect_stroke(item_rect.expand(1.0), 2.0, egui::Stroke::new(1.0, Palette::color(self.palette.hover)));
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                    }
+                    return;
+                }
+                
+                // A merged drop cap is drawn on its own at its box; the rest flows beside it
+                let laid_out = match &item.drop_cap {
+                    Some(cap) if !cap.decorative => text.strip_prefix(cap.glyph.as_str()).unwrap_or(&text).to_string(),
+                    _ => text.clone(),
+                };
+                
+                // Fit single lines to their box so justified text keeps its shape
+                let mut letter_spacing = 0.0;
+                let fits = self.width_fitting != WidthFitting::Off
+                    && !needs_wrapping
+                    && !laid_out.contains('\n')
+                    && !matches!(item.item_type, crate::types::ItemType::Checkbox | crate::types::ItemType::Code);
+                if fits {
+                    let natural_width = ui.fonts(|f| f.layout_no_wrap(laid_out.clone(), font_id.clone(), color)).rect.width();
+                    let (size, spacing) = self.width_fitting.fit(font_id.size, natural_width, bbox_width, laid_out.chars().count());
+                    font_id.size = size;
+                    letter_spacing = spacing;
+                }
+                
                 // Create a layout job for styled text
                 let mut job = egui::text::LayoutJob::single_section(
-                    text.clone(),
+                    laid_out.clone(),
                     egui::text::TextFormat {
                         font_id: font_id.clone(),
                         color,
                         // Apply text decorations
                         italics: item.italic,
+                        extra_letter_spacing: letter_spacing,
                         // Note: egui doesn't have a direct bold property in TextFormat
                         // Bold is handled through font selection
                         ..Default::default()
                     }
                 );
-                job.wrap.max_width = max_width;
+                // Fitted lines are already the box's width; don't let the 10% slack wrap them
+                job.wrap.max_width = if fits || is_code { f32::INFINITY } else { max_width };
                 job.wrap.break_anywhere = false;
                 job.wrap.max_rows = 10; // Allow text to wrap to multiple lines
                 
@@ -233,16 +731,67 @@ impl DocumentCanvas {
                 
                 // Get the actual height the text needs
                 let text_height = galley.rect.height();
+                let overflow = Overflow::detect(
+                    laid_out.lines().count(),
+                    galley.rows.len(),
+                    galley.elided,
+                    text_height,
+                    item.bbox.height as f32 * scale,
+                ).filter(|_| !matches!(item.item_type, crate::types::ItemType::Checkbox | crate::types::ItemType::Formula));
+                
+                // Formulas are drawn as the matching crop of the page, at their box
+                let formula_crop = self.page_image
+                    .filter(|_| item.item_type == crate::types::ItemType::Formula)
+                    .map(|texture| {
+                        let (page_width, page_height) = self.document_state.page_size;
+                        let uv = Rect::from_min_size(
+                            Pos2::new(item.bbox.left as f32 / page_width, item.bbox.top as f32 / page_height),
+                            egui::Vec2::new(item.bbox.width as f32 / page_width, item.bbox.height as f32 / page_height),
+                        );
+                        (texture, uv)
+                    });
+                
+                // Sit the first line on the item's baseline so neighbouring lines align
+                // like in the PDF; checkboxes are drawn as boxes and keep to the top
+                let galley_baseline = galley.rows.first()
+                    .and_then(|row| row.glyphs.first())
+                    .map_or(0.0, |glyph| glyph.pos.y);
+                let y = if matches!(item.item_type, crate::types::ItemType::Checkbox) || formula_crop.is_some() {
+                    (item.bbox.top as f32 * scale) + item_offset.1
+                } else {
+                    (item.baseline() as f32 * scale) + item_offset.1 - galley_baseline
+                };
+                let content_local = |size: Vec2| Rect::from_min_size(Pos2::new(x, y), size);
                 
-                // Draw highlight background if this is a search match
-                if is_search_match {
+                // What the item occupies on screen: its box for formula crops, else its text
+                let content_size = if formula_crop.is_some() {
+                    egui::Vec2::new(item.bbox.width as f32, item.bbox.height as f32) * scale
+                } else {
+                    egui::Vec2::new(galley.rect.width(), text_height)
+                };
+                
+                // Draw selection background
+                if self.document_state.selected_items.contains(&item.id) {
+                    ui.painter().rect_filled(
+                        transform.local_rect_to_screen(content_local(content_size)),
+                        2.0,
+                        Palette::color(self.palette.selection)
+                    );
+                }
+                
+                // Search matches are highlighted where they occur in the text,
+                // or as a whole where the text isn't drawn as characters
+                let has_glyphs = formula_crop.is_none() && !matches!(item.item_type, crate::types::ItemType::Checkbox);
+                let match_ranges = if is_search_match && has_glyphs {
+                    crate::search::match_ranges(&laid_out, &self.document_state.search_query)
+                } else {
+                    Vec::new()
+                };
+                if is_search_match && match_ranges.is_empty() {
                     ui.painter().rect_filled(
-                        egui::Rect::from_min_size(
-                            Pos2::new(x + rect.left(), y + rect.top()),
-                            egui::Vec2::new(galley.rect.width(), text_height)
-                        ),
+                        transform.local_rect_to_screen(content_local(content_size)),
                         0.0,
-                        Color32::from_rgba_premultiplied(255, 255, 0, 60) // Yellow highlight
+                        Palette::color(self.palette.search_highlight)
                     );
                 }
                 
@@ -250,14 +799,11 @@ impl DocumentCanvas {
                 if matches!(item.item_type, crate::types::ItemType::Checkbox) {
                     // Draw checkbox as a square
                     let checkbox_size = base_font_size * 0.8;
-                    let checkbox_rect = egui::Rect::from_min_size(
-                        Pos2::new(x + rect.left(), y + rect.top()),
-                        egui::Vec2::splat(checkbox_size)
-                    );
+                    let checkbox_rect = content_local(egui::Vec2::splat(checkbox_size));
                     
                     // Draw checkbox outline
                     ui.painter().rect_stroke(
-                        checkbox_rect,
+                        transform.local_rect_to_screen(checkbox_rect),
                         2.0,
                         egui::Stroke::new(1.5, color)
                     );
@@ -266,14 +812,14 @@ impl DocumentCanvas {
                     if item.content.contains('x') || item.content.contains('X') || 
                        item.content.contains('☑') || item.content.contains('■') {
                         // Draw checkmark
-                        let check_points = vec![
+                        let check_points = [
                             Pos2::new(checkbox_rect.left() + checkbox_size * 0.2, 
                                      checkbox_rect.center().y),
                             Pos2::new(checkbox_rect.center().x - checkbox_size * 0.1, 
                                      checkbox_rect.bottom() - checkbox_size * 0.3),
                             Pos2::new(checkbox_rect.right() - checkbox_size * 0.2, 
                                      checkbox_rect.top() + checkbox_size * 0.3),
-                        ];
+                        ].map(|point| transform.local_to_screen(point.to_vec2()));
                         ui.painter().line_segment(
                             [check_points[0], check_points[1]],
                             egui::Stroke::new(2.0, color)
@@ -283,40 +829,85 @@ impl DocumentCanvas {
                             egui::Stroke::new(2.0, color)
                         );
                     }
+                } else if let Some((texture, uv)) = formula_crop {
+                    transform.paint_image(ui.painter(), texture, content_local(content_size), uv, Color32::WHITE);
                 } else {
-                    // Draw the text normally
-                    ui.painter().galley(
-                        Pos2::new(x + rect.left(), y + rect.top()),
-                        galley.clone(),
-                        color,
-                    );
+                    for range in &match_ranges {
+                        paint_char_range(ui.painter(), &transform, &galley, Vec2::new(x, y), (range.start, range.end), Palette::color(self.palette.search_highlight));
+                    }
+                    
+                    // Shade the characters selected by dragging across the text
+                    if let Some(selected) = text_selection.as_ref().filter(|s| s.item_id == item.id && !s.is_empty()) {
+                        paint_char_range(ui.painter(), &transform, &galley, Vec2::new(x, y), selected.range(), Palette::color(self.palette.selection));
+                    }
+                    
+                    // Draw the text normally, turned with the page
+                    let anchor = transform.local_to_screen(Vec2::new(x, y));
+                    ui.painter().add(egui::epaint::TextShape::new(anchor, galley.clone(), color).with_angle(angle));
+                }
+                
+                // Drop cap, sitting on the baseline at the bottom of its box
+                if let Some(cap) = &item.drop_cap {
+                    let cap_font = FontId::proportional((cap.bbox.height as f32 * scale / CAP_HEIGHT).max(1.0));
+                    let cap_galley = ui.fonts(|f| f.layout_no_wrap(cap.glyph.clone(), cap_font, color));
+                    let cap_baseline = cap_galley.rows.first()
+                        .and_then(|row| row.glyphs.first())
+                        .map_or(0.0, |glyph| glyph.pos.y);
+                    let anchor = transform.local_to_screen(Vec2::new(
+                        cap.bbox.left as f32 * scale + item_offset.0,
+                        (cap.bbox.top + cap.bbox.height) as f32 * scale + item_offset.1 - cap_baseline,
+                    ));
+                    ui.painter().add(egui::epaint::TextShape::new(anchor, cap_galley, color).with_angle(angle));
                 }
                 
                 // Add some padding to prevent overlapping
                 let padding = 2.0;
                 
                 // Always allow interaction
-                let item_rect = egui::Rect::from_min_size(
-                    Pos2::new(x + rect.left(), y + rect.top()),
-                    egui::Vec2::new(galley.rect.width() + padding * 2.0, text_height + padding * 2.0)
-                );
+                let item_rect = transform.local_rect_to_screen(content_local(content_size + egui::Vec2::splat(padding * 2.0)));
+                
+                // Check if pointer is over this item; drags across text select part of it
+                let selectable = self.text_selection && formula_crop.is_none()
+                    && item.item_type != crate::types::ItemType::Checkbox;
+                let sense = if selectable { Sense::click_and_drag() } else { Sense::click() };
+                let response = ui.interact(item_rect, ui.id().with(item.id.clone()), sense);
                 
-                // Check if pointer is over this item
-                let response = ui.interact(item_rect, ui.id().with(item.id.clone()), Sense::click());
+                let char_at = |pos: Pos2| galley.cursor_from_pos(transform.screen_to_local(pos) - Vec2::new(x, y)).ccursor.index;
+                if selectable {
+                    if response.drag_started_by(egui::PointerButton::Primary) {
+                        if let Some(start) = ui.input(|i| i.pointer.press_origin()) {
+                            let anchor = char_at(start);
+                            text_selection = Some(TextSelection { item_id: item.id.clone(), text: laid_out.clone(), anchor, cursor: anchor });
+                        }
+                    } else if response.dragged_by(egui::PointerButton::Primary) {
+                        if let (Some(selected), Some(pos)) = (text_selection.as_mut().filter(|s| s.item_id == item.id), response.interact_pointer_pos()) {
+                            selected.cursor = char_at(pos);
+                        }
+                    }
+                    if response.hovered() && !response.dragged() {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::Text);
+                    }
+                }
                 
-                // Handle click - copy text
+                // Handle click - copy text in the format picked by the held modifier
                 if response.clicked() {
-                    // Get text (with overrides)
-                    let text = self.document_state.item_text_overrides.get(&item.id)
-                        .cloned()
-                        .unwrap_or_else(|| item.content.clone());
-                    
-                    // Copy text to clipboard
-                    ui.ctx().copy_text(text.clone());
-                    self.copied_text = Some(text);
+                    text_selection = None;
+                    let modifiers = ui.input(|i| i.modifiers);
+                    let table_part = TablePart::for_modifiers(modifiers)
+                        .filter(|_| item.item_type == crate::types::ItemType::Table);
+                    if let (Some(part), Some(pos)) = (table_part, response.interact_pointer_pos()) {
+                        // Just the clicked row or column, ready to paste into a spreadsheet
+                        let cell = clipboard::table_cell_at(&laid_out, char_at(pos));
+                        Self::copy(ui.ctx(), CopyFormat::Tsv, clipboard::table_part(&laid_out, cell, part));
+                    } else {
+                        let format = self.copy_settings.format_for(modifiers);
+                        let mut copied_item = item.clone();
+                        copied_item.content = text.clone();
+                        Self::copy(ui.ctx(), format, clipboard::format_items(&[copied_item], format));
+                    }
                     
-                    // Visual feedback
-                    ui.ctx().request_repaint_after(std::time::Duration::from_secs(2));
+                    // Clicking an item also selects it
+                    selection = Some(vec![item.id.clone()]);
                 }
                 
                 // Draw hover effect
@@ -324,13 +915,123 @@ impl DocumentCanvas {
                     ui.painter().rect_stroke(
                         item_rect.expand(2.0),
                         4.0,
-                        egui::Stroke::new(1.0, Color32::from_rgb(59, 130, 246))
+                        egui::Stroke::new(1.0, Palette::color(self.palette.hover))
                     );
                     
                     // Show pointer cursor
-                    ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                    if !selectable {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                    }
+                }
+                
+                if !self.context_actions.is_empty() {
+                    response.context_menu(|ui| {
+                        for (index, action) in self.context_actions.iter().enumerate() {
+                            if ui.button(action).clicked() {
+                                ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(CONTEXT_ACTION_ID), (item.id.clone(), index)));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
+                
+                // Handle double-click - ask the app to open the edit dialog
+                if response.double_clicked() {
+                    ui.ctx().data_mut(|d| d.insert_temp(
+                        egui::Id::new(EDIT_REQUEST_ID),
+                        (item.id.clone(), text.clone()),
+                    ));
+                }
+                
+                // Show which items collaborators are currently editing
+                if let Some(peer_name) = self.document_state.remote_editing.get(&item.id) {
+                    let peer_color = Palette::color(self.palette.peer_editing);
+                    ui.painter().rect_stroke(
+                        item_rect.expand(3.0),
+                        4.0,
+                        egui::Stroke::new(2.0, peer_color)
+                    );
+                    ui.painter().text(
+                        Pos2::new(item_rect.left(), item_rect.top() - 4.0),
+                        Align2::LEFT_BOTTOM,
+                        format!("✏ {}", peer_name),
+                        FontId::proportional(10.0),
+                        peer_color,
+                    );
+                }
+                
+                // Flag text that doesn't fit its box, at the bottom right corner
+                if let Some(overflow) = overflow {
+                    let marker_pos = Pos2::new(item_rect.right() + 2.0, item_rect.bottom());
+                    ui.painter().text(
+                        marker_pos,
+                        Align2::LEFT_BOTTOM,
+                        "⚠",
+                        FontId::proportional(9.0),
+                        Palette::color(self.palette.overflow),
+                    );
+                    let marker_rect = egui::Rect::from_min_size(marker_pos - egui::Vec2::new(0.0, 10.0), egui::Vec2::splat(10.0));
+                    ui.interact(marker_rect, ui.id().with("overflow"), Sense::hover())
+                        .on_hover_text(format!("Text {}", overflow.describe()));
+                    overflowing.push((item.id.clone(), overflow));
+                }
+                
+                // Outline items holding personal data
+                if self.document_state.pii_items.contains(&item.id) {
+                    ui.painter().rect_stroke(item_rect.expand(1.0), 2.0, egui::Stroke::new(1.5, Palette::color(self.palette.pii)));
+                }
+                
+                // Squiggle under items that differ from the reference transcript
+                if self.document_state.divergent_items.contains(&item.id) {
+                    let stroke = egui::Stroke::new(1.5, Palette::color(self.palette.divergent));
+                    let y = item_rect.bottom() + 1.0;
+                    let points: Vec<Pos2> = (0..=((item_rect.width() / 3.0) as usize))
+                        .map(|i| Pos2::new(item_rect.left() + i as f32 * 3.0, if i % 2 == 0 { y } else { y + 2.0 }))
+                        .collect();
+                    ui.painter().add(egui::Shape::line(points, stroke));
+                }
+                
+                // Mark items carrying a user annotation
+                if let Some(annotation) = self.document_state.item_annotations.get(&item.id) {
+                    let marker_pos = Pos2::new(item_rect.right() + 2.0, item_rect.top());
+                    ui.painter().text(
+                        marker_pos,
+                        Align2::LEFT_TOP,
+                        "📝",
+                        FontId::proportional(10.0),
+                        Palette::color(self.palette.annotation),
+                    );
+                    let marker_rect = egui::Rect::from_min_size(marker_pos, egui::Vec2::splat(12.0));
+                    ui.interact(marker_rect, ui.id().with("annotation"), Sense::hover())
+                        .on_hover_text(annotation);
+                }
+                
+                // Mark items with a comment thread; resolved threads are greyed out
+                if let Some(thread) = self.document_state.item_comments.get(&item.id).filter(|t| !t.comments.is_empty()) {
+                    let below_annotation = if self.document_state.item_annotations.contains_key(&item.id) { 12.0 } else { 0.0 };
+                    let marker_pos = Pos2::new(item_rect.right() + 2.0, item_rect.top() + below_annotation);
+                    let color = if thread.resolved { Color32::GRAY } else { Palette::color(self.palette.annotation) };
+                    ui.painter().text(marker_pos, Align2::LEFT_TOP, "💬", FontId::proportional(10.0), color);
+                    let marker_rect = egui::Rect::from_min_size(marker_pos, egui::Vec2::splat(12.0));
+                    let response = ui.interact(marker_rect, ui.id().with(("comments", &item.id)), Sense::click())
+                        .on_hover_text(thread.summary());
+                    if response.clicked() {
+                        ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(COMMENT_REQUEST_ID), item.id.clone()));
+                    }
                 }
             });
         }
+        
+        ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(OVERFLOW_ID), overflowing));
+        
+        ui.ctx().data_mut(|d| match text_selection {
+            Some(selected) => d.insert_temp(egui::Id::new(TEXT_SELECTION_ID), selected),
+            None => d.remove::<TextSelection>(egui::Id::new(TEXT_SELECTION_ID)),
+        });
+        
+        if let Some(selection) = selection {
+            self.document_state.selected_items = selection.clone();
+            ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(SELECTION_ID), selection));
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,10 @@
 //! Document rendering with egui
 
 mod document_canvas;
-pub use document_canvas::DocumentCanvas;
\ No newline at end of file
+pub use document_canvas::{jump_delta, visible_span, DocumentCanvas, Overflow, TextDetail, TextSelection};
+
+mod pdf_page;
+pub use pdf_page::{page_png_name, page_pngs, render_pdf_page, write_page_pngs, RenderTarget, ResizeDebounce};
+
+mod prefetch;
+pub use prefetch::{page_pixel_size, prefetch_pages, PagePrefetcher, PrefetchedPage};
@@ -0,0 +1,154 @@
+//! Rasterizing PDF pages into egui images
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Context, Result};
+use egui::{Color32, ColorImage};
+use pdfium_render::prelude::*;
+
+use crate::jobs::JobHandle;
+
+/// Render a page at the given pixel size
+pub fn render_pdf_page(page: &PdfPage, width: i32, height: i32) -> Option<ColorImage> {
+    let config = PdfRenderConfig::new()
+        .set_target_size(width, height)
+        .render_form_data(true);
+
+    let bitmap = page.render_with_config(&config).ok()?;
+    let image = bitmap.as_image();
+    let image_buffer = image.as_bytes();
+    // pdfium renders BGRA
+    let pixels: Vec<_> = image_buffer
+        .chunks_exact(4)
+        .map(|p| Color32::from_rgba_unmultiplied(p[2], p[1], p[0], p[3]))
+        .collect();
+
+    Some(ColorImage {
+        size: [width as usize, height as usize],
+        pixels,
+    })
+}
+
+/// File name of a (zero-based) page's image, `{stem}_page_NNN.png`
+pub fn page_png_name(stem: &str, index: usize) -> String {
+    format!("{}_page_{:03}.png", stem, index + 1)
+}
+
+/// Render every page to `page_png_name` in `dir`, one job step per page.
+/// Returns the number of pages written.
+pub fn write_page_pngs(
+    pdfium: &Pdfium,
+    pdf_bytes: &[u8],
+    password: Option<&str>,
+    dir: &Path,
+    stem: &str,
+    scale: f32,
+    job: &JobHandle,
+) -> Result<usize> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let page_count = document.pages().len() as usize;
+    job.set_total(page_count);
+
+    let config = PdfRenderConfig::new()
+        .scale_page_by_factor(scale)
+        .render_form_data(true);
+    for (index, page) in document.pages().iter().enumerate() {
+        job.begin_step(format!("Page {} of {}", index + 1, page_count))?;
+        let path = dir.join(page_png_name(stem, index));
+        page.render_with_config(&config)
+            .map_err(|e| anyhow!("Failed to render page {}: {}", index + 1, e))?
+            .as_image()
+            .save(&path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        job.finish_step();
+    }
+    Ok(page_count)
+}
+
+/// Render every page to PNG in memory, one job step per page
+pub fn page_pngs(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, scale: f32, job: &JobHandle) -> Result<Vec<Vec<u8>>> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let page_count = document.pages().len() as usize;
+    job.set_total(page_count);
+
+    let config = PdfRenderConfig::new()
+        .scale_page_by_factor(scale)
+        .render_form_data(true);
+    let mut pngs = Vec::with_capacity(page_count);
+    for (index, page) in document.pages().iter().enumerate() {
+        job.begin_step(format!("Page {} of {}", index + 1, page_count))?;
+        let bitmap = page.render_with_config(&config)
+            .map_err(|e| anyhow!("Failed to render page {}: {}", index + 1, e))?;
+        let image = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+            .ok_or_else(|| anyhow!("Page {} rendered to an unexpected size", index + 1))?;
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .with_context(|| format!("Failed to encode page {}", index + 1))?;
+        pngs.push(png);
+        job.finish_step();
+    }
+    Ok(pngs)
+}
+
+/// How long the page size has to hold still before the page is re-rendered
+const RESIZE_SETTLE: Duration = Duration::from_millis(250);
+
+/// The on-screen size a page texture was rendered for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderTarget {
+    /// Page width on screen in points
+    pub width: f32,
+    pub pixels_per_point: f32,
+}
+
+impl RenderTarget {
+    /// Same size to within a pixel and the same DPI
+    fn matches(&self, other: &RenderTarget) -> bool {
+        (self.width - other.width).abs() < 1.0 && self.pixels_per_point == other.pixels_per_point
+    }
+}
+
+/// Decides when a zoom or DPI change should re-render the page, waiting for
+/// wheel zooming to settle instead of rendering every frame
+#[derive(Debug, Default)]
+pub struct ResizeDebounce {
+    rendered: Option<RenderTarget>,
+    pending: Option<(RenderTarget, Instant)>,
+}
+
+impl ResizeDebounce {
+    /// Record what the current texture was rendered for
+    pub fn rendered(&mut self, target: RenderTarget) {
+        self.rendered = Some(target);
+        self.pending = None;
+    }
+
+    /// What the current texture was rendered for
+    pub fn rendered_target(&self) -> Option<RenderTarget> {
+        self.rendered
+    }
+
+    /// True once `target` has differed from the rendered one for long enough
+    pub fn update(&mut self, target: RenderTarget, now: Instant) -> bool {
+        let Some(rendered) = self.rendered else { return false };
+        if rendered.matches(&target) {
+            self.pending = None;
+            return false;
+        }
+        match self.pending {
+            Some((pending, since)) if pending.matches(&target) => now.saturating_duration_since(since) >= RESIZE_SETTLE,
+            _ => {
+                self.pending = Some((target, now));
+                false
+            }
+        }
+    }
+
+    /// Whether a re-render is waiting for the size to settle
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
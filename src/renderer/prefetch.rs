@@ -0,0 +1,131 @@
+//! Rendering the pages the reader is heading to in the background
+//!
+//! A worker thread with its own pdfium binding renders the next pages in the
+//! direction of travel (and one behind) at the size the page panel shows them,
+//! so a page turn only has to upload a finished image.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use egui::ColorImage;
+
+use crate::core::{bind_pdfium_from, PdfBytes};
+
+/// A page rendered ahead of time
+pub struct PrefetchedPage {
+    pub image: ColorImage,
+    /// Page size in points
+    pub page_size: (f32, f32),
+}
+
+/// Pixel size of a page rendered `pixel_width` wide
+pub fn page_pixel_size(page_size: (f32, f32), pixel_width: i32) -> (i32, i32) {
+    let height = page_size.1 * pixel_width as f32 / page_size.0.max(1.0);
+    (pixel_width, height.round() as i32)
+}
+
+/// Pages worth rendering from `current`: `depth` ahead in the reading direction,
+/// nearest first, then the one behind
+pub fn prefetch_pages(current: usize, forward: bool, page_count: usize, depth: usize) -> Vec<usize> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let ahead = (1..=depth).filter_map(|step| {
+        if forward { current.checked_add(step) } else { current.checked_sub(step) }
+    });
+    let behind = if forward { current.checked_sub(1) } else { current.checked_add(1) };
+    ahead.chain(behind).filter(|&page| page < page_count).collect()
+}
+
+struct Request {
+    pages: Vec<usize>,
+    pixel_width: i32,
+}
+
+type Rendered = Arc<Mutex<HashMap<usize, (i32, PrefetchedPage)>>>;
+
+/// Background renderer for one open PDF; the worker stops when this is dropped
+pub struct PagePrefetcher {
+    requests: Sender<Request>,
+    rendered: Rendered,
+    last_page: Option<usize>,
+    forward: bool,
+}
+
+impl PagePrefetcher {
+    pub fn new(pdf_bytes: PdfBytes, password: Option<String>, library: Option<PathBuf>) -> Self {
+        let (requests, receiver) = mpsc::channel();
+        let rendered = Rendered::default();
+        let worker_rendered = rendered.clone();
+        std::thread::spawn(move || render_worker(pdf_bytes, password, library, receiver, worker_rendered));
+        Self { requests, rendered, last_page: None, forward: true }
+    }
+
+    /// The page, if it was rendered ahead at this width
+    pub fn take(&mut self, page: usize, pixel_width: i32) -> Option<PrefetchedPage> {
+        let mut rendered = self.rendered.lock().unwrap();
+        match rendered.remove(&page) {
+            Some((width, prefetched)) if width == pixel_width => Some(prefetched),
+            _ => None,
+        }
+    }
+
+    /// Note that `page` is showing and queue the pages around it, dropping the
+    /// ones the reader has moved away from
+    pub fn showing(&mut self, page: usize, pixel_width: i32, page_count: usize, depth: usize) {
+        if let Some(last) = self.last_page {
+            if page != last {
+                self.forward = page > last;
+            }
+        }
+        self.last_page = Some(page);
+        let pages = prefetch_pages(page, self.forward, page_count, depth);
+        self.rendered.lock().unwrap()
+            .retain(|p, (width, _)| *width == pixel_width && pages.contains(p));
+        // The worker is gone if pdfium couldn't be bound; pages then render on demand
+        let _ = self.requests.send(Request { pages, pixel_width });
+    }
+
+    /// Drop the rendered pages, e.g. to free memory
+    pub fn clear(&mut self) {
+        self.rendered.lock().unwrap().clear();
+    }
+
+    /// Memory held by the rendered pages
+    pub fn bytes(&self) -> usize {
+        self.rendered.lock().unwrap().values()
+            .map(|(_, page)| crate::memory::texture_bytes(page.image.size))
+            .sum()
+    }
+}
+
+fn render_worker(pdf_bytes: PdfBytes, password: Option<String>, library: Option<PathBuf>, receiver: Receiver<Request>, rendered: Rendered) {
+    let pdfium = match bind_pdfium_from(library.as_deref()) {
+        Ok(pdfium) => pdfium,
+        Err(e) => {
+            log::warn!("Page prefetch disabled: {}", e);
+            return;
+        }
+    };
+    let Ok(document) = pdfium.load_pdf_from_byte_slice(&pdf_bytes, password.as_deref()) else { return };
+    while let Ok(mut request) = receiver.recv() {
+        // Only the latest position matters
+        while let Ok(newer) = receiver.try_recv() {
+            request = newer;
+        }
+        for page_index in request.pages {
+            let done = rendered.lock().unwrap().get(&page_index)
+                .is_some_and(|(width, _)| *width == request.pixel_width);
+            if done {
+                continue;
+            }
+            let Ok(page) = document.pages().get(page_index as u16) else { continue };
+            let page_size = (page.width().value, page.height().value);
+            let (width, height) = page_pixel_size(page_size, request.pixel_width);
+            if let Some(image) = super::render_pdf_page(&page, width, height) {
+                rendered.lock().unwrap().insert(page_index, (request.pixel_width, PrefetchedPage { image, page_size }));
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Re-running OCR on one item or a region of a page
+//!
+//! The region is rendered at a higher resolution than extraction uses and
+//! handed to a small script in the extraction venv, which reads it with the
+//! first OCR engine it finds: Apple Vision (ocrmac), EasyOCR, then Tesseract.
+//! Fixing one garbled number this way takes seconds instead of a re-extraction.
+//! The OCR quick tool reads images pasted from the clipboard the same way.
+
+use std::path::Path;
+use std::process::Command;
+use anyhow::{anyhow, Context, Result};
+use image::DynamicImage;
+use pdfium_render::prelude::*;
+use serde::Deserialize;
+
+use crate::python_env;
+use crate::types::{BoundingBox, ProvenanceStage, ProvenanceStep};
+
+/// Resolution regions are rendered at for re-OCR
+pub const REOCR_DPI: f32 = 400.0;
+
+/// Margin around the region in points, so glyphs touching the box aren't cut
+const CROP_MARGIN: f64 = 2.0;
+
+/// Text read from a region
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrText {
+    pub text: String,
+    /// Mean confidence (0-1) when the engine reports one
+    pub confidence: Option<f32>,
+    pub engine: String,
+}
+
+impl OcrText {
+    /// The provenance step recording this read
+    pub fn provenance(&self) -> ProvenanceStep {
+        ProvenanceStep::new(ProvenanceStage::Ocr, &format!("re-OCR with {} at {} dpi", self.engine, REOCR_DPI))
+    }
+}
+
+/// Pixel rectangle (left, top, width, height) of a TOPLEFT region, with a
+/// margin, on a page of `page_size` points rendered at `dpi`. None if the
+/// region is off the page or empty.
+pub fn crop_rect(bbox: &BoundingBox, page_size: (f64, f64), dpi: f32) -> Option<(u32, u32, u32, u32)> {
+    let scale = dpi as f64 / 72.0;
+    let left = (bbox.left - CROP_MARGIN).max(0.0);
+    let top = (bbox.top - CROP_MARGIN).max(0.0);
+    let right = (bbox.left + bbox.width + CROP_MARGIN).min(page_size.0);
+    let bottom = (bbox.top + bbox.height + CROP_MARGIN).min(page_size.1);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    let (x, y) = ((left * scale).floor() as u32, (top * scale).floor() as u32);
+    let (width, height) = (((right - left) * scale).ceil() as u32, ((bottom - top) * scale).ceil() as u32);
+    (width > 0 && height > 0).then_some((x, y, width, height))
+}
+
+/// Render a region of a (zero-based) page at `REOCR_DPI`
+pub fn render_region(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, page_index: usize, bbox: &BoundingBox) -> Result<DynamicImage> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let page = document.pages().get(page_index as u16)
+        .map_err(|e| anyhow!("Failed to open page {}: {}", page_index + 1, e))?;
+    let page_size = (page.width().value as f64, page.height().value as f64);
+    let (x, y, width, height) = crop_rect(bbox, page_size, REOCR_DPI)
+        .ok_or_else(|| anyhow!("The region is outside page {}", page_index + 1))?;
+
+    let config = PdfRenderConfig::new()
+        .scale_page_by_factor(REOCR_DPI / 72.0)
+        .render_form_data(true);
+    let bitmap = page.render_with_config(&config)
+        .map_err(|e| anyhow!("Failed to render page {}: {}", page_index + 1, e))?;
+    let rendered = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+        .ok_or_else(|| anyhow!("Page {} rendered to an unexpected size", page_index + 1))?;
+    let width = width.min(rendered.width().saturating_sub(x));
+    let height = height.min(rendered.height().saturating_sub(y));
+    Ok(DynamicImage::ImageRgba8(rendered).crop_imm(x, y, width, height))
+}
+
+#[derive(Deserialize)]
+struct ScriptOutput {
+    #[serde(default)]
+    text: String,
+    confidence: Option<f32>,
+    #[serde(default)]
+    engine: String,
+    error: Option<String>,
+}
+
+/// Read the text in an image with the venv's OCR engine
+pub fn recognize(image: &DynamicImage) -> Result<OcrText> {
+    let venv_python = python_env::venv_python(&python_env::venv_dir());
+    if !venv_python.exists() {
+        return Err(anyhow!("Python environment not found. Open Settings > Python environment to set it up."));
+    }
+    let image_path = crate::storage::temp_dir().join(format!("chonker3_reocr_{}.png", std::process::id()));
+    image.save(&image_path).with_context(|| format!("Failed to write {}", image_path.display()))?;
+    let output = run_script(&venv_python, &image_path);
+    let _ = std::fs::remove_file(&image_path);
+    let output = output?;
+
+    // Engines may print to stdout themselves; the result is the last line
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let Some(result) = stdout.lines().rev().find_map(|line| serde_json::from_str::<ScriptOutput>(line).ok()) else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("OCR failed: {}", stderr.lines().last().unwrap_or("no output")));
+    };
+    if let Some(error) = result.error {
+        return Err(anyhow!(error));
+    }
+    Ok(OcrText { text: result.text, confidence: result.confidence, engine: result.engine })
+}
+
+fn run_script(venv_python: &Path, image_path: &Path) -> Result<std::process::Output> {
+    Command::new(venv_python)
+        .arg("-c")
+        .arg(OCR_SCRIPT)
+        .arg(image_path)
+        .output()
+        .context("Failed to run the OCR script")
+}
+
+/// The image on the system clipboard
+pub fn clipboard_image() -> Result<DynamicImage> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| anyhow!("Can't open the clipboard: {}", e))?;
+    let pasted = clipboard.get_image().map_err(|_| anyhow!("There's no image on the clipboard"))?;
+    let image = image::RgbaImage::from_raw(pasted.width as u32, pasted.height as u32, pasted.bytes.into_owned())
+        .ok_or_else(|| anyhow!("The clipboard image has an unexpected size"))?;
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+/// Render a region of a page and read it
+pub fn reocr_region(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, page_index: usize, bbox: &BoundingBox) -> Result<OcrText> {
+    let image = render_region(pdfium, pdf_bytes, password, page_index, bbox)?;
+    recognize(&image)
+}
+
+const OCR_SCRIPT: &str = r#"
+import sys
+import json
+
+def emit(**fields):
+    print(json.dumps(fields), flush=True)
+
+def join_lines(words):
+    # words: (text, left, top, bottom, confidence); boxes whose middles are
+    # within half a line of each other share a line
+    lines = []
+    for word in sorted(words, key=lambda w: (w[2], w[1])):
+        middle = (word[2] + word[3]) / 2
+        if lines and abs(middle - lines[-1][0]) < (word[3] - word[2]) / 2:
+            lines[-1][1].append(word)
+        else:
+            lines.append([middle, [word]])
+    text = '\n'.join(' '.join(w[0] for w in sorted(line, key=lambda w: w[1])) for _, line in lines)
+    confidences = [w[4] for w in words if w[4] is not None]
+    return text, (sum(confidences) / len(confidences) if confidences else None)
+
+try:
+    path = sys.argv[1]
+    try:
+        from ocrmac import ocrmac
+        # Boxes are (x, y, width, height) as shares of the image, from the bottom left
+        results = ocrmac.OCR(path).recognize()
+        words = [(text, box[0], 1 - box[1] - box[3], 1 - box[1], conf) for text, conf, box in results]
+        engine = 'Apple Vision'
+    except ImportError:
+        try:
+            import easyocr
+            reader = easyocr.Reader(['en'], verbose=False)
+            words = [(text, min(p[0] for p in box), min(p[1] for p in box), max(p[1] for p in box), conf)
+                     for box, text, conf in reader.readtext(path)]
+            engine = 'EasyOCR'
+        except ImportError:
+            import pytesseract
+            from PIL import Image
+            data = pytesseract.image_to_data(Image.open(path), output_type=pytesseract.Output.DICT)
+            words = [(text, data['left'][i], data['top'][i], data['top'][i] + data['height'][i],
+                      float(data['conf'][i]) / 100 if float(data['conf'][i]) >= 0 else None)
+                     for i, text in enumerate(data['text']) if text.strip()]
+            engine = 'Tesseract'
+    text, confidence = join_lines(words)
+    emit(text=text, confidence=confidence, engine=engine)
+except ImportError:
+    emit(error='No OCR engine found. Install ocrmac, easyocr or pytesseract in the Python environment.')
+except Exception as e:
+    emit(error=str(e))
+"#;
@@ -0,0 +1,57 @@
+//! Repaint scheduling
+//!
+//! egui only draws a frame on input unless something asks for another. Code
+//! that animates or polls background work tells the scheduler how soon it
+//! needs the next frame, and at the end of the frame the app makes a single
+//! request for the soonest of them. When nothing is animating or running no
+//! request is made, so an idle window costs no CPU or GPU time.
+
+use std::time::Duration;
+
+/// Poll interval for work that reports progress, like jobs and setup checks
+pub const PROGRESS_POLL: Duration = Duration::from_millis(100);
+
+/// Poll interval for network state, like collaboration sessions
+pub const NETWORK_POLL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Default)]
+pub struct RepaintScheduler {
+    /// Shortest time between animation frames; zero for every frame
+    pub frame_interval: Duration,
+    next: Option<Duration>,
+}
+
+impl RepaintScheduler {
+    pub fn new(frame_interval: Duration) -> Self {
+        Self { frame_interval, next: None }
+    }
+
+    /// Ask for the next frame of an animation, no sooner than the frame interval
+    pub fn animate(&mut self) {
+        self.within(self.frame_interval);
+    }
+
+    /// Ask for a frame within `delay`, e.g. to poll background work
+    pub fn within(&mut self, delay: Duration) {
+        self.next = Some(self.next.map_or(delay, |next| next.min(delay)));
+    }
+
+    /// Whether anything asked for another frame
+    pub fn is_pending(&self) -> bool {
+        self.next.is_some()
+    }
+
+    /// The delay to request for this frame, if any, clearing the requests for the next
+    pub fn take(&mut self) -> Option<Duration> {
+        self.next.take()
+    }
+
+    /// Make this frame's request
+    pub fn finish_frame(&mut self, ctx: &egui::Context) {
+        match self.take() {
+            Some(Duration::ZERO) => ctx.request_repaint(),
+            Some(delay) => ctx.request_repaint_after(delay),
+            None => {}
+        }
+    }
+}
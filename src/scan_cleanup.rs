@@ -0,0 +1,270 @@
+//! Scan cleanup before OCR
+//!
+//! Pages of a scanned PDF are rendered, straightened, despeckled and
+//! contrast-stretched, then wrapped into a fresh PDF at the original page
+//! sizes for the extractor to OCR. Skew is found by rotating a thresholded
+//! thumbnail through small angles and keeping the one whose row profile has
+//! the highest variance: text lines are sharpest when level.
+
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, GrayImage, Luma};
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::extractor::ExtractedDocument;
+use crate::inputs::{self, ScanPage};
+use crate::types::{ProvenanceStage, ProvenanceStep};
+
+/// Resolution pages are rendered at for cleanup and OCR
+pub const RENDER_DPI: f32 = 200.0;
+
+/// Largest skew corrected, in degrees either way
+pub const MAX_SKEW: f32 = 5.0;
+
+/// Skew estimation works on a thumbnail this wide
+const THUMBNAIL_WIDTH: u32 = 800;
+
+/// Skews smaller than this are left alone; resampling would only blur
+const MIN_SKEW: f32 = 0.1;
+
+/// Which cleanup steps run; off unless the user enables it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanCleanup {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub deskew: bool,
+    #[serde(default = "default_true")]
+    pub despeckle: bool,
+    #[serde(default = "default_true")]
+    pub normalize_contrast: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ScanCleanup {
+    fn default() -> Self {
+        Self { enabled: false, deskew: true, despeckle: true, normalize_contrast: true }
+    }
+}
+
+/// Otsu threshold between dark and light pixels; values at or below it are dark
+pub fn otsu_threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram.iter().enumerate().map(|(value, count)| value as f64 * *count as f64).sum();
+
+    let (mut background, mut background_sum) = (0u64, 0.0);
+    let (mut best, mut best_variance) = (128u8, 0.0);
+    for (value, count) in histogram.iter().enumerate() {
+        background += count;
+        if background == 0 {
+            continue;
+        }
+        let foreground = total - background;
+        if foreground == 0 {
+            break;
+        }
+        background_sum += value as f64 * *count as f64;
+        let background_mean = background_sum / background as f64;
+        let foreground_mean = (sum - background_sum) / foreground as f64;
+        let variance = background as f64 * foreground as f64 * (background_mean - foreground_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = value as u8;
+        }
+    }
+    best
+}
+
+/// Variance of the dark-pixel count per row after rotating the points by `degrees`
+fn profile_variance(points: &[(f32, f32)], height: u32, degrees: f32) -> f64 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let margin = height as f32;
+    let mut rows = vec![0u32; (height as f32 + 2.0 * margin) as usize + 1];
+    for &(x, y) in points {
+        let row = (y * cos - x * sin + margin) as usize;
+        if let Some(count) = rows.get_mut(row) {
+            *count += 1;
+        }
+    }
+    let mean = points.len() as f64 / rows.len() as f64;
+    rows.iter().map(|&count| (count as f64 - mean).powi(2)).sum::<f64>() / rows.len() as f64
+}
+
+/// Skew of the page's text lines in degrees, positive when they rise to the
+/// right; rotate by the negative to straighten. Zero for blank pages.
+pub fn estimate_skew(image: &GrayImage) -> f32 {
+    let thumbnail = if image.width() > THUMBNAIL_WIDTH {
+        let height = (image.height() as u64 * THUMBNAIL_WIDTH as u64 / image.width() as u64).max(1) as u32;
+        image::imageops::resize(image, THUMBNAIL_WIDTH, height, image::imageops::FilterType::Triangle)
+    } else {
+        image.clone()
+    };
+    let threshold = otsu_threshold(&thumbnail);
+    let points: Vec<(f32, f32)> = thumbnail.enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel[0] <= threshold)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    if points.len() < 50 {
+        return 0.0;
+    }
+
+    // Coarse search, then refine around the best angle
+    let best_between = |from: f32, to: f32, step: f32| {
+        let steps = ((to - from) / step).round() as i32;
+        (0..=steps)
+            .map(|i| from + i as f32 * step)
+            .map(|angle| (angle, profile_variance(&points, thumbnail.height(), angle)))
+            .fold((0.0, f64::MIN), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+            .0
+    };
+    let coarse = best_between(-MAX_SKEW, MAX_SKEW, 0.5);
+    // Points rotated by `angle` are level, so the lines rise by its negative
+    -best_between(coarse - 0.5, coarse + 0.5, 0.05)
+}
+
+/// Rotate about the center by `degrees` counter-clockwise, keeping the size and
+/// filling uncovered corners with white
+pub fn rotate(image: &GrayImage, degrees: f32) -> GrayImage {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (image.width() as f32 / 2.0, image.height() as f32 / 2.0);
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        // Source position of this target pixel (inverse rotation; y points down)
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        let sx = cos * dx - sin * dy + cx - 0.5;
+        let sy = sin * dx + cos * dy + cy - 0.5;
+        Luma([bilinear(image, sx, sy)])
+    })
+}
+
+fn bilinear(image: &GrayImage, x: f32, y: f32) -> u8 {
+    let sample = |x: i64, y: i64| -> f32 {
+        if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+            255.0
+        } else {
+            image.get_pixel(x as u32, y as u32)[0] as f32
+        }
+    };
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let top = sample(x0, y0) * (1.0 - fx) + sample(x0 + 1, y0) * fx;
+    let bottom = sample(x0, y0 + 1) * (1.0 - fx) + sample(x0 + 1, y0 + 1) * fx;
+    (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8
+}
+
+/// 3×3 median filter: removes isolated specks while keeping stroke edges
+pub fn despeckle(image: &GrayImage) -> GrayImage {
+    let (width, height) = image.dimensions();
+    GrayImage::from_fn(width, height, |x, y| {
+        let mut window = [0u8; 9];
+        let mut n = 0;
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                window[n] = image.get_pixel(nx, ny)[0];
+                n += 1;
+            }
+        }
+        let window = &mut window[..n];
+        window.sort_unstable();
+        Luma([window[n / 2]])
+    })
+}
+
+/// Stretch the levels between the 1st and 99th percentile to full black and white
+pub fn normalize_contrast(image: &GrayImage) -> GrayImage {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let percentile = |share: f64| {
+        let target = (total as f64 * share) as u64;
+        let mut seen = 0;
+        histogram.iter().position(|count| {
+            seen += count;
+            seen > target
+        }).unwrap_or(255) as f32
+    };
+    let (low, high) = (percentile(0.01), percentile(0.99));
+    if high - low < 1.0 {
+        return image.clone();
+    }
+    let mut out = image.clone();
+    for pixel in out.pixels_mut() {
+        pixel[0] = ((pixel[0] as f32 - low) / (high - low) * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
+/// Run the enabled steps on one page image; returns grayscale for OCR
+pub fn clean_page(image: &DynamicImage, cleanup: &ScanCleanup) -> GrayImage {
+    let mut page = image.to_luma8();
+    if cleanup.despeckle {
+        page = despeckle(&page);
+    }
+    if cleanup.deskew {
+        let skew = estimate_skew(&page);
+        if skew.abs() >= MIN_SKEW {
+            page = rotate(&page, -skew);
+        }
+    }
+    if cleanup.normalize_contrast {
+        page = normalize_contrast(&page);
+    }
+    page
+}
+
+/// A cleaned-up copy of a scanned PDF, or None when a page has a text layer:
+/// rasterizing would throw away text that needs no OCR
+pub fn clean_pdf(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, cleanup: &ScanCleanup) -> Result<Option<Vec<u8>>> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let has_text = document.pages().iter()
+        .any(|page| page.text().map(|text| !text.all().trim().is_empty()).unwrap_or(false));
+    if has_text {
+        return Ok(None);
+    }
+
+    let page_count = document.pages().len() as usize;
+    let config = PdfRenderConfig::new()
+        .scale_page_by_factor(RENDER_DPI / 72.0)
+        .render_form_data(true);
+    let mut pages = Vec::with_capacity(page_count);
+    for (index, page) in document.pages().iter().enumerate() {
+        let bitmap = page.render_with_config(&config)
+            .map_err(|e| anyhow!("Failed to render page {}: {}", index + 1, e))?;
+        let rendered = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+            .ok_or_else(|| anyhow!("Page {} rendered to an unexpected size", index + 1))?;
+        let cleaned = clean_page(&DynamicImage::ImageRgba8(rendered), cleanup);
+        pages.push(ScanPage { image: DynamicImage::ImageLuma8(cleaned), dpi: RENDER_DPI });
+    }
+    inputs::images_to_pdf(&pages).map(Some)
+}
+
+/// Clean up a scanned PDF into the temp folder; returns the copy to extract,
+/// or None when the PDF has a text layer
+pub fn write_cleaned(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, pdf_path: &Path, cleanup: &ScanCleanup) -> Result<Option<PathBuf>> {
+    let Some(cleaned) = clean_pdf(pdfium, pdf_bytes, password, cleanup)? else { return Ok(None) };
+    let stem = pdf_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "scan".to_string());
+    let dir = inputs::default_output_dir();
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}_cleaned.pdf", stem));
+    std::fs::write(&path, cleaned)?;
+    Ok(Some(path))
+}
+
+/// Note the cleanup at the start of every item's provenance chain, in memory
+/// and in the extraction's JSON file
+pub fn record_cleanup(document: &mut ExtractedDocument) -> Result<()> {
+    crate::document::record_preprocessing(&mut document.data, ProvenanceStep::new(ProvenanceStage::Preprocess, "scan cleanup"));
+    std::fs::write(&document.json_path, serde_json::to_string_pretty(&document.data)?)?;
+    Ok(())
+}
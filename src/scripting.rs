@@ -0,0 +1,158 @@
+//! Rhai scripting hooks
+//!
+//! Scripts see the extraction as an `items` array (with edits applied) and call
+//! back into the session to change it:
+//!
+//! ```text
+//! for item in items {
+//!     if item.type == "Header" && item.text.starts_with("Page ") {
+//!         delete(item.id);
+//!     }
+//! }
+//! export_markdown("/tmp/out.md");
+//! ```
+//!
+//! Calls are recorded while the script runs and applied to the session in
+//! order afterwards, so an export sees every edit made before it.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, Map, Scope};
+
+use crate::core::Session;
+use crate::document;
+use crate::types::ItemType;
+
+/// Scripts that run longer than this many operations are stopped
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// Script file run after every extraction, if present in the session directory
+pub const AUTORUN_FILE: &str = "chonker3_autorun.rhai";
+
+pub fn autorun_path() -> std::path::PathBuf {
+    crate::storage::session_dir().join(AUTORUN_FILE)
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    SetText(String, String),
+    SetType(String, ItemType),
+    Delete(String),
+    Annotate(String, String),
+    ExportMarkdown(PathBuf),
+    ExportStructured(PathBuf),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptOutcome {
+    /// `print`/`log` output and export results, in order
+    pub log: Vec<String>,
+    pub edits: usize,
+}
+
+/// Item types by UI label or extraction type name, case-insensitively
+fn parse_item_type(name: &str) -> Option<ItemType> {
+    ItemType::ALL.iter().copied().find(|t| {
+        t.label().eq_ignore_ascii_case(name) || t.json_type().eq_ignore_ascii_case(name)
+    })
+}
+
+/// Every item of the extraction as script maps
+fn script_items(session: &Session) -> rhai::Array {
+    let Some(data) = &session.extracted_data else { return rhai::Array::new() };
+    let patch = session.to_patch();
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+
+    let mut items = rhai::Array::new();
+    for page in 0..page_count {
+        for item in document::page_items(data, page, &patch) {
+            let text = patch.text_overrides.get(&item.id).cloned().unwrap_or(item.content);
+            let mut map = Map::new();
+            map.insert("id".into(), item.id.into());
+            map.insert("page".into(), ((page + 1) as i64).into());
+            map.insert("type".into(), item.item_type.label().into());
+            map.insert("text".into(), text.into());
+            map.insert("left".into(), item.bbox.left.into());
+            map.insert("top".into(), item.bbox.top.into());
+            map.insert("width".into(), item.bbox.width.into());
+            map.insert("height".into(), item.bbox.height.into());
+            map.insert("font_size".into(), (item.font_size as f64).into());
+            items.push(map.into());
+        }
+    }
+    items
+}
+
+/// Run a script against the session and apply what it did
+pub fn run_script(source: &str, session: &mut Session) -> Result<ScriptOutcome> {
+    let actions: Arc<Mutex<Vec<Action>>> = Arc::default();
+    let log: Arc<Mutex<Vec<String>>> = Arc::default();
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let sink = log.clone();
+    engine.on_print(move |text| sink.lock().unwrap().push(text.to_string()));
+    let sink = log.clone();
+    engine.register_fn("log", move |text: &str| sink.lock().unwrap().push(text.to_string()));
+
+    let sink = actions.clone();
+    engine.register_fn("set_text", move |id: &str, text: &str| {
+        sink.lock().unwrap().push(Action::SetText(id.to_string(), text.to_string()));
+    });
+    let sink = actions.clone();
+    engine.register_fn("set_type", move |id: &str, name: &str| -> std::result::Result<(), Box<rhai::EvalAltResult>> {
+        let item_type = parse_item_type(name).ok_or_else(|| format!("Unknown item type '{}'", name))?;
+        sink.lock().unwrap().push(Action::SetType(id.to_string(), item_type));
+        Ok(())
+    });
+    let sink = actions.clone();
+    engine.register_fn("delete", move |id: &str| {
+        sink.lock().unwrap().push(Action::Delete(id.to_string()));
+    });
+    let sink = actions.clone();
+    engine.register_fn("annotate", move |id: &str, note: &str| {
+        sink.lock().unwrap().push(Action::Annotate(id.to_string(), note.to_string()));
+    });
+    let sink = actions.clone();
+    engine.register_fn("export_markdown", move |path: &str| {
+        sink.lock().unwrap().push(Action::ExportMarkdown(PathBuf::from(path)));
+    });
+    let sink = actions.clone();
+    engine.register_fn("export_structured", move |path: &str| {
+        sink.lock().unwrap().push(Action::ExportStructured(PathBuf::from(path)));
+    });
+
+    let mut scope = Scope::new();
+    scope.push("items", script_items(session));
+    scope.push("page_count", session.page_count as i64);
+    scope.push("source_file", Dynamic::from(session.source_file_name().unwrap_or_default()));
+
+    engine.run_with_scope(&mut scope, source).map_err(|e| anyhow!("Script error: {}", e))?;
+
+    let mut outcome = ScriptOutcome {
+        log: std::mem::take(&mut *log.lock().unwrap()),
+        edits: 0,
+    };
+    for action in std::mem::take(&mut *actions.lock().unwrap()) {
+        match action {
+            Action::SetText(id, text) => session.set_text(&id, text),
+            Action::SetType(id, item_type) => session.set_type(&id, Some(item_type)),
+            Action::Delete(id) => session.delete_item(&id),
+            Action::Annotate(id, note) => session.set_annotation(&id, &note),
+            Action::ExportMarkdown(path) => {
+                session.export_markdown(&path)?;
+                outcome.log.push(format!("Exported Markdown to {}", path.display()));
+                continue;
+            }
+            Action::ExportStructured(path) => {
+                let count = session.export_structured(&path)?;
+                outcome.log.push(format!("Exported {} items to {}", count, path.display()));
+                continue;
+            }
+        }
+        outcome.edits += 1;
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,91 @@
+//! Smooth and kinetic scrolling
+//!
+//! Wheel input is eased in over a few frames instead of jumping, and a drag
+//! that is released while moving keeps coasting and slows down with friction,
+//! like native PDF viewers. The panels feed input in and apply whatever
+//! `step` returns each frame.
+
+use std::collections::VecDeque;
+use egui::Vec2;
+
+/// Fraction of the remaining wheel scroll applied per second, as an exponential rate
+const SMOOTHING_RATE: f32 = 18.0;
+
+/// Velocity decay per second, as an exponential rate
+const FRICTION_RATE: f32 = 4.0;
+
+/// Below this speed (points/second) the fling stops
+const MIN_VELOCITY: f32 = 20.0;
+
+/// Drag samples older than this don't count towards the release velocity
+const VELOCITY_WINDOW: f64 = 0.1;
+
+#[derive(Debug, Default)]
+pub struct KineticScroll {
+    /// Wheel scroll not applied yet
+    pending: Vec2,
+    /// Fling velocity in points per second
+    velocity: Vec2,
+    /// Recent drag deltas with their timestamps (seconds)
+    samples: VecDeque<(f64, Vec2)>,
+}
+
+impl KineticScroll {
+    /// Queue wheel scroll to be eased in
+    pub fn scroll(&mut self, delta: Vec2) {
+        self.velocity = Vec2::ZERO;
+        self.pending += delta;
+    }
+
+    /// A drag moved by `delta`; it applies immediately and stops any fling
+    pub fn drag(&mut self, delta: Vec2, time: f64) -> Vec2 {
+        self.velocity = Vec2::ZERO;
+        self.pending = Vec2::ZERO;
+        self.samples.push_back((time, delta));
+        while self.samples.front().is_some_and(|(t, _)| time - t > VELOCITY_WINDOW) {
+            self.samples.pop_front();
+        }
+        delta
+    }
+
+    /// The drag ended; keep moving at its recent speed
+    pub fn release(&mut self, time: f64) {
+        let recent: Vec2 = self.samples.iter()
+            .filter(|(t, _)| time - t <= VELOCITY_WINDOW)
+            .fold(Vec2::ZERO, |sum, (_, delta)| sum + *delta);
+        self.samples.clear();
+        let velocity = recent / VELOCITY_WINDOW as f32;
+        self.velocity = if velocity.length() >= MIN_VELOCITY { velocity } else { Vec2::ZERO };
+    }
+
+    pub fn stop(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Still easing or coasting; keep repainting
+    pub fn is_active(&self) -> bool {
+        self.pending != Vec2::ZERO || self.velocity != Vec2::ZERO
+    }
+
+    /// Advance by `dt` seconds; returns how far to scroll this frame
+    pub fn step(&mut self, dt: f32) -> Vec2 {
+        let eased = self.pending * (1.0 - (-SMOOTHING_RATE * dt).exp());
+        self.pending -= eased;
+        if self.pending.length() < 0.5 {
+            // Finish the last fraction of a point
+            let rest = self.pending;
+            self.pending = Vec2::ZERO;
+            return eased + rest + self.coast(dt);
+        }
+        eased + self.coast(dt)
+    }
+
+    fn coast(&mut self, dt: f32) -> Vec2 {
+        let moved = self.velocity * dt;
+        self.velocity *= (-FRICTION_RATE * dt).exp();
+        if self.velocity.length() < MIN_VELOCITY {
+            self.velocity = Vec2::ZERO;
+        }
+        moved
+    }
+}
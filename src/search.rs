@@ -0,0 +1,337 @@
+//! Ranked, typo-tolerant search
+//!
+//! Items are matched against the query, ignoring case, and ranked by how they
+//! match: the query as whole words beats the start of a word, which beats the
+//! middle of one, which beats a near miss. Near misses are runs of as many
+//! words as the query whose edit distance to it is small for its length, so
+//! OCR slips like "lnvoice" or "rn" for "m" are still found.
+//!
+//! Searches can be saved under a name, as words or as a regular expression
+//! for things like invoice numbers, and run on any document in the workspace.
+
+use std::ops::Range;
+use anyhow::{anyhow, bail, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::document;
+use crate::patch::EditPatch;
+use crate::types::DocumentItem;
+
+/// Recent queries kept per document
+pub const RECENT_SEARCHES: usize = 20;
+
+/// Characters of context on each side of the match in a snippet
+const SNIPPET_CONTEXT: usize = 30;
+
+/// How an item matched the query, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The query as whole words
+    Exact,
+    /// The start of a word
+    Prefix,
+    /// Inside a word
+    Substring,
+    /// Within a few typos
+    Fuzzy,
+}
+
+impl MatchKind {
+    fn weight(&self) -> f32 {
+        match self {
+            MatchKind::Exact => 3.0,
+            MatchKind::Prefix => 2.0,
+            MatchKind::Substring => 1.5,
+            MatchKind::Fuzzy => 0.0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchKind::Exact => "exact",
+            MatchKind::Prefix => "prefix",
+            MatchKind::Substring => "partial",
+            MatchKind::Fuzzy => "similar",
+        }
+    }
+}
+
+/// How well a text matches, and where
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMatch {
+    pub kind: MatchKind,
+    /// Higher is better; exact matches always outrank fuzzy ones
+    pub score: f32,
+    /// Char range of the best match in the text
+    pub range: Range<usize>,
+}
+
+/// A ranked match on some page
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// Zero-based page
+    pub page: usize,
+    pub item_id: String,
+    pub kind: MatchKind,
+    pub score: f32,
+    pub snippet: String,
+}
+
+/// Typos tolerated in a query of this many chars; short queries must match as typed
+pub fn allowed_typos(query_chars: usize) -> usize {
+    match query_chars {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Edit distance between two char slices
+pub fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+/// Char ranges of the words in `chars`
+fn words(chars: &[char]) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &c) in chars.iter().enumerate() {
+        match (is_word_char(c), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..chars.len());
+    }
+    words
+}
+
+/// Lowercased chars, one per char of `text` so ranges carry over
+fn lowercase(text: &str) -> Vec<char> {
+    text.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect()
+}
+
+/// Best match of the query in the text, if any
+pub fn match_text(text: &str, query: &str) -> Option<TextMatch> {
+    let text = lowercase(text);
+    let query = lowercase(query.trim());
+    if query.is_empty() || text.is_empty() {
+        return None;
+    }
+
+    // Literal occurrences, graded by the word boundaries around them
+    let mut best: Option<TextMatch> = None;
+    let mut occurrences = 0;
+    for start in 0..text.len().saturating_sub(query.len() - 1) {
+        if text[start..start + query.len()] != query[..] {
+            continue;
+        }
+        occurrences += 1;
+        let end = start + query.len();
+        let starts_word = start == 0 || !is_word_char(text[start - 1]);
+        let ends_word = end == text.len() || !is_word_char(text[end]);
+        let kind = match (starts_word, ends_word) {
+            (true, true) => MatchKind::Exact,
+            (true, false) => MatchKind::Prefix,
+            _ => MatchKind::Substring,
+        };
+        if best.as_ref().is_none_or(|b| kind < b.kind) {
+            best = Some(TextMatch { kind, score: 0.0, range: start..end });
+        }
+    }
+    if let Some(mut found) = best {
+        // A little extra for repeats and for the match making up more of the item
+        let coverage = query.len() as f32 / text.len() as f32;
+        found.score = found.kind.weight() + 0.1 * occurrences.min(5) as f32 + 0.2 * coverage;
+        return Some(found);
+    }
+
+    // Near misses: runs of as many words as the query has
+    let typos = allowed_typos(query.len());
+    if typos == 0 {
+        return None;
+    }
+    let span = words(&query).len().max(1);
+    let text_words = words(&text);
+    let mut best: Option<(usize, Range<usize>)> = None;
+    for window in text_words.windows(span) {
+        let range = window[0].start..window[span - 1].end;
+        if range.len().abs_diff(query.len()) > typos {
+            continue;
+        }
+        let distance = levenshtein(&text[range.clone()], &query);
+        if distance <= typos && best.as_ref().is_none_or(|(d, _)| distance < *d) {
+            best = Some((distance, range));
+        }
+    }
+    best.map(|(distance, range)| TextMatch {
+        kind: MatchKind::Fuzzy,
+        score: 1.0 - distance as f32 / query.len() as f32,
+        range,
+    })
+}
+
+/// Char ranges to highlight for the query: every occurrence, ignoring case,
+/// or the near miss `match_text` found when there are none
+pub fn match_ranges(text: &str, query: &str) -> Vec<Range<usize>> {
+    let chars = lowercase(text);
+    let needle = lowercase(query.trim());
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start + needle.len() <= chars.len() {
+        if chars[start..start + needle.len()] == needle[..] {
+            ranges.push(start..start + needle.len());
+            start += needle.len();
+        } else {
+            start += 1;
+        }
+    }
+    if ranges.is_empty() {
+        ranges.extend(match_text(text, query).map(|m| m.range));
+    }
+    ranges
+}
+
+/// The match with some text on either side, on one line
+pub fn snippet(text: &str, range: &Range<usize>) -> String {
+    let chars: Vec<char> = text.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+    let start = range.start.saturating_sub(SNIPPET_CONTEXT);
+    let end = (range.end + SNIPPET_CONTEXT).min(chars.len());
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&chars[start..end]);
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// What a search looks for
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// Words, ranked and typo-tolerant as in `match_text`
+    Words(String),
+    /// A regular expression, ignoring case; every match counts as exact
+    Pattern(Regex),
+}
+
+impl Matcher {
+    pub fn new(query: &str, regex: bool) -> Result<Self> {
+        if !regex {
+            return Ok(Matcher::Words(query.to_string()));
+        }
+        if query.is_empty() {
+            bail!("The pattern is empty");
+        }
+        RegexBuilder::new(query).case_insensitive(true).build()
+            .map(Matcher::Pattern)
+            .map_err(|e| anyhow!("Invalid pattern: {}", e))
+    }
+
+    /// Best match in the text, if any
+    pub fn find(&self, text: &str) -> Option<TextMatch> {
+        match self {
+            Matcher::Words(query) => match_text(text, query),
+            Matcher::Pattern(regex) => {
+                let mut found = regex.find_iter(text).filter(|m| !m.is_empty());
+                let first = found.next()?;
+                let occurrences = 1 + found.count();
+                // Byte offsets to chars, to match `TextMatch::range`
+                let start = text[..first.start()].chars().count();
+                let end = start + first.as_str().chars().count();
+                Some(TextMatch {
+                    kind: MatchKind::Exact,
+                    score: MatchKind::Exact.weight() + 0.1 * occurrences.min(5) as f32,
+                    range: start..end,
+                })
+            }
+        }
+    }
+}
+
+/// A search kept under a name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    /// `query` is a regular expression
+    #[serde(default)]
+    pub regex: bool,
+}
+
+impl SavedSearch {
+    pub fn matcher(&self) -> Result<Matcher> {
+        Matcher::new(&self.query, self.regex)
+    }
+}
+
+/// Put `query` at the front of the recent queries, dropping older copies and
+/// the oldest past `RECENT_SEARCHES`
+pub fn remember_query(recent: &mut Vec<String>, query: &str) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+    recent.retain(|q| q != query);
+    recent.insert(0, query.to_string());
+    recent.truncate(RECENT_SEARCHES);
+}
+
+/// Items of a page matching, with how they matched, in page order
+pub fn page_matches<'a>(items: &'a [DocumentItem], matcher: &Matcher) -> Vec<(&'a DocumentItem, TextMatch)> {
+    items.iter()
+        .filter_map(|item| matcher.find(&item.content).map(|m| (item, m)))
+        .collect()
+}
+
+/// Hits on every page of an extraction, best first
+pub fn document_hits(data: &Value, edits: &EditPatch, page_count: usize, matcher: &Matcher) -> Vec<SearchHit> {
+    let pages = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    let mut hits: Vec<SearchHit> = (0..pages.max(page_count))
+        .flat_map(|page| {
+            let items = document::page_items(data, page, edits);
+            page_matches(&items, matcher).into_iter()
+                .map(|(item, found)| SearchHit {
+                    page,
+                    item_id: item.id.clone(),
+                    kind: found.kind,
+                    score: found.score,
+                    snippet: snippet(&item.content, &found.range),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    rank(&mut hits);
+    hits
+}
+
+/// Sort hits best first; ties keep document order
+pub fn rank(hits: &mut [SearchHit]) {
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.page.cmp(&b.page)));
+}
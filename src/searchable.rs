@@ -0,0 +1,108 @@
+//! Searchable PDF
+//!
+//! Writes the recognized text back into the scanned PDF as an invisible text
+//! layer, the way OCR tools produce "searchable PDFs": the page images stay as
+//! they are, and viewers, indexers and copy and paste find the text where it
+//! appears on the page. Each line is stretched to the width of its item so
+//! search hits highlight the right spot. Pages that already carry a text
+//! layer are left alone, so born-digital pages don't get their text twice.
+
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use pdfium_render::prelude::*;
+use serde_json::Value;
+
+use crate::document;
+use crate::patch::EditPatch;
+use crate::pdf_output::{self, PdfOutput};
+use crate::reflow;
+use crate::types::{DocumentItem, ItemType};
+
+/// Baseline below the top of a line, as a multiple of the font size
+const ASCENT: f32 = 0.8;
+
+/// A line of hidden text, in page points from the top left
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiddenLine {
+    pub text: String,
+    pub left: f32,
+    /// Baseline, from the page top
+    pub baseline: f32,
+    pub width: f32,
+    pub font_size: f32,
+}
+
+impl HiddenLine {
+    /// Horizontal stretch that makes the line as wide as its item
+    pub fn horizontal_scale(&self) -> f32 {
+        let natural = reflow::text_width(&self.text, self.font_size);
+        if natural > 0.0 && self.width > 0.0 { self.width / natural } else { 1.0 }
+    }
+}
+
+/// Items whose content isn't text read off the page
+fn has_page_text(item: &DocumentItem) -> bool {
+    !matches!(item.item_type, ItemType::Picture | ItemType::Checkbox | ItemType::Formula | ItemType::Barcode)
+}
+
+/// An item's lines, sharing its box evenly from the top
+pub fn item_lines(item: &DocumentItem) -> Vec<HiddenLine> {
+    let lines: Vec<&str> = item.content.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let line_height = item.bbox.height as f32 / lines.len() as f32;
+    let font_size = if item.font_size > 0.0 { item.font_size.min(line_height) } else { line_height * ASCENT };
+    let font_size = font_size.max(1.0);
+    lines.into_iter().enumerate().map(|(i, text)| {
+        let top = item.bbox.top as f32 + i as f32 * line_height;
+        HiddenLine {
+            text: text.to_string(),
+            left: item.bbox.left as f32,
+            baseline: top + (line_height - font_size) / 2.0 + font_size * ASCENT,
+            width: item.bbox.width as f32,
+            font_size,
+        }
+    }).collect()
+}
+
+/// Hidden lines for each page, with edits applied; pages with a text layer of
+/// their own (`text_layer_chars`, as from `Session::text_layer_chars`) get none
+pub fn plan(data: &Value, edits: &EditPatch, text_layer_chars: &[Option<usize>]) -> Vec<Vec<HiddenLine>> {
+    text_layer_chars.iter().enumerate().map(|(page, chars)| {
+        if chars.is_some() {
+            return Vec::new();
+        }
+        document::edited_page_items(data, page, edits).iter()
+            .filter(|item| has_page_text(item))
+            .flat_map(item_lines)
+            .collect()
+    }).collect()
+}
+
+/// Write a copy of the PDF with the planned lines as invisible text; returns
+/// the number of pages that got text
+pub fn write_pdf(pdfium: &Pdfium, pdf_bytes: &[u8], password: Option<&str>, plan: &[Vec<HiddenLine>], path: &Path, output: &PdfOutput) -> Result<usize> {
+    let mut document = pdfium.load_pdf_from_byte_vec(pdf_bytes.to_vec(), password)
+        .map_err(|e| anyhow!("Failed to load PDF: {}", e))?;
+    let font = document.fonts_mut().helvetica();
+    let mut written = 0;
+    for (index, lines) in plan.iter().enumerate().filter(|(_, lines)| !lines.is_empty()) {
+        let mut page = document.pages().get(index as u16)
+            .map_err(|e| anyhow!("Failed to open page {}: {}", index + 1, e))?;
+        let page_height = page.height().value;
+        for line in lines {
+            let mut object = PdfPageTextObject::new(&document, &line.text, font, PdfPoints::new(line.font_size))
+                .map_err(|e| anyhow!("Failed to place text: {}", e))?;
+            object.set_render_mode(PdfPageTextRenderMode::Invisible)
+                .and_then(|()| object.scale(line.horizontal_scale(), 1.0))
+                .and_then(|()| object.translate(PdfPoints::new(line.left), PdfPoints::new(page_height - line.baseline)))
+                .map_err(|e| anyhow!("Failed to place text: {}", e))?;
+            page.objects_mut().add_text_object(object)
+                .map_err(|e| anyhow!("Failed to place text: {}", e))?;
+        }
+        written += 1;
+    }
+    pdf_output::save(&document, path, output)?;
+    Ok(written)
+}
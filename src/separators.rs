@@ -0,0 +1,134 @@
+//! Blank pages and separator sheets in batch scans
+//!
+//! A stack of documents scanned in one go comes out as a single PDF. Pages
+//! are rendered small and classified: blank pages (the empty backs of duplex
+//! scans) are dropped, and patch-code separator sheets — a few thick vertical
+//! bars and nothing else — end one document and start the next. Each
+//! resulting document is written as its own PDF.
+
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use image::GrayImage;
+use pdfium_render::prelude::*;
+
+/// Pages are classified at this resolution
+const ANALYSIS_DPI: f32 = 50.0;
+
+/// Darker than this counts as ink
+const INK_LEVEL: u8 = 128;
+
+/// Share of the width and height at each edge ignored, for scanner shadows and punch holes
+const EDGE_MARGIN: f32 = 0.05;
+
+/// Pages with less ink than this share of their area are blank
+pub const BLANK_INK: f32 = 0.003;
+
+/// A column belongs to a bar when this share of its rows is ink
+const BAR_COLUMN_INK: f32 = 0.6;
+
+/// Patch codes have four bars; allow some slack for smeared or doubled bars
+const BAR_COUNT: std::ops::RangeInclusive<usize> = 3..=6;
+
+/// Ink outside the bars a separator sheet may have, as a share of the page
+const SEPARATOR_STRAY_INK: f32 = 0.02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    Content,
+    Blank,
+    Separator,
+}
+
+/// Classify a rendered page
+pub fn classify_page(page: &GrayImage) -> PageKind {
+    let (width, height) = page.dimensions();
+    let (x_margin, y_margin) = ((width as f32 * EDGE_MARGIN) as u32, (height as f32 * EDGE_MARGIN) as u32);
+    let columns = x_margin..width.saturating_sub(x_margin);
+    let rows = y_margin..height.saturating_sub(y_margin);
+    if columns.is_empty() || rows.is_empty() {
+        return PageKind::Blank;
+    }
+
+    let column_ink: Vec<u32> = columns.clone()
+        .map(|x| rows.clone().filter(|&y| page.get_pixel(x, y)[0] < INK_LEVEL).count() as u32)
+        .collect();
+    let area = (columns.len() * rows.len()) as f32;
+    let ink: u32 = column_ink.iter().sum();
+    if (ink as f32) < area * BLANK_INK {
+        return PageKind::Blank;
+    }
+
+    // Runs of nearly solid columns are bars
+    let is_bar = |count: &u32| *count as f32 >= rows.len() as f32 * BAR_COLUMN_INK;
+    let mut bars = 0;
+    let mut previous = false;
+    for count in &column_ink {
+        let bar = is_bar(count);
+        if bar && !previous {
+            bars += 1;
+        }
+        previous = bar;
+    }
+    let stray: u32 = column_ink.iter().filter(|c| !is_bar(c)).sum();
+    if BAR_COUNT.contains(&bars) && (stray as f32) < area * SEPARATOR_STRAY_INK {
+        PageKind::Separator
+    } else {
+        PageKind::Content
+    }
+}
+
+/// Page ranges of the logical documents: separators split, blank pages are left out
+pub fn split_documents(kinds: &[PageKind]) -> Vec<Vec<usize>> {
+    let mut documents = Vec::new();
+    let mut current = Vec::new();
+    for (index, kind) in kinds.iter().enumerate() {
+        match kind {
+            PageKind::Content => current.push(index),
+            PageKind::Blank => {}
+            PageKind::Separator => {
+                if !current.is_empty() {
+                    documents.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        documents.push(current);
+    }
+    documents
+}
+
+/// Render and classify every page
+pub fn classify_pdf(pdfium: &Pdfium, pdf_bytes: &[u8]) -> Result<Vec<PageKind>> {
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, None)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let config = PdfRenderConfig::new()
+        .scale_page_by_factor(ANALYSIS_DPI / 72.0)
+        .render_form_data(true);
+    let mut kinds = Vec::with_capacity(document.pages().len() as usize);
+    for (index, page) in document.pages().iter().enumerate() {
+        let bitmap = page.render_with_config(&config)
+            .map_err(|e| anyhow!("Failed to render page {}: {}", index + 1, e))?;
+        let rendered = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+            .ok_or_else(|| anyhow!("Page {} rendered to an unexpected size", index + 1))?;
+        kinds.push(classify_page(&image::DynamicImage::ImageRgba8(rendered).to_luma8()));
+    }
+    Ok(kinds)
+}
+
+/// Write each document's pages to `{stem}_doc{N}.pdf` in `dir`
+pub fn write_documents(pdfium: &Pdfium, pdf_bytes: &[u8], documents: &[Vec<usize>], dir: &Path, stem: &str) -> Result<Vec<PathBuf>> {
+    let source = pdfium.load_pdf_from_byte_slice(pdf_bytes, None)
+        .map_err(|e| anyhow!("Failed to open PDF: {}", e))?;
+    let mut paths = Vec::new();
+    for (number, pages) in documents.iter().enumerate() {
+        let mut output = pdfium.create_new_pdf()?;
+        for (dest_index, page) in pages.iter().enumerate() {
+            output.pages_mut().copy_page_from_document(&source, *page as u16, dest_index as u16)?;
+        }
+        let path = dir.join(format!("{}_doc{}.pdf", stem, number + 1));
+        output.save_to_file(&path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,367 @@
+//! User settings
+//!
+//! Settings live in a JSON file in the session directory, next to the
+//! workspace. Missing or unreadable files fall back to the defaults.
+
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::CopyFormat;
+use crate::extractor::{ExtractOptions, ExtractorKind};
+use crate::llm::LlmSettings;
+use crate::palette::Palette;
+use crate::pdf_output::PdfOutput;
+use crate::pii::PiiSettings;
+use crate::pipeline::ExportPipeline;
+use crate::reflow::PrintLayout;
+use crate::remote::RemoteLocation;
+use crate::stamp::StampMode;
+use crate::storage::{self, StorageDirs};
+
+pub const DEFAULT_SETTINGS_FILE: &str = "chonker3_settings.json";
+
+/// Copy format for a click on an item, by held modifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopySettings {
+    pub click: CopyFormat,
+    pub shift_click: CopyFormat,
+    pub alt_click: CopyFormat,
+    /// Ctrl on Windows/Linux, Cmd on macOS
+    pub command_click: CopyFormat,
+}
+
+impl Default for CopySettings {
+    fn default() -> Self {
+        Self {
+            click: CopyFormat::Plain,
+            shift_click: CopyFormat::Layout,
+            alt_click: CopyFormat::Markdown,
+            command_click: CopyFormat::Tsv,
+        }
+    }
+}
+
+impl CopySettings {
+    /// Format for a click with these modifiers; command wins over alt over shift
+    pub fn format_for(&self, modifiers: egui::Modifiers) -> CopyFormat {
+        if modifiers.command {
+            self.command_click
+        } else if modifiers.alt {
+            self.alt_click
+        } else if modifiers.shift {
+            self.shift_click
+        } else {
+            self.click
+        }
+    }
+}
+
+/// Rendering quality against power and memory use. A preset sets the page
+/// texture scale, prefetch depth, memory budget and repaint interval; the
+/// settings file stores those values, and the preset is whichever one they match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityPreset {
+    BatterySaver,
+    Balanced,
+    Quality,
+}
+
+impl QualityPreset {
+    pub const ALL: [QualityPreset; 3] = [QualityPreset::BatterySaver, QualityPreset::Balanced, QualityPreset::Quality];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QualityPreset::BatterySaver => "Battery saver",
+            QualityPreset::Balanced => "Balanced",
+            QualityPreset::Quality => "Quality",
+        }
+    }
+
+    /// Page textures render at this multiple of the screen's pixel density
+    pub fn texture_scale(&self) -> f32 {
+        match self {
+            QualityPreset::BatterySaver => 0.75,
+            QualityPreset::Balanced => 1.0,
+            QualityPreset::Quality => 1.5,
+        }
+    }
+
+    pub fn prefetch_depth(&self) -> usize {
+        match self {
+            QualityPreset::BatterySaver => 0,
+            QualityPreset::Balanced => 2,
+            QualityPreset::Quality => 4,
+        }
+    }
+
+    pub fn memory_budget_mb(&self) -> usize {
+        match self {
+            QualityPreset::BatterySaver => 512,
+            QualityPreset::Balanced => crate::memory::DEFAULT_BUDGET_MB,
+            QualityPreset::Quality => 2048,
+        }
+    }
+
+    pub fn repaint_interval_ms(&self) -> u64 {
+        match self {
+            QualityPreset::BatterySaver => 66,
+            QualityPreset::Balanced => 16,
+            QualityPreset::Quality => 0,
+        }
+    }
+}
+
+/// How single-line items are stretched or squeezed to their box width
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WidthFitting {
+    /// Natural width; lines may run past or stop short of their box
+    #[default]
+    Off,
+    /// Spread or tighten the letters
+    LetterSpacing,
+    /// Grow or shrink the font
+    Scale,
+}
+
+impl WidthFitting {
+    pub const ALL: [WidthFitting; 3] = [WidthFitting::Off, WidthFitting::LetterSpacing, WidthFitting::Scale];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WidthFitting::Off => "Off",
+            WidthFitting::LetterSpacing => "Letter spacing",
+            WidthFitting::Scale => "Font scale",
+        }
+    }
+
+    /// Font size and extra letter spacing that bring a line laid out `natural_width`
+    /// wide to `target_width`. Adjustments are capped so odd boxes don't mangle the text.
+    pub fn fit(&self, font_size: f32, natural_width: f32, target_width: f32, char_count: usize) -> (f32, f32) {
+        if natural_width <= 0.0 || target_width <= 0.0 {
+            return (font_size, 0.0);
+        }
+        match self {
+            WidthFitting::Off => (font_size, 0.0),
+            WidthFitting::LetterSpacing if char_count > 1 => {
+                let spacing = (target_width - natural_width) / (char_count - 1) as f32;
+                (font_size, spacing.clamp(-0.15 * font_size, 0.5 * font_size))
+            }
+            WidthFitting::LetterSpacing => (font_size, 0.0),
+            WidthFitting::Scale => (font_size * (target_width / natural_width).clamp(0.7, 1.4), 0.0),
+        }
+    }
+}
+
+/// Start extraction as soon as a PDF is opened, optionally only for small files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoExtract {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Skip files larger than `max_size_mb`, which would tie up the extractor
+    #[serde(default)]
+    pub limit_size: bool,
+    #[serde(default = "default_auto_extract_max_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for AutoExtract {
+    fn default() -> Self {
+        Self { enabled: false, limit_size: false, max_size_mb: default_auto_extract_max_mb() }
+    }
+}
+
+impl AutoExtract {
+    /// Whether a file of `file_size` bytes should be extracted on open
+    pub fn applies_to(&self, file_size: u64) -> bool {
+        self.enabled && (!self.limit_size || file_size <= self.max_size_mb * 1024 * 1024)
+    }
+}
+
+fn default_auto_extract_max_mb() -> u64 {
+    20
+}
+
+fn default_memory_budget_mb() -> usize {
+    crate::memory::DEFAULT_BUDGET_MB
+}
+
+fn default_prefetch_depth() -> usize {
+    QualityPreset::Balanced.prefetch_depth()
+}
+
+fn default_texture_scale() -> f32 {
+    QualityPreset::Balanced.texture_scale()
+}
+
+fn default_repaint_interval_ms() -> u64 {
+    QualityPreset::Balanced.repaint_interval_ms()
+}
+
+fn default_ghost_opacity() -> f32 {
+    0.25
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub copy: CopySettings,
+    /// Above this, caches are evicted and pages render at lower resolution
+    #[serde(default = "default_memory_budget_mb")]
+    pub memory_budget_mb: usize,
+    /// Pages rendered ahead in the reading direction; 0 renders only on demand
+    #[serde(default = "default_prefetch_depth")]
+    pub prefetch_depth: usize,
+    /// Page textures render at this multiple of the screen's pixel density
+    #[serde(default = "default_texture_scale")]
+    pub texture_scale: f32,
+    /// Shortest time between frames while scrolling or extracting; 0 repaints every frame
+    #[serde(default = "default_repaint_interval_ms")]
+    pub repaint_interval_ms: u64,
+    /// pdfium library the user located by hand
+    #[serde(default)]
+    pub pdfium_library: Option<PathBuf>,
+    /// Whether the first-run offer to download pdfium has been answered
+    #[serde(default)]
+    pub pdfium_download_offered: bool,
+    /// Ease wheel scrolling in and keep coasting after a drag
+    #[serde(default = "default_true")]
+    pub smooth_scrolling: bool,
+    /// Stack every page in one vertical scroll instead of showing one at a time
+    #[serde(default)]
+    pub continuous_pages: bool,
+    /// Fit single-line items to their box width
+    #[serde(default)]
+    pub width_fitting: WidthFitting,
+    /// Highlight and marker colors for both panels
+    #[serde(default)]
+    pub palette: Palette,
+    /// Opacity of the PDF page drawn under the extraction in ghost mode
+    #[serde(default = "default_ghost_opacity")]
+    pub ghost_opacity: f32,
+    /// Paper and margins of the reflowed PDF export
+    #[serde(default)]
+    pub print_layout: PrintLayout,
+    /// Image compression and linearization of exported PDFs
+    #[serde(default)]
+    pub pdf_output: PdfOutput,
+    /// Outputs and manifest of the workspace batch export
+    #[serde(default)]
+    pub export_pipeline: ExportPipeline,
+    /// Whether exports record their source checksum and provenance
+    #[serde(default)]
+    pub stamping: StampMode,
+    /// Backend the Extract button runs
+    #[serde(default)]
+    pub extractor: ExtractorKind,
+    #[serde(default)]
+    pub extract_options: ExtractOptions,
+    #[serde(default)]
+    pub auto_extract: AutoExtract,
+    /// Reuse the cached extraction of a PDF extracted before with the same settings
+    #[serde(default = "default_true")]
+    pub cache_extractions: bool,
+    /// What the personal data pass looks for
+    #[serde(default)]
+    pub pii: PiiSettings,
+    /// Temp, cache and session directories; the environment variables win
+    #[serde(default)]
+    pub storage: StorageDirs,
+    /// Name comments are signed with; the login name when unset
+    #[serde(default)]
+    pub comment_author: Option<String>,
+    /// Keep the passwords of encrypted PDFs in the OS keyring (see `passwords`)
+    #[serde(default)]
+    pub remember_pdf_passwords: bool,
+    /// OpenAI-compatible endpoint for post-processing; off by default
+    #[serde(default)]
+    pub llm: LlmSettings,
+    /// S3 buckets and WebDAV folders to open from and export to
+    #[serde(default)]
+    pub remote_locations: Vec<RemoteLocation>,
+    #[serde(skip)]
+    pub file_path: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            copy: CopySettings::default(),
+            memory_budget_mb: default_memory_budget_mb(),
+            prefetch_depth: default_prefetch_depth(),
+            texture_scale: default_texture_scale(),
+            repaint_interval_ms: default_repaint_interval_ms(),
+            pdfium_library: None,
+            pdfium_download_offered: false,
+            smooth_scrolling: true,
+            continuous_pages: false,
+            width_fitting: WidthFitting::default(),
+            palette: Palette::default(),
+            ghost_opacity: default_ghost_opacity(),
+            print_layout: PrintLayout::default(),
+            pdf_output: PdfOutput::default(),
+            export_pipeline: ExportPipeline::default(),
+            stamping: StampMode::default(),
+            extractor: ExtractorKind::default(),
+            extract_options: ExtractOptions::default(),
+            auto_extract: AutoExtract::default(),
+            cache_extractions: true,
+            pii: PiiSettings::default(),
+            storage: StorageDirs::default(),
+            comment_author: None,
+            remember_pdf_passwords: false,
+            llm: LlmSettings::default(),
+            remote_locations: Vec::new(),
+            file_path: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut settings: Settings = serde_json::from_str(&contents).context("Invalid settings file")?;
+        settings.file_path = Some(path.to_path_buf());
+        Ok(settings)
+    }
+
+    /// Load settings from the session directory, or start from the defaults there.
+    /// Only `CHONKER3_SESSION_DIR` can move this file; the setting moves the rest.
+    pub fn load_default() -> Self {
+        let path = storage::session_dir().join(DEFAULT_SETTINGS_FILE);
+        Self::load(&path).unwrap_or_else(|_| Settings {
+            file_path: Some(path),
+            ..Default::default()
+        })
+    }
+
+    /// The preset the quality settings match; None once one is changed by hand
+    pub fn quality_preset(&self) -> Option<QualityPreset> {
+        QualityPreset::ALL.into_iter().find(|preset| {
+            self.texture_scale == preset.texture_scale()
+                && self.prefetch_depth == preset.prefetch_depth()
+                && self.memory_budget_mb == preset.memory_budget_mb()
+                && self.repaint_interval_ms == preset.repaint_interval_ms()
+        })
+    }
+
+    pub fn apply_quality_preset(&mut self, preset: QualityPreset) {
+        self.texture_scale = preset.texture_scale();
+        self.prefetch_depth = preset.prefetch_depth();
+        self.memory_budget_mb = preset.memory_budget_mb();
+        self.repaint_interval_ms = preset.repaint_interval_ms();
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.file_path {
+            let json = serde_json::to_string_pretty(self)?;
+            std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
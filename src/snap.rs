@@ -0,0 +1,85 @@
+//! Snap item boxes to the PDF's text geometry
+//!
+//! Extractors sometimes report boxes a line off, or from a different origin
+//! than they claim. When an item's text can be found in pdfium's text page,
+//! the box around the matching glyphs is the true one; the nearest such match
+//! replaces the extracted box as an edit.
+
+use std::collections::BTreeMap;
+use pdfium_render::prelude::*;
+
+use crate::types::{BoundingBox, DocumentItem};
+
+/// Matches farther than this many item heights away are taken to be other occurrences
+const MAX_SNAP_HEIGHTS: f64 = 4.0;
+
+/// ...but always allow this far (points), for boxes with a wrong height
+const MIN_SNAP_DISTANCE: f64 = 24.0;
+
+/// Changes smaller than this (points) aren't worth an edit
+const SNAP_EPSILON: f64 = 0.5;
+
+/// Every place `text` occurs on the page, as TOPLEFT boxes
+pub fn find_text_boxes(page: &PdfPage, text: &str) -> Vec<BoundingBox> {
+    let needle = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (Ok(text_page), false) = (page.text(), needle.is_empty()) else { return Vec::new() };
+    let Ok(search) = text_page.search(&needle, &PdfSearchOptions::new()) else { return Vec::new() };
+    let page_height = page.height().value as f64;
+
+    let mut boxes = Vec::new();
+    while let Some(segments) = search.find_next() {
+        // A match spanning lines comes back as one segment per line
+        let rects: Vec<PdfRect> = segments.iter().map(|s| s.bounds()).collect();
+        let Some(left) = rects.iter().map(|r| r.left().value).reduce(f32::min) else { continue };
+        let right = rects.iter().map(|r| r.right().value).fold(left, f32::max);
+        let top = rects.iter().map(|r| r.top().value).fold(f32::MIN, f32::max);
+        let bottom = rects.iter().map(|r| r.bottom().value).fold(f32::MAX, f32::min);
+        boxes.push(BoundingBox {
+            left: left as f64,
+            top: page_height - top as f64,
+            width: (right - left) as f64,
+            height: (top - bottom) as f64,
+        });
+    }
+    boxes
+}
+
+/// The candidate closest to `current`, if it's near enough to be the same text
+pub fn nearest_box(current: &BoundingBox, candidates: &[BoundingBox]) -> Option<BoundingBox> {
+    let limit = (current.height * MAX_SNAP_HEIGHTS).max(MIN_SNAP_DISTANCE);
+    candidates.iter()
+        .map(|candidate| {
+            let distance = (candidate.left - current.left).hypot(candidate.top - current.top);
+            (distance, candidate)
+        })
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate.clone())
+}
+
+fn differs(a: &BoundingBox, b: &BoundingBox) -> bool {
+    [(a.left, b.left), (a.top, b.top), (a.width, b.width), (a.height, b.height)]
+        .iter()
+        .any(|(x, y)| (x - y).abs() > SNAP_EPSILON)
+}
+
+/// Snapped boxes for the page's items whose text was found and whose box is off.
+/// Items with several lines fall back to their first line's position, keeping their size.
+pub fn snap_items(page: &PdfPage, items: &[DocumentItem]) -> BTreeMap<String, BoundingBox> {
+    let mut snapped = BTreeMap::new();
+    for item in items {
+        let mut target = nearest_box(&item.bbox, &find_text_boxes(page, &item.content));
+        if target.is_none() && item.content.trim().lines().count() > 1 {
+            let first_line = item.content.trim().lines().next().unwrap_or("");
+            target = nearest_box(&item.bbox, &find_text_boxes(page, first_line)).map(|line| BoundingBox {
+                width: item.bbox.width.max(line.width),
+                height: item.bbox.height,
+                ..line
+            });
+        }
+        if let Some(target) = target.filter(|t| differs(t, &item.bbox)) {
+            snapped.insert(item.id.clone(), target);
+        }
+    }
+    snapped
+}
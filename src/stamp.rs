@@ -0,0 +1,179 @@
+//! Checksum and provenance stamps on exports
+//!
+//! So outputs stay traceable in compliance settings, every export can record
+//! where it came from: the SHA-256 of the source PDF, the extractor and its
+//! version, the app version and when it was written. JSON and Markdown carry
+//! the stamp inside (a top-level `stamp` object, or a comment on the first
+//! line); other formats, or all of them when sidecars are chosen, get a
+//! `<file>.stamp.json` next to them that also holds the output's own
+//! checksum, so a changed file can be told apart from the one exported.
+//! Exports that fill a folder get one `<folder>.stamp.json` inside it, with
+//! the checksum of each file there.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::checksum::sha256_hex;
+use crate::pipeline::extractor_name;
+
+/// Key of the stamp in JSON exports
+pub const STAMP_KEY: &str = "stamp";
+/// Start of the stamp comment in Markdown exports
+pub const MARKDOWN_PREFIX: &str = "<!-- chonker3-stamp ";
+/// Added to an output's file name to name its sidecar
+pub const SIDECAR_SUFFIX: &str = ".stamp.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StampMode {
+    Off,
+    /// Inside the file where the format allows, a sidecar otherwise
+    #[default]
+    Embedded,
+    /// Always a sidecar, leaving exports as they'd be without stamps
+    Sidecar,
+}
+
+impl StampMode {
+    pub const ALL: [StampMode; 3] = [StampMode::Off, StampMode::Embedded, StampMode::Sidecar];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StampMode::Off => "Off",
+            StampMode::Embedded => "In the file",
+            StampMode::Sidecar => "Sidecar file",
+        }
+    }
+}
+
+/// Where an export came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stamp {
+    pub source_file: Option<String>,
+    /// None when the PDF couldn't be read
+    pub source_sha256: Option<String>,
+    pub extractor: String,
+    /// As recorded by the extractor, when it recorded one
+    pub extractor_version: Option<String>,
+    pub app_version: String,
+    pub generated: String,
+}
+
+/// Version of whatever produced an extraction, from its metadata
+pub fn extractor_version(data: &Value) -> Option<String> {
+    let metadata = &data["metadata"];
+    ["extractor_version", "docling_version", "importer_version"].iter()
+        .find_map(|key| metadata[*key].as_str())
+        .map(str::to_string)
+}
+
+/// Path of the sidecar stamp of an output
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(SIDECAR_SUFFIX);
+    output.with_file_name(name)
+}
+
+/// Path of the sidecar stamp of an output folder, inside it
+pub fn folder_sidecar_path(dir: &Path) -> PathBuf {
+    let name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "export".to_string());
+    dir.join(format!("{}{}", name, SIDECAR_SUFFIX))
+}
+
+impl Stamp {
+    /// Stamp for exports of an extraction of `source`, whose bytes are hashed
+    pub fn new(source: Option<&Path>, source_bytes: Option<&[u8]>, data: &Value) -> Self {
+        Self {
+            source_file: source.map(|path| path.display().to_string()),
+            source_sha256: source_bytes.map(sha256_hex),
+            extractor: extractor_name(data),
+            extractor_version: extractor_version(data),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated: chrono::Local::now().to_rfc3339(),
+        }
+    }
+
+    /// The contents with the stamp inside, if the format of `path` has room
+    /// for it: JSON objects and Markdown
+    pub fn embed(&self, contents: &[u8], path: &Path) -> Option<Vec<u8>> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        let text = std::str::from_utf8(contents).ok()?;
+        match extension.as_str() {
+            "json" => {
+                let mut value: Value = serde_json::from_str(text).ok()?;
+                value.as_object_mut()?.insert(STAMP_KEY.to_string(), json!(self));
+                serde_json::to_string_pretty(&value).ok().map(String::into_bytes)
+            }
+            "md" | "markdown" => {
+                let comment = format!("{}{} -->\n\n", MARKDOWN_PREFIX, serde_json::to_string(self).ok()?);
+                Some([comment.as_bytes(), contents].concat())
+            }
+            _ => None,
+        }
+    }
+
+    /// The sidecar of an output: this stamp plus the output's name and checksum
+    pub fn sidecar(&self, output: &Path, output_bytes: &[u8]) -> Value {
+        let mut sidecar = json!(self);
+        sidecar["output"] = json!(output.file_name().map(|n| n.to_string_lossy().to_string()));
+        sidecar["output_sha256"] = json!(sha256_hex(output_bytes));
+        sidecar
+    }
+
+    /// Write an export stamped as `mode` asks; returns the bytes written to
+    /// `path` and the sidecar written next to it, if any
+    pub fn write(&self, path: &Path, contents: Vec<u8>, mode: StampMode) -> Result<(Vec<u8>, Option<PathBuf>)> {
+        let (contents, needs_sidecar) = match mode {
+            StampMode::Off => (contents, false),
+            StampMode::Embedded => match self.embed(&contents, path) {
+                Some(stamped) => (stamped, false),
+                None => (contents, true),
+            },
+            StampMode::Sidecar => (contents, true),
+        };
+        std::fs::write(path, &contents).with_context(|| format!("Failed to write {}", path.display()))?;
+        if !needs_sidecar {
+            return Ok((contents, None));
+        }
+        let sidecar = sidecar_path(path);
+        std::fs::write(&sidecar, serde_json::to_string_pretty(&self.sidecar(path, &contents))?)
+            .with_context(|| format!("Failed to write {}", sidecar.display()))?;
+        Ok((contents, Some(sidecar)))
+    }
+
+    /// Stamp an export that filled a folder with a sidecar listing the
+    /// checksums of its files; returns the sidecar, if one was written
+    pub fn apply_folder(&self, dir: &Path, mode: StampMode) -> Result<Option<PathBuf>> {
+        if mode == StampMode::Off {
+            return Ok(None);
+        }
+        let mut files = BTreeMap::new();
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !path.is_file() || name.ends_with(SIDECAR_SUFFIX) {
+                continue;
+            }
+            let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            files.insert(name, sha256_hex(&bytes));
+        }
+        let mut sidecar = json!(self);
+        sidecar["output"] = json!(dir.file_name().map(|n| n.to_string_lossy().to_string()));
+        sidecar["files_sha256"] = json!(files);
+        let path = folder_sidecar_path(dir);
+        std::fs::write(&path, serde_json::to_string_pretty(&sidecar)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Some(path))
+    }
+
+    /// Stamp an export that is already written
+    pub fn apply(&self, path: &Path, mode: StampMode) -> Result<Option<PathBuf>> {
+        if mode == StampMode::Off {
+            return Ok(None);
+        }
+        let contents = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(self.write(path, contents, mode)?.1)
+    }
+}
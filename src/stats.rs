@@ -0,0 +1,150 @@
+//! Document statistics
+//!
+//! A summary of one extraction with the user's edits applied: items by type,
+//! words and characters, average recognition confidence, the pages that need
+//! a look, and how long the extractor took. Exported as JSON for reporting.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::document;
+use crate::patch::EditPatch;
+use crate::types::{self, ItemType, LOW_CONFIDENCE};
+
+/// Why a page needs review
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PageIssues {
+    /// One-based, like the page markers in exports
+    pub page: usize,
+    /// Nothing was extracted from the page
+    pub empty: bool,
+    /// Items below the low-confidence threshold
+    pub low_confidence_items: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DocumentStats {
+    pub source_file: Option<String>,
+    pub extractor: Option<String>,
+    pub pages: usize,
+    pub items: usize,
+    /// Item count per type label; types without items are left out
+    pub items_by_type: BTreeMap<String, usize>,
+    pub words: usize,
+    /// Characters other than whitespace
+    pub characters: usize,
+    pub tables: usize,
+    pub images: usize,
+    /// Mean over the items that report a confidence
+    pub average_confidence: Option<f32>,
+    pub low_confidence_items: usize,
+    pub pages_with_issues: Vec<PageIssues>,
+    /// Wall-clock time the extractor took, when it recorded it
+    pub extraction_seconds: Option<f64>,
+}
+
+/// Where review effort is needed on one page, for the heatmap strip
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PageHeat {
+    /// Edited items: text, moves, boxes, deletions, type changes and annotations
+    pub edits: usize,
+    pub low_confidence_items: usize,
+    /// Share of the PDF text layer's characters found in the extraction, at most 1;
+    /// None for pages without a text layer
+    pub coverage: Option<f32>,
+}
+
+impl PageHeat {
+    /// Share of the text layer the extraction is missing
+    pub fn coverage_gap(&self) -> f32 {
+        self.coverage.map_or(0.0, |coverage| 1.0 - coverage)
+    }
+}
+
+/// Statistics of the extraction as edited
+pub fn document_stats(data: &Value, edits: &EditPatch) -> DocumentStats {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map_or(0, |p| p.len());
+    let metadata = data.get("metadata");
+    let mut stats = DocumentStats {
+        source_file: edits.source_file.clone(),
+        extractor: metadata.and_then(|m| m.get("importer").or_else(|| m.get("extractor"))).and_then(|v| v.as_str()).map(str::to_string),
+        pages: page_count,
+        extraction_seconds: metadata.and_then(|m| m.get("extraction_seconds")).and_then(|v| v.as_f64()),
+        ..DocumentStats::default()
+    };
+
+    let mut confidences = Vec::new();
+    for page_index in 0..page_count {
+        let items = document::page_items(data, page_index, edits);
+        let mut issues = PageIssues { page: page_index + 1, empty: items.is_empty(), low_confidence_items: 0 };
+        for item in &items {
+            let text = edits.text_overrides.get(&item.id).unwrap_or(&item.content);
+            stats.items += 1;
+            *stats.items_by_type.entry(item.item_type.label().to_string()).or_default() += 1;
+            stats.words += text.split_whitespace().count();
+            stats.characters += text.chars().filter(|c| !c.is_whitespace()).count();
+            match item.item_type {
+                ItemType::Table => stats.tables += 1,
+                ItemType::Picture => stats.images += 1,
+                _ => {}
+            }
+            if let Some(confidence) = item.confidence {
+                confidences.push(confidence);
+                if confidence < LOW_CONFIDENCE {
+                    issues.low_confidence_items += 1;
+                }
+            }
+        }
+        stats.low_confidence_items += issues.low_confidence_items;
+        if issues.empty || issues.low_confidence_items > 0 {
+            stats.pages_with_issues.push(issues);
+        }
+    }
+    if !confidences.is_empty() {
+        stats.average_confidence = Some(confidences.iter().sum::<f32>() / confidences.len() as f32);
+    }
+    stats
+}
+
+/// Edits, low-confidence items and text-layer coverage per page. `text_layer_chars`
+/// holds the non-whitespace characters of each PDF page's text layer.
+pub fn page_heat(data: &Value, edits: &EditPatch, text_layer_chars: &[Option<usize>]) -> Vec<PageHeat> {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map_or(0, |p| p.len());
+    let mut heat = vec![PageHeat::default(); page_count];
+
+    let edited = edits.text_overrides.keys()
+        .chain(edits.offsets.keys())
+        .chain(edits.boxes.keys())
+        .chain(edits.deletions.iter())
+        .chain(edits.type_changes.keys())
+        .chain(edits.annotations.keys());
+    for id in edited {
+        if let Some(page) = types::item_page(id).and_then(|page| heat.get_mut(page)) {
+            page.edits += 1;
+        }
+    }
+
+    for (page_index, page) in heat.iter_mut().enumerate() {
+        let mut characters = 0;
+        for item in document::page_items(data, page_index, edits) {
+            let text = edits.text_overrides.get(&item.id).unwrap_or(&item.content);
+            characters += text.chars().filter(|c| !c.is_whitespace()).count();
+            if item.confidence.is_some_and(|confidence| confidence < LOW_CONFIDENCE) {
+                page.low_confidence_items += 1;
+            }
+        }
+        page.coverage = text_layer_chars.get(page_index).copied().flatten()
+            .filter(|&layer| layer > 0)
+            .map(|layer| (characters as f32 / layer as f32).min(1.0));
+    }
+    heat
+}
+
+/// Write the statistics as pretty-printed JSON
+pub fn write_stats(path: &Path, stats: &DocumentStats) -> Result<()> {
+    let json = serde_json::to_string_pretty(stats)?;
+    std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
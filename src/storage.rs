@@ -0,0 +1,155 @@
+//! Where files are written
+//!
+//! Extraction JSON, OCR crops and the extractor's own scratch files go to the
+//! temp directory; converted inputs and cleaned scans, which are worth keeping
+//! between extractions, to the cache directory; the settings, workspace and
+//! autorun script to the session directory. Each can be set in the settings or
+//! with an environment variable, which wins, for machines that only allow
+//! writing to particular places. A directory that can't be written to is
+//! reported and the default used instead.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    Temp,
+    Cache,
+    Session,
+}
+
+impl StorageKind {
+    pub const ALL: [StorageKind; 3] = [StorageKind::Temp, StorageKind::Cache, StorageKind::Session];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StorageKind::Temp => "Temporary files",
+            StorageKind::Cache => "Page cache",
+            StorageKind::Session => "Session files",
+        }
+    }
+
+    /// Environment variable that overrides the setting
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            StorageKind::Temp => "CHONKER3_TEMP_DIR",
+            StorageKind::Cache => "CHONKER3_CACHE_DIR",
+            StorageKind::Session => "CHONKER3_SESSION_DIR",
+        }
+    }
+
+    /// Where files go when nothing is configured
+    pub fn default_dir(&self) -> PathBuf {
+        match self {
+            StorageKind::Temp => std::env::temp_dir(),
+            StorageKind::Cache => std::env::temp_dir().join("chonker3_inputs"),
+            StorageKind::Session => std::env::current_dir().unwrap_or_default(),
+        }
+    }
+}
+
+/// Directories chosen in the settings; None uses the default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageDirs {
+    #[serde(default)]
+    pub temp: Option<PathBuf>,
+    #[serde(default)]
+    pub cache: Option<PathBuf>,
+    #[serde(default)]
+    pub session: Option<PathBuf>,
+}
+
+impl StorageDirs {
+    pub fn get(&self, kind: StorageKind) -> Option<&PathBuf> {
+        match kind {
+            StorageKind::Temp => self.temp.as_ref(),
+            StorageKind::Cache => self.cache.as_ref(),
+            StorageKind::Session => self.session.as_ref(),
+        }
+    }
+
+    pub fn get_mut(&mut self, kind: StorageKind) -> &mut Option<PathBuf> {
+        match kind {
+            StorageKind::Temp => &mut self.temp,
+            StorageKind::Cache => &mut self.cache,
+            StorageKind::Session => &mut self.session,
+        }
+    }
+}
+
+/// Where one kind of file goes, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedDir {
+    pub path: PathBuf,
+    /// Set by the environment variable rather than the settings
+    pub from_env: bool,
+}
+
+/// Check that a directory can hold Chonker3's files, creating it if needed
+pub fn validate_dir(path: &Path) -> Result<()> {
+    if path.as_os_str().is_empty() {
+        bail!("No directory given");
+    }
+    if path.exists() && !path.is_dir() {
+        bail!("{} is not a directory", path.display());
+    }
+    std::fs::create_dir_all(path).with_context(|| format!("Can't create {}", path.display()))?;
+    let probe = path.join(format!(".chonker3_write_test_{}", std::process::id()));
+    std::fs::write(&probe, b"").with_context(|| format!("Can't write to {}", path.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// The directory for `kind`: the environment variable's, else the setting's,
+/// else the default. `env` looks up a variable. A directory that fails
+/// validation is skipped with a warning.
+pub fn resolve(kind: StorageKind, dirs: &StorageDirs, env: impl Fn(&str) -> Option<String>, warnings: &mut Vec<String>) -> ResolvedDir {
+    let from_env = env(kind.env_var()).filter(|value| !value.trim().is_empty()).map(PathBuf::from);
+    let candidates = [(from_env, true), (dirs.get(kind).cloned(), false)];
+    for (path, from_env) in candidates {
+        let Some(path) = path else { continue };
+        match validate_dir(&path) {
+            Ok(()) => return ResolvedDir { path, from_env },
+            Err(e) => {
+                let source = if from_env { kind.env_var().to_string() } else { "the settings".to_string() };
+                warnings.push(format!("{} from {}: {:#}; using the next choice", kind.label(), source, e));
+            }
+        }
+    }
+    ResolvedDir { path: kind.default_dir(), from_env: false }
+}
+
+static CONFIGURED: RwLock<Option<[ResolvedDir; 3]>> = RwLock::new(None);
+
+/// Resolve all directories against the process environment and use them from
+/// now on; returns warnings about the ones that couldn't be used
+pub fn configure(dirs: &StorageDirs) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let resolved = StorageKind::ALL.map(|kind| resolve(kind, dirs, |name| std::env::var(name).ok(), &mut warnings));
+    *CONFIGURED.write().unwrap() = Some(resolved);
+    warnings
+}
+
+/// The directory for `kind`. Before `configure` runs, only the environment
+/// variable and the default count, which is how the settings file is found.
+pub fn resolved(kind: StorageKind) -> ResolvedDir {
+    let index = StorageKind::ALL.iter().position(|k| *k == kind).unwrap_or(0);
+    if let Some(configured) = CONFIGURED.read().unwrap().as_ref() {
+        return configured[index].clone();
+    }
+    resolve(kind, &StorageDirs::default(), |name| std::env::var(name).ok(), &mut Vec::new())
+}
+
+pub fn temp_dir() -> PathBuf {
+    resolved(StorageKind::Temp).path
+}
+
+pub fn cache_dir() -> PathBuf {
+    resolved(StorageKind::Cache).path
+}
+
+pub fn session_dir() -> PathBuf {
+    resolved(StorageKind::Session).path
+}
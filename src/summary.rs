@@ -0,0 +1,167 @@
+//! Page and document summaries
+//!
+//! Extractive summaries by TextRank: the sentences most similar to the rest of
+//! the text, in reading order. They run locally on the edited text; an LLM
+//! summary can stand in for either one when an endpoint is set up. Summaries
+//! are cached on the session until the extraction changes and go into
+//! exported bundles as `summary.md`.
+
+use std::collections::{BTreeMap, HashSet};
+use serde_json::Value;
+
+use crate::document;
+use crate::patch::EditPatch;
+use crate::types::ItemType;
+
+/// Sentences in a page summary
+pub const PAGE_SENTENCES: usize = 3;
+/// Sentences in a document summary
+pub const DOCUMENT_SENTENCES: usize = 6;
+
+/// TextRank damping factor and iterations
+const DAMPING: f64 = 0.85;
+const ITERATIONS: usize = 50;
+
+/// Words too common to say two sentences are about the same thing
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "have", "in", "is", "it",
+    "its", "of", "on", "or", "that", "the", "this", "to", "was", "were", "which", "with",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SummaryMethod {
+    #[default]
+    TextRank,
+    Llm,
+}
+
+impl SummaryMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SummaryMethod::TextRank => "TextRank",
+            SummaryMethod::Llm => "LLM",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub method: SummaryMethod,
+    pub text: String,
+}
+
+/// Summaries made so far, by zero-based page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summaries {
+    pub pages: BTreeMap<usize, Summary>,
+    pub document: Option<Summary>,
+}
+
+impl Summaries {
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty() && self.document.is_none()
+    }
+
+    /// The summaries as Markdown, document first
+    pub fn markdown(&self, title: &str) -> String {
+        let mut out = format!("# Summary of {}\n", title);
+        if let Some(summary) = &self.document {
+            out.push_str(&format!("\n## Document ({})\n\n{}\n", summary.method.label(), summary.text));
+        }
+        for (page, summary) in &self.pages {
+            out.push_str(&format!("\n## Page {} ({})\n\n{}\n", page + 1, summary.method.label(), summary.text));
+        }
+        out
+    }
+}
+
+/// The text's sentences, split at sentence punctuation followed by
+/// whitespace and at line breaks
+pub fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            current.push(c);
+            if matches!(c, '.' | '!' | '?') && chars.peek().is_none_or(|next| next.is_whitespace()) {
+                sentences.push(std::mem::take(&mut current));
+            }
+        }
+        sentences.push(current);
+    }
+    sentences.into_iter()
+        .map(|sentence| sentence.trim().to_string())
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+/// A sentence's distinct content words, lowercased
+fn words(sentence: &str) -> HashSet<String> {
+    sentence.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() > 1 && !STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Shared words, normalized by sentence length as in the TextRank paper
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let shared = a.intersection(b).count();
+    if shared == 0 {
+        return 0.0;
+    }
+    let norm = (a.len() as f64).ln() + (b.len() as f64).ln();
+    if norm <= 0.0 { shared as f64 } else { shared as f64 / norm }
+}
+
+/// Each sentence's TextRank score
+pub fn scores(sentences: &[String]) -> Vec<f64> {
+    let words: Vec<HashSet<String>> = sentences.iter().map(|s| words(s)).collect();
+    let n = sentences.len();
+    let weights: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 0.0 } else { similarity(&words[i], &words[j]) }).collect())
+        .collect();
+    let totals: Vec<f64> = weights.iter().map(|row| row.iter().sum()).collect();
+    let mut scores = vec![1.0; n];
+    for _ in 0..ITERATIONS {
+        scores = (0..n).map(|i| {
+            let incoming: f64 = (0..n)
+                .filter(|&j| totals[j] > 0.0)
+                .map(|j| weights[j][i] / totals[j] * scores[j])
+                .sum();
+            (1.0 - DAMPING) + DAMPING * incoming
+        }).collect();
+    }
+    scores
+}
+
+/// The `count` best-scoring sentences of the text, in their original order
+pub fn textrank(text: &str, count: usize) -> String {
+    let sentences = sentences(text);
+    let scores = scores(&sentences);
+    let mut ranked: Vec<usize> = (0..sentences.len()).collect();
+    // Earlier sentences win ties
+    ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+    ranked.truncate(count);
+    ranked.sort_unstable();
+    ranked.into_iter().map(|index| sentences[index].as_str()).collect::<Vec<_>>().join(" ")
+}
+
+/// A page's running text: its body text and titles, one item per line
+pub fn page_text(data: &Value, page: usize, edits: &EditPatch) -> String {
+    document::edited_page_items(data, page, edits).into_iter()
+        .filter(|item| matches!(item.item_type, ItemType::Text | ItemType::Title))
+        .map(|item| item.content.trim().to_string())
+        .filter(|content| !content.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn page_summary(data: &Value, page: usize, edits: &EditPatch) -> Summary {
+    Summary { method: SummaryMethod::TextRank, text: textrank(&page_text(data, page, edits), PAGE_SENTENCES) }
+}
+
+pub fn document_summary(data: &Value, edits: &EditPatch, page_count: usize) -> Summary {
+    let text: Vec<String> = (0..page_count).map(|page| page_text(data, page, edits)).collect();
+    Summary { method: SummaryMethod::TextRank, text: textrank(&text.join("\n"), DOCUMENT_SENTENCES) }
+}
@@ -0,0 +1,309 @@
+//! Tables that run across page breaks
+//!
+//! Extractors see each page on its own, so a long table comes out as one
+//! table item per page. A table at the foot of a page is taken to continue in
+//! the first table of the next page when both have the same number of columns
+//! and either the next page repeats the header row, a "continued" label sits
+//! between them, or nothing but page furniture does. Detected continuations
+//! are stitched into one logical table for the CSV and XLSX table exports
+//! unless the user rejects them; decisions are kept in the edits, keyed by the
+//! continuing table's item ID.
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::bundle::{self, BundleFile};
+use crate::document;
+use crate::export::{csv_field, html_escape};
+use crate::normalize::{self, NumberLocale};
+use crate::patch::EditPatch;
+use crate::types::{DocumentItem, ItemType};
+
+/// Items of at most this many words don't separate a table from its
+/// continuation; they are page numbers, running heads and the like
+const FURNITURE_WORDS: usize = 3;
+
+/// A table item split into rows of cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct TablePiece {
+    pub id: String,
+    /// Zero-based page
+    pub page: usize,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TablePiece {
+    pub fn columns(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+/// Why a table is taken to continue on the next page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContinuationSign {
+    /// The continuation starts with the same header row
+    RepeatedHeader,
+    /// "Continued" or "cont'd" appears between the two, or atop the continuation
+    ContinuedLabel,
+    /// Nothing but page furniture lies between the two
+    Adjacent,
+}
+
+impl ContinuationSign {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContinuationSign::RepeatedHeader => "repeated header",
+            ContinuationSign::ContinuedLabel => "\"continued\" label",
+            ContinuationSign::Adjacent => "nothing in between",
+        }
+    }
+}
+
+/// A table that appears to continue on the next page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Continuation {
+    /// The table at the foot of `page - 1`
+    pub from: String,
+    /// The table atop `page`; decisions are keyed by it
+    pub to: String,
+    /// Zero-based page of the continuation
+    pub page: usize,
+    pub columns: usize,
+    pub signs: Vec<ContinuationSign>,
+}
+
+/// Tab-separated table text as rows of trimmed cells
+pub fn parse_rows(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split('\t').map(|cell| cell.trim().to_string()).collect())
+        .collect()
+}
+
+/// Whether text says a table carries on from an earlier page
+pub fn is_continued_label(text: &str) -> bool {
+    let text = text.to_lowercase();
+    ["continued", "cont'd", "contd.", "(cont.)", "cont."].iter().any(|label| text.contains(label))
+}
+
+/// A row that only labels the table as continued, e.g. "Table 2 (continued)"
+fn is_label_row(row: &[String]) -> bool {
+    let filled: Vec<&String> = row.iter().filter(|cell| !cell.is_empty()).collect();
+    filled.len() == 1 && is_continued_label(filled[0])
+}
+
+fn same_row(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+fn is_furniture(item: &DocumentItem) -> bool {
+    item.content.split_whitespace().count() <= FURNITURE_WORDS
+}
+
+fn bottom(item: &DocumentItem) -> f64 {
+    item.bbox.top + item.bbox.height
+}
+
+fn page_count(data: &Value) -> usize {
+    data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0)
+}
+
+/// A page's table items, top to bottom, with edits applied
+fn page_tables(items: &[DocumentItem], page: usize) -> Vec<(TablePiece, &DocumentItem)> {
+    let mut tables: Vec<&DocumentItem> = items.iter().filter(|item| item.item_type == ItemType::Table).collect();
+    tables.sort_by(|a, b| a.bbox.top.total_cmp(&b.bbox.top));
+    tables.into_iter()
+        .map(|item| (TablePiece { id: item.id.clone(), page, rows: parse_rows(&item.content) }, item))
+        .collect()
+}
+
+/// Every table continuation in the document, in page order
+pub fn detect_continuations(data: &Value, edits: &EditPatch) -> Vec<Continuation> {
+    let pages: Vec<Vec<DocumentItem>> = (0..page_count(data)).map(|page| document::edited_page_items(data, page, edits)).collect();
+    let mut continuations = Vec::new();
+    for page in 1..pages.len() {
+        let (previous, current) = (&pages[page - 1], &pages[page]);
+        let Some((last, last_item)) = page_tables(previous, page - 1).pop() else { continue };
+        let Some((first, first_item)) = page_tables(current, page).into_iter().next() else { continue };
+        let columns = last.columns();
+        if columns < 2 || first.columns() != columns {
+            continue;
+        }
+
+        // What lies below the table on its page and above the continuation on the next
+        let between: Vec<&DocumentItem> = previous.iter().filter(|item| item.bbox.top >= bottom(last_item))
+            .chain(current.iter().filter(|item| bottom(item) <= first_item.bbox.top))
+            .collect();
+        let mut signs = Vec::new();
+        let header = |piece: &TablePiece| piece.rows.iter().find(|row| !is_label_row(row)).cloned();
+        if header(&last).zip(header(&first)).is_some_and(|(a, b)| same_row(&a, &b)) {
+            signs.push(ContinuationSign::RepeatedHeader);
+        }
+        if between.iter().any(|item| is_continued_label(&item.content)) || first.rows.first().is_some_and(|row| is_label_row(row)) {
+            signs.push(ContinuationSign::ContinuedLabel);
+        }
+        if between.iter().all(|item| is_furniture(item)) {
+            signs.push(ContinuationSign::Adjacent);
+        }
+        if !signs.is_empty() {
+            continuations.push(Continuation { from: last.id, to: first.id, page, columns, signs });
+        }
+    }
+    continuations
+}
+
+/// Whether a detected continuation is stitched: yes unless the user rejected it
+pub fn is_stitched(edits: &EditPatch, continuation: &Continuation) -> bool {
+    edits.table_stitches.get(&continuation.to).copied().unwrap_or(true)
+}
+
+/// A table as exported, possibly stitched from several pages
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalTable {
+    /// Zero-based pages it spans, in order
+    pub pages: Vec<usize>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl LogicalTable {
+    /// "p. 3" or "pp. 3-5"
+    pub fn page_label(&self) -> String {
+        match (self.pages.first(), self.pages.last()) {
+            (Some(first), Some(last)) if first != last => format!("pp. {}-{}", first + 1, last + 1),
+            (Some(page), _) => format!("p. {}", page + 1),
+            _ => String::new(),
+        }
+    }
+}
+
+/// The document's tables in page order, with accepted continuations stitched
+/// on and their repeated header or "continued" rows dropped
+pub fn logical_tables(data: &Value, edits: &EditPatch) -> Vec<LogicalTable> {
+    let continuations = detect_continuations(data, edits);
+    let mut tables: Vec<LogicalTable> = Vec::new();
+    let mut last_id: Option<String> = None;
+    for page in 0..page_count(data) {
+        let items = document::edited_page_items(data, page, edits);
+        for (piece, _) in page_tables(&items, page) {
+            let stitch = continuations.iter()
+                .find(|c| c.to == piece.id && last_id.as_deref() == Some(c.from.as_str()) && is_stitched(edits, c));
+            match (stitch, tables.last_mut()) {
+                (Some(continuation), Some(table)) => {
+                    let header = table.rows.iter().find(|row| !is_label_row(row)).cloned();
+                    let repeated = continuation.signs.contains(&ContinuationSign::RepeatedHeader);
+                    let mut rows = piece.rows.into_iter().skip_while(|row| is_label_row(row)).peekable();
+                    if repeated && header.as_ref().zip(rows.peek()).is_some_and(|(h, r)| same_row(h, r)) {
+                        rows.next();
+                    }
+                    table.rows.extend(rows);
+                    table.pages.push(page);
+                }
+                _ => tables.push(LogicalTable { pages: vec![page], rows: piece.rows }),
+            }
+            last_id = Some(piece.id);
+        }
+    }
+    tables
+}
+
+/// One CSV row per table row, led by the table's number
+pub fn tables_csv(tables: &[LogicalTable]) -> String {
+    let mut out = String::new();
+    for (number, table) in tables.iter().enumerate() {
+        for row in &table.rows {
+            let cells = std::iter::once((number + 1).to_string()).chain(row.iter().cloned());
+            out.push_str(&cells.map(|cell| csv_field(&cell)).collect::<Vec<_>>().join(","));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+pub fn write_csv(path: &Path, tables: &[LogicalTable]) -> Result<()> {
+    std::fs::write(path, tables_csv(tables)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Sheet name for a table: unique, at most 31 characters, none of []:*?/\
+fn sheet_name(number: usize, table: &LogicalTable) -> String {
+    format!("Table {} ({})", number, table.page_label()).chars().take(31).collect()
+}
+
+/// A cell as a number when it is a plain one; amounts with currencies,
+/// percentages and codes with leading zeros stay text
+fn numeric_cell(cell: &str, locale: NumberLocale) -> Option<f64> {
+    let trimmed = cell.trim();
+    if trimmed.len() > 1 && trimmed.starts_with('0') && trimmed.as_bytes()[1].is_ascii_digit() {
+        return None;
+    }
+    normalize::parse_number(trimmed, locale)
+        .filter(|number| number.currency.is_none() && !number.percent)
+        .map(|number| number.value)
+}
+
+fn column_name(mut index: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.insert(0, (b'A' + (index % 26) as u8) as char);
+        if index < 26 {
+            return name;
+        }
+        index = index / 26 - 1;
+    }
+}
+
+fn worksheet_xml(table: &LogicalTable, locale: NumberLocale) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#);
+    for (r, row) in table.rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate().filter(|(_, cell)| !cell.is_empty()) {
+            let reference = format!("{}{}", column_name(c), r + 1);
+            match numeric_cell(cell, locale) {
+                Some(value) => xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, value)),
+                None => xml.push_str(&format!(r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#, reference, html_escape(cell))),
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// The files of a workbook with one sheet per table
+pub fn xlsx_files(tables: &[LogicalTable], locale: NumberLocale) -> Vec<BundleFile> {
+    let mut sheets = String::new();
+    let mut relationships = String::new();
+    let mut overrides = String::new();
+    let mut files = Vec::new();
+    // A workbook needs at least one sheet
+    let empty = [LogicalTable { pages: Vec::new(), rows: Vec::new() }];
+    let tables = if tables.is_empty() { &empty[..] } else { tables };
+    for (index, table) in tables.iter().enumerate() {
+        let number = index + 1;
+        let name = if table.pages.is_empty() { "Tables".to_string() } else { sheet_name(number, table) };
+        sheets.push_str(&format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, html_escape(&name), number, number));
+        relationships.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+            number, number,
+        ));
+        overrides.push_str(&format!(
+            r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+            number,
+        ));
+        files.push(BundleFile::new(format!("xl/worksheets/sheet{}.xml", number), worksheet_xml(table, locale).into_bytes()));
+    }
+    files.insert(0, BundleFile::new("[Content_Types].xml", format!(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>{}</Types>"#, overrides).into_bytes()));
+    files.insert(1, BundleFile::new("_rels/.rels", r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#.as_bytes().to_vec()));
+    files.insert(2, BundleFile::new("xl/workbook.xml", format!(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>{}</sheets></workbook>"#, sheets).into_bytes()));
+    files.insert(3, BundleFile::new("xl/_rels/workbook.xml.rels", format!(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}</Relationships>"#, relationships).into_bytes()));
+    files
+}
+
+pub fn write_xlsx(path: &Path, tables: &[LogicalTable], locale: NumberLocale) -> Result<()> {
+    bundle::write_zip(path, &xlsx_files(tables, locale))
+}
@@ -0,0 +1,169 @@
+//! Open documents as tabs
+//!
+//! The app keeps the active document's state (session, page, zoom, pan,
+//! rendered texture) in its own fields, where all of its code works on it.
+//! The other open documents wait here, parked with their state as they were
+//! left, so switching tabs swaps states instead of reloading or re-rendering.
+
+use std::path::{Path, PathBuf};
+
+/// One open document; `parked` holds its state while another tab is active
+#[derive(Debug)]
+pub struct Tab<T> {
+    pub path: PathBuf,
+    parked: Option<T>,
+}
+
+impl<T> Tab<T> {
+    /// File name shown on the tab
+    pub fn title(&self) -> String {
+        self.path.file_name().map_or_else(|| self.path.display().to_string(), |name| name.to_string_lossy().to_string())
+    }
+
+    pub fn parked(&self) -> Option<&T> {
+        self.parked.as_ref()
+    }
+
+    pub fn parked_mut(&mut self) -> Option<&mut T> {
+        self.parked.as_mut()
+    }
+}
+
+/// What closing a tab leaves the app to do
+#[derive(Debug, PartialEq)]
+pub enum Closed<T> {
+    /// A background tab closed; the active one stays
+    Background,
+    /// The active tab closed; this neighbour's state is now active
+    Activate(T),
+    /// The last tab closed
+    Empty,
+}
+
+/// The open documents, in tab order
+#[derive(Debug)]
+pub struct TabStrip<T> {
+    tabs: Vec<Tab<T>>,
+    active: usize,
+}
+
+impl<T> Default for TabStrip<T> {
+    fn default() -> Self {
+        Self { tabs: Vec::new(), active: 0 }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+impl<T> TabStrip<T> {
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    pub fn tabs(&self) -> &[Tab<T>] {
+        &self.tabs
+    }
+
+    /// Index of the active tab, if any document is open
+    pub fn active(&self) -> Option<usize> {
+        (!self.tabs.is_empty()).then_some(self.active)
+    }
+
+    /// The tab showing this file
+    pub fn find(&self, path: &Path) -> Option<usize> {
+        self.tabs.iter().position(|tab| same_file(&tab.path, path))
+    }
+
+    /// Parked state of the background tab showing this file
+    pub fn parked_for(&mut self, path: &Path) -> Option<&mut T> {
+        self.tabs.iter_mut().find(|tab| same_file(&tab.path, path)).and_then(|tab| tab.parked.as_mut())
+    }
+
+    /// Parked states of the background tabs
+    pub fn parked_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.tabs.iter_mut().filter_map(|tab| tab.parked.as_mut())
+    }
+
+    /// Open a new tab after the active one and make it active, parking
+    /// `leaving`, the state of the tab that was active
+    pub fn open(&mut self, path: PathBuf, leaving: T) {
+        let tab = Tab { path, parked: None };
+        if self.tabs.is_empty() {
+            self.tabs.push(tab);
+            self.active = 0;
+            return;
+        }
+        self.tabs[self.active].parked = Some(leaving);
+        self.active += 1;
+        self.tabs.insert(self.active, tab);
+    }
+
+    /// Undo the last `open`, e.g. when its file failed to load: the new tab
+    /// goes away and the one it was opened from is active again
+    pub fn cancel_open(&mut self) -> Closed<T> {
+        if self.tabs.len() <= 1 {
+            return self.close(self.active);
+        }
+        self.tabs.remove(self.active);
+        self.active = self.active.saturating_sub(1);
+        match self.tabs[self.active].parked.take() {
+            Some(state) => Closed::Activate(state),
+            None => Closed::Empty,
+        }
+    }
+
+    /// Point the active tab at another file, e.g. after saving a reordered copy
+    pub fn retarget(&mut self, path: PathBuf) {
+        match self.tabs.get_mut(self.active) {
+            Some(tab) => tab.path = path,
+            None => self.tabs.push(Tab { path, parked: None }),
+        }
+    }
+
+    /// Make another tab active, parking `leaving`; returns the state to show,
+    /// or gives `leaving` back if the tab doesn't exist or is already active
+    pub fn switch(&mut self, index: usize, leaving: T) -> Result<T, T> {
+        if index == self.active || index >= self.tabs.len() {
+            return Err(leaving);
+        }
+        let Some(state) = self.tabs[index].parked.take() else { return Err(leaving) };
+        self.tabs[self.active].parked = Some(leaving);
+        self.active = index;
+        Ok(state)
+    }
+
+    /// The tab after the active one, wrapping around
+    pub fn next(&self) -> Option<usize> {
+        (self.tabs.len() > 1).then(|| (self.active + 1) % self.tabs.len())
+    }
+
+    /// Close a tab. Closing the active one activates its right neighbour, or
+    /// the left one at the end of the strip.
+    pub fn close(&mut self, index: usize) -> Closed<T> {
+        if index >= self.tabs.len() {
+            return Closed::Background;
+        }
+        self.tabs.remove(index);
+        if index != self.active {
+            if index < self.active {
+                self.active -= 1;
+            }
+            return Closed::Background;
+        }
+        if self.tabs.is_empty() {
+            self.active = 0;
+            return Closed::Empty;
+        }
+        self.active = index.min(self.tabs.len() - 1);
+        match self.tabs[self.active].parked.take() {
+            Some(state) => Closed::Activate(state),
+            None => Closed::Empty,
+        }
+    }
+}
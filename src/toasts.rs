@@ -0,0 +1,191 @@
+//! Toast notifications
+//!
+//! Short-lived messages stacked in the bottom-right corner. Each toast
+//! dismisses itself after a while (errors stay longer) or on click, and every
+//! toast is kept in a bounded history the status bar can show.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use egui::{Align2, Color32, RichText};
+
+/// How many past toasts the history keeps
+const HISTORY_LIMIT: usize = 50;
+
+/// How many toasts are on screen at once; older ones are dismissed early
+const MAX_VISIBLE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Success,
+    Info,
+    Error,
+}
+
+impl ToastKind {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ToastKind::Success => "✔",
+            ToastKind::Info => "ℹ",
+            ToastKind::Error => "⚠",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            ToastKind::Success => Color32::from_rgb(16, 185, 129),
+            ToastKind::Info => Color32::from_rgb(59, 130, 246),
+            ToastKind::Error => Color32::from_rgb(239, 68, 68),
+        }
+    }
+
+    /// How long a toast of this kind stays up
+    pub fn lifetime(&self) -> Duration {
+        match self {
+            ToastKind::Success | ToastKind::Info => Duration::from_secs(4),
+            ToastKind::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub kind: ToastKind,
+    pub message: String,
+    pub created: Instant,
+    /// Wall-clock time for the history list
+    pub time: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Toasts {
+    active: VecDeque<Toast>,
+    history: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, kind: ToastKind, message: impl Into<String>) {
+        let toast = Toast {
+            kind,
+            message: message.into(),
+            created: Instant::now(),
+            time: chrono::Local::now().format("%H:%M:%S").to_string(),
+        };
+        self.history.push_front(toast.clone());
+        self.history.truncate(HISTORY_LIMIT);
+        self.active.push_back(toast);
+        while self.active.len() > MAX_VISIBLE {
+            self.active.pop_front();
+        }
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Success, message);
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Info, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Error, message);
+    }
+
+    /// Drop toasts that have outlived their kind's lifetime
+    pub fn expire(&mut self, now: Instant) {
+        self.active.retain(|t| now.saturating_duration_since(t.created) < t.kind.lifetime());
+    }
+
+    /// Toasts currently on screen, oldest first
+    pub fn active(&self) -> impl Iterator<Item = &Toast> {
+        self.active.iter()
+    }
+
+    /// Every recent toast, newest first
+    pub fn history(&self) -> impl Iterator<Item = &Toast> {
+        self.history.iter()
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Draw the active toasts above the status bar; a click dismisses one
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        self.expire(now);
+        if self.active.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, egui::Vec2::new(-12.0, -36.0))
+            .order(egui::Order::Foreground)
+            .interactable(true)
+            .show(ctx, |ui| {
+                // Newest at the bottom, nearest the status bar
+                for (index, toast) in self.active.iter().enumerate() {
+                    let frame = egui::Frame::popup(ui.style())
+                        .stroke(egui::Stroke::new(1.0, toast.kind.color()));
+                    let response = frame.show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(toast.kind.icon()).color(toast.kind.color()).strong());
+                            ui.label(&toast.message);
+                        });
+                    }).response.interact(egui::Sense::click());
+                    if response.on_hover_text("Click to dismiss").clicked() {
+                        dismissed = Some(index);
+                    }
+                }
+            });
+        if let Some(index) = dismissed {
+            self.active.remove(index);
+        }
+
+        // Wake up for the next expiry
+        if let Some(next) = self.active.iter()
+            .map(|t| t.kind.lifetime().saturating_sub(now.saturating_duration_since(t.created)))
+            .min()
+        {
+            ctx.request_repaint_after(next);
+        }
+    }
+
+    /// A bell button for the status bar that opens the toast history
+    pub fn history_button(&mut self, ui: &mut egui::Ui) {
+        let popup_id = ui.make_persistent_id("toast_history");
+        let response = ui.button(format!("🔔 {}", self.history_len()))
+            .on_hover_text("Notification history");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        egui::popup::popup_above_or_below_widget(
+            ui,
+            popup_id,
+            &response,
+            egui::AboveOrBelow::Above,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(320.0);
+                if self.history.is_empty() {
+                    ui.label(RichText::new("No notifications yet").color(Color32::GRAY));
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for toast in &self.history {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label(RichText::new(&toast.time).small().color(Color32::GRAY));
+                            ui.label(RichText::new(toast.kind.icon()).color(toast.kind.color()));
+                            ui.label(&toast.message);
+                        });
+                    }
+                });
+                ui.separator();
+                if ui.button("Clear").clicked() {
+                    self.history.clear();
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Accuracy against a reference transcript
+//!
+//! A ground-truth text file is split into pages (at form feeds, as pdftotext
+//! writes them, or at the `<!-- Page N -->` markers of our Markdown export)
+//! and each page of the extraction is scored against it with word and
+//! character error rates. Items whose words don't occur in the reference page
+//! are reported as divergent so they can be highlighted.
+
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::patch::EditPatch;
+use crate::types::DocumentItem;
+
+/// Items with more than this share of words missing from the reference are divergent
+pub const DIVERGENT_SHARE: f32 = 0.3;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    /// Reference text of each page, in order
+    pub pages: Vec<String>,
+}
+
+impl Transcript {
+    pub fn parse(text: &str) -> Self {
+        let mut pages: Vec<String> = if text.contains('\u{c}') {
+            text.split('\u{c}').map(str::to_string).collect()
+        } else {
+            let mut pages: Vec<String> = Vec::new();
+            let mut current = String::new();
+            let mut marked = false;
+            for line in text.lines() {
+                let trimmed = line.trim();
+                if trimmed.starts_with("<!-- Page ") && trimmed.ends_with("-->") {
+                    if marked || !current.trim().is_empty() {
+                        pages.push(std::mem::take(&mut current));
+                    }
+                    marked = true;
+                    continue;
+                }
+                current.push_str(line);
+                current.push('\n');
+            }
+            pages.push(current);
+            pages
+        };
+        // pdftotext ends the last page with a form feed too
+        while pages.len() > 1 && pages.last().is_some_and(|p| p.trim().is_empty()) {
+            pages.pop();
+        }
+        Self { pages }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self::parse(&text))
+    }
+}
+
+/// Edit distance between two sequences, in insertions, deletions and substitutions
+pub fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Words lowercased with surrounding punctuation dropped, so layout and
+/// punctuation noise doesn't dominate the word error rate
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Error rate of `hypothesis` against `reference`; 0.0 for a perfect match.
+/// Can exceed 1.0 when the hypothesis has many extra units.
+fn error_rate<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> f32 {
+    if reference.is_empty() {
+        return if hypothesis.is_empty() { 0.0 } else { 1.0 };
+    }
+    edit_distance(reference, hypothesis) as f32 / reference.len() as f32
+}
+
+/// Word error rate
+pub fn wer(reference: &str, hypothesis: &str) -> f32 {
+    error_rate(&words(reference), &words(hypothesis))
+}
+
+/// Character error rate, with runs of whitespace counted as one space
+pub fn cer(reference: &str, hypothesis: &str) -> f32 {
+    let chars = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ").chars().collect::<Vec<char>>();
+    error_rate(&chars(reference), &chars(hypothesis))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageScore {
+    /// Zero-based page
+    pub page: usize,
+    pub wer: f32,
+    pub cer: f32,
+}
+
+/// A page's extracted text: its items' text in order
+fn page_text(items: &[DocumentItem]) -> String {
+    items.iter().map(|item| item.content.as_str()).collect::<Vec<_>>().join("\n")
+}
+
+/// Score every page that has a reference page, with edits applied
+pub fn score_pages(data: &Value, edits: &EditPatch, transcript: &Transcript) -> Vec<PageScore> {
+    let page_count = data.get("pages").and_then(|v| v.as_array()).map(|p| p.len()).unwrap_or(0);
+    (0..page_count.min(transcript.pages.len()))
+        .map(|page| {
+            let text = page_text(&crate::document::edited_page_items(data, page, edits));
+            let reference = &transcript.pages[page];
+            PageScore { page, wer: wer(reference, &text), cer: cer(reference, &text) }
+        })
+        .collect()
+}
+
+/// IDs of the items with more than `DIVERGENT_SHARE` of their words missing from the reference page
+pub fn divergent_items(items: &[DocumentItem], reference: &str) -> Vec<String> {
+    let mut available: HashMap<String, usize> = HashMap::new();
+    for word in words(reference) {
+        *available.entry(word).or_default() += 1;
+    }
+    items.iter()
+        .filter(|item| {
+            let item_words = words(&item.content);
+            if item_words.is_empty() {
+                return false;
+            }
+            let missing = item_words.iter()
+                .filter(|word| match available.get_mut(*word) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        false
+                    }
+                    _ => true,
+                })
+                .count();
+            missing as f32 / item_words.len() as f32 > DIVERGENT_SHARE
+        })
+        .map(|item| item.id.clone())
+        .collect()
+}
@@ -0,0 +1,94 @@
+//! Translation overlay
+//!
+//! Machine-translated text shown in place of the items, in their boxes, so a
+//! document can be read in another language with its layout intact.
+//! Translations come from the LLM endpoint in Settings, which can be a hosted
+//! API or an offline model served locally (Ollama, llama.cpp). They are kept
+//! apart from the edits: flipping back shows the original, and only an export
+//! of the translated document writes them out.
+
+use std::collections::HashMap;
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::document;
+use crate::llm::{self, LlmSettings};
+use crate::patch::EditPatch;
+use crate::types::{DocumentItem, DocumentState, ItemType};
+
+/// Items per request, so long pages don't outgrow the model's context
+pub const BATCH_SIZE: usize = 40;
+
+/// Translated text by item ID, in one language
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Translation {
+    pub language: String,
+    pub texts: HashMap<String, String>,
+}
+
+impl Translation {
+    pub fn new(language: &str) -> Self {
+        Self { language: language.trim().to_string(), texts: HashMap::new() }
+    }
+
+    /// Whether every translatable item on the page has been translated
+    pub fn covers(&self, items: &[DocumentItem]) -> bool {
+        items.iter().filter(|item| translatable(item)).all(|item| self.texts.contains_key(&item.id))
+    }
+
+    /// Show the translations in place of the items' text
+    pub fn apply(&self, state: &mut DocumentState) {
+        for item in &state.items {
+            if let Some(text) = self.texts.get(&item.id) {
+                state.item_text_overrides.insert(item.id.clone(), text.clone());
+            }
+        }
+    }
+
+    /// The edits with the translations as text overrides, for exporting the
+    /// translated document
+    pub fn patch(&self, edits: &EditPatch) -> EditPatch {
+        let mut patch = edits.clone();
+        patch.text_overrides.extend(self.texts.iter().map(|(id, text)| (id.clone(), text.clone())));
+        patch
+    }
+}
+
+/// Whether an item's text is prose worth translating; tables, code, formulas
+/// and decoded payloads keep their original text
+pub fn translatable(item: &DocumentItem) -> bool {
+    !item.content.trim().is_empty()
+        && !matches!(
+            item.item_type,
+            ItemType::Table | ItemType::Formula | ItemType::Code | ItemType::Barcode | ItemType::Picture | ItemType::Checkbox
+        )
+}
+
+/// Instructions for translating numbered blocks into `language`
+pub fn prompt(language: &str) -> String {
+    format!(
+        "Translate each of the following text blocks into {}. Keep numbers, names and codes as they are. Reply with every block, each starting with its [n] marker on a new line, and nothing else.",
+        language.trim(),
+    )
+}
+
+/// Translations by item ID from a reply to `prompt` on these items
+pub fn parse_reply(reply: &str, items: &[DocumentItem]) -> Vec<(String, String)> {
+    llm::numbered_blocks(reply, items.len()).into_iter()
+        .map(|(index, text)| (items[index].id.clone(), text))
+        .collect()
+}
+
+/// Translate the page's translatable items, `BATCH_SIZE` at a time
+pub fn translate_page(settings: &LlmSettings, language: &str, data: &Value, page: usize, edits: &EditPatch) -> Result<Vec<(String, String)>> {
+    let items: Vec<DocumentItem> = document::edited_page_items(data, page, edits).into_iter()
+        .filter(translatable)
+        .collect();
+    let prompt = prompt(language);
+    let mut translated = Vec::new();
+    for batch in items.chunks(BATCH_SIZE) {
+        let reply = llm::complete_prompt(settings, &prompt, batch)?;
+        translated.extend(parse_reply(&reply, batch));
+    }
+    Ok(translated)
+}
@@ -0,0 +1,235 @@
+//! System tray icon for quick-drop mode
+//!
+//! In quick-drop mode Chonker3 sits in the system tray. The icon's tooltip
+//! shows what the queue is doing, and its menu brings back the drop window,
+//! adds files to the queue or quits; closing the window only tucks it away.
+//! Linux desktops get a StatusNotifierItem over D-Bus, macOS and Windows a
+//! native icon. Where there is no tray (a Linux desktop without a
+//! StatusNotifier host, say) `Tray::start` fails and the window stays up.
+
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use anyhow::Result;
+
+/// What the tray's menu asks the app to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayCommand {
+    ShowWindow,
+    AddFiles,
+    Quit,
+}
+
+impl TrayCommand {
+    pub const MENU: [TrayCommand; 3] = [TrayCommand::ShowWindow, TrayCommand::AddFiles, TrayCommand::Quit];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrayCommand::ShowWindow => "Show drop window",
+            TrayCommand::AddFiles => "Add files...",
+            TrayCommand::Quit => "Quit",
+        }
+    }
+
+    /// Stable ID of the menu item
+    pub fn id(&self) -> &'static str {
+        match self {
+            TrayCommand::ShowWindow => "show",
+            TrayCommand::AddFiles => "add_files",
+            TrayCommand::Quit => "quit",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::MENU.into_iter().find(|command| command.id() == id)
+    }
+}
+
+/// Tooltip text for the queue's state
+pub fn status_text(current: Option<&str>, pending: usize) -> String {
+    match current {
+        Some(name) if pending > 1 => format!("Extracting {} ({} more queued)", name, pending - 1),
+        Some(name) => format!("Extracting {}", name),
+        None => "Idle: drop files on the window or use Add files".to_string(),
+    }
+}
+
+/// Square RGBA icon
+#[derive(Debug, Clone)]
+pub struct TrayImage {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The tray icon; it goes away when this is dropped
+pub struct Tray {
+    commands: Receiver<TrayCommand>,
+    status: String,
+    icon: platform::Icon,
+}
+
+impl Tray {
+    /// Put the icon in the tray. `wake` is called when a menu item is picked,
+    /// from whichever thread the platform reports it on.
+    pub fn start(image: TrayImage, wake: impl Fn() + Send + Sync + 'static) -> Result<Self> {
+        let (sender, commands) = channel();
+        let status = status_text(None, 0);
+        let icon = platform::Icon::start(image, &status, sender, Arc::new(wake))?;
+        Ok(Self { commands, status, icon })
+    }
+
+    /// Menu items picked since the last call
+    pub fn commands(&self) -> Vec<TrayCommand> {
+        self.commands.try_iter().collect()
+    }
+
+    /// Show the queue's state in the tooltip
+    pub fn set_status(&mut self, status: String) {
+        if status != self.status {
+            self.icon.set_status(&status);
+            self.status = status;
+        }
+    }
+}
+
+type Wake = Arc<dyn Fn() + Send + Sync>;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::sync::mpsc::Sender;
+    use anyhow::{Context, Result};
+    use ksni::blocking::{Handle, TrayMethods};
+
+    use super::{TrayCommand, TrayImage, Wake};
+
+    struct Item {
+        status: String,
+        pixmap: ksni::Icon,
+        commands: Sender<TrayCommand>,
+        wake: Wake,
+    }
+
+    impl Item {
+        fn send(&self, command: TrayCommand) {
+            if self.commands.send(command).is_ok() {
+                (self.wake)();
+            }
+        }
+    }
+
+    impl ksni::Tray for Item {
+        fn id(&self) -> String {
+            "chonker3-quick-drop".to_string()
+        }
+
+        fn title(&self) -> String {
+            "Chonker3 quick drop".to_string()
+        }
+
+        fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+            vec![self.pixmap.clone()]
+        }
+
+        fn tool_tip(&self) -> ksni::ToolTip {
+            ksni::ToolTip { title: "Chonker3".to_string(), description: self.status.clone(), ..Default::default() }
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayCommand::ShowWindow);
+        }
+
+        fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+            TrayCommand::MENU.into_iter()
+                .map(|command| ksni::menu::StandardItem {
+                    label: command.label().to_string(),
+                    activate: Box::new(move |item: &mut Self| item.send(command)),
+                    ..Default::default()
+                }.into())
+                .collect()
+        }
+    }
+
+    pub struct Icon(Handle<Item>);
+
+    impl Icon {
+        pub fn start(image: TrayImage, status: &str, commands: Sender<TrayCommand>, wake: Wake) -> Result<Self> {
+            // StatusNotifierItem wants ARGB in network byte order
+            let data = image.rgba.chunks_exact(4).flat_map(|px| [px[3], px[0], px[1], px[2]]).collect();
+            let pixmap = ksni::Icon { width: image.width as i32, height: image.height as i32, data };
+            let item = Item { status: status.to_string(), pixmap, commands, wake };
+            Ok(Self(item.spawn().context("No system tray")?))
+        }
+
+        pub fn set_status(&self, status: &str) {
+            let status = status.to_string();
+            self.0.update(move |item| item.status = status);
+        }
+    }
+
+    impl Drop for Icon {
+        fn drop(&mut self) {
+            self.0.shutdown().wait();
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", windows))]
+mod platform {
+    use std::sync::mpsc::Sender;
+    use anyhow::{Context, Result};
+    use tray_icon::menu::{Menu, MenuEvent, MenuItem};
+    use tray_icon::{TrayIcon, TrayIconBuilder};
+
+    use super::{TrayCommand, TrayImage, Wake};
+
+    /// Must be made on the main thread, which the app's constructor runs on
+    pub struct Icon(TrayIcon);
+
+    impl Icon {
+        pub fn start(image: TrayImage, status: &str, commands: Sender<TrayCommand>, wake: Wake) -> Result<Self> {
+            let menu = Menu::new();
+            for command in TrayCommand::MENU {
+                menu.append(&MenuItem::with_id(command.id(), command.label(), true, None))?;
+            }
+            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                if let Some(command) = TrayCommand::from_id(event.id().as_ref()) {
+                    if commands.send(command).is_ok() {
+                        wake();
+                    }
+                }
+            }));
+            let icon = tray_icon::Icon::from_rgba(image.rgba, image.width, image.height)?;
+            let tray = TrayIconBuilder::new()
+                .with_menu(Box::new(menu))
+                .with_icon(icon)
+                .with_tooltip(status)
+                .build()
+                .context("No system tray")?;
+            Ok(Self(tray))
+        }
+
+        pub fn set_status(&self, status: &str) {
+            if let Err(e) = self.0.set_tooltip(Some(status)) {
+                log::warn!("Failed to update the tray tooltip: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use std::sync::mpsc::Sender;
+    use anyhow::{bail, Result};
+
+    use super::{TrayCommand, TrayImage, Wake};
+
+    pub struct Icon;
+
+    impl Icon {
+        pub fn start(_image: TrayImage, _status: &str, _commands: Sender<TrayCommand>, _wake: Wake) -> Result<Self> {
+            bail!("No system tray on this platform")
+        }
+
+        pub fn set_status(&self, _status: &str) {}
+    }
+}
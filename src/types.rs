@@ -12,9 +12,97 @@ pub struct DocumentItem {
     pub item_type: ItemType,
     pub bold: bool,
     pub italic: bool,
+    /// Recognition confidence (0-1) when the extractor or importer reports one
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// First line's baseline, from the page top, when the extraction records one
+    #[serde(default)]
+    pub baseline: Option<f64>,
+    /// LaTeX source of a formula, when the extractor recognized it
+    #[serde(default)]
+    pub latex: Option<String>,
+    /// Oversized first letter or ornament that was extracted as its own item
+    /// and merged into this one
+    #[serde(default)]
+    pub drop_cap: Option<DropCap>,
+    /// Steps that produced and changed the item, oldest first
+    #[serde(default)]
+    pub provenance: Vec<ProvenanceStep>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A drop cap or decorative glyph merged into the paragraph it starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DropCap {
+    pub glyph: String,
+    /// Where the glyph sits on the page
+    pub bbox: BoundingBox,
+    /// Ornaments aren't part of the text; letters begin the item's content
+    pub decorative: bool,
+}
+
+/// Part of the pipeline a provenance step belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvenanceStage {
+    /// Work on the PDF or page images before extraction
+    Preprocess,
+    /// The backend that found the item
+    Extractor,
+    /// Text recognized from the page image, including imported OCR results
+    Ocr,
+    /// Changes to the extraction after the backend ran
+    Postprocess,
+}
+
+impl ProvenanceStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProvenanceStage::Preprocess => "Preprocessing",
+            ProvenanceStage::Extractor => "Extractor",
+            ProvenanceStage::Ocr => "OCR",
+            ProvenanceStage::Postprocess => "Post-processing",
+        }
+    }
+}
+
+/// One step in an item's provenance chain, stored in the extraction JSON as
+/// `{"stage": "extractor", "name": "docling"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenanceStep {
+    pub stage: ProvenanceStage,
+    pub name: String,
+}
+
+impl ProvenanceStep {
+    pub fn new(stage: ProvenanceStage, name: &str) -> Self {
+        Self { stage, name: name.to_string() }
+    }
+}
+
+/// Share of the font size above and below the baseline, for fonts we have no metrics for
+const ASCENT_RATIO: f64 = 0.8;
+const DESCENT_RATIO: f64 = 0.2;
+
+impl DocumentItem {
+    /// First line's baseline from the page top: the recorded one, else estimated
+    /// from the font size and box
+    pub fn baseline(&self) -> f64 {
+        if let Some(baseline) = self.baseline {
+            return baseline;
+        }
+        let size = self.font_size as f64;
+        if size <= 0.0 {
+            self.bbox.top + self.bbox.height * ASCENT_RATIO
+        } else if self.bbox.height < size * 1.5 {
+            // A single line; its box ends at the descenders
+            self.bbox.top + self.bbox.height - size * DESCENT_RATIO
+        } else {
+            self.bbox.top + size * ASCENT_RATIO
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct BoundingBox {
     pub left: f64,
     pub top: f64,
@@ -23,7 +111,7 @@ pub struct BoundingBox {
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ItemType {
     Text,
     Title,
@@ -32,6 +120,170 @@ pub enum ItemType {
     FormLabel,
     FormField,
     Checkbox,
+    Picture,
+    /// An entry of a reference list
+    Reference,
+    /// A math formula; shown as the PDF region rather than its text
+    Formula,
+    /// Source code or other preformatted text; whitespace is significant
+    Code,
+    /// A barcode or QR code; the content is its decoded payload
+    Barcode,
+}
+
+impl ItemType {
+    pub const ALL: [ItemType; 12] = [
+        ItemType::Text,
+        ItemType::Title,
+        ItemType::Header,
+        ItemType::Table,
+        ItemType::FormLabel,
+        ItemType::FormField,
+        ItemType::Checkbox,
+        ItemType::Picture,
+        ItemType::Reference,
+        ItemType::Formula,
+        ItemType::Code,
+        ItemType::Barcode,
+    ];
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            ItemType::Text => "Text",
+            ItemType::Title => "Title",
+            ItemType::Header => "Header",
+            ItemType::Table => "Table",
+            ItemType::FormLabel => "Form label",
+            ItemType::FormField => "Form field",
+            ItemType::Checkbox => "Checkbox",
+            ItemType::Picture => "Picture",
+            ItemType::Reference => "Reference",
+            ItemType::Formula => "Formula",
+            ItemType::Code => "Code",
+            ItemType::Barcode => "Barcode",
+        }
+    }
+    
+    /// Map an extraction JSON `type` onto an item type
+    pub fn from_json_type(name: &str) -> Self {
+        match name {
+            "TitleItem" => ItemType::Title,
+            "SectionHeaderItem" => ItemType::Header,
+            "TableItem" => ItemType::Table,
+            "FormLabel" => ItemType::FormLabel,
+            "FormField" => ItemType::FormField,
+            "Checkbox" => ItemType::Checkbox,
+            "PictureItem" => ItemType::Picture,
+            "ReferenceItem" => ItemType::Reference,
+            "FormulaItem" => ItemType::Formula,
+            "CodeItem" => ItemType::Code,
+            "BarcodeItem" => ItemType::Barcode,
+            _ => ItemType::Text,
+        }
+    }
+    
+    /// The extraction JSON `type` for this item type
+    pub fn json_type(&self) -> &'static str {
+        match self {
+            ItemType::Text => "TextItem",
+            ItemType::Title => "TitleItem",
+            ItemType::Header => "SectionHeaderItem",
+            ItemType::Table => "TableItem",
+            ItemType::FormLabel => "FormLabel",
+            ItemType::FormField => "FormField",
+            ItemType::Checkbox => "Checkbox",
+            ItemType::Picture => "PictureItem",
+            ItemType::Reference => "ReferenceItem",
+            ItemType::Formula => "FormulaItem",
+            ItemType::Code => "CodeItem",
+            ItemType::Barcode => "BarcodeItem",
+        }
+    }
+}
+
+/// Items below this confidence count as low-confidence
+pub const LOW_CONFIDENCE: f32 = 0.8;
+
+/// Classes of items the canvas can hide while reviewing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemCategory {
+    Tables,
+    Headers,
+    FormFields,
+    Images,
+    LowConfidence,
+}
+
+impl ItemCategory {
+    pub const ALL: [ItemCategory; 5] = [
+        ItemCategory::Tables,
+        ItemCategory::Headers,
+        ItemCategory::FormFields,
+        ItemCategory::Images,
+        ItemCategory::LowConfidence,
+    ];
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            ItemCategory::Tables => "Tables",
+            ItemCategory::Headers => "Titles and headers",
+            ItemCategory::FormFields => "Form fields",
+            ItemCategory::Images => "Images",
+            ItemCategory::LowConfidence => "Low confidence",
+        }
+    }
+    
+    pub fn contains(&self, item: &DocumentItem) -> bool {
+        match self {
+            ItemCategory::Tables => item.item_type == ItemType::Table,
+            ItemCategory::Headers => matches!(item.item_type, ItemType::Title | ItemType::Header),
+            ItemCategory::FormFields => matches!(item.item_type, ItemType::FormLabel | ItemType::FormField | ItemType::Checkbox),
+            ItemCategory::Images => item.item_type == ItemType::Picture,
+            ItemCategory::LowConfidence => item.confidence.is_some_and(|c| c < LOW_CONFIDENCE),
+        }
+    }
+}
+
+/// Which item categories the canvas hides
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemFilter {
+    pub hidden: std::collections::HashSet<ItemCategory>,
+}
+
+impl ItemFilter {
+    pub fn is_hidden(&self, category: ItemCategory) -> bool {
+        self.hidden.contains(&category)
+    }
+    
+    pub fn set_hidden(&mut self, category: ItemCategory, hidden: bool) {
+        if hidden {
+            self.hidden.insert(category);
+        } else {
+            self.hidden.remove(&category);
+        }
+    }
+    
+    /// Whether an item shows: not in any hidden category
+    pub fn is_visible(&self, item: &DocumentItem) -> bool {
+        !self.hidden.iter().any(|category| category.contains(item))
+    }
+    
+    /// Drop hidden items from a page
+    pub fn apply(&self, items: &mut Vec<DocumentItem>) {
+        if !self.hidden.is_empty() {
+            items.retain(|item| self.is_visible(item));
+        }
+    }
+}
+
+/// Stable item ID from its zero-based page and top-left corner
+pub fn item_id(page_index: usize, left: f64, top: f64) -> String {
+    format!("item_{}_{}_{}", page_index, (left * 1000.0) as i32, (top * 1000.0) as i32)
+}
+
+/// Zero-based page index encoded in an item ID
+pub fn item_page(id: &str) -> Option<usize> {
+    id.strip_prefix("item_")?.split('_').next()?.parse().ok()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,12 +292,20 @@ pub struct DocumentState {
     pub page_size: (f32, f32),
     pub zoom: f32,
     pub offset: (f32, f32),
-    pub selected_item: Option<String>,
+    pub rotation: crate::viewport::Rotation,
+    pub selected_items: Vec<String>, // IDs of selected items
     pub editing_item: Option<String>,
     pub search_query: String,
     pub search_results: Vec<String>, // IDs of matching items
     pub item_offsets: std::collections::HashMap<String, (f32, f32)>,
     pub item_text_overrides: std::collections::HashMap<String, String>,
+    pub item_annotations: std::collections::HashMap<String, String>,
+    pub item_comments: std::collections::HashMap<String, crate::comments::CommentThread>,
+    pub named_regions: Vec<(String, BoundingBox)>, // Named regions on this page
+    pub remote_editing: std::collections::HashMap<String, String>, // Item ID -> collaborator name
+    pub divergent_items: Vec<String>, // IDs of items that differ from a reference transcript
+    #[serde(default)]
+    pub pii_items: Vec<String>, // IDs of items holding personal data
     pub text_padding_factor: f32, // Multiplier for text bounds padding
     pub edit_mode: bool,
     pub dragging_item: Option<String>, // ID of item being dragged
@@ -60,12 +320,19 @@ impl Default for DocumentState {
             page_size: (612.0, 792.0),
             zoom: 1.0,
             offset: (0.0, 0.0),
-            selected_item: None,
+            rotation: Default::default(),
+            selected_items: Vec::new(),
             editing_item: None,
             search_query: String::new(),
             search_results: Vec::new(),
             item_offsets: std::collections::HashMap::new(),
             item_text_overrides: std::collections::HashMap::new(),
+            item_annotations: std::collections::HashMap::new(),
+            item_comments: std::collections::HashMap::new(),
+            named_regions: Vec::new(),
+            remote_editing: std::collections::HashMap::new(),
+            divergent_items: Vec::new(),
+            pii_items: Vec::new(),
             text_padding_factor: 1.0, // Default padding factor
             edit_mode: false,
             dragging_item: None,
@@ -0,0 +1,190 @@
+//! Validation rules
+//!
+//! Checks on the key-value data read off a document through its named
+//! regions: a field must match a pattern, line items must add up to a total,
+//! a date must fall in a range. Rules are kept in the workspace so they apply
+//! to every document of a kind, and what fails is listed as problems to fix
+//! before the data goes on to invoice or claims processing.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::normalize::{self, NumberLocale};
+use crate::regions::RegionField;
+
+/// How far a sum may be off its total by default, to allow for rounding
+pub const DEFAULT_TOLERANCE: f64 = 0.005;
+
+fn default_tolerance() -> f64 {
+    DEFAULT_TOLERANCE
+}
+
+/// A check on named fields. Field names match regions ignoring case; in a
+/// sum's line items, a name ending in `*` matches every field it starts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Rule {
+    /// The whole field matches a regular expression
+    Pattern { field: String, pattern: String },
+    /// The line item fields add up to the total field
+    Sum {
+        items: Vec<String>,
+        total: String,
+        #[serde(default = "default_tolerance")]
+        tolerance: f64,
+    },
+    /// The field is a date on or between the bounds (YYYY-MM-DD, either optional)
+    DateRange {
+        field: String,
+        #[serde(default)]
+        earliest: Option<String>,
+        #[serde(default)]
+        latest: Option<String>,
+    },
+}
+
+impl Rule {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Rule::Pattern { .. } => "Pattern",
+            Rule::Sum { .. } => "Sum",
+            Rule::DateRange { .. } => "Date range",
+        }
+    }
+
+    /// One line on what the rule checks
+    pub fn describe(&self) -> String {
+        match self {
+            Rule::Pattern { field, pattern } => format!("{} matches /{}/", field, pattern),
+            Rule::Sum { items, total, .. } => format!("{} add up to {}", items.join(" + "), total),
+            Rule::DateRange { field, earliest, latest } => match (earliest, latest) {
+                (Some(earliest), Some(latest)) => format!("{} is between {} and {}", field, earliest, latest),
+                (Some(earliest), None) => format!("{} is on or after {}", field, earliest),
+                (None, Some(latest)) => format!("{} is on or before {}", field, latest),
+                (None, None) => format!("{} is a date", field),
+            },
+        }
+    }
+}
+
+/// A rule a document fails
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Index of the rule
+    pub rule: usize,
+    /// The field at fault, when there is one
+    pub field: Option<String>,
+    /// Zero-based page of that field
+    pub page: Option<usize>,
+    pub message: String,
+}
+
+fn find<'a>(fields: &'a [RegionField], name: &str) -> Option<&'a RegionField> {
+    fields.iter().find(|f| f.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Fields a line item name matches, in region order
+fn matching<'a>(fields: &'a [RegionField], name: &str) -> Vec<&'a RegionField> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Vec::new();
+    }
+    match name.strip_suffix('*') {
+        Some(prefix) => fields.iter().filter(|f| f.name.to_lowercase().starts_with(prefix)).collect(),
+        None => fields.iter().filter(|f| f.name.to_lowercase() == name).collect(),
+    }
+}
+
+/// A date in ISO, dotted, slashed or written-out form
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    const FORMATS: [&str; 6] = ["%Y-%m-%d", "%d.%m.%Y", "%B %d, %Y", "%b %d, %Y", "%d %B %Y", "%d %b %Y"];
+    if let Some(date) = FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(text, format).ok()) {
+        return Some(date);
+    }
+    // US order when only it makes sense, day first otherwise
+    let parts: Vec<u32> = text.split('/').map(|p| p.trim().parse().ok()).collect::<Option<Vec<_>>>()?;
+    let [a, b, year] = parts[..] else { return None };
+    let year = if year < 100 { 2000 + year } else { year };
+    let (month, day) = if a <= 12 && b > 12 { (a, b) } else { (b, a) };
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+/// Everything the fields break, in rule order
+pub fn evaluate(rules: &[Rule], fields: &[RegionField], locale: NumberLocale) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let mut fail = |field: Option<&RegionField>, name: &str, message: String| violations.push(Violation {
+            rule: index,
+            field: Some(field.map_or(name.trim().to_string(), |f| f.name.clone())),
+            page: field.map(|f| f.page),
+            message,
+        });
+        match rule {
+            Rule::Pattern { field, pattern } => {
+                let Some(found) = find(fields, field) else {
+                    fail(None, field, format!("No field named \"{}\"", field.trim()));
+                    continue;
+                };
+                match Regex::new(&format!("^(?:{})$", pattern)) {
+                    Ok(regex) if regex.is_match(found.text.trim()) => {}
+                    Ok(_) => fail(Some(found), field, format!("\"{}\" doesn't match /{}/", found.text.trim(), pattern)),
+                    Err(e) => fail(Some(found), field, format!("Invalid pattern: {}", e)),
+                }
+            }
+            Rule::Sum { items, total, tolerance } => {
+                let Some(total_field) = find(fields, total) else {
+                    fail(None, total, format!("No field named \"{}\"", total.trim()));
+                    continue;
+                };
+                let Some(expected) = normalize::parse_number(&total_field.text, locale) else {
+                    fail(Some(total_field), total, format!("\"{}\" is not a number", total_field.text.trim()));
+                    continue;
+                };
+                let line_items: Vec<&RegionField> = items.iter().flat_map(|name| matching(fields, name)).collect();
+                if line_items.is_empty() {
+                    fail(None, &items.join(", "), "No line item fields".to_string());
+                    continue;
+                }
+                let mut sum = 0.0;
+                let mut parsed = true;
+                for item in &line_items {
+                    match normalize::parse_number(&item.text, locale) {
+                        Some(number) => sum += number.value,
+                        None => {
+                            fail(Some(item), &item.name, format!("\"{}\" is not a number", item.text.trim()));
+                            parsed = false;
+                        }
+                    }
+                }
+                if parsed && (sum - expected.value).abs() > *tolerance {
+                    fail(Some(total_field), total, format!(
+                        "{} line items add up to {:.2}, not {:.2}",
+                        line_items.len(), sum, expected.value,
+                    ));
+                }
+            }
+            Rule::DateRange { field, earliest, latest } => {
+                let Some(found) = find(fields, field) else {
+                    fail(None, field, format!("No field named \"{}\"", field.trim()));
+                    continue;
+                };
+                let Some(date) = parse_date(&found.text) else {
+                    fail(Some(found), field, format!("\"{}\" is not a date", found.text.trim()));
+                    continue;
+                };
+                for (bound, from) in [(earliest, true), (latest, false)] {
+                    let Some(text) = bound.as_deref().filter(|t| !t.trim().is_empty()) else { continue };
+                    match parse_date(text) {
+                        None => fail(Some(found), field, format!("Invalid date bound \"{}\"", text.trim())),
+                        Some(limit) if from && date < limit => fail(Some(found), field, format!("{} is before {}", date, limit)),
+                        Some(limit) if !from && date > limit => fail(Some(found), field, format!("{} is after {}", date, limit)),
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
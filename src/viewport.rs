@@ -0,0 +1,305 @@
+//! Zoom, pan and rotation of the page view
+//!
+//! `ViewportController` holds the view state both panels share, and every
+//! change to it (toolbar buttons, keyboard shortcuts, wheel and drag) goes
+//! through `apply` as a `ViewCommand`. `PageTransform` maps between PDF points
+//! (TOPLEFT) and the screen for a page drawn at some origin, so the PDF panel's
+//! overlays and the extraction canvas place boxes with the same math.
+
+use egui::{Color32, Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::types::BoundingBox;
+
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+/// Factor one zoom step in or out changes the zoom by
+pub const ZOOM_STEP: f32 = 1.2;
+/// Zoom a new window starts at, fitting a letter page in half the default window
+pub const DEFAULT_ZOOM: f32 = 0.86;
+/// Screen points one arrow key press pans by; Shift pans a screenful's worth more
+pub const PAN_STEP: f32 = 40.0;
+
+/// Quarter turns, clockwise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise,
+    UpsideDown,
+    CounterClockwise,
+}
+
+impl Rotation {
+    pub fn degrees(&self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise => 90,
+            Rotation::UpsideDown => 180,
+            Rotation::CounterClockwise => 270,
+        }
+    }
+
+    /// Angle in radians, clockwise on screen
+    pub fn radians(&self) -> f32 {
+        (self.degrees() as f32).to_radians()
+    }
+
+    pub fn clockwise(&self) -> Self {
+        match self {
+            Rotation::None => Rotation::Clockwise,
+            Rotation::Clockwise => Rotation::UpsideDown,
+            Rotation::UpsideDown => Rotation::CounterClockwise,
+            Rotation::CounterClockwise => Rotation::None,
+        }
+    }
+
+    pub fn counter_clockwise(&self) -> Self {
+        self.clockwise().clockwise().clockwise()
+    }
+
+    /// Whether width and height trade places on screen
+    pub fn is_sideways(&self) -> bool {
+        matches!(self, Rotation::Clockwise | Rotation::CounterClockwise)
+    }
+}
+
+/// A change to the view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewCommand {
+    ZoomIn,
+    ZoomOut,
+    /// Multiply the zoom, e.g. from Cmd+scroll
+    ZoomBy(f32),
+    /// Zoom so the page's width fills the available width
+    FitWidth,
+    /// Zoom so the whole page fits the available space
+    FitPage,
+    /// Back to 100%, unrotated and unpanned
+    Reset,
+    /// Move the page by a screen distance
+    Pan(Vec2),
+    /// Pan so a page point sits near the view's top-left corner; the page's
+    /// own corner goes back to where it starts
+    Reveal((f32, f32)),
+    RotateClockwise,
+    RotateCounterClockwise,
+}
+
+/// What a command changed, so the app knows what to redo
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ViewChange {
+    /// Zoom or rotation changed: the page needs rendering at the new size
+    pub resized: bool,
+    pub panned: bool,
+}
+
+/// View state shared by the PDF panel and the extraction canvas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportController {
+    pub zoom: f32,
+    /// Offset of the extraction canvas's page, in screen points
+    pub pan: Vec2,
+    pub rotation: Rotation,
+}
+
+impl Default for ViewportController {
+    fn default() -> Self {
+        Self { zoom: DEFAULT_ZOOM, pan: Vec2::ZERO, rotation: Rotation::None }
+    }
+}
+
+impl ViewportController {
+    /// Apply a command for a page of `page_size` points shown in `available`
+    /// screen space (used by the fit commands)
+    pub fn apply(&mut self, command: ViewCommand, page_size: (f32, f32), available: Vec2) -> ViewChange {
+        let before = *self;
+        match command {
+            ViewCommand::ZoomIn => self.set_zoom(self.zoom * ZOOM_STEP),
+            ViewCommand::ZoomOut => self.set_zoom(self.zoom / ZOOM_STEP),
+            ViewCommand::ZoomBy(factor) => self.set_zoom(self.zoom * factor),
+            ViewCommand::FitWidth | ViewCommand::FitPage => {
+                let (width, height) = self.rotated_size(page_size);
+                let fit_width = available.x / width.max(1.0);
+                let zoom = match command {
+                    ViewCommand::FitPage => fit_width.min(available.y / height.max(1.0)),
+                    _ => fit_width,
+                };
+                self.set_zoom(zoom);
+                self.pan = Vec2::ZERO;
+            }
+            ViewCommand::Reset => *self = Self { zoom: 1.0, ..Default::default() },
+            ViewCommand::Pan(delta) => self.pan += delta,
+            ViewCommand::Reveal(point) => {
+                let offset = self.transform(Pos2::ZERO, page_size).to_screen(point).to_vec2();
+                self.pan = offset.min(Vec2::splat(PAN_STEP)) - offset;
+            }
+            ViewCommand::RotateClockwise => self.rotation = self.rotation.clockwise(),
+            ViewCommand::RotateCounterClockwise => self.rotation = self.rotation.counter_clockwise(),
+        }
+        ViewChange {
+            resized: self.zoom != before.zoom || self.rotation != before.rotation,
+            panned: self.pan != before.pan,
+        }
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Page size in points as it stands on screen
+    pub fn rotated_size(&self, page_size: (f32, f32)) -> (f32, f32) {
+        if self.rotation.is_sideways() { (page_size.1, page_size.0) } else { page_size }
+    }
+
+    /// The transform for a page drawn with its top-left corner at `origin`
+    pub fn transform(&self, origin: Pos2, page_size: (f32, f32)) -> PageTransform {
+        PageTransform { origin, scale: self.zoom, rotation: self.rotation, page_size }
+    }
+}
+
+/// The view commands pressed this frame. Zoom and rotation use Cmd
+/// (+ / - / 0 to reset / 1 fit width / 2 fit page / R rotate, Shift+R back);
+/// the arrow keys pan unless a text field has focus.
+pub fn keyboard_commands(ctx: &egui::Context) -> Vec<ViewCommand> {
+    let typing = ctx.wants_keyboard_input();
+    ctx.input_mut(|i| {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+        let mut commands = Vec::new();
+        let shortcuts = [
+            (Modifiers::COMMAND, Key::Plus, ViewCommand::ZoomIn),
+            (Modifiers::COMMAND, Key::Equals, ViewCommand::ZoomIn),
+            (Modifiers::COMMAND, Key::Minus, ViewCommand::ZoomOut),
+            (Modifiers::COMMAND, Key::Num0, ViewCommand::Reset),
+            (Modifiers::COMMAND, Key::Num1, ViewCommand::FitWidth),
+            (Modifiers::COMMAND, Key::Num2, ViewCommand::FitPage),
+            (Modifiers::COMMAND | Modifiers::SHIFT, Key::R, ViewCommand::RotateCounterClockwise),
+            (Modifiers::COMMAND, Key::R, ViewCommand::RotateClockwise),
+        ];
+        for (modifiers, key, command) in shortcuts {
+            if i.consume_shortcut(&KeyboardShortcut::new(modifiers, key)) {
+                commands.push(command);
+            }
+        }
+        if !typing && !i.modifiers.command {
+            let step = if i.modifiers.shift { PAN_STEP * 5.0 } else { PAN_STEP };
+            for (key, direction) in [
+                (Key::ArrowLeft, Vec2::new(1.0, 0.0)),
+                (Key::ArrowRight, Vec2::new(-1.0, 0.0)),
+                (Key::ArrowUp, Vec2::new(0.0, 1.0)),
+                (Key::ArrowDown, Vec2::new(0.0, -1.0)),
+            ] {
+                if i.key_pressed(key) {
+                    commands.push(ViewCommand::Pan(direction * step));
+                }
+            }
+        }
+        commands
+    })
+}
+
+/// Maps a page drawn at `origin` between PDF points (TOPLEFT) and the screen.
+/// "Local" positions are screen offsets from the origin before rotation, i.e.
+/// page points times the scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageTransform {
+    pub origin: Pos2,
+    /// Screen points per PDF point
+    pub scale: f32,
+    pub rotation: Rotation,
+    /// Unrotated page size in PDF points
+    pub page_size: (f32, f32),
+}
+
+impl PageTransform {
+    /// Unrotated page size on screen
+    fn local_size(&self) -> Vec2 {
+        Vec2::new(self.page_size.0, self.page_size.1) * self.scale
+    }
+
+    /// The page's rectangle on screen
+    pub fn page_rect(&self) -> Rect {
+        let size = self.local_size();
+        let size = if self.rotation.is_sideways() { Vec2::new(size.y, size.x) } else { size };
+        Rect::from_min_size(self.origin, size)
+    }
+
+    /// Where a local (unrotated, scaled) offset lands on screen
+    pub fn local_to_screen(&self, local: Vec2) -> Pos2 {
+        let size = self.local_size();
+        let turned = match self.rotation {
+            Rotation::None => local,
+            Rotation::Clockwise => Vec2::new(size.y - local.y, local.x),
+            Rotation::UpsideDown => size - local,
+            Rotation::CounterClockwise => Vec2::new(local.y, size.x - local.x),
+        };
+        self.origin + turned
+    }
+
+    /// The local offset of a screen position
+    pub fn screen_to_local(&self, pos: Pos2) -> Vec2 {
+        let size = self.local_size();
+        let turned = pos - self.origin;
+        match self.rotation {
+            Rotation::None => turned,
+            Rotation::Clockwise => Vec2::new(turned.y, size.y - turned.x),
+            Rotation::UpsideDown => size - turned,
+            Rotation::CounterClockwise => Vec2::new(size.x - turned.y, turned.x),
+        }
+    }
+
+    /// Screen position of a point on the page
+    pub fn to_screen(&self, point: (f32, f32)) -> Pos2 {
+        self.local_to_screen(Vec2::new(point.0, point.1) * self.scale)
+    }
+
+    /// Page point under a screen position; may be off the page
+    pub fn to_page(&self, pos: Pos2) -> (f32, f32) {
+        let local = self.screen_to_local(pos) / self.scale;
+        (local.x, local.y)
+    }
+
+    /// Screen rectangle of a local rectangle
+    pub fn local_rect_to_screen(&self, local: Rect) -> Rect {
+        Rect::from_two_pos(self.local_to_screen(local.min.to_vec2()), self.local_to_screen(local.max.to_vec2()))
+    }
+
+    /// Screen rectangle of a box on the page, moved by a local offset
+    pub fn bbox_to_screen(&self, bbox: &BoundingBox, offset: Vec2) -> Rect {
+        let min = Vec2::new(bbox.left as f32, bbox.top as f32) * self.scale + offset;
+        let size = Vec2::new(bbox.width as f32, bbox.height as f32) * self.scale;
+        self.local_rect_to_screen(Rect::from_min_size(min.to_pos2(), size))
+    }
+
+    /// The page box under a screen rectangle
+    pub fn screen_rect_to_bbox(&self, screen: Rect) -> BoundingBox {
+        let (a, b) = (self.to_page(screen.min), self.to_page(screen.max));
+        BoundingBox {
+            left: a.0.min(b.0) as f64,
+            top: a.1.min(b.1) as f64,
+            width: (a.0 - b.0).abs() as f64,
+            height: (a.1 - b.1).abs() as f64,
+        }
+    }
+
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        (0.0..=self.page_size.0).contains(&point.0) && (0.0..=self.page_size.1).contains(&point.1)
+    }
+
+    /// Paint part of a texture (`uv`) over a local rectangle, turned with the page
+    pub fn paint_image(&self, painter: &egui::Painter, texture: egui::TextureId, local: Rect, uv: Rect, tint: Color32) {
+        let mut mesh = egui::Mesh::with_texture(texture);
+        mesh.add_rect_with_uv(local.translate(self.origin.to_vec2()), uv, tint);
+        // Turning about the origin leaves the page left of or above it; shift it back
+        let size = self.local_size();
+        let shift = match self.rotation {
+            Rotation::None => Vec2::ZERO,
+            Rotation::Clockwise => Vec2::new(size.y, 0.0),
+            Rotation::UpsideDown => size,
+            Rotation::CounterClockwise => Vec2::new(0.0, size.x),
+        };
+        mesh.rotate(egui::emath::Rot2::from_angle(self.rotation.radians()), self.origin);
+        mesh.translate(shift);
+        painter.add(mesh);
+    }
+}
@@ -0,0 +1,141 @@
+//! Batch export to a URL
+//!
+//! With a webhook set up, batch export also POSTs every document's structured
+//! output, with its named fields and manifest entry, as JSON to an HTTP
+//! endpoint, so downstream systems get it without files being moved by hand.
+//! Plain headers are kept with the export settings; the Authorization header
+//! is a secret and lives in the OS keyring, like the LLM API key. Each
+//! delivery is recorded in the manifest; one that fails doesn't stop the batch.
+
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::export;
+use crate::extractor::ExtractedDocument;
+use crate::normalize::NumberLocale;
+use crate::passwords::SERVICE;
+use crate::patch::EditPatch;
+use crate::pipeline::ManifestEntry;
+use crate::regions;
+use crate::workspace::WorkspaceDocument;
+
+/// Keyring account of the Authorization header
+const AUTHORIZATION_ACCOUNT: &str = "webhook-authorization";
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct WebhookHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Where batch export posts each document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub url: String,
+    /// Sent with every request, e.g. `X-Source: chonker3`
+    #[serde(default)]
+    pub headers: Vec<WebhookHeader>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Self { enabled: false, url: String::new(), headers: Vec::new(), timeout_secs: default_timeout_secs() }
+    }
+}
+
+impl Webhook {
+    /// Whether batch export should post anything
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.url.trim().is_empty()
+    }
+}
+
+/// How posting one document went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Delivery {
+    /// HTTP status of the reply, if there was one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Delivery {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The JSON posted for a document: its manifest entry, named fields and
+/// structured export
+pub fn payload(doc: &WorkspaceDocument, entry: &ManifestEntry) -> Result<Value> {
+    let Some(json_path) = &doc.extracted_json else { bail!("{} has not been extracted", doc.display_name()) };
+    let extracted = ExtractedDocument::load(json_path)?;
+    let mut edits = EditPatch::new(Some(doc.path.display().to_string()));
+    edits.bookmarks = doc.bookmarks.clone();
+    edits.page_notes = doc.page_notes.clone();
+    let fields = regions::fields(&extracted.data, &edits, &doc.regions);
+    Ok(json!({
+        "document": entry,
+        "metadata": doc.metadata,
+        "fields": regions::fields_json(Some(&entry.input), &fields),
+        "structured": export::structured_export(&extracted.data, &edits, NumberLocale::Auto),
+    }))
+}
+
+/// POST the payload; replies outside 2xx count as failures
+pub fn post(webhook: &Webhook, payload: &Value) -> Delivery {
+    let mut request = ureq::post(webhook.url.trim())
+        .set("User-Agent", "chonker3")
+        .set("Content-Type", "application/json")
+        .timeout(Duration::from_secs(webhook.timeout_secs.max(1)));
+    for header in webhook.headers.iter().filter(|h| !h.name.trim().is_empty()) {
+        request = request.set(header.name.trim(), header.value.trim());
+    }
+    if let Some(authorization) = authorization() {
+        request = request.set("Authorization", &authorization);
+    }
+    match request.send_string(&payload.to_string()) {
+        Ok(response) => Delivery { status: Some(response.status()), error: None },
+        Err(ureq::Error::Status(code, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            let error = format!("{} replied {}: {}", webhook.url.trim(), code, text.trim());
+            Delivery { status: Some(code), error: Some(error) }
+        }
+        Err(e) => Delivery { status: None, error: Some(format!("Request to {} failed: {}", webhook.url.trim(), e)) },
+    }
+}
+
+fn authorization_entry() -> Result<Entry> {
+    Entry::new(SERVICE, AUTHORIZATION_ACCOUNT).map_err(|e| anyhow!("Can't use the keyring: {}", e))
+}
+
+/// The Authorization header value in the keyring, if one was saved
+pub fn authorization() -> Option<String> {
+    authorization_entry().ok()?.get_password().ok().filter(|value| !value.is_empty())
+}
+
+/// Save the Authorization header value (e.g. `Bearer ...`) in the keyring, or
+/// remove it when empty
+pub fn set_authorization(value: &str) -> Result<()> {
+    let entry = authorization_entry()?;
+    if value.trim().is_empty() {
+        return match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!("Couldn't remove the Authorization header from the keyring: {}", e)),
+        };
+    }
+    entry.set_password(value.trim()).map_err(|e| anyhow!("Couldn't save the Authorization header in the keyring: {}", e))
+}
@@ -0,0 +1,278 @@
+//! Workspace of documents with user tags and metadata
+//!
+//! The workspace is a JSON file listing the PDFs the user has worked on, along
+//! with their tags, free-form metadata fields, page bookmarks and notes, comment
+//! threads, recent searches and latest extraction output, plus a history of
+//! extraction runs that can be re-run with the options they used and the
+//! searches saved to run on any of the documents.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::comments::CommentThread;
+use crate::regions::NamedRegion;
+use crate::extractor::{ExtractOptions, ExtractedDocument, ExtractorKind, PythonBackend};
+use crate::patch::EditPatch;
+use crate::search::{self, Matcher, SavedSearch, SearchHit};
+
+pub const DEFAULT_WORKSPACE_FILE: &str = "chonker3_workspace.json";
+
+/// Extraction runs kept in the history; older ones are dropped
+pub const HISTORY_LIMIT: usize = 200;
+
+/// A document's index and its search hits, best first
+pub type DocumentHits = (usize, Vec<SearchHit>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceDocument {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Latest extraction JSON for this document
+    #[serde(default)]
+    pub extracted_json: Option<PathBuf>,
+    /// Simhash of the extracted text, see `dedup`
+    #[serde(default)]
+    pub fingerprint: Option<u64>,
+    /// Bookmarked pages, by zero-based index
+    #[serde(default)]
+    pub bookmarks: BTreeSet<usize>,
+    /// Notes on whole pages, by zero-based index
+    #[serde(default)]
+    pub page_notes: BTreeMap<usize, String>,
+    /// Pages extracted with another backend than the settings' chain, by zero-based index
+    #[serde(default)]
+    pub page_backends: BTreeMap<usize, PythonBackend>,
+    /// Comment threads on items, by item ID
+    #[serde(default)]
+    pub comments: BTreeMap<String, CommentThread>,
+    /// Named regions drawn on its pages
+    #[serde(default)]
+    pub regions: Vec<NamedRegion>,
+    /// Queries searched for in it, most recent first
+    #[serde(default)]
+    pub recent_searches: Vec<String>,
+    pub added: String,
+}
+
+impl WorkspaceDocument {
+    pub fn display_name(&self) -> String {
+        self.path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// True if the document carries every tag in `filter`
+    pub fn matches_tags(&self, filter: &BTreeSet<String>) -> bool {
+        filter.iter().all(|tag| self.tags.contains(tag))
+    }
+}
+
+/// One extraction of a document, successful or not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionRun {
+    pub document: PathBuf,
+    pub extractor: ExtractorKind,
+    pub options: ExtractOptions,
+    /// Per-page backends in effect, which `options` doesn't keep
+    #[serde(default)]
+    pub page_backends: BTreeMap<usize, PythonBackend>,
+    /// When it started, RFC 3339
+    pub started: String,
+    pub seconds: f64,
+    /// Backend that produced the result, as the extraction recorded it
+    #[serde(default)]
+    pub backend: Option<String>,
+    #[serde(default)]
+    pub items: usize,
+    /// Extraction JSON written
+    #[serde(default)]
+    pub json_path: Option<PathBuf>,
+    /// Why it failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ExtractionRun {
+    /// The options to extract with again, page backends included
+    pub fn rerun_options(&self) -> ExtractOptions {
+        ExtractOptions { page_backends: self.page_backends.clone(), ..self.options.clone() }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    pub documents: Vec<WorkspaceDocument>,
+    /// Recorded edit macros by name
+    #[serde(default)]
+    pub macros: BTreeMap<String, crate::macros::Macro>,
+    /// Extraction runs, oldest first
+    #[serde(default)]
+    pub extraction_history: Vec<ExtractionRun>,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    /// Checks on the named fields of every document
+    #[serde(default)]
+    pub validation_rules: Vec<crate::validation::Rule>,
+    #[serde(skip)]
+    pub file_path: Option<PathBuf>,
+}
+
+impl Workspace {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut workspace: Workspace = serde_json::from_str(&contents).context("Invalid workspace file")?;
+        workspace.file_path = Some(path.to_path_buf());
+        Ok(workspace)
+    }
+
+    /// Load the default workspace from the session directory, or start an empty one there
+    pub fn load_default() -> Self {
+        let path = crate::storage::session_dir().join(DEFAULT_WORKSPACE_FILE);
+        Self::load(&path).unwrap_or_else(|_| Workspace {
+            file_path: Some(path),
+            ..Default::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.file_path {
+            let json = serde_json::to_string_pretty(self)?;
+            std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Add a document if it isn't already in the workspace; returns its index
+    pub fn add_document(&mut self, path: &Path) -> usize {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if let Some(index) = self.documents.iter().position(|d| d.path == path) {
+            return index;
+        }
+        self.documents.push(WorkspaceDocument {
+            path,
+            tags: BTreeSet::new(),
+            metadata: BTreeMap::new(),
+            extracted_json: None,
+            fingerprint: None,
+            bookmarks: BTreeSet::new(),
+            page_notes: BTreeMap::new(),
+            page_backends: BTreeMap::new(),
+            comments: BTreeMap::new(),
+            regions: Vec::new(),
+            recent_searches: Vec::new(),
+            added: chrono::Local::now().to_rfc3339(),
+        });
+        self.documents.len() - 1
+    }
+
+    pub fn find(&self, path: &Path) -> Option<usize> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.documents.iter().position(|d| d.path == path)
+    }
+
+    /// Record a new extraction for a document, invalidating its fingerprint
+    pub fn set_extraction(&mut self, index: usize, json_path: PathBuf) {
+        if let Some(doc) = self.documents.get_mut(index) {
+            doc.extracted_json = Some(json_path);
+            doc.fingerprint = None;
+        }
+    }
+
+    /// Remember a document's page bookmarks and notes
+    pub fn set_page_marks(&mut self, index: usize, bookmarks: &BTreeSet<usize>, page_notes: &BTreeMap<usize, String>) {
+        if let Some(doc) = self.documents.get_mut(index) {
+            doc.bookmarks = bookmarks.clone();
+            doc.page_notes = page_notes.clone();
+        }
+    }
+
+    pub fn set_comments(&mut self, index: usize, comments: BTreeMap<String, CommentThread>) {
+        if let Some(doc) = self.documents.get_mut(index) {
+            doc.comments = comments;
+        }
+    }
+
+    pub fn set_regions(&mut self, index: usize, regions: Vec<NamedRegion>) {
+        if let Some(doc) = self.documents.get_mut(index) {
+            doc.regions = regions;
+        }
+    }
+
+    pub fn remember_search(&mut self, index: usize, query: &str) {
+        if let Some(doc) = self.documents.get_mut(index) {
+            search::remember_query(&mut doc.recent_searches, query);
+        }
+    }
+
+    /// Save a search, replacing any of the same name (ignoring case)
+    pub fn save_search(&mut self, saved: SavedSearch) {
+        match self.saved_searches.iter_mut().find(|s| s.name.eq_ignore_ascii_case(&saved.name)) {
+            Some(existing) => *existing = saved,
+            None => self.saved_searches.push(saved),
+        }
+    }
+
+    /// Run a search on the extraction of each listed document; returns the
+    /// documents with any hits
+    pub fn search(&self, indexes: &[usize], matcher: &Matcher) -> Vec<DocumentHits> {
+        indexes.iter().filter_map(|&index| {
+            let doc = self.documents.get(index)?;
+            let extracted = ExtractedDocument::load(doc.extracted_json.as_ref()?).ok()?;
+            let edits = EditPatch::new(Some(doc.path.display().to_string()));
+            let hits = search::document_hits(&extracted.data, &edits, extracted.page_count(), matcher);
+            (!hits.is_empty()).then_some((index, hits))
+        }).collect()
+    }
+
+    /// Extract a page of a document with `backend`, or with the usual chain for None
+    pub fn set_page_backend(&mut self, index: usize, page: usize, backend: Option<PythonBackend>) {
+        if let Some(doc) = self.documents.get_mut(index) {
+            match backend {
+                Some(backend) => doc.page_backends.insert(page, backend),
+                None => doc.page_backends.remove(&page),
+            };
+        }
+    }
+
+    /// Fingerprint every extracted document that doesn't have one yet.
+    /// Returns (document index, fingerprint) for all fingerprinted documents.
+    pub fn fingerprints(&mut self) -> Vec<(usize, u64)> {
+        for doc in self.documents.iter_mut().filter(|d| d.fingerprint.is_none()) {
+            let Some(json_path) = &doc.extracted_json else { continue };
+            let Ok(contents) = std::fs::read_to_string(json_path) else { continue };
+            let Ok(data) = serde_json::from_str::<serde_json::Value>(&contents) else { continue };
+            let text = crate::dedup::document_text(&data);
+            if !text.trim().is_empty() {
+                doc.fingerprint = Some(crate::dedup::simhash(&text));
+            }
+        }
+        self.documents.iter()
+            .enumerate()
+            .filter_map(|(i, d)| d.fingerprint.map(|f| (i, f)))
+            .collect()
+    }
+
+    /// Add a run to the history, dropping the oldest past `HISTORY_LIMIT`
+    pub fn record_run(&mut self, run: ExtractionRun) {
+        self.extraction_history.push(run);
+        let excess = self.extraction_history.len().saturating_sub(HISTORY_LIMIT);
+        self.extraction_history.drain(..excess);
+    }
+
+    /// Every tag used in the workspace
+    pub fn all_tags(&self) -> BTreeSet<String> {
+        self.documents.iter()
+            .flat_map(|d| d.tags.iter().cloned())
+            .collect()
+    }
+}
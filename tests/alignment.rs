@@ -0,0 +1,52 @@
+//! Page alignment scoring
+
+mod common;
+
+use chonker3::alignment::{self, Gray};
+use chonker3::core::bind_pdfium;
+use chonker3::document;
+use chonker3::patch::EditPatch;
+
+/// White image with a dark block
+fn block(x: usize, y: usize) -> Gray {
+    let (width, height) = (64, 64);
+    let mut pixels = vec![1.0; width * height];
+    for row in y..y + 8 {
+        for col in x..x + 24 {
+            pixels[row * width + col] = 0.0;
+        }
+    }
+    Gray { width, height, pixels }
+}
+
+#[test]
+fn ssim_rewards_matching_ink() {
+    let original = block(16, 16);
+    assert!((alignment::ssim(&original, &original) - 1.0).abs() < 1e-4);
+
+    // Same block a line lower scores worse than in place, and missing ink worse still
+    let shifted = alignment::ssim(&original.blurred(), &block(16, 28).blurred());
+    let blank = Gray { pixels: vec![1.0; 64 * 64], ..original.clone() };
+    let missing = alignment::ssim(&original, &blank);
+    assert!(shifted < 0.9, "shifted scored {}", shifted);
+    assert!(missing < 0.1, "missing scored {}", missing);
+
+    // Nothing on either page is a perfect match
+    assert_eq!(alignment::ssim(&blank, &blank), 1.0);
+}
+
+#[test]
+fn scores_fixture_pages() {
+    let Ok(pdfium) = bind_pdfium() else {
+        eprintln!("pdfium not available, skipping");
+        return;
+    };
+
+    let document = pdfium.load_pdf_from_file(&common::fixture_path("simple.pdf"), None).unwrap();
+    let page = document.pages().get(0).unwrap();
+    let data = common::fixture_json("simple.json");
+    let items = document::edited_page_items(&data, 0, &EditPatch::default());
+    let extracted = alignment::score_page(&pdfium, &page, &items).unwrap();
+    let empty = alignment::score_page(&pdfium, &page, &[]).unwrap();
+    assert!(extracted > empty, "extracted {} vs empty {}", extracted, empty);
+}
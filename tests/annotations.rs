@@ -0,0 +1,47 @@
+//! COCO and YOLO layout annotations
+
+mod common;
+
+use chonker3::annotations::{class_index, coco_json, collect, image_size, yolo_classes, yolo_labels, IMAGE_SCALE};
+use chonker3::patch::EditPatch;
+use chonker3::types::ItemType;
+use common::fixture_json;
+
+#[test]
+fn boxes_follow_the_page_images() {
+    let data = fixture_json("simple.json");
+    let mut edits = EditPatch::default();
+    let pages = collect(&data, &edits, &[(612.0, 792.0)], "simple");
+    assert_eq!(pages[0].image, "simple_page_001.png");
+    let title = pages[0].boxes.iter().find(|b| b.item_type == ItemType::Title).unwrap().clone();
+
+    let coco = coco_json(&pages, IMAGE_SCALE);
+    assert_eq!(coco["images"][0]["width"], 1224);
+    assert_eq!(coco["images"][0]["height"], 1584);
+    assert_eq!(coco["annotations"].as_array().unwrap().len(), pages[0].boxes.len());
+    let first = &coco["annotations"][0];
+    assert_eq!(first["bbox"][0].as_f64().unwrap(), title.bbox.left * 2.0);
+    assert_eq!(first["category_id"], class_index(ItemType::Title) + 1);
+    assert_eq!(coco["categories"].as_array().unwrap().len(), ItemType::ALL.len());
+
+    // Deleted items are left out
+    let id = chonker3::document::page_items(&data, 0, &edits)[0].id.clone();
+    edits.deletions.insert(id);
+    let fewer = collect(&data, &edits, &[(612.0, 792.0)], "simple");
+    assert_eq!(fewer[0].boxes.len(), pages[0].boxes.len() - 1);
+}
+
+#[test]
+fn yolo_boxes_are_centered_fractions() {
+    let data = fixture_json("simple.json");
+    let pages = collect(&data, &EditPatch::default(), &[(612.0, 792.0)], "simple");
+    let labels = yolo_labels(&pages[0]);
+    assert_eq!(labels.lines().count(), pages[0].boxes.len());
+    for line in labels.lines() {
+        let fields: Vec<f64> = line.split(' ').map(|f| f.parse().unwrap()).collect();
+        assert_eq!(fields.len(), 5);
+        assert!(fields[1..].iter().all(|v| (0.0..=1.0).contains(v)));
+    }
+    assert_eq!(yolo_classes().lines().nth(class_index(ItemType::Table)), Some("TableItem"));
+    assert_eq!(image_size((595.3, 841.9), 2.0), (1191, 1684));
+}
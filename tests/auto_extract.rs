@@ -0,0 +1,20 @@
+//! Extraction on open
+
+use chonker3::settings::{AutoExtract, Settings};
+
+#[test]
+fn extracts_on_open_only_when_enabled_and_small_enough() {
+    let mb = 1024 * 1024;
+    assert!(!AutoExtract::default().applies_to(mb));
+
+    let mut auto = AutoExtract { enabled: true, ..Default::default() };
+    assert!(auto.applies_to(500 * mb));
+    auto.limit_size = true;
+    auto.max_size_mb = 5;
+    assert!(auto.applies_to(5 * mb));
+    assert!(!auto.applies_to(5 * mb + 1));
+
+    // Settings saved before the option existed keep extraction manual
+    let old: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(old.auto_extract, AutoExtract::default());
+}
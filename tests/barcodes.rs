@@ -0,0 +1,231 @@
+//! Barcode and QR code decoding
+
+use chonker3::barcodes::{self, PageBarcode, Symbology};
+use chonker3::patch::EditPatch;
+use chonker3::types::BoundingBox;
+use image::{GrayImage, Luma};
+use qrcodegen::{Mask, QrCode, QrCodeEcc, QrSegment, Version};
+use serde_json::json;
+
+fn page() -> GrayImage {
+    GrayImage::from_pixel(600, 400, Luma([255]))
+}
+
+/// Draw bar/space widths (bar first) as full-height bars starting at `left`
+fn draw_bars(page: &mut GrayImage, left: u32, top: u32, height: u32, widths: &[u8], module: u32) {
+    let mut x = left;
+    for (index, &width) in widths.iter().enumerate() {
+        let width = width as u32 * module;
+        if index % 2 == 0 {
+            for px in x..x + width {
+                for py in top..top + height {
+                    page.put_pixel(px, py, Luma([0]));
+                }
+            }
+        }
+        x += width;
+    }
+}
+
+/// Code 128 widths for text in code set B, with check and stop symbols
+fn code128_b(text: &str) -> Vec<u8> {
+    let values: Vec<usize> = text.bytes().map(|b| (b - b' ') as usize).collect();
+    let check = values.iter().enumerate().fold(104, |sum, (i, v)| sum + (i + 1) * v) % 103;
+    std::iter::once(104).chain(values).chain([check])
+        .flat_map(|v| barcodes::CODE128_PATTERNS[v])
+        .chain(barcodes::CODE128_STOP)
+        .collect()
+}
+
+/// Code 39 widths (narrow 1, wide 3) with the start/stop asterisks and narrow gaps
+fn code39(text: &str) -> Vec<u8> {
+    let mut widths = Vec::new();
+    for c in format!("*{}*", text).bytes() {
+        let index = barcodes::CODE39_ALPHABET.iter().position(|&a| a == c).unwrap();
+        let pattern = barcodes::CODE39_PATTERNS[index];
+        widths.extend((0..9).map(|i| if pattern & (1 << (8 - i)) != 0 { 3 } else { 1 }));
+        widths.push(1);
+    }
+    widths.pop();
+    widths
+}
+
+/// EAN-13 widths for 13 digits, guards included
+fn ean13(digits: &str) -> Vec<u8> {
+    let digits: Vec<usize> = digits.bytes().map(|b| (b - b'0') as usize).collect();
+    let parity = barcodes::EAN_FIRST_DIGIT[digits[0]];
+    let mut widths = vec![1, 1, 1];
+    for (i, &d) in digits[1..7].iter().enumerate() {
+        let mut pattern = barcodes::EAN_DIGITS[d];
+        if parity & (1 << (5 - i)) != 0 {
+            pattern.reverse();
+        }
+        widths.extend(pattern);
+    }
+    widths.extend([1, 1, 1, 1, 1]);
+    for &d in &digits[7..] {
+        widths.extend(barcodes::EAN_DIGITS[d]);
+    }
+    widths.extend([1, 1, 1]);
+    widths
+}
+
+fn qr_modules(code: &QrCode) -> Vec<Vec<bool>> {
+    (0..code.size()).map(|y| (0..code.size()).map(|x| code.get_module(x, y)).collect()).collect()
+}
+
+fn draw_qr(page: &mut GrayImage, code: &QrCode, left: u32, top: u32, module: u32) {
+    for (row, modules) in qr_modules(code).iter().enumerate() {
+        for (col, &dark) in modules.iter().enumerate() {
+            if dark {
+                for dy in 0..module {
+                    for dx in 0..module {
+                        page.put_pixel(left + col as u32 * module + dx, top + row as u32 * module + dy, Luma([0]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn symbol_tables_are_well_formed() {
+    for pattern in barcodes::CODE128_PATTERNS {
+        assert_eq!(pattern.iter().map(|&w| w as u32).sum::<u32>(), 11);
+    }
+    let mut unique = barcodes::CODE128_PATTERNS.to_vec();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), 106);
+    for pattern in barcodes::CODE39_PATTERNS {
+        assert_eq!(pattern.count_ones(), 3);
+    }
+    assert!(barcodes::ean13_checksum(&[4, 0, 0, 6, 3, 8, 1, 3, 3, 3, 9, 3, 1]));
+    assert!(!barcodes::ean13_checksum(&[4, 0, 0, 6, 3, 8, 1, 3, 3, 3, 9, 3, 2]));
+}
+
+#[test]
+fn decodes_linear_barcodes_in_any_direction() {
+    let mut image = page();
+    draw_bars(&mut image, 40, 20, 60, &code128_b("INV-2024/0042"), 2);
+    draw_bars(&mut image, 40, 120, 60, &code39("PO 7731"), 2);
+    draw_bars(&mut image, 40, 220, 60, &ean13("4006381333931"), 3);
+    let mut found = barcodes::decode_image(&image);
+    found.sort_by(|a, b| a.bounds[1].total_cmp(&b.bounds[1]));
+    let read: Vec<(Symbology, &str)> = found.iter().map(|b| (b.symbology, b.payload.as_str())).collect();
+    assert_eq!(read, vec![
+        (Symbology::Code128, "INV-2024/0042"),
+        (Symbology::Code39, "PO 7731"),
+        (Symbology::Ean13, "4006381333931"),
+    ]);
+    // The box spans the bars and the scanned rows
+    let [left, top, _, bottom] = found[0].bounds;
+    assert_eq!(left, 40.0);
+    assert!(top <= 23.0 && bottom >= 77.0, "{:?}", found[0].bounds);
+
+    // Upside down and turned on its side
+    let flipped = image::imageops::rotate180(&image);
+    assert_eq!(barcodes::decode_image(&flipped).len(), 3);
+    let turned = image::imageops::rotate90(&image);
+    assert_eq!(barcodes::decode_image(&turned).len(), 3);
+}
+
+#[test]
+fn rejects_bars_that_fail_the_check() {
+    let mut widths = code128_b("SHIP-1");
+    // Swap one data symbol for another: the check symbol no longer matches
+    let symbol = barcodes::CODE128_PATTERNS[(b'X' - b' ') as usize];
+    widths[6..12].copy_from_slice(&symbol);
+    let mut image = page();
+    draw_bars(&mut image, 40, 20, 60, &widths, 2);
+    assert!(barcodes::decode_image(&image).is_empty());
+    assert!(barcodes::decode_image(&page()).is_empty());
+}
+
+#[test]
+fn reads_qr_codes_of_every_level_and_mask() {
+    let levels = [QrCodeEcc::Low, QrCodeEcc::Medium, QrCodeEcc::Quartile, QrCodeEcc::High];
+    for (version, level) in (1..=10).zip(levels.iter().cycle()) {
+        for mask in 0..8 {
+            let text = format!("INV-{}/{}", version, mask);
+            let code = QrCode::encode_segments_advanced(
+                &QrSegment::make_segments(&text), *level,
+                Version::new(version), Version::new(version), Some(Mask::new(mask)), false,
+            ).unwrap();
+            assert_eq!(barcodes::decode_qr_modules(&qr_modules(&code)).as_deref(), Some(text.as_str()), "version {} mask {}", version, mask);
+        }
+    }
+
+    // Numeric and alphanumeric segments
+    for text in ["0123456789012345", "SHIPMENT 42-A/7"] {
+        let code = QrCode::encode_text(text, QrCodeEcc::Medium).unwrap();
+        assert_eq!(barcodes::decode_qr_modules(&qr_modules(&code)).as_deref(), Some(text));
+    }
+    // UTF-8 in byte mode
+    let code = QrCode::encode_text("Größe: 42 €", QrCodeEcc::Low).unwrap();
+    assert_eq!(barcodes::decode_qr_modules(&qr_modules(&code)).as_deref(), Some("Größe: 42 €"));
+}
+
+#[test]
+fn skips_damaged_qr_codes() {
+    let code = QrCode::encode_text("PAYMENT REF 991", QrCodeEcc::Low).unwrap();
+    let mut modules = qr_modules(&code);
+    // Flip a data module in the bottom-right corner
+    let last = modules.len() - 1;
+    modules[last][last] = !modules[last][last];
+    assert_eq!(barcodes::decode_qr_modules(&modules), None);
+}
+
+#[test]
+fn finds_qr_codes_on_a_page() {
+    let mut image = page();
+    let code = QrCode::encode_text("https://example.com/track/1Z999AA10123456784", QrCodeEcc::Medium).unwrap();
+    draw_qr(&mut image, &code, 300, 60, 4);
+    draw_bars(&mut image, 40, 60, 60, &code128_b("1Z999"), 2);
+    let found = barcodes::decode_image(&image);
+    let qr = found.iter().find(|b| b.symbology == Symbology::Qr).expect("QR code");
+    assert_eq!(qr.payload, "https://example.com/track/1Z999AA10123456784");
+    let size = (code.size() * 4) as f32;
+    assert!((qr.bounds[0] - 300.0).abs() <= 2.0 && (qr.bounds[2] - (300.0 + size)).abs() <= 2.0, "{:?}", qr.bounds);
+    assert!(found.iter().any(|b| b.symbology == Symbology::Code128 && b.payload == "1Z999"));
+
+    let turned = image::imageops::rotate90(&image);
+    assert!(barcodes::decode_image(&turned).iter().any(|b| b.symbology == Symbology::Qr && b.payload == qr.payload));
+}
+
+#[test]
+fn barcodes_become_extraction_items() {
+    let mut data = json!({
+        "pages": [{"page_number": 1, "width": 612.0, "height": 792.0}],
+        "items": [
+            {"type": "TextItem", "page": 1, "content": "Invoice", "bbox": {"left": 72.0, "top": 72.0, "width": 100.0, "height": 14.0}},
+            {"type": "BarcodeItem", "page": 1, "content": "stale", "bbox": {"left": 10.0, "top": 10.0, "width": 50.0, "height": 50.0}},
+        ],
+    });
+    let found = vec![PageBarcode {
+        page: 0,
+        symbology: Symbology::Qr,
+        payload: "https://example.com/pay/42".to_string(),
+        bbox: BoundingBox { left: 450.0, top: 60.0, width: 80.0, height: 80.0 },
+    }];
+    assert_eq!(barcodes::add_to_extraction(&mut data, &found), 1);
+    let items = data["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1]["symbology"], "qr");
+
+    let edits = EditPatch::default();
+    let listed = barcodes::extracted_barcodes(&data, &edits);
+    assert_eq!(listed.len(), 1);
+    assert_eq!((listed[0].page, listed[0].symbology), (0, Some(Symbology::Qr)));
+    assert_eq!(listed[0].payload, "https://example.com/pay/42");
+
+    let markdown = chonker3::export::markdown_export(&data, &edits);
+    assert!(markdown.contains("**Barcode:** `https://example.com/pay/42`"), "{}", markdown);
+    let structured = chonker3::export::structured_export(&data, &edits, Default::default());
+    let record = structured["items"].as_array().unwrap().iter().find(|i| i["type"] == "BarcodeItem").unwrap();
+    assert_eq!(record["symbology"], "qr");
+
+    let mut deleted = EditPatch::default();
+    deleted.deletions.insert(listed[0].item_id.clone());
+    assert!(barcodes::extracted_barcodes(&data, &deleted).is_empty());
+}
@@ -0,0 +1,42 @@
+//! Bates number recognition
+
+use std::collections::BTreeMap;
+
+use chonker3::bates;
+use chonker3::patch::EditPatch;
+use serde_json::json;
+
+#[test]
+fn finds_bates_identifiers() {
+    assert_eq!(bates::find_bates("Confidential ABC0001234").as_deref(), Some("ABC0001234"));
+    assert_eq!(bates::find_bates("DEF-000567 page 3").as_deref(), Some("DEF-000567"));
+    assert_eq!(bates::find_bates("SMITH 00012345").as_deref(), Some("SMITH 00012345"));
+    // Too few digits, part of a longer word, or too many digits
+    assert_eq!(bates::find_bates("Page 12 of AB123"), None);
+    assert_eq!(bates::find_bates("xABC000123"), None);
+    assert_eq!(bates::find_bates("ISBN9780262033848"), None);
+}
+
+#[test]
+fn maps_pages_to_their_stamp() {
+    let item = |page: u64, top: f64, content: &str| json!({
+        "page": page, "type": "TextItem", "content": content,
+        "bbox": { "left": 400.0, "top": top, "width": 100.0, "height": 10.0 },
+    });
+    let data = json!({
+        "pages": [
+            { "page": 1, "width": 612.0, "height": 792.0 },
+            { "page": 2, "width": 612.0, "height": 792.0 },
+            { "page": 3, "width": 612.0, "height": 792.0 },
+        ],
+        "items": [
+            // Body text mentioning another stamp loses to the one in the margin
+            item(1, 300.0, "See also XYZ000999 for details"),
+            item(1, 760.0, "ABC000001"),
+            item(3, 20.0, "ABC000003"),
+        ],
+    });
+    let numbers = bates::bates_numbers(&data, &EditPatch::default());
+    assert_eq!(numbers, BTreeMap::from([(0, "ABC000001".to_string()), (2, "ABC000003".to_string())]));
+    assert_eq!(bates::bates_csv(&numbers, 3), "page,bates\n1,ABC000001\n2,\n3,ABC000003\n");
+}
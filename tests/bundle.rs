@@ -0,0 +1,64 @@
+//! Document bundle export: HTML, audit report and the zip itself
+
+use chonker3::bundle::{self, BundleFile};
+use chonker3::export;
+use chonker3::patch::EditPatch;
+use chonker3::types::{self, ItemType};
+use serde_json::json;
+
+fn sample() -> serde_json::Value {
+    let item = |top: f64, kind: &str, content: &str| json!({
+        "page": 1, "type": kind, "content": content,
+        "bbox": { "left": 72.0, "top": top, "width": 200.0, "height": 12.0 },
+    });
+    json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            item(50.0, "TitleItem", "Results & <Discussion>"),
+            item(100.0, "TextItem", "Teh first paragraph"),
+            item(150.0, "TextItem", "Dropped line"),
+        ],
+    })
+}
+
+#[test]
+fn html_export_escapes_text() {
+    let html = export::html_export(&sample(), &EditPatch::default());
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h1>Results &amp; &lt;Discussion&gt;</h1>"));
+    assert!(html.contains("<section class=\"page\" id=\"page-1\">"));
+}
+
+#[test]
+fn audit_report_lists_edits_against_the_original() {
+    let mut edits = EditPatch::new(Some("paper.pdf".to_string()));
+    edits.text_overrides.insert(types::item_id(0, 72.0, 100.0), "The first paragraph".to_string());
+    edits.deletions.insert(types::item_id(0, 72.0, 150.0));
+    edits.type_changes.insert(types::item_id(0, 72.0, 50.0), ItemType::Header);
+
+    let report = export::audit_report(&sample(), &edits);
+    assert!(report.contains("## Page 1"));
+    assert!(report.contains("text: \"Teh first paragraph\" → \"The first paragraph\""));
+    assert!(report.contains("deleted: \"Dropped line\""));
+    assert!(report.contains("type: Title → Header"));
+}
+
+#[test]
+fn writes_a_zip_with_every_file() {
+    let mut files = bundle::document_files("paper.pdf", b"%PDF-1.4", &sample(), &EditPatch::default()).unwrap();
+    files.extend(bundle::page_image_files(vec![vec![0x89, b'P', b'N', b'G']]));
+    let path = std::env::temp_dir().join(format!("chonker3_bundle_{}.zip", std::process::id()));
+    bundle::write_zip(&path, &files).unwrap();
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    names.sort();
+    assert_eq!(names, [
+        "audit.md", "edits.json", "extraction.json", "pages/page_001.png",
+        "paper.html", "paper.md", "paper.pdf",
+    ]);
+    let mut pdf = Vec::new();
+    std::io::Read::read_to_end(&mut archive.by_name("paper.pdf").unwrap(), &mut pdf).unwrap();
+    assert_eq!(BundleFile::new("paper.pdf", pdf), files[0]);
+    std::fs::remove_file(&path).unwrap();
+}
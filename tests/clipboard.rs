@@ -0,0 +1,76 @@
+//! Copy-as formats
+
+use chonker3::clipboard::{format_items, table_cell_at, table_part, CopyFormat, TablePart};
+use chonker3::settings::CopySettings;
+use chonker3::types::{BoundingBox, DocumentItem, ItemType};
+
+fn item(item_type: ItemType, content: &str, left: f64, top: f64, width: f64) -> DocumentItem {
+    DocumentItem {
+        id: format!("{}_{}", left, top),
+        bbox: BoundingBox { left, top, width, height: 12.0 },
+        content: content.to_string(),
+        font_size: 12.0,
+        color: (0, 0, 0),
+        item_type,
+        bold: false,
+        italic: false,
+        confidence: None,
+        baseline: None,
+        latex: None,
+        drop_cap: None,
+        provenance: Vec::new(),
+    }
+}
+
+#[test]
+fn layout_keeps_columns_and_gaps() {
+    let items = [
+        item(ItemType::Text, "Name", 72.0, 100.0, 30.0),
+        item(ItemType::Text, "Qty", 132.0, 101.0, 20.0),
+        item(ItemType::Text, "Total", 72.0, 124.0, 30.0),
+    ];
+    // 6pt columns: "Qty" starts 10 columns in; a line-height gap leaves a blank line
+    assert_eq!(format_items(&items, CopyFormat::Layout), "Name      Qty\n\nTotal\n");
+}
+
+#[test]
+fn markdown_only_decorates_headings_and_tables() {
+    let title = item(ItemType::Title, "Report", 72.0, 50.0, 100.0);
+    let table = item(ItemType::Table, "A\tB\n1\t2", 72.0, 80.0, 100.0);
+    let text = item(ItemType::Text, "Body", 72.0, 120.0, 100.0);
+    assert_eq!(
+        format_items(&[title, table, text], CopyFormat::Markdown),
+        "# Report\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n\nBody"
+    );
+}
+
+#[test]
+fn tsv_splits_rows_and_cells() {
+    let items = [
+        item(ItemType::Text, "Name", 72.0, 100.0, 30.0),
+        item(ItemType::Text, "Qty", 132.0, 100.0, 20.0),
+        item(ItemType::Table, "a \t 1\nb\t2", 72.0, 120.0, 100.0),
+    ];
+    assert_eq!(format_items(&items, CopyFormat::Tsv), "Name\tQty\na\t1\nb\t2");
+}
+
+#[test]
+fn modifiers_pick_configured_format() {
+    let settings = CopySettings::default();
+    assert_eq!(settings.format_for(egui::Modifiers::NONE), CopyFormat::Plain);
+    assert_eq!(settings.format_for(egui::Modifiers::SHIFT), CopyFormat::Layout);
+    assert_eq!(settings.format_for(egui::Modifiers::ALT), CopyFormat::Markdown);
+    assert_eq!(settings.format_for(egui::Modifiers::COMMAND), CopyFormat::Tsv);
+}
+
+#[test]
+fn copies_the_clicked_table_row_or_column() {
+    let table = "Item\tQty\tPrice\n\nApples\t3\t1.20\nPears\t 5 \t0.80";
+    // The "5" in the Pears row, past the blank line
+    let index = table.chars().count() - 6;
+    let cell = table_cell_at(table, index);
+    assert_eq!(cell, (2, 1));
+    assert_eq!(table_part(table, cell, TablePart::Row), "Pears\t5\t0.80");
+    assert_eq!(table_part(table, cell, TablePart::Column), "Qty\n3\n5");
+    assert_eq!(table_cell_at(table, 0), (0, 0));
+}
@@ -0,0 +1,113 @@
+//! LAN collaboration: handshake, merging and convergence over loopback
+
+use std::io::{BufReader, Cursor, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use chonker3::collab::{self, CollabSession};
+use chonker3::patch::EditPatch;
+
+fn host() -> CollabSession {
+    CollabSession::host(0, "host".into(), "doc".into()).unwrap()
+}
+
+fn join(host: &CollabSession, name: &str) -> CollabSession {
+    let address = format!("127.0.0.1:{}", host.port.unwrap());
+    CollabSession::join(&address, &host.token, name.into(), "doc".into()).unwrap()
+}
+
+/// Poll every session until `done` holds for all of them
+fn settle(sessions: &mut [&mut CollabSession], done: impl Fn(&CollabSession) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !sessions.iter().all(|session| done(session)) {
+        assert!(Instant::now() < deadline, "sessions never settled");
+        for session in sessions.iter_mut() {
+            session.poll();
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn text_edit(item_id: &str, text: &str) -> EditPatch {
+    let mut patch = EditPatch::new(None);
+    patch.text_overrides.insert(item_id.into(), text.into());
+    patch
+}
+
+#[test]
+fn relays_edits_between_joiners() {
+    let mut host = host();
+    let mut alice = join(&host, "alice");
+    let mut bob = join(&host, "bob");
+    settle(&mut [&mut host, &mut alice, &mut bob], |s| s.peers().count() >= if s.is_host { 2 } else { 1 });
+
+    alice.sync_local(&text_edit("item_1", "Invoice"));
+    settle(&mut [&mut host, &mut alice, &mut bob], |s| s.edits().text_overrides.get("item_1").map(String::as_str) == Some("Invoice"));
+
+    // Taking the edit back reaches everyone too
+    alice.sync_local(&EditPatch::new(None));
+    settle(&mut [&mut host, &mut alice, &mut bob], |s| s.edits().text_overrides.is_empty());
+}
+
+#[test]
+fn concurrent_edits_converge_on_one_value() {
+    let mut host = host();
+    let mut alice = join(&host, "alice");
+    settle(&mut [&mut host, &mut alice], |s| s.peers().count() == 1);
+
+    host.sync_local(&text_edit("item_1", "from host"));
+    let mut edits = text_edit("item_1", "from alice");
+    edits.text_overrides.insert("item_2".into(), "only alice".into());
+    alice.sync_local(&edits);
+    settle(&mut [&mut host, &mut alice], |s| s.edits().text_overrides.len() == 2);
+    // Both wrote item_1; every peer keeps the one with the higher stamp
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while host.edits().text_overrides != alice.edits().text_overrides && Instant::now() < deadline {
+        host.poll();
+        alice.poll();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(host.edits().text_overrides, alice.edits().text_overrides);
+}
+
+#[test]
+fn late_joiner_gets_the_current_state() {
+    let mut host = host();
+    host.sync_local(&text_edit("item_1", "Total"));
+    let mut late = join(&host, "late");
+    settle(&mut [&mut host, &mut late], |s| s.edits().text_overrides.contains_key("item_1"));
+}
+
+#[test]
+fn refuses_a_wrong_code_or_a_different_pdf() {
+    let host = host();
+    let address = format!("127.0.0.1:{}", host.port.unwrap());
+    let wrong_code = CollabSession::join(&address, "0000-0000-0000-0000", "eve".into(), "doc".into()).err().unwrap();
+    assert!(format!("{:#}", wrong_code).contains("Wrong session code"));
+    let other_pdf = CollabSession::join(&address, &host.token, "bob".into(), "other".into()).err().unwrap();
+    assert!(format!("{:#}", other_pdf).contains("different PDF"));
+    // Typed codes are forgiving of case and spacing
+    assert!(CollabSession::join(&address, &format!(" {} ", host.token.to_uppercase()), "bob".into(), "doc".into()).is_ok());
+}
+
+#[test]
+fn hangs_up_on_an_oversized_handshake() {
+    let host = host();
+    let mut stream = TcpStream::connect(("127.0.0.1", host.port.unwrap())).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let _ = stream.write_all(&vec![b'x'; collab::MAX_MESSAGE_BYTES + 16]);
+    // Only a refusal comes back, never the session code
+    let mut reply = String::new();
+    let _ = stream.read_to_string(&mut reply);
+    assert!(!reply.contains(&host.token));
+}
+
+#[test]
+fn reads_lines_up_to_the_cap() {
+    let mut reader = BufReader::new(Cursor::new(b"{\"a\":1}\r\nlast".to_vec()));
+    assert_eq!(collab::read_line(&mut reader).unwrap().as_deref(), Some("{\"a\":1}"));
+    assert_eq!(collab::read_line(&mut reader).unwrap().as_deref(), Some("last"));
+    assert_eq!(collab::read_line(&mut reader).unwrap(), None);
+
+    let mut reader = BufReader::new(Cursor::new(vec![b'x'; collab::MAX_MESSAGE_BYTES + 1]));
+    assert!(collab::read_line(&mut reader).is_err());
+}
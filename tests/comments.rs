@@ -0,0 +1,61 @@
+//! Comment threads on items
+
+use chonker3::comments::CommentThread;
+use chonker3::core::Edits;
+use chonker3::export;
+use chonker3::patch::EditPatch;
+use chonker3::types;
+use serde_json::json;
+
+#[test]
+fn replies_reopen_resolved_threads() {
+    let mut thread = CommentThread::default();
+    assert!(!thread.add("ana", "   "), "blank comments are ignored");
+    assert!(thread.add("ana", " Is this total right? "));
+    thread.resolved = true;
+    assert!(thread.add("ben", "No, it's 1,250"));
+    assert!(!thread.resolved);
+    assert_eq!(thread.comments.len(), 2);
+    assert_eq!(thread.last().unwrap().text, "No, it's 1,250");
+    assert_eq!(thread.comments[0].text, "Is this total right?");
+    assert!(thread.summary().starts_with("ana ("), "{}", thread.summary());
+}
+
+#[test]
+fn threads_travel_with_the_patch_and_follow_their_item() {
+    let mut thread = CommentThread::default();
+    thread.add("ana", "Check against the invoice");
+    let mut edits = Edits::default();
+    edits.comments.insert("old".to_string(), thread.clone());
+
+    let patch = edits.to_patch(None);
+    assert_eq!(patch.edit_count(), 0, "comments aren't edits");
+    let json = serde_json::to_string(&patch).unwrap();
+    let restored: EditPatch = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.comments.get("old"), Some(&thread));
+
+    edits.remap(|id| (id == "old").then(|| "new".to_string()));
+    assert!(edits.comments.contains_key("new") && !edits.comments.contains_key("old"));
+}
+
+#[test]
+fn audit_report_lists_comments() {
+    let data = json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [{
+            "page": 1, "type": "TextItem", "content": "Total 1,205",
+            "bbox": { "left": 72.0, "top": 100.0, "width": 200.0, "height": 12.0 },
+        }],
+    });
+    let mut thread = CommentThread::default();
+    thread.add("ana", "Digits swapped?");
+    thread.resolved = true;
+    let mut edits = EditPatch::new(None);
+    edits.comments.insert(types::item_id(0, 72.0, 100.0), thread);
+
+    let report = export::audit_report(&data, &edits);
+    assert!(report.contains("## Page 1"), "{}", report);
+    assert!(report.contains("comment by ana ("), "{}", report);
+    assert!(report.contains("\"Digits swapped?\""), "{}", report);
+    assert!(report.contains("comments resolved"), "{}", report);
+}
@@ -0,0 +1,72 @@
+//! Shared helpers for the fixture and golden-output tests
+//!
+//! Goldens live in `tests/golden/`. Run with `UPDATE_GOLDEN=1` to rewrite them
+//! after an intentional output change, then review the diff.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use serde_json::Value;
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+pub fn fixture_json(name: &str) -> Value {
+    let contents = std::fs::read_to_string(fixture_path(name))
+        .unwrap_or_else(|e| panic!("missing fixture {}: {}", name, e));
+    serde_json::from_str(&contents).unwrap_or_else(|e| panic!("invalid fixture {}: {}", name, e))
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(name)
+}
+
+fn update_golden() -> bool {
+    std::env::var_os("UPDATE_GOLDEN").is_some()
+}
+
+/// Compare text against a golden file (or rewrite it with UPDATE_GOLDEN)
+pub fn assert_golden_text(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if update_golden() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden {} ({}); run with UPDATE_GOLDEN=1", name, e));
+    assert_eq!(actual, expected, "output differs from golden {}", name);
+}
+
+/// Compare JSON against a golden file, ignoring formatting
+pub fn assert_golden_json(name: &str, actual: &Value) {
+    let path = golden_path(name);
+    if update_golden() {
+        std::fs::write(&path, serde_json::to_string_pretty(actual).unwrap() + "\n").unwrap();
+        return;
+    }
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden {} ({}); run with UPDATE_GOLDEN=1", name, e));
+    let expected: Value = serde_json::from_str(&contents).unwrap();
+    assert!(
+        json_close(actual, &expected),
+        "output differs from golden {}\nactual:\n{}",
+        name,
+        serde_json::to_string_pretty(actual).unwrap()
+    );
+}
+
+/// Structural equality with a tolerance for float text round-trips
+fn json_close(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() <= 1e-9 * x.abs().max(1.0),
+            _ => x == y,
+        },
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_close(x, y)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| json_close(v, w)))
+        }
+        _ => a == b,
+    }
+}
@@ -0,0 +1,47 @@
+//! Stacking pages for the continuous scroll view
+
+use chonker3::continuous::{PageStack, OVERSCAN, PAGE_GAP};
+use egui::Vec2;
+
+/// Two letter pages and a landscape one, at 100%
+fn stack() -> PageStack {
+    PageStack::new([Vec2::new(612.0, 792.0), Vec2::new(612.0, 792.0), Vec2::new(792.0, 612.0)])
+}
+
+#[test]
+fn stacks_pages_with_gaps() {
+    let stack = stack();
+    assert_eq!(stack.len(), 3);
+    assert_eq!(stack.top(0), 0.0);
+    assert_eq!(stack.top(1), 792.0 + PAGE_GAP);
+    assert_eq!(stack.top(2), 2.0 * (792.0 + PAGE_GAP));
+    assert_eq!(stack.size(), Vec2::new(792.0, 2.0 * (792.0 + PAGE_GAP) + 612.0));
+    // Narrower pages are centred under the widest
+    assert_eq!(stack.rect(0).left(), 90.0);
+    assert_eq!(stack.rect(2).left(), 0.0);
+    assert!(PageStack::new([]).is_empty());
+}
+
+#[test]
+fn finds_pages_by_scroll_position() {
+    let stack = stack();
+    assert_eq!(stack.page_at(0.0), 0);
+    assert_eq!(stack.page_at(792.0 + PAGE_GAP / 2.0), 0, "a gap belongs to the page above");
+    assert_eq!(stack.page_at(stack.top(1)), 1);
+    assert_eq!(stack.page_at(1e6), 2);
+
+    // The counter reads the page a third of the way down the view
+    assert_eq!(stack.current_page(500.0, 600.0), 0);
+    assert_eq!(stack.current_page(700.0, 600.0), 1);
+}
+
+#[test]
+fn renders_only_pages_near_the_view() {
+    let sizes = vec![Vec2::new(600.0, 800.0); 100];
+    let stack = PageStack::new(sizes);
+    let top = stack.top(50) + 10.0;
+    let visible = stack.visible(top, top + 900.0);
+    assert_eq!(visible, 50 - OVERSCAN..52 + OVERSCAN);
+    assert_eq!(stack.visible(0.0, 500.0), 0..1 + OVERSCAN);
+    assert_eq!(stack.visible(stack.top(99), stack.size().y + 100.0), 99 - OVERSCAN..100);
+}
@@ -0,0 +1,330 @@
+//! Headless session: extraction, edits, search and exports without egui
+
+mod common;
+
+use std::path::Path;
+use chonker3::core::Session;
+use chonker3::extractor::{
+    self, BackendFailure, Capabilities, ExtractOptions, ExtractedDocument, ExtractedPage, ExtractionUpdate, Extractor,
+    PartialExtraction, PythonBackend, StreamEnd,
+};
+use chonker3::patch::EditPatch;
+use chonker3::types::{self, ItemType};
+use common::fixture_json;
+
+fn session(fixture: &str) -> Session {
+    let mut session = Session { page_count: 2, ..Default::default() };
+    session.set_extraction(fixture_json(fixture), None);
+    session
+}
+
+#[test]
+fn edits_flow_into_document_state() {
+    let mut session = session("two_column.json");
+    let left = types::item_id(0, 72.0, 92.0);
+    let right = types::item_id(0, 330.0, 92.0);
+
+    session.set_text(&left, "Edited".to_string());
+    session.set_type(&right, Some(ItemType::Header));
+    session.set_annotation(&right, "check spelling");
+
+    let state = session.document_state().unwrap();
+    assert_eq!(state.item_text_overrides.get(&left).map(String::as_str), Some("Edited"));
+    assert_eq!(state.items.iter().find(|i| i.id == right).unwrap().item_type, ItemType::Header);
+    assert!(state.item_annotations.contains_key(&right));
+
+    // Blank annotations and None types clear the edit
+    session.set_annotation(&right, "  ");
+    session.set_type(&right, None);
+    assert!(session.edits.annotations.is_empty());
+    assert!(session.edits.type_overrides.is_empty());
+
+    session.delete_item(&left);
+    assert!(session.document_state().unwrap().items.iter().all(|i| i.id != left));
+}
+
+#[test]
+fn searches_every_page() {
+    let mut session = session("two_column.json");
+    let pages = |query: &str| session.search_all(query).into_iter().map(|(page, _)| page).collect::<Vec<_>>();
+    assert_eq!(pages("COLUMN"), vec![0, 0]);
+    assert_eq!(pages("review"), vec![1]);
+    assert!(pages("missing").is_empty());
+
+    assert!(session.go_to_page(1));
+    assert!(!session.go_to_page(2));
+    assert_eq!(session.document_state().unwrap().items.len(), 2);
+}
+
+#[test]
+fn patches_round_trip() {
+    let mut session = session("simple.json");
+    let id = types::item_id(0, 72.0, 56.0);
+    session.set_text(&id, "Annual Report".to_string());
+    let patch = session.to_patch();
+    assert_eq!(patch.edit_count(), 1);
+
+    let mut other = Session::default();
+    let message = other.apply_patch(patch);
+    assert_eq!(message, "Applied 1 edits from patch");
+    assert_eq!(other.edits.text_overrides.get(&id).map(String::as_str), Some("Annual Report"));
+
+    other.edits.replace(EditPatch::default());
+    assert!(other.edits.text_overrides.is_empty());
+}
+
+#[test]
+fn exports_from_session() {
+    let session = session("simple.json");
+    let path = std::env::temp_dir().join(format!("chonker3_core_test_{}.md", std::process::id()));
+    session.export_markdown(&path).unwrap();
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    // The stamp comes first, then the document
+    let (stamp, rest) = markdown.split_once(" -->\n\n").unwrap();
+    assert!(stamp.starts_with(chonker3::stamp::MARKDOWN_PREFIX));
+    assert!(rest.starts_with("<!-- Page 1 -->\n\n# Quarterly Report"));
+
+    assert!(Session::default().export_markdown(&path).is_err());
+}
+
+#[test]
+fn reports_extraction_backend() {
+    // Fixtures carry a docling_version, imports name their format
+    assert_eq!(session("simple.json").extraction_backend().as_deref(), Some("docling"));
+
+    let mut data = fixture_json("simple.json");
+    data["metadata"]["extractor"] = serde_json::json!("enhanced");
+    let mut session = Session::default();
+    session.set_extraction(data.clone(), None);
+    assert_eq!(session.extraction_backend().as_deref(), Some("enhanced"));
+
+    data["metadata"]["importer"] = serde_json::json!("hOCR");
+    session.set_extraction(data, None);
+    assert_eq!(session.extraction_backend().as_deref(), Some("hOCR import"));
+}
+
+#[test]
+fn reports_extraction_capabilities() {
+    assert_eq!(Session::default().extraction_capabilities(), Capabilities::default());
+    assert!(session("simple.json").extraction_capabilities().tables);
+
+    let mut data = fixture_json("simple.json");
+    data["metadata"]["extractor"] = serde_json::json!("simple");
+    let mut session = Session::default();
+    session.set_extraction(data.clone(), None);
+    assert_eq!(session.extraction_capabilities().summary(), "text only");
+
+    data["metadata"]["importer"] = serde_json::json!("Textract");
+    session.set_extraction(data, None);
+    assert_eq!(session.extraction_capabilities().summary(), "tables, OCR");
+}
+
+#[test]
+fn maps_pdf_bytes() {
+    use chonker3::core::PdfBytes;
+
+    let path = common::fixture_path("simple.pdf");
+    let mapped = PdfBytes::open(&path).unwrap();
+    assert!(matches!(mapped, PdfBytes::Mapped(_)));
+    assert_eq!(&*mapped, std::fs::read(&path).unwrap().as_slice());
+    assert_eq!(mapped.heap_bytes(), 0);
+
+    // Empty files can't be mapped on every platform; either way the bytes are empty
+    let empty = std::env::temp_dir().join(format!("chonker3_empty_{}.pdf", std::process::id()));
+    std::fs::write(&empty, b"").unwrap();
+    assert!(PdfBytes::open(&empty).unwrap().is_empty());
+    std::fs::remove_file(&empty).ok();
+}
+
+#[test]
+fn page_count_falls_back_to_extraction() {
+    // No PDF counted (as without pdfium): the extraction decides
+    let mut session = Session::default();
+    session.set_extraction(fixture_json("two_column.json"), None);
+    assert_eq!(session.page_count, 2);
+    assert!(session.go_to_page(1));
+
+    assert!(session.set_pdfium_library(std::path::Path::new("/nonexistent/libpdfium.so")).is_err());
+    assert!(session.pdfium_library.is_none());
+}
+
+/// Serves a fixture as the extraction, standing in for a real backend
+struct FixtureExtractor(&'static str);
+
+impl Extractor for FixtureExtractor {
+    fn name(&self) -> &'static str {
+        "fixture"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    fn extract(&self, _pdf: &Path, _opts: &ExtractOptions) -> anyhow::Result<ExtractedDocument> {
+        ExtractedDocument::load(&common::fixture_path(self.0))
+    }
+}
+
+#[test]
+fn extracts_through_a_backend() {
+    let mut session = Session::default();
+    assert!(session.extract(&FixtureExtractor("two_column.json"), &ExtractOptions::default()).is_err());
+
+    session.pdf_path = Some(common::fixture_path("two_column.pdf"));
+    let items = session.extract(&FixtureExtractor("two_column.json"), &ExtractOptions::default()).unwrap();
+    assert!(items > 0);
+    assert_eq!(session.extracted_json, Some(common::fixture_path("two_column.json")));
+}
+
+#[test]
+fn shows_leading_pages_while_extracting() {
+    let page = |number: usize, content: &str| ExtractedPage {
+        page: number,
+        page_count: 3,
+        data: serde_json::json!({
+            "metadata": {"extractor": "simple"},
+            "pages": [{"page_number": number, "width": 612.0, "height": 792.0}],
+            "items": [{"type": "TextItem", "page": number, "content": content,
+                       "bbox": {"left": 72.0, "top": 72.0, "width": 100.0, "height": 14.0}}],
+        }),
+    };
+    let mut partial = PartialExtraction::default();
+    partial.add(page(1, "First"));
+    // Page 3 can't be placed until page 2 arrives
+    partial.add(page(3, "Third"));
+    assert_eq!((partial.ready_pages(), partial.page_count()), (1, 3));
+    assert_eq!(partial.data()["items"].as_array().unwrap().len(), 1);
+
+    partial.add(page(2, "Second"));
+    let data = partial.data();
+    assert_eq!(data["pages"].as_array().unwrap().len(), 3);
+    assert_eq!(data["items"][2]["content"], "Third");
+    assert_eq!(data["items"][2]["index"], 2);
+    assert_eq!(data["metadata"]["extractor"], "simple");
+
+    let mut session = Session { page_count: 3, ..Default::default() };
+    session.set_extraction(data, None);
+    session.go_to_page(2);
+    assert_eq!(session.document_state().unwrap().items[0].content, "Third");
+}
+
+#[test]
+fn reads_the_extractor_event_stream() {
+    let dir = std::env::temp_dir().join(format!("chonker3_stream_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let page_json = dir.join("page_0001.json");
+    std::fs::write(&page_json, r#"{"pages": [{"page_number": 1, "width": 612, "height": 792}], "items": []}"#).unwrap();
+    let done_json = common::fixture_path("two_column.json");
+
+    let stream = [
+        r#"{"event": "progress", "message": "Loading extractor"}"#.to_string(),
+        "some library chatter".to_string(),
+        r#"{"event": "progress", "message": "Extracting page 1 of 2", "page": 1, "pages": 2}"#.to_string(),
+        serde_json::json!({"event": "page", "page": 1, "pages": 2, "json_path": page_json}).to_string(),
+        serde_json::json!({"event": "done", "json_path": done_json, "items": 3, "pages": 2, "extractor_used": "simple"}).to_string(),
+    ].join("\n");
+    let mut updates = Vec::new();
+    let result = extractor::read_stream(stream.as_bytes(), &mut |update| updates.push(update)).unwrap();
+    assert_eq!(result, StreamEnd::Done(done_json));
+    assert!(matches!(&updates[0], ExtractionUpdate::Progress { message, page: None, .. } if message == "Loading extractor"));
+    assert!(matches!(&updates[1], ExtractionUpdate::Progress { page: Some(1), pages: Some(2), .. }));
+    assert!(matches!(&updates[2], ExtractionUpdate::Page(page) if page.page == 1 && page.page_count == 2));
+
+    // Errors the extractor reports come back as they are; a silent exit is no result
+    let error = r#"{"event": "error", "error": "Docling not installed", "missing": true}"#;
+    let end = extractor::read_stream(error.as_bytes(), &mut |_| {}).unwrap();
+    assert_eq!(end, StreamEnd::Failed { error: "Docling not installed".to_string(), missing: true });
+    assert_eq!(extractor::read_stream("Traceback...".as_bytes(), &mut |_| {}).unwrap(), StreamEnd::Ended);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn falls_back_through_the_backend_chain() {
+    let failure = |backend, error: &str, missing| BackendFailure { backend, error: error.to_string(), missing };
+    let mut tried = Vec::new();
+    let mut reported = Vec::new();
+    let result = extractor::run_fallback_chain(
+        &PythonBackend::ALL,
+        1,
+        |backend| {
+            tried.push(backend);
+            match backend {
+                PythonBackend::Enhanced => Err(failure(backend, "No module named 'ocrmac'", true)),
+                PythonBackend::Docling => Err(failure(backend, "CUDA out of memory", false)),
+                PythonBackend::Simple => Ok("extracted"),
+            }
+        },
+        |failure| reported.push(failure.backend),
+    );
+    assert_eq!(result, Ok("extracted"));
+    // Missing backends aren't retried; failing ones are
+    assert_eq!(tried, vec![PythonBackend::Enhanced, PythonBackend::Docling, PythonBackend::Docling, PythonBackend::Simple]);
+    assert_eq!(reported.len(), 3);
+
+    let failures = extractor::run_fallback_chain(
+        &[PythonBackend::Docling, PythonBackend::Simple],
+        0,
+        |backend| Err::<(), _>(failure(backend, "broken", false)),
+        |_| {},
+    ).unwrap_err();
+    assert_eq!(
+        extractor::describe_failures(&failures),
+        "Every extraction backend failed:\nDocling: broken\nSimple (pypdfium2): broken"
+    );
+
+    // Settings saved before the chain existed get the full chain
+    let options: ExtractOptions = serde_json::from_str(r#"{"preprocess": true}"#).unwrap();
+    assert_eq!(options.fallback_chain, PythonBackend::ALL.to_vec());
+}
+
+#[test]
+fn merges_pages_extracted_with_other_backends() {
+    let page = |number: u64, text: &str| serde_json::json!({
+        "pages": [{"page_number": number, "width": 612.0, "height": 792.0}],
+        "items": [{"type": "TextItem", "page": number, "content": text, "index": 0}],
+    });
+    let mut base = page(1, "Report");
+    base["pages"].as_array_mut().unwrap().extend(page(3, "").get("pages").unwrap().as_array().unwrap().clone());
+    base["items"].as_array_mut().unwrap().push(serde_json::json!({"type": "TextItem", "page": 3, "content": "Summary"}));
+    base["metadata"] = serde_json::json!({"extractor": "docling"});
+
+    let merged = extractor::merge_page_extractions(base, vec![(PythonBackend::Enhanced, page(2, "Scanned appendix"))]);
+    let numbers: Vec<u64> = merged["pages"].as_array().unwrap().iter().map(|p| p["page_number"].as_u64().unwrap()).collect();
+    assert_eq!(numbers, vec![1, 2, 3]);
+    let items: Vec<(&str, u64)> = merged["items"].as_array().unwrap().iter()
+        .map(|i| (i["content"].as_str().unwrap(), i["index"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(items, vec![("Report", 0), ("Scanned appendix", 1), ("Summary", 2)]);
+    assert_eq!(merged["metadata"]["extractor"], "docling");
+    assert_eq!(merged["metadata"]["page_backends"], serde_json::json!({"1": "docling", "2": "enhanced", "3": "docling"}));
+}
+
+#[test]
+fn page_bookmarks_and_notes() {
+    let mut session = session("two_column.json");
+    assert!(session.toggle_bookmark(1));
+    session.set_page_note(0, "Check totals");
+    session.set_page_note(1, "  ");
+    assert_eq!(session.marked_pages(), vec![0, 1]);
+
+    // Carried in patches and exports
+    let patch = session.to_patch();
+    assert_eq!(patch.edit_count(), 2);
+    let mut other = Session::default();
+    other.apply_patch(patch.clone());
+    assert_eq!(other.edits.page_notes.get(&0).map(String::as_str), Some("Check totals"));
+
+    let export = chonker3::export::structured_export(session.extracted_data.as_ref().unwrap(), &patch, session.number_locale);
+    assert_eq!(export["bookmarks"], serde_json::json!([2]));
+    assert_eq!(export["page_notes"][0]["page"], 1);
+    let markdown = chonker3::export::markdown_export(session.extracted_data.as_ref().unwrap(), &patch);
+    assert!(markdown.starts_with("<!-- Page 1 -->\n\n> Check totals\n\n"));
+    assert!(markdown.contains("<!-- Page 2 -->\n\n> 🔖 Bookmarked\n\n"));
+
+    // Reordering pages moves the marks; deleted pages lose them
+    session.edits.remap_pages(|page| (page == 1).then_some(0));
+    assert_eq!(session.marked_pages(), vec![0]);
+    assert!(session.edits.page_notes.is_empty());
+    assert!(!session.toggle_bookmark(0));
+}
@@ -0,0 +1,84 @@
+//! chonker3:// links to a spot in a document
+
+use std::path::PathBuf;
+use chonker3::deep_link::{self, DeepLink, LinkTarget};
+use chonker3::launch;
+use chonker3::types::{BoundingBox, DocumentItem, ItemType};
+
+fn item(id: &str, left: f64, top: f64) -> DocumentItem {
+    DocumentItem {
+        id: id.to_string(),
+        bbox: BoundingBox { left, top, width: 50.0, height: 12.0 },
+        content: id.to_string(),
+        font_size: 12.0,
+        color: (0, 0, 0),
+        item_type: ItemType::Text,
+        bold: false,
+        italic: false,
+        confidence: None,
+        baseline: None,
+        latex: None,
+        drop_cap: None,
+        provenance: Vec::new(),
+    }
+}
+
+#[test]
+fn links_round_trip() {
+    let targets = [
+        LinkTarget::Page,
+        LinkTarget::Item("p2 table/1&x".to_string()),
+        LinkTarget::Region(BoundingBox { left: 72.0, top: 100.5, width: 200.0, height: 40.0 }),
+    ];
+    for target in targets {
+        let link = DeepLink::new(b"%PDF-1.4 test", 2, target);
+        let uri = link.to_uri();
+        assert!(uri.starts_with("chonker3://open?doc=") && uri.contains("&page=3"), "{}", uri);
+        assert_eq!(DeepLink::parse(&uri).unwrap(), link);
+    }
+}
+
+#[test]
+fn rejects_malformed_links() {
+    let hash = deep_link::document_hash(b"x");
+    assert!(DeepLink::parse("https://example.com").is_err());
+    assert!(DeepLink::parse("chonker3://open?page=2").is_err(), "no document");
+    assert!(DeepLink::parse("chonker3://open?doc=abc").is_err(), "short hash");
+    assert!(DeepLink::parse(&format!("chonker3://open?doc={}&page=0", hash)).is_err());
+    assert!(DeepLink::parse(&format!("chonker3://open?doc={}&bbox=1,2,3", hash)).is_err());
+    // Unknown parameters are left for newer versions
+    assert_eq!(DeepLink::parse(&format!("chonker3://open/?doc={}&zoom=2", hash)).unwrap().page, 0);
+}
+
+#[test]
+fn targets_the_selection() {
+    let items = [item("a", 72.0, 100.0), item("b", 72.0, 130.0), item("c", 300.0, 500.0)];
+    assert_eq!(LinkTarget::for_items(&[]), LinkTarget::Page);
+    assert_eq!(LinkTarget::for_items(&[&items[0]]), LinkTarget::Item("a".to_string()));
+
+    let region = LinkTarget::for_items(&[&items[0], &items[1]]);
+    assert_eq!(region, LinkTarget::Region(BoundingBox { left: 72.0, top: 100.0, width: 50.0, height: 42.0 }));
+    let (selected, spot) = region.resolve(&items);
+    assert_eq!(selected, vec!["a", "b"]);
+    assert_eq!(spot.unwrap().top, 100.0);
+
+    let (selected, spot) = LinkTarget::Item("c".to_string()).resolve(&items);
+    assert_eq!((selected, spot.map(|b| b.left)), (vec!["c".to_string()], Some(300.0)));
+}
+
+#[test]
+fn finds_the_document_by_content() {
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/simple.pdf");
+    let hash = deep_link::document_hash(&std::fs::read(&fixture).unwrap());
+    let candidates = vec![PathBuf::from("no/such/file.pdf"), fixture.clone()];
+    assert_eq!(deep_link::find_document(&hash, candidates.clone()), Some(fixture));
+    assert_eq!(deep_link::find_document(&deep_link::document_hash(b"other"), candidates), None);
+}
+
+#[test]
+fn launches_with_links() {
+    let uri = DeepLink::new(b"pdf", 0, LinkTarget::Page).to_uri();
+    let args = launch::parse_args([uri.clone(), "invoice.pdf".to_string()]).unwrap();
+    assert_eq!(args.links, vec![DeepLink::parse(&uri).unwrap()]);
+    assert_eq!(args.files, vec![PathBuf::from("invoice.pdf")]);
+}
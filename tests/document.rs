@@ -0,0 +1,232 @@
+//! Offscreen document construction: edits, search and columns
+
+mod common;
+
+use chonker3::document;
+use chonker3::patch::EditPatch;
+use chonker3::types::{self, DocumentItem, ItemType, ProvenanceStage, ProvenanceStep};
+use common::fixture_json;
+
+#[test]
+fn converts_bottomleft_to_topleft() {
+    let data = fixture_json("simple.json");
+    let items = document::page_items(&data, 0, &EditPatch::default());
+    let title = items.iter().find(|i| i.content == "Quarterly Report").unwrap();
+    assert_eq!(title.bbox.top, 792.0 - 736.0);
+    assert_eq!(title.id, types::item_id(0, 72.0, 56.0));
+}
+
+#[test]
+fn skips_blank_items_and_other_pages() {
+    let data = fixture_json("two_column.json");
+    let items = document::page_items(&data, 1, &EditPatch::default());
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|i| !i.content.trim().is_empty()));
+
+    let simple = fixture_json("simple.json");
+    assert_eq!(document::page_items(&simple, 0, &EditPatch::default()).len(), 6);
+    assert!(document::page_items(&simple, 1, &EditPatch::default()).is_empty());
+}
+
+#[test]
+fn applies_deletions_and_type_changes() {
+    let data = fixture_json("two_column.json");
+    let left_id = types::item_id(0, 72.0, 92.0);
+    let right_id = types::item_id(0, 330.0, 92.0);
+
+    let mut edits = EditPatch::default();
+    edits.deletions.insert(left_id.clone());
+    edits.type_changes.insert(right_id.clone(), ItemType::Header);
+
+    let items = document::page_items(&data, 0, &edits);
+    assert!(items.iter().all(|i| i.id != left_id));
+    let right = items.iter().find(|i| i.id == right_id).unwrap();
+    assert_eq!(right.item_type, ItemType::Header);
+}
+
+#[test]
+fn search_is_case_insensitive() {
+    let data = fixture_json("two_column.json");
+    let state = document::document_state(&data, 0, &EditPatch::default(), "COLUMN text");
+    assert_eq!(state.search_results.len(), 2);
+    assert!(document::document_state(&data, 0, &EditPatch::default(), "").search_results.is_empty());
+}
+
+#[test]
+fn reads_column_layout() {
+    let data = fixture_json("two_column.json");
+    assert_eq!(document::page_columns(&data, 0), (2, vec![306.0]));
+    // Pages without column info fall back to a single column
+    assert_eq!(document::page_columns(&data, 1), (1, vec![]));
+    assert_eq!(document::page_columns(&data, 5), (1, vec![]));
+}
+
+#[test]
+fn filters_hidden_item_categories() {
+    use types::{ItemCategory, ItemFilter};
+
+    let data = fixture_json("simple.json");
+    let mut items = document::page_items(&data, 0, &EditPatch::default());
+    assert!(items.iter().all(|i| i.confidence == Some(1.0)));
+    items[0].confidence = Some(0.5);
+
+    let mut filter = ItemFilter::default();
+    filter.set_hidden(ItemCategory::Headers, true);
+    filter.set_hidden(ItemCategory::FormFields, true);
+    filter.set_hidden(ItemCategory::LowConfidence, true);
+    let mut visible = items.clone();
+    filter.apply(&mut visible);
+    let types: Vec<ItemType> = visible.iter().map(|i| i.item_type).collect();
+    assert_eq!(types, vec![ItemType::Text, ItemType::Table]);
+
+    // Un-hiding brings a category back
+    filter.set_hidden(ItemCategory::Headers, false);
+    let mut visible = items;
+    filter.apply(&mut visible);
+    assert!(visible.iter().any(|i| i.item_type == ItemType::Header));
+    assert!(!visible.iter().any(|i| i.item_type == ItemType::Title), "the title is low-confidence");
+}
+
+#[test]
+fn baselines_are_recorded_or_estimated() {
+    let mut data = fixture_json("simple.json");
+    // simple.json is BOTTOMLEFT; a recorded baseline converts with the box
+    data["items"][0]["baseline"] = serde_json::json!(726.0);
+    let items = document::page_items(&data, 0, &EditPatch::default());
+    let title = items.iter().find(|i| i.content == "Quarterly Report").unwrap();
+    assert_eq!(title.baseline(), 792.0 - 726.0);
+
+    // Without one, a single line sits a descent above its box bottom
+    let mut header = items.iter().find(|i| i.item_type == ItemType::Header).unwrap().clone();
+    header.baseline = None;
+    header.bbox.height = header.font_size as f64 * 1.2;
+    let bottom = header.bbox.top + header.bbox.height;
+    assert!(header.baseline() < bottom && header.baseline() > header.bbox.top);
+}
+
+#[test]
+fn detects_formulas_and_their_latex() {
+    assert!(document::looks_like_formula("E = mc²"));
+    assert!(document::looks_like_formula("∑ xᵢ ≤ √n"));
+    assert!(!document::looks_like_formula("The total is computed as shown below = see table"));
+    assert!(!document::looks_like_formula("Invoice 2024"));
+    assert!(document::looks_like_latex("\\frac{a}{b}"));
+    assert!(!document::looks_like_latex("C:\\ 12"));
+
+    let item = |kind: &str, content: &str, top: f64, extra: serde_json::Value| {
+        let mut item = serde_json::json!({
+            "page": 1, "type": kind, "content": content,
+            "bbox": { "left": 72.0, "top": top, "width": 200.0, "height": 20.0 },
+        });
+        item.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        item
+    };
+    let data = serde_json::json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            item("FormulaItem", "", 100.0, serde_json::json!({ "attributes": { "latex": "x^2" } })),
+            item("FormulaItem", "\\sum_{i=1}^{n} x_i", 150.0, serde_json::json!({})),
+            item("TextItem", "a² + b² = c²", 200.0, serde_json::json!({})),
+        ],
+    });
+    let items = document::page_items(&data, 0, &EditPatch::default());
+    assert!(items.iter().all(|i| i.item_type == ItemType::Formula));
+    assert_eq!(items[0].content, "[Formula]");
+    assert_eq!(items[0].latex.as_deref(), Some("x^2"));
+    assert_eq!(items[1].latex.as_deref(), Some("\\sum_{i=1}^{n} x_i"));
+    assert_eq!(items[2].latex, None);
+
+    let markdown = chonker3::export::markdown_export(&data, &EditPatch::default());
+    assert!(markdown.contains("$$\nx^2\n$$"));
+    assert!(markdown.contains("*[Formula]*"));
+}
+
+#[test]
+fn detects_code_blocks() {
+    let code = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}";
+    assert!(document::looks_like_code(code));
+    assert!(!document::looks_like_code("First line of a poem,\nsecond line of it,\nand the third."));
+    assert!(document::is_monospace_font("CourierNewPSMT"));
+    assert!(!document::is_monospace_font("Helvetica-Bold"));
+
+    let data = serde_json::json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            { "page": 1, "type": "TextItem", "content": code, "bbox": { "left": 72.0, "top": 100.0, "width": 300.0, "height": 60.0 } },
+            { "page": 1, "type": "TextItem", "content": "ls -la", "attributes": { "style": { "font": "Menlo-Regular" } },
+              "bbox": { "left": 72.0, "top": 200.0, "width": 60.0, "height": 12.0 } },
+        ],
+    });
+    let items = document::page_items(&data, 0, &EditPatch::default());
+    assert!(items.iter().all(|i| i.item_type == ItemType::Code));
+
+    // Indentation survives into a fence longer than any backtick run in the code
+    let markdown = chonker3::export::markdown_export(&data, &EditPatch::default());
+    assert!(markdown.contains("```\nfn main() {\n    let x = 1;"));
+    assert_eq!(chonker3::export::code_fence("a ``` b"), "````\na ``` b\n````");
+}
+
+#[test]
+fn merges_drop_caps_into_their_paragraph() {
+    let item = |content: &str, left: f64, top: f64, height: f64, font_size: f64| serde_json::json!({
+        "page": 1, "type": "TextItem", "content": content,
+        "attributes": { "style": { "font_size": font_size } },
+        "bbox": { "left": left, "top": top, "width": if content.chars().count() == 1 { 30.0 } else { 300.0 }, "height": height },
+    });
+    let data = serde_json::json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            item("O", 72.0, 100.0, 36.0, 40.0),
+            item("nce upon a time there was a parser.", 106.0, 101.0, 40.0, 11.0),
+            item("❦", 72.0, 300.0, 30.0, 32.0),
+            item("Chapter two begins here.", 106.0, 302.0, 30.0, 11.0),
+            // A lone letter set at body size stays its own item
+            item("A", 72.0, 500.0, 12.0, 11.0),
+            item("Option one", 90.0, 500.0, 12.0, 11.0),
+        ],
+    });
+    let items = document::page_items(&data, 0, &EditPatch::default());
+    let contents: Vec<&str> = items.iter().map(|i| i.content.as_str()).collect();
+    assert_eq!(contents, vec![
+        "Once upon a time there was a parser.",
+        "Chapter two begins here.",
+        "A",
+        "Option one",
+    ]);
+    let cap = items[0].drop_cap.as_ref().unwrap();
+    assert_eq!((cap.glyph.as_str(), cap.decorative, cap.bbox.left), ("O", false, 72.0));
+    assert!(items[1].drop_cap.as_ref().unwrap().decorative);
+    assert!(items[2].drop_cap.is_none());
+}
+
+#[test]
+fn items_carry_their_provenance_chain() {
+    let mut data = serde_json::json!({
+        "pages": [{"page_number": 1, "width": 612.0, "height": 792.0}],
+        "items": [
+            {"type": "TextItem", "page": 1, "content": "Total 42", "bbox": {"left": 72.0, "top": 72.0, "width": 100.0, "height": 14.0},
+             "provenance": [{"stage": "extractor", "name": "enhanced"}, {"stage": "ocr", "name": "Apple Vision"}, {"stage": "bogus"}]},
+            {"type": "TextItem", "page": 1, "content": "E = mc^2", "bbox": {"left": 72.0, "top": 120.0, "width": 80.0, "height": 14.0}},
+        ],
+    });
+    document::record_preprocessing(&mut data, ProvenanceStep::new(ProvenanceStage::Preprocess, "scan cleanup"));
+
+    let items = document::page_items(&data, 0, &EditPatch::default());
+    fn chain(item: &DocumentItem) -> Vec<(ProvenanceStage, &str)> {
+        item.provenance.iter().map(|s| (s.stage, s.name.as_str())).collect()
+    }
+    assert_eq!(chain(&items[0]), vec![
+        (ProvenanceStage::Preprocess, "scan cleanup"),
+        (ProvenanceStage::Extractor, "enhanced"),
+        (ProvenanceStage::Ocr, "Apple Vision"),
+    ]);
+    // Reclassifying plain text is a post-processing step of its own
+    assert_eq!(items[1].item_type, ItemType::Formula);
+    assert_eq!(chain(&items[1]), vec![
+        (ProvenanceStage::Preprocess, "scan cleanup"),
+        (ProvenanceStage::Postprocess, "formula detection"),
+    ]);
+
+    let export = chonker3::export::structured_export(&data, &EditPatch::default(), Default::default());
+    assert_eq!(export["items"][0]["provenance"][2], serde_json::json!({"stage": "ocr", "name": "Apple Vision"}));
+}
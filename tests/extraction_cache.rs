@@ -0,0 +1,77 @@
+//! Extraction results cached by the PDF's hash
+
+use chonker3::extraction_cache::{cache_key, default_dir, dir_for, ExtractionCache, CACHE_KEY};
+use chonker3::extractor::{ExtractOptions, ExtractedDocument, ExtractorKind, PythonBackend};
+use chonker3::checksum::sha256_hex;
+use chonker3::storage::{ResolvedDir, StorageKind};
+use serde_json::json;
+
+fn cache(name: &str) -> ExtractionCache {
+    let dir = std::env::temp_dir().join(format!("chonker3_extraction_cache_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    ExtractionCache::new(dir)
+}
+
+fn document() -> ExtractedDocument {
+    ExtractedDocument {
+        json_path: "simple_chonker3.json".into(),
+        data: json!({ "metadata": { "extractor": "native" }, "pages": [{}], "items": [{ "id": "a" }] }),
+    }
+}
+
+#[test]
+fn hits_for_the_same_file_and_settings_only() {
+    let cache = cache("hits");
+    let sha256 = sha256_hex(b"%PDF-1.4 simple");
+    let options = ExtractOptions::default();
+    let key = cache_key(ExtractorKind::Native, &options);
+    assert!(cache.get(&sha256, &key).is_none());
+
+    let cached = cache.put(&sha256, &key, &document()).unwrap();
+    assert_eq!(cached.json_path, cache.dir().join(format!("{}.json", sha256)));
+    assert_eq!(cached.data["metadata"][CACHE_KEY], key.as_str());
+
+    let hit = cache.get(&sha256, &key).unwrap();
+    assert_eq!(hit.item_count(), 1);
+    // The app works on a copy, so what it writes back stays out of the cache
+    assert_eq!(hit.json_path, ExtractionCache::working_copy_path(&sha256));
+    std::fs::write(&hit.json_path, r#"{ "items": [{ "id": "a" }, { "id": "ocr" }] }"#).unwrap();
+    assert_eq!(cache.get(&sha256, &key).unwrap().item_count(), 1);
+    let _ = std::fs::remove_file(&hit.json_path);
+
+    // Another file, extractor or option set misses
+    assert!(cache.get(&sha256_hex(b"%PDF-1.4 edited"), &key).is_none());
+    assert!(cache.get(&sha256, &cache_key(ExtractorKind::Docling, &options)).is_none());
+    let line_items = ExtractOptions { line_items: true, ..Default::default() };
+    assert!(cache.get(&sha256, &cache_key(ExtractorKind::Native, &line_items)).is_none());
+    // Per-document settings that aren't saved still count
+    let mut page_backends = ExtractOptions::default();
+    page_backends.page_backends.insert(0, PythonBackend::Simple);
+    assert_ne!(cache_key(ExtractorKind::Native, &page_backends), key);
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+#[test]
+fn clears_only_cached_results() {
+    let cache = cache("clear");
+    let key = cache_key(ExtractorKind::Native, &ExtractOptions::default());
+    cache.put(&sha256_hex(b"one"), &key, &document()).unwrap();
+    cache.put(&sha256_hex(b"two"), &key, &document()).unwrap();
+    std::fs::write(cache.dir().join("notes.json"), "{}").unwrap();
+
+    let (count, bytes) = cache.usage();
+    assert_eq!(count, 2);
+    assert!(bytes > 0);
+    assert_eq!(cache.clear().unwrap(), 2);
+    assert_eq!(cache.usage(), (0, 0));
+    assert!(cache.dir().join("notes.json").exists());
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+#[test]
+fn follows_a_configured_cache_directory() {
+    let unset = ResolvedDir { path: StorageKind::Cache.default_dir(), from_env: false };
+    assert_eq!(dir_for(&unset), default_dir());
+    let configured = ResolvedDir { path: "/data/chonker3_cache".into(), from_env: true };
+    assert_eq!(dir_for(&configured), std::path::PathBuf::from("/data/chonker3_cache"));
+}
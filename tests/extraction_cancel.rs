@@ -0,0 +1,33 @@
+//! Cancelling a running extraction
+
+mod common;
+
+use chonker3::core::bind_pdfium;
+use chonker3::extractor::{CancelToken, ExtractOptions, Extractor, NativeExtractor, CANCELLED};
+
+#[test]
+fn clones_share_the_flag() {
+    let token = CancelToken::default();
+    let options = ExtractOptions { cancel: token.clone(), ..Default::default() };
+    assert!(!options.cancel.is_cancelled());
+    token.cancel();
+    assert!(options.cancel.is_cancelled());
+
+    // A fresh run starts uncancelled, whatever the saved settings held
+    let saved = serde_json::to_string(&options).unwrap();
+    let loaded: ExtractOptions = serde_json::from_str(&saved).unwrap();
+    assert!(!loaded.cancel.is_cancelled());
+}
+
+#[test]
+fn a_cancelled_native_run_stops() {
+    if bind_pdfium().is_err() {
+        eprintln!("pdfium not available, skipping");
+        return;
+    }
+
+    let options = ExtractOptions::default();
+    options.cancel.cancel();
+    let error = NativeExtractor.extract(&common::fixture_path("simple.pdf"), &options).unwrap_err();
+    assert_eq!(error.to_string(), CANCELLED);
+}
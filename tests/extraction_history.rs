@@ -0,0 +1,43 @@
+//! Extraction run history in the workspace
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use chonker3::extractor::{ExtractOptions, ExtractorKind, PythonBackend};
+use chonker3::workspace::{ExtractionRun, Workspace, HISTORY_LIMIT};
+
+fn run(document: &str, page_backends: BTreeMap<usize, PythonBackend>) -> ExtractionRun {
+    ExtractionRun {
+        document: PathBuf::from(document),
+        extractor: ExtractorKind::Docling,
+        options: ExtractOptions { preprocess: false, password: Some("secret".into()), ..Default::default() },
+        page_backends,
+        started: "2026-10-16T09:30:00+00:00".into(),
+        seconds: 12.5,
+        backend: Some("docling".into()),
+        items: 40,
+        json_path: Some(PathBuf::from("/tmp/run.json")),
+        error: None,
+    }
+}
+
+#[test]
+fn reruns_with_the_recorded_options() {
+    let page_backends = BTreeMap::from([(2, PythonBackend::ALL[0])]);
+    let saved = serde_json::to_string(&run("/docs/a.pdf", page_backends.clone())).unwrap();
+    assert!(!saved.contains("secret"), "passwords stay out of the workspace");
+    let loaded: ExtractionRun = serde_json::from_str(&saved).unwrap();
+    let options = loaded.rerun_options();
+    assert!(!options.preprocess);
+    assert_eq!(options.page_backends, page_backends);
+    assert!(loaded.succeeded());
+}
+
+#[test]
+fn keeps_the_latest_runs() {
+    let mut workspace = Workspace::default();
+    for i in 0..HISTORY_LIMIT + 5 {
+        workspace.record_run(run(&format!("/docs/{}.pdf", i), BTreeMap::new()));
+    }
+    assert_eq!(workspace.extraction_history.len(), HISTORY_LIMIT);
+    assert_eq!(workspace.extraction_history[0].document, PathBuf::from("/docs/5.pdf"));
+}
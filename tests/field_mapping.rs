@@ -0,0 +1,85 @@
+//! Output mapping of named fields to a sheet, one row per document
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use chonker3::field_mapping::{field_names, ColumnSource, DocumentFields, FieldMapping, MappedColumn};
+use chonker3::regions::NamedRegion;
+use chonker3::types::BoundingBox;
+use chonker3::workspace::WorkspaceDocument;
+use serde_json::json;
+
+fn document(dir: &std::path::Path, name: &str, number: &str, total: &str) -> WorkspaceDocument {
+    let json_path = dir.join(format!("{}.json", name));
+    let item = |left: f64, content: &str| json!({
+        "page": 1, "type": "TextItem", "content": content,
+        "bbox": { "left": left, "top": 100.0, "width": 100.0, "height": 12.0 },
+    });
+    std::fs::write(&json_path, json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [item(72.0, number), item(400.0, total)],
+    }).to_string()).unwrap();
+    let region = |name: &str, left: f64| NamedRegion {
+        name: name.to_string(),
+        page: 0,
+        bbox: BoundingBox { left, top: 90.0, width: 120.0, height: 30.0 },
+    };
+    WorkspaceDocument {
+        path: PathBuf::from(format!("/invoices/{}.pdf", name)),
+        tags: BTreeSet::new(),
+        metadata: [("client".to_string(), "Acme, Inc.".to_string())].into(),
+        extracted_json: Some(json_path),
+        fingerprint: None,
+        bookmarks: BTreeSet::new(),
+        page_notes: Default::default(),
+        page_backends: Default::default(),
+        comments: Default::default(),
+        regions: vec![region("Invoice number", 60.0), region("Total", 390.0)],
+        recent_searches: Default::default(),
+        added: String::new(),
+    }
+}
+
+#[test]
+fn one_row_per_document() {
+    let dir = std::env::temp_dir().join(format!("chonker3_field_mapping_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let documents = vec![document(&dir, "a", "INV-1", "10.00"), document(&dir, "b", "INV-2", "20.00")];
+    assert_eq!(field_names(&documents), ["Invoice number", "Total"]);
+
+    let mapping = FieldMapping {
+        columns: vec![
+            MappedColumn { header: "File".to_string(), source: ColumnSource::FileName },
+            MappedColumn::field("invoice number"),
+            MappedColumn::field("Total"),
+            MappedColumn { header: "Client".to_string(), source: ColumnSource::Metadata("client".to_string()) },
+            MappedColumn::field("PO"),
+            MappedColumn { header: "Pages".to_string(), source: ColumnSource::Pages },
+        ],
+        ..Default::default()
+    };
+    let rows: Vec<DocumentFields> = documents.iter().map(|doc| DocumentFields::load(doc).unwrap()).collect();
+    assert_eq!(
+        mapping.csv(&rows),
+        "File,invoice number,Total,Client,PO,Pages\n\
+         a.pdf,INV-1,10.00,\"Acme, Inc.\",,1\n\
+         b.pdf,INV-2,20.00,\"Acme, Inc.\",,1\n",
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mapping_round_trips_through_json() {
+    let mapping = FieldMapping {
+        columns: vec![
+            MappedColumn::field("Total"),
+            MappedColumn { header: "Path".to_string(), source: ColumnSource::Path },
+        ],
+        file_name: "invoices.csv".to_string(),
+    };
+    let json = serde_json::to_value(&mapping).unwrap();
+    assert_eq!(json["columns"][0]["source"], json!({ "source": "field", "name": "Total" }));
+    assert_eq!(json["columns"][1]["source"], json!({ "source": "path" }));
+    assert_eq!(serde_json::from_value::<FieldMapping>(json).unwrap(), mapping);
+    assert_eq!(serde_json::from_str::<FieldMapping>("{}").unwrap(), FieldMapping::default());
+}
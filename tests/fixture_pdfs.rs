@@ -0,0 +1,55 @@
+//! Fixture PDFs agree with their extraction fixtures.
+//! Needs the pdfium library (see PDFIUM_DYNAMIC_LIB_PATH); skipped when it can't be loaded.
+
+mod common;
+
+use chonker3::core::bind_pdfium;
+
+#[test]
+fn page_sizes_match_extractions() {
+    let Ok(pdfium) = bind_pdfium() else {
+        eprintln!("pdfium not available, skipping");
+        return;
+    };
+
+    for name in ["simple", "two_column"] {
+        let document = pdfium.load_pdf_from_file(&common::fixture_path(&format!("{}.pdf", name)), None).unwrap();
+        let data = common::fixture_json(&format!("{}.json", name));
+        let pages = data["pages"].as_array().unwrap();
+        assert_eq!(document.pages().len() as usize, pages.len(), "{}", name);
+        for (page, expected) in document.pages().iter().zip(pages) {
+            assert_eq!(page.width().value as f64, expected["width"].as_f64().unwrap());
+            assert_eq!(page.height().value as f64, expected["height"].as_f64().unwrap());
+        }
+    }
+}
+
+#[test]
+fn writes_one_png_per_page() {
+    if bind_pdfium().is_err() {
+        eprintln!("pdfium not available, skipping");
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("chonker3_png_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let bytes = std::fs::read(common::fixture_path("two_column.pdf")).unwrap();
+    let out = dir.clone();
+    let job = chonker3::jobs::Job::spawn("png", move |job| {
+        let pdfium = bind_pdfium()?;
+        let count = chonker3::renderer::write_page_pngs(&pdfium, &bytes, None, &out, "two_column", 1.0, job)?;
+        Ok(count.to_string())
+    });
+    let result = loop {
+        if let Some(result) = job.take_result() {
+            break result;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    assert_eq!(result.unwrap(), "2");
+    assert_eq!(job.progress().0, 2);
+    assert!(dir.join("two_column_page_001.png").exists());
+    assert!(dir.join("two_column_page_002.png").exists());
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,65 @@
+//! Golden-output tests for conversion, exports and imports
+
+mod common;
+
+use chonker3::{document, export, importers, normalize::NumberLocale, patch::EditPatch};
+use common::{assert_golden_json, assert_golden_text, fixture_json, fixture_path};
+
+fn edits() -> EditPatch {
+    EditPatch::new(Some("fixture.pdf".to_string()))
+}
+
+#[test]
+fn document_state_simple() {
+    let data = fixture_json("simple.json");
+    let state = document::document_state(&data, 0, &edits(), "");
+    assert_golden_json("simple.state.json", &serde_json::to_value(&state).unwrap());
+}
+
+#[test]
+fn document_state_two_column() {
+    let data = fixture_json("two_column.json");
+    for page in 0..2 {
+        let state = document::document_state(&data, page, &edits(), "");
+        assert_golden_json(&format!("two_column.page{}.state.json", page + 1), &serde_json::to_value(&state).unwrap());
+    }
+}
+
+#[test]
+fn markdown_export() {
+    for name in ["simple", "two_column"] {
+        let data = fixture_json(&format!("{}.json", name));
+        assert_golden_text(&format!("{}.md", name), &export::markdown_export(&data, &edits()));
+    }
+}
+
+#[test]
+fn structured_export() {
+    let data = fixture_json("simple.json");
+    let mut export = export::structured_export(&data, &edits(), NumberLocale::Auto);
+    // Timestamp changes on every run
+    export.as_object_mut().unwrap().remove("generated");
+    assert_golden_json("simple.structured.json", &export);
+}
+
+#[test]
+fn hocr_import() {
+    let (format, mut data) = importers::import_file(&fixture_path("scan.hocr"), &[(612.0, 792.0)]).unwrap();
+    assert_eq!(format, importers::ImportFormat::Hocr);
+    // Metadata holds the absolute fixture path
+    data["metadata"]["source_file"] = serde_json::json!("scan.hocr");
+    assert_golden_json("scan.import.json", &data);
+}
+
+#[test]
+fn csv_export() {
+    let data = fixture_json("simple.json");
+    assert_golden_text("simple.csv", &export::csv_export(&data, &edits(), NumberLocale::Auto));
+}
+
+#[test]
+fn items_jsonl_export() {
+    let data = fixture_json("simple.json");
+    let export = export::structured_export(&data, &edits(), NumberLocale::Auto);
+    assert_golden_text("simple.items.jsonl", &export::items_jsonl(&export));
+}
@@ -0,0 +1,90 @@
+//! Scans, emails and Outlook messages as inputs
+
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use base64::Engine;
+use chonker3::inputs::{self, InputKind, ScanPage};
+
+#[test]
+fn recognizes_inputs_by_extension() {
+    assert_eq!(InputKind::from_path(Path::new("a.PDF")), Some(InputKind::Pdf));
+    assert_eq!(InputKind::from_path(Path::new("scan.tif")), Some(InputKind::Scan));
+    assert_eq!(InputKind::from_path(Path::new("mail.eml")), Some(InputKind::Email));
+    assert_eq!(InputKind::from_path(Path::new("mail.msg")), Some(InputKind::OutlookMessage));
+    assert_eq!(InputKind::from_path(Path::new("notes.txt")), None);
+}
+
+#[test]
+fn reads_every_page_of_a_tiff() {
+    let mut bytes = Cursor::new(Vec::new());
+    let mut encoder = tiff::encoder::TiffEncoder::new(&mut bytes).unwrap();
+    encoder.write_image::<tiff::encoder::colortype::Gray8>(4, 2, &[0; 8]).unwrap();
+    encoder.write_image::<tiff::encoder::colortype::RGB8>(3, 5, &[255; 45]).unwrap();
+
+    let pages = inputs::read_tiff(bytes.get_ref()).unwrap();
+    let sizes: Vec<_> = pages.iter().map(|p| (p.image.width(), p.image.height())).collect();
+    assert_eq!(sizes, [(4, 2), (3, 5)]);
+}
+
+#[test]
+fn wraps_scans_into_a_pdf_page_each() {
+    let page = |width, height| ScanPage { image: image::DynamicImage::new_luma8(width, height), dpi: 144.0 };
+    let pdf = inputs::images_to_pdf(&[page(288, 144), page(144, 144)]).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.contains("/Count 2"));
+    // 288 pixels at 144 dpi is two inches
+    assert!(text.contains("/MediaBox [0 0 144.00 72.00]"));
+
+    // Every xref entry points at its object
+    let xref = text.rfind("xref\n").unwrap();
+    for (number, line) in text[xref..].lines().skip(3).take(8).enumerate() {
+        let offset: usize = line[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj", number + 1).as_bytes()));
+    }
+}
+
+#[test]
+fn finds_pdf_attachments_in_emails() {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(b"%PDF-1.4 invoice");
+    let email = format!(
+        "From: a@example.com\r\nSubject: Invoice\r\nContent-Type: multipart/mixed;\r\n boundary=\"outer\"\r\n\r\n\
+         --outer\r\nContent-Type: text/plain\r\n\r\nSee attached.\r\n\
+         --outer\r\nContent-Type: application/pdf; name=\"invoice.pdf\"\r\n\
+         Content-Disposition: attachment; filename=\"invoice.pdf\"\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n\
+         --outer\r\nContent-Type: image/png; name=\"logo.png\"\r\nContent-Transfer-Encoding: base64\r\n\r\niVBORw==\r\n\
+         --outer--\r\n",
+        encoded,
+    );
+    let attachments = inputs::email_pdf_attachments(email.as_bytes()).unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].name, "invoice.pdf");
+    assert_eq!(attachments[0].bytes, b"%PDF-1.4 invoice");
+
+    let dir = std::env::temp_dir().join(format!("chonker3_inputs_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let eml = dir.join("mail.eml");
+    std::fs::write(&eml, &email).unwrap();
+    let pdfs = inputs::prepare(&eml, &dir.join("out")).unwrap();
+    assert_eq!(pdfs, [dir.join("out").join("mail_invoice.pdf")]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn finds_pdf_attachments_in_outlook_messages() {
+    let utf16 = |text: &str| text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect::<Vec<u8>>();
+    let mut file = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    for (index, (name, data)) in [("report.pdf", &b"%PDF-1.7"[..]), ("photo.jpg", &b"\xFF\xD8"[..])].iter().enumerate() {
+        let storage = format!("/__attach_version1.0_#{:08X}", index);
+        file.create_storage(&storage).unwrap();
+        file.create_stream(format!("{}/__substg1.0_3707001F", storage)).unwrap().write_all(&utf16(name)).unwrap();
+        file.create_stream(format!("{}/__substg1.0_37010102", storage)).unwrap().write_all(data).unwrap();
+    }
+    file.flush().unwrap();
+    let bytes = file.into_inner().into_inner();
+
+    let attachments = inputs::msg_pdf_attachments(&bytes).unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!((attachments[0].name.as_str(), attachments[0].bytes.as_slice()), ("report.pdf", &b"%PDF-1.7"[..]));
+}
@@ -0,0 +1,47 @@
+//! Single-instance mode
+
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use chonker3::instance::{self, InstanceServer, Launch};
+
+#[test]
+fn later_launches_hand_their_files_to_the_running_instance() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = InstanceServer::start(listener, || {});
+
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/simple.pdf");
+    assert!(matches!(instance::claim(port, std::slice::from_ref(&fixture), &[]), Launch::Forwarded));
+    // Without files a launch opens its own window
+    assert!(matches!(instance::claim(port, &[], &[]), Launch::Standalone));
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut received = Vec::new();
+    while received.is_empty() && Instant::now() < deadline {
+        received = server.take_requests();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(received, vec![(vec![fixture.canonicalize().unwrap()], Vec::new())]);
+
+    // Files that don't exist aren't sent
+    assert!(instance::forward(port, &[PathBuf::from("no/such/file.pdf")], &[]).is_err());
+}
+
+#[test]
+fn forwards_deep_links() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = InstanceServer::start(listener, || {});
+
+    let link = "chonker3://open?doc=00&page=2".to_string();
+    assert!(matches!(instance::claim(port, &[], std::slice::from_ref(&link)), Launch::Forwarded));
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut received = Vec::new();
+    while received.is_empty() && Instant::now() < deadline {
+        received = server.take_requests();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(received, vec![(Vec::new(), vec![link])]);
+}
@@ -0,0 +1,40 @@
+//! Background jobs: ETA and cancellation
+
+use std::time::Duration;
+use chonker3::jobs::{eta, format_duration, Job};
+
+#[test]
+fn eta_extrapolates_from_finished_steps() {
+    assert_eq!(eta(Duration::from_secs(10), 0, 100), None);
+    assert_eq!(eta(Duration::from_secs(10), 5, 20), Some(Duration::from_secs(30)));
+    assert_eq!(eta(Duration::from_secs(10), 20, 20), Some(Duration::ZERO));
+
+    assert_eq!(format_duration(Duration::from_secs(12)), "12s");
+    assert_eq!(format_duration(Duration::from_secs(185)), "3m 05s");
+    assert_eq!(format_duration(Duration::from_secs(3720)), "1h 02m");
+}
+
+#[test]
+fn cancelled_job_stops_at_next_step() {
+    let job = Job::spawn("test", |job| {
+        job.set_total(1000);
+        for page in 0..1000 {
+            job.begin_step(format!("Page {}", page))?;
+            std::thread::sleep(Duration::from_millis(2));
+            job.finish_step();
+        }
+        Ok("done".to_string())
+    });
+    std::thread::sleep(Duration::from_millis(20));
+    job.cancel();
+
+    let result = loop {
+        if let Some(result) = job.take_result() {
+            break result;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+    assert!(result.is_err());
+    let (done, total, _) = job.progress();
+    assert!(done < total);
+}
@@ -0,0 +1,45 @@
+//! Label Studio task export and import
+
+mod common;
+
+use chonker3::annotations::collect;
+use chonker3::importers::{import_file, ImportFormat};
+use chonker3::label_studio::{item_type_for_label, labeling_config, tasks};
+use chonker3::patch::EditPatch;
+use chonker3::types::ItemType;
+use common::fixture_json;
+
+#[test]
+fn tasks_round_trip_through_import() {
+    let data = fixture_json("simple.json");
+    let mut edits = EditPatch::new(Some("simple.pdf".to_string()));
+    let original = chonker3::document::page_items(&data, 0, &edits);
+    edits.text_overrides.insert(original[0].id.clone(), "Quarterly Report 2024".to_string());
+    let pages = collect(&data, &edits, &[(612.0, 792.0)], "simple");
+    let exported = tasks(&data, &edits, &pages);
+    assert_eq!(exported[0]["data"]["image"], "simple_page_001.png");
+    assert_eq!(exported[0]["annotations"][0]["result"].as_array().unwrap().len(), original.len() * 2);
+
+    let path = std::env::temp_dir().join(format!("chonker3_label_studio_{}.json", std::process::id()));
+    std::fs::write(&path, exported.to_string()).unwrap();
+    let (format, imported) = import_file(&path, &[(612.0, 792.0)]).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(format, ImportFormat::LabelStudio);
+
+    let items = chonker3::document::page_items(&imported, 0, &EditPatch::default());
+    assert_eq!(items.len(), original.len());
+    let title = items.iter().find(|item| item.item_type == ItemType::Title).unwrap();
+    assert_eq!(title.content, "Quarterly Report 2024");
+    assert!((title.bbox.left - original[0].bbox.left).abs() < 1e-6);
+    assert!((title.bbox.top - original[0].bbox.top).abs() < 1e-6);
+}
+
+#[test]
+fn labels_name_item_types() {
+    assert_eq!(item_type_for_label("Table"), Some(ItemType::Table));
+    assert_eq!(item_type_for_label("sectionheaderitem"), Some(ItemType::Header));
+    assert_eq!(item_type_for_label("Stamp"), None);
+    let config = labeling_config();
+    assert!(config.contains("<Label value=\"Formula\"/>"));
+    assert!(config.contains("perRegion=\"true\""));
+}
@@ -0,0 +1,26 @@
+//! Command-line arguments
+
+use std::path::PathBuf;
+use chonker3::launch::{self, LaunchArgs};
+
+fn parse(args: &[&str]) -> anyhow::Result<LaunchArgs> {
+    launch::parse_args(args.iter().map(|a| a.to_string()))
+}
+
+#[test]
+fn takes_files_to_open() {
+    assert_eq!(parse(&[]).unwrap(), LaunchArgs::default());
+    let launch = parse(&["invoice.PDF", "-psn_0_12345", "scan.tiff"]).unwrap();
+    assert_eq!(launch.files, vec![PathBuf::from("invoice.PDF"), PathBuf::from("scan.tiff")]);
+    assert!(parse(&["--help"]).unwrap().help);
+    assert!(parse(&["-V"]).unwrap().version);
+    // After --, names that look like options are files
+    assert_eq!(parse(&["--", "-draft.pdf"]).unwrap().files, vec![PathBuf::from("-draft.pdf")]);
+}
+
+#[test]
+fn rejects_unknown_options_and_file_types() {
+    assert!(parse(&["--fullscreen"]).is_err());
+    let e = parse(&["notes.docx"]).unwrap_err();
+    assert!(e.to_string().contains("notes.docx"), "{}", e);
+}
@@ -0,0 +1,48 @@
+//! Splitting paragraph items into lines
+
+use chonker3::lines::{self, WordBox};
+use chonker3::types::BoundingBox;
+use serde_json::json;
+
+fn word(text: &str, left: f64, top: f64) -> WordBox {
+    WordBox { text: text.to_string(), bbox: BoundingBox { left, top, width: 30.0, height: 10.0 } }
+}
+
+#[test]
+fn groups_words_into_lines() {
+    let words = vec![
+        word("lazy", 110.0, 115.0),
+        word("quick", 110.0, 100.0),
+        word("The", 72.0, 101.0),
+        word("the", 72.0, 114.0),
+    ];
+    let lines = lines::group_lines(words);
+    let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+    assert_eq!(texts, ["The quick", "the lazy"]);
+    assert_eq!(lines[0].bbox, BoundingBox { left: 72.0, top: 100.0, width: 68.0, height: 11.0 });
+}
+
+#[test]
+fn splits_paragraphs_but_not_tables() {
+    let mut data = json!({
+        "pages": [{ "width": 612.0, "height": 792.0 }],
+        "items": [
+            { "type": "TextItem", "page": 1, "content": "The quick the lazy",
+              "bbox": { "left": 70.0, "top": 98.0, "width": 80.0, "height": 30.0, "coord_origin": "TOPLEFT" } },
+            { "type": "TableItem", "page": 1, "content": "a\tb",
+              "bbox": { "left": 70.0, "top": 98.0, "width": 80.0, "height": 30.0, "coord_origin": "TOPLEFT" } },
+        ],
+    });
+    let split = lines::split_into_lines(&mut data, |_| vec![
+        word("The", 72.0, 100.0),
+        word("quick", 110.0, 100.0),
+        word("the", 72.0, 114.0),
+        word("lazy", 110.0, 114.0),
+    ]);
+    assert_eq!(split, 1);
+    let items = data["items"].as_array().unwrap();
+    let contents: Vec<&str> = items.iter().map(|i| i["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, ["The quick", "the lazy", "a\tb"]);
+    assert_eq!(items[1]["bbox"]["top"], 114.0);
+    assert_eq!(items[2]["index"], 2);
+}
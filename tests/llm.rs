@@ -0,0 +1,58 @@
+//! LLM post-processing requests and replies
+
+mod common;
+
+use chonker3::document::page_items;
+use chonker3::llm::{cleaned_texts, default_templates, prompt_request_body, reply_text, request_body, LlmSettings, LlmTask};
+use chonker3::patch::EditPatch;
+use common::fixture_json;
+use serde_json::json;
+
+#[test]
+fn request_numbers_the_items() {
+    let items = page_items(&fixture_json("simple.json"), 0, &EditPatch::default());
+    let settings = LlmSettings { endpoint: "https://api.example.com/v1/".to_string(), ..Default::default() };
+    assert_eq!(settings.completions_url(), "https://api.example.com/v1/chat/completions");
+
+    let body = request_body(&settings, LlmTask::Summarize, &items);
+    assert_eq!(body["model"], settings.model);
+    let prompt = body["messages"][1]["content"].as_str().unwrap();
+    assert!(prompt.contains(&format!("[1] {}", items[0].content.trim())));
+    assert!(prompt.contains(&format!("[{}] ", items.len())));
+}
+
+#[test]
+fn replies_and_errors_are_read() {
+    let reply = json!({ "choices": [{ "message": { "role": "assistant", "content": "  A short summary.\n" } }] });
+    assert_eq!(reply_text(&reply).unwrap(), "A short summary.");
+    let error = json!({ "error": { "message": "Invalid API key" } });
+    assert_eq!(reply_text(&error).unwrap_err().to_string(), "Invalid API key");
+    assert!(reply_text(&json!({})).is_err());
+}
+
+#[test]
+fn clean_up_keeps_only_changed_blocks() {
+    let items = page_items(&fixture_json("simple.json"), 0, &EditPatch::default());
+    assert!(items.len() >= 2);
+    let reply = format!(
+        "[1] {}\n[2] First line\nsecond line\n[99] not an item",
+        items[0].content.trim(),
+    );
+    let changes = cleaned_texts(&reply, &items);
+    assert_eq!(changes, vec![(1, "First line\nsecond line\n[99] not an item".to_string())]);
+}
+
+#[test]
+fn templates_default_and_prompt_the_request() {
+    let settings: LlmSettings = serde_json::from_value(json!({ "enabled": true, "model": "gpt-4o-mini" })).unwrap();
+    assert_eq!(settings.templates, default_templates());
+    let invoice = settings.templates.iter().find(|t| t.name == "Extract invoice fields to JSON").unwrap();
+    assert!(invoice.whole_page);
+    assert!(!settings.templates.iter().find(|t| t.name == "Translate selection").unwrap().whole_page);
+
+    let items = page_items(&fixture_json("simple.json"), 0, &EditPatch::default());
+    let body = prompt_request_body(&settings, &invoice.prompt, &items[..1]);
+    let prompt = body["messages"][1]["content"].as_str().unwrap();
+    assert!(prompt.starts_with(&invoice.prompt));
+    assert!(prompt.ends_with(&format!("[1] {}", items[0].content.trim())));
+}
@@ -0,0 +1,82 @@
+//! Recording-independent macro replay
+
+mod common;
+
+use chonker3::core::Session;
+use chonker3::macros::{ItemSelector, Macro, MacroStep, MatchBy, ReplayScope};
+use chonker3::types::{self, ItemType};
+
+fn session() -> Session {
+    let mut session = Session { page_count: 2, ..Default::default() };
+    session.set_extraction(common::fixture_json("two_column.json"), None);
+    session
+}
+
+fn selector(session: &Session, page: usize, id: &str, match_by: MatchBy) -> ItemSelector {
+    let data = session.extracted_data.as_ref().unwrap();
+    let items = chonker3::document::page_items(data, page, &session.to_patch());
+    let item = items.iter().find(|i| i.id == id).unwrap();
+    ItemSelector::from_item(item, &item.content, match_by)
+}
+
+#[test]
+fn replays_by_type_across_pages() {
+    let mut session = session();
+    let title = types::item_id(0, 72.0, 56.0);
+    let recorded = Macro {
+        steps: vec![MacroStep::SetType(selector(&session, 0, &title, MatchBy::SameType), ItemType::Text)],
+    };
+
+    // Only the title is a Title item
+    assert_eq!(recorded.replay(&mut session, ReplayScope::AllPages).unwrap(), 1);
+    assert_eq!(session.edits.type_overrides.get(&title), Some(&ItemType::Text));
+}
+
+#[test]
+fn region_matches_only_nearby_items() {
+    let mut session = session();
+    let left = types::item_id(0, 72.0, 92.0);
+    let recorded = Macro {
+        steps: vec![MacroStep::SetType(selector(&session, 0, &left, MatchBy::SameRegion), ItemType::Table)],
+    };
+
+    assert_eq!(recorded.replay(&mut session, ReplayScope::CurrentPage).unwrap(), 1);
+    assert_eq!(session.edits.type_overrides.len(), 1);
+    assert_eq!(session.edits.type_overrides.get(&left), Some(&ItemType::Table));
+}
+
+#[test]
+fn later_steps_see_earlier_edits() {
+    let mut session = session();
+    let right = types::item_id(0, 330.0, 92.0);
+    let recorded = Macro {
+        steps: vec![
+            MacroStep::Delete(selector(&session, 0, &right, MatchBy::SameText)),
+            // Would match the deleted item too if it were still there
+            MacroStep::Annotate(selector(&session, 0, &right, MatchBy::SameType), "body".to_string()),
+        ],
+    };
+
+    assert_eq!(recorded.replay(&mut session, ReplayScope::CurrentPage).unwrap(), 2);
+    assert!(session.edits.deletions.contains(&right));
+    assert_eq!(session.edits.annotations.len(), 1);
+}
+
+#[test]
+fn exports_expand_name_and_round_trip() {
+    let mut session = session();
+    session.pdf_path = Some("/tmp/field_notes.pdf".into());
+    let dir = std::env::temp_dir();
+    let template = dir.join(format!("{{name}}_{}.csv", std::process::id())).display().to_string();
+    let recorded = Macro { steps: vec![MacroStep::ExportCsv(template)] };
+
+    let json = serde_json::to_string(&recorded).unwrap();
+    let loaded: Macro = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, recorded);
+
+    loaded.replay(&mut session, ReplayScope::AllPages).unwrap();
+    let path = dir.join(format!("field_notes_{}.csv", std::process::id()));
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(csv.lines().count(), 6);
+}
@@ -0,0 +1,56 @@
+//! Memory guard decisions
+
+use chonker3::memory::{json_bytes, GuardAction, MemoryGuard, MemoryUsage, MIN_RENDER_SCALE};
+
+const MB: usize = 1024 * 1024;
+
+fn guard(budget_mb: usize) -> MemoryGuard {
+    let mut guard = MemoryGuard::default();
+    guard.set_budget_mb(budget_mb);
+    guard
+}
+
+#[test]
+fn halves_resolution_until_the_floor() {
+    let mut guard = guard(100);
+    let over = MemoryUsage { page_textures: 80 * MB, extraction: 40 * MB, ..Default::default() };
+
+    assert_eq!(guard.update(over), GuardAction::Evict { render_scale: 0.5 });
+    assert_eq!(guard.update(over), GuardAction::Evict { render_scale: 0.25 });
+    assert_eq!(guard.update(over), GuardAction::None);
+    assert_eq!(guard.render_scale, MIN_RENDER_SCALE);
+}
+
+#[test]
+fn restores_only_well_under_budget() {
+    let mut guard = guard(100);
+    guard.render_scale = 0.5;
+
+    // Textures would be 4x at full scale: 10 + 4 * 10 = 50 MB, under 60% of budget
+    let small = MemoryUsage { page_textures: 10 * MB, extraction: 10 * MB, ..Default::default() };
+    assert_eq!(guard.update(small), GuardAction::Restore { render_scale: 1.0 });
+
+    // 20 + 4 * 15 = 80 MB would come straight back over the threshold
+    guard.render_scale = 0.5;
+    let medium = MemoryUsage { page_textures: 15 * MB, extraction: 20 * MB, ..Default::default() };
+    assert_eq!(guard.update(medium), GuardAction::None);
+
+    // Nothing rendered yet, nothing to judge by
+    let evicted = MemoryUsage { extraction: 10 * MB, ..Default::default() };
+    assert_eq!(guard.update(evicted), GuardAction::None);
+}
+
+#[test]
+fn json_size_grows_with_content() {
+    let small = serde_json::json!({"items": [{"content": "a"}]});
+    let large = serde_json::json!({"items": [{"content": "a".repeat(10_000)}]});
+    assert!(json_bytes(&large) >= json_bytes(&small) + 9_999);
+}
+
+#[test]
+fn adds_up_the_usage_of_background_documents() {
+    let mut usage = MemoryUsage { page_textures: 10 * MB, galleys: MB, ..Default::default() };
+    usage += MemoryUsage { page_textures: 5 * MB, extraction: 2 * MB, pdf_bytes: 3 * MB, ..Default::default() };
+    assert_eq!(usage, MemoryUsage { page_textures: 15 * MB, galleys: MB, extraction: 2 * MB, pdf_bytes: 3 * MB, thumbnails: 0 });
+    assert_eq!(usage.total(), 21 * MB);
+}
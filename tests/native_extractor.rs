@@ -0,0 +1,68 @@
+//! Extraction from the PDF's text layer without Python
+
+mod common;
+
+use chonker3::core::bind_pdfium;
+use chonker3::document;
+use chonker3::extractor::{self, Capabilities, ExtractOptions, Extractor, NativeExtractor, PythonBackend};
+use chonker3::lines::WordBox;
+use chonker3::patch::EditPatch;
+use chonker3::types::{BoundingBox, ItemType};
+
+fn word(text: &str, left: f64, top: f64) -> WordBox {
+    WordBox { text: text.to_string(), bbox: BoundingBox { left, top, width: 30.0, height: 10.0 } }
+}
+
+#[test]
+fn writes_one_item_per_line_of_each_column() {
+    let words = vec![
+        word("Invoice", 72.0, 100.0),
+        word("number:", 104.0, 100.0),
+        // Far to the right: the other column
+        word("Total", 400.0, 101.0),
+        word("Due", 72.0, 120.0),
+        word("soon", 104.0, 120.0),
+    ];
+    let data = extractor::text_layer_page(1, 612.0, 792.0, words);
+    assert_eq!(data["pages"][0]["width"], 612.0);
+    assert_eq!(data["metadata"]["extractor"], "native");
+
+    let items = document::edited_page_items(&data, 0, &EditPatch::new(None));
+    let texts: Vec<&str> = items.iter().map(|item| item.content.as_str()).collect();
+    assert_eq!(texts, ["Invoice number:", "Total", "Due soon"]);
+    assert_eq!(items[0].item_type, ItemType::FormLabel);
+    assert_eq!(items[2].item_type, ItemType::Text);
+    assert_eq!(data["items"][0]["bbox"]["right"], 134.0);
+    assert_eq!(data["items"][0]["provenance"][0]["name"], "native");
+}
+
+#[test]
+fn a_page_without_a_text_layer_has_no_items() {
+    let data = extractor::text_layer_page(3, 612.0, 792.0, Vec::new());
+    assert_eq!(data["items"].as_array().unwrap().len(), 0);
+    assert_eq!(data["pages"][0]["page_number"], 3);
+}
+
+#[test]
+fn names_python_backends_and_its_capabilities() {
+    assert_eq!(PythonBackend::from_arg("docling"), Some(PythonBackend::Docling));
+    assert_eq!(PythonBackend::from_arg("native"), None);
+    let data = serde_json::json!({ "metadata": { "extractor": "native" } });
+    assert_eq!(Capabilities::of_extraction(&data), NativeExtractor.capabilities());
+}
+
+#[test]
+fn extracts_fixture_without_python() {
+    if bind_pdfium().is_err() {
+        eprintln!("pdfium not available, skipping");
+        return;
+    }
+
+    // An empty chain keeps pages without text away from Python
+    let opts = ExtractOptions { fallback_chain: Vec::new(), ..Default::default() };
+    let document = NativeExtractor.extract(&common::fixture_path("two_column.pdf"), &opts).unwrap();
+    let expected = common::fixture_json("two_column.json");
+    assert_eq!(document.page_count(), expected["pages"].as_array().unwrap().len());
+    assert_eq!(document.data["metadata"]["extractor"], "native");
+    assert!(document.json_path.exists());
+}
@@ -0,0 +1,61 @@
+//! Locale-aware number parsing
+
+mod common;
+
+use chonker3::normalize::{detect_locale, normalize_extraction, parse_number, NumberLocale};
+
+fn value(text: &str, locale: NumberLocale) -> Option<f64> {
+    parse_number(text, locale).map(|n| n.value)
+}
+
+#[test]
+fn parses_both_notations() {
+    assert_eq!(value("1.234,56", NumberLocale::Auto), Some(1234.56));
+    assert_eq!(value("1,234.56", NumberLocale::Auto), Some(1234.56));
+    assert_eq!(value("1'234.56", NumberLocale::Auto), Some(1234.56));
+    assert_eq!(value("12 345,6", NumberLocale::Auto), Some(12345.6));
+    assert_eq!(value("1.234.567", NumberLocale::Auto), Some(1234567.0));
+}
+
+#[test]
+fn locale_resolves_single_separator() {
+    assert_eq!(value("1,234", NumberLocale::DecimalComma), Some(1.234));
+    assert_eq!(value("1,234", NumberLocale::DecimalPoint), Some(1234.0));
+    // Without a locale three trailing digits read as grouping
+    assert_eq!(value("1,234", NumberLocale::Auto), Some(1234.0));
+    assert_eq!(value("12,5", NumberLocale::Auto), Some(12.5));
+}
+
+#[test]
+fn currency_sign_and_percent() {
+    let n = parse_number("€ 1.234,50", NumberLocale::Auto).unwrap();
+    assert_eq!((n.value, n.currency), (1234.5, Some("EUR")));
+    let n = parse_number("(US$ 99.95)", NumberLocale::Auto).unwrap();
+    assert_eq!((n.value, n.currency), (-99.95, Some("USD")));
+    assert!(parse_number("12,5 %", NumberLocale::Auto).unwrap().percent);
+    assert_eq!(value("250.00-", NumberLocale::Auto), Some(-250.0));
+}
+
+#[test]
+fn rejects_non_numbers() {
+    assert_eq!(value("Total", NumberLocale::Auto), None);
+    assert_eq!(value("2023 2024", NumberLocale::Auto), None);
+    assert_eq!(value("1,2,3.4.5", NumberLocale::Auto), None);
+}
+
+#[test]
+fn detects_document_locale() {
+    assert_eq!(detect_locale(["1.234,56", "987,00"].into_iter()), NumberLocale::DecimalComma);
+    assert_eq!(detect_locale(["$1,234.56", "Total 9.5"].into_iter()), NumberLocale::DecimalPoint);
+    assert_eq!(detect_locale(["no numbers here"].into_iter()), NumberLocale::Auto);
+}
+
+#[test]
+fn stores_values_on_items() {
+    let mut data = common::fixture_json("simple.json");
+    assert_eq!(normalize_extraction(&mut data, NumberLocale::Auto), NumberLocale::DecimalComma);
+    let total = data["items"].as_array().unwrap().iter().find(|i| i["content"] == "2.221,56 EUR").unwrap();
+    assert_eq!(total["normalized"]["value"], 2221.56);
+    assert_eq!(total["normalized"]["currency"], "EUR");
+    assert_eq!(data["metadata"]["number_locale"], "DecimalComma");
+}
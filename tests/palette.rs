@@ -0,0 +1,34 @@
+//! Highlight palettes
+
+use chonker3::palette::{BuiltinPalette, Palette};
+use chonker3::settings::Settings;
+
+#[test]
+fn builtin_palettes_are_recognized_until_customized() {
+    for builtin in BuiltinPalette::ALL {
+        assert_eq!(builtin.palette().builtin(), Some(builtin));
+    }
+    let mut palette = Palette::default();
+    assert_eq!(palette.builtin(), Some(BuiltinPalette::Standard));
+    palette.search_highlight = [1, 2, 3, 255];
+    assert_eq!(palette.builtin(), None);
+}
+
+#[test]
+fn palette_round_trips_through_settings() {
+    let mut settings = Settings { palette: BuiltinPalette::Deuteranopia.palette(), ..Default::default() };
+    settings.palette.annotation = [10, 20, 30, 255];
+    let json = serde_json::to_string(&settings).unwrap();
+    let loaded: Settings = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.palette, settings.palette);
+
+    // Settings saved before palettes existed get the standard one
+    let old: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(old.palette, Palette::default());
+
+    // ...and palettes saved before the overflow marker get its standard color
+    let mut value = serde_json::to_value(BuiltinPalette::HighContrast.palette()).unwrap();
+    value.as_object_mut().unwrap().remove("overflow");
+    let palette: Palette = serde_json::from_value(value).unwrap();
+    assert_eq!(palette.overflow, Palette::default().overflow);
+}
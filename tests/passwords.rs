@@ -0,0 +1,12 @@
+//! Keyring accounts for remembered PDF passwords
+
+use chonker3::passwords::account;
+
+#[test]
+fn accounts_follow_the_file_contents() {
+    let statement = b"%PDF-1.7 statement";
+    assert_eq!(account(statement), account(b"%PDF-1.7 statement"));
+    assert_ne!(account(statement), account(b"%PDF-1.7 another statement"));
+    assert!(account(statement).starts_with("pdf-"));
+    assert_eq!(account(statement).len(), "pdf-".len() + 32);
+}
@@ -0,0 +1,21 @@
+//! PDF export size options
+
+use chonker3::pdf_output::{self, PdfOutput};
+
+#[test]
+fn downsamples_only_above_the_cap() {
+    // A 2550x3300 scan filling a letter page is 300 dpi
+    assert_eq!(pdf_output::target_pixels((2550, 3300), (612.0, 792.0), 150), (1275, 1650));
+    assert_eq!(pdf_output::target_pixels((1275, 1650), (612.0, 792.0), 150), (1275, 1650));
+    // Flipped placements have negative extents
+    assert_eq!(pdf_output::target_pixels((600, 600), (-72.0, 72.0), 300), (300, 300));
+    // Degenerate placements are left alone
+    assert_eq!(pdf_output::target_pixels((600, 600), (0.0, 72.0), 300), (600, 600));
+}
+
+#[test]
+fn older_settings_get_the_defaults() {
+    let output: PdfOutput = serde_json::from_str("{}").unwrap();
+    assert_eq!(output, PdfOutput::default());
+    assert!(!output.recompress_images && !output.linearize);
+}
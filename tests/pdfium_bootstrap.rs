@@ -0,0 +1,48 @@
+//! pdfium download checksum and unpacking
+
+use chonker3::checksum::sha256_hex;
+use chonker3::pdfium_bootstrap::{extract_library, pinned_checksum, release_url, verify_sha256};
+
+#[test]
+fn verifies_sha256_checksums() {
+    let bytes = b"abc";
+    let hex = sha256_hex(bytes);
+    assert_eq!(hex, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+    assert!(verify_sha256(bytes, &hex).is_ok());
+    assert!(verify_sha256(bytes, &hex.to_uppercase()).is_ok());
+    assert!(verify_sha256(b"abd", &hex).is_err());
+}
+
+#[test]
+fn reads_pinned_checksums() {
+    let checksums = "1111  pdfium-linux-x64.tgz\n2222 *pdfium-mac-arm64.tgz\n";
+    assert_eq!(pinned_checksum(checksums, "pdfium-linux-x64.tgz"), Some("1111"));
+    assert_eq!(pinned_checksum(checksums, "pdfium-mac-arm64.tgz"), Some("2222"));
+    assert_eq!(pinned_checksum(checksums, "pdfium-win-x64.tgz"), None);
+    assert!(release_url("pdfium-linux-x64.tgz").ends_with("/releases/download/chromium%2F7543/pdfium-linux-x64.tgz"));
+}
+
+#[test]
+fn unpacks_the_platform_library() {
+    // One library per platform, laid out as in the pdfium-binaries archives
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast()));
+    for path in ["./LICENSE", "./bin/pdfium.dll", "./lib/libpdfium.so", "./lib/libpdfium.dylib"] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(path.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, path.as_bytes()).unwrap();
+    }
+    let archive = builder.into_inner().unwrap().finish().unwrap();
+
+    let dest = std::env::temp_dir().join(format!("chonker3_pdfium_{}", std::process::id()));
+    let library = extract_library(&archive, &dest).unwrap();
+    let file_name = library.file_name().unwrap().to_string_lossy().to_string();
+    let contents = std::fs::read_to_string(&library).unwrap();
+    std::fs::remove_dir_all(&dest).ok();
+
+    assert!(contents.ends_with(&file_name), "{} holds {}", file_name, contents);
+    assert_ne!(file_name, "LICENSE");
+    assert!(extract_library(&archive[..archive.len() / 2], &dest).is_err());
+}
@@ -0,0 +1,76 @@
+//! Finding, masking and redacting personal data
+
+use chonker3::core::Session;
+use chonker3::pii::{self, CustomPattern, PiiDetector, PiiKind, PiiSettings};
+use serde_json::{json, Value};
+
+fn found(detector: &PiiDetector, text: &str) -> Vec<(PiiKind, String)> {
+    detector.find(text).into_iter().map(|(kind, _, range)| (kind, text[range].to_string())).collect()
+}
+
+#[test]
+fn finds_ssns_cards_and_emails_but_not_lookalikes() {
+    let detector = PiiDetector::new(&PiiSettings::default()).unwrap();
+    assert_eq!(found(&detector, "SSN 123-45-6789, card 4111 1111 1111 1111, mail ana.diaz@example.com"), vec![
+        (PiiKind::Ssn, "123-45-6789".to_string()),
+        (PiiKind::CreditCard, "4111 1111 1111 1111".to_string()),
+        (PiiKind::Email, "ana.diaz@example.com".to_string()),
+    ]);
+    // Never-issued SSNs and numbers failing the Luhn check
+    assert!(found(&detector, "Ref 000-12-3456 / 666-12-3456, order 4111 1111 1111 1112").is_empty());
+    // A card running into a date or a reference is still found, on its own
+    assert_eq!(found(&detector, "Paid 4111 1111 1111 1111 2024 07"), vec![(PiiKind::CreditCard, "4111 1111 1111 1111".to_string())]);
+    assert_eq!(found(&detector, "Ref 12 4111-1111-1111-1111"), vec![(PiiKind::CreditCard, "4111-1111-1111-1111".to_string())]);
+    assert_eq!(found(&detector, "1234 5678 4111 1111 1111 1111"), vec![(PiiKind::CreditCard, "4111 1111 1111 1111".to_string())]);
+    assert!(pii::luhn("79927398713"));
+    assert!(!pii::luhn("79927398710"));
+
+    let off = PiiSettings { ssn: false, credit_cards: false, emails: false, ..Default::default() };
+    assert!(PiiDetector::new(&off).unwrap().is_empty());
+}
+
+#[test]
+fn names_and_patterns_come_from_the_settings() {
+    let settings = PiiSettings {
+        names: vec!["Ana  Díaz".to_string(), String::new(), "Bo".to_string()],
+        patterns: vec![CustomPattern { label: "Passport".to_string(), pattern: r"\bA\d{8}\b".to_string() }],
+        ..Default::default()
+    };
+    let detector = PiiDetector::new(&settings).unwrap();
+    let hits = detector.find("Passport A12345678 of ANA DÍAZ; Bob signed");
+    let labels: Vec<&str> = hits.iter().map(|(_, label, _)| label.as_str()).collect();
+    assert_eq!(labels, ["Passport", "Name"], "whole words only, so Bob isn't Bo");
+
+    let bad = PiiSettings { patterns: vec![CustomPattern { label: "Broken".to_string(), pattern: "(".to_string() }], ..Default::default() };
+    assert!(format!("{:#}", PiiDetector::new(&bad).unwrap_err()).contains("Broken"));
+}
+
+#[test]
+fn masks_text_and_writes_redaction_boxes() {
+    assert_eq!(pii::mask("SSN 123-45-6789, 4111", &[4..15, 17..21]), "SSN ███-██-████, ████");
+
+    let item = |top: f64, content: &str| json!({
+        "page": 1, "type": "TextItem", "content": content,
+        "bbox": { "left": 72.0, "top": top, "width": 200.0, "height": 12.0 },
+    });
+    let mut session = Session::default();
+    session.set_extraction(json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [item(100.0, "Contact: ana@example.com"), item(200.0, "Nothing here"), item(300.0, "SSN 123-45-6789")],
+    }), None);
+    let detector = PiiDetector::new(&PiiSettings::default()).unwrap();
+    assert_eq!(session.pii_hits(&detector).len(), 2);
+
+    let path = std::env::temp_dir().join(format!("chonker3_redactions_{}.json", std::process::id()));
+    assert_eq!(session.export_redactions(&path, &detector).unwrap(), 2);
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written["format"], pii::REDACTIONS_FORMAT);
+    assert_eq!(written["redactions"][1]["bbox"]["top"], 300.0);
+    assert_eq!(written["redactions"][1]["reasons"], json!(["SSN"]));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(session.mask_pii(&detector), 2);
+    assert!(session.pii_hits(&detector).is_empty());
+    let items = chonker3::document::edited_page_items(session.extracted_data.as_ref().unwrap(), 0, &session.to_patch());
+    assert_eq!(items[0].content, "Contact: ███@███████.███");
+}
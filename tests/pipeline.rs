@@ -0,0 +1,82 @@
+//! Batch export layout templates and manifests
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+
+use chonker3::pipeline::{self, ExportPipeline, ManifestFormat, OutputFormat, PipelineOutput};
+use chonker3::workspace::WorkspaceDocument;
+use serde_json::json;
+
+#[test]
+fn expands_layout_templates() {
+    assert_eq!(pipeline::expand_template("{stem}/{page}.md", "report", Some(2)).unwrap(), PathBuf::from("report/3.md"));
+    assert_eq!(pipeline::expand_template("out/{stem}.json", "report", None).unwrap(), PathBuf::from("out/report.json"));
+    assert!(pipeline::is_per_page("{stem}/{page}.md"));
+    // Unknown placeholders, a page outside per-page templates, and paths leaving the folder
+    assert!(pipeline::expand_template("{name}.md", "report", None).is_err());
+    assert!(pipeline::expand_template("{page}.md", "report", None).is_err());
+    assert!(pipeline::expand_template("../{stem}.md", "report", None).is_err());
+    assert!(pipeline::expand_template("/tmp/{stem}.md", "report", None).is_err());
+}
+
+#[test]
+fn exports_documents_with_checksummed_manifest() {
+    let dir = std::env::temp_dir().join(format!("chonker3_pipeline_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let json_path = dir.join("report.extraction.json");
+    let item = |page: u64, content: &str| json!({
+        "page": page, "type": "TextItem", "content": content,
+        "bbox": { "left": 72.0, "top": 100.0, "width": 200.0, "height": 12.0 },
+    });
+    std::fs::write(&json_path, json!({
+        "metadata": { "extractor": "simple" },
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }, { "page": 2, "width": 612.0, "height": 792.0 }],
+        "items": [item(1, "First page"), item(2, "Second page")],
+    }).to_string()).unwrap();
+    let doc = WorkspaceDocument {
+        path: PathBuf::from("/nonexistent/report.pdf"),
+        tags: BTreeSet::new(),
+        metadata: Default::default(),
+        extracted_json: Some(json_path),
+        fingerprint: None,
+        bookmarks: BTreeSet::new(),
+        page_notes: Default::default(),
+        page_backends: Default::default(),
+        comments: Default::default(),
+        regions: Default::default(),
+        recent_searches: Default::default(),
+        added: String::new(),
+    };
+    let layout = ExportPipeline {
+        outputs: vec![
+            PipelineOutput { format: OutputFormat::Markdown, template: "{stem}/{page}.md".to_string() },
+            PipelineOutput { format: OutputFormat::Csv, template: "{stem}.csv".to_string() },
+        ],
+        manifest: ManifestFormat::Csv,
+        field_mapping: Default::default(),
+        webhook: Default::default(),
+        upload_to: None,
+        remote: None,
+        stamping: Default::default(),
+    };
+
+    let out = dir.join("out");
+    let mut written = HashSet::new();
+    let entry = pipeline::export_document(&out, &doc, &layout, &mut written).unwrap();
+    assert_eq!((entry.pages, entry.items, entry.extractor.as_str()), (2, 2, "simple"));
+    assert_eq!(entry.input_sha256, None);
+    let paths: Vec<&str> = entry.outputs.iter().map(|o| o.path.as_str()).collect();
+    assert_eq!(paths, ["report/1.md", "report/2.md", "report.csv"]);
+
+    let second_page = std::fs::read_to_string(out.join("report/2.md")).unwrap();
+    assert!(second_page.contains("Second page") && !second_page.contains("First page"));
+    assert_eq!(entry.outputs[1].sha256, chonker3::checksum::sha256_hex(second_page.as_bytes()));
+
+    // The same document again would overwrite its outputs
+    assert!(pipeline::export_document(&out, &doc, &layout, &mut written).is_err());
+
+    let manifest = pipeline::manifest_csv(&[entry]);
+    assert_eq!(manifest.lines().count(), 4);
+    assert!(manifest.lines().nth(2).unwrap().starts_with("/nonexistent/report.pdf,,simple,2,2,report/2.md,Markdown,2,"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,17 @@
+//! Python environment checks
+
+use chonker3::python_env::{check, venv_python, EnvStatus};
+
+#[test]
+fn missing_venv_needs_setup() {
+    let venv = std::env::temp_dir().join(format!("chonker3_no_venv_{}", std::process::id()));
+    assert!(venv_python(&venv).starts_with(&venv));
+    assert_eq!(check(&venv), EnvStatus::Missing);
+}
+
+#[test]
+fn describes_missing_packages() {
+    let status = EnvStatus::Incomplete(vec!["docling".to_string(), "pypdfium2".to_string()]);
+    assert_eq!(status.describe(), "Missing packages: docling, pypdfium2");
+    assert_eq!(EnvStatus::Ready.describe(), "Ready");
+}
@@ -0,0 +1,22 @@
+//! Rendering quality presets
+
+use chonker3::settings::{QualityPreset, Settings};
+
+#[test]
+fn presets_are_recognized_from_the_saved_values() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.quality_preset(), Some(QualityPreset::Balanced));
+
+    settings.apply_quality_preset(QualityPreset::BatterySaver);
+    assert_eq!((settings.prefetch_depth, settings.texture_scale), (0, 0.75));
+    let saved: Settings = serde_json::from_str(&serde_json::to_string(&settings).unwrap()).unwrap();
+    assert_eq!(saved.quality_preset(), Some(QualityPreset::BatterySaver));
+
+    // Changing one value by hand leaves the presets
+    settings.memory_budget_mb += 256;
+    assert_eq!(settings.quality_preset(), None);
+
+    // Settings saved before the presets existed are Balanced
+    let old: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(old.quality_preset(), Some(QualityPreset::Balanced));
+}
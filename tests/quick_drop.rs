@@ -0,0 +1,32 @@
+//! Quick-drop extraction queue
+
+use std::time::{Duration, Instant};
+use chonker3::quick_drop::{DropQueue, QueueEvent, QueueSummary};
+
+#[test]
+fn reports_each_file_and_the_end_of_the_run() {
+    let mut queue = DropQueue::start(Default::default(), Default::default(), || {});
+    assert!(queue.push("notes.docx".into()).is_err());
+    let missing = std::env::temp_dir().join(format!("chonker3_missing_scan_{}.png", std::process::id()));
+    queue.push(missing.clone()).unwrap();
+    assert_eq!(queue.pending, 1);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let (mut events, mut finished) = (Vec::new(), None);
+    while finished.is_none() && Instant::now() < deadline {
+        let (new_events, summary) = queue.poll();
+        events.extend(new_events);
+        finished = summary;
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(finished, Some(QueueSummary { extracted: 0, failed: 1 }));
+    assert!(matches!(&events[0], QueueEvent::Started(path) if *path == missing));
+    assert!(matches!(&events[1], QueueEvent::Finished { result: Err(_), .. }));
+    assert_eq!((queue.pending, queue.current.is_none()), (0, true));
+}
+
+#[test]
+fn summarizes_a_run() {
+    assert_eq!(QueueSummary { extracted: 1, failed: 0 }.describe(), "Extracted 1 document");
+    assert_eq!(QueueSummary { extracted: 3, failed: 2 }.describe(), "Extracted 3, 2 failed");
+}
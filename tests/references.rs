@@ -0,0 +1,61 @@
+//! Reference lists, citations and BibTeX
+
+use chonker3::patch::EditPatch;
+use chonker3::references::{self, CitationKey};
+use serde_json::json;
+
+#[test]
+fn parses_author_year_and_numbered_entries() {
+    let (authors, year, title) = references::parse_reference("Smith, J., Jones, K. (2019). Deep parsing of tables. Journal of Docs, 4.");
+    assert_eq!(authors, vec!["Smith, J.", "Jones, K."]);
+    assert_eq!(year.as_deref(), Some("2019"));
+    assert_eq!(title.as_deref(), Some("Deep parsing of tables"));
+
+    let (authors, year, title) = references::parse_reference("J. Smith and K. Jones. Layout analysis. In Proc. ICDAR, 2018.");
+    assert_eq!(authors, vec!["J. Smith", "K. Jones"]);
+    assert_eq!(year.as_deref(), Some("2018"));
+    assert_eq!(title.as_deref(), Some("Layout analysis"));
+    assert_eq!(references::surname(&authors[0]), "Smith");
+}
+
+#[test]
+fn finds_numeric_and_author_year_citations() {
+    let found = references::find_citations("As shown in [2, 4-6] and (Smith et al., 2019; Jones 2020a), not (see above) or [Fig. 3].");
+    let keys: Vec<Vec<CitationKey>> = found.into_iter().map(|(_, keys)| keys).collect();
+    assert_eq!(keys, vec![
+        vec![CitationKey::Number(2), CitationKey::Number(4), CitationKey::Number(5), CitationKey::Number(6)],
+        vec![
+            CitationKey::AuthorYear { author: "Smith".to_string(), year: "2019".to_string() },
+            CitationKey::AuthorYear { author: "Jones".to_string(), year: "2020a".to_string() },
+        ],
+    ]);
+}
+
+#[test]
+fn links_citations_to_the_reference_section() {
+    let item = |top: f64, kind: &str, content: &str| json!({
+        "page": 1, "type": kind, "content": content,
+        "bbox": { "left": 72.0, "top": top, "width": 400.0, "height": 12.0 },
+    });
+    let data = json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            item(72.0, "TextItem", "Tables are hard [1]. Forms too [2, 3]."),
+            item(200.0, "SectionHeaderItem", "5. References"),
+            item(220.0, "TextItem", "[1] A. Author. First paper. 2001.\n[2] B. Writer. Second paper. 2002."),
+        ],
+    });
+    let found = references::collect(&data, &EditPatch::default());
+    assert_eq!(found.references.len(), 2);
+    assert_eq!(found.references[1].label, Some(2));
+    assert_eq!(found.citations.len(), 2);
+    assert_eq!(found.citations[0].references, vec![0]);
+    // [3] has no entry; [2] still resolves
+    assert_eq!(found.citations[1].references, vec![1]);
+    assert_eq!(found.citations_of(1).count(), 1);
+
+    let bibtex = references::bibtex(&found.references);
+    assert!(bibtex.contains("@misc{author2001first,"));
+    assert!(bibtex.contains("  title = {Second paper},"));
+    assert_eq!(bibtex.matches("@misc").count(), 2);
+}
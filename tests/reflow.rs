@@ -0,0 +1,68 @@
+//! Reflowed print layout
+
+use chonker3::patch::EditPatch;
+use chonker3::reflow::{self, Block, PaperSize, PrintLayout};
+use chonker3::types::ItemType;
+use serde_json::json;
+
+fn block(text: &str, item_type: ItemType) -> Block {
+    Block { text: text.to_string(), item_type }
+}
+
+#[test]
+fn wraps_to_the_text_width() {
+    let text = "the quick brown fox jumps over the lazy dog";
+    let lines = reflow::wrap(text, 10.0, 100.0);
+    assert!(lines.len() > 1);
+    assert_eq!(lines.join(" "), text);
+    assert!(lines.iter().all(|line| reflow::text_width(line, 10.0) <= 100.0));
+
+    // Hard breaks stay, and an overlong word gets its own line
+    assert_eq!(reflow::wrap("a\nb", 10.0, 100.0), vec!["a", "b"]);
+    assert_eq!(reflow::wrap("x Supercalifragilistic", 10.0, 40.0), vec!["x", "Supercalifragilistic"]);
+}
+
+#[test]
+fn paginates_within_the_margins() {
+    let layout = PrintLayout { paper: PaperSize::Letter, margin: 72.0, font_size: 12.0 };
+    let blocks: Vec<Block> = (0..60).map(|i| block(&format!("Paragraph {}", i), ItemType::Text)).collect();
+    let pages = reflow::paginate(&blocks, &layout);
+    assert!(pages.len() > 1);
+    let text_height = 792.0 - 2.0 * 72.0;
+    for page in &pages {
+        assert!(page.iter().all(|line| line.top + line.font_size <= text_height));
+    }
+    let count: usize = pages.iter().map(|p| p.len()).sum();
+    assert_eq!(count, 60);
+}
+
+#[test]
+fn headings_move_over_with_their_paragraph() {
+    let layout = PrintLayout::default();
+    // Enough filler that the heading alone would still fit at the foot of page 1
+    let mut blocks: Vec<Block> = (0..34).map(|_| block("Filler", ItemType::Text)).collect();
+    blocks.push(block("Heading", ItemType::Header));
+    blocks.push(block("Body", ItemType::Text));
+    let pages = reflow::paginate(&blocks, &layout);
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].len(), 34);
+    let texts: Vec<&str> = pages[1].iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(texts, vec!["Heading", "Body"]);
+}
+
+#[test]
+fn blocks_follow_edits_and_skip_pictures() {
+    let data = json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            { "page": 1, "type": "TitleItem", "content": "Title", "bbox": { "left": 72.0, "top": 72.0, "width": 200.0, "height": 20.0 } },
+            { "page": 1, "type": "PictureItem", "content": "", "bbox": { "left": 72.0, "top": 100.0, "width": 200.0, "height": 200.0 } },
+            { "page": 1, "type": "TextItem", "content": "Body", "bbox": { "left": 72.0, "top": 320.0, "width": 200.0, "height": 12.0 } },
+        ],
+    });
+    let mut edits = EditPatch::default();
+    let body = chonker3::types::item_id(0, 72.0, 320.0);
+    edits.text_overrides.insert(body, "Edited body".to_string());
+    let blocks = reflow::blocks(&data, &edits);
+    assert_eq!(blocks, vec![block("Title", ItemType::Title), block("Edited body", ItemType::Text)]);
+}
@@ -0,0 +1,58 @@
+//! Named regions
+
+use chonker3::core::Session;
+use chonker3::patch::EditPatch;
+use chonker3::regions::{self, NamedRegion};
+use chonker3::types::BoundingBox;
+use serde_json::{json, Value};
+
+fn text(top: f64, left: f64, content: &str) -> Value {
+    json!({ "page": 1, "type": "TextItem", "content": content, "bbox": { "left": left, "top": top, "width": 60.0, "height": 12.0 } })
+}
+
+fn data() -> Value {
+    json!({
+        "pages": [{ "width": 612.0, "height": 792.0 }],
+        "items": [
+            text(72.0, 400.0, "Invoice"),
+            text(72.0, 470.0, "No. 4711"),
+            text(90.0, 400.0, "Due 2024-05-01"),
+            text(300.0, 72.0, "Body text outside the region"),
+        ],
+    })
+}
+
+fn region(name: &str) -> NamedRegion {
+    NamedRegion { name: name.to_string(), page: 0, bbox: BoundingBox { left: 390.0, top: 60.0, width: 200.0, height: 50.0 } }
+}
+
+#[test]
+fn reads_the_items_inside_in_reading_order() {
+    let fields = regions::fields(&data(), &EditPatch::default(), &[region("Invoice number")]);
+    assert_eq!(fields[0].text, "Invoice No. 4711\nDue 2024-05-01");
+
+    let exported = regions::fields_json(Some("invoice.pdf"), &fields);
+    assert_eq!(exported["fields"]["Invoice number"], "Invoice No. 4711\nDue 2024-05-01");
+    assert_eq!(exported["pages"]["Invoice number"], 1);
+}
+
+#[test]
+fn names_are_unique_and_regions_follow_page_moves() {
+    let mut session = Session { extracted_data: Some(data()), ..Default::default() };
+    let bbox = region("x").bbox;
+    session.add_region("Total", 0, bbox.clone()).unwrap();
+    assert!(session.add_region(" total ", 0, bbox.clone()).is_err());
+    assert!(session.add_region("  ", 0, bbox.clone()).is_err());
+    session.add_region("Signature", 0, bbox).unwrap();
+    assert!(session.rename_region(1, "TOTAL").is_err());
+    session.rename_region(0, "Total due").unwrap();
+
+    // Regions travel in the patch
+    let patch = session.to_patch();
+    assert_eq!(patch.regions.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["Total due", "Signature"]);
+
+    session.edits.remap_pages(|page| Some(page + 2));
+    assert!(session.edits.regions.iter().all(|r| r.page == 2));
+    session.edits.remap_pages(|_| None);
+    assert!(session.edits.regions.is_empty());
+}
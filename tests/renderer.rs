@@ -0,0 +1,118 @@
+//! Page re-render debouncing, prefetching, canvas position indicators and width fitting
+
+use std::time::{Duration, Instant};
+use chonker3::renderer::{jump_delta, page_pixel_size, prefetch_pages, visible_span, Overflow, RenderTarget, ResizeDebounce, TextDetail, TextSelection};
+
+fn target(width: f32, pixels_per_point: f32) -> RenderTarget {
+    RenderTarget { width, pixels_per_point }
+}
+
+#[test]
+fn waits_for_resize_to_settle() {
+    let start = Instant::now();
+    let mut debounce = ResizeDebounce::default();
+    assert!(!debounce.update(target(600.0, 1.0), start), "nothing rendered yet");
+
+    debounce.rendered(target(600.0, 1.0));
+    assert!(!debounce.update(target(600.4, 1.0), start));
+    assert!(!debounce.is_pending());
+
+    // Still dragging: each new width restarts the wait
+    assert!(!debounce.update(target(650.0, 1.0), start));
+    assert!(!debounce.update(target(700.0, 1.0), start + Duration::from_millis(200)));
+    assert!(!debounce.update(target(700.0, 1.0), start + Duration::from_millis(300)));
+    assert!(debounce.update(target(700.0, 1.0), start + Duration::from_millis(500)));
+
+    debounce.rendered(target(700.0, 1.0));
+    assert!(!debounce.is_pending());
+}
+
+#[test]
+fn dpi_change_rerenders() {
+    let start = Instant::now();
+    let mut debounce = ResizeDebounce::default();
+    debounce.rendered(target(600.0, 1.0));
+    assert!(!debounce.update(target(600.0, 2.0), start));
+    assert!(debounce.update(target(600.0, 2.0), start + Duration::from_secs(1)));
+
+    // Resizing back before it settles cancels the re-render
+    debounce.rendered(target(600.0, 2.0));
+    assert!(!debounce.update(target(500.0, 2.0), start));
+    assert!(!debounce.update(target(600.0, 2.0), start + Duration::from_secs(1)));
+    assert!(!debounce.is_pending());
+}
+
+#[test]
+fn position_indicator_tracks_the_view() {
+    // Page 1000pt tall at y=0, view shows 200..450
+    assert_eq!(visible_span(0.0, 1000.0, 200.0, 250.0), Some((0.2, 0.45)));
+    // Scrolled past the top and bottom
+    assert_eq!(visible_span(100.0, 1000.0, 0.0, 300.0), Some((0.0, 0.2)));
+    assert_eq!(visible_span(-900.0, 1000.0, 0.0, 300.0), Some((0.9, 1.0)));
+    // Whole page in view: no indicator
+    assert_eq!(visible_span(50.0, 200.0, 0.0, 300.0), None);
+
+    // Jumping to the middle of the page centers it in the view
+    let delta = jump_delta(0.0, 1000.0, 200.0, 250.0, 0.5);
+    assert_eq!(0.0 + delta + 500.0, 200.0 + 125.0);
+}
+
+#[test]
+fn overflow_is_detected_past_the_box() {
+    // One line that fits, and a paragraph whose rows fill its tall box
+    assert_eq!(Overflow::detect(1, 1, false, 14.0, 12.0), None);
+    assert_eq!(Overflow::detect(1, 4, false, 56.0, 50.0), None);
+    // A one-line box whose text wrapped onto a second row
+    assert_eq!(Overflow::detect(1, 2, false, 28.0, 14.0), Some(Overflow::Wrapped { rows: 2 }));
+    // Hard line breaks aren't wrapping
+    assert_eq!(Overflow::detect(2, 2, false, 28.0, 14.0), None);
+    // Text cut off at the row limit
+    assert_eq!(Overflow::detect(1, 10, true, 140.0, 200.0), Some(Overflow::Clipped));
+}
+
+#[test]
+fn fits_lines_to_their_box() {
+    use chonker3::settings::WidthFitting;
+
+    // 11 characters, 100pt natural, 120pt box: 2pt more between each pair
+    assert_eq!(WidthFitting::LetterSpacing.fit(12.0, 100.0, 120.0, 11), (12.0, 2.0));
+    assert_eq!(WidthFitting::Scale.fit(12.0, 100.0, 110.0, 11), (12.0 * 1.1, 0.0));
+    assert_eq!(WidthFitting::Off.fit(12.0, 100.0, 120.0, 11), (12.0, 0.0));
+
+    // Wild boxes are capped rather than followed
+    let (_, spacing) = WidthFitting::LetterSpacing.fit(12.0, 100.0, 1000.0, 11);
+    assert_eq!(spacing, 6.0);
+    let (size, _) = WidthFitting::Scale.fit(12.0, 100.0, 10.0, 11);
+    assert!((size - 12.0 * 0.7).abs() < 1e-4);
+}
+
+#[test]
+fn prefetches_in_the_reading_direction() {
+    assert_eq!(prefetch_pages(4, true, 10, 3), vec![5, 6, 7, 3]);
+    assert_eq!(prefetch_pages(4, false, 10, 2), vec![3, 2, 5]);
+    // Clipped at both ends of the document
+    assert_eq!(prefetch_pages(8, true, 10, 3), vec![9, 7]);
+    assert_eq!(prefetch_pages(0, false, 10, 2), vec![1]);
+    assert!(prefetch_pages(4, true, 10, 0).is_empty());
+
+    assert_eq!(page_pixel_size((612.0, 792.0), 1224), (1224, 1584));
+}
+
+#[test]
+fn small_text_is_drawn_as_shapes() {
+    assert_eq!(TextDetail::for_font_px(1.0), TextDetail::Block);
+    assert_eq!(TextDetail::for_font_px(4.0), TextDetail::Lines);
+    assert_eq!(TextDetail::for_font_px(12.0), TextDetail::Full);
+}
+
+#[test]
+fn selects_characters_in_either_direction() {
+    let mut selection = TextSelection { item_id: "t1".into(), text: "Größe in Metern".into(), anchor: 8, cursor: 3 };
+    assert_eq!(selection.range(), (3, 8));
+    assert_eq!(selection.selected(), "ße in");
+    selection.anchor = 9;
+    selection.cursor = 20;
+    assert_eq!(selection.selected(), "Metern");
+    selection.cursor = 9;
+    assert!(selection.is_empty());
+}
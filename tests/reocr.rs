@@ -0,0 +1,41 @@
+//! Re-OCR of single items and regions
+
+mod common;
+
+use chonker3::core::Session;
+use chonker3::reocr::{self, OcrText};
+use chonker3::types::{self, BoundingBox, ProvenanceStage};
+use common::fixture_json;
+
+#[test]
+fn crops_the_region_with_a_margin_inside_the_page() {
+    let bbox = BoundingBox { left: 72.0, top: 100.0, width: 36.0, height: 18.0 };
+    // 144 dpi: two pixels per point, plus a two-point margin on each side
+    assert_eq!(reocr::crop_rect(&bbox, (612.0, 792.0), 144.0), Some((140, 196, 80, 44)));
+
+    let corner = BoundingBox { left: -10.0, top: 780.0, width: 30.0, height: 30.0 };
+    assert_eq!(reocr::crop_rect(&corner, (612.0, 792.0), 72.0), Some((0, 778, 22, 14)));
+    let off_page = BoundingBox { left: 700.0, top: 100.0, width: 20.0, height: 10.0 };
+    assert_eq!(reocr::crop_rect(&off_page, (612.0, 792.0), 72.0), None);
+}
+
+#[test]
+fn replaces_item_text_and_adds_region_items() {
+    let mut session = Session { page_count: 1, ..Default::default() };
+    session.set_extraction(fixture_json("simple.json"), None);
+    let title = types::item_id(0, 72.0, 56.0);
+    let ocr = OcrText { text: "Quarterly Report 2024".to_string(), confidence: Some(0.97), engine: "EasyOCR".to_string() };
+
+    session.apply_reocr(&title, &ocr).unwrap();
+    let item = session.item(&title).unwrap();
+    assert_eq!(session.edits.text_overrides.get(&title).map(String::as_str), Some("Quarterly Report 2024"));
+    let last = item.provenance.last().unwrap();
+    assert_eq!((last.stage, last.name.as_str()), (ProvenanceStage::Ocr, "re-OCR with EasyOCR at 400 dpi"));
+
+    let region = BoundingBox { left: 400.0, top: 700.0, width: 120.0, height: 20.0 };
+    let added = session.add_ocr_item(0, &region, &OcrText { text: "Page 1 of 3".to_string(), ..ocr }).unwrap();
+    let item = session.item(&added).unwrap();
+    assert_eq!((item.content.as_str(), item.confidence, item.bbox), ("Page 1 of 3", Some(0.97), region));
+
+    assert!(session.apply_reocr("item_0_1_1", &OcrText { text: String::new(), confidence: None, engine: String::new() }).is_err());
+}
@@ -0,0 +1,26 @@
+//! Repaint scheduling
+
+use std::time::Duration;
+use chonker3::repaint::RepaintScheduler;
+
+#[test]
+fn requests_the_soonest_frame_once() {
+    let mut repaint = RepaintScheduler::new(Duration::from_millis(16));
+    assert_eq!(repaint.take(), None);
+
+    repaint.within(Duration::from_millis(200));
+    repaint.within(Duration::from_millis(100));
+    assert!(repaint.is_pending());
+    assert_eq!(repaint.take(), Some(Duration::from_millis(100)));
+    // Idle frames ask for nothing
+    assert!(!repaint.is_pending());
+    assert_eq!(repaint.take(), None);
+
+    // Animations are limited by the frame interval
+    repaint.within(Duration::from_millis(200));
+    repaint.animate();
+    assert_eq!(repaint.take(), Some(Duration::from_millis(16)));
+    repaint.frame_interval = Duration::ZERO;
+    repaint.animate();
+    assert_eq!(repaint.take(), Some(Duration::ZERO));
+}
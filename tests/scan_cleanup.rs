@@ -0,0 +1,60 @@
+//! Deskew, despeckle and contrast normalization of scanned pages
+
+use chonker3::scan_cleanup::{self, ScanCleanup};
+use image::{GrayImage, Luma};
+
+/// A white page with dark "text lines" rising to the right by `degrees`
+fn tilted_lines(degrees: f32) -> GrayImage {
+    let slope = degrees.to_radians().tan();
+    let mut page = GrayImage::from_pixel(600, 400, Luma([255]));
+    for line in 0..8 {
+        let baseline = 60.0 + line as f32 * 40.0;
+        for x in 50..550 {
+            for thickness in 0..4 {
+                let y = baseline - (x as f32 - 300.0) * slope + thickness as f32;
+                page.put_pixel(x, y as u32, Luma([20]));
+            }
+        }
+    }
+    page
+}
+
+#[test]
+fn measures_and_corrects_skew() {
+    let page = tilted_lines(2.0);
+    let skew = scan_cleanup::estimate_skew(&page);
+    assert!((skew - 2.0).abs() < 0.2, "estimated {}", skew);
+
+    let falling = scan_cleanup::estimate_skew(&tilted_lines(-3.0));
+    assert!((falling + 3.0).abs() < 0.2, "estimated {}", falling);
+
+    let straightened = scan_cleanup::rotate(&page, -skew);
+    assert!(scan_cleanup::estimate_skew(&straightened).abs() < 0.2);
+
+    // Blank pages have no skew to measure
+    assert_eq!(scan_cleanup::estimate_skew(&GrayImage::from_pixel(100, 100, Luma([255]))), 0.0);
+}
+
+#[test]
+fn despeckle_removes_isolated_dots() {
+    let mut page = GrayImage::from_pixel(20, 20, Luma([255]));
+    page.put_pixel(5, 5, Luma([0]));
+    for x in 10..15 {
+        for y in 10..15 {
+            page.put_pixel(x, y, Luma([0]));
+        }
+    }
+    let cleaned = scan_cleanup::despeckle(&page);
+    assert_eq!(cleaned.get_pixel(5, 5)[0], 255);
+    assert_eq!(cleaned.get_pixel(12, 12)[0], 0);
+}
+
+#[test]
+fn contrast_is_stretched_to_full_range() {
+    let page = GrayImage::from_fn(100, 1, |x, _| Luma([if x < 50 { 90 } else { 180 }]));
+    let stretched = scan_cleanup::normalize_contrast(&page);
+    assert_eq!((stretched.get_pixel(0, 0)[0], stretched.get_pixel(99, 0)[0]), (0, 255));
+
+    let cleaned = scan_cleanup::clean_page(&image::DynamicImage::ImageLuma8(page), &ScanCleanup { enabled: true, ..Default::default() });
+    assert_eq!(cleaned.dimensions(), (100, 1));
+}
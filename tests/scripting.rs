@@ -0,0 +1,60 @@
+//! Rhai scripts editing a headless session
+
+mod common;
+
+use chonker3::core::Session;
+use chonker3::scripting::run_script;
+use chonker3::types::{self, ItemType};
+
+fn session() -> Session {
+    let mut session = Session { page_count: 2, ..Default::default() };
+    session.set_extraction(common::fixture_json("two_column.json"), None);
+    session
+}
+
+#[test]
+fn edits_items() {
+    let mut session = session();
+    let outcome = run_script(r#"
+        for item in items {
+            if item.type == "Header" { delete(item.id); }
+            if item.text.contains("rivers") { set_type(item.id, "FormLabel"); }
+            if item.page == 2 && item.type == "Checkbox" { set_text(item.id, item.text + " (done)"); }
+        }
+        print(`${items.len()} items`);
+    "#, &mut session).unwrap();
+
+    assert_eq!(outcome.edits, 3);
+    assert_eq!(outcome.log, vec!["5 items".to_string()]);
+    assert!(session.edits.deletions.contains(&types::item_id(1, 72.0, 62.0)));
+    assert_eq!(session.edits.type_overrides.get(&types::item_id(0, 72.0, 92.0)), Some(&ItemType::FormLabel));
+    assert_eq!(
+        session.edits.text_overrides.get(&types::item_id(1, 72.0, 82.0)).map(String::as_str),
+        Some("Reviewed (done)")
+    );
+}
+
+#[test]
+fn exports_see_earlier_edits() {
+    let mut session = session();
+    let path = std::env::temp_dir().join(format!("chonker3_script_test_{}.md", std::process::id()));
+    let script = format!(r#"
+        for item in items {{ if item.type == "Title" {{ set_text(item.id, "Renamed"); }} }}
+        export_markdown("{}");
+    "#, path.display());
+    let outcome = run_script(&script, &mut session).unwrap();
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(markdown.contains("# Renamed"));
+    assert!(outcome.log[0].starts_with("Exported Markdown"));
+}
+
+#[test]
+fn reports_errors_without_editing() {
+    let mut session = session();
+    assert!(run_script("set_type(items[0].id, \"Nonsense\");", &mut session).is_err());
+    assert!(run_script("this is not rhai", &mut session).is_err());
+    assert!(run_script("loop {}", &mut session).is_err());
+    assert!(session.edits.type_overrides.is_empty());
+}
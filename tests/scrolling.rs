@@ -0,0 +1,45 @@
+//! Smooth wheel scrolling and drag momentum
+
+use chonker3::scrolling::KineticScroll;
+use egui::Vec2;
+
+/// Step at 60 fps until the motion settles; returns the total distance
+fn settle(scroll: &mut KineticScroll) -> Vec2 {
+    let mut moved = Vec2::ZERO;
+    for _ in 0..600 {
+        if !scroll.is_active() {
+            break;
+        }
+        moved += scroll.step(1.0 / 60.0);
+    }
+    assert!(!scroll.is_active(), "motion never settled");
+    moved
+}
+
+#[test]
+fn eases_wheel_scroll_in_exactly() {
+    let mut scroll = KineticScroll::default();
+    scroll.scroll(Vec2::new(0.0, 120.0));
+    let first = scroll.step(1.0 / 60.0);
+    assert!(first.y > 0.0 && first.y < 120.0, "first frame applies part of it: {:?}", first);
+    assert!((first + settle(&mut scroll) - Vec2::new(0.0, 120.0)).length() < 1e-3);
+}
+
+#[test]
+fn flings_after_a_fast_drag() {
+    let mut scroll = KineticScroll::default();
+    let mut time = 0.0;
+    for _ in 0..6 {
+        time += 1.0 / 60.0;
+        assert_eq!(scroll.drag(Vec2::new(0.0, 20.0), time), Vec2::new(0.0, 20.0));
+    }
+    scroll.release(time);
+    assert!(scroll.is_active());
+    let coasted = settle(&mut scroll);
+    assert!(coasted.y > 20.0, "keeps moving the same way: {:?}", coasted);
+
+    // A drag that stopped before release doesn't fling
+    scroll.drag(Vec2::new(0.0, 20.0), 0.0);
+    scroll.release(1.0);
+    assert!(!scroll.is_active());
+}
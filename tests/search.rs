@@ -0,0 +1,85 @@
+//! Ranked, typo-tolerant and saved searches
+
+use chonker3::search::{levenshtein, match_text, rank, remember_query, snippet, MatchKind, Matcher, SearchHit, RECENT_SEARCHES};
+
+fn kind(text: &str, query: &str) -> Option<MatchKind> {
+    match_text(text, query).map(|m| m.kind)
+}
+
+#[test]
+fn grades_matches_by_word_boundaries() {
+    assert_eq!(kind("Total Invoice Amount", "invoice"), Some(MatchKind::Exact));
+    assert_eq!(kind("Invoices received", "invoice"), Some(MatchKind::Prefix));
+    assert_eq!(kind("Reinvoiced", "invoice"), Some(MatchKind::Substring));
+    assert_eq!(kind("Total lnvoice Amount", "invoice"), Some(MatchKind::Fuzzy));
+    assert_eq!(kind("Total Amount", "invoice"), None);
+    // Short queries must match as typed
+    assert_eq!(kind("tax", "tux"), None);
+}
+
+#[test]
+fn tolerates_typos_across_words() {
+    let found = match_text("Please see the paymemt terms below", "payment terms").unwrap();
+    assert_eq!(found.kind, MatchKind::Fuzzy);
+    assert_eq!(found.range, 15..28);
+    assert_eq!(levenshtein(&['k', 'i', 't', 't', 'e', 'n'], &['s', 'i', 't', 't', 'i', 'n', 'g']), 3);
+}
+
+#[test]
+fn ranks_exact_before_fuzzy() {
+    let hit = |page, text: &str| {
+        let found = match_text(text, "invoice").unwrap();
+        SearchHit { page, item_id: text.into(), kind: found.kind, score: found.score, snippet: snippet(text, &found.range) }
+    };
+    let mut hits = vec![hit(0, "lnvoice no."), hit(1, "Reinvoiced"), hit(2, "Invoices"), hit(3, "Invoice")];
+    rank(&mut hits);
+    let pages: Vec<usize> = hits.iter().map(|h| h.page).collect();
+    assert_eq!(pages, vec![3, 2, 1, 0]);
+}
+
+#[test]
+fn snippets_trim_long_text() {
+    let text = format!("{}needle{}", "a ".repeat(40), " b".repeat(40));
+    let found = match_text(&text, "needle").unwrap();
+    let snippet = snippet(&text, &found.range);
+    assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+    assert!(snippet.contains("needle"));
+}
+
+#[test]
+fn patterns_match_anywhere_ignoring_case() {
+    let matcher = Matcher::new(r"inv-\d{4}", true).unwrap();
+    let found = matcher.find("Réf. INV-2041 / INV-2042").unwrap();
+    assert_eq!(found.kind, MatchKind::Exact);
+    assert_eq!(found.range, 5..13);
+    assert!(matcher.find("Invoice").is_none());
+    assert!(Matcher::new("inv-(", true).is_err());
+}
+
+#[test]
+fn keeps_recent_queries_newest_first() {
+    let mut recent = Vec::new();
+    for query in ["total", "due date", " total ", ""] {
+        remember_query(&mut recent, query);
+    }
+    assert_eq!(recent, vec!["total".to_string(), "due date".to_string()]);
+    for n in 0..RECENT_SEARCHES + 5 {
+        remember_query(&mut recent, &n.to_string());
+    }
+    assert_eq!(recent.len(), RECENT_SEARCHES);
+    assert_eq!(recent[0], (RECENT_SEARCHES + 4).to_string());
+}
+
+#[test]
+fn highlights_every_occurrence_in_an_item() {
+    use chonker3::search::match_ranges;
+
+    assert_eq!(match_ranges("Net total: 10; TOTAL due: 12", "total"), vec![4..9, 15..20]);
+    // Overlapping repeats are counted once
+    assert_eq!(match_ranges("aaaa", "aa"), vec![0..2, 2..4]);
+    // Ranges are in chars, as the galley counts them
+    assert_eq!(match_ranges("Größe größe", "größe"), vec![0..5, 6..11]);
+    // Near misses highlight what matched
+    assert_eq!(match_ranges("Total lnvoice Amount", "invoice"), vec![6..13]);
+    assert!(match_ranges("Total", "").is_empty());
+}
@@ -0,0 +1,46 @@
+//! Searchable PDF text layer
+
+use chonker3::patch::EditPatch;
+use chonker3::reflow;
+use chonker3::searchable;
+use serde_json::json;
+
+fn data() -> serde_json::Value {
+    json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }, { "page": 2, "width": 612.0, "height": 792.0 }],
+        "items": [
+            { "page": 1, "type": "TextItem", "content": "First line\nSecond line", "bbox": { "left": 72.0, "top": 100.0, "width": 300.0, "height": 40.0 } },
+            { "page": 1, "type": "PictureItem", "content": "", "bbox": { "left": 72.0, "top": 200.0, "width": 200.0, "height": 200.0 } },
+            { "page": 2, "type": "TextItem", "content": "Born digital", "bbox": { "left": 72.0, "top": 100.0, "width": 300.0, "height": 20.0 } },
+        ],
+    })
+}
+
+#[test]
+fn splits_items_into_lines_down_their_box() {
+    let plan = searchable::plan(&data(), &EditPatch::default(), &[None, None]);
+    let lines = &plan[0];
+    assert_eq!(lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>(), vec!["First line", "Second line"]);
+    // Each line gets half the box, with its baseline inside its half
+    assert!(lines[0].baseline > 100.0 && lines[0].baseline < 120.0);
+    assert!(lines[1].baseline > 120.0 && lines[1].baseline < 140.0);
+    assert!(lines.iter().all(|l| l.left == 72.0 && l.font_size <= 20.0));
+}
+
+#[test]
+fn stretches_lines_to_the_item_width() {
+    let plan = searchable::plan(&data(), &EditPatch::default(), &[None, None]);
+    let line = &plan[0][0];
+    let stretched = reflow::text_width(&line.text, line.font_size) * line.horizontal_scale();
+    assert!((stretched - 300.0).abs() < 0.01);
+}
+
+#[test]
+fn skips_pages_with_their_own_text_layer_and_applies_edits() {
+    let mut edits = EditPatch::default();
+    edits.text_overrides.insert(chonker3::types::item_id(0, 72.0, 100.0), "Corrected".to_string());
+    let plan = searchable::plan(&data(), &edits, &[None, Some(12)]);
+    assert_eq!(plan.len(), 2);
+    assert_eq!(plan[0].iter().map(|l| l.text.as_str()).collect::<Vec<_>>(), vec!["Corrected"]);
+    assert!(plan[1].is_empty());
+}
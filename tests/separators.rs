@@ -0,0 +1,47 @@
+//! Blank page and separator sheet detection
+
+use chonker3::separators::{self, PageKind};
+use image::{GrayImage, Luma};
+
+fn page() -> GrayImage {
+    GrayImage::from_pixel(425, 550, Luma([255]))
+}
+
+fn fill(page: &mut GrayImage, x: std::ops::Range<u32>, y: std::ops::Range<u32>) {
+    for px in x {
+        for py in y.clone() {
+            page.put_pixel(px, py, Luma([0]));
+        }
+    }
+}
+
+#[test]
+fn classifies_blank_separator_and_content_pages() {
+    // A few specks and a dark scanner edge are still blank
+    let mut blank = page();
+    fill(&mut blank, 0..10, 0..550);
+    fill(&mut blank, 200..202, 300..302);
+    assert_eq!(separators::classify_page(&blank), PageKind::Blank);
+
+    // Four full-height bars, wide and narrow
+    let mut separator = page();
+    for (left, width) in [(100, 16), (140, 6), (170, 6), (200, 16)] {
+        fill(&mut separator, left..left + width, 40..510);
+    }
+    assert_eq!(separators::classify_page(&separator), PageKind::Separator);
+
+    // Lines of text
+    let mut content = page();
+    for line in 0..20 {
+        fill(&mut content, 50..375, 60 + line * 22..68 + line * 22);
+    }
+    assert_eq!(separators::classify_page(&content), PageKind::Content);
+}
+
+#[test]
+fn splits_at_separators_and_drops_blank_pages() {
+    use PageKind::*;
+    let kinds = [Content, Blank, Content, Separator, Content, Blank, Separator, Separator, Content];
+    assert_eq!(separators::split_documents(&kinds), vec![vec![0, 2], vec![4], vec![8]]);
+    assert_eq!(separators::split_documents(&[Blank, Separator]), Vec::<Vec<usize>>::new());
+}
@@ -0,0 +1,50 @@
+//! Snapping item boxes to the PDF's text
+
+mod common;
+
+use chonker3::core::{bind_pdfium, Session};
+use chonker3::document;
+use chonker3::patch::EditPatch;
+use chonker3::snap;
+use chonker3::types::BoundingBox;
+
+fn bbox(left: f64, top: f64) -> BoundingBox {
+    BoundingBox { left, top, width: 100.0, height: 12.0 }
+}
+
+#[test]
+fn picks_the_nearest_occurrence() {
+    let current = bbox(72.0, 100.0);
+    // The same words a line below and far down the page
+    let candidates = [bbox(72.0, 400.0), bbox(72.0, 114.0)];
+    assert_eq!(snap::nearest_box(&current, &candidates), Some(bbox(72.0, 114.0)));
+    // Nothing near enough to be this item's text
+    assert_eq!(snap::nearest_box(&current, &[bbox(72.0, 400.0)]), None);
+}
+
+#[test]
+fn box_edits_replace_extracted_boxes() {
+    let data = common::fixture_json("simple.json");
+    let id = document::page_items(&data, 0, &EditPatch::default())[0].id.clone();
+    let mut patch = EditPatch::default();
+    patch.boxes.insert(id.clone(), bbox(80.0, 60.0));
+
+    let items = document::page_items(&data, 0, &patch);
+    let item = items.iter().find(|i| i.id == id).unwrap();
+    assert_eq!(item.bbox, bbox(80.0, 60.0));
+}
+
+#[test]
+fn snaps_fixture_boxes() {
+    if bind_pdfium().is_err() {
+        eprintln!("pdfium not available, skipping");
+        return;
+    }
+
+    let mut session = Session::default();
+    session.open_pdf(&common::fixture_path("simple.pdf")).unwrap();
+    session.set_extraction(common::fixture_json("simple.json"), None);
+    session.snap_boxes(Some(0)).unwrap();
+    // Snapped boxes sit on the glyphs, so a second pass has nothing to move
+    assert_eq!(session.snap_boxes(Some(0)).unwrap(), 0);
+}
@@ -0,0 +1,112 @@
+//! Source checksum and provenance stamps on exports
+
+mod common;
+
+use chonker3::core::{PdfBytes, Session};
+use chonker3::checksum::sha256_hex;
+use chonker3::stamp::{self, StampMode};
+use common::{fixture_json, fixture_path};
+use serde_json::Value;
+
+fn session(stamping: StampMode) -> Session {
+    let pdf = fixture_path("simple.pdf");
+    let mut session = Session {
+        page_count: 2,
+        pdf_bytes: Some(PdfBytes::open(&pdf).unwrap()),
+        pdf_path: Some(pdf),
+        stamping,
+        ..Default::default()
+    };
+    session.set_extraction(fixture_json("simple.json"), None);
+    session
+}
+
+fn out_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("chonker3_stamp_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn json_carries_the_stamp_and_csv_gets_a_sidecar() {
+    let dir = out_dir("embedded");
+    let source_sha256 = sha256_hex(&std::fs::read(fixture_path("simple.pdf")).unwrap());
+    let session = session(StampMode::Embedded);
+
+    let json_path = dir.join("simple.structured.json");
+    session.export_structured(&json_path).unwrap();
+    let export: Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+    let stamp = &export[stamp::STAMP_KEY];
+    assert_eq!(stamp["source_sha256"], source_sha256.as_str());
+    assert_eq!(stamp["extractor"], "docling");
+    assert_eq!(stamp["extractor_version"], "fixture");
+    assert_eq!(stamp["app_version"], env!("CARGO_PKG_VERSION"));
+    assert!(stamp["generated"].as_str().is_some_and(|time| !time.is_empty()));
+    assert!(export["items"].as_array().is_some_and(|items| !items.is_empty()), "the export itself is untouched");
+    assert!(!stamp::sidecar_path(&json_path).exists());
+
+    // CSV has nowhere to put it
+    let csv_path = dir.join("simple.csv");
+    session.export_csv(&csv_path).unwrap();
+    let csv = std::fs::read(&csv_path).unwrap();
+    assert!(csv.starts_with(b"id,page,type"));
+    let sidecar: Value = serde_json::from_str(&std::fs::read_to_string(stamp::sidecar_path(&csv_path)).unwrap()).unwrap();
+    assert_eq!(sidecar["output"], "simple.csv");
+    assert_eq!(sidecar["output_sha256"], sha256_hex(&csv).as_str());
+    assert_eq!(sidecar["source_sha256"], source_sha256.as_str());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sidecars_leave_exports_as_they_were() {
+    let dir = out_dir("sidecar");
+    let path = dir.join("simple.md");
+    session(StampMode::Sidecar).export_markdown(&path).unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().starts_with("<!-- Page 1 -->"));
+    assert_eq!(stamp::sidecar_path(&path), dir.join("simple.md.stamp.json"));
+    assert!(stamp::sidecar_path(&path).exists());
+
+    let path = dir.join("unstamped.md");
+    session(StampMode::Off).export_markdown(&path).unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().starts_with("<!-- Page 1 -->"));
+    assert!(!stamp::sidecar_path(&path).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn translations_carry_the_stamp() {
+    let dir = out_dir("translation");
+    let session = session(StampMode::Embedded);
+    let title = session.document_state().unwrap().items.into_iter().find(|item| item.content == "Quarterly Report").unwrap();
+    let mut translation = chonker3::translation::Translation::new("French");
+    translation.texts.insert(title.id, "Rapport trimestriel".to_string());
+    let path = dir.join("simple.french.md");
+    session.export_translation(&path, &translation).unwrap();
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    assert!(markdown.starts_with(stamp::MARKDOWN_PREFIX));
+    assert!(markdown.contains("Rapport trimestriel") && !markdown.contains("Quarterly Report"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn folder_exports_get_one_sidecar() {
+    let dir = out_dir("folder").join("pages");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("simple_page_1.png"), b"one").unwrap();
+    std::fs::write(dir.join("simple_page_2.png"), b"two").unwrap();
+    let stamp = session(StampMode::Embedded).stamp().unwrap();
+
+    let sidecar_path = stamp.apply_folder(&dir, StampMode::Embedded).unwrap().unwrap();
+    assert_eq!(sidecar_path, dir.join("pages.stamp.json"));
+    let sidecar: Value = serde_json::from_str(&std::fs::read_to_string(&sidecar_path).unwrap()).unwrap();
+    assert_eq!(sidecar["output"], "pages");
+    assert_eq!(sidecar["files_sha256"]["simple_page_2.png"], sha256_hex(b"two").as_str());
+    assert_eq!(sidecar["files_sha256"].as_object().unwrap().len(), 2, "the sidecar isn't one of the files");
+
+    // Stamping again doesn't list the earlier sidecar
+    stamp.apply_folder(&dir, StampMode::Sidecar).unwrap();
+    let sidecar: Value = serde_json::from_str(&std::fs::read_to_string(&sidecar_path).unwrap()).unwrap();
+    assert_eq!(sidecar["files_sha256"].as_object().unwrap().len(), 2);
+    assert_eq!(stamp.apply_folder(&dir, StampMode::Off).unwrap(), None);
+    std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,85 @@
+//! Document statistics
+
+use chonker3::patch::EditPatch;
+use chonker3::stats;
+use serde_json::json;
+
+fn extraction() -> serde_json::Value {
+    json!({
+        "metadata": {"extractor": "docling", "extraction_seconds": 12.5},
+        "pages": [
+            {"page_number": 1, "width": 612.0, "height": 792.0},
+            {"page_number": 2, "width": 612.0, "height": 792.0},
+            {"page_number": 3, "width": 612.0, "height": 792.0},
+        ],
+        "items": [
+            {"type": "TitleItem", "page": 1, "content": "Annual report", "confidence": 0.99,
+             "bbox": {"left": 72.0, "top": 72.0, "width": 200.0, "height": 20.0}},
+            {"type": "TextItem", "page": 1, "content": "Revenue grew  strongly", "confidence": 0.95,
+             "bbox": {"left": 72.0, "top": 100.0, "width": 200.0, "height": 14.0}},
+            {"type": "TableItem", "page": 2, "content": "Year\tRevenue\n2024\t10", "confidence": 0.5,
+             "bbox": {"left": 72.0, "top": 100.0, "width": 300.0, "height": 60.0}},
+            {"type": "PictureItem", "page": 2, "content": "",
+             "bbox": {"left": 72.0, "top": 200.0, "width": 300.0, "height": 200.0}},
+        ],
+    })
+}
+
+#[test]
+fn counts_items_words_and_problem_pages() {
+    let stats = stats::document_stats(&extraction(), &EditPatch::default());
+    assert_eq!((stats.pages, stats.items, stats.tables, stats.images), (3, 4, 1, 1));
+    assert_eq!(stats.items_by_type.get("Title"), Some(&1));
+    assert_eq!(stats.items_by_type.get("Text"), Some(&1));
+    // "Annual report", "Revenue grew strongly", the table's four cells and "[Picture]"
+    assert_eq!(stats.words, 2 + 3 + 4 + 1);
+    assert_eq!(stats.characters, "Annualreport".len() + "Revenuegrewstrongly".len() + "YearRevenue202410".len() + "[Picture]".len());
+    assert!((stats.average_confidence.unwrap() - (0.99 + 0.95 + 0.5) / 3.0).abs() < 1e-6);
+    assert_eq!(stats.low_confidence_items, 1);
+    assert_eq!(stats.extractor.as_deref(), Some("docling"));
+    assert_eq!(stats.extraction_seconds, Some(12.5));
+
+    let issues: Vec<(usize, bool, usize)> = stats.pages_with_issues.iter().map(|p| (p.page, p.empty, p.low_confidence_items)).collect();
+    assert_eq!(issues, vec![(2, false, 1), (3, true, 0)]);
+}
+
+#[test]
+fn applies_edits_and_exports_json() {
+    let data = extraction();
+    let mut edits = EditPatch::new(Some("report.pdf".to_string()));
+    let table = chonker3::types::item_id(1, 72.0, 100.0);
+    edits.deletions.insert(table);
+    let stats = stats::document_stats(&data, &edits);
+    assert_eq!((stats.items, stats.tables, stats.low_confidence_items), (3, 0, 0));
+    assert_eq!(stats.pages_with_issues.len(), 1);
+
+    let dir = std::env::temp_dir().join(format!("chonker3_stats_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("stats.json");
+    stats::write_stats(&path, &stats).unwrap();
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written["source_file"], "report.pdf");
+    assert_eq!(written["items_by_type"]["Picture"], 1);
+    assert_eq!(written["pages_with_issues"][0]["page"], 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn heat_counts_edits_low_confidence_and_missing_text() {
+    let data = extraction();
+    let mut edits = EditPatch::default();
+    let title = chonker3::types::item_id(0, 72.0, 72.0);
+    assert_eq!(chonker3::types::item_page(&title), Some(0));
+    edits.text_overrides.insert(title.clone(), "Annual report 2024".to_string());
+    edits.annotations.insert(title, "check year".to_string());
+    edits.offsets.insert(chonker3::types::item_id(1, 72.0, 200.0), (0.0, 4.0));
+
+    // Page 1's text layer has more text than was extracted; page 3 has no text layer
+    let layer = "Annualreport2024".len() + "Revenuegrewstrongly".len();
+    let heat = stats::page_heat(&data, &edits, &[Some(layer * 2), Some(10), None]);
+    assert_eq!(heat.iter().map(|h| (h.edits, h.low_confidence_items)).collect::<Vec<_>>(), vec![(2, 0), (1, 1), (0, 0)]);
+    assert!((heat[0].coverage_gap() - 0.5).abs() < 1e-6);
+    // More extracted than the layer holds counts as full coverage
+    assert_eq!(heat[1].coverage, Some(1.0));
+    assert_eq!((heat[2].coverage, heat[2].coverage_gap()), (None, 0.0));
+}
@@ -0,0 +1,47 @@
+//! Configurable temp, cache and session directories
+
+use std::path::PathBuf;
+use chonker3::storage::{self, StorageDirs, StorageKind};
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chonker3_storage_{}_{}", name, std::process::id()))
+}
+
+#[test]
+fn environment_wins_over_settings_over_default() {
+    let (from_env, from_settings) = (scratch("env"), scratch("settings"));
+    let dirs = StorageDirs { cache: Some(from_settings.clone()), ..Default::default() };
+    let env_value = from_env.display().to_string();
+    let env = |name: &str| (name == "CHONKER3_CACHE_DIR").then(|| env_value.clone());
+    let no_env = |_: &str| None;
+    let mut warnings = Vec::new();
+
+    let resolved = storage::resolve(StorageKind::Cache, &dirs, env, &mut warnings);
+    assert_eq!((resolved.path, resolved.from_env), (from_env.clone(), true));
+    assert!(from_env.is_dir(), "validation creates the directory");
+    let resolved = storage::resolve(StorageKind::Cache, &dirs, no_env, &mut warnings);
+    assert_eq!((resolved.path, resolved.from_env), (from_settings.clone(), false));
+    let resolved = storage::resolve(StorageKind::Temp, &dirs, no_env, &mut warnings);
+    assert_eq!(resolved.path, StorageKind::Temp.default_dir());
+    assert!(warnings.is_empty(), "{:?}", warnings);
+
+    std::fs::remove_dir_all(&from_env).unwrap();
+    std::fs::remove_dir_all(&from_settings).unwrap();
+}
+
+#[test]
+fn unusable_directories_fall_back_with_a_warning() {
+    let file = scratch("file");
+    std::fs::write(&file, "not a directory").unwrap();
+    assert!(storage::validate_dir(&file).is_err());
+    assert!(storage::validate_dir(std::path::Path::new("")).is_err());
+
+    let dirs = StorageDirs { temp: Some(file.clone()), ..Default::default() };
+    let mut warnings = Vec::new();
+    let resolved = storage::resolve(StorageKind::Temp, &dirs, |_| Some("  ".to_string()), &mut warnings);
+    assert_eq!(resolved.path, StorageKind::Temp.default_dir());
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("is not a directory"), "{}", warnings[0]);
+
+    std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,56 @@
+//! Extractive page and document summaries
+
+mod common;
+
+use chonker3::core::Session;
+use chonker3::summary::{sentences, textrank, Summaries, Summary, SummaryMethod};
+use common::fixture_json;
+
+#[test]
+fn sentences_split_at_punctuation_and_lines() {
+    assert_eq!(
+        sentences("Revenue grew 12%. Costs fell!\nVersion 1.2 ships soon"),
+        vec!["Revenue grew 12%.", "Costs fell!", "Version 1.2 ships soon"],
+    );
+}
+
+#[test]
+fn textrank_keeps_central_sentences_in_order() {
+    let text = "Solar power output rose sharply this year. \
+        The cat slept all afternoon. \
+        Solar panels and wind turbines raised power output. \
+        Wind power output also rose this year.";
+    let summary = textrank(text, 2);
+    assert!(!summary.contains("cat"), "{}", summary);
+    assert!(summary.starts_with("Solar power output"), "{}", summary);
+    assert_eq!(textrank("Only one.", 3), "Only one.");
+    assert_eq!(textrank("", 3), "");
+}
+
+#[test]
+fn summaries_are_cached_until_the_extraction_changes() {
+    let mut session = Session { page_count: 1, ..Default::default() };
+    session.set_extraction(fixture_json("simple.json"), None);
+    assert!(session.summaries.is_empty());
+
+    let page = session.summarize_page(0).unwrap().clone();
+    assert_eq!(page.method, SummaryMethod::TextRank);
+    assert!(!page.text.is_empty());
+    session.summarize_document().unwrap();
+    let markdown = session.summaries.markdown("simple.pdf");
+    assert!(markdown.starts_with("# Summary of simple.pdf\n"));
+    assert!(markdown.contains("## Document (TextRank)"));
+    assert!(markdown.contains(&format!("## Page 1 (TextRank)\n\n{}\n", page.text)));
+
+    session.set_extraction(fixture_json("simple.json"), None);
+    assert_eq!(session.summaries, Summaries::default());
+}
+
+#[test]
+fn llm_summaries_are_labeled() {
+    let summaries = Summaries {
+        document: Some(Summary { method: SummaryMethod::Llm, text: "A report.".to_string() }),
+        ..Default::default()
+    };
+    assert!(summaries.markdown("report.pdf").contains("## Document (LLM)\n\nA report.\n"));
+}
@@ -0,0 +1,91 @@
+//! Tables stitched across page breaks
+
+use chonker3::patch::EditPatch;
+use chonker3::tables::{self, ContinuationSign};
+use chonker3::types::item_id;
+use serde_json::{json, Value};
+
+fn table(page: u64, top: f64, content: &str) -> Value {
+    json!({ "page": page, "type": "TableItem", "content": content, "bbox": { "left": 72.0, "top": top, "width": 400.0, "height": 200.0 } })
+}
+
+fn text(page: u64, top: f64, content: &str) -> Value {
+    json!({ "page": page, "type": "TextItem", "content": content, "bbox": { "left": 72.0, "top": top, "width": 400.0, "height": 12.0 } })
+}
+
+fn document(items: Vec<Value>) -> Value {
+    let page = json!({ "width": 612.0, "height": 792.0 });
+    json!({ "pages": [page, page, page], "items": items })
+}
+
+#[test]
+fn stitches_a_table_with_a_repeated_header() {
+    let data = document(vec![
+        text(1, 72.0, "A paragraph before the table, long enough not to be furniture."),
+        table(1, 500.0, "Name\tQty\nBolts\t10\nNuts\t20"),
+        text(1, 760.0, "Page 1"),
+        table(2, 72.0, "Name\tQty\nWashers\t30"),
+        text(2, 300.0, "A paragraph after the table, long enough not to be furniture."),
+        table(3, 72.0, "Other\tTable\n1\t2"),
+    ]);
+    let continuations = tables::detect_continuations(&data, &EditPatch::default());
+    assert_eq!(continuations.len(), 1);
+    assert_eq!(continuations[0].to, item_id(1, 72.0, 72.0));
+    assert!(continuations[0].signs.contains(&ContinuationSign::RepeatedHeader));
+    assert!(continuations[0].signs.contains(&ContinuationSign::Adjacent));
+
+    let logical = tables::logical_tables(&data, &EditPatch::default());
+    assert_eq!(logical.len(), 2);
+    assert_eq!(logical[0].pages, vec![0, 1]);
+    assert_eq!(logical[0].page_label(), "pp. 1-2");
+    let names: Vec<&str> = logical[0].rows.iter().map(|row| row[0].as_str()).collect();
+    assert_eq!(names, vec!["Name", "Bolts", "Nuts", "Washers"]);
+}
+
+#[test]
+fn continued_labels_count_and_are_dropped() {
+    let data = document(vec![
+        table(1, 500.0, "Name\tQty\nBolts\t10"),
+        text(1, 720.0, "The table continues on the next page, see there for more."),
+        table(2, 72.0, "Table 1 (continued)\nNuts\t20"),
+    ]);
+    let continuations = tables::detect_continuations(&data, &EditPatch::default());
+    assert_eq!(continuations[0].signs, vec![ContinuationSign::ContinuedLabel]);
+    let logical = tables::logical_tables(&data, &EditPatch::default());
+    assert_eq!(logical[0].rows, vec![vec!["Name", "Qty"], vec!["Bolts", "10"], vec!["Nuts", "20"]]);
+}
+
+#[test]
+fn different_columns_or_rejection_keep_tables_apart() {
+    let data = document(vec![
+        table(1, 500.0, "A\tB\n1\t2"),
+        table(2, 72.0, "A\tB\tC\n1\t2\t3"),
+    ]);
+    assert!(tables::detect_continuations(&data, &EditPatch::default()).is_empty());
+
+    let data = document(vec![table(1, 500.0, "A\tB\n1\t2"), table(2, 72.0, "A\tB\n3\t4")]);
+    let mut edits = EditPatch::default();
+    edits.table_stitches.insert(item_id(1, 72.0, 72.0), false);
+    assert_eq!(tables::logical_tables(&data, &edits).len(), 2);
+    assert_eq!(edits.edit_count(), 1);
+}
+
+#[test]
+fn csv_numbers_each_table() {
+    let data = document(vec![table(1, 72.0, "A\tB\n1\t\"x, y\"")]);
+    let csv = tables::tables_csv(&tables::logical_tables(&data, &EditPatch::default()));
+    assert_eq!(csv, "1,A,B\n1,1,\"\"\"x, y\"\"\"\n");
+}
+
+#[test]
+fn workbook_has_a_sheet_per_table() {
+    let data = document(vec![table(1, 72.0, "Item\tPrice\nBolts\t1.50\nCode\t007"), table(3, 72.0, "X\tY\n1\t2")]);
+    let files = tables::xlsx_files(&tables::logical_tables(&data, &EditPatch::default()), Default::default());
+    let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+    assert!(names.contains(&"xl/worksheets/sheet1.xml") && names.contains(&"xl/worksheets/sheet2.xml"));
+    let sheet = String::from_utf8(files.iter().find(|f| f.name == "xl/worksheets/sheet1.xml").unwrap().bytes.clone()).unwrap();
+    assert!(sheet.contains(r#"<c r="B2"><v>1.5</v></c>"#));
+    assert!(sheet.contains(">007<"));
+    let workbook = String::from_utf8(files.iter().find(|f| f.name == "xl/workbook.xml").unwrap().bytes.clone()).unwrap();
+    assert!(workbook.contains(r#"name="Table 1 (p. 1)""#));
+}
@@ -0,0 +1,72 @@
+//! Open documents as tabs
+
+use std::path::{Path, PathBuf};
+use chonker3::tabs::{Closed, TabStrip};
+
+fn titles(strip: &TabStrip<&'static str>) -> Vec<String> {
+    strip.tabs().iter().map(|tab| tab.title()).collect()
+}
+
+#[test]
+fn parks_the_state_of_tabs_left_behind() {
+    let mut strip = TabStrip::default();
+    assert_eq!(strip.active(), None);
+    strip.retarget(PathBuf::from("/docs/a.pdf"));
+    strip.open(PathBuf::from("/docs/b.pdf"), "state of a");
+    assert_eq!(titles(&strip), ["a.pdf", "b.pdf"]);
+    assert_eq!(strip.active(), Some(1));
+    assert_eq!(strip.tabs()[0].parked(), Some(&"state of a"));
+
+    // Switching hands back the parked state and parks the one left
+    assert_eq!(strip.switch(0, "state of b"), Ok("state of a"));
+    assert_eq!(strip.tabs()[1].parked(), Some(&"state of b"));
+    assert_eq!(strip.tabs()[0].parked(), None);
+    assert_eq!(strip.switch(0, "unchanged"), Err("unchanged"), "already active");
+    assert_eq!(strip.find(Path::new("/docs/b.pdf")), Some(1));
+    assert_eq!(strip.parked_for(Path::new("/docs/b.pdf")), Some(&mut "state of b"));
+    assert_eq!(strip.next(), Some(1));
+    assert_eq!(strip.parked_mut().collect::<Vec<_>>(), [&mut "state of b"], "only background tabs are parked");
+}
+
+#[test]
+fn closing_activates_a_neighbour() {
+    let mut strip = TabStrip::default();
+    strip.retarget(PathBuf::from("a.pdf"));
+    strip.open(PathBuf::from("b.pdf"), "a");
+    strip.open(PathBuf::from("c.pdf"), "b");
+    // New tabs open after the active one
+    strip.switch(0, "c").unwrap();
+    strip.open(PathBuf::from("d.pdf"), "a");
+    assert_eq!(titles(&strip), ["a.pdf", "d.pdf", "b.pdf", "c.pdf"]);
+
+    assert_eq!(strip.close(3), Closed::Background);
+    assert_eq!(strip.active(), Some(1));
+    assert_eq!(strip.close(0), Closed::Background);
+    assert_eq!(strip.active(), Some(0));
+    assert_eq!(strip.close(0), Closed::Activate("b"));
+    assert_eq!(titles(&strip), ["b.pdf"]);
+    assert_eq!(strip.close(0), Closed::Empty);
+    assert!(strip.is_empty());
+}
+
+#[test]
+fn cancelling_an_open_goes_back_to_the_tab_it_was_opened_from() {
+    let mut strip = TabStrip::default();
+    strip.retarget(PathBuf::from("a.pdf"));
+    strip.open(PathBuf::from("b.pdf"), "a");
+    strip.switch(0, "b").unwrap();
+    strip.open(PathBuf::from("c.pdf"), "a");
+    assert_eq!(titles(&strip), ["a.pdf", "c.pdf", "b.pdf"]);
+
+    // Not the right neighbour, as closing the tab would pick
+    assert_eq!(strip.cancel_open(), Closed::Activate("a"));
+    assert_eq!(titles(&strip), ["a.pdf", "b.pdf"]);
+    assert_eq!(strip.active(), Some(0));
+    assert_eq!(strip.tabs()[1].parked(), Some(&"b"));
+
+    // The only tab
+    let mut strip = TabStrip::<&str>::default();
+    strip.retarget(PathBuf::from("a.pdf"));
+    assert_eq!(strip.cancel_open(), Closed::Empty);
+    assert!(strip.is_empty());
+}
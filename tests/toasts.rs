@@ -0,0 +1,33 @@
+//! Toast queue expiry and history
+
+use std::time::{Duration, Instant};
+use chonker3::toasts::{ToastKind, Toasts};
+
+#[test]
+fn errors_outlive_other_toasts() {
+    let mut toasts = Toasts::default();
+    toasts.success("Exported");
+    toasts.error("Export failed");
+
+    toasts.expire(Instant::now() + Duration::from_secs(5));
+    let active: Vec<_> = toasts.active().map(|t| t.kind).collect();
+    assert_eq!(active, vec![ToastKind::Error]);
+
+    toasts.expire(Instant::now() + Duration::from_secs(9));
+    assert_eq!(toasts.active().count(), 0);
+    // Expired toasts stay in the history, newest first
+    let history: Vec<_> = toasts.history().map(|t| t.message.as_str()).collect();
+    assert_eq!(history, vec!["Export failed", "Exported"]);
+}
+
+#[test]
+fn limits_visible_toasts_and_history() {
+    let mut toasts = Toasts::default();
+    for i in 0..60 {
+        toasts.info(format!("toast {}", i));
+    }
+    assert_eq!(toasts.active().count(), 4);
+    assert_eq!(toasts.active().next().unwrap().message, "toast 56");
+    assert_eq!(toasts.history_len(), 50);
+    assert_eq!(toasts.history().next().unwrap().message, "toast 59");
+}
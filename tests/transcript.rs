@@ -0,0 +1,46 @@
+//! Scoring against a reference transcript
+
+use chonker3::patch::EditPatch;
+use chonker3::transcript::{self, Transcript};
+use serde_json::json;
+
+#[test]
+fn splits_pages_at_form_feeds_or_markers() {
+    let pdftotext = Transcript::parse("First page\n\u{c}Second page\n\u{c}");
+    assert_eq!(pdftotext.pages, vec!["First page\n", "Second page\n"]);
+    
+    let markdown = Transcript::parse("<!-- Page 1 -->\nOne\n<!-- Page 2 -->\nTwo\n");
+    assert_eq!(markdown.pages, vec!["One\n", "Two\n"]);
+}
+
+#[test]
+fn error_rates_ignore_case_punctuation_and_spacing() {
+    assert_eq!(transcript::wer("The quick fox.", "the  quick\nfox"), 0.0);
+    // One substituted word out of four
+    assert_eq!(transcript::wer("a b c d", "a x c d"), 0.25);
+    assert_eq!(transcript::cer("abcd", "abxd"), 0.25);
+    assert_eq!(transcript::edit_distance(&['k', 'i', 't'], &['s', 'i', 't', 's']), 2);
+}
+
+#[test]
+fn scores_pages_and_flags_divergent_items() {
+    let item = |page: u64, top: f64, content: &str| json!({
+        "page": page, "type": "TextItem", "content": content,
+        "bbox": { "left": 72.0, "top": top, "width": 200.0, "height": 12.0 },
+    });
+    let data = json!({
+        "pages": [{ "page": 1, "width": 612.0, "height": 792.0 }],
+        "items": [
+            item(1, 100.0, "Hello world"),
+            item(1, 200.0, "Tbe qnick brovvn fox"),
+        ],
+    });
+    let reference = Transcript::parse("Hello world\nThe quick brown fox\n");
+    let scores = transcript::score_pages(&data, &EditPatch::default(), &reference);
+    assert_eq!(scores.len(), 1);
+    assert!((scores[0].wer - 0.5).abs() < 1e-6);
+    
+    let items = chonker3::document::edited_page_items(&data, 0, &EditPatch::default());
+    let divergent = transcript::divergent_items(&items, &reference.pages[0]);
+    assert_eq!(divergent, vec![items[1].id.clone()]);
+}
@@ -0,0 +1,49 @@
+//! Translation overlay
+
+mod common;
+
+use chonker3::document::{document_state, page_items};
+use chonker3::export::markdown_export;
+use chonker3::patch::EditPatch;
+use chonker3::translation::{parse_reply, translatable, Translation};
+use common::fixture_json;
+
+#[test]
+fn reply_blocks_map_to_item_ids() {
+    let items = page_items(&fixture_json("simple.json"), 0, &EditPatch::default());
+    let items: Vec<_> = items.into_iter().filter(translatable).take(2).collect();
+    assert_eq!(items.len(), 2);
+    let translated = parse_reply("[2] Deuxième\n[1] Premier\nsur deux lignes", &items);
+    assert_eq!(translated, vec![
+        (items[1].id.clone(), "Deuxième".to_string()),
+        (items[0].id.clone(), "Premier\nsur deux lignes".to_string()),
+    ]);
+}
+
+#[test]
+fn translation_overlays_and_exports_without_touching_edits() {
+    let data = fixture_json("simple.json");
+    let edits = EditPatch::default();
+    let items = page_items(&data, 0, &edits);
+    let first = items.iter().find(|item| translatable(item)).unwrap();
+
+    let mut translation = Translation::new(" French ");
+    assert_eq!(translation.language, "French");
+    assert!(!translation.covers(&items));
+    translation.texts.insert(first.id.clone(), "Rapport trimestriel".to_string());
+
+    let mut state = document_state(&data, 0, &edits, "");
+    translation.apply(&mut state);
+    assert_eq!(state.item_text_overrides[&first.id], "Rapport trimestriel");
+    assert!(state.items.iter().any(|item| item.id == first.id && item.content == first.content));
+
+    let patch = translation.patch(&edits);
+    assert_eq!(patch.text_overrides[&first.id], "Rapport trimestriel");
+    assert!(edits.text_overrides.is_empty());
+    assert!(markdown_export(&data, &patch).contains("Rapport trimestriel"));
+
+    for item in items.iter().filter(|item| translatable(item)) {
+        translation.texts.entry(item.id.clone()).or_insert_with(|| "…".to_string());
+    }
+    assert!(translation.covers(&items));
+}
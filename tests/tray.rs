@@ -0,0 +1,18 @@
+//! Quick-drop tray icon
+
+use chonker3::tray::{self, TrayCommand};
+
+#[test]
+fn menu_items_round_trip_through_their_ids() {
+    for command in TrayCommand::MENU {
+        assert_eq!(TrayCommand::from_id(command.id()), Some(command));
+    }
+    assert_eq!(TrayCommand::from_id("open"), None);
+}
+
+#[test]
+fn tooltip_shows_the_queue() {
+    assert_eq!(tray::status_text(Some("a.pdf"), 1), "Extracting a.pdf");
+    assert_eq!(tray::status_text(Some("a.pdf"), 3), "Extracting a.pdf (2 more queued)");
+    assert!(tray::status_text(None, 0).starts_with("Idle"));
+}
@@ -0,0 +1,89 @@
+//! Validation rules on named region fields
+
+use chonker3::normalize::NumberLocale;
+use chonker3::regions::RegionField;
+use chonker3::validation::{evaluate, parse_date, Rule, DEFAULT_TOLERANCE};
+use chrono::NaiveDate;
+
+fn field(name: &str, page: usize, text: &str) -> RegionField {
+    RegionField { name: name.to_string(), page, text: text.to_string() }
+}
+
+fn invoice() -> Vec<RegionField> {
+    vec![
+        field("Invoice number", 0, "INV-004211"),
+        field("Invoice date", 0, "14.03.2024"),
+        field("Line 1", 0, "€ 120.00"),
+        field("Line 2", 0, "€ 80.50"),
+        field("Line 3", 1, "€ 9.50"),
+        field("Total", 1, "€ 210.00"),
+    ]
+}
+
+#[test]
+fn passing_rules_find_nothing() {
+    let rules = vec![
+        Rule::Pattern { field: "invoice NUMBER".to_string(), pattern: r"INV-\d{6}".to_string() },
+        Rule::Sum { items: vec!["Line *".to_string()], total: "Total".to_string(), tolerance: DEFAULT_TOLERANCE },
+        Rule::DateRange { field: "Invoice date".to_string(), earliest: Some("2024-01-01".to_string()), latest: None },
+    ];
+    assert_eq!(evaluate(&rules, &invoice(), NumberLocale::Auto), vec![]);
+}
+
+#[test]
+fn violations_name_the_field_and_page() {
+    let mut fields = invoice();
+    fields[5].text = "€ 215.00".to_string();
+    let rules = vec![
+        Rule::Pattern { field: "Invoice number".to_string(), pattern: r"\d+".to_string() },
+        Rule::Sum { items: vec!["Line 1".to_string(), "Line 2".to_string(), "Line 3".to_string()], total: "Total".to_string(), tolerance: DEFAULT_TOLERANCE },
+        Rule::DateRange { field: "Invoice date".to_string(), earliest: None, latest: Some("2023-12-31".to_string()) },
+        Rule::Pattern { field: "PO number".to_string(), pattern: ".+".to_string() },
+    ];
+    let violations = evaluate(&rules, &fields, NumberLocale::Auto);
+    let summary: Vec<_> = violations.iter().map(|v| (v.rule, v.field.as_deref(), v.page, v.message.as_str())).collect();
+    assert_eq!(summary, vec![
+        (0, Some("Invoice number"), Some(0), "\"INV-004211\" doesn't match /\\d+/"),
+        (1, Some("Total"), Some(1), "3 line items add up to 210.00, not 215.00"),
+        (2, Some("Invoice date"), Some(0), "2024-03-14 is after 2023-12-31"),
+        (3, Some("PO number"), None, "No field named \"PO number\""),
+    ]);
+}
+
+#[test]
+fn unparsable_values_and_rules_are_reported() {
+    let mut fields = invoice();
+    fields[3].text = "eighty".to_string();
+    let rules = vec![
+        Rule::Sum { items: vec!["Line *".to_string()], total: "Total".to_string(), tolerance: DEFAULT_TOLERANCE },
+        Rule::Pattern { field: "Invoice number".to_string(), pattern: "(".to_string() },
+    ];
+    let violations = evaluate(&rules, &fields, NumberLocale::Auto);
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0].message, "\"eighty\" is not a number");
+    assert_eq!(violations[0].field.as_deref(), Some("Line 2"));
+    assert!(violations[1].message.starts_with("Invalid pattern"));
+}
+
+#[test]
+fn dates_parse_in_common_forms() {
+    let date = NaiveDate::from_ymd_opt(2024, 3, 14);
+    for text in ["2024-03-14", "14.03.2024", "03/14/2024", "14/03/24", "March 14, 2024", "14 Mar 2024"] {
+        assert_eq!(parse_date(text), date, "{}", text);
+    }
+    assert_eq!(parse_date("soon"), None);
+}
+
+#[test]
+fn rules_round_trip_through_json() {
+    let rules = vec![
+        Rule::Sum { items: vec!["Line *".to_string()], total: "Total".to_string(), tolerance: 0.01 },
+        Rule::DateRange { field: "Due".to_string(), earliest: None, latest: Some("2025-01-01".to_string()) },
+    ];
+    let json = serde_json::to_value(&rules).unwrap();
+    assert_eq!(json[0]["kind"], "sum");
+    assert_eq!(json[1]["kind"], "date_range");
+    assert_eq!(serde_json::from_value::<Vec<Rule>>(json).unwrap(), rules);
+    let defaulted: Rule = serde_json::from_str(r#"{"kind": "sum", "items": ["A"], "total": "B"}"#).unwrap();
+    assert_eq!(defaulted, Rule::Sum { items: vec!["A".to_string()], total: "B".to_string(), tolerance: DEFAULT_TOLERANCE });
+}
@@ -0,0 +1,87 @@
+//! View commands and the page transform shared by both panels
+
+use chonker3::types::BoundingBox;
+use chonker3::viewport::{Rotation, ViewCommand, ViewportController, MAX_ZOOM, MIN_ZOOM};
+use egui::{Pos2, Vec2};
+
+const LETTER: (f32, f32) = (612.0, 792.0);
+
+fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+    (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+}
+
+#[test]
+fn zoom_steps_stay_within_limits() {
+    let mut view = ViewportController::default();
+    for _ in 0..20 {
+        view.apply(ViewCommand::ZoomIn, LETTER, Vec2::splat(500.0));
+    }
+    assert_eq!(view.zoom, MAX_ZOOM);
+    for _ in 0..20 {
+        view.apply(ViewCommand::ZoomOut, LETTER, Vec2::splat(500.0));
+    }
+    assert_eq!(view.zoom, MIN_ZOOM);
+    let change = view.apply(ViewCommand::ZoomOut, LETTER, Vec2::splat(500.0));
+    assert!(!change.resized, "already at the minimum");
+}
+
+#[test]
+fn fits_the_turned_page() {
+    let mut view = ViewportController::default();
+    view.apply(ViewCommand::Pan(Vec2::new(30.0, 40.0)), LETTER, Vec2::ZERO);
+    let change = view.apply(ViewCommand::FitWidth, LETTER, Vec2::new(918.0, 600.0));
+    assert!((view.zoom - 1.5).abs() < 1e-4);
+    assert!(change.resized && change.panned);
+    assert_eq!(view.pan, Vec2::ZERO);
+
+    view.apply(ViewCommand::FitPage, LETTER, Vec2::new(918.0, 594.0));
+    assert!((view.zoom - 0.75).abs() < 1e-4, "height limits it: {}", view.zoom);
+
+    view.apply(ViewCommand::RotateClockwise, LETTER, Vec2::ZERO);
+    view.apply(ViewCommand::FitWidth, LETTER, Vec2::new(792.0, 100.0));
+    assert!((view.zoom - 1.0).abs() < 1e-4, "sideways, the page is 792 wide");
+}
+
+#[test]
+fn rotation_goes_round() {
+    let mut view = ViewportController::default();
+    for expected in [Rotation::Clockwise, Rotation::UpsideDown, Rotation::CounterClockwise, Rotation::None] {
+        view.apply(ViewCommand::RotateClockwise, LETTER, Vec2::ZERO);
+        assert_eq!(view.rotation, expected);
+    }
+    view.apply(ViewCommand::RotateCounterClockwise, LETTER, Vec2::ZERO);
+    assert_eq!(view.rotation, Rotation::CounterClockwise);
+    view.apply(ViewCommand::Reset, LETTER, Vec2::ZERO);
+    assert_eq!((view.zoom, view.rotation), (1.0, Rotation::None));
+}
+
+#[test]
+fn points_round_trip_in_every_rotation() {
+    let mut view = ViewportController { zoom: 2.0, ..Default::default() };
+    for _ in 0..4 {
+        let transform = view.transform(Pos2::new(10.0, 20.0), LETTER);
+        for point in [(0.0, 0.0), (100.0, 50.0), LETTER, (612.0, 0.0)] {
+            let screen = transform.to_screen(point);
+            assert!(transform.page_rect().expand(1e-3).contains(screen), "{:?} lands on the page in {:?}", point, view.rotation);
+            assert!(close(transform.to_page(screen), point), "{:?} in {:?}", point, view.rotation);
+        }
+        view.apply(ViewCommand::RotateClockwise, LETTER, Vec2::ZERO);
+    }
+}
+
+#[test]
+fn turns_the_page_clockwise() {
+    let view = ViewportController { zoom: 1.0, pan: Vec2::ZERO, rotation: Rotation::Clockwise };
+    let transform = view.transform(Pos2::ZERO, LETTER);
+    assert_eq!(transform.page_rect().size(), Vec2::new(792.0, 612.0));
+    // The page's top-left corner ends up top-right
+    assert_eq!(transform.to_screen((0.0, 0.0)), Pos2::new(792.0, 0.0));
+    assert_eq!(transform.to_screen((0.0, 792.0)), Pos2::new(0.0, 0.0));
+
+    let bbox = BoundingBox { left: 10.0, top: 20.0, width: 100.0, height: 30.0 };
+    let screen = transform.bbox_to_screen(&bbox, Vec2::ZERO);
+    assert_eq!(screen.size(), Vec2::new(30.0, 100.0));
+    let back = transform.screen_rect_to_bbox(screen);
+    assert!(close((back.left as f32, back.top as f32), (10.0, 20.0)));
+    assert!(close((back.width as f32, back.height as f32), (100.0, 30.0)));
+}
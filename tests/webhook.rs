@@ -0,0 +1,122 @@
+//! Posting batch export output to a URL
+
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+use chonker3::pipeline::{self, ExportPipeline, ManifestFormat, OutputFormat, PipelineOutput};
+use chonker3::regions::NamedRegion;
+use chonker3::types::BoundingBox;
+use chonker3::webhook::{self, Webhook, WebhookHeader};
+use chonker3::workspace::WorkspaceDocument;
+use serde_json::{json, Value};
+
+/// Answer one request with `status`, handing back its head and body
+fn serve_once(status: u16) -> (String, std::thread::JoinHandle<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            head.push_str(&line);
+        }
+        let length: usize = head.lines()
+            .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        write!(stream, "HTTP/1.1 {} Status\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok", status).unwrap();
+        (head, String::from_utf8(body).unwrap())
+    });
+    (url, server)
+}
+
+fn document(dir: &std::path::Path) -> WorkspaceDocument {
+    let json_path = dir.join("invoice.extraction.json");
+    std::fs::write(&json_path, json!({
+        "metadata": { "extractor": "native" },
+        "pages": [{ "page_number": 1, "width": 612.0, "height": 792.0 }],
+        "items": [{
+            "page": 1, "type": "TextItem", "content": "INV-42",
+            "bbox": { "left": 72.0, "top": 100.0, "width": 60.0, "height": 12.0, "coord_origin": "TOPLEFT" },
+        }],
+    }).to_string()).unwrap();
+    WorkspaceDocument {
+        path: PathBuf::from("/nonexistent/invoice.pdf"),
+        tags: BTreeSet::new(),
+        metadata: [("customer".to_string(), "ACME".to_string())].into(),
+        extracted_json: Some(json_path),
+        fingerprint: None,
+        bookmarks: BTreeSet::new(),
+        page_notes: Default::default(),
+        page_backends: Default::default(),
+        comments: Default::default(),
+        regions: vec![NamedRegion { name: "Number".into(), page: 0, bbox: BoundingBox { left: 60.0, top: 90.0, width: 100.0, height: 30.0 } }],
+        recent_searches: Default::default(),
+        added: String::new(),
+    }
+}
+
+#[test]
+fn posts_each_exported_document() {
+    let dir = std::env::temp_dir().join(format!("chonker3_webhook_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (url, server) = serve_once(200);
+    let layout = ExportPipeline {
+        outputs: vec![PipelineOutput { format: OutputFormat::Markdown, template: "{stem}.md".to_string() }],
+        manifest: ManifestFormat::Json,
+        field_mapping: Default::default(),
+        webhook: Webhook {
+            enabled: true,
+            url,
+            headers: vec![WebhookHeader { name: "X-Source".into(), value: "chonker3".into() }],
+            ..Default::default()
+        },
+        upload_to: None,
+        remote: None,
+        stamping: Default::default(),
+    };
+
+    let job = chonker3::jobs::Job::spawn("batch", {
+        let (out, documents) = (dir.join("out"), vec![document(&dir)]);
+        move |job| Ok(pipeline::run(&out, &documents, &layout, job)?.describe())
+    });
+    let summary = loop {
+        if let Some(result) = job.take_result() {
+            break result.unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert!(summary.ends_with("posted 1 of 1"), "{}", summary);
+
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /hook"));
+    assert!(head.to_lowercase().contains("x-source: chonker3"));
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["document"]["input"], "/nonexistent/invoice.pdf");
+    assert_eq!(body["metadata"]["customer"], "ACME");
+    assert_eq!(body["fields"]["fields"]["Number"], "INV-42");
+    assert_eq!(body["structured"]["items"][0]["text"], "INV-42");
+
+    let manifest: Value = serde_json::from_str(&std::fs::read_to_string(dir.join("out/manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["documents"][0]["webhook"]["status"], 200);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn error_replies_are_failures() {
+    let (url, server) = serve_once(500);
+    let delivery = webhook::post(&Webhook { enabled: true, url, ..Default::default() }, &json!({}));
+    server.join().unwrap();
+    assert_eq!(delivery.status, Some(500));
+    assert!(!delivery.succeeded());
+    assert!(!Webhook { enabled: true, ..Default::default() }.is_active(), "no URL, nothing to post");
+}